@@ -0,0 +1,255 @@
+//! AOF文件检查 - 与redis-check-aof一样找出最后一条完整命令的位置
+//!
+//! 检查内容:
+//! - 每条命令都是由批量字符串组成的RESP数组，长度和`\r\n`分隔符都与声明一致
+//! - `#TS:`等注释行完整
+//! - 带RDB前缀(aof-use-rdb-preamble)时先按RDB解析前缀并核对校验和
+//! - 文件末尾没有缺少EXEC的MULTI
+//!
+//! 崩溃时最后一条命令可能只写入了一部分。这种截断可以通过把文件截短到
+//! `AofReport::valid_len`修复；文件中间的格式错误则需要人工处理。
+//!
+//! Rust特点展示:
+//! - 手写解析器直接在切片上移动偏移量，错误能精确到字节
+//! - `?`在`Result<_, AofError>`之间传播，截断和格式错误用一个字段区分
+
+use crate::rdb::{self, Checksum, RdbSummary};
+use thiserror::Error;
+
+/// 检查发现的问题
+#[derive(Debug, Error)]
+#[error("偏移 {offset}: {message}")]
+pub struct AofError {
+    pub offset: usize,
+    pub message: String,
+    /// 文件在命令中途结束，可以截短修复
+    pub truncated: bool,
+}
+
+/// 检查结果
+#[derive(Debug)]
+pub struct AofReport {
+    /// 完整命令的条数(不含被截断或未提交的事务)
+    pub commands: u64,
+    /// 有效内容的长度，修复截断时把文件截短到这里
+    pub valid_len: usize,
+    /// RDB前缀的解析结果
+    pub preamble: Option<RdbSummary>,
+    /// 第一个问题，文件完好时为None
+    pub error: Option<AofError>,
+}
+
+impl AofReport {
+    /// 问题能否通过截短文件修复
+    pub fn fixable(&self) -> bool {
+        self.error.as_ref().is_some_and(|e| e.truncated)
+    }
+}
+
+/// 检查一个AOF文件的内容
+pub fn check(data: &[u8]) -> AofReport {
+    let mut report = AofReport {
+        commands: 0,
+        valid_len: 0,
+        preamble: None,
+        error: None,
+    };
+
+    let mut pos = 0;
+    if data.starts_with(b"REDIS") {
+        match rdb::parse(data, |_| {}) {
+            Ok(summary) => {
+                if let Checksum::Mismatch { .. } = summary.checksum {
+                    report.error = Some(invalid(summary.len - 8, "RDB前缀的校验和不符"));
+                    report.preamble = Some(summary);
+                    return report;
+                }
+                pos = summary.len;
+                report.preamble = Some(summary);
+            }
+            Err(e) => {
+                report.error = Some(invalid(e.offset, format!("RDB前缀损坏: {}", e.message)));
+                return report;
+            }
+        }
+    }
+    report.valid_len = pos;
+
+    // 未提交的事务: (MULTI的位置, 之前的命令数)
+    let mut multi = None;
+    while pos < data.len() {
+        let start = pos;
+        let result = if data[pos] == b'#' {
+            line(data, pos).map(|end| (end, None))
+        } else {
+            command(data, pos).map(|(end, name)| (end, Some(name)))
+        };
+        match result {
+            Ok((end, name)) => {
+                pos = end;
+                let Some(name) = name else {
+                    report.valid_len = pos;
+                    continue;
+                };
+                if name.eq_ignore_ascii_case(b"MULTI") {
+                    multi = Some((start, report.commands));
+                } else if name.eq_ignore_ascii_case(b"EXEC") {
+                    multi = None;
+                }
+                report.commands += 1;
+                if multi.is_none() {
+                    report.valid_len = pos;
+                }
+            }
+            Err(e) => {
+                report.error = Some(e);
+                break;
+            }
+        }
+    }
+
+    if let Some((start, commands)) = multi {
+        report.commands = commands;
+        report.valid_len = start;
+        if report.error.is_none() {
+            report.error = Some(AofError {
+                offset: start,
+                message: "文件末尾的MULTI没有对应的EXEC".to_string(),
+                truncated: true,
+            });
+        }
+    }
+    report
+}
+
+fn invalid(offset: usize, message: impl Into<String>) -> AofError {
+    AofError {
+        offset,
+        message: message.into(),
+        truncated: false,
+    }
+}
+
+fn truncated(offset: usize) -> AofError {
+    AofError {
+        offset,
+        message: "文件在命令中途结束".to_string(),
+        truncated: true,
+    }
+}
+
+/// 读取以`\r\n`结尾的一行，返回下一行的位置
+fn line(data: &[u8], pos: usize) -> Result<usize, AofError> {
+    data[pos..]
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .map(|end| pos + end + 2)
+        .ok_or_else(|| truncated(pos))
+}
+
+/// 读取`<prefix><整数>\r\n`
+fn header(data: &[u8], pos: usize, prefix: u8) -> Result<(usize, usize), AofError> {
+    match data.get(pos) {
+        None => return Err(truncated(pos)),
+        Some(&b) if b != prefix => {
+            return Err(invalid(
+                pos,
+                format!("应为'{}'，实际为'{}'", prefix as char, b.escape_ascii()),
+            ))
+        }
+        Some(_) => {}
+    }
+    let end = line(data, pos)?;
+    std::str::from_utf8(&data[pos + 1..end - 2])
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .map(|n| (end, n))
+        .ok_or_else(|| invalid(pos, "无效的长度"))
+}
+
+/// 读取一条命令，返回(下一条命令的位置, 命令名)
+fn command(data: &[u8], pos: usize) -> Result<(usize, &[u8]), AofError> {
+    let (mut pos, argc) = header(data, pos, b'*')?;
+    if argc == 0 {
+        return Err(invalid(pos, "命令没有参数"));
+    }
+
+    let mut name: &[u8] = &[];
+    for i in 0..argc {
+        let (start, len) = header(data, pos, b'$')?;
+        let end = start.checked_add(len).ok_or_else(|| invalid(pos, "无效的长度"))?;
+        match data.get(end..end + 2) {
+            Some(b"\r\n") => {}
+            Some(_) => return Err(invalid(end, "批量字符串后缺少\\r\\n")),
+            None => return Err(truncated(pos)),
+        }
+        if i == 0 {
+            name = &data[start..end];
+        }
+        pos = end + 2;
+    }
+    Ok((pos, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SET: &[u8] = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n";
+
+    #[test]
+    fn test_check() {
+        let mut data = b"#TS:1700000000\r\n".to_vec();
+        data.extend_from_slice(SET);
+        data.extend_from_slice(SET);
+        let report = check(&data);
+        assert!(report.error.is_none());
+        assert_eq!((report.commands, report.valid_len), (2, data.len()));
+
+        // 截断在最后一条命令中途
+        for cut in 1..SET.len() {
+            let partial = &data[..data.len() - cut];
+            let report = check(partial);
+            assert!(report.fixable(), "cut {}", cut);
+            assert_eq!((report.commands, report.valid_len), (1, data.len() - SET.len()));
+        }
+
+        // 中间的格式错误不能通过截短修复
+        let mut corrupt = data.clone();
+        corrupt[16] = b'+';
+        let report = check(&corrupt);
+        assert!(!report.fixable());
+        assert_eq!(report.error.unwrap().offset, 16);
+    }
+
+    #[test]
+    fn test_unfinished_multi() {
+        let mut data = SET.to_vec();
+        data.extend_from_slice(b"*1\r\n$5\r\nMULTI\r\n");
+        data.extend_from_slice(SET);
+        let report = check(&data);
+        assert!(report.fixable());
+        assert_eq!((report.commands, report.valid_len), (1, SET.len()));
+
+        data.extend_from_slice(b"*1\r\n$4\r\nEXEC\r\n");
+        let report = check(&data);
+        assert!(report.error.is_none());
+        assert_eq!(report.commands, 4);
+    }
+
+    #[test]
+    fn test_rdb_preamble() {
+        // 关闭校验和的空快照
+        let mut data = b"REDIS0011\xFF".to_vec();
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(SET);
+        let report = check(&data);
+        assert!(report.error.is_none());
+        assert_eq!(report.preamble.unwrap().checksum, Checksum::Disabled);
+        assert_eq!(report.commands, 1);
+
+        let report = check(&data[..12]);
+        assert!(!report.fixable());
+        assert_eq!(report.valid_len, 0);
+    }
+}
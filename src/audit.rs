@@ -0,0 +1,203 @@
+//! 审计日志模块 - 以追加方式记录每条命令，供合规审计使用
+//!
+//! 每条命令一行JSON，包含时间戳、客户端地址、已认证的用户、数据库和命令参数:
+//! - 写入文件时与服务器日志一样支持SIGUSR1重新打开，配合logrotate轮转
+//! - 写入syslog时通过`/dev/log`发送，facility为LOG_AUTHPRIV(仅Unix)
+//! - AUTH的密码、CONFIG SET requirepass的值总是被隐去；
+//!   涉及匹配`audit-redact-keys`的键的命令，除命令名和键以外的参数都被隐去
+//!
+//! Rust特点展示:
+//! - 枚举表示不同的输出目标，`#[cfg(unix)]`只在Unix平台提供syslog
+//! - 复用`logging::LogFile`，审计日志与服务器日志的轮转方式一致
+
+use crate::command::Command;
+use crate::error::RedisResult;
+use crate::logging::LogFile;
+use crate::resp::RespValue;
+use crate::store::Store;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// 被隐去的参数的替代文本
+pub const REDACTED: &str = "(redacted)";
+
+/// `audit-log`取该值时写入syslog
+pub const SYSLOG: &str = "syslog";
+
+/// 审计日志的输出目标
+#[derive(Debug)]
+enum Sink {
+    File(LogFile),
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram),
+}
+
+/// 审计日志
+///
+/// Rust特点: Clone只复制Arc，所有连接写入同一个输出目标
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    sink: Arc<Sink>,
+}
+
+impl AuditLog {
+    /// 打开审计日志: `syslog`表示写入syslog，其他值是文件路径
+    pub fn open(target: &str) -> RedisResult<Self> {
+        let sink = if target == SYSLOG {
+            open_syslog()?
+        } else {
+            Sink::File(LogFile::open(target)?)
+        };
+        Ok(Self {
+            sink: Arc::new(sink),
+        })
+    }
+
+    /// 审计日志文件，写入syslog时为None
+    pub fn log_file(&self) -> Option<&LogFile> {
+        match self.sink.as_ref() {
+            Sink::File(log_file) => Some(log_file),
+            #[cfg(unix)]
+            Sink::Syslog(_) => None,
+        }
+    }
+
+    /// 记录一条命令；写入失败只记录警告，不影响命令执行
+    pub fn record(&self, client: &str, user: Option<&str>, db: u32, args: &[String]) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let entry = serde_json::json!({
+            "timestamp": timestamp,
+            "client": client,
+            "user": user,
+            "db": db,
+            "command": args,
+        });
+
+        let result = match self.sink.as_ref() {
+            Sink::File(log_file) => {
+                log_file.make_writer().write_all(format!("{}\n", entry).as_bytes())
+            }
+            // <85> = LOG_AUTHPRIV(10) * 8 + LOG_NOTICE(5)
+            #[cfg(unix)]
+            Sink::Syslog(socket) => socket
+                .send(format!("<85>redis-server: {}", entry).as_bytes())
+                .map(|_| ()),
+        };
+        if let Err(e) = result {
+            warn!(error = %e, "写入审计日志失败");
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_syslog() -> RedisResult<Sink> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket.connect("/dev/log")?;
+    Ok(Sink::Syslog(socket))
+}
+
+#[cfg(not(unix))]
+fn open_syslog() -> RedisResult<Sink> {
+    Err(crate::error::RedisError::Config("当前平台不支持syslog".to_string()))
+}
+
+/// 把请求中的参数转成文本，二进制内容按UTF-8有损转换
+pub fn command_args(request: &RespValue) -> Vec<String> {
+    let RespValue::Array(items) = request else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            RespValue::BulkString(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            RespValue::SimpleString(s) => s.clone(),
+            RespValue::Integer(i) => i.to_string(),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+/// 隐去敏感参数
+///
+/// `sensitive_keys`是glob模式，命令涉及匹配的键时，除命令名和键以外的参数都被隐去
+pub fn redact(args: &mut [String], cmd: &Command, sensitive_keys: &[String]) {
+    let redact_from = |args: &mut [String], start: usize| {
+        args.iter_mut().skip(start).for_each(|arg| *arg = REDACTED.to_string());
+    };
+
+    match cmd {
+        Command::Auth { .. } => redact_from(args, 1),
+        Command::ConfigSet { name, .. } if name.eq_ignore_ascii_case("requirepass") => {
+            redact_from(args, 3)
+        }
+        _ => {
+            let keys = cmd.keys();
+            let sensitive = keys
+                .iter()
+                .any(|key| sensitive_keys.iter().any(|pattern| Store::match_pattern(key, pattern)));
+            if sensitive {
+                for arg in args.iter_mut().skip(1) {
+                    if !keys.contains(&arg.as_str()) {
+                        *arg = REDACTED.to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp;
+
+    fn redacted(args: &[&str], sensitive_keys: &[&str]) -> Vec<String> {
+        let request = RespValue::Array(args.iter().map(|a| resp::bulk_string(a)).collect());
+        let cmd = Command::from_resp(request.clone()).unwrap();
+        let sensitive_keys: Vec<String> = sensitive_keys.iter().map(|p| p.to_string()).collect();
+        let mut args = command_args(&request);
+        redact(&mut args, &cmd, &sensitive_keys);
+        args
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redacted(&["AUTH", "admin", "pw"], &[]), ["AUTH", REDACTED, REDACTED]);
+        assert_eq!(
+            redacted(&["CONFIG", "SET", "requirepass", "pw"], &[]),
+            ["CONFIG", "SET", "requirepass", REDACTED]
+        );
+        assert_eq!(redacted(&["SET", "user:1", "v"], &["secret:*"]), ["SET", "user:1", "v"]);
+        assert_eq!(
+            redacted(&["SET", "secret:1", "v", "EX", "10"], &["secret:*"]),
+            ["SET", "secret:1", REDACTED, REDACTED, REDACTED]
+        );
+        assert_eq!(
+            redacted(&["MSET", "a", "1", "secret:2", "2"], &["secret:*"]),
+            ["MSET", "a", REDACTED, "secret:2", REDACTED]
+        );
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = std::env::temp_dir().join(format!("rust-redis-audit-{}.log", std::process::id()));
+        let audit = AuditLog::open(path.to_str().unwrap()).unwrap();
+        audit.record("127.0.0.1:5000", Some("default"), 0, &["GET".to_string(), "k".to_string()]);
+        audit.record("127.0.0.1:5000", None, 0, &["PING".to_string()]);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> =
+            content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["client"], "127.0.0.1:5000");
+        assert_eq!(lines[0]["user"], "default");
+        assert_eq!(lines[0]["command"], serde_json::json!(["GET", "k"]));
+        assert!(lines[1]["user"].is_null());
+        let _ = std::fs::remove_file(path);
+    }
+}
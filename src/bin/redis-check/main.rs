@@ -0,0 +1,180 @@
+//! 持久化文件检查工具 - 对应redis-check-rdb和redis-check-aof
+//!
+//! - `redis-check rdb <文件>`: 检查RDB快照的结构和CRC64校验和
+//! - `redis-check aof [--fix] <文件或目录>`: 检查AOF，`--fix`把末尾不完整的命令截掉；
+//!   参数是Redis 7的appendonlydir目录时，按清单依次检查基础文件和增量文件，
+//!   只有最后一个增量文件(崩溃时正在写入的文件)可以被修复
+//!
+//! 解析由库中的`rdb`和`aof`模块完成，本工具只负责读写文件和报告结果。
+//!
+//! Rust特点展示:
+//! - clap的`Subcommand`派生宏定义子命令
+//! - `File::set_len`原地截短文件
+
+use clap::{Parser, Subcommand};
+use redis_lib::aof;
+use redis_lib::client::tokenize;
+use redis_lib::rdb::{self, Checksum};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+type CheckResult = Result<bool, Box<dyn std::error::Error>>;
+
+/// redis-check的命令行参数
+#[derive(Debug, Parser)]
+#[command(
+    name = "redis-check",
+    version,
+    about = "离线检查RDB快照和AOF文件"
+)]
+struct Args {
+    #[command(subcommand)]
+    target: Target,
+}
+
+#[derive(Debug, Subcommand)]
+enum Target {
+    /// 检查RDB快照
+    Rdb {
+        file: PathBuf,
+    },
+    /// 检查AOF文件或appendonlydir目录
+    Aof {
+        /// 截掉末尾不完整的命令
+        #[arg(long)]
+        fix: bool,
+        path: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let result = match Args::parse().target {
+        Target::Rdb { file } => check_rdb(&file),
+        Target::Aof { fix, path } if path.is_dir() => check_aof_dir(&path, fix),
+        Target::Aof { fix, path } => check_aof(&path, fix),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))
+}
+
+fn check_rdb(path: &Path) -> CheckResult {
+    let data = read(path)?;
+    println!("检查RDB文件 {}", path.display());
+
+    let mut keys_per_db = BTreeMap::new();
+    let summary = match rdb::parse(&data, |entry| *keys_per_db.entry(entry.db).or_insert(0u64) += 1) {
+        Ok(summary) => summary,
+        Err(e) => {
+            println!("已读取 {} 个键", keys_per_db.values().sum::<u64>());
+            println!("RDB文件损坏: {}", e);
+            return Ok(false);
+        }
+    };
+
+    println!("RDB版本 {}", summary.version);
+    for (name, value) in &summary.aux {
+        println!("AUX {} = {}", name, value);
+    }
+    for (db, keys) in &keys_per_db {
+        println!("db{}: {} 个键", db, keys);
+    }
+    if summary.len < data.len() {
+        println!("EOF之后还有 {} 字节多余的数据", data.len() - summary.len);
+        return Ok(false);
+    }
+    match summary.checksum {
+        Checksum::Valid => println!("校验和正确"),
+        Checksum::Disabled => println!("校验和为0(写入时关闭了rdbchecksum)，未校验"),
+        Checksum::Absent => println!("版本5以前的文件没有校验和"),
+        Checksum::Mismatch { expected, actual } => {
+            println!("校验和不符: 文件中为 {:016x}，计算结果为 {:016x}", expected, actual);
+            return Ok(false);
+        }
+    }
+    println!("RDB文件完好");
+    Ok(true)
+}
+
+fn check_aof(path: &Path, fix: bool) -> CheckResult {
+    let data = read(path)?;
+    let report = aof::check(&data);
+
+    if let Some(preamble) = &report.preamble {
+        println!("{}: RDB前缀，版本 {}，{} 个键", path.display(), preamble.version, preamble.keys);
+    }
+    println!("{}: {} 条完整的命令", path.display(), report.commands);
+    let Some(error) = &report.error else {
+        println!("AOF文件完好");
+        return Ok(true);
+    };
+
+    println!("{}: {}", path.display(), error);
+    if !report.fixable() {
+        println!("文件中间的错误无法自动修复，请根据偏移量人工检查");
+        return Ok(false);
+    }
+
+    let discarded = data.len() - report.valid_len;
+    if !fix {
+        println!("可以用 --fix 截掉末尾的 {} 字节", discarded);
+        return Ok(false);
+    }
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(report.valid_len as u64)?;
+    println!("已截掉末尾的 {} 字节，文件长度现在为 {}", discarded, report.valid_len);
+    Ok(true)
+}
+
+/// Redis 7的多文件AOF: 清单中每行形如`file appendonly.aof.1.base.rdb seq 1 type b`，
+/// 类型b为基础文件，i为增量文件，h为等待删除的历史文件
+fn check_aof_dir(dir: &Path, fix: bool) -> CheckResult {
+    let manifest = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.extension().is_some_and(|ext| ext == "manifest"))
+        .ok_or_else(|| format!("{} 中没有AOF清单文件", dir.display()))?;
+
+    let content = fs::read_to_string(&manifest)
+        .map_err(|e| format!("无法读取 {}: {}", manifest.display(), e))?;
+    let mut files = Vec::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let tokens = tokenize(line);
+        let field = |name: &str| {
+            tokens
+                .chunks(2)
+                .find(|pair| pair[0] == name)
+                .and_then(|pair| pair.get(1).cloned())
+        };
+        let (Some(file), Some(kind)) = (field("file"), field("type")) else {
+            return Err(format!("无法解析清单中的一行: {}", line).into());
+        };
+        if kind != "h" {
+            files.push((dir.join(file), kind));
+        }
+    }
+
+    let last_incr = files.iter().rposition(|(_, kind)| kind == "i");
+    let mut ok = true;
+    for (index, (path, kind)) in files.iter().enumerate() {
+        let is_rdb = kind == "b" && path.extension().is_some_and(|ext| ext == "rdb");
+        ok &= if is_rdb {
+            check_rdb(path)?
+        } else {
+            check_aof(path, fix && Some(index) == last_incr)?
+        };
+    }
+    Ok(ok)
+}
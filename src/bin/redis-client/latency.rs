@@ -0,0 +1,110 @@
+//! 延迟测量 - `--latency`和`--latency-history`
+//!
+//! 持续发送PING并统计往返时间，输出最小/平均/最大值和百分位数。
+//!
+//! Rust特点展示:
+//! - tokio::select! 同时等待采样间隔和Ctrl-C
+//! - Instant/Duration 做高精度计时
+
+use redis_lib::client::Client;
+use redis_lib::RedisResult;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 两次采样之间的间隔，与redis-cli一致
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// `--latency-history`默认每15秒输出一行
+pub const DEFAULT_HISTORY_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 一段时间内的延迟样本(微秒)
+///
+/// 样本保持有序，每次刷新输出时直接取百分位数，不需要重新排序
+#[derive(Debug, Default)]
+struct LatencyStats {
+    sorted: Vec<u64>,
+    total: u64,
+}
+
+impl LatencyStats {
+    /// 记录一次往返时间
+    fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        let index = self.sorted.partition_point(|&s| s <= us);
+        self.sorted.insert(index, us);
+        self.total += us;
+    }
+
+    /// 汇总为一行文本，时间单位为毫秒
+    fn summary(&self) -> String {
+        let sorted = &self.sorted;
+        let ms = |us: u64| us as f64 / 1000.0;
+        let percentile = |p: f64| {
+            let index = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+            ms(sorted[index])
+        };
+
+        if sorted.is_empty() {
+            return "min: -, max: -, avg: - (0 samples)".to_string();
+        }
+        format!(
+            "min: {:.2}, max: {:.2}, avg: {:.2}, p50: {:.2}, p99: {:.2} ({} samples)",
+            ms(sorted[0]),
+            ms(sorted[sorted.len() - 1]),
+            ms(self.total) / sorted.len() as f64,
+            percentile(0.50),
+            percentile(0.99),
+            sorted.len()
+        )
+    }
+}
+
+/// 持续测量PING延迟，直到Ctrl-C
+///
+/// `history`为Some时每隔一段时间输出一行并重新统计，否则在同一行刷新累计结果
+pub async fn latency<S>(client: &mut Client<S>, history: Option<Duration>) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stats = LatencyStats::default();
+    let mut period_start = Instant::now();
+    let mut stdout = io::stdout();
+    let interactive = stdout.is_terminal();
+    // Rust特点: 固定(pin)同一个future，采样期间收到的Ctrl-C也不会丢失
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let start = Instant::now();
+        client.ping().await?;
+        stats.record(start.elapsed());
+
+        match history {
+            Some(interval) if period_start.elapsed() >= interval => {
+                println!("{} -- {:.2} seconds range", stats.summary(), period_start.elapsed().as_secs_f64());
+                stats = LatencyStats::default();
+                period_start = Instant::now();
+            }
+            Some(_) => {}
+            // 终端中在同一行刷新；输出被重定向时只在结束时输出一次
+            None if interactive => {
+                print!("\r\x1b[K{}", stats.summary());
+                stdout.flush()?;
+            }
+            None => {}
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(SAMPLE_INTERVAL) => {}
+            _ = &mut ctrl_c => break,
+        }
+    }
+
+    match history {
+        None if interactive => println!(),
+        None => println!("{}", stats.summary()),
+        Some(_) => {}
+    }
+    Ok(())
+}
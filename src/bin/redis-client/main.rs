@@ -0,0 +1,213 @@
+//! Redis客户端 - 展示Rust的异步IO和用户交互
+//!
+//! 选项与redis-cli一致: 不带命令时进入交互式REPL，带命令时执行一次后退出，
+//! 网络通信由 `redis_lib::client::Client` 完成
+//!
+//! Rust特点展示:
+//! - 异步网络IO
+//! - 二进制crate拆分为多个模块
+//! - 错误处理
+
+mod args;
+mod latency;
+mod monitor;
+mod output;
+mod pipe;
+mod rdb;
+mod repl;
+mod scan;
+mod stat;
+
+use args::Args;
+use clap::Parser;
+use output::print_response;
+use redis_lib::client::Client;
+use redis_lib::resp::RespValue;
+use redis_lib::uri::RedisUri;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // 解析命令行参数
+    let args = Args::parse();
+
+    // 以Display格式报告错误，与redis-cli的输出习惯一致
+    match start(args).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// 建立连接
+async fn start(args: Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut uri = args.connection()?;
+    if args.askpass {
+        uri.password = Some(read_password("请输入密码: ")?);
+    }
+
+    let addr = match &uri.socket {
+        Some(path) => path.display().to_string(),
+        None => format!("{}:{}", uri.host, uri.port),
+    };
+
+    // 连接服务器
+    // Rust特点: 几种连接类型不同，但都交给同一个泛型函数处理
+    match (&uri.socket, uri.tls) {
+        #[cfg(unix)]
+        (Some(path), _) => run(Client::connect_unix(path).await?, &args, &uri, &addr).await,
+        #[cfg(not(unix))]
+        (Some(_), _) => Err("当前平台不支持Unix域套接字".into()),
+        (None, true) => {
+            let client = Client::connect_tls(&addr, &args.tls_options()).await?;
+            run(client, &args, &uri, &addr).await
+        }
+        (None, false) => run(Client::connect(&addr).await?, &args, &uri, &addr).await,
+    }
+}
+
+/// 完成认证和选库，然后执行单条命令或进入交互模式
+///
+/// Rust特点: 泛型函数，对任何底层流类型的客户端都适用
+async fn run<S>(
+    mut client: Client<S>,
+    args: &Args,
+    uri: &RedisUri,
+    addr: &str,
+) -> Result<ExitCode, Box<dyn std::error::Error>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Some(password) = &uri.password {
+        match &uri.username {
+            Some(username) => client.auth_with_username(username, password).await?,
+            None => client.auth(password).await?,
+        }
+    }
+    if let Some(db) = uri.db {
+        client.select(db).await?;
+    }
+
+    // 管道模式: 批量导入，有错误回复时退出码非零
+    if args.pipe {
+        let summary = pipe::pipe(&mut client, tokio::io::stdin()).await?;
+        return Ok(if summary.errors == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        });
+    }
+
+    // 键空间扫描
+    if args.scan {
+        scan::scan_keys(&mut client, args.pattern.as_deref(), args.count).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.bigkeys || args.memkeys {
+        let measure = if args.memkeys {
+            scan::Measure::Memory
+        } else {
+            scan::Measure::Length
+        };
+        scan::big_keys(&mut client, measure, args.count).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.latency || args.latency_history {
+        latency::latency(&mut client, args.latency_history()).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(path) = &args.rdb {
+        let written = rdb::save_rdb(&mut client, path).await?;
+        eprintln!("已保存 {} 字节到 {}", written, path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.monitor {
+        monitor::monitor(&mut client, args.pattern.as_deref()).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.stat {
+        stat::stat(&mut client, args.interval.unwrap_or(stat::DEFAULT_INTERVAL)).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // 非交互模式: 执行命令行上的命令，-r/-i指定重复次数和间隔
+    let command = args.command_args()?;
+    if !command.is_empty() {
+        let request = redis_lib::client::build_command(&command);
+        let repeat = args.repeat.unwrap_or(1);
+        let mut failed = false;
+        let mut executed = 0;
+        while repeat < 0 || executed < repeat {
+            if executed > 0 {
+                if let Some(interval) = args.interval {
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            let response = client.execute(&request).await?;
+            print_response(&response, args.output_format());
+            failed |= matches!(response, RespValue::Error(_));
+            executed += 1;
+        }
+        return Ok(if failed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+
+    let prompt = match uri.db {
+        Some(db) if db != 0 => format!("{}[{}]> ", addr, db),
+        _ => format!("{}> ", addr),
+    };
+    println!("连接到 {}...", addr);
+    repl::repl(client, &prompt, args.output_format()).await?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 从终端读取密码，输入时不回显
+#[cfg(unix)]
+fn read_password(prompt: &str) -> io::Result<String> {
+    use std::os::unix::io::AsRawFd;
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let fd = io::stdin().as_raw_fd();
+    // Rust特点: MaybeUninit避免读取未初始化的内存，由tcgetattr填充
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    let original = if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } == 0 {
+        let original = unsafe { termios.assume_init() };
+        let mut silent = original;
+        silent.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) };
+        Some(original)
+    } else {
+        // 标准输入不是终端(如管道)，直接读取
+        None
+    };
+
+    let mut password = String::new();
+    let result = io::stdin().read_line(&mut password);
+
+    if let Some(original) = original {
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+        eprintln!();
+    }
+    result?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(not(unix))]
+fn read_password(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    let mut password = String::new();
+    io::stdin().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
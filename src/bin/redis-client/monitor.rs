@@ -0,0 +1,91 @@
+//! 监视模式 - `--monitor`
+//!
+//! 发送MONITOR后逐行输出服务器执行的命令: 时间戳换成本地时间，后面是数据库、客户端地址和参数。
+//! `--pattern`只显示涉及匹配的键的命令，键的位置来自命令元数据表，
+//! 表中没有的命令按全部参数匹配。Ctrl-C时退出。
+//!
+//! Rust特点展示:
+//! - tokio::select! 同时等待下一行输出和Ctrl-C
+//! - cfg属性按平台选择本地时间的实现
+
+use redis_lib::client::Client;
+use redis_lib::metadata;
+use redis_lib::monitor::{self, MonitorLine};
+use redis_lib::resp::RespValue;
+use redis_lib::{RedisResult, Store};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 进入MONITOR并输出命令，直到Ctrl-C或连接关闭
+pub async fn monitor<S>(client: &mut Client<S>, pattern: Option<&str>) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    client.command(["MONITOR"]).await?;
+    eprintln!("正在监视，按Ctrl-C退出");
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let reply = tokio::select! {
+            reply = client.read_reply() => reply?,
+            _ = &mut ctrl_c => return Ok(()),
+        };
+        let RespValue::SimpleString(text) = reply else {
+            continue;
+        };
+        match monitor::parse_line(&text) {
+            Some(line) if pattern.is_none_or(|pattern| matches(&line, pattern)) => {
+                // 时间戳之后的部分原样输出，参数保持服务器转义后的形式
+                let rest = text.split_once(' ').map_or("", |(_, rest)| rest);
+                println!("{} {}", format_time(line.timestamp), rest);
+            }
+            Some(_) => {}
+            None => println!("{}", text),
+        }
+    }
+}
+
+/// 命令是否涉及匹配`pattern`的键
+fn matches(line: &MonitorLine, pattern: &str) -> bool {
+    let argv: Vec<String> = line
+        .args
+        .iter()
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    match metadata::get_keys(&argv) {
+        Ok(keys) => keys.iter().any(|key| Store::match_pattern(key, pattern)),
+        Err(_) => argv[1..].iter().any(|arg| Store::match_pattern(arg, pattern)),
+    }
+}
+
+/// 把unix时间戳格式化为本地时间`HH:MM:SS.ffffff`
+#[cfg(unix)]
+fn format_time(timestamp: f64) -> String {
+    let secs = timestamp.trunc() as libc::time_t;
+    let micros = (timestamp.fract() * 1_000_000.0).round() as u32;
+    // Rust特点: MaybeUninit由localtime_r填充，失败时退回UTC
+    let mut tm = std::mem::MaybeUninit::<libc::tm>::uninit();
+    if unsafe { libc::localtime_r(&secs, tm.as_mut_ptr()) }.is_null() {
+        return format_utc(timestamp);
+    }
+    let tm = unsafe { tm.assume_init() };
+    format!("{:02}:{:02}:{:02}.{:06}", tm.tm_hour, tm.tm_min, tm.tm_sec, micros.min(999_999))
+}
+
+#[cfg(not(unix))]
+fn format_time(timestamp: f64) -> String {
+    format_utc(timestamp)
+}
+
+/// UTC时间`HH:MM:SS.ffffffZ`
+fn format_utc(timestamp: f64) -> String {
+    let secs = timestamp as u64 % 86400;
+    let micros = (timestamp.fract() * 1_000_000.0).round() as u32;
+    format!(
+        "{:02}:{:02}:{:02}.{:06}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        micros.min(999_999)
+    )
+}
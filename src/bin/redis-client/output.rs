@@ -0,0 +1,184 @@
+//! 输出格式 - 以redis-cli的风格打印回复
+//!
+//! 除默认的交互格式外，还支持`--raw`、`--csv`和`--json`，方便把结果交给jq或awk处理。
+//! 与redis-cli一样，标准输出不是终端时默认使用原始格式。
+//!
+//! Rust特点展示:
+//! - 递归模式匹配处理嵌套数组
+//! - 以Vec<u8>拼接输出，原始格式下二进制数据原样写出
+
+use redis_lib::resp::RespValue;
+use std::io::{self, Write};
+
+/// 回复的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 交互格式: 带类型标注和编号，字符串加引号并转义
+    Standard,
+    /// 原始格式: 每个值一行，字符串不加引号也不转义
+    Raw,
+    /// CSV: 一条回复一行，元素以逗号分隔
+    Csv,
+    /// JSON: 一条回复一个JSON值
+    Json,
+}
+
+/// 按指定格式打印响应
+pub fn print_response(value: &RespValue, format: OutputFormat) {
+    let mut out = format_response(value, format);
+    out.push(b'\n');
+
+    // 管道另一端关闭时静默结束，与其他命令行工具一致
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(&out).and_then(|_| stdout.flush());
+}
+
+/// 把响应格式化为字节，不含结尾换行
+pub fn format_response(value: &RespValue, format: OutputFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    match format {
+        OutputFormat::Standard => format_standard(value, 0, &mut out),
+        OutputFormat::Raw => format_raw(value, &mut out),
+        OutputFormat::Csv => format_csv(value, &mut out),
+        OutputFormat::Json => format_json(value, &mut out),
+    }
+    out
+}
+
+/// 交互格式
+///
+/// Rust特点: 递归模式匹配，嵌套数组按层级缩进
+fn format_standard(value: &RespValue, indent: usize, out: &mut Vec<u8>) {
+    match value {
+        RespValue::SimpleString(s) => quote(s.as_bytes(), out),
+        RespValue::Error(e) => out.extend_from_slice(format!("(error) {}", e).as_bytes()),
+        RespValue::Integer(i) => out.extend_from_slice(format!("(integer) {}", i).as_bytes()),
+        RespValue::BulkString(data) => quote(data, out),
+        RespValue::Null => out.extend_from_slice(b"(nil)"),
+        RespValue::Array(arr) if arr.is_empty() => out.extend_from_slice(b"(empty array)"),
+        RespValue::Array(arr) => {
+            let prefix = "  ".repeat(indent);
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b'\n');
+                    out.extend_from_slice(prefix.as_bytes());
+                }
+                out.extend_from_slice(format!("{}) ", i + 1).as_bytes());
+                // 嵌套数组另起一行并多缩进一级
+                if matches!(item, RespValue::Array(items) if !items.is_empty()) {
+                    out.push(b'\n');
+                    out.extend_from_slice(prefix.as_bytes());
+                    out.extend_from_slice(b"  ");
+                }
+                format_standard(item, indent + 1, out);
+            }
+        }
+    }
+}
+
+/// 原始格式
+fn format_raw(value: &RespValue, out: &mut Vec<u8>) {
+    match value {
+        RespValue::SimpleString(s) => out.extend_from_slice(s.as_bytes()),
+        RespValue::Error(e) => out.extend_from_slice(e.as_bytes()),
+        RespValue::Integer(i) => out.extend_from_slice(i.to_string().as_bytes()),
+        RespValue::BulkString(data) => out.extend_from_slice(data),
+        RespValue::Null => {}
+        RespValue::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b'\n');
+                }
+                format_raw(item, out);
+            }
+        }
+    }
+}
+
+/// CSV格式
+fn format_csv(value: &RespValue, out: &mut Vec<u8>) {
+    match value {
+        RespValue::SimpleString(s) => quote(s.as_bytes(), out),
+        RespValue::Error(e) => {
+            out.extend_from_slice(b"ERROR,");
+            quote(e.as_bytes(), out);
+        }
+        RespValue::Integer(i) => out.extend_from_slice(i.to_string().as_bytes()),
+        RespValue::BulkString(data) => quote(data, out),
+        RespValue::Null => out.extend_from_slice(b"NULL"),
+        RespValue::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                format_csv(item, out);
+            }
+        }
+    }
+}
+
+/// JSON格式
+fn format_json(value: &RespValue, out: &mut Vec<u8>) {
+    match value {
+        RespValue::SimpleString(s) => json_string(s.as_bytes(), out),
+        RespValue::Error(e) => {
+            out.extend_from_slice(b"{\"error\":");
+            json_string(e.as_bytes(), out);
+            out.push(b'}');
+        }
+        RespValue::Integer(i) => out.extend_from_slice(i.to_string().as_bytes()),
+        RespValue::BulkString(data) => json_string(data, out),
+        RespValue::Null => out.extend_from_slice(b"null"),
+        RespValue::Array(arr) => {
+            out.push(b'[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                format_json(item, out);
+            }
+            out.push(b']');
+        }
+    }
+}
+
+/// 加双引号并转义，不可打印字节写成`\xNN`(与redis-cli一致)
+fn quote(data: &[u8], out: &mut Vec<u8>) {
+    let utf8 = std::str::from_utf8(data).is_ok();
+    out.push(b'"');
+    for &b in data {
+        match b {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            0x07 => out.extend_from_slice(b"\\a"),
+            0x08 => out.extend_from_slice(b"\\b"),
+            // UTF-8多字节字符原样保留，中文等可以直接显示
+            b if b.is_ascii_graphic() || b == b' ' || (b >= 0x80 && utf8) => out.push(b),
+            b => out.extend_from_slice(format!("\\x{:02x}", b).as_bytes()),
+        }
+    }
+    out.push(b'"');
+}
+
+/// JSON字符串；数据不是合法UTF-8时，每个非ASCII字节按Latin-1写成`\u00NN`
+fn json_string(data: &[u8], out: &mut Vec<u8>) {
+    let utf8 = std::str::from_utf8(data).is_ok();
+    out.push(b'"');
+    for &b in data {
+        match b {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b if b < 0x20 || b == 0x7f || (b >= 0x80 && !utf8) => {
+                out.extend_from_slice(format!("\\u{:04x}", b).as_bytes())
+            }
+            b => out.push(b),
+        }
+    }
+    out.push(b'"');
+}
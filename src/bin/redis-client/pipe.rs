@@ -0,0 +1,143 @@
+//! 管道模式 - 把标准输入中的大量命令以流水线方式发给服务器
+//!
+//! 输入可以是原始RESP(以`*`开头)，也可以是每行一条的命令文本。
+//! 每批最多`BATCH_SIZE`条命令一次写出，再读取这一批的回复，
+//! 既能摊薄往返延迟，又不会因为回复堆积而互相阻塞。
+//!
+//! Rust特点展示:
+//! - BytesMut 作为增量解析的缓冲区
+//! - 枚举表示输入格式，首个字节决定走哪个分支
+
+use bytes::{Buf, BytesMut};
+use redis_lib::client::{build_command, tokenize, Client};
+use redis_lib::resp::{RespParser, RespValue};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+/// 每批发送的命令数
+const BATCH_SIZE: usize = 1000;
+
+/// 最多打印多少条错误回复
+const MAX_PRINTED_ERRORS: usize = 10;
+
+/// 输入格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    /// 原始RESP协议
+    Resp,
+    /// 每行一条命令
+    Lines,
+}
+
+/// 导入结果统计
+#[derive(Debug, Default)]
+pub struct PipeSummary {
+    /// 收到的回复数
+    pub replies: u64,
+    /// 其中的错误回复数
+    pub errors: u64,
+}
+
+/// 从`input`读取命令并以流水线方式发送
+pub async fn pipe<S, R>(client: &mut Client<S>, mut input: R) -> redis_lib::RedisResult<PipeSummary>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let start = Instant::now();
+    let mut summary = PipeSummary::default();
+    let mut buffer = BytesMut::with_capacity(64 * 1024);
+    let mut format = None;
+    let mut batch = Vec::new();
+    let mut pending = 0;
+
+    loop {
+        let eof = input.read_buf(&mut buffer).await? == 0;
+
+        // 第一个非空白字节决定输入格式
+        let format = *format.get_or_insert_with(|| match buffer.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'*') => InputFormat::Resp,
+            _ => InputFormat::Lines,
+        });
+
+        // 从缓冲区取出所有完整的命令
+        while let Some(command) = next_command(&mut buffer, format, eof)? {
+            batch.extend_from_slice(&command.serialize());
+            pending += 1;
+
+            if pending == BATCH_SIZE {
+                flush_batch(client, &mut batch, &mut pending, &mut summary).await?;
+            }
+        }
+
+        if eof {
+            break;
+        }
+    }
+    flush_batch(client, &mut batch, &mut pending, &mut summary).await?;
+
+    eprintln!(
+        "全部数据已发送，耗时 {:.2?}，errors: {}, replies: {}",
+        start.elapsed(),
+        summary.errors,
+        summary.replies
+    );
+    Ok(summary)
+}
+
+/// 从缓冲区解析下一条命令；数据不完整时返回None
+///
+/// 行模式下最后一行可以没有换行符，所以需要知道是否已经读到输入末尾
+fn next_command(buffer: &mut BytesMut, format: InputFormat, eof: bool) -> redis_lib::RedisResult<Option<RespValue>> {
+    match format {
+        InputFormat::Resp => {
+            // 跳过命令之间多余的换行
+            while buffer.first().is_some_and(|b| b.is_ascii_whitespace()) {
+                buffer.advance(1);
+            }
+            RespParser::parse(buffer)
+        }
+        InputFormat::Lines => loop {
+            let line = match buffer.iter().position(|&b| b == b'\n') {
+                Some(end) => buffer.split_to(end + 1),
+                None if eof && !buffer.is_empty() => buffer.split(),
+                None => return Ok(None),
+            };
+
+            let line = String::from_utf8_lossy(&line);
+            let tokens = tokenize(line.trim());
+            if !tokens.is_empty() {
+                return Ok(Some(build_command(tokens)));
+            }
+        },
+    }
+}
+
+/// 发送一批命令并读取全部回复
+async fn flush_batch<S>(
+    client: &mut Client<S>,
+    batch: &mut Vec<u8>,
+    pending: &mut usize,
+    summary: &mut PipeSummary,
+) -> redis_lib::RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if *pending == 0 {
+        return Ok(());
+    }
+
+    client.send_raw(batch).await?;
+    batch.clear();
+
+    for _ in 0..std::mem::take(pending) {
+        if let RespValue::Error(e) = client.read_reply().await? {
+            if summary.errors < MAX_PRINTED_ERRORS as u64 {
+                eprintln!("(error) {}", e);
+            }
+            summary.errors += 1;
+        }
+        summary.replies += 1;
+    }
+    Ok(())
+}
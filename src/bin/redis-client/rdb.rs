@@ -0,0 +1,109 @@
+//! 远程备份 - `--rdb <file>`
+//!
+//! 像副本一样向服务器发送SYNC，把服务器传来的RDB快照原样保存到本地文件。
+//! 快照有两种格式: `$<长度>`之后跟固定长度的数据；无盘复制时是`$EOF:<40字节标记>`，
+//! 数据以同一个标记结尾。生成快照期间服务器会发送空行保活。
+//!
+//! Rust特点展示:
+//! - 边接收边写文件，不把整个快照放在内存中
+//! - 无盘格式只保留末尾可能是标记的一小段，其余数据直接写出
+
+use redis_lib::client::Client;
+use redis_lib::{RedisError, RedisResult};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 无盘复制的结束标记长度
+const EOF_MARK_LEN: usize = 40;
+
+/// 每次读取的最大字节数
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 快照的传输格式
+#[derive(Debug, PartialEq, Eq)]
+enum Payload {
+    /// 已知长度
+    Length(u64),
+    /// 以标记结尾
+    Eof(Vec<u8>),
+}
+
+/// 解析SYNC回复的第一行
+fn parse_header(line: &[u8]) -> RedisResult<Payload> {
+    let text = String::from_utf8_lossy(line);
+    if let Some(error) = text.strip_prefix('-') {
+        return Err(RedisError::ServerError(error.to_string()));
+    }
+    let Some(rest) = text.strip_prefix('$') else {
+        return Err(RedisError::Protocol(format!("意外的SYNC回复: {}", text)));
+    };
+    match rest.strip_prefix("EOF:") {
+        Some(mark) if mark.len() == EOF_MARK_LEN => Ok(Payload::Eof(mark.as_bytes().to_vec())),
+        Some(_) => Err(RedisError::Protocol(format!("无效的结束标记: {}", text))),
+        None => rest
+            .parse()
+            .map(Payload::Length)
+            .map_err(|_| RedisError::Protocol(format!("无效的快照长度: {}", text))),
+    }
+}
+
+/// 通过SYNC下载快照并保存到`path`，返回写入的字节数
+pub async fn save_rdb<S>(client: &mut Client<S>, path: &Path) -> RedisResult<u64>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // 只需要快照，不需要之后的命令流；不支持该选项的服务器忽略错误即可
+    let _ = client.command(["REPLCONF", "rdb-only", "1"]).await;
+    client.send(&redis_lib::client::build_command(["SYNC"])).await?;
+
+    // 跳过生成快照期间的保活空行
+    let header = loop {
+        let line = client.read_line().await?;
+        if !line.is_empty() {
+            break parse_header(&line)?;
+        }
+    };
+
+    let mut file = BufWriter::new(File::create(path)?);
+    let written = match header {
+        Payload::Length(len) => {
+            eprintln!("SYNC已开始，需要接收 {} 字节", len);
+            let mut remaining = len;
+            while remaining > 0 {
+                let chunk = client.read_raw(CHUNK_SIZE.min(remaining as usize)).await?;
+                if chunk.is_empty() {
+                    return Err(RedisError::ConnectionClosed);
+                }
+                file.write_all(&chunk)?;
+                remaining -= chunk.len() as u64;
+            }
+            len
+        }
+        Payload::Eof(mark) => {
+            eprintln!("SYNC已开始(无盘复制)，接收到结束标记为止");
+            // 末尾的EOF_MARK_LEN字节可能是标记，确认之前不写出
+            let mut tail = Vec::new();
+            let mut written = 0;
+            loop {
+                let chunk = client.read_raw(CHUNK_SIZE).await?;
+                if chunk.is_empty() {
+                    return Err(RedisError::ConnectionClosed);
+                }
+                tail.extend_from_slice(&chunk);
+                if tail.ends_with(&mark) {
+                    tail.truncate(tail.len() - EOF_MARK_LEN);
+                    file.write_all(&tail)?;
+                    break written + tail.len() as u64;
+                }
+                let keep = tail.len().min(EOF_MARK_LEN);
+                let flushed: Vec<u8> = tail.drain(..tail.len() - keep).collect();
+                file.write_all(&flushed)?;
+                written += flushed.len() as u64;
+            }
+        }
+    };
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(written)
+}
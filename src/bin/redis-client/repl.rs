@@ -0,0 +1,172 @@
+//! 交互模式 - 读取一行命令，发送并打印回复
+//!
+//! Rust特点展示:
+//! - 泛型函数适配任何底层流类型的客户端
+//! - 状态机式的分词器
+
+use crate::output::{print_response, OutputFormat};
+use redis_lib::client::Client;
+use redis_lib::metadata;
+use redis_lib::resp::RespValue;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Validator};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 行编辑辅助: 命令名补全和参数提示，数据来自命令元数据表
+///
+/// Rust特点: derive宏为不需要定制的trait(Helper、Validator)生成默认实现
+#[derive(Helper, Validator)]
+struct CommandHelper;
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    /// 只补全第一个单词(命令名)
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix = &line[..pos];
+        if prefix.contains(' ') {
+            return Ok((pos, Vec::new()));
+        }
+
+        let candidates = metadata::completions(prefix)
+            .map(|info| Pair {
+                display: info.name.to_string(),
+                replacement: format!("{} ", info.name),
+            })
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+
+    /// 输入完命令名后，以灰色显示其余参数的格式
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let mut words = line.split_whitespace();
+        let info = metadata::lookup(words.next()?)?;
+        let typed = words.count();
+        let ends_with_space = line.ends_with(' ');
+
+        // 跳过已经输入的参数
+        let remaining: Vec<&str> = argument_groups(info.arguments).skip(typed).collect();
+        if remaining.is_empty() {
+            return None;
+        }
+        let hint = remaining.join(" ");
+        Some(if ends_with_space { hint } else { format!(" {}", hint) })
+    }
+}
+
+impl Highlighter for CommandHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint))
+    }
+}
+
+/// 按顶层空格拆分参数格式，方括号内的空格不拆分
+///
+/// 例如`key value [EX seconds]`拆为`key`、`value`、`[EX seconds]`
+fn argument_groups(arguments: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    arguments
+        .split(move |c: char| {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            c == ' ' && depth == 0
+        })
+        .filter(|group| !group.is_empty())
+}
+
+/// 历史文件路径: 优先使用REDISCLI_HISTFILE，否则为~/.rediscli_history
+fn history_path() -> Option<PathBuf> {
+    match std::env::var_os("REDISCLI_HISTFILE") {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rediscli_history")),
+    }
+}
+
+/// REPL循环
+///
+/// Rust特点: 泛型函数，对任何底层流类型的客户端都适用
+pub async fn repl<S>(
+    mut client: Client<S>,
+    prompt: &str,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    println!("已连接！输入 QUIT 退出，TAB 补全命令。\n");
+
+    let mut editor: Editor<CommandHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(CommandHelper));
+    let history = history_path();
+    if let Some(path) = &history {
+        // 第一次运行时历史文件还不存在
+        let _ = editor.load_history(path);
+    }
+
+    loop {
+        // 读取用户输入；Ctrl-C放弃当前行，Ctrl-D退出
+        let input = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let input = input.trim();
+        if input.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(input)?;
+
+        // 解析用户输入为RESP命令并发送
+        let command = parse_input(input);
+        match client.execute(&command).await {
+            Ok(response) => print_response(&response, format),
+            Err(redis_lib::RedisError::ConnectionClosed) => {
+                println!("服务器断开连接");
+                break;
+            }
+            Err(e) => eprintln!("错误: {}", e),
+        }
+
+        // 检查是否是QUIT命令
+        if input.eq_ignore_ascii_case("QUIT") {
+            println!("再见！");
+            break;
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("保存历史记录失败: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// 将用户输入解析为RESP数组
+///
+/// Rust特点: 迭代器和闭包的组合
+fn parse_input(input: &str) -> RespValue {
+    // 简单的空格分割，支持引号内的空格
+    redis_lib::client::build_command(redis_lib::client::tokenize(input))
+}
+
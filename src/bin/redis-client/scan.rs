@@ -0,0 +1,178 @@
+//! 键空间扫描 - `--scan`列出键，`--bigkeys`/`--memkeys`找出每种类型最大的键
+//!
+//! 全部基于SCAN增量遍历，不会像KEYS那样长时间阻塞服务器。
+//!
+//! Rust特点展示:
+//! - BTreeMap 按类型名排序输出统计
+//! - 枚举区分按长度还是按内存统计
+
+use redis_lib::client::Client;
+use redis_lib::resp::RespValue;
+use redis_lib::RedisResult;
+use std::collections::BTreeMap;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 每次SCAN请求的键数
+pub const DEFAULT_COUNT: usize = 100;
+
+/// 大键统计的度量方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measure {
+    /// 按元素数量或字符串长度(--bigkeys)
+    Length,
+    /// 按内存占用(--memkeys)
+    Memory,
+}
+
+/// 单个类型的统计
+#[derive(Debug, Default)]
+struct TypeStats {
+    /// 该类型的键数
+    keys: u64,
+    /// 大小总和
+    total: u64,
+    /// 最大的键及其大小
+    biggest: Option<(String, u64)>,
+}
+
+/// 流式打印所有匹配的键
+pub async fn scan_keys<S>(client: &mut Client<S>, pattern: Option<&str>, count: usize) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut cursor = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, pattern, count).await?;
+        for key in keys {
+            println!("{}", key);
+        }
+        if next == 0 {
+            return Ok(());
+        }
+        cursor = next;
+    }
+}
+
+/// 遍历整个键空间，报告每种类型最大的键和平均大小
+pub async fn big_keys<S>(client: &mut Client<S>, measure: Measure, count: usize) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let total_keys = match client.command(["DBSIZE"]).await? {
+        RespValue::Integer(n) => n.max(1) as u64,
+        _ => 1,
+    };
+
+    println!();
+    println!("# 扫描整个键空间，找出每种类型最大的键以及平均大小");
+    println!();
+
+    let mut stats: BTreeMap<String, TypeStats> = BTreeMap::new();
+    let mut sampled = 0u64;
+    let mut key_bytes = 0u64;
+    let mut cursor = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, None, count).await?;
+        for key in keys {
+            // 遍历期间被删除的键TYPE返回none，跳过
+            let key_type = match client.command(["TYPE", &key]).await? {
+                RespValue::SimpleString(t) if t != "none" => t,
+                _ => continue,
+            };
+            let Some(size) = key_size(client, &key, &key_type, measure).await? else {
+                continue;
+            };
+
+            sampled += 1;
+            key_bytes += key.len() as u64;
+            let entry = stats.entry(key_type.clone()).or_default();
+            entry.keys += 1;
+            entry.total += size;
+            if entry.biggest.as_ref().is_none_or(|(_, biggest)| size > *biggest) {
+                println!(
+                    "[{:05.2}%] 目前最大的 {} '{}' 有 {} {}",
+                    sampled.min(total_keys) as f64 * 100.0 / total_keys as f64,
+                    key_type,
+                    key,
+                    size,
+                    unit(&key_type, measure)
+                );
+                entry.biggest = Some((key, size));
+            }
+        }
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+
+    println!();
+    println!("-------- 汇总 -------");
+    println!();
+    println!("共采样 {} 个键", sampled);
+    println!(
+        "键名总长度 {} 字节 (平均 {:.2})",
+        key_bytes,
+        key_bytes as f64 / sampled.max(1) as f64
+    );
+    println!();
+    for (key_type, entry) in &stats {
+        if let Some((key, size)) = &entry.biggest {
+            println!("最大的 {} '{}' 有 {} {}", key_type, key, size, unit(key_type, measure));
+        }
+    }
+    println!();
+    for (key_type, entry) in &stats {
+        println!(
+            "{} 个 {}，共 {} {} (占全部键的 {:.2}%，平均 {:.2})",
+            entry.keys,
+            key_type,
+            entry.total,
+            unit(key_type, measure),
+            entry.keys as f64 * 100.0 / sampled.max(1) as f64,
+            entry.total as f64 / entry.keys as f64
+        );
+    }
+    Ok(())
+}
+
+/// 查询键的大小；类型不支持或键已被删除时返回None
+async fn key_size<S>(client: &mut Client<S>, key: &str, key_type: &str, measure: Measure) -> RedisResult<Option<u64>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let reply = match measure {
+        Measure::Memory => client.command(["MEMORY", "USAGE", key]).await?,
+        Measure::Length => {
+            let Some(command) = length_command(key_type) else {
+                return Ok(None);
+            };
+            client.command([command, key]).await?
+        }
+    };
+    Ok(match reply {
+        RespValue::Integer(n) => Some(n.max(0) as u64),
+        _ => None,
+    })
+}
+
+/// 获取各类型长度的命令
+fn length_command(key_type: &str) -> Option<&'static str> {
+    match key_type {
+        "string" => Some("STRLEN"),
+        "list" => Some("LLEN"),
+        "set" => Some("SCARD"),
+        "zset" => Some("ZCARD"),
+        "hash" => Some("HLEN"),
+        _ => None,
+    }
+}
+
+/// 大小的单位
+fn unit(key_type: &str, measure: Measure) -> &'static str {
+    match (measure, key_type) {
+        (Measure::Memory, _) | (Measure::Length, "string") => "bytes",
+        (Measure::Length, "hash") => "fields",
+        _ => "members",
+    }
+}
@@ -0,0 +1,94 @@
+//! 滚动统计 - `--stat`
+//!
+//! 每隔一段时间执行INFO，输出一行键数、内存、连接数和命令数，
+//! 命令数后面的括号中是与上一行的差值，即这段时间内执行的命令数。
+//! 服务器没有提供的字段显示为`-`。
+//!
+//! Rust特点展示:
+//! - HashMap<&str, &str> 借用INFO文本，不复制每个字段
+//! - tokio::select! 同时等待间隔和Ctrl-C
+
+use redis_lib::client::Client;
+use redis_lib::resp::RespValue;
+use redis_lib::{RedisError, RedisResult};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// 默认每秒输出一行
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 每隔多少行重新输出一次表头
+const HEADER_EVERY: usize = 20;
+
+/// 解析INFO回复: 跳过`#`开头的段落标题，返回`字段 -> 值`
+fn parse_info(text: &str) -> HashMap<&str, &str> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .collect()
+}
+
+/// 所有数据库的键数之和，`db0:keys=1,expires=0`
+fn total_keys(info: &HashMap<&str, &str>) -> Option<u64> {
+    let counts: Vec<u64> = info
+        .iter()
+        .filter(|(name, _)| name.starts_with("db"))
+        .filter_map(|(_, value)| {
+            value
+                .split(',')
+                .find_map(|field| field.strip_prefix("keys="))
+                .and_then(|keys| keys.parse().ok())
+        })
+        .collect();
+    (!counts.is_empty()).then(|| counts.iter().sum())
+}
+
+/// 持续输出统计，直到Ctrl-C
+pub async fn stat<S>(client: &mut Client<S>, interval: Duration) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut last_requests: Option<u64> = None;
+
+    for line in 0.. {
+        let text = match client.command(["INFO"]).await? {
+            RespValue::BulkString(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            other => return Err(RedisError::Protocol(format!("意外的INFO回复: {:?}", other))),
+        };
+        let info = parse_info(&text);
+        let field = |name: &str| info.get(name).copied().unwrap_or("-");
+
+        if line % HEADER_EVERY == 0 {
+            println!("------- data ------ ------------------ load ------------------");
+            println!("keys       mem      clients requests            connections");
+        }
+
+        let keys = total_keys(&info).map_or("-".to_string(), |keys| keys.to_string());
+        let requests = info
+            .get("total_commands_processed")
+            .and_then(|value| value.parse::<u64>().ok());
+        let requests_column = match (requests, last_requests) {
+            (Some(now), Some(before)) => format!("{} (+{})", now, now.saturating_sub(before)),
+            (Some(now), None) => now.to_string(),
+            (None, _) => "-".to_string(),
+        };
+        last_requests = requests;
+        println!(
+            "{:<10} {:<8} {:<7} {:<19} {}",
+            keys,
+            field("used_memory_human"),
+            field("connected_clients"),
+            requests_column,
+            field("total_connections_received")
+        );
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = &mut ctrl_c => break,
+        }
+    }
+    Ok(())
+}
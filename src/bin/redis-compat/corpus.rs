@@ -0,0 +1,82 @@
+//! 用例文件解析
+//!
+//! 格式:
+//! ```text
+//! # 注释
+//! === 用例名称
+//! SET k v
+//! ~ KEYS *
+//! ```
+//! 每个用例以`=== 名称`开头，之后每行一条命令，参数按redis-cli的规则拆分(支持引号)。
+//! 命令前加`~ `表示只比较回复的类型，用于错误消息措辞不同或结果顺序不确定的命令。
+//!
+//! Rust特点展示:
+//! - `include_str!`在编译期把内置用例嵌入二进制
+//! - `last_mut`取得最后一个用例的可变引用，向其中追加命令
+
+use redis_lib::client::tokenize;
+
+/// 内置用例: (文件名, 内容)
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("strings.txt", include_str!("corpus/strings.txt")),
+    ("keys.txt", include_str!("corpus/keys.txt")),
+    ("hashes.txt", include_str!("corpus/hashes.txt")),
+    ("lists.txt", include_str!("corpus/lists.txt")),
+    ("sets.txt", include_str!("corpus/sets.txt")),
+    ("zsets.txt", include_str!("corpus/zsets.txt")),
+    ("streams.txt", include_str!("corpus/streams.txt")),
+    ("expire.txt", include_str!("corpus/expire.txt")),
+    ("errors.txt", include_str!("corpus/errors.txt")),
+];
+
+/// 一个用例: 每个用例在清空的数据库上执行
+#[derive(Debug)]
+pub struct Case {
+    /// `文件名: 用例名称`
+    pub name: String,
+    pub commands: Vec<CaseCommand>,
+}
+
+/// 用例中的一条命令
+#[derive(Debug)]
+pub struct CaseCommand {
+    /// 原始文本，用于报告差异
+    pub line: String,
+    pub args: Vec<String>,
+    /// 只比较回复的类型
+    pub kind_only: bool,
+}
+
+/// 解析用例文件，`source`用于用例名称和错误信息
+pub fn parse(source: &str, content: &str) -> Result<Vec<Case>, String> {
+    let mut cases: Vec<Case> = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("===") {
+            cases.push(Case {
+                name: format!("{}: {}", source, name.trim()),
+                commands: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(case) = cases.last_mut() else {
+            return Err(format!("{} 第 {} 行: 命令必须位于`=== 名称`之后", source, index + 1));
+        };
+        let (kind_only, command) = match line.strip_prefix('~') {
+            Some(rest) => (true, rest.trim()),
+            None => (false, line),
+        };
+        case.commands.push(CaseCommand {
+            line: command.to_string(),
+            args: tokenize(command),
+            kind_only,
+        });
+    }
+
+    Ok(cases)
+}
@@ -0,0 +1,155 @@
+//! 兼容性对比工具 - 把同一组命令发给本服务器和真实Redis，逐字节比较回复
+//!
+//! 每个用例开始前对两边执行FLUSHALL，所以参照服务器必须是可以清空的临时实例。
+//! 参照服务器不可用时跳过对比并以0退出，便于在没有Redis的环境中运行；
+//! 指定`--require-reference`时视为失败。用例格式见`corpus`模块。
+//!
+//! Rust特点展示:
+//! - 二进制crate复用库中的`Client`和`TestServer`
+//! - `std::mem::discriminant`比较枚举的变体而忽略内容
+
+mod corpus;
+
+use clap::Parser;
+use corpus::{Case, BUILTIN};
+use redis_lib::client::{build_command, Client};
+use redis_lib::resp::RespValue;
+use redis_lib::testing::TestServer;
+use redis_lib::RedisResult;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// redis-compat的命令行参数
+#[derive(Debug, Parser)]
+#[command(
+    name = "redis-compat",
+    version,
+    about = "对比本服务器与真实Redis对同一组命令的回复"
+)]
+struct Args {
+    /// 参照Redis的地址，会被FLUSHALL清空
+    #[arg(long, default_value = "127.0.0.1:6379")]
+    reference: String,
+
+    /// 被测服务器的地址；不指定时在进程内启动一个
+    #[arg(long)]
+    target: Option<String>,
+
+    /// 参照服务器不可用时视为失败，而不是跳过
+    #[arg(long)]
+    require_reference: bool,
+
+    /// 只运行名称包含该字符串的用例
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// 用例文件；不指定时使用内置用例
+    files: Vec<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let cases = load_cases(&args.files)?;
+
+    let mut reference = match Client::connect(args.reference.as_str()).await {
+        Ok(client) => client,
+        Err(e) if !args.require_reference => {
+            println!("参照服务器 {} 不可用({})，跳过对比", args.reference, e);
+            return Ok(ExitCode::SUCCESS);
+        }
+        Err(e) => return Err(format!("无法连接参照服务器 {}: {}", args.reference, e).into()),
+    };
+
+    // 进程内服务器需要在整个对比期间存活
+    let local;
+    let mut target = match &args.target {
+        Some(addr) => Client::connect(addr.as_str()).await?,
+        None => {
+            local = TestServer::start().await;
+            local.client().await
+        }
+    };
+
+    let selected = cases
+        .iter()
+        .filter(|case| args.filter.as_ref().is_none_or(|filter| case.name.contains(filter.as_str())));
+    let (mut total, mut failed) = (0, 0);
+    for case in selected {
+        total += 1;
+        match run_case(case, &mut target, &mut reference).await? {
+            None => println!("PASS {}", case.name),
+            Some(diff) => {
+                failed += 1;
+                println!("FAIL {}\n{}", case.name, diff);
+            }
+        }
+    }
+
+    println!("\n{} 个用例，{} 个失败", total, failed);
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// 读取用例文件，没有指定文件时使用内置用例
+fn load_cases(files: &[PathBuf]) -> Result<Vec<Case>, String> {
+    if files.is_empty() {
+        return BUILTIN
+            .iter()
+            .map(|(name, content)| corpus::parse(name, content))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|cases| cases.into_iter().flatten().collect());
+    }
+
+    let mut cases = Vec::new();
+    for path in files {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+        cases.extend(corpus::parse(&path.display().to_string(), &content)?);
+    }
+    Ok(cases)
+}
+
+/// 在两边执行一个用例，返回第一处差异
+async fn run_case(case: &Case, target: &mut Client, reference: &mut Client) -> RedisResult<Option<String>> {
+    target.command(["FLUSHALL"]).await?;
+    reference.command(["FLUSHALL"]).await?;
+
+    for command in &case.commands {
+        let request = build_command(&command.args);
+        let ours = target.execute(&request).await?;
+        let theirs = reference.execute(&request).await?;
+
+        let same = if command.kind_only {
+            std::mem::discriminant(&ours) == std::mem::discriminant(&theirs)
+        } else {
+            ours.serialize() == theirs.serialize()
+        };
+        if !same {
+            return Ok(Some(format!(
+                "  > {}\n    本服务器: {}\n    Redis:    {}",
+                command.line,
+                escape(&ours),
+                escape(&theirs)
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// 回复的RESP编码，控制字符转义后显示在一行内
+fn escape(reply: &RespValue) -> String {
+    reply.serialize().escape_ascii().to_string()
+}
@@ -0,0 +1,89 @@
+//! 逐键导出 - 列与rdb-tools的内存报告一致，便于用现有脚本处理
+//!
+//! 每读出一个键写一行，不在内存中保留全部键。JSON格式是一个对象数组。
+//!
+//! Rust特点展示:
+//! - 枚举的每个变体持有自己的写入状态
+//! - `serde_json::json!`构造对象，负责字符串转义
+
+use redis_lib::rdb::RdbEntry;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const CSV_HEADER: &str = "database,type,key,size_in_bytes,encoding,num_elements,expiry";
+
+/// 导出目标
+pub enum Export {
+    Csv(BufWriter<File>),
+    Json {
+        out: BufWriter<File>,
+        /// 是否还没有写入任何对象，用于决定是否需要逗号
+        first: bool,
+    },
+}
+
+impl Export {
+    pub fn csv(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", CSV_HEADER)?;
+        Ok(Export::Csv(out))
+    }
+
+    pub fn json(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"[")?;
+        Ok(Export::Json { out, first: true })
+    }
+
+    /// 写入一个键；`expiry`列是过期时刻的unix毫秒数，没有过期时间时为空
+    pub fn write(&mut self, entry: &RdbEntry, memory: u64) -> io::Result<()> {
+        let key = String::from_utf8_lossy(&entry.key);
+        match self {
+            Export::Csv(out) => writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                entry.db,
+                entry.kind.name(),
+                csv_field(&key),
+                memory,
+                entry.encoding,
+                entry.elements,
+                entry.expires_at.map(|t| t.to_string()).unwrap_or_default()
+            ),
+            Export::Json { out, first } => {
+                let row = serde_json::json!({
+                    "database": entry.db,
+                    "type": entry.kind.name(),
+                    "key": key,
+                    "size_in_bytes": memory,
+                    "encoding": entry.encoding,
+                    "num_elements": entry.elements,
+                    "expiry": entry.expires_at,
+                });
+                let separator = if std::mem::take(first) { "\n" } else { ",\n" };
+                write!(out, "{}{}", separator, row)
+            }
+        }
+    }
+
+    /// 写入结尾并刷新缓冲区
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Export::Csv(mut out) => out.flush(),
+            Export::Json { mut out, .. } => {
+                out.write_all(b"\n]\n")?;
+                out.flush()
+            }
+        }
+    }
+}
+
+/// 含逗号、引号或换行的字段加双引号，内部的引号写两次
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
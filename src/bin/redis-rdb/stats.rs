@@ -0,0 +1,157 @@
+//! 统计汇总 - 按类型、数据库、过期时间分组，并记录估算内存最大的键
+//!
+//! Rust特点展示:
+//! - `BinaryHeap<Reverse<_>>`作为容量固定的小顶堆，只保留最大的N个键
+//! - 派生`Ord`按字段顺序比较，结构体第一个字段决定排序
+
+use redis_lib::rdb::{RdbEntry, RdbType};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// 剩余生存时间的分组: (名称, 上限毫秒)，超过最后一组的归入"更久"
+const TTL_BUCKETS: &[(&str, u64)] = &[
+    ("1分钟内", 60_000),
+    ("1小时内", 3_600_000),
+    ("1天内", 86_400_000),
+    ("7天内", 7 * 86_400_000),
+];
+
+/// 估算键在Redis中占用的内存
+///
+/// 与rdb-tools一样只是近似值: 键名和值的数据，加上每个键的固定开销(字典项、对象头、SDS头)，
+/// 非紧凑编码再加上每个元素的开销。紧凑编码(listpack、intset等)在内存中与文件中的布局基本相同。
+pub fn estimate_memory(entry: &RdbEntry) -> u64 {
+    const KEY_OVERHEAD: u64 = 64;
+    // expires字典中的一项
+    const EXPIRY_OVERHEAD: u64 = 32;
+
+    let per_element = match (entry.kind, entry.encoding) {
+        // 字典项加字段和值两个SDS头
+        (RdbType::Hash, "hashtable") => 56,
+        (_, "hashtable") => 40,
+        // 跳表节点加字典项
+        (_, "skiplist") => 80,
+        (_, "linkedlist") => 48,
+        _ => 0,
+    };
+    let expiry = if entry.expires_at.is_some() { EXPIRY_OVERHEAD } else { 0 };
+    KEY_OVERHEAD + entry.key.len() as u64 + entry.value_len + entry.elements * per_element + expiry
+}
+
+/// 单个类型的统计
+#[derive(Debug, Default)]
+pub struct TypeStats {
+    pub keys: u64,
+    pub elements: u64,
+    pub memory: u64,
+}
+
+/// 一个大键
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BigKey {
+    pub memory: u64,
+    pub key: String,
+    pub db: u64,
+    pub kind: RdbType,
+    pub encoding: &'static str,
+    pub elements: u64,
+}
+
+/// 整个快照的统计
+#[derive(Debug)]
+pub struct Stats {
+    pub keys: u64,
+    pub memory: u64,
+    pub by_type: BTreeMap<RdbType, TypeStats>,
+    pub by_db: BTreeMap<u64, u64>,
+    /// 所有带过期时间的键的过期时刻(unix毫秒)
+    expiries: Vec<u64>,
+    biggest: BinaryHeap<Reverse<BigKey>>,
+    top: usize,
+}
+
+impl Stats {
+    /// 创建统计，`top`是保留的大键个数
+    pub fn new(top: usize) -> Self {
+        Self {
+            keys: 0,
+            memory: 0,
+            by_type: BTreeMap::new(),
+            by_db: BTreeMap::new(),
+            expiries: Vec::new(),
+            biggest: BinaryHeap::with_capacity(top + 1),
+            top,
+        }
+    }
+
+    pub fn add(&mut self, entry: &RdbEntry, memory: u64) {
+        self.keys += 1;
+        self.memory += memory;
+        let by_type = self.by_type.entry(entry.kind).or_default();
+        by_type.keys += 1;
+        by_type.elements += entry.elements;
+        by_type.memory += memory;
+        *self.by_db.entry(entry.db).or_insert(0) += 1;
+        self.expiries.extend(entry.expires_at);
+
+        if self.top == 0 {
+            return;
+        }
+        // 堆满且不比最小的大时不必构造BigKey
+        if self.biggest.len() == self.top
+            && self.biggest.peek().is_some_and(|Reverse(min)| memory <= min.memory)
+        {
+            return;
+        }
+        self.biggest.push(Reverse(BigKey {
+            memory,
+            key: String::from_utf8_lossy(&entry.key).into_owned(),
+            db: entry.db,
+            kind: entry.kind,
+            encoding: entry.encoding,
+            elements: entry.elements,
+        }));
+        if self.biggest.len() > self.top {
+            self.biggest.pop();
+        }
+    }
+
+    /// 按剩余生存时间分组的键数，`now`是计算剩余时间的参照时刻(unix毫秒)
+    pub fn ttl_histogram(&self, now: u64) -> Vec<(&'static str, u64)> {
+        let mut histogram = vec![("无过期时间", self.keys - self.expiries.len() as u64), ("已过期", 0)];
+        histogram.extend(TTL_BUCKETS.iter().map(|(name, _)| (*name, 0)));
+        histogram.push(("更久", 0));
+
+        for &expires_at in &self.expiries {
+            let index = match expires_at.checked_sub(now).filter(|&ttl| ttl > 0) {
+                None => 1,
+                Some(ttl) => 2 + TTL_BUCKETS
+                    .iter()
+                    .position(|&(_, limit)| ttl <= limit)
+                    .unwrap_or(TTL_BUCKETS.len()),
+            };
+            histogram[index].1 += 1;
+        }
+        histogram
+    }
+
+    /// 最大的键，从大到小
+    pub fn into_biggest(self) -> Vec<BigKey> {
+        self.biggest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(big)| big)
+            .collect()
+    }
+}
+
+/// 与INFO中`used_memory_human`相同的格式
+pub fn human(bytes: u64) -> String {
+    const UNITS: &[(&str, u64)] = &[("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+    UNITS
+        .iter()
+        .find(|&&(_, size)| bytes >= size)
+        .map_or_else(|| format!("{}B", bytes), |&(unit, size)| {
+            format!("{:.2}{}", bytes as f64 / size as f64, unit)
+        })
+}
@@ -0,0 +1,204 @@
+//! 重放工具 - 把AOF文件或保存下来的MONITOR输出重新发给目标服务器
+//!
+//! 用于复现线上负载、验证迁移后的数据:
+//! - `--speed 1`按原始节奏重放，`--speed 10`加快10倍，`--speed 0`不等待、尽快发送
+//! - 不需要等待的命令以流水线方式成批发送
+//! - MONITOR输出中数据库变化时先发送SELECT
+//!
+//! Rust特点展示:
+//! - `tokio::time::sleep_until`按计划时刻发送，不会因发送耗时而累积误差
+//! - 二进制crate拆分为多个模块
+
+mod source;
+
+use clap::Parser;
+use redis_lib::client::{build_command, Client};
+use redis_lib::resp::RespValue;
+use redis_lib::RedisResult;
+use source::{Format, Source};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
+
+/// 每批最多发送的命令数
+const BATCH_SIZE: usize = 1000;
+
+/// 最多打印多少条错误回复
+const MAX_PRINTED_ERRORS: u64 = 10;
+
+/// redis-replay的命令行参数
+#[derive(Debug, Parser)]
+#[command(
+    name = "redis-replay",
+    version,
+    about = "重放AOF文件或MONITOR输出"
+)]
+struct Args {
+    /// 目标服务器地址
+    #[arg(long, default_value = "127.0.0.1:6379")]
+    target: String,
+
+    /// 目标服务器的密码
+    #[arg(short = 'a', long = "pass")]
+    password: Option<String>,
+
+    /// 输入格式；不指定时根据文件开头判断
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// 相对原始节奏的倍速，0表示不等待
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// 输入文件；不指定时读取标准输入
+    file: Option<PathBuf>,
+}
+
+/// 重放结果统计
+#[derive(Debug, Default)]
+struct Summary {
+    /// 收到的回复数
+    replies: u64,
+    /// 其中的错误回复数
+    errors: u64,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    if !(args.speed >= 0.0 && args.speed.is_finite()) {
+        return Err("--speed必须是非负数".into());
+    }
+
+    let mut client = Client::connect(args.target.as_str()).await?;
+    if let Some(password) = &args.password {
+        client.auth(password).await?;
+    }
+
+    let summary = match &args.file {
+        Some(path) => {
+            let file = tokio::fs::File::open(path)
+                .await
+                .map_err(|e| format!("无法打开 {}: {}", path.display(), e))?;
+            let source = Source::open(file, args.format).await?;
+            replay(source, &mut client, args.speed).await?
+        }
+        None => {
+            let source = Source::open(tokio::io::stdin(), args.format).await?;
+            replay(source, &mut client, args.speed).await?
+        }
+    };
+
+    Ok(if summary.errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// 按计划时刻把命令发给目标服务器
+async fn replay<R, S>(
+    mut source: Source<R>,
+    client: &mut Client<S>,
+    speed: f64,
+) -> RedisResult<Summary>
+where
+    R: AsyncRead + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let start = Instant::now();
+    let mut summary = Summary::default();
+    let mut first_timestamp = None;
+    let mut last_timestamp = None;
+    let mut db = None;
+    let mut batch = Vec::new();
+    let mut pending = 0;
+
+    while let Some(entry) = source.next().await? {
+        if let Some(timestamp) = entry.timestamp {
+            let first = *first_timestamp.get_or_insert(timestamp);
+            last_timestamp = Some(timestamp);
+            if speed > 0.0 {
+                // 时间戳倒退(如时钟回拨)时不等待
+                let offset = ((timestamp - first) / speed).max(0.0);
+                let due = start + Duration::from_secs_f64(offset);
+                if due > Instant::now() {
+                    flush_batch(client, &mut batch, &mut pending, &mut summary).await?;
+                    tokio::time::sleep_until(due).await;
+                }
+            }
+        }
+
+        if entry.db.is_some() && entry.db != db {
+            db = entry.db;
+            let index = entry.db.unwrap_or_default().to_string();
+            batch.extend_from_slice(&build_command(["SELECT", index.as_str()]).serialize());
+            pending += 1;
+        }
+        batch.extend_from_slice(&entry.request.serialize());
+        pending += 1;
+
+        if pending >= BATCH_SIZE {
+            flush_batch(client, &mut batch, &mut pending, &mut summary).await?;
+        }
+    }
+    flush_batch(client, &mut batch, &mut pending, &mut summary).await?;
+
+    let original = match (first_timestamp, last_timestamp) {
+        (Some(first), Some(last)) => {
+            format!("，原始时长 {:.2?}", Duration::from_secs_f64((last - first).max(0.0)))
+        }
+        _ => String::new(),
+    };
+    eprintln!(
+        "重放完成，耗时 {:.2?}{}，errors: {}, replies: {}",
+        start.elapsed(),
+        original,
+        summary.errors,
+        summary.replies
+    );
+    if source.skipped > 0 {
+        eprintln!("跳过了 {} 行无法识别的输入", source.skipped);
+    }
+    Ok(summary)
+}
+
+/// 发送一批命令并读取全部回复
+async fn flush_batch<S>(
+    client: &mut Client<S>,
+    batch: &mut Vec<u8>,
+    pending: &mut usize,
+    summary: &mut Summary,
+) -> RedisResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if *pending == 0 {
+        return Ok(());
+    }
+
+    client.send_raw(batch).await?;
+    batch.clear();
+
+    for _ in 0..std::mem::take(pending) {
+        if let RespValue::Error(e) = client.read_reply().await? {
+            if summary.errors < MAX_PRINTED_ERRORS {
+                eprintln!("(error) {}", e);
+            }
+            summary.errors += 1;
+        }
+        summary.replies += 1;
+    }
+    Ok(())
+}
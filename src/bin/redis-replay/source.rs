@@ -0,0 +1,159 @@
+//! 输入解析 - 从AOF文件或MONITOR输出中逐条读出命令
+//!
+//! - AOF: RESP编码的命令序列；Redis 7在命令之间写入`#TS:<unix秒>`注释，据此恢复原始节奏
+//! - MONITOR: 每行形如`1339518083.107412 [0 127.0.0.1:60866] "SET" "k" "v"`，
+//!   由`redis_lib::monitor`解析
+//!
+//! Rust特点展示:
+//! - 泛型`Source<R>`既能读文件也能读标准输入
+//! - 增量解析: 缓冲区中的数据不完整时继续读取，而不是把整个文件读进内存
+
+use bytes::{Buf, BytesMut};
+use clap::ValueEnum;
+use redis_lib::client::build_command;
+use redis_lib::error::{RedisError, RedisResult};
+use redis_lib::monitor;
+use redis_lib::resp::{RespParser, RespValue};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+/// 输入格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// AOF文件(RESP命令序列)
+    Aof,
+    /// 保存下来的MONITOR输出
+    Monitor,
+}
+
+impl Format {
+    /// 根据开头的内容判断格式: AOF以`*`或`#`开头，MONITOR以时间戳开头
+    fn detect(head: &[u8]) -> RedisResult<Self> {
+        match head.first() {
+            Some(b'*' | b'#') => Ok(Format::Aof),
+            _ if head.starts_with(b"REDIS") => Err(RedisError::Protocol(
+                "AOF包含RDB前缀(aof-use-rdb-preamble)，无法重放".to_string(),
+            )),
+            _ => Ok(Format::Monitor),
+        }
+    }
+}
+
+/// 一条要重放的命令
+#[derive(Debug)]
+pub struct Entry {
+    /// 原始执行时间(unix秒)；没有时间信息时为None
+    pub timestamp: Option<f64>,
+    /// 执行时所在的数据库；AOF中的SELECT本身就是命令，为None
+    pub db: Option<u32>,
+    pub request: RespValue,
+}
+
+/// 命令来源
+pub struct Source<R> {
+    reader: BufReader<R>,
+    format: Format,
+    /// AOF的解析缓冲区
+    buffer: BytesMut,
+    /// 最近一个`#TS:`注释的时间
+    timestamp: Option<f64>,
+    /// 跳过的无法识别的MONITOR行数
+    pub skipped: u64,
+}
+
+impl<R: AsyncRead + Unpin> Source<R> {
+    /// 创建命令来源；`format`为None时根据开头的内容自动判断
+    pub async fn open(reader: R, format: Option<Format>) -> RedisResult<Self> {
+        let mut reader = BufReader::new(reader);
+        let format = match format {
+            Some(format) => format,
+            None => Format::detect(reader.fill_buf().await?)?,
+        };
+        Ok(Self {
+            reader,
+            format,
+            buffer: BytesMut::with_capacity(64 * 1024),
+            timestamp: None,
+            skipped: 0,
+        })
+    }
+
+    /// 读取下一条命令，输入结束时返回None
+    pub async fn next(&mut self) -> RedisResult<Option<Entry>> {
+        match self.format {
+            Format::Aof => self.next_aof().await,
+            Format::Monitor => self.next_monitor().await,
+        }
+    }
+
+    async fn next_aof(&mut self) -> RedisResult<Option<Entry>> {
+        loop {
+            // 跳过命令之间多余的换行
+            while self.buffer.first().is_some_and(|b| b.is_ascii_whitespace()) {
+                self.buffer.advance(1);
+            }
+
+            if self.buffer.first() == Some(&b'#') {
+                if let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                    let line = self.buffer.split_to(end + 2);
+                    let ts = std::str::from_utf8(&line[..end])
+                        .ok()
+                        .and_then(|line| line.strip_prefix("#TS:"))
+                        .and_then(|ts| ts.parse().ok());
+                    if ts.is_some() {
+                        self.timestamp = ts;
+                    }
+                    continue;
+                }
+            } else if let Some(request) = RespParser::parse(&mut self.buffer)? {
+                return Ok(Some(Entry {
+                    timestamp: self.timestamp,
+                    db: None,
+                    request,
+                }));
+            }
+
+            if self.reader.read_buf(&mut self.buffer).await? == 0 {
+                if !self.buffer.is_empty() {
+                    // 与redis-check-aof一样丢弃写入时被截断的尾部
+                    eprintln!("警告: AOF末尾有 {} 字节不完整的命令，已忽略", self.buffer.len());
+                }
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn next_monitor(&mut self) -> RedisResult<Option<Entry>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            // 开头的OK是MONITOR命令本身的回复
+            if line.is_empty() || line == "OK" || line == "+OK" {
+                continue;
+            }
+            match parse_monitor_line(line) {
+                Some(Some(entry)) => return Ok(Some(entry)),
+                Some(None) => continue,
+                None => self.skipped += 1,
+            }
+        }
+    }
+}
+
+/// 解析一行MONITOR输出
+///
+/// 无法识别时返回None；Lua脚本执行的命令返回Some(None)，重放EVAL时它们会被再次执行
+fn parse_monitor_line(line: &str) -> Option<Option<Entry>> {
+    let line = monitor::parse_line(line)?;
+    if line.is_lua() {
+        return Some(None);
+    }
+    Some(Some(Entry {
+        timestamp: Some(line.timestamp),
+        db: line.db,
+        request: build_command(line.args),
+    }))
+}
@@ -0,0 +1,319 @@
+//! 位操作模块 - BITOP和BITFIELD使用的按位计算
+//!
+//! 字符串被看作一串位: 第0位是第一个字节的最高位，与SETBIT/GETBIT的编号一致。
+//! 读取超出字符串末尾的位得到0；写入时字符串自动用0字节补长。
+//!
+//! BITFIELD把任意位置、任意宽度(有符号1~64位，无符号1~63位)的一段位当作整数读写，
+//! 写入超出范围时按OVERFLOW指定的方式处理: WRAP回绕、SAT饱和到最大/最小值、FAIL放弃写入。
+//!
+//! Rust特点展示:
+//! - 用i128计算增量结果，溢出检查不需要担心中间值本身溢出
+//! - 枚举携带操作数，一个match处理全部子操作
+
+use crate::error::{RedisError, RedisResult};
+
+/// 位偏移的上限(不含)，与Redis一样以512MB的字符串为限
+const MAX_BIT_OFFSET: u64 = 512 * 1024 * 1024 * 8;
+
+/// BITOP的运算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOp {
+    /// 按名称解析(大小写不敏感)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "AND" => Some(BitOp::And),
+            "OR" => Some(BitOp::Or),
+            "XOR" => Some(BitOp::Xor),
+            "NOT" => Some(BitOp::Not),
+            _ => None,
+        }
+    }
+
+    /// 对所有源字符串逐字节运算
+    ///
+    /// 结果的长度是最长的源字符串的长度，较短的源字符串按0字节补齐。
+    /// NOT只使用第一个源字符串
+    pub fn apply(self, sources: &[&[u8]]) -> Vec<u8> {
+        let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+        let byte = |source: &[u8], i: usize| source.get(i).copied().unwrap_or(0);
+        (0..len)
+            .map(|i| {
+                let mut bytes = sources.iter().map(|source| byte(source, i));
+                let first = bytes.next().unwrap_or(0);
+                match self {
+                    BitOp::And => bytes.fold(first, |acc, b| acc & b),
+                    BitOp::Or => bytes.fold(first, |acc, b| acc | b),
+                    BitOp::Xor => bytes.fold(first, |acc, b| acc ^ b),
+                    BitOp::Not => !first,
+                }
+            })
+            .collect()
+    }
+}
+
+/// BITFIELD的整数类型，如`i8`、`u16`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl FieldType {
+    /// 解析`i<位数>`或`u<位数>`
+    pub fn parse(text: &str) -> RedisResult<Self> {
+        let invalid = || {
+            RedisError::InvalidArgument(
+                "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                    .to_string(),
+            )
+        };
+        let (signed, bits) = match text.as_bytes().first() {
+            Some(b'i' | b'I') => (true, &text[1..]),
+            Some(b'u' | b'U') => (false, &text[1..]),
+            _ => return Err(invalid()),
+        };
+        let bits: u32 = bits.parse().map_err(|_| invalid())?;
+        let max_bits = if signed { 64 } else { 63 };
+        if bits == 0 || bits > max_bits {
+            return Err(invalid());
+        }
+        Ok(FieldType { signed, bits })
+    }
+
+    /// 类型能表示的最小值和最大值
+    fn limits(self) -> (i128, i128) {
+        if self.signed {
+            let max = (1i128 << (self.bits - 1)) - 1;
+            (-max - 1, max)
+        } else {
+            (0, (1i128 << self.bits) - 1)
+        }
+    }
+
+    /// 把超出范围的值回绕到类型的位宽内
+    fn wrap(self, value: i128) -> i64 {
+        let low = (value as u64) & (u64::MAX >> (64 - self.bits));
+        if self.signed && self.bits < 64 && low >> (self.bits - 1) == 1 {
+            // 符号位为1，符号扩展
+            (low | (u64::MAX << self.bits)) as i64
+        } else {
+            low as i64
+        }
+    }
+}
+
+/// 解析位偏移: 整数表示位编号，`#n`表示第n个该类型的字段(即`n * 位数`)
+pub fn parse_offset(text: &str, field: FieldType) -> RedisResult<u64> {
+    let invalid = || {
+        RedisError::InvalidArgument("bit offset is not an integer or out of range".to_string())
+    };
+    let offset = match text.strip_prefix('#') {
+        Some(index) => index
+            .parse::<u64>()
+            .ok()
+            .and_then(|index| index.checked_mul(u64::from(field.bits))),
+        None => text.parse::<u64>().ok(),
+    };
+    offset.filter(|&offset| offset < MAX_BIT_OFFSET).ok_or_else(invalid)
+}
+
+/// OVERFLOW指定的溢出处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Wrap,
+    Sat,
+    Fail,
+}
+
+impl Overflow {
+    /// 按名称解析(大小写不敏感)
+    pub fn parse(name: &str) -> RedisResult<Self> {
+        match name.to_uppercase().as_str() {
+            "WRAP" => Ok(Overflow::Wrap),
+            "SAT" => Ok(Overflow::Sat),
+            "FAIL" => Ok(Overflow::Fail),
+            _ => Err(RedisError::InvalidArgument("Invalid OVERFLOW type specified".to_string())),
+        }
+    }
+}
+
+/// BITFIELD的子操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldOp {
+    Get,
+    Set(i64),
+    IncrBy(i64),
+}
+
+/// 一个BITFIELD子操作及其参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub op: FieldOp,
+    pub field: FieldType,
+    pub offset: u64,
+    /// 子操作之前最近一次OVERFLOW指定的方式，只影响SET和INCRBY
+    pub overflow: Overflow,
+}
+
+impl BitField {
+    /// 是否写入
+    pub fn writes(&self) -> bool {
+        !matches!(self.op, FieldOp::Get)
+    }
+
+    /// 执行后字符串至少需要的字节数
+    pub fn required_len(&self) -> usize {
+        (self.offset + u64::from(self.field.bits)).div_ceil(8) as usize
+    }
+
+    /// 读取字段的当前值
+    fn get(&self, data: &[u8]) -> i64 {
+        let raw = (0..u64::from(self.field.bits))
+            .fold(0u64, |acc, i| (acc << 1) | get_bit(data, self.offset + i));
+        self.field.wrap(i128::from(raw))
+    }
+
+    /// 写入字段，调用前字符串必须已经足够长
+    fn set(&self, data: &mut [u8], value: i64) {
+        let bits = u64::from(self.field.bits);
+        for i in 0..bits {
+            let bit = (value as u64 >> (bits - 1 - i)) & 1;
+            set_bit(data, self.offset + i, bit);
+        }
+    }
+}
+
+/// 按顺序执行BITFIELD的子操作，返回每个子操作的结果
+///
+/// GET返回当前值，SET返回旧值，INCRBY返回新值；OVERFLOW FAIL时溢出的写入不执行，结果为None。
+/// 有写入时字符串先补长到所有写入都能容纳的长度(与Redis一致，即使写入最终因FAIL放弃)
+pub fn bitfield(data: &mut Vec<u8>, ops: &[BitField]) -> Vec<Option<i64>> {
+    let required = ops.iter().filter(|op| op.writes()).map(BitField::required_len).max();
+    if let Some(required) = required {
+        if data.len() < required {
+            data.resize(required, 0);
+        }
+    }
+
+    ops.iter()
+        .map(|op| {
+            let old = op.get(data);
+            let target = match op.op {
+                FieldOp::Get => return Some(old),
+                // 无符号类型的SET把值当作u64，负数即超出范围
+                FieldOp::Set(value) if !op.field.signed => i128::from(value as u64),
+                FieldOp::Set(value) => i128::from(value),
+                FieldOp::IncrBy(increment) => i128::from(old) + i128::from(increment),
+            };
+            let (min, max) = op.field.limits();
+            let value = match op.overflow {
+                _ if (min..=max).contains(&target) => target as i64,
+                Overflow::Wrap => op.field.wrap(target),
+                Overflow::Sat => target.clamp(min, max) as i64,
+                Overflow::Fail => return None,
+            };
+            op.set(data, value);
+            Some(if matches!(op.op, FieldOp::Set(_)) { old } else { value })
+        })
+        .collect()
+}
+
+/// 读取一位，超出末尾时为0
+fn get_bit(data: &[u8], offset: u64) -> u64 {
+    let byte = data.get((offset / 8) as usize).copied().unwrap_or(0);
+    u64::from(byte >> (7 - offset % 8) & 1)
+}
+
+/// 写入一位
+fn set_bit(data: &mut [u8], offset: u64, bit: u64) {
+    let mask = 1u8 << (7 - offset % 8);
+    let byte = &mut data[(offset / 8) as usize];
+    if bit == 1 {
+        *byte |= mask;
+    } else {
+        *byte &= !mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(op: FieldOp, ty: &str, offset: u64, overflow: Overflow) -> BitField {
+        BitField {
+            op,
+            field: FieldType::parse(ty).unwrap(),
+            offset,
+            overflow,
+        }
+    }
+
+    #[test]
+    fn test_bitop() {
+        let sources: [&[u8]; 2] = [b"\xff\x0f", b"\x0f"];
+        assert_eq!(BitOp::And.apply(&sources), b"\x0f\x00");
+        assert_eq!(BitOp::Or.apply(&sources), b"\xff\x0f");
+        assert_eq!(BitOp::Xor.apply(&sources), b"\xf0\x0f");
+        assert_eq!(BitOp::Not.apply(&sources[..1]), b"\x00\xf0");
+        assert!(BitOp::Or.apply(&[b"", b""]).is_empty());
+        assert_eq!(BitOp::parse("xor"), Some(BitOp::Xor));
+        assert_eq!(BitOp::parse("nand"), None);
+    }
+
+    #[test]
+    fn test_parse_type_and_offset() {
+        assert_eq!(FieldType::parse("i64").unwrap(), FieldType { signed: true, bits: 64 });
+        assert_eq!(FieldType::parse("u63").unwrap(), FieldType { signed: false, bits: 63 });
+        assert!(FieldType::parse("u64").is_err());
+        assert!(FieldType::parse("i0").is_err());
+        assert!(FieldType::parse("x8").is_err());
+
+        let u8_type = FieldType::parse("u8").unwrap();
+        assert_eq!(parse_offset("13", u8_type).unwrap(), 13);
+        assert_eq!(parse_offset("#2", u8_type).unwrap(), 16);
+        assert!(parse_offset("-1", u8_type).is_err());
+        assert!(parse_offset("4294967296", u8_type).is_err());
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let mut data = Vec::new();
+        // 只读时不补长
+        let get = field(FieldOp::Get, "u8", 100, Overflow::Wrap);
+        assert_eq!(bitfield(&mut data, &[get]), [Some(0)]);
+        assert!(data.is_empty());
+
+        let ops = [
+            field(FieldOp::Set(255), "u8", 0, Overflow::Wrap),
+            field(FieldOp::Get, "i8", 0, Overflow::Wrap),
+            field(FieldOp::Get, "u4", 4, Overflow::Wrap),
+            field(FieldOp::IncrBy(1), "u8", 0, Overflow::Wrap),
+        ];
+        assert_eq!(bitfield(&mut data, &ops), [Some(0), Some(-1), Some(15), Some(0)]);
+        assert_eq!(data, b"\x00");
+
+        let ops = [
+            field(FieldOp::IncrBy(200), "i8", 8, Overflow::Sat),
+            field(FieldOp::IncrBy(-300), "i8", 8, Overflow::Sat),
+            field(FieldOp::IncrBy(-1), "i8", 8, Overflow::Fail),
+            field(FieldOp::Set(-1), "u4", 16, Overflow::Sat),
+            field(FieldOp::IncrBy(i64::MAX), "i64", 24, Overflow::Wrap),
+            field(FieldOp::IncrBy(1), "i64", 24, Overflow::Wrap),
+        ];
+        assert_eq!(
+            bitfield(&mut data, &ops),
+            [Some(127), Some(-128), None, Some(0), Some(i64::MAX), Some(i64::MIN)]
+        );
+        assert_eq!(data.len(), 11);
+        assert_eq!(data[1], 0x80);
+        assert_eq!(data[2], 0xf0);
+    }
+}
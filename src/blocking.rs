@@ -0,0 +1,161 @@
+//! 阻塞命令的等待队列 - BLPOP/BRPOP/BLMOVE/BLMPOP/BZPOPMIN等
+//!
+//! 阻塞命令第一次执行没有取到数据时，连接按键排进等待队列。其他连接写入某个键后，
+//! Store发出的信号只唤醒该键队首的连接；被唤醒的连接重新执行命令，取到数据后离开队列，
+//! 离开时再唤醒各个键的新队首。这样先阻塞的连接先得到数据，一次写入多个元素时依次传递下去。
+//!
+//! 被唤醒的连接没有取到数据(被不阻塞的命令抢先取走)时继续留在原来的位置等待。
+//!
+//! Rust特点展示:
+//! - `Notify`在没有等待者时保存一个许可，先发信号、后等待也不会错过
+//! - RAII: `BlockedClient`被drop时自动离开所有队列，超时、断开连接都不会留下残余
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::sync::Notify;
+
+/// 队列中的一个等待者
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    notify: Arc<Notify>,
+}
+
+/// 所有键的等待队列，由Store持有
+#[derive(Debug, Default)]
+pub struct BlockedClients {
+    queues: Mutex<HashMap<String, VecDeque<Waiter>>>,
+    next_id: AtomicU64,
+    /// 阻塞中的连接数，没有连接阻塞时发信号不需要加锁
+    blocked: AtomicUsize,
+}
+
+impl BlockedClients {
+    /// 在`keys`的等待队列末尾排队
+    pub fn block(self: &Arc<Self>, keys: &[String]) -> BlockedClient {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let notify = Arc::new(Notify::new());
+        let mut queues = self.lock();
+        for key in keys {
+            let queue = queues.entry(key.clone()).or_default();
+            // 同一个键出现多次时只排一次
+            if queue.back().is_none_or(|waiter| waiter.id != id) {
+                queue.push_back(Waiter {
+                    id,
+                    notify: notify.clone(),
+                });
+            }
+        }
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        BlockedClient {
+            id,
+            keys: keys.to_vec(),
+            notify,
+            registry: self.clone(),
+        }
+    }
+
+    /// `key`可能有了新数据，唤醒它的队首
+    pub fn signal(&self, key: &str) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(waiter) = self.lock().get(key).and_then(VecDeque::front) {
+            waiter.notify.notify_one();
+        }
+    }
+
+    /// 阻塞中的连接数
+    pub fn len(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, VecDeque<Waiter>>> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// 一个阻塞中的连接在等待队列中的位置，被drop时离开队列
+#[derive(Debug)]
+pub struct BlockedClient {
+    id: u64,
+    keys: Vec<String>,
+    notify: Arc<Notify>,
+    registry: Arc<BlockedClients>,
+}
+
+impl BlockedClient {
+    /// 等待某个键的信号
+    ///
+    /// 被唤醒只说明数据可能已经到达，调用者应重新执行命令，没有取到数据时再次等待
+    pub async fn ready(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl Drop for BlockedClient {
+    fn drop(&mut self) {
+        let mut queues = self.registry.lock();
+        for key in &self.keys {
+            let Some(queue) = queues.get_mut(key) else {
+                continue;
+            };
+            queue.retain(|waiter| waiter.id != self.id);
+            // 键中可能还有数据(一次写入了多个元素，或者本连接被唤醒时正好超时)，交给下一个连接检查
+            match queue.front() {
+                Some(next) => next.notify.notify_one(),
+                None => {
+                    queues.remove(key);
+                }
+            }
+        }
+        self.registry.blocked.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    async fn is_ready(client: &BlockedClient) -> bool {
+        timeout(Duration::from_millis(10), client.ready()).await.is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_fifo_wakeup() {
+        let registry = Arc::new(BlockedClients::default());
+        let first = registry.block(&["a".to_string(), "b".to_string()]);
+        let second = registry.block(&["a".to_string()]);
+        assert_eq!(registry.len(), 2);
+
+        // 只唤醒队首，没有等待者的键不受影响
+        registry.signal("a");
+        registry.signal("missing");
+        assert!(is_ready(&first).await);
+        assert!(!is_ready(&second).await);
+
+        // 队首离开时唤醒下一个
+        drop(first);
+        assert!(is_ready(&second).await);
+        drop(second);
+        assert!(registry.is_empty());
+        assert!(registry.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_signal_before_wait() {
+        let registry = Arc::new(BlockedClients::default());
+        let client = registry.block(&["k".to_string(), "k".to_string()]);
+        assert_eq!(registry.lock()["k"].len(), 1);
+        registry.signal("k");
+        assert!(is_ready(&client).await);
+        assert!(!is_ready(&client).await);
+    }
+}
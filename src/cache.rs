@@ -0,0 +1,56 @@
+//! 缓存加载模块 - 读穿透/写穿透
+//!
+//! 为Store注入一个`CacheLoader`后，它就成为后端数据库前面的缓存层:
+//! 未命中时由加载器从后端读取并写入缓存(读穿透)，写入时先同步到后端再更新缓存(写穿透)。
+//! 应用程序不需要自己编写"先查缓存、再查数据库、再回填"的逻辑。
+//!
+//! Rust特点展示:
+//! - trait对象 (`Arc<dyn CacheLoader>`) 与时钟一样通过依赖注入
+//! - 返回`Pin<Box<dyn Future>>`，让包含异步方法的trait可以作为trait对象使用
+
+use crate::error::RedisResult;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// 加载器方法返回的future
+pub type LoadFuture<'a, T> = Pin<Box<dyn Future<Output = RedisResult<T>> + Send + 'a>>;
+
+/// 从后端加载到的值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loaded {
+    /// 值
+    pub value: Vec<u8>,
+    /// 在缓存中保留的时长；None表示不过期
+    pub ttl: Option<Duration>,
+}
+
+impl Loaded {
+    /// 不过期的值
+    pub fn new(value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            value: value.into(),
+            ttl: None,
+        }
+    }
+
+    /// 设置在缓存中保留的时长
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// 缓存加载器 - 连接Store与后端数据库
+///
+/// Rust特点: 默认方法实现让只需要读穿透的加载器只实现`load`
+pub trait CacheLoader: Debug + Send + Sync {
+    /// 缓存未命中时从后端加载；后端也没有该键时返回None
+    fn load<'a>(&'a self, key: &'a str) -> LoadFuture<'a, Option<Loaded>>;
+
+    /// 把写入同步到后端，`value`为None表示删除；默认不同步
+    fn write<'a>(&'a self, _key: &'a str, _value: Option<&'a [u8]>) -> LoadFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+}
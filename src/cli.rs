@@ -0,0 +1,187 @@
+//! 命令行参数模块 - 基于clap解析redis-server的参数
+//!
+//! 用法与redis-server一致: `redis-server [配置文件] [--选项 值]...`。
+//! 优先级从低到高: 默认值、配置文件、命名选项、`--config-override`。
+//!
+//! Rust特点展示:
+//! - derive宏从结构体定义生成参数解析器和帮助信息
+//! - Option<T> 区分"未指定"和"指定了值"
+
+use crate::config::Config;
+use crate::error::RedisResult;
+use clap::Parser;
+use std::path::PathBuf;
+
+/// redis-server的命令行参数
+#[derive(Debug, Parser)]
+#[command(name = "redis-server", version, about = "一个用Rust实现的简单Redis服务器")]
+pub struct ServerArgs {
+    /// redis.conf格式的配置文件
+    pub config_file: Option<PathBuf>,
+
+    /// 监听端口
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// 监听地址
+    #[arg(long)]
+    pub bind: Option<String>,
+
+    /// 同时监听的Unix socket路径
+    #[arg(long)]
+    pub unixsocket: Option<String>,
+
+    /// 内存上限，如100mb、2gb
+    #[arg(long)]
+    pub maxmemory: Option<String>,
+
+    /// 连接需要通过AUTH提供的密码
+    #[arg(long)]
+    pub requirepass: Option<String>,
+
+    /// 日志级别: debug、verbose、notice、warning
+    #[arg(long)]
+    pub loglevel: Option<String>,
+
+    /// 日志文件，空字符串表示标准输出
+    #[arg(long)]
+    pub logfile: Option<String>,
+
+    /// 日志格式: human、json
+    #[arg(long)]
+    pub log_format: Option<String>,
+
+    /// OTLP导出地址(需要otel特性)
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    /// 健康检查HTTP端口
+    #[arg(long)]
+    pub health_port: Option<u16>,
+
+    /// 是否在后台运行: yes、no
+    #[arg(long)]
+    pub daemonize: Option<String>,
+
+    /// pid文件路径
+    #[arg(long)]
+    pub pidfile: Option<String>,
+
+    /// 缓存模式: allkeys-lru淘汰，maxmemory默认取可用内存的3/4
+    #[arg(long)]
+    pub cache_mode: bool,
+
+    /// 覆盖任意配置项，可重复使用
+    #[arg(long = "config-override", num_args = 2, value_names = ["NAME", "VALUE"])]
+    pub config_overrides: Vec<String>,
+
+    /// 只检查本机端口上的服务器是否存活，然后退出
+    #[arg(long)]
+    pub healthcheck: bool,
+}
+
+impl ServerArgs {
+    /// 合并配置文件和命令行选项，得到最终配置
+    pub fn to_config(&self) -> RedisResult<Config> {
+        let mut config = match &self.config_file {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+
+        // Rust特点: 数组中的元组统一了不同类型的选项
+        // 缓存模式最先应用，其他选项可以覆盖预设的值
+        let options = [
+            ("cache-mode", self.cache_mode.then(|| "yes".to_string())),
+            ("port", self.port.map(|p| p.to_string())),
+            ("bind", self.bind.clone()),
+            ("unixsocket", self.unixsocket.clone()),
+            ("maxmemory", self.maxmemory.clone()),
+            ("requirepass", self.requirepass.clone()),
+            ("loglevel", self.loglevel.clone()),
+            ("logfile", self.logfile.clone()),
+            ("log-format", self.log_format.clone()),
+            ("otlp-endpoint", self.otlp_endpoint.clone()),
+            ("health-port", self.health_port.map(|p| p.to_string())),
+            ("daemonize", self.daemonize.clone()),
+            ("pidfile", self.pidfile.clone()),
+        ];
+        for (name, value) in options {
+            if let Some(value) = value {
+                config.apply(name, &value)?;
+            }
+        }
+
+        // clap保证每次--config-override都带两个值
+        for pair in self.config_overrides.chunks(2) {
+            config.apply(&pair[0], &pair[1])?;
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogLevel;
+
+    fn parse(args: &[&str]) -> ServerArgs {
+        ServerArgs::try_parse_from(std::iter::once("redis-server").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_flags() {
+        let config = parse(&[
+            "--port",
+            "6380",
+            "--bind",
+            "127.0.0.1",
+            "--maxmemory",
+            "1gb",
+            "--requirepass",
+            "secret",
+            "--loglevel",
+            "verbose",
+        ])
+        .to_config()
+        .unwrap();
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.bind, "127.0.0.1");
+        assert_eq!(config.maxmemory, 1024 * 1024 * 1024);
+        assert_eq!(config.requirepass, Some("secret".to_string()));
+        assert_eq!(config.loglevel, LogLevel::Verbose);
+    }
+
+    #[test]
+    fn test_config_file_and_overrides() {
+        let path = std::env::temp_dir().join(format!("rust-redis-{}.conf", std::process::id()));
+        std::fs::write(&path, "port 7000\nloglevel warning\nrequirepass fromfile\n").unwrap();
+
+        let args = parse(&[
+            path.to_str().unwrap(),
+            "--port",
+            "7001",
+            "--config-override",
+            "port",
+            "7002",
+            "--config-override",
+            "requirepass",
+            "",
+        ]);
+        let config = args.to_config().unwrap();
+        assert_eq!(config.port, 7002);
+        assert_eq!(config.loglevel, LogLevel::Warning);
+        assert_eq!(config.requirepass, None);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(parse(&["--loglevel", "loud"]).to_config().is_err());
+        assert!(parse(&["--config-override", "bogus", "1"]).to_config().is_err());
+        assert!(ServerArgs::try_parse_from(["redis-server", "--port", "abc"]).is_err());
+        assert!(ServerArgs::try_parse_from(["redis-server", "--config-override", "port"]).is_err());
+    }
+}
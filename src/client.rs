@@ -1,123 +1,476 @@
-//! Redis客户端 - 展示Rust的异步IO和用户交互
+//! 客户端库模块 - 展示Rust的异步IO和泛型API
+//!
+//! 提供可被其他Rust程序复用的异步客户端，交互式的`redis-client`
+//! 二进制程序也构建在它之上。
 //!
 //! Rust特点展示:
-//! - 异步网络IO
-//! - 字符串处理
-//! - 错误处理
+//! - async fn 封装网络往返
+//! - 泛型参数 (`AsRef<[u8]>`) 同时接受 &str、String、Vec<u8>
+//! - Result类型统一处理网络错误和服务器错误回复
 
+use crate::codec::Codec;
+use crate::error::{RedisError, RedisResult};
+use crate::resp::{RespParser, RespValue};
+use crate::tls::{self, TlsOptions};
 use bytes::BytesMut;
-use redis_lib::resp::{RespParser, RespValue};
-use redis_lib::DEFAULT_PORT;
-use std::env;
-use std::io::{self, Write};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 解析命令行参数
-    let (host, port) = parse_args();
-    let addr = format!("{}:{}", host, port);
-
-    println!("连接到 {}...", addr);
-
-    // 连接服务器
-    let mut stream = TcpStream::connect(&addr).await?;
-    println!("已连接！输入 QUIT 退出。\n");
-
-    // 创建读取缓冲区
-    let mut buffer = BytesMut::with_capacity(4096);
-
-    // REPL循环
-    loop {
-        // 显示提示符
-        print!("{}:{}> ", host, port);
-        io::stdout().flush()?;
-
-        // 读取用户输入
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        let input = input.trim();
-        if input.is_empty() {
-            continue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+#[cfg(unix)]
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio_rustls::client::TlsStream;
+
+/// 异步Redis客户端
+///
+/// Rust特点:
+/// - 泛型参数S抽象底层流，TCP、TLS和Unix套接字连接共用同一套协议代码
+/// - 默认类型参数让`Client`直接表示TCP客户端
+/// - &mut self保证同一时刻只有一个请求在进行
+pub struct Client<S = TcpStream> {
+    /// 底层字节流
+    stream: S,
+    /// 读取缓冲区
+    buffer: BytesMut,
+}
+
+impl Client<TcpStream> {
+    /// 连接到服务器
+    ///
+    /// Rust特点: ToSocketAddrs trait约束，既可以传"host:port"也可以传SocketAddr
+    pub async fn connect(addr: impl ToSocketAddrs) -> RedisResult<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self::from_stream(stream))
+    }
+}
+
+impl Client<TlsStream<TcpStream>> {
+    /// 通过TLS连接到服务器
+    ///
+    /// `addr`形如"host:port"
+    pub async fn connect_tls(addr: &str, options: &TlsOptions) -> RedisResult<Self> {
+        let stream = tls::connect(addr, options).await?;
+        Ok(Self::from_stream(stream))
+    }
+}
+
+#[cfg(unix)]
+impl Client<UnixStream> {
+    /// 通过Unix域套接字连接到服务器
+    pub async fn connect_unix(path: impl AsRef<Path>) -> RedisResult<Self> {
+        let stream = UnixStream::connect(path).await?;
+        Ok(Self::from_stream(stream))
+    }
+}
+
+impl<S> Client<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// 基于已建立的流创建客户端
+    pub fn from_stream(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: BytesMut::with_capacity(4096),
         }
+    }
 
-        // 解析用户输入为RESP命令
-        let command = parse_input(input);
-        let data = command.serialize();
+    /// 发送一个RESP值(不等待回复)
+    ///
+    /// 与`read_reply`配合可以实现流水线(pipelining)
+    pub async fn send(&mut self, value: &RespValue) -> RedisResult<()> {
+        self.stream.write_all(&value.serialize()).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
 
-        // 发送命令
-        stream.write_all(&data).await?;
+    /// 发送已经编码好的字节(可以包含多条命令)
+    ///
+    /// 批量导入时一次写入整批命令，再逐个读取回复
+    pub async fn send_raw(&mut self, bytes: &[u8]) -> RedisResult<()> {
+        self.stream.write_all(bytes).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
 
-        // 读取响应
+    /// 读取一个回复
+    pub async fn read_reply(&mut self) -> RedisResult<RespValue> {
         loop {
-            let n = stream.read_buf(&mut buffer).await?;
-            if n == 0 {
-                println!("服务器断开连接");
-                return Ok(());
+            if let Some(value) = RespParser::parse(&mut self.buffer)? {
+                return Ok(value);
             }
 
-            // 尝试解析响应
-            match RespParser::parse(&mut buffer) {
-                Ok(Some(response)) => {
-                    print_response(&response);
-                    break;
-                }
-                Ok(None) => {
-                    // 数据不完整，继续读取
-                    continue;
-                }
-                Err(e) => {
-                    eprintln!("解析错误: {}", e);
-                    break;
-                }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(RedisError::ConnectionClosed);
             }
         }
+    }
 
-        // 检查是否是QUIT命令
-        if input.to_uppercase() == "QUIT" {
-            println!("再见！");
-            break;
+    /// 读取一行原始数据(不含行尾)
+    ///
+    /// 用于SYNC等回复不是完整RESP值的命令，如快照前的长度行
+    pub async fn read_line(&mut self) -> RedisResult<Vec<u8>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line = self.buffer.split_to(end + 1);
+                let line = line.strip_suffix(b"\r\n").unwrap_or(&line[..end]);
+                return Ok(line.to_vec());
+            }
+
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(RedisError::ConnectionClosed);
+            }
         }
     }
 
-    Ok(())
-}
+    /// 读取最多`max`字节的原始数据，先返回缓冲区中已有的部分；连接关闭时返回空
+    pub async fn read_raw(&mut self, max: usize) -> RedisResult<Vec<u8>> {
+        if self.buffer.is_empty() && self.stream.read_buf(&mut self.buffer).await? == 0 {
+            return Ok(Vec::new());
+        }
+        let len = max.min(self.buffer.len());
+        Ok(self.buffer.split_to(len).to_vec())
+    }
+
+    /// 发送请求并返回原始回复
+    ///
+    /// 服务器的错误回复会原样返回为`RespValue::Error`，适合需要自行展示错误的场景
+    pub async fn execute(&mut self, request: &RespValue) -> RedisResult<RespValue> {
+        self.send(request).await?;
+        self.read_reply().await
+    }
+
+    /// 执行任意命令
+    ///
+    /// 服务器的错误回复会转换为`RedisError::ServerError`
+    ///
+    /// Rust特点: IntoIterator + AsRef<[u8]> 让调用者可以传入各种参数集合
+    pub async fn command<I, A>(&mut self, args: I) -> RedisResult<RespValue>
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<[u8]>,
+    {
+        let request = build_command(args);
+        match self.execute(&request).await? {
+            RespValue::Error(e) => Err(RedisError::ServerError(e)),
+            reply => Ok(reply),
+        }
+    }
+
+    /// PING
+    pub async fn ping(&mut self) -> RedisResult<String> {
+        let reply = self.command(["PING"]).await?;
+        reply
+            .as_string()
+            .ok_or_else(|| unexpected_reply("PING", &reply))
+    }
+
+    /// AUTH password
+    pub async fn auth(&mut self, password: &str) -> RedisResult<()> {
+        self.command(["AUTH", password]).await?;
+        Ok(())
+    }
+
+    /// AUTH username password
+    pub async fn auth_with_username(&mut self, username: &str, password: &str) -> RedisResult<()> {
+        self.command(["AUTH", username, password]).await?;
+        Ok(())
+    }
+
+    /// SELECT db
+    pub async fn select(&mut self, db: u32) -> RedisResult<()> {
+        self.command(["SELECT", &db.to_string()]).await?;
+        Ok(())
+    }
+
+    /// GET key
+    pub async fn get(&mut self, key: &str) -> RedisResult<Option<Vec<u8>>> {
+        match self.command(["GET", key]).await? {
+            RespValue::BulkString(data) => Ok(Some(data)),
+            RespValue::Null => Ok(None),
+            reply => Err(unexpected_reply("GET", &reply)),
+        }
+    }
+
+    /// SET key value
+    pub async fn set(&mut self, key: &str, value: impl AsRef<[u8]>) -> RedisResult<()> {
+        let args: [&[u8]; 3] = [b"SET", key.as_bytes(), value.as_ref()];
+        self.command(args).await?;
+        Ok(())
+    }
+
+    /// SET key value PX milliseconds
+    pub async fn set_ex(
+        &mut self,
+        key: &str,
+        value: impl AsRef<[u8]>,
+        ttl: Duration,
+    ) -> RedisResult<()> {
+        let ms = ttl.as_millis().to_string();
+        let args: [&[u8]; 5] = [b"SET", key.as_bytes(), value.as_ref(), b"PX", ms.as_bytes()];
+        self.command(args).await?;
+        Ok(())
+    }
+
+    /// 用指定编解码器序列化值后SET
+    ///
+    /// Rust特点: 编解码器作为泛型参数传入，调用处决定存储格式
+    pub async fn set_typed<C, T>(&mut self, codec: C, key: &str, value: &T) -> RedisResult<()>
+    where
+        C: Codec,
+        T: Serialize + ?Sized,
+    {
+        let data = codec.encode(value)?;
+        self.set(key, data).await
+    }
+
+    /// GET后用指定编解码器反序列化
+    pub async fn get_typed<C, T>(&mut self, codec: C, key: &str) -> RedisResult<Option<T>>
+    where
+        C: Codec,
+        T: DeserializeOwned,
+    {
+        match self.get(key).await? {
+            Some(data) => codec.decode(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// DEL key [key ...]，返回删除的键数量
+    pub async fn del<K: AsRef<str>>(&mut self, keys: &[K]) -> RedisResult<i64> {
+        let args = std::iter::once("DEL").chain(keys.iter().map(|k| k.as_ref()));
+        let reply = self.command(args).await?;
+        expect_integer("DEL", reply)
+    }
+
+    /// EXISTS key
+    pub async fn exists(&mut self, key: &str) -> RedisResult<bool> {
+        let reply = self.command(["EXISTS", key]).await?;
+        Ok(expect_integer("EXISTS", reply)? > 0)
+    }
+
+    /// PEXPIRE key milliseconds，返回是否设置成功
+    pub async fn expire(&mut self, key: &str, ttl: Duration) -> RedisResult<bool> {
+        let ms = ttl.as_millis().to_string();
+        let reply = self.command(["PEXPIRE", key, &ms]).await?;
+        Ok(expect_integer("PEXPIRE", reply)? == 1)
+    }
+
+    /// PTTL key - 剩余毫秒数，-1表示永不过期，-2表示键不存在
+    pub async fn pttl(&mut self, key: &str) -> RedisResult<i64> {
+        let reply = self.command(["PTTL", key]).await?;
+        expect_integer("PTTL", reply)
+    }
+
+    /// INCR key
+    pub async fn incr(&mut self, key: &str) -> RedisResult<i64> {
+        let reply = self.command(["INCR", key]).await?;
+        expect_integer("INCR", reply)
+    }
+
+    /// INCRBY key delta
+    pub async fn incr_by(&mut self, key: &str, delta: i64) -> RedisResult<i64> {
+        let reply = self.command(["INCRBY", key, &delta.to_string()]).await?;
+        expect_integer("INCRBY", reply)
+    }
+
+    /// DECR key
+    pub async fn decr(&mut self, key: &str) -> RedisResult<i64> {
+        let reply = self.command(["DECR", key]).await?;
+        expect_integer("DECR", reply)
+    }
+
+    /// LOCK key token milliseconds，返回是否获取(或续期)了锁
+    pub async fn lock(&mut self, key: &str, token: &str, ttl: Duration) -> RedisResult<bool> {
+        let ms = ttl.as_millis().to_string();
+        match self.command(["LOCK", key, token, &ms]).await? {
+            RespValue::SimpleString(_) => Ok(true),
+            RespValue::Null => Ok(false),
+            reply => Err(unexpected_reply("LOCK", &reply)),
+        }
+    }
+
+    /// UNLOCK key token，返回是否释放了锁
+    pub async fn unlock(&mut self, key: &str, token: &str) -> RedisResult<bool> {
+        let reply = self.command(["UNLOCK", key, token]).await?;
+        Ok(expect_integer("UNLOCK", reply)? == 1)
+    }
 
-/// 解析命令行参数
-fn parse_args() -> (String, u16) {
-    let args: Vec<String> = env::args().collect();
+    /// SCAN cursor [MATCH pattern] COUNT count，返回下一个游标和本批的键
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> RedisResult<(u64, Vec<String>)> {
+        let cursor = cursor.to_string();
+        let count = count.to_string();
+        let mut args = vec!["SCAN", &cursor, "COUNT", &count];
+        if let Some(pattern) = pattern {
+            args.extend(["MATCH", pattern]);
+        }
+
+        let reply = self.command(args).await?;
+        let RespValue::Array(parts) = &reply else {
+            return Err(unexpected_reply("SCAN", &reply));
+        };
+        match parts.as_slice() {
+            [next, RespValue::Array(keys)] => {
+                let next = next
+                    .as_string()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| unexpected_reply("SCAN", &reply))?;
+                let keys = keys.iter().filter_map(RespValue::as_string).collect();
+                Ok((next, keys))
+            }
+            _ => Err(unexpected_reply("SCAN", &reply)),
+        }
+    }
+
+    /// 以MULTI/EXEC事务执行闭包中排队的命令
+    ///
+    /// 返回每条命令的回复(命令级错误以`RespValue::Error`出现在结果中)
+    pub async fn transaction<F>(&mut self, f: F) -> RedisResult<Vec<RespValue>>
+    where
+        F: FnOnce(&mut Transaction),
+    {
+        let mut tx = Transaction::new();
+        f(&mut tx);
+        self.exec_transaction(tx)
+            .await?
+            .ok_or_else(|| RedisError::Protocol("EXEC 意外返回空值".to_string()))
+    }
+
+    /// 基于WATCH的乐观事务 (check-and-set)
+    ///
+    /// 每一轮: WATCH所有键 → 读取它们的当前值 → 闭包根据当前值排队命令 → EXEC。
+    /// 如果期间有其他客户端修改了被监视的键，EXEC会被中止并重新开始，
+    /// 最多重试`max_retries`次。闭包没有排队任何命令时视为主动放弃，返回`Ok(None)`。
+    ///
+    /// Rust特点: FnMut闭包可以被多次调用，并修改捕获的状态
+    pub async fn watch_transaction<F>(
+        &mut self,
+        keys: &[&str],
+        max_retries: usize,
+        mut f: F,
+    ) -> RedisResult<Option<Vec<RespValue>>>
+    where
+        F: FnMut(&[Option<Vec<u8>>], &mut Transaction),
+    {
+        for _ in 0..=max_retries {
+            self.command(std::iter::once("WATCH").chain(keys.iter().copied()))
+                .await?;
+
+            let values = match self
+                .command(std::iter::once("MGET").chain(keys.iter().copied()))
+                .await?
+            {
+                RespValue::Array(items) => items
+                    .into_iter()
+                    .map(|item| match item {
+                        RespValue::BulkString(data) => Some(data),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+                reply => return Err(unexpected_reply("MGET", &reply)),
+            };
+
+            let mut tx = Transaction::new();
+            f(&values, &mut tx);
+            if tx.is_empty() {
+                self.command(["UNWATCH"]).await?;
+                return Ok(None);
+            }
+
+            if let Some(replies) = self.exec_transaction(tx).await? {
+                return Ok(Some(replies));
+            }
+        }
+
+        Err(RedisError::TransactionAborted(max_retries))
+    }
+
+    /// 以流水线方式发送 MULTI + 命令 + EXEC
+    ///
+    /// 返回None表示事务因WATCH被中止
+    async fn exec_transaction(&mut self, tx: Transaction) -> RedisResult<Option<Vec<RespValue>>> {
+        let mut data = build_command(["MULTI"]).serialize();
+        for cmd in &tx.commands {
+            data.extend(cmd.serialize());
+        }
+        data.extend(build_command(["EXEC"]).serialize());
+        self.stream.write_all(&data).await?;
+        self.stream.flush().await?;
 
-    let host = args.get(1).cloned().unwrap_or_else(|| "127.0.0.1".to_string());
+        // MULTI和每条命令的回复(OK/QUEUED或排队错误)，EXEC会汇总报告
+        for _ in 0..=tx.commands.len() {
+            self.read_reply().await?;
+        }
 
-    let port = args
-        .get(2)
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(DEFAULT_PORT);
+        match self.read_reply().await? {
+            RespValue::Array(replies) => Ok(Some(replies)),
+            RespValue::Null => Ok(None),
+            RespValue::Error(e) => Err(RedisError::ServerError(e)),
+            reply => Err(unexpected_reply("EXEC", &reply)),
+        }
+    }
+}
 
-    (host, port)
+/// 事务构建器 - 收集要在MULTI/EXEC中执行的命令
+#[derive(Debug, Default)]
+pub struct Transaction {
+    commands: Vec<RespValue>,
 }
 
-/// 将用户输入解析为RESP数组
-///
-/// Rust特点: 迭代器和闭包的组合
-fn parse_input(input: &str) -> RespValue {
-    // 简单的空格分割，支持引号内的空格
-    let parts = tokenize(input);
+impl Transaction {
+    /// 创建空事务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 排队一条命令
+    ///
+    /// Rust特点: 返回&mut Self支持链式调用
+    pub fn cmd<I, A>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<[u8]>,
+    {
+        self.commands.push(build_command(args));
+        self
+    }
+
+    /// 已排队的命令数量
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
 
+    /// 是否没有排队任何命令
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// 将参数列表构造成RESP命令数组
+pub fn build_command<I, A>(args: I) -> RespValue
+where
+    I: IntoIterator<Item = A>,
+    A: AsRef<[u8]>,
+{
     RespValue::Array(
-        parts
-            .into_iter()
-            .map(|s| RespValue::BulkString(s.into_bytes()))
+        args.into_iter()
+            .map(|arg| RespValue::BulkString(arg.as_ref().to_vec()))
             .collect(),
     )
 }
 
-/// 分词器 - 支持引号
+/// 分词器 - 把一行命令文本拆成参数，支持单引号和双引号
 ///
 /// Rust特点: 状态机模式匹配
-fn tokenize(input: &str) -> Vec<String> {
+pub fn tokenize(input: &str) -> Vec<String> {
     let mut tokens = Vec::new();
     let mut current = String::new();
     let mut in_quotes = false;
@@ -151,61 +504,170 @@ fn tokenize(input: &str) -> Vec<String> {
     tokens
 }
 
-/// 格式化打印响应
-///
-/// Rust特点: 递归模式匹配
-fn print_response(value: &RespValue) {
-    print_response_inner(value, 0);
+/// 从回复中取出整数
+fn expect_integer(command: &str, reply: RespValue) -> RedisResult<i64> {
+    match reply {
+        RespValue::Integer(n) => Ok(n),
+        reply => Err(unexpected_reply(command, &reply)),
+    }
 }
 
-fn print_response_inner(value: &RespValue, indent: usize) {
-    let prefix = "  ".repeat(indent);
+/// 构造"意外回复类型"错误
+fn unexpected_reply(command: &str, reply: &RespValue) -> RedisError {
+    RedisError::Protocol(format!("{} 收到意外的回复: {:?}", command, reply))
+}
 
-    match value {
-        RespValue::SimpleString(s) => {
-            println!("{}\"{s}\"", prefix);
-        }
-        RespValue::Error(e) => {
-            println!("{}(error) {}", prefix, e);
-        }
-        RespValue::Integer(i) => {
-            println!("{}(integer) {}", prefix, i);
-        }
-        RespValue::BulkString(data) => {
-            match String::from_utf8(data.clone()) {
-                Ok(s) => println!("{}\"{s}\"", prefix),
-                Err(_) => println!("{}<binary data, {} bytes>", prefix, data.len()),
-            }
-        }
-        RespValue::Null => {
-            println!("{}(nil)", prefix);
-        }
-        RespValue::Array(arr) => {
-            if arr.is_empty() {
-                println!("{}(empty array)", prefix);
-            } else {
-                for (i, item) in arr.iter().enumerate() {
-                    print!("{}{}) ", prefix, i + 1);
-                    // 数组元素不需要额外缩进前缀
-                    match item {
-                        RespValue::SimpleString(s) => println!("\"{s}\""),
-                        RespValue::Error(e) => println!("(error) {}", e),
-                        RespValue::Integer(i) => println!("(integer) {}", i),
-                        RespValue::BulkString(data) => {
-                            match String::from_utf8(data.clone()) {
-                                Ok(s) => println!("\"{s}\""),
-                                Err(_) => println!("<binary data, {} bytes>", data.len()),
-                            }
-                        }
-                        RespValue::Null => println!("(nil)"),
-                        RespValue::Array(_) => {
-                            println!();
-                            print_response_inner(item, indent + 1);
-                        }
-                    }
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+
+    #[test]
+    fn test_build_command() {
+        let cmd = build_command(["SET", "key", "value"]);
+        assert_eq!(
+            cmd.serialize(),
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n"
+        );
     }
-}
 
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("SET  k \"a b\" 'c d'"), ["SET", "k", "a b", "c d"]);
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_roundtrip() {
+        let server = TestServer::start().await;
+        let mut client = server.client().await;
+
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        client.set("foo", "bar").await.unwrap();
+        assert_eq!(client.get("foo").await.unwrap(), Some(b"bar".to_vec()));
+        assert_eq!(client.incr("counter").await.unwrap(), 1);
+        assert_eq!(client.incr_by("counter", 9).await.unwrap(), 10);
+        assert!(client.expire("foo", Duration::from_secs(60)).await.unwrap());
+        assert!(client.pttl("foo").await.unwrap() > 0);
+        assert_eq!(client.del(&["foo", "counter"]).await.unwrap(), 2);
+        assert!(!client.exists("foo").await.unwrap());
+
+        client.set("scan:a", "1").await.unwrap();
+        let (next, keys) = client.scan(0, Some("scan:*"), 100).await.unwrap();
+        assert_eq!((next, keys), (0, vec!["scan:a".to_string()]));
+
+        let err = client.command(["NOSUCHCOMMAND"]).await.unwrap_err();
+        assert!(matches!(err, RedisError::ServerError(_)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_unix() {
+        let path = std::env::temp_dir().join(format!("rust-redis-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(b"+PONG\r\n").await.unwrap();
+        });
+
+        let mut client = Client::connect_unix(&path).await.unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_read_raw() {
+        let (stream, mut peer) = tokio::io::duplex(64);
+        let mut client = Client::from_stream(stream);
+        peer.write_all(b"\n$5\r\nhello").await.unwrap();
+        drop(peer);
+
+        // SYNC的回复: 保活的空行、长度行，然后是不以CRLF结尾的数据
+        assert_eq!(client.read_line().await.unwrap(), b"");
+        assert_eq!(client.read_line().await.unwrap(), b"$5");
+        assert_eq!(client.read_raw(3).await.unwrap(), b"hel");
+        assert_eq!(client.read_raw(10).await.unwrap(), b"lo");
+        assert!(client.read_raw(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_typed_values() {
+        use crate::codec::{JsonCodec, MsgPackCodec};
+
+        let server = TestServer::start().await;
+        let mut client = server.client().await;
+
+        let point = (3i32, -4i32, "origin".to_string());
+        client.set_typed(MsgPackCodec, "point", &point).await.unwrap();
+        let loaded: Option<(i32, i32, String)> =
+            client.get_typed(MsgPackCodec, "point").await.unwrap();
+        assert_eq!(loaded, Some(point));
+
+        client.set_typed(JsonCodec, "list", &[1, 2, 3]).await.unwrap();
+        assert_eq!(client.get("list").await.unwrap(), Some(b"[1,2,3]".to_vec()));
+
+        let missing: Option<Vec<i32>> = client.get_typed(JsonCodec, "missing").await.unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let server = TestServer::start().await;
+        let mut client = server.client().await;
+
+        let replies = client
+            .transaction(|tx| {
+                tx.cmd(["SET", "a", "1"]).cmd(["INCR", "a"]).cmd(["GET", "a"]);
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            replies,
+            vec![
+                RespValue::SimpleString("OK".to_string()),
+                RespValue::Integer(2),
+                RespValue::BulkString(b"2".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_transaction() {
+        let server = TestServer::start().await;
+        let mut client = server.client().await;
+        let mut other = server.client().await;
+
+        // 手动协议序列: WATCH之后被其他客户端修改，EXEC返回nil
+        client.command(["WATCH", "balance"]).await.unwrap();
+        other.set("balance", "100").await.unwrap();
+        client.command(["MULTI"]).await.unwrap();
+        client.command(["SET", "balance", "0"]).await.unwrap();
+        assert_eq!(client.command(["EXEC"]).await.unwrap(), RespValue::Null);
+
+        // 辅助方法: 读取-修改-写入
+        let mut attempts = 0;
+        let result = client
+            .watch_transaction(&["balance"], 3, |values, tx| {
+                attempts += 1;
+                let balance: i64 = values[0]
+                    .as_deref()
+                    .and_then(|v| std::str::from_utf8(v).ok()?.parse().ok())
+                    .unwrap_or(0);
+                tx.cmd(["SET", "balance", &(balance - 30).to_string()]);
+            })
+            .await;
+        assert!(result.unwrap().is_some());
+        assert_eq!(attempts, 1);
+        assert_eq!(client.get("balance").await.unwrap(), Some(b"70".to_vec()));
+
+        // 放弃事务
+        let aborted = client
+            .watch_transaction(&["balance"], 3, |_, _| {})
+            .await
+            .unwrap();
+        assert!(aborted.is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! 客户端内存统计 - maxmemory-clients
+//!
+//! 每个连接登记自己的输入缓冲区和正在发送的回复占用的内存，注册表维护所有连接的总和。
+//! 总和超过`maxmemory-clients`时，按占用从大到小断开连接，直到总和回到上限以内，
+//! 避免大量连接缓冲区(慢客户端、超大的管道请求)挤占数据集的内存。
+//!
+//! 管理连接(Unix socket)计入总和，但不会被断开，与Redis的`CLIENT NO-EVICT on`相同。
+//!
+//! Rust特点展示:
+//! - 总和用原子变量增量维护，只有超过上限时才加锁遍历所有连接
+//! - `Notify`把断开请求从检查的线程送到目标连接的任务，目标连接在select!中响应
+//! - RAII: `ClientHandle`被drop时自动注销并从总和中减去它的占用
+
+use crate::stats::Stats;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::Notify;
+
+/// 一个连接的内存占用和断开请求
+#[derive(Debug, Default)]
+struct ClientState {
+    /// 当前占用的字节数
+    memory: AtomicUsize,
+    /// 不会被断开的管理连接
+    protected: bool,
+    /// 已被选中断开
+    evicted: AtomicBool,
+    notify: Notify,
+}
+
+/// 所有连接的内存占用，由Store持有
+#[derive(Debug)]
+pub struct ClientRegistry {
+    clients: Mutex<HashMap<u64, Arc<ClientState>>>,
+    next_id: AtomicU64,
+    /// 所有连接占用的总和
+    total: AtomicUsize,
+    /// 断开的连接计入evicted_clients
+    stats: Arc<Stats>,
+}
+
+impl ClientRegistry {
+    pub fn new(stats: Arc<Stats>) -> Self {
+        Self {
+            clients: Mutex::default(),
+            next_id: AtomicU64::new(0),
+            total: AtomicUsize::new(0),
+            stats,
+        }
+    }
+
+    /// 登记一个新连接，`protected`为true时该连接不会被断开
+    pub fn register(self: &Arc<Self>, protected: bool) -> ClientHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let state = Arc::new(ClientState {
+            protected,
+            ..Default::default()
+        });
+        self.lock().insert(id, state.clone());
+        ClientHandle {
+            id,
+            state,
+            registry: self.clone(),
+        }
+    }
+
+    /// 当前的连接数
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 所有连接占用的内存总和(字节)
+    pub fn memory(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// 总和超过`limit`时断开占用最多的连接，返回本次选中的连接数；`limit`为0表示不限制
+    ///
+    /// 被选中的连接占用的内存在它真正断开、注销之后才释放，
+    /// 已被选中的连接不再重复选择，也不再计入需要释放的部分
+    pub fn enforce(&self, limit: usize) -> usize {
+        if limit == 0 || self.memory() <= limit {
+            return 0;
+        }
+
+        let clients = self.lock();
+        let mut candidates: Vec<&ClientState> = Vec::new();
+        let mut total = 0;
+        for state in clients.values().map(Arc::as_ref) {
+            if state.evicted.load(Ordering::Relaxed) {
+                continue;
+            }
+            total += state.memory.load(Ordering::Relaxed);
+            if !state.protected {
+                candidates.push(state);
+            }
+        }
+        candidates.sort_by_key(|state| std::cmp::Reverse(state.memory.load(Ordering::Relaxed)));
+
+        let mut evicted = 0;
+        for state in candidates {
+            if total <= limit {
+                break;
+            }
+            total -= state.memory.load(Ordering::Relaxed);
+            state.evicted.store(true, Ordering::Relaxed);
+            state.notify.notify_one();
+            evicted += 1;
+        }
+        self.stats.record_evicted_clients(evicted as u64);
+        evicted
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<ClientState>>> {
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// INFO中的`# Clients`部分
+    pub fn info(&self) -> String {
+        format!(
+            "# Clients\r\n\
+             connected_clients:{}\r\n\
+             mem_clients_normal:{}\r\n",
+            self.len(),
+            self.memory()
+        )
+    }
+}
+
+/// 一个连接在注册表中的登记，被drop时注销
+#[derive(Debug)]
+pub struct ClientHandle {
+    id: u64,
+    state: Arc<ClientState>,
+    registry: Arc<ClientRegistry>,
+}
+
+impl ClientHandle {
+    /// 更新本连接占用的内存，并按`limit`检查所有连接的总和
+    pub fn set_memory(&self, bytes: usize, limit: usize) {
+        self.update(bytes);
+        if bytes > 0 {
+            self.registry.enforce(limit);
+        }
+    }
+
+    fn update(&self, bytes: usize) {
+        let old = self.state.memory.swap(bytes, Ordering::Relaxed);
+        if bytes >= old {
+            self.registry.total.fetch_add(bytes - old, Ordering::Relaxed);
+        } else {
+            self.registry.total.fetch_sub(old - bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// 是否已被选中断开
+    pub fn is_evicted(&self) -> bool {
+        self.state.evicted.load(Ordering::Relaxed)
+    }
+
+    /// 等待本连接被选中断开
+    ///
+    /// 返回的Future不借用self，可以与使用本登记的连接处理并发等待；
+    /// `notify_one`在没有等待者时保存一个许可，所以先选中、后等待也不会错过
+    pub fn evicted(&self) -> impl Future<Output = ()> + Send + 'static {
+        let state = self.state.clone();
+        async move {
+            while !state.evicted.load(Ordering::Relaxed) {
+                state.notify.notified().await;
+            }
+        }
+    }
+}
+
+impl Drop for ClientHandle {
+    fn drop(&mut self) {
+        self.update(0);
+        self.registry.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforce() {
+        let registry = Arc::new(ClientRegistry::new(Arc::default()));
+        let small = registry.register(false);
+        let big = registry.register(false);
+        let admin = registry.register(true);
+        small.set_memory(1000, 0);
+        big.set_memory(5000, 0);
+        admin.set_memory(8000, 0);
+        assert_eq!(registry.memory(), 14000);
+
+        assert_eq!(registry.enforce(0), 0);
+        assert_eq!(registry.enforce(20000), 0);
+
+        // 只需断开最大的普通连接，管理连接即使更大也不会被选中
+        assert_eq!(registry.enforce(10000), 1);
+        assert!(big.is_evicted());
+        assert!(!small.is_evicted() && !admin.is_evicted());
+        // 被选中的连接还没断开，再次检查不会重复选择
+        assert_eq!(registry.enforce(10000), 0);
+
+        drop(big);
+        assert_eq!(registry.memory(), 9000);
+        assert_eq!(registry.len(), 2);
+
+        // 断开所有普通连接仍然不够时，只断开能断开的
+        assert_eq!(registry.enforce(100), 1);
+        assert!(small.is_evicted());
+        assert_eq!(registry.stats.evicted_clients(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_evicted_wakes_waiter() {
+        let registry = Arc::new(ClientRegistry::new(Arc::default()));
+        let client = registry.register(false);
+        client.set_memory(100, 10);
+        // 先被选中、后开始等待
+        client.evicted().await;
+    }
+}
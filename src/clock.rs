@@ -0,0 +1,164 @@
+//! 时钟抽象模块 - 展示Rust的trait对象和依赖注入
+//!
+//! 过期逻辑不直接读取系统时间，而是通过注入的`Clock`获取当前时间。
+//! 生产环境使用`SystemClock`，测试中使用`ManualClock`手动推进时间，
+//! 无需真正sleep即可验证TTL行为；整个服务器在模拟运行时中测试时使用`TokioClock`，
+//! 过期与定期清理、限流等使用的tokio定时器走同一个时间。
+//!
+//! 时间以Unix毫秒表示，过期时间因此是绝对时间点，可以直接写入持久化文件
+//! 或同步给副本，与EXPIREAT的语义一致。
+//!
+//! Rust特点展示:
+//! - trait对象 (`Arc<dyn Clock>`) 实现运行时多态
+//! - `Send + Sync` 约束保证可以跨线程共享
+//! - Mutex和原子类型实现内部可变性
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 时钟trait - 提供当前时间
+///
+/// Rust特点: trait作为接口，任何实现者都可以注入到Store中
+pub trait Clock: Debug + Send + Sync {
+    /// 当前Unix时间(毫秒)
+    fn now_ms(&self) -> u64;
+}
+
+/// 读取系统的Unix时间(毫秒)
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// 系统时钟 - 读取墙上时间，但保证不会倒退
+///
+/// NTP校时等原因可能让系统时间往回跳，如果直接使用，已经过期的键会"复活"。
+/// 这里记住返回过的最大值，时间倒退时继续返回它，相当于单调时钟兜底。
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+/// SystemClock返回过的最大时间
+static LAST_MS: AtomicU64 = AtomicU64::new(0);
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        let now = unix_time_ms();
+        // Rust特点: fetch_max原子地更新并返回旧值，无需加锁
+        now.max(LAST_MS.fetch_max(now, Ordering::Relaxed))
+    }
+}
+
+/// 手动时钟 - 只有调用`advance`时时间才会前进
+///
+/// 用于确定性地测试过期逻辑
+#[derive(Debug)]
+pub struct ManualClock {
+    /// 当前时间(Unix毫秒)
+    now_ms: Mutex<u64>,
+}
+
+impl ManualClock {
+    /// 创建一个从当前时刻开始的手动时钟
+    pub fn new() -> Self {
+        Self::at(unix_time_ms())
+    }
+
+    /// 创建一个从指定Unix时间(毫秒)开始的手动时钟
+    pub fn at(now_ms: u64) -> Self {
+        Self {
+            now_ms: Mutex::new(now_ms),
+        }
+    }
+
+    /// 将时钟向前推进指定时长
+    pub fn advance(&self, duration: Duration) {
+        let mut now_ms = self.now_ms.lock().unwrap();
+        *now_ms += duration.as_millis() as u64;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        *self.now_ms.lock().unwrap()
+    }
+}
+
+/// tokio时钟 - 从创建时的Unix时间开始，按tokio运行时的时间前进
+///
+/// 运行时的时间被`tokio::time::pause`暂停(或由turmoil等模拟器驱动)时，
+/// 只有模拟器推进时间时才会前进，键的过期与`cleanup_task`的定时器保持一致，测试结果可以精确复现
+#[derive(Debug)]
+pub struct TokioClock {
+    /// 创建时的Unix时间(毫秒)
+    base_ms: u64,
+    /// 创建时的运行时时刻
+    start: tokio::time::Instant,
+}
+
+impl TokioClock {
+    /// 创建一个从当前时刻开始的tokio时钟
+    pub fn new() -> Self {
+        Self::at(unix_time_ms())
+    }
+
+    /// 创建一个从指定Unix时间(毫秒)开始的tokio时钟
+    pub fn at(base_ms: u64) -> Self {
+        Self {
+            base_ms,
+            start: tokio::time::Instant::now(),
+        }
+    }
+}
+
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for TokioClock {
+    fn now_ms(&self) -> u64 {
+        self.base_ms + self.start.elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_advance() {
+        let clock = ManualClock::at(1_000);
+        assert_eq!(clock.now_ms(), 1_000);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now_ms(), 6_000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokio_clock_follows_runtime() {
+        let clock = TokioClock::at(1_000);
+        assert_eq!(clock.now_ms(), 1_000);
+
+        // 暂停的运行时中sleep立即返回，时间前进的正好是sleep的时长
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+        assert_eq!(clock.now_ms(), 2_500);
+    }
+
+    #[test]
+    fn test_system_clock_is_wall_clock() {
+        let before = unix_time_ms();
+        let now = SystemClock.now_ms();
+        assert!(now >= before);
+        assert!(SystemClock.now_ms() >= now);
+    }
+}
@@ -0,0 +1,402 @@
+//! 集群客户端模块 - 按槽位路由命令
+//!
+//! Redis Cluster把键空间划分为16384个槽位，每个节点负责若干槽位区间。
+//! `ClusterClient`通过`CLUSTER SLOTS`获取拓扑，按键计算槽位后把命令发往
+//! 对应节点，并自动处理`MOVED`/`ASK`重定向。
+//!
+//! Rust特点展示:
+//! - 纯函数(CRC16、槽位计算)便于单元测试
+//! - HashMap缓存到各节点的连接
+//! - 枚举表示两种不同语义的重定向
+
+use crate::client::Client;
+use crate::error::{RedisError, RedisResult};
+use crate::resp::RespValue;
+use std::collections::HashMap;
+
+/// 槽位总数
+pub const SLOT_COUNT: u16 = 16384;
+
+/// 单条命令最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 16;
+
+/// 计算CRC16 (XMODEM变体，Redis Cluster使用的算法)
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// 计算键所属的槽位
+///
+/// 键中包含非空的`{...}`哈希标签时只对标签内容求哈希，
+/// 使相关的键落在同一个槽位上
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = key
+        .iter()
+        .position(|&b| b == b'{')
+        .and_then(|open| {
+            key[open + 1..]
+                .iter()
+                .position(|&b| b == b'}')
+                .filter(|&len| len > 0)
+                .map(|len| &key[open + 1..open + 1 + len])
+        })
+        .unwrap_or(key);
+
+    crc16(hashed) % SLOT_COUNT
+}
+
+/// 集群重定向
+///
+/// Rust特点: 枚举区分永久迁移(MOVED)和临时迁移(ASK)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redirect {
+    /// 槽位已永久迁移到另一个节点，需要更新路由表
+    Moved { slot: u16, addr: String },
+    /// 槽位正在迁移，本次请求需先发送ASKING再发往目标节点
+    Ask { slot: u16, addr: String },
+}
+
+impl Redirect {
+    /// 从错误消息中解析重定向，如 "MOVED 3999 127.0.0.1:6381"
+    pub fn parse(message: &str) -> Option<Redirect> {
+        let mut parts = message.split_whitespace();
+        let kind = parts.next()?;
+        let slot = parts.next()?.parse().ok()?;
+        let addr = parts.next()?.to_string();
+
+        match kind {
+            "MOVED" => Some(Redirect::Moved { slot, addr }),
+            "ASK" => Some(Redirect::Ask { slot, addr }),
+            _ => None,
+        }
+    }
+}
+
+/// 一段连续槽位及其主节点地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub addr: String,
+}
+
+/// 解析`CLUSTER SLOTS`的回复
+///
+/// 每一项形如 `[start, end, [host, port, id], [replica...] ...]`，只取主节点
+pub fn parse_cluster_slots(reply: &RespValue) -> RedisResult<Vec<SlotRange>> {
+    let invalid = || RedisError::Protocol("无效的 CLUSTER SLOTS 回复".to_string());
+
+    let entries = match reply {
+        RespValue::Array(entries) => entries,
+        _ => return Err(invalid()),
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let fields = match entry {
+                RespValue::Array(fields) if fields.len() >= 3 => fields,
+                _ => return Err(invalid()),
+            };
+            let start = fields[0].as_integer().ok_or_else(invalid)?;
+            let end = fields[1].as_integer().ok_or_else(invalid)?;
+            let node = match &fields[2] {
+                RespValue::Array(node) if node.len() >= 2 => node,
+                _ => return Err(invalid()),
+            };
+            let host = node[0].as_string().ok_or_else(invalid)?;
+            let port = node[1].as_integer().ok_or_else(invalid)?;
+
+            Ok(SlotRange {
+                start: start as u16,
+                end: end as u16,
+                addr: format!("{}:{}", host, port),
+            })
+        })
+        .collect()
+}
+
+/// 集群客户端
+///
+/// Rust特点: 组合多个Client，按需建立到各节点的连接
+pub struct ClusterClient {
+    /// 初始节点地址，拓扑刷新失败时从这里重新开始
+    seeds: Vec<String>,
+    /// 槽位路由表
+    slots: Vec<SlotRange>,
+    /// 到各节点的连接
+    connections: HashMap<String, Client>,
+}
+
+impl ClusterClient {
+    /// 连接集群并加载拓扑
+    pub async fn connect<S: AsRef<str>>(seeds: &[S]) -> RedisResult<Self> {
+        let mut client = Self {
+            seeds: seeds.iter().map(|s| s.as_ref().to_string()).collect(),
+            slots: Vec::new(),
+            connections: HashMap::new(),
+        };
+        client.refresh_slots().await?;
+        Ok(client)
+    }
+
+    /// 当前的槽位路由表
+    pub fn slots(&self) -> &[SlotRange] {
+        &self.slots
+    }
+
+    /// 重新获取集群拓扑
+    ///
+    /// 依次尝试已知节点和初始节点，直到有一个返回CLUSTER SLOTS
+    pub async fn refresh_slots(&mut self) -> RedisResult<()> {
+        let mut candidates: Vec<String> = self.connections.keys().cloned().collect();
+        candidates.extend(self.seeds.iter().cloned());
+
+        let mut last_error = RedisError::Protocol("没有可用的集群节点".to_string());
+        for addr in candidates {
+            let result = match self.connection(&addr).await {
+                Ok(conn) => conn.command(["CLUSTER", "SLOTS"]).await,
+                Err(e) => Err(e),
+            };
+            match result.and_then(|reply| parse_cluster_slots(&reply)) {
+                Ok(mut slots) => {
+                    slots.sort_by_key(|range| range.start);
+                    self.slots = slots;
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.connections.remove(&addr);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// 查找负责某个槽位的节点
+    pub fn node_for_slot(&self, slot: u16) -> Option<&str> {
+        self.slots
+            .iter()
+            .find(|range| range.start <= slot && slot <= range.end)
+            .map(|range| range.addr.as_str())
+    }
+
+    /// 执行任意命令，按第一个参数后的键路由
+    ///
+    /// 没有键的命令发往任一节点
+    pub async fn command<I, A>(&mut self, args: I) -> RedisResult<RespValue>
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<[u8]>,
+    {
+        let args: Vec<Vec<u8>> = args.into_iter().map(|a| a.as_ref().to_vec()).collect();
+        let slot = args.get(1).map(|key| key_slot(key));
+
+        let mut target = match slot.and_then(|slot| self.node_for_slot(slot)) {
+            Some(addr) => addr.to_string(),
+            None => self.any_node()?,
+        };
+        let mut asking = false;
+
+        for _ in 0..MAX_REDIRECTS {
+            let conn = self.connection(&target).await?;
+            if asking {
+                conn.command(["ASKING"]).await?;
+            }
+
+            match conn.command(&args).await {
+                Err(RedisError::ServerError(message)) => match Redirect::parse(&message) {
+                    Some(Redirect::Moved { slot, addr }) => {
+                        self.update_slot(slot, &addr);
+                        // 槽位迁移通常是批量的，顺便刷新整个路由表
+                        let _ = self.refresh_slots().await;
+                        target = addr;
+                        asking = false;
+                    }
+                    Some(Redirect::Ask { addr, .. }) => {
+                        target = addr;
+                        asking = true;
+                    }
+                    None => return Err(RedisError::ServerError(message)),
+                },
+                Err(e @ (RedisError::Io(_) | RedisError::ConnectionClosed)) => {
+                    // 节点不可用，丢弃连接并刷新拓扑后由调用者重试
+                    self.connections.remove(&target);
+                    let _ = self.refresh_slots().await;
+                    return Err(e);
+                }
+                result => return result,
+            }
+        }
+
+        Err(RedisError::Protocol("集群重定向次数过多".to_string()))
+    }
+
+    /// GET key
+    pub async fn get(&mut self, key: &str) -> RedisResult<Option<Vec<u8>>> {
+        match self.command(["GET", key]).await? {
+            RespValue::BulkString(data) => Ok(Some(data)),
+            RespValue::Null => Ok(None),
+            reply => Err(RedisError::Protocol(format!("GET 收到意外的回复: {:?}", reply))),
+        }
+    }
+
+    /// SET key value
+    pub async fn set(&mut self, key: &str, value: impl AsRef<[u8]>) -> RedisResult<()> {
+        let args: [&[u8]; 3] = [b"SET", key.as_bytes(), value.as_ref()];
+        self.command(args).await?;
+        Ok(())
+    }
+
+    /// 将单个槽位指向新节点(MOVED之后、拓扑刷新之前的临时修正)
+    fn update_slot(&mut self, slot: u16, addr: &str) {
+        if let Some(index) = self
+            .slots
+            .iter()
+            .position(|range| range.start <= slot && slot <= range.end)
+        {
+            let range = self.slots.remove(index);
+            let mut pieces = Vec::new();
+            if range.start < slot {
+                pieces.push(SlotRange {
+                    start: range.start,
+                    end: slot - 1,
+                    addr: range.addr.clone(),
+                });
+            }
+            pieces.push(SlotRange {
+                start: slot,
+                end: slot,
+                addr: addr.to_string(),
+            });
+            if slot < range.end {
+                pieces.push(SlotRange {
+                    start: slot + 1,
+                    end: range.end,
+                    addr: range.addr,
+                });
+            }
+            self.slots.splice(index..index, pieces);
+        } else {
+            self.slots.push(SlotRange {
+                start: slot,
+                end: slot,
+                addr: addr.to_string(),
+            });
+            self.slots.sort_by_key(|range| range.start);
+        }
+    }
+
+    /// 选择任意一个节点
+    fn any_node(&self) -> RedisResult<String> {
+        self.slots
+            .first()
+            .map(|range| range.addr.clone())
+            .or_else(|| self.seeds.first().cloned())
+            .ok_or_else(|| RedisError::Protocol("没有可用的集群节点".to_string()))
+    }
+
+    /// 获取(必要时建立)到某个节点的连接
+    async fn connection(&mut self, addr: &str) -> RedisResult<&mut Client> {
+        if !self.connections.contains_key(addr) {
+            let client = Client::connect(addr).await?;
+            self.connections.insert(addr.to_string(), client);
+        }
+        Ok(self.connections.get_mut(addr).expect("连接刚刚插入"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+    }
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+        assert_eq!(
+            key_slot(b"{user1000}.following"),
+            key_slot(b"{user1000}.followers")
+        );
+        // 空哈希标签按整个键计算
+        assert_eq!(key_slot(b"foo{}bar"), crc16(b"foo{}bar") % SLOT_COUNT);
+    }
+
+    #[test]
+    fn test_parse_redirect() {
+        assert_eq!(
+            Redirect::parse("MOVED 3999 127.0.0.1:6381"),
+            Some(Redirect::Moved {
+                slot: 3999,
+                addr: "127.0.0.1:6381".to_string()
+            })
+        );
+        assert_eq!(
+            Redirect::parse("ASK 3999 127.0.0.1:6381"),
+            Some(Redirect::Ask {
+                slot: 3999,
+                addr: "127.0.0.1:6381".to_string()
+            })
+        );
+        assert_eq!(Redirect::parse("ERR unknown command"), None);
+    }
+
+    #[test]
+    fn test_parse_cluster_slots() {
+        let node = |port| {
+            RespValue::Array(vec![
+                RespValue::BulkString(b"127.0.0.1".to_vec()),
+                RespValue::Integer(port),
+                RespValue::BulkString(b"node-id".to_vec()),
+            ])
+        };
+        let reply = RespValue::Array(vec![
+            RespValue::Array(vec![RespValue::Integer(0), RespValue::Integer(8191), node(7000)]),
+            RespValue::Array(vec![
+                RespValue::Integer(8192),
+                RespValue::Integer(16383),
+                node(7001),
+                node(7002),
+            ]),
+        ]);
+
+        let slots = parse_cluster_slots(&reply).unwrap();
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots[1].addr, "127.0.0.1:7001");
+    }
+
+    #[test]
+    fn test_update_slot_splits_range() {
+        let mut client = ClusterClient {
+            seeds: Vec::new(),
+            slots: vec![SlotRange {
+                start: 0,
+                end: 100,
+                addr: "a:1".to_string(),
+            }],
+            connections: HashMap::new(),
+        };
+
+        client.update_slot(50, "b:2");
+        assert_eq!(client.node_for_slot(49), Some("a:1"));
+        assert_eq!(client.node_for_slot(50), Some("b:2"));
+        assert_eq!(client.node_for_slot(51), Some("a:1"));
+        assert_eq!(client.slots().len(), 3);
+    }
+}
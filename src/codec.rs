@@ -0,0 +1,104 @@
+//! 编解码模块 - 把应用程序的结构体与存储的字节互相转换
+//!
+//! Rust特点展示:
+//! - trait定义可插拔的编解码接口
+//! - serde的Serialize/DeserializeOwned泛型约束
+//! - 零大小类型(单元结构体)作为策略对象
+
+use crate::error::{RedisError, RedisResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// 编解码器trait
+///
+/// Rust特点: 泛型方法让同一个编解码器可以处理任意可序列化类型
+pub trait Codec {
+    /// 将值编码为字节
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> RedisResult<Vec<u8>>;
+
+    /// 从字节解码出值
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> RedisResult<T>;
+}
+
+/// JSON编解码器 - 人类可读，便于用redis-cli直接查看
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> RedisResult<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> RedisResult<T> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// bincode编解码器 - 紧凑的二进制格式，只适合Rust程序之间交换数据
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> RedisResult<Vec<u8>> {
+        bincode::serialize(value).map_err(|e| RedisError::Codec(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> RedisResult<T> {
+        bincode::deserialize(data).map_err(|e| RedisError::Codec(e.to_string()))
+    }
+}
+
+/// MessagePack编解码器 - 紧凑且跨语言
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MsgPackCodec;
+
+impl Codec for MsgPackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> RedisResult<Vec<u8>> {
+        // 以map形式编码结构体，字段名随数据保存，其他语言也能读取
+        rmp_serde::to_vec_named(value).map_err(|e| RedisError::Codec(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> RedisResult<T> {
+        rmp_serde::from_slice(data).map_err(|e| RedisError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn sample() -> User {
+        User {
+            id: 7,
+            name: "alice".to_string(),
+            tags: vec!["admin".to_string()],
+        }
+    }
+
+    fn roundtrip<C: Codec>(codec: C) {
+        let data = codec.encode(&sample()).unwrap();
+        let decoded: User = codec.decode(&data).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[test]
+    fn test_codecs_roundtrip() {
+        roundtrip(JsonCodec);
+        roundtrip(BincodeCodec);
+        roundtrip(MsgPackCodec);
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let result: RedisResult<User> = MsgPackCodec.decode(b"not msgpack");
+        assert!(matches!(result, Err(RedisError::Codec(_))));
+    }
+}
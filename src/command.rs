@@ -6,10 +6,40 @@
 //! - 模式匹配解析和执行命令
 //! - 生命周期标注
 
+use crate::bitops::{self, BitField, BitOp, FieldOp, FieldType, Overflow};
+use crate::config::{Limits, DEFAULT_MAX_VALUE_SIZE};
 use crate::error::{RedisError, RedisResult};
+use crate::hash::{self, ExpireCondition};
+use crate::json::{self, JsonPath};
+use crate::memory;
+use crate::metadata::{self, CommandFlags};
 use crate::resp::{self, RespValue};
-use crate::store::Store;
-use std::time::Duration;
+use crate::probabilistic::{
+    BloomFilter, CuckooFilter, DEFAULT_BLOOM_CAPACITY, DEFAULT_CUCKOO_CAPACITY, DEFAULT_ERROR_RATE, DEFAULT_EXPANSION,
+};
+use crate::ratelimit::Throttle;
+use crate::store::{self, HashValue, ListValue, SetExpiry, SetOptions, SetValue, Store, ValueKind};
+use crate::stream::{
+    self, ClaimOptions, ConsumerGroup, Fields, IdSpec, PendingQuery, Stream, StreamId, Trim,
+    TrimStrategy,
+};
+use crate::timeseries::{tsdb_error, Aggregation, LabelFilter, TimeSeries};
+use crate::zset::{
+    self, LexBound, RangeBy, RangeQuery, ScoreBound, SortedSet, ZAddOptions, ZAddOutcome,
+};
+use std::time::{Duration, Instant};
+
+/// CLIENT REPLY的回复模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplyMode {
+    /// 正常回复
+    #[default]
+    On,
+    /// 不回复任何命令，直到CLIENT REPLY ON
+    Off,
+    /// 只跳过下一条命令的回复
+    Skip,
+}
 
 /// Redis命令枚举
 ///
@@ -20,18 +50,28 @@ pub enum Command {
     Ping(Option<String>),
     Echo(String),
     Quit,
+    Auth {
+        username: Option<String>,
+        password: String,
+    },
+    Select { index: i64 },
+    ClientReply { mode: ReplyMode },
 
     // 字符串命令
     Get { key: String },
     Set {
         key: String,
         value: Vec<u8>,
-        expiry: Option<Duration>,
+        expiry: Option<SetExpiry>, // None表示清除已有的过期时间
         nx: bool, // 仅当键不存在时设置
         xx: bool, // 仅当键存在时设置
+        get: bool, // 返回旧值
     },
+    SetNx { key: String, value: Vec<u8> },
     GetSet { key: String, value: Vec<u8> },
     Append { key: String, value: Vec<u8> },
+    SetRange { key: String, offset: usize, value: Vec<u8> },
+    GetRange { key: String, start: i64, end: i64 },
     Strlen { key: String },
     Incr { key: String },
     IncrBy { key: String, delta: i64 },
@@ -39,29 +79,652 @@ pub enum Command {
     DecrBy { key: String, delta: i64 },
     MGet { keys: Vec<String> },
     MSet { pairs: Vec<(String, Vec<u8>)> },
+    MSetNx { pairs: Vec<(String, Vec<u8>)> },
+    BitOp { op: BitOp, destination: String, keys: Vec<String> },
+    BitField { key: String, fields: Vec<BitField> },
 
     // 键命令
     Del { keys: Vec<String> },
     Exists { keys: Vec<String> },
-    Expire { key: String, seconds: u64 },
-    PExpire { key: String, milliseconds: u64 },
+    /// EXPIRE/PEXPIRE的时长统一换算为毫秒，不大于0时执行时删除键
+    Expire { key: String, milliseconds: i64 },
+    PExpire { key: String, milliseconds: i64 },
+    /// EXPIREAT/PEXPIREAT的时间戳统一换算为Unix毫秒，不晚于当前时间时执行时删除键
+    ExpireAt { key: String, at_ms: i64 },
+    PExpireAt { key: String, at_ms: i64 },
+    ExpireTime { key: String },
+    PExpireTime { key: String },
     Ttl { key: String },
     PTtl { key: String },
     Persist { key: String },
     Keys { pattern: String },
     Type { key: String },
     Rename { old_key: String, new_key: String },
+    RenameNx { old_key: String, new_key: String },
+    Scan {
+        cursor: u64,
+        pattern: Option<String>,
+        count: usize,
+        key_type: Option<String>,
+    },
+
+    // 哈希命令
+    HSet { key: String, pairs: Vec<(Vec<u8>, Vec<u8>)> },
+    HGet { key: String, field: Vec<u8> },
+    HMGet { key: String, fields: Vec<Vec<u8>> },
+    HGetAll { key: String },
+    HDel { key: String, fields: Vec<Vec<u8>> },
+    HExists { key: String, field: Vec<u8> },
+    HLen { key: String },
+    HKeys { key: String },
+    HVals { key: String },
+    /// count为None时回复单个字段
+    HRandField { key: String, count: Option<i64>, with_values: bool },
+    HIncrBy { key: String, field: Vec<u8>, delta: i64 },
+    /// HEXPIRE/HPEXPIRE/HEXPIREAT/HPEXPIREAT: time已换算为毫秒，`at`表示是Unix时间；
+    /// `millis`表示参数以毫秒为单位，只用于区分命令名
+    HExpire {
+        key: String,
+        time: u64,
+        millis: bool,
+        at: bool,
+        condition: Option<ExpireCondition>,
+        fields: Vec<Vec<u8>>,
+    },
+    /// HTTL/HPTTL
+    HTtl { key: String, millis: bool, fields: Vec<Vec<u8>> },
+    HPersist { key: String, fields: Vec<Vec<u8>> },
+
+    // 列表命令
+    LPush { key: String, values: Vec<Vec<u8>> },
+    RPush { key: String, values: Vec<Vec<u8>> },
+    /// count为None时回复单个元素，否则回复数组
+    LPop { key: String, count: Option<usize> },
+    RPop { key: String, count: Option<usize> },
+    LRange { key: String, start: i64, stop: i64 },
+    LLen { key: String },
+    LIndex { key: String, index: i64 },
+    /// LPOS: rank为负数时从尾部开始查找；count为None时回复单个下标，否则回复数组
+    ///
+    /// count和maxlen为0表示不限制
+    LPos { key: String, element: Vec<u8>, rank: i64, count: Option<usize>, maxlen: usize },
+    LInsert { key: String, before: bool, pivot: Vec<u8>, element: Vec<u8> },
+    LSet { key: String, index: i64, element: Vec<u8> },
+    /// LREM: count为正数时从头部开始删除，负数时从尾部开始，0表示全部删除
+    LRem { key: String, count: i64, element: Vec<u8> },
+    LTrim { key: String, start: i64, stop: i64 },
+    /// LMOVE: `from_front`/`to_front`对应LEFT，否则为RIGHT
+    LMove { source: String, destination: String, from_front: bool, to_front: bool },
+    RPopLPush { source: String, destination: String },
+    /// BLPOP/BRPOP: 从第一个非空列表弹出；timeout为None表示一直等待
+    BPop { keys: Vec<String>, front: bool, timeout: Option<Duration> },
+    /// LMPOP: 从第一个非空列表的一端弹出最多count个元素
+    LMPop { keys: Vec<String>, front: bool, count: usize },
+    /// BLMPOP: 都为空时阻塞等待的LMPOP
+    BLMPop { keys: Vec<String>, front: bool, count: usize, timeout: Option<Duration> },
+    /// BLMOVE: 源列表为空时阻塞等待的LMOVE
+    BLMove {
+        source: String,
+        destination: String,
+        from_front: bool,
+        to_front: bool,
+        timeout: Option<Duration>,
+    },
+
+    // 集合命令
+    SAdd { key: String, members: Vec<Vec<u8>> },
+    SRem { key: String, members: Vec<Vec<u8>> },
+    SMembers { key: String },
+    SIsMember { key: String, member: Vec<u8> },
+    SMIsMember { key: String, members: Vec<Vec<u8>> },
+    SCard { key: String },
+    /// count为None时回复单个成员，否则回复数组
+    SPop { key: String, count: Option<usize> },
+    /// count为None时回复单个成员；为正数时成员不重复，为负数时可能重复
+    SRandMember { key: String, count: Option<i64> },
+    SUnion { keys: Vec<String> },
+    SInter { keys: Vec<String> },
+    /// SINTERCARD: limit为0表示不限制
+    SInterCard { keys: Vec<String>, limit: usize },
+    SDiff { keys: Vec<String> },
+    /// SUNIONSTORE/SINTERSTORE/SDIFFSTORE: 结果写入destination
+    SUnionStore { destination: String, keys: Vec<String> },
+    SInterStore { destination: String, keys: Vec<String> },
+    SDiffStore { destination: String, keys: Vec<String> },
+
+    // 有序集合命令
+    ZAdd {
+        key: String,
+        options: ZAddOptions,
+        /// (分数, 成员)
+        pairs: Vec<(f64, Vec<u8>)>,
+    },
+    ZScore { key: String, member: Vec<u8> },
+    ZIncrBy { key: String, delta: f64, member: Vec<u8> },
+    ZRank { key: String, member: Vec<u8> },
+    ZRevRank { key: String, member: Vec<u8> },
+    /// ZRANGE及ZREVRANGE、ZRANGEBYSCORE、ZRANGEBYLEX等变体
+    ZRange {
+        /// 客户端使用的命令名
+        name: &'static str,
+        key: String,
+        query: RangeQuery,
+        with_scores: bool,
+    },
+    /// 范围查询的结果写入destination
+    ZRangeStore {
+        destination: String,
+        source: String,
+        query: RangeQuery,
+    },
+    /// count为None时回复单个成员
+    ZRandMember { key: String, count: Option<i64>, with_scores: bool },
+    ZCard { key: String },
+    ZRem { key: String, members: Vec<Vec<u8>> },
+    /// BZPOPMIN/BZPOPMAX: 从第一个非空有序集合弹出分数最小/最大的成员
+    BZPop { keys: Vec<String>, min: bool, timeout: Option<Duration> },
+    /// ZMPOP: 从第一个非空有序集合弹出最多count个成员
+    ZMPop { keys: Vec<String>, min: bool, count: usize },
+    /// BZMPOP: 都为空时阻塞等待的ZMPOP
+    BZMPop { keys: Vec<String>, min: bool, count: usize, timeout: Option<Duration> },
+
+    // 流命令
+    XAdd {
+        key: String,
+        /// 键不存在时不创建，回复nil
+        no_mkstream: bool,
+        trim: Option<Trim>,
+        id: IdSpec,
+        fields: Fields,
+    },
+    XLen { key: String },
+    /// XRANGE/XREVRANGE，count为Some(0)时回复nil
+    XRange {
+        key: String,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    },
+    XTrim { key: String, trim: Trim },
+    /// 每个流读取ID之后的条目，ID为None表示`$`(只读取新条目)
+    XRead {
+        count: Option<usize>,
+        streams: Vec<(String, Option<StreamId>)>,
+    },
+    /// ID为None表示`$`(流的最后一个ID)
+    XGroupCreate {
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        mkstream: bool,
+    },
+    XGroupSetId { key: String, group: String, id: Option<StreamId> },
+    XGroupDestroy { key: String, group: String },
+    XGroupCreateConsumer { key: String, group: String, consumer: String },
+    XGroupDelConsumer { key: String, group: String, consumer: String },
+    /// 每个流读取ID之后的条目，ID为None表示`>`(从未投递过的新条目)
+    XReadGroup {
+        group: String,
+        consumer: String,
+        count: Option<usize>,
+        noack: bool,
+        streams: Vec<(String, Option<StreamId>)>,
+    },
+    XAck { key: String, group: String, ids: Vec<StreamId> },
+    /// query为None时回复摘要
+    XPending { key: String, group: String, query: Option<PendingQuery> },
+    XClaim {
+        key: String,
+        group: String,
+        consumer: String,
+        ids: Vec<StreamId>,
+        options: ClaimOptions,
+    },
+
+    // JSON命令
+    JsonSet {
+        key: String,
+        path: JsonPath,
+        value: serde_json::Value,
+        nx: bool,
+        xx: bool,
+    },
+    JsonGet { key: String, paths: Vec<JsonPath> },
+    JsonDel { key: String, path: JsonPath },
+    JsonType { key: String, path: JsonPath },
+    JsonNumIncrBy {
+        key: String,
+        path: JsonPath,
+        by: serde_json::Number,
+    },
+    JsonArrAppend {
+        key: String,
+        path: JsonPath,
+        values: Vec<serde_json::Value>,
+    },
+
+    // 时间序列命令
+    TsAdd {
+        key: String,
+        /// None表示使用服务器当前时间(`*`)
+        timestamp: Option<u64>,
+        value: f64,
+        /// 以下选项只在创建新序列时生效
+        retention: Option<u64>,
+        labels: Vec<(String, String)>,
+    },
+    TsRange {
+        key: String,
+        from: u64,
+        to: u64,
+        aggregation: Option<Aggregation>,
+    },
+    TsGet { key: String },
+    TsMRange {
+        from: u64,
+        to: u64,
+        aggregation: Option<Aggregation>,
+        with_labels: bool,
+        filters: Vec<LabelFilter>,
+    },
+
+    // 布隆过滤器命令
+    BfReserve {
+        key: String,
+        error_rate: f64,
+        capacity: u64,
+        /// 0表示不扩展(NONSCALING)
+        expansion: u32,
+    },
+    BfAdd { key: String, item: Vec<u8> },
+    BfMAdd { key: String, items: Vec<Vec<u8>> },
+    BfExists { key: String, item: Vec<u8> },
+
+    // 布谷鸟过滤器命令
+    CfAdd { key: String, item: Vec<u8> },
+    CfExists { key: String, item: Vec<u8> },
+    CfDel { key: String, item: Vec<u8> },
+
+    // 限流命令
+    ClThrottle {
+        key: String,
+        throttle: Throttle,
+        quantity: u64,
+    },
+
+    // 分布式锁命令
+    Lock {
+        key: String,
+        token: Vec<u8>,
+        ttl: Duration,
+    },
+    Unlock { key: String, token: Vec<u8> },
+
+    // 事务命令
+    Multi,
+    Exec,
+    Discard,
+    Watch { keys: Vec<String> },
+    Unwatch,
 
     // 服务器命令
     DbSize,
-    FlushDb,
+    FlushDb { lazy: bool },
     Info,
+    ConfigReload,
+    ConfigSet { name: String, value: String },
+    MemoryUsage { key: String },
+    ObjectEncoding { key: String },
 
-    // 未知命令
-    Unknown(String),
+    // 命令元数据
+    CommandTable,
+    CommandCount,
+    CommandList,
+    CommandInfo { names: Vec<String> },
+    CommandGetKeys { argv: Vec<String> },
+
+    // 容器命令的HELP子命令
+    Help { container: &'static str },
+
+    // 模块管理
+    ModuleLoad { path: String, args: Vec<String> },
+    ModuleUnload { name: String },
+    ModuleList,
+
+    // 未知命令，保留参数交给模块处理
+    Unknown { name: String, args: Vec<Vec<u8>> },
 }
 
 impl Command {
+    /// 命令名称(大写)，用于日志和统计
+    pub fn name(&self) -> &str {
+        match self {
+            Command::Ping(_) => "PING",
+            Command::Echo(_) => "ECHO",
+            Command::Quit => "QUIT",
+            Command::Auth { .. } => "AUTH",
+            Command::Select { .. } => "SELECT",
+            Command::Get { .. } => "GET",
+            Command::Set { .. } => "SET",
+            Command::SetNx { .. } => "SETNX",
+            Command::GetSet { .. } => "GETSET",
+            Command::Append { .. } => "APPEND",
+            Command::SetRange { .. } => "SETRANGE",
+            Command::GetRange { .. } => "GETRANGE",
+            Command::Strlen { .. } => "STRLEN",
+            Command::Incr { .. } => "INCR",
+            Command::IncrBy { .. } => "INCRBY",
+            Command::Decr { .. } => "DECR",
+            Command::DecrBy { .. } => "DECRBY",
+            Command::MGet { .. } => "MGET",
+            Command::MSet { .. } => "MSET",
+            Command::MSetNx { .. } => "MSETNX",
+            Command::BitOp { .. } => "BITOP",
+            Command::BitField { .. } => "BITFIELD",
+            Command::Del { .. } => "DEL",
+            Command::Exists { .. } => "EXISTS",
+            Command::Expire { .. } => "EXPIRE",
+            Command::PExpire { .. } => "PEXPIRE",
+            Command::ExpireAt { .. } => "EXPIREAT",
+            Command::PExpireAt { .. } => "PEXPIREAT",
+            Command::ExpireTime { .. } => "EXPIRETIME",
+            Command::PExpireTime { .. } => "PEXPIRETIME",
+            Command::Ttl { .. } => "TTL",
+            Command::PTtl { .. } => "PTTL",
+            Command::Persist { .. } => "PERSIST",
+            Command::Keys { .. } => "KEYS",
+            Command::Type { .. } => "TYPE",
+            Command::Rename { .. } => "RENAME",
+            Command::RenameNx { .. } => "RENAMENX",
+            Command::Scan { .. } => "SCAN",
+            Command::HSet { .. } => "HSET",
+            Command::HGet { .. } => "HGET",
+            Command::HMGet { .. } => "HMGET",
+            Command::HGetAll { .. } => "HGETALL",
+            Command::HDel { .. } => "HDEL",
+            Command::HExists { .. } => "HEXISTS",
+            Command::HLen { .. } => "HLEN",
+            Command::HKeys { .. } => "HKEYS",
+            Command::HVals { .. } => "HVALS",
+            Command::HRandField { .. } => "HRANDFIELD",
+            Command::HIncrBy { .. } => "HINCRBY",
+            Command::HExpire { millis: false, at: false, .. } => "HEXPIRE",
+            Command::HExpire { millis: true, at: false, .. } => "HPEXPIRE",
+            Command::HExpire { millis: false, at: true, .. } => "HEXPIREAT",
+            Command::HExpire { millis: true, at: true, .. } => "HPEXPIREAT",
+            Command::HTtl { millis: false, .. } => "HTTL",
+            Command::HTtl { millis: true, .. } => "HPTTL",
+            Command::HPersist { .. } => "HPERSIST",
+            Command::LPush { .. } => "LPUSH",
+            Command::RPush { .. } => "RPUSH",
+            Command::LPop { .. } => "LPOP",
+            Command::RPop { .. } => "RPOP",
+            Command::LRange { .. } => "LRANGE",
+            Command::LLen { .. } => "LLEN",
+            Command::LIndex { .. } => "LINDEX",
+            Command::LPos { .. } => "LPOS",
+            Command::LInsert { .. } => "LINSERT",
+            Command::LSet { .. } => "LSET",
+            Command::LRem { .. } => "LREM",
+            Command::LTrim { .. } => "LTRIM",
+            Command::LMove { .. } => "LMOVE",
+            Command::RPopLPush { .. } => "RPOPLPUSH",
+            Command::BPop { front: true, .. } => "BLPOP",
+            Command::BPop { front: false, .. } => "BRPOP",
+            Command::LMPop { .. } => "LMPOP",
+            Command::BLMPop { .. } => "BLMPOP",
+            Command::BLMove { .. } => "BLMOVE",
+            Command::SAdd { .. } => "SADD",
+            Command::SRem { .. } => "SREM",
+            Command::SMembers { .. } => "SMEMBERS",
+            Command::SIsMember { .. } => "SISMEMBER",
+            Command::SMIsMember { .. } => "SMISMEMBER",
+            Command::SCard { .. } => "SCARD",
+            Command::SPop { .. } => "SPOP",
+            Command::SRandMember { .. } => "SRANDMEMBER",
+            Command::SUnion { .. } => "SUNION",
+            Command::SInter { .. } => "SINTER",
+            Command::SInterCard { .. } => "SINTERCARD",
+            Command::SUnionStore { .. } => "SUNIONSTORE",
+            Command::SInterStore { .. } => "SINTERSTORE",
+            Command::SDiffStore { .. } => "SDIFFSTORE",
+            Command::SDiff { .. } => "SDIFF",
+            Command::ZAdd { .. } => "ZADD",
+            Command::ZScore { .. } => "ZSCORE",
+            Command::ZIncrBy { .. } => "ZINCRBY",
+            Command::ZRank { .. } => "ZRANK",
+            Command::ZRevRank { .. } => "ZREVRANK",
+            Command::ZRange { name, .. } => name,
+            Command::ZRangeStore { .. } => "ZRANGESTORE",
+            Command::ZRandMember { .. } => "ZRANDMEMBER",
+            Command::BZPop { min: true, .. } => "BZPOPMIN",
+            Command::BZPop { min: false, .. } => "BZPOPMAX",
+            Command::ZMPop { .. } => "ZMPOP",
+            Command::BZMPop { .. } => "BZMPOP",
+            Command::ZCard { .. } => "ZCARD",
+            Command::ZRem { .. } => "ZREM",
+            Command::XAdd { .. } => "XADD",
+            Command::XLen { .. } => "XLEN",
+            Command::XRange { rev: false, .. } => "XRANGE",
+            Command::XRange { rev: true, .. } => "XREVRANGE",
+            Command::XTrim { .. } => "XTRIM",
+            Command::XRead { .. } => "XREAD",
+            Command::XGroupCreate { .. }
+            | Command::XGroupSetId { .. }
+            | Command::XGroupDestroy { .. }
+            | Command::XGroupCreateConsumer { .. }
+            | Command::XGroupDelConsumer { .. } => "XGROUP",
+            Command::XReadGroup { .. } => "XREADGROUP",
+            Command::XAck { .. } => "XACK",
+            Command::XPending { .. } => "XPENDING",
+            Command::XClaim { .. } => "XCLAIM",
+            Command::JsonSet { .. } => "JSON.SET",
+            Command::JsonGet { .. } => "JSON.GET",
+            Command::JsonDel { .. } => "JSON.DEL",
+            Command::JsonType { .. } => "JSON.TYPE",
+            Command::JsonNumIncrBy { .. } => "JSON.NUMINCRBY",
+            Command::JsonArrAppend { .. } => "JSON.ARRAPPEND",
+            Command::TsAdd { .. } => "TS.ADD",
+            Command::TsRange { .. } => "TS.RANGE",
+            Command::TsGet { .. } => "TS.GET",
+            Command::TsMRange { .. } => "TS.MRANGE",
+            Command::BfReserve { .. } => "BF.RESERVE",
+            Command::BfAdd { .. } => "BF.ADD",
+            Command::BfMAdd { .. } => "BF.MADD",
+            Command::BfExists { .. } => "BF.EXISTS",
+            Command::CfAdd { .. } => "CF.ADD",
+            Command::CfExists { .. } => "CF.EXISTS",
+            Command::CfDel { .. } => "CF.DEL",
+            Command::ClThrottle { .. } => "CL.THROTTLE",
+            Command::Lock { .. } => "LOCK",
+            Command::Unlock { .. } => "UNLOCK",
+            Command::Multi => "MULTI",
+            Command::Exec => "EXEC",
+            Command::Discard => "DISCARD",
+            Command::Watch { .. } => "WATCH",
+            Command::Unwatch => "UNWATCH",
+            Command::DbSize => "DBSIZE",
+            Command::FlushDb { .. } => "FLUSHDB",
+            Command::Info => "INFO",
+            Command::ClientReply { .. } => "CLIENT",
+            Command::ConfigReload | Command::ConfigSet { .. } => "CONFIG",
+            Command::MemoryUsage { .. } => "MEMORY",
+            Command::ObjectEncoding { .. } => "OBJECT",
+            Command::ModuleLoad { .. } | Command::ModuleUnload { .. } | Command::ModuleList => "MODULE",
+            Command::CommandTable
+            | Command::CommandCount
+            | Command::CommandList
+            | Command::CommandInfo { .. }
+            | Command::CommandGetKeys { .. } => "COMMAND",
+            Command::Help { container } => container,
+            Command::Unknown { name, .. } => name,
+        }
+    }
+
+    /// 命令涉及的键
+    ///
+    /// Rust特点: 返回借用自self的&str，不需要克隆字符串
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Command::Get { key }
+            | Command::Set { key, .. }
+            | Command::SetNx { key, .. }
+            | Command::GetSet { key, .. }
+            | Command::Append { key, .. }
+            | Command::SetRange { key, .. }
+            | Command::GetRange { key, .. }
+            | Command::BitField { key, .. }
+            | Command::Strlen { key }
+            | Command::Incr { key }
+            | Command::IncrBy { key, .. }
+            | Command::Decr { key }
+            | Command::DecrBy { key, .. }
+            | Command::Expire { key, .. }
+            | Command::PExpire { key, .. }
+            | Command::ExpireAt { key, .. }
+            | Command::PExpireAt { key, .. }
+            | Command::ExpireTime { key }
+            | Command::PExpireTime { key }
+            | Command::Ttl { key }
+            | Command::PTtl { key }
+            | Command::Persist { key }
+            | Command::Type { key }
+            | Command::MemoryUsage { key }
+            | Command::ObjectEncoding { key }
+            | Command::HSet { key, .. }
+            | Command::HGet { key, .. }
+            | Command::HMGet { key, .. }
+            | Command::HGetAll { key }
+            | Command::HRandField { key, .. }
+            | Command::HDel { key, .. }
+            | Command::HExists { key, .. }
+            | Command::HLen { key }
+            | Command::HKeys { key }
+            | Command::HVals { key }
+            | Command::HIncrBy { key, .. }
+            | Command::HExpire { key, .. }
+            | Command::HTtl { key, .. }
+            | Command::HPersist { key, .. }
+            | Command::LPush { key, .. }
+            | Command::RPush { key, .. }
+            | Command::LPop { key, .. }
+            | Command::RPop { key, .. }
+            | Command::LRange { key, .. }
+            | Command::LLen { key }
+            | Command::LIndex { key, .. }
+            | Command::LPos { key, .. }
+            | Command::LInsert { key, .. }
+            | Command::LSet { key, .. }
+            | Command::LRem { key, .. }
+            | Command::LTrim { key, .. }
+            | Command::SAdd { key, .. }
+            | Command::SRem { key, .. }
+            | Command::SMembers { key }
+            | Command::SIsMember { key, .. }
+            | Command::SMIsMember { key, .. }
+            | Command::SCard { key }
+            | Command::SPop { key, .. }
+            | Command::SRandMember { key, .. }
+            | Command::ZAdd { key, .. }
+            | Command::ZScore { key, .. }
+            | Command::ZIncrBy { key, .. }
+            | Command::ZRank { key, .. }
+            | Command::ZRevRank { key, .. }
+            | Command::ZRange { key, .. }
+            | Command::ZRandMember { key, .. }
+            | Command::ZCard { key }
+            | Command::ZRem { key, .. }
+            | Command::XAdd { key, .. }
+            | Command::XLen { key }
+            | Command::XRange { key, .. }
+            | Command::XTrim { key, .. }
+            | Command::XGroupCreate { key, .. }
+            | Command::XGroupSetId { key, .. }
+            | Command::XGroupDestroy { key, .. }
+            | Command::XGroupCreateConsumer { key, .. }
+            | Command::XGroupDelConsumer { key, .. }
+            | Command::XAck { key, .. }
+            | Command::XPending { key, .. }
+            | Command::XClaim { key, .. }
+            | Command::JsonSet { key, .. }
+            | Command::JsonGet { key, .. }
+            | Command::JsonDel { key, .. }
+            | Command::JsonType { key, .. }
+            | Command::JsonNumIncrBy { key, .. }
+            | Command::JsonArrAppend { key, .. }
+            | Command::TsAdd { key, .. }
+            | Command::TsRange { key, .. }
+            | Command::TsGet { key }
+            | Command::BfReserve { key, .. }
+            | Command::BfAdd { key, .. }
+            | Command::BfMAdd { key, .. }
+            | Command::BfExists { key, .. }
+            | Command::CfAdd { key, .. }
+            | Command::CfExists { key, .. }
+            | Command::CfDel { key, .. }
+            | Command::ClThrottle { key, .. }
+            | Command::Lock { key, .. }
+            | Command::Unlock { key, .. } => vec![key.as_str()],
+            Command::MGet { keys }
+            | Command::Del { keys }
+            | Command::Exists { keys }
+            | Command::Watch { keys }
+            | Command::BPop { keys, .. }
+            | Command::LMPop { keys, .. }
+            | Command::BLMPop { keys, .. }
+            | Command::ZMPop { keys, .. }
+            | Command::SInterCard { keys, .. }
+            | Command::BZPop { keys, .. }
+            | Command::BZMPop { keys, .. }
+            | Command::SUnion { keys }
+            | Command::SInter { keys }
+            | Command::SDiff { keys } => keys.iter().map(String::as_str).collect(),
+            Command::MSet { pairs } | Command::MSetNx { pairs } => {
+                pairs.iter().map(|(key, _)| key.as_str()).collect()
+            }
+            Command::Rename { old_key, new_key } | Command::RenameNx { old_key, new_key } => {
+                vec![old_key.as_str(), new_key.as_str()]
+            }
+            Command::ZRangeStore {
+                destination, source, ..
+            } => vec![destination.as_str(), source.as_str()],
+            Command::LMove {
+                source, destination, ..
+            }
+            | Command::BLMove {
+                source, destination, ..
+            }
+            | Command::RPopLPush {
+                source, destination, ..
+            } => vec![source.as_str(), destination.as_str()],
+            Command::BitOp { destination, keys, .. }
+            | Command::SUnionStore { destination, keys }
+            | Command::SInterStore { destination, keys }
+            | Command::SDiffStore { destination, keys } => {
+                std::iter::once(destination).chain(keys).map(String::as_str).collect()
+            }
+            Command::XRead { streams, .. } | Command::XReadGroup { streams, .. } => {
+                streams.iter().map(|(key, _)| key.as_str()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// 阻塞命令等待的键和超时时间(None表示一直等待)；不是阻塞命令时返回None
+    pub fn blocking(&self) -> Option<(Vec<&str>, Option<Duration>)> {
+        match self {
+            Command::BPop { keys, timeout, .. }
+            | Command::BLMPop { keys, timeout, .. }
+            | Command::BZPop { keys, timeout, .. }
+            | Command::BZMPop { keys, timeout, .. } => {
+                Some((keys.iter().map(String::as_str).collect(), *timeout))
+            }
+            Command::BLMove { source, timeout, .. } => Some((vec![source.as_str()], *timeout)),
+            _ => None,
+        }
+    }
+
     /// 从RESP值解析命令
     ///
     /// Rust特点: 强大的模式匹配，可以同时匹配和解构
@@ -82,6 +745,17 @@ impl Command {
             .ok_or_else(|| RedisError::Protocol("命令名必须是字符串".to_string()))?
             .to_uppercase();
 
+        // 参数个数按元数据表中的arity统一检查，各命令的解析代码可以直接按下标取参数
+        if let Some(info) = metadata::lookup(&cmd_name) {
+            if !info.accepts(parts.len()) {
+                return Err(RedisError::WrongNumberOfArguments {
+                    command: cmd_name,
+                    expected: info.arity.unsigned_abs() as usize - 1,
+                    got: parts.len() - 1,
+                });
+            }
+        }
+
         // 获取参数
         let args: Vec<RespValue> = parts.into_iter().skip(1).collect();
 
@@ -101,7 +775,6 @@ impl Command {
             }
 
             "ECHO" => {
-                Self::require_args("ECHO", &args, 1)?;
                 let msg = args[0]
                     .as_string()
                     .ok_or_else(|| RedisError::TypeError("参数必须是字符串".to_string()))?;
@@ -110,48 +783,71 @@ impl Command {
 
             "QUIT" => Ok(Command::Quit),
 
+            "SELECT" => {
+                let index = Self::get_string(&args[0])?
+                    .parse()
+                    .map_err(|_| RedisError::Protocol("invalid DB index".to_string()))?;
+                Ok(Command::Select { index })
+            }
+
+            "AUTH" => match args.len() {
+                1 => Ok(Command::Auth {
+                    username: None,
+                    password: Self::get_string(&args[0])?,
+                }),
+                2 => Ok(Command::Auth {
+                    username: Some(Self::get_string(&args[0])?),
+                    password: Self::get_string(&args[1])?,
+                }),
+                got => Err(RedisError::WrongNumberOfArguments {
+                    command: "AUTH".to_string(),
+                    expected: 1,
+                    got,
+                }),
+            },
+
             // ===== 字符串命令 =====
             "GET" => {
-                Self::require_args("GET", &args, 1)?;
                 let key = Self::get_string(&args[0])?;
                 Ok(Command::Get { key })
             }
 
             "SET" => {
-                Self::require_min_args("SET", &args, 2)?;
                 let key = Self::get_string(&args[0])?;
                 let value = Self::get_bytes(&args[1])?;
 
-                // 解析可选参数
+                // 解析可选参数: 同一选项可以重复，互斥的选项(NX/XX、EX/PX/EXAT/PXAT/KEEPTTL)
+                // 同时出现是语法错误
                 let mut expiry = None;
+                let mut unit = None;
                 let mut nx = false;
                 let mut xx = false;
-                let mut i = 2;
-
-                while i < args.len() {
-                    let opt = args[i]
-                        .as_string()
-                        .ok_or_else(|| RedisError::Protocol("无效的选项".to_string()))?
-                        .to_uppercase();
+                let mut get = false;
+                let mut options = args[2..].iter();
 
+                while let Some(option) = options.next() {
+                    let opt = Self::get_string(option)?.to_uppercase();
                     match opt.as_str() {
-                        "EX" => {
-                            i += 1;
-                            let secs = Self::get_integer(&args[i])?;
-                            expiry = Some(Duration::from_secs(secs as u64));
-                        }
-                        "PX" => {
-                            i += 1;
-                            let ms = Self::get_integer(&args[i])?;
-                            expiry = Some(Duration::from_millis(ms as u64));
+                        "NX" if !xx => nx = true,
+                        "XX" if !nx => xx = true,
+                        "GET" => get = true,
+                        "KEEPTTL" if unit.as_deref().is_none_or(|u| u == opt) => {
+                            expiry = Some(SetExpiry::Keep);
+                            unit = Some(opt);
                         }
-                        "NX" => nx = true,
-                        "XX" => xx = true,
-                        _ => {
-                            return Err(RedisError::Protocol(format!("未知选项: {}", opt)));
+                        "EX" | "PX" | "EXAT" | "PXAT" if unit.as_deref().is_none_or(|u| u == opt) => {
+                            let value = options.next().ok_or_else(syntax_error)?;
+                            // EXAT/PXAT的时间戳与EX/PX的时长一样按秒或毫秒换算
+                            let amount = Self::parse_expire_time(value, &opt[..2], "set")?;
+                            expiry = Some(if opt.ends_with("AT") {
+                                SetExpiry::At(amount.as_millis() as u64)
+                            } else {
+                                SetExpiry::After(amount)
+                            });
+                            unit = Some(opt);
                         }
+                        _ => return Err(syntax_error()),
                     }
-                    i += 1;
                 }
 
                 Ok(Command::Set {
@@ -160,11 +856,30 @@ impl Command {
                     expiry,
                     nx,
                     xx,
+                    get,
+                })
+            }
+
+            // 旧版命令: SETNX回复整数，SETEX/PSETEX等同于带EX/PX的SET
+            "SETNX" => Ok(Command::SetNx {
+                key: Self::get_string(&args[0])?,
+                value: Self::get_bytes(&args[1])?,
+            }),
+
+            "SETEX" | "PSETEX" => {
+                let unit = if cmd == "SETEX" { "EX" } else { "PX" };
+                let ttl = Self::parse_expire_time(&args[1], unit, &cmd.to_lowercase())?;
+                Ok(Command::Set {
+                    key: Self::get_string(&args[0])?,
+                    expiry: Some(SetExpiry::After(ttl)),
+                    value: Self::get_bytes(&args[2])?,
+                    nx: false,
+                    xx: false,
+                    get: false,
                 })
             }
 
             "GETSET" => {
-                Self::require_args("GETSET", &args, 2)?;
                 Ok(Command::GetSet {
                     key: Self::get_string(&args[0])?,
                     value: Self::get_bytes(&args[1])?,
@@ -172,29 +887,50 @@ impl Command {
             }
 
             "APPEND" => {
-                Self::require_args("APPEND", &args, 2)?;
                 Ok(Command::Append {
                     key: Self::get_string(&args[0])?,
                     value: Self::get_bytes(&args[1])?,
                 })
             }
 
+            "SETRANGE" => {
+                let offset = args[1].as_integer().ok_or_else(not_an_integer)?;
+                let value = Self::get_bytes(&args[2])?;
+                let offset = usize::try_from(offset)
+                    .map_err(|_| RedisError::InvalidArgument("offset is out of range".to_string()))?;
+                // 与Redis一样，结果最长512MB，避免超大的offset分配内存
+                let end = offset.saturating_add(value.len()) as u64;
+                if !value.is_empty() && end > DEFAULT_MAX_VALUE_SIZE {
+                    return Err(RedisError::InvalidArgument(
+                        "string exceeds maximum allowed size (proto-max-bulk-len)".to_string(),
+                    ));
+                }
+                Ok(Command::SetRange {
+                    key: Self::get_string(&args[0])?,
+                    offset,
+                    value,
+                })
+            }
+
+            "GETRANGE" => Ok(Command::GetRange {
+                key: Self::get_string(&args[0])?,
+                start: args[1].as_integer().ok_or_else(not_an_integer)?,
+                end: args[2].as_integer().ok_or_else(not_an_integer)?,
+            }),
+
             "STRLEN" => {
-                Self::require_args("STRLEN", &args, 1)?;
                 Ok(Command::Strlen {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "INCR" => {
-                Self::require_args("INCR", &args, 1)?;
                 Ok(Command::Incr {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "INCRBY" => {
-                Self::require_args("INCRBY", &args, 2)?;
                 Ok(Command::IncrBy {
                     key: Self::get_string(&args[0])?,
                     delta: Self::get_integer(&args[1])?,
@@ -202,14 +938,12 @@ impl Command {
             }
 
             "DECR" => {
-                Self::require_args("DECR", &args, 1)?;
                 Ok(Command::Decr {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "DECRBY" => {
-                Self::require_args("DECRBY", &args, 2)?;
                 Ok(Command::DecrBy {
                     key: Self::get_string(&args[0])?,
                     delta: Self::get_integer(&args[1])?,
@@ -217,15 +951,14 @@ impl Command {
             }
 
             "MGET" => {
-                Self::require_min_args("MGET", &args, 1)?;
                 let keys: Result<Vec<_>, _> = args.iter().map(Self::get_string).collect();
                 Ok(Command::MGet { keys: keys? })
             }
 
-            "MSET" => {
-                if args.len() < 2 || args.len() % 2 != 0 {
+            "MSET" | "MSETNX" => {
+                if args.len() < 2 || !args.len().is_multiple_of(2) {
                     return Err(RedisError::WrongNumberOfArguments {
-                        command: "MSET".to_string(),
+                        command: cmd.to_string(),
                         expected: 2,
                         got: args.len(),
                     });
@@ -234,373 +967,6046 @@ impl Command {
                 for chunk in args.chunks(2) {
                     pairs.push((Self::get_string(&chunk[0])?, Self::get_bytes(&chunk[1])?));
                 }
-                Ok(Command::MSet { pairs })
+                match cmd {
+                    "MSET" => Ok(Command::MSet { pairs }),
+                    _ => Ok(Command::MSetNx { pairs }),
+                }
+            }
+
+            "BITOP" => {
+                let op = BitOp::parse(&Self::get_string(&args[0])?).ok_or_else(syntax_error)?;
+                let keys: Vec<String> =
+                    args[2..].iter().map(Self::get_string).collect::<Result<_, _>>()?;
+                if op == BitOp::Not && keys.len() != 1 {
+                    return Err(RedisError::InvalidArgument(
+                        "BITOP NOT must be called with a single source key.".to_string(),
+                    ));
+                }
+                Ok(Command::BitOp {
+                    op,
+                    destination: Self::get_string(&args[1])?,
+                    keys,
+                })
+            }
+
+            "BITFIELD" => {
+                let mut fields = Vec::new();
+                let mut overflow = Overflow::Wrap;
+                let mut i = 1;
+                while i < args.len() {
+                    let name = Self::get_string(&args[i])?.to_uppercase();
+                    let operands = &args[i + 1..];
+                    if name == "OVERFLOW" && !operands.is_empty() {
+                        overflow = Overflow::parse(&Self::get_string(&operands[0])?)?;
+                        i += 2;
+                        continue;
+                    }
+                    let (op, used) = match (name.as_str(), operands.len()) {
+                        ("GET", 2..) => (FieldOp::Get, 2),
+                        ("SET" | "INCRBY", 3..) => {
+                            let value = operands[2].as_integer().ok_or_else(not_an_integer)?;
+                            match name.as_str() {
+                                "SET" => (FieldOp::Set(value), 3),
+                                _ => (FieldOp::IncrBy(value), 3),
+                            }
+                        }
+                        _ => return Err(syntax_error()),
+                    };
+                    let field = FieldType::parse(&Self::get_string(&operands[0])?)?;
+                    fields.push(BitField {
+                        op,
+                        field,
+                        offset: bitops::parse_offset(&Self::get_string(&operands[1])?, field)?,
+                        overflow,
+                    });
+                    i += 1 + used;
+                }
+                Ok(Command::BitField {
+                    key: Self::get_string(&args[0])?,
+                    fields,
+                })
             }
 
             // ===== 键命令 =====
             "DEL" => {
-                Self::require_min_args("DEL", &args, 1)?;
                 let keys: Result<Vec<_>, _> = args.iter().map(Self::get_string).collect();
                 Ok(Command::Del { keys: keys? })
             }
 
             "EXISTS" => {
-                Self::require_min_args("EXISTS", &args, 1)?;
                 let keys: Result<Vec<_>, _> = args.iter().map(Self::get_string).collect();
                 Ok(Command::Exists { keys: keys? })
             }
 
-            "EXPIRE" => {
-                Self::require_args("EXPIRE", &args, 2)?;
-                Ok(Command::Expire {
-                    key: Self::get_string(&args[0])?,
-                    seconds: Self::get_integer(&args[1])? as u64,
+            // 四个命令共用换算和溢出检查；过期时间不晚于当前时间(包括负数)时执行时删除键，
+            // 与Redis一致
+            "EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" => {
+                let key = Self::get_string(&args[0])?;
+                let ms = Self::get_expire_ms(cmd, &args[1])?;
+                Ok(match cmd {
+                    "EXPIRE" => Command::Expire { key, milliseconds: ms },
+                    "PEXPIRE" => Command::PExpire { key, milliseconds: ms },
+                    "EXPIREAT" => Command::ExpireAt { key, at_ms: ms },
+                    _ => Command::PExpireAt { key, at_ms: ms },
                 })
             }
 
-            "PEXPIRE" => {
-                Self::require_args("PEXPIRE", &args, 2)?;
-                Ok(Command::PExpire {
-                    key: Self::get_string(&args[0])?,
-                    milliseconds: Self::get_integer(&args[1])? as u64,
-                })
-            }
+            "EXPIRETIME" => Ok(Command::ExpireTime {
+                key: Self::get_string(&args[0])?,
+            }),
+
+            "PEXPIRETIME" => Ok(Command::PExpireTime {
+                key: Self::get_string(&args[0])?,
+            }),
 
             "TTL" => {
-                Self::require_args("TTL", &args, 1)?;
                 Ok(Command::Ttl {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "PTTL" => {
-                Self::require_args("PTTL", &args, 1)?;
                 Ok(Command::PTtl {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "PERSIST" => {
-                Self::require_args("PERSIST", &args, 1)?;
                 Ok(Command::Persist {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "KEYS" => {
-                Self::require_args("KEYS", &args, 1)?;
                 Ok(Command::Keys {
                     pattern: Self::get_string(&args[0])?,
                 })
             }
 
             "TYPE" => {
-                Self::require_args("TYPE", &args, 1)?;
                 Ok(Command::Type {
                     key: Self::get_string(&args[0])?,
                 })
             }
 
             "RENAME" => {
-                Self::require_args("RENAME", &args, 2)?;
                 Ok(Command::Rename {
                     old_key: Self::get_string(&args[0])?,
                     new_key: Self::get_string(&args[1])?,
                 })
             }
 
-            // ===== 服务器命令 =====
-            "DBSIZE" => Ok(Command::DbSize),
+            "RENAMENX" => {
+                Ok(Command::RenameNx {
+                    old_key: Self::get_string(&args[0])?,
+                    new_key: Self::get_string(&args[1])?,
+                })
+            }
 
-            "FLUSHDB" | "FLUSHALL" => Ok(Command::FlushDb),
+            "SCAN" => {
+                let cursor = Self::get_string(&args[0])?
+                    .parse()
+                    .map_err(|_| RedisError::Protocol("invalid cursor".to_string()))?;
 
-            "INFO" => Ok(Command::Info),
+                // 解析可选参数
+                let mut pattern = None;
+                let mut count = 10;
+                let mut key_type = None;
+                for option in args[1..].chunks(2) {
+                    let [name, value] = option else {
+                        return Err(syntax_error());
+                    };
+                    match Self::get_string(name)?.to_uppercase().as_str() {
+                        "MATCH" => pattern = Some(Self::get_string(value)?),
+                        "COUNT" => {
+                            count = usize::try_from(Self::get_integer(value)?)
+                                .ok()
+                                .filter(|&n| n > 0)
+                                .ok_or_else(syntax_error)?
+                        }
+                        "TYPE" => key_type = Some(Self::get_string(value)?.to_lowercase()),
+                        _ => return Err(syntax_error()),
+                    }
+                }
 
-            // 未知命令
-            _ => Ok(Command::Unknown(cmd.to_string())),
-        }
-    }
+                Ok(Command::Scan {
+                    cursor,
+                    pattern,
+                    count,
+                    key_type,
+                })
+            }
 
-    /// 检查参数数量是否正确
-    fn require_args(cmd: &str, args: &[RespValue], expected: usize) -> RedisResult<()> {
-        if args.len() != expected {
-            Err(RedisError::WrongNumberOfArguments {
-                command: cmd.to_string(),
-                expected,
-                got: args.len(),
-            })
-        } else {
-            Ok(())
-        }
-    }
+            // ===== 哈希命令 =====
+            "HSET" => {
+                if args.len() % 2 != 1 {
+                    return Err(RedisError::WrongNumberOfArguments {
+                        command: cmd.to_string(),
+                        expected: 3,
+                        got: args.len(),
+                    });
+                }
+                let mut pairs = Vec::new();
+                for chunk in args[1..].chunks(2) {
+                    pairs.push((Self::get_bytes(&chunk[0])?, Self::get_bytes(&chunk[1])?));
+                }
+                Ok(Command::HSet {
+                    key: Self::get_string(&args[0])?,
+                    pairs,
+                })
+            }
 
-    /// 检查最少参数数量
-    fn require_min_args(cmd: &str, args: &[RespValue], min: usize) -> RedisResult<()> {
-        if args.len() < min {
-            Err(RedisError::WrongNumberOfArguments {
-                command: cmd.to_string(),
-                expected: min,
-                got: args.len(),
-            })
-        } else {
-            Ok(())
-        }
-    }
+            "HGET" | "HEXISTS" => {
+                let key = Self::get_string(&args[0])?;
+                let field = Self::get_bytes(&args[1])?;
+                match cmd {
+                    "HGET" => Ok(Command::HGet { key, field }),
+                    _ => Ok(Command::HExists { key, field }),
+                }
+            }
 
-    /// 从RESP值获取字符串
-    fn get_string(value: &RespValue) -> RedisResult<String> {
-        value
-            .as_string()
-            .ok_or_else(|| RedisError::TypeError("期望字符串".to_string()))
-    }
+            "HMGET" | "HDEL" => {
+                let key = Self::get_string(&args[0])?;
+                let fields: Result<Vec<_>, _> = args[1..].iter().map(Self::get_bytes).collect();
+                let fields = fields?;
+                match cmd {
+                    "HMGET" => Ok(Command::HMGet { key, fields }),
+                    _ => Ok(Command::HDel { key, fields }),
+                }
+            }
 
-    /// 从RESP值获取字节
-    fn get_bytes(value: &RespValue) -> RedisResult<Vec<u8>> {
-        match value {
-            RespValue::BulkString(data) => Ok(data.clone()),
-            RespValue::SimpleString(s) => Ok(s.as_bytes().to_vec()),
-            _ => Err(RedisError::TypeError("期望字符串".to_string())),
-        }
-    }
+            "HGETALL" | "HLEN" | "HKEYS" | "HVALS" => {
+                let key = Self::get_string(&args[0])?;
+                match cmd {
+                    "HGETALL" => Ok(Command::HGetAll { key }),
+                    "HLEN" => Ok(Command::HLen { key }),
+                    "HKEYS" => Ok(Command::HKeys { key }),
+                    _ => Ok(Command::HVals { key }),
+                }
+            }
 
-    /// 从RESP值获取整数
-    fn get_integer(value: &RespValue) -> RedisResult<i64> {
-        value
-            .as_integer()
-            .ok_or_else(|| RedisError::TypeError("期望整数".to_string()))
-    }
-}
+            "HRANDFIELD" | "ZRANDMEMBER" => {
+                let key = Self::get_string(&args[0])?;
+                let count = args
+                    .get(1)
+                    .map(|count| count.as_integer().ok_or_else(not_an_integer))
+                    .transpose()?;
+                let option = if cmd == "HRANDFIELD" { "WITHVALUES" } else { "WITHSCORES" };
+                let with = match args.get(2) {
+                    Some(arg) if Self::get_string(arg)?.eq_ignore_ascii_case(option) => true,
+                    Some(_) => return Err(syntax_error()),
+                    None => false,
+                };
+                if args.len() > 3 {
+                    return Err(syntax_error());
+                }
+                // 与Redis一致: 带值时回复的元素数是count的两倍，不能溢出
+                if with && count.is_some_and(|count| count < -(i64::MAX / 2)) {
+                    return Err(RedisError::InvalidArgument("value is out of range".to_string()));
+                }
+                if cmd == "HRANDFIELD" {
+                    Ok(Command::HRandField { key, count, with_values: with })
+                } else {
+                    Ok(Command::ZRandMember { key, count, with_scores: with })
+                }
+            }
 
-/// 命令执行器 - 实现命令执行逻辑
-///
-/// Rust特点: 结构体方法实现业务逻辑
-pub struct CommandExecutor<'a> {
-    store: &'a Store,
-}
+            "HINCRBY" => Ok(Command::HIncrBy {
+                key: Self::get_string(&args[0])?,
+                field: Self::get_bytes(&args[1])?,
+                delta: args[2].as_integer().ok_or_else(not_an_integer)?,
+            }),
 
-impl<'a> CommandExecutor<'a> {
-    /// 创建新的执行器
-    ///
-    /// Rust特点: 生命周期'a确保执行器不会比store活得更久
-    pub fn new(store: &'a Store) -> Self {
-        Self { store }
-    }
+            "HEXPIRE" | "HPEXPIRE" | "HEXPIREAT" | "HPEXPIREAT" => {
+                let millis = cmd.starts_with("HP");
+                let time = Self::get_integer(&args[1])?;
+                let time = u64::try_from(time).map_err(|_| {
+                    RedisError::InvalidArgument("invalid expire time, must be >= 0".to_string())
+                })?;
+                let time = if millis { Some(time) } else { time.checked_mul(1000) };
+                let time = time.filter(|&time| time <= hash::MAX_EXPIRES_AT).ok_or_else(|| {
+                    RedisError::InvalidArgument(format!(
+                        "invalid expire time in '{}' command",
+                        cmd.to_lowercase()
+                    ))
+                })?;
+                let condition = ExpireCondition::parse(&Self::get_string(&args[2])?);
+                let fields = if condition.is_some() { &args[3..] } else { &args[2..] };
+                Ok(Command::HExpire {
+                    key: Self::get_string(&args[0])?,
+                    time,
+                    millis,
+                    at: cmd.ends_with("AT"),
+                    condition,
+                    fields: Self::parse_hash_fields(fields)?,
+                })
+            }
 
-    /// 执行命令并返回响应
-    ///
-    /// Rust特点: 穷尽的模式匹配确保所有命令都被处理
-    pub fn execute(&self, cmd: Command) -> (RespValue, bool) {
-        let should_quit = matches!(cmd, Command::Quit);
+            "HTTL" | "HPTTL" => Ok(Command::HTtl {
+                key: Self::get_string(&args[0])?,
+                millis: cmd == "HPTTL",
+                fields: Self::parse_hash_fields(&args[1..])?,
+            }),
 
-        let response = match cmd {
-            // 连接命令
-            Command::Ping(msg) => match msg {
-                Some(m) => resp::bulk_string(&m),
-                None => resp::pong(),
-            },
+            "HPERSIST" => Ok(Command::HPersist {
+                key: Self::get_string(&args[0])?,
+                fields: Self::parse_hash_fields(&args[1..])?,
+            }),
 
-            Command::Echo(msg) => resp::bulk_string(&msg),
+            // ===== 列表命令 =====
+            "LPUSH" | "RPUSH" => {
+                let key = Self::get_string(&args[0])?;
+                let values: Result<Vec<_>, _> = args[1..].iter().map(Self::get_bytes).collect();
+                let values = values?;
+                match cmd {
+                    "LPUSH" => Ok(Command::LPush { key, values }),
+                    _ => Ok(Command::RPush { key, values }),
+                }
+            }
 
-            Command::Quit => resp::ok(),
+            "LPOP" | "RPOP" => {
+                if args.len() > 2 {
+                    return Err(syntax_error());
+                }
+                let count = Self::parse_pop_count(args.get(1))?;
+                let key = Self::get_string(&args[0])?;
+                match cmd {
+                    "LPOP" => Ok(Command::LPop { key, count }),
+                    _ => Ok(Command::RPop { key, count }),
+                }
+            }
 
-            // 字符串命令
-            Command::Get { key } => match self.store.get(&key) {
-                Some(data) => RespValue::BulkString(data),
-                None => RespValue::Null,
-            },
+            "LRANGE" => Ok(Command::LRange {
+                key: Self::get_string(&args[0])?,
+                start: args[1].as_integer().ok_or_else(not_an_integer)?,
+                stop: args[2].as_integer().ok_or_else(not_an_integer)?,
+            }),
 
-            Command::Set {
-                key,
-                value,
-                expiry,
-                nx,
-                xx,
-            } => {
-                // NX: 只在键不存在时设置
-                // XX: 只在键存在时设置
-                let exists = self.store.exists(&key);
+            "LLEN" => Ok(Command::LLen {
+                key: Self::get_string(&args[0])?,
+            }),
 
-                if (nx && exists) || (xx && !exists) {
-                    RespValue::Null
-                } else {
-                    match expiry {
-                        Some(ttl) => self.store.set_with_expiry(key, value, ttl),
-                        None => self.store.set(key, value),
+            "LINDEX" => Ok(Command::LIndex {
+                key: Self::get_string(&args[0])?,
+                index: args[1].as_integer().ok_or_else(not_an_integer)?,
+            }),
+
+            "LPOS" => {
+                let mut rank = 1;
+                let mut count = None;
+                let mut maxlen = 0;
+                for option in args[2..].chunks(2) {
+                    let [name, value] = option else {
+                        return Err(syntax_error());
+                    };
+                    let value = value.as_integer().ok_or_else(not_an_integer)?;
+                    match Self::get_string(name)?.to_uppercase().as_str() {
+                        // i64::MIN取反会溢出，与Redis一样拒绝
+                        "RANK" if value == 0 || value == i64::MIN => {
+                            return Err(RedisError::InvalidArgument(
+                                "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the last match".to_string(),
+                            ))
+                        }
+                        "RANK" => rank = value,
+                        "COUNT" => {
+                            count = Some(usize::try_from(value).map_err(|_| {
+                                RedisError::InvalidArgument("COUNT can't be negative".to_string())
+                            })?)
+                        }
+                        "MAXLEN" => {
+                            maxlen = usize::try_from(value).map_err(|_| {
+                                RedisError::InvalidArgument("MAXLEN can't be negative".to_string())
+                            })?
+                        }
+                        _ => return Err(syntax_error()),
                     }
-                    resp::ok()
                 }
+                Ok(Command::LPos {
+                    key: Self::get_string(&args[0])?,
+                    element: Self::get_bytes(&args[1])?,
+                    rank,
+                    count,
+                    maxlen,
+                })
             }
 
-            Command::GetSet { key, value } => {
-                let old = self.store.get(&key);
-                self.store.set(key, value);
-                match old {
-                    Some(data) => RespValue::BulkString(data),
-                    None => RespValue::Null,
-                }
+            "LINSERT" => {
+                let before = match Self::get_string(&args[1])?.to_uppercase().as_str() {
+                    "BEFORE" => true,
+                    "AFTER" => false,
+                    _ => return Err(syntax_error()),
+                };
+                Ok(Command::LInsert {
+                    key: Self::get_string(&args[0])?,
+                    before,
+                    pivot: Self::get_bytes(&args[2])?,
+                    element: Self::get_bytes(&args[3])?,
+                })
             }
 
-            Command::Append { key, value } => {
-                let len = self.store.append(&key, &value);
-                RespValue::Integer(len as i64)
-            }
+            "LSET" => Ok(Command::LSet {
+                key: Self::get_string(&args[0])?,
+                index: args[1].as_integer().ok_or_else(not_an_integer)?,
+                element: Self::get_bytes(&args[2])?,
+            }),
 
-            Command::Strlen { key } => {
-                let len = self.store.strlen(&key);
-                RespValue::Integer(len as i64)
-            }
+            "LREM" => Ok(Command::LRem {
+                key: Self::get_string(&args[0])?,
+                count: args[1].as_integer().ok_or_else(not_an_integer)?,
+                element: Self::get_bytes(&args[2])?,
+            }),
 
-            Command::Incr { key } => match self.store.incr(&key, 1) {
-                Ok(n) => RespValue::Integer(n),
-                Err(e) => resp::error(&e),
-            },
+            "LTRIM" => Ok(Command::LTrim {
+                key: Self::get_string(&args[0])?,
+                start: args[1].as_integer().ok_or_else(not_an_integer)?,
+                stop: args[2].as_integer().ok_or_else(not_an_integer)?,
+            }),
 
-            Command::IncrBy { key, delta } => match self.store.incr(&key, delta) {
-                Ok(n) => RespValue::Integer(n),
-                Err(e) => resp::error(&e),
-            },
+            "LMOVE" => Ok(Command::LMove {
+                source: Self::get_string(&args[0])?,
+                destination: Self::get_string(&args[1])?,
+                from_front: Self::parse_list_end(&args[2])?,
+                to_front: Self::parse_list_end(&args[3])?,
+            }),
 
-            Command::Decr { key } => match self.store.incr(&key, -1) {
-                Ok(n) => RespValue::Integer(n),
-                Err(e) => resp::error(&e),
-            },
+            "RPOPLPUSH" => Ok(Command::RPopLPush {
+                source: Self::get_string(&args[0])?,
+                destination: Self::get_string(&args[1])?,
+            }),
 
-            Command::DecrBy { key, delta } => match self.store.incr(&key, -delta) {
-                Ok(n) => RespValue::Integer(n),
-                Err(e) => resp::error(&e),
-            },
+            "BLPOP" | "BRPOP" => {
+                let (keys, timeout) = args.split_at(args.len() - 1);
+                Ok(Command::BPop {
+                    keys: keys.iter().map(Self::get_string).collect::<Result<_, _>>()?,
+                    front: cmd == "BLPOP",
+                    timeout: Self::parse_timeout(&timeout[0])?,
+                })
+            }
 
-            Command::MGet { keys } => {
-                let values: Vec<RespValue> = keys
-                    .iter()
-                    .map(|k| match self.store.get(k) {
-                        Some(data) => RespValue::BulkString(data),
-                        None => RespValue::Null,
-                    })
-                    .collect();
-                RespValue::Array(values)
+            "LMPOP" => {
+                let (keys, front, count) = Self::parse_mpop(&args, ["LEFT", "RIGHT"])?;
+                Ok(Command::LMPop { keys, front, count })
             }
 
-            Command::MSet { pairs } => {
-                for (key, value) in pairs {
-                    self.store.set(key, value);
+            "BLMPOP" => {
+                let (keys, front, count) = Self::parse_mpop(&args[1..], ["LEFT", "RIGHT"])?;
+                Ok(Command::BLMPop {
+                    keys,
+                    front,
+                    count,
+                    timeout: Self::parse_timeout(&args[0])?,
+                })
+            }
+
+            "BLMOVE" => Ok(Command::BLMove {
+                source: Self::get_string(&args[0])?,
+                destination: Self::get_string(&args[1])?,
+                from_front: Self::parse_list_end(&args[2])?,
+                to_front: Self::parse_list_end(&args[3])?,
+                timeout: Self::parse_timeout(&args[4])?,
+            }),
+
+            // ===== 集合命令 =====
+            "SADD" | "SREM" => {
+                let key = Self::get_string(&args[0])?;
+                let members: Result<Vec<_>, _> = args[1..].iter().map(Self::get_bytes).collect();
+                let members = members?;
+                match cmd {
+                    "SADD" => Ok(Command::SAdd { key, members }),
+                    _ => Ok(Command::SRem { key, members }),
                 }
-                resp::ok()
             }
 
-            // 键命令
-            Command::Del { keys } => {
-                let count = self.store.del_multi(&keys);
-                RespValue::Integer(count as i64)
+            "SMEMBERS" | "SCARD" => {
+                let key = Self::get_string(&args[0])?;
+                match cmd {
+                    "SMEMBERS" => Ok(Command::SMembers { key }),
+                    _ => Ok(Command::SCard { key }),
+                }
             }
 
-            Command::Exists { keys } => {
-                let count = self.store.exists_multi(&keys);
-                RespValue::Integer(count as i64)
+            "SISMEMBER" => Ok(Command::SIsMember {
+                key: Self::get_string(&args[0])?,
+                member: Self::get_bytes(&args[1])?,
+            }),
+
+            "SMISMEMBER" => Ok(Command::SMIsMember {
+                key: Self::get_string(&args[0])?,
+                members: args[1..].iter().map(Self::get_bytes).collect::<Result<_, _>>()?,
+            }),
+
+            "SPOP" => {
+                if args.len() > 2 {
+                    return Err(syntax_error());
+                }
+                Ok(Command::SPop {
+                    key: Self::get_string(&args[0])?,
+                    count: Self::parse_pop_count(args.get(1))?,
+                })
             }
 
-            Command::Expire { key, seconds } => {
-                let success = self.store.expire(&key, Duration::from_secs(seconds));
-                RespValue::Integer(if success { 1 } else { 0 })
+            "SRANDMEMBER" => {
+                if args.len() > 2 {
+                    return Err(syntax_error());
+                }
+                Ok(Command::SRandMember {
+                    key: Self::get_string(&args[0])?,
+                    count: args
+                        .get(1)
+                        .map(|count| count.as_integer().ok_or_else(not_an_integer))
+                        .transpose()?,
+                })
             }
 
-            Command::PExpire { key, milliseconds } => {
-                let success = self.store.expire(&key, Duration::from_millis(milliseconds));
-                RespValue::Integer(if success { 1 } else { 0 })
+            "SUNION" | "SINTER" | "SDIFF" => {
+                let keys: Result<Vec<_>, _> = args.iter().map(Self::get_string).collect();
+                let keys = keys?;
+                match cmd {
+                    "SUNION" => Ok(Command::SUnion { keys }),
+                    "SINTER" => Ok(Command::SInter { keys }),
+                    _ => Ok(Command::SDiff { keys }),
+                }
             }
 
-            Command::Ttl { key } => {
-                let ttl_ms = self.store.pttl(&key);
-                let ttl_s = if ttl_ms > 0 {
-                    ttl_ms / 1000
-                } else {
-                    ttl_ms
+            "SUNIONSTORE" | "SINTERSTORE" | "SDIFFSTORE" => {
+                let destination = Self::get_string(&args[0])?;
+                let keys: Result<Vec<_>, _> = args[1..].iter().map(Self::get_string).collect();
+                let keys = keys?;
+                match cmd {
+                    "SUNIONSTORE" => Ok(Command::SUnionStore { destination, keys }),
+                    "SINTERSTORE" => Ok(Command::SInterStore { destination, keys }),
+                    _ => Ok(Command::SDiffStore { destination, keys }),
+                }
+            }
+
+            "SINTERCARD" => {
+                let numkeys = Self::parse_numkeys(&args[0])?;
+                if numkeys > args.len() - 1 {
+                    return Err(RedisError::InvalidArgument(
+                        "Number of keys can't be greater than number of args".to_string(),
+                    ));
+                }
+                let keys =
+                    args[1..=numkeys].iter().map(Self::get_string).collect::<Result<_, _>>()?;
+                let limit = match &args[numkeys + 1..] {
+                    [] => 0,
+                    [option, limit] if Self::get_string(option)?.eq_ignore_ascii_case("LIMIT") => {
+                        Self::get_string(limit)?.parse::<usize>().map_err(|_| {
+                            RedisError::InvalidArgument("LIMIT can't be negative".to_string())
+                        })?
+                    }
+                    _ => return Err(syntax_error()),
                 };
-                RespValue::Integer(ttl_s)
+                Ok(Command::SInterCard { keys, limit })
             }
 
-            Command::PTtl { key } => {
-                let ttl = self.store.pttl(&key);
-                RespValue::Integer(ttl)
+            // ===== 有序集合命令 =====
+            "ZADD" => {
+                let mut options = ZAddOptions::default();
+                let mut rest = &args[1..];
+                while let Some(option) = rest.first() {
+                    match Self::get_string(option)?.to_uppercase().as_str() {
+                        "NX" => options.nx = true,
+                        "XX" => options.xx = true,
+                        "GT" => options.gt = true,
+                        "LT" => options.lt = true,
+                        "CH" => options.ch = true,
+                        "INCR" => options.incr = true,
+                        _ => break,
+                    }
+                    rest = &rest[1..];
+                }
+
+                if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                    return Err(syntax_error());
+                }
+                if options.nx && options.xx {
+                    return Err(RedisError::InvalidArgument(
+                        "XX and NX options at the same time are not compatible".to_string(),
+                    ));
+                }
+                if [options.nx, options.gt, options.lt].iter().filter(|&&set| set).count() > 1 {
+                    return Err(RedisError::InvalidArgument(
+                        "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+                    ));
+                }
+                if options.incr && rest.len() > 2 {
+                    return Err(RedisError::InvalidArgument(
+                        "INCR option supports a single increment-element pair".to_string(),
+                    ));
+                }
+
+                let mut pairs = Vec::new();
+                for chunk in rest.chunks(2) {
+                    pairs.push((zset::parse_score(&Self::get_bytes(&chunk[0])?)?, Self::get_bytes(&chunk[1])?));
+                }
+                Ok(Command::ZAdd {
+                    key: Self::get_string(&args[0])?,
+                    options,
+                    pairs,
+                })
             }
 
-            Command::Persist { key } => {
-                let success = self.store.persist(&key);
-                RespValue::Integer(if success { 1 } else { 0 })
+            "ZSCORE" | "ZRANK" | "ZREVRANK" => {
+                let key = Self::get_string(&args[0])?;
+                let member = Self::get_bytes(&args[1])?;
+                match cmd {
+                    "ZSCORE" => Ok(Command::ZScore { key, member }),
+                    "ZRANK" => Ok(Command::ZRank { key, member }),
+                    _ => Ok(Command::ZRevRank { key, member }),
+                }
             }
 
-            Command::Keys { pattern } => {
-                let keys = self.store.keys(&pattern);
-                RespValue::Array(
-                    keys.into_iter()
-                        .map(|k| RespValue::BulkString(k.into_bytes()))
-                        .collect(),
-                )
+            "ZINCRBY" => Ok(Command::ZIncrBy {
+                key: Self::get_string(&args[0])?,
+                delta: zset::parse_score(&Self::get_bytes(&args[1])?)?,
+                member: Self::get_bytes(&args[2])?,
+            }),
+
+            "ZRANGE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE" | "ZRANGEBYLEX"
+            | "ZREVRANGEBYLEX" => {
+                let (query, with_scores) = Self::parse_range_query(cmd, &args[1..])?;
+                Ok(Command::ZRange {
+                    name: metadata::lookup(cmd).map_or("ZRANGE", |info| info.name),
+                    key: Self::get_string(&args[0])?,
+                    query,
+                    with_scores,
+                })
             }
 
-            Command::Type { key } => match self.store.key_type(&key) {
-                Some(t) => RespValue::SimpleString(t.to_string()),
-                None => RespValue::SimpleString("none".to_string()),
-            },
+            "ZRANGESTORE" => Ok(Command::ZRangeStore {
+                destination: Self::get_string(&args[0])?,
+                source: Self::get_string(&args[1])?,
+                query: Self::parse_range_query(cmd, &args[2..])?.0,
+            }),
 
-            Command::Rename { old_key, new_key } => {
-                if self.store.rename(&old_key, &new_key) {
-                    resp::ok()
-                } else {
-                    resp::error("ERR no such key")
+            "ZCARD" => Ok(Command::ZCard {
+                key: Self::get_string(&args[0])?,
+            }),
+
+            "ZREM" => {
+                let members: Result<Vec<_>, _> = args[1..].iter().map(Self::get_bytes).collect();
+                Ok(Command::ZRem {
+                    key: Self::get_string(&args[0])?,
+                    members: members?,
+                })
+            }
+
+            "BZPOPMIN" | "BZPOPMAX" => {
+                let (keys, timeout) = args.split_at(args.len() - 1);
+                Ok(Command::BZPop {
+                    keys: keys.iter().map(Self::get_string).collect::<Result<_, _>>()?,
+                    min: cmd == "BZPOPMIN",
+                    timeout: Self::parse_timeout(&timeout[0])?,
+                })
+            }
+
+            "ZMPOP" => {
+                let (keys, min, count) = Self::parse_mpop(&args, ["MIN", "MAX"])?;
+                Ok(Command::ZMPop { keys, min, count })
+            }
+
+            "BZMPOP" => {
+                let (keys, min, count) = Self::parse_mpop(&args[1..], ["MIN", "MAX"])?;
+                Ok(Command::BZMPop {
+                    keys,
+                    min,
+                    count,
+                    timeout: Self::parse_timeout(&args[0])?,
+                })
+            }
+
+            // ===== 流命令 =====
+            "XADD" => {
+                let mut no_mkstream = false;
+                let mut trim = None;
+                let mut i = 1;
+                loop {
+                    match Self::get_string(&args[i])?.to_uppercase().as_str() {
+                        "NOMKSTREAM" => {
+                            no_mkstream = true;
+                            i += 1;
+                        }
+                        "MAXLEN" | "MINID" => {
+                            let (parsed, used) = Self::parse_trim(&args[i..])?;
+                            trim = Some(parsed);
+                            i += used;
+                        }
+                        _ => break,
+                    }
+                    if i >= args.len() {
+                        return Err(syntax_error());
+                    }
                 }
+                let id = IdSpec::parse(&Self::get_bytes(&args[i])?)?;
+                let rest = &args[i + 1..];
+                if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                    return Err(RedisError::WrongNumberOfArguments {
+                        command: cmd.to_string(),
+                        expected: 4,
+                        got: args.len(),
+                    });
+                }
+                let mut fields = Vec::new();
+                for chunk in rest.chunks(2) {
+                    fields.push((Self::get_bytes(&chunk[0])?, Self::get_bytes(&chunk[1])?));
+                }
+                Ok(Command::XAdd {
+                    key: Self::get_string(&args[0])?,
+                    no_mkstream,
+                    trim,
+                    id,
+                    fields,
+                })
             }
 
-            // 服务器命令
-            Command::DbSize => RespValue::Integer(self.store.dbsize() as i64),
+            "XLEN" => Ok(Command::XLen {
+                key: Self::get_string(&args[0])?,
+            }),
 
-            Command::FlushDb => {
-                self.store.flushdb();
-                resp::ok()
+            "XRANGE" | "XREVRANGE" => {
+                let rev = cmd == "XREVRANGE";
+                let count = match &args[3..] {
+                    [] => None,
+                    [option, count] if Self::get_string(option)?.eq_ignore_ascii_case("COUNT") => {
+                        // 与Redis一致: 负数按0处理
+                        let count = count.as_integer().ok_or_else(not_an_integer)?;
+                        Some(usize::try_from(count).unwrap_or(0))
+                    }
+                    _ => return Err(syntax_error()),
+                };
+                // XREVRANGE先给出终点
+                let (start, end) = if rev { (&args[2], &args[1]) } else { (&args[1], &args[2]) };
+                Ok(Command::XRange {
+                    key: Self::get_string(&args[0])?,
+                    start: stream::parse_range_start(&Self::get_bytes(start)?)?,
+                    end: stream::parse_range_end(&Self::get_bytes(end)?)?,
+                    count,
+                    rev,
+                })
             }
 
-            Command::Info => {
-                let info = format!(
-                    "# Server\r\n\
-                     redis_version:0.1.0\r\n\
-                     rust_version:{}\r\n\
-                     # Keyspace\r\n\
-                     db0:keys={}\r\n",
-                    env!("CARGO_PKG_VERSION"),
-                    self.store.dbsize()
-                );
-                RespValue::BulkString(info.into_bytes())
+            "XTRIM" => {
+                let (trim, used) = Self::parse_trim(&args[1..])?;
+                if used + 1 != args.len() {
+                    return Err(syntax_error());
+                }
+                Ok(Command::XTrim {
+                    key: Self::get_string(&args[0])?,
+                    trim,
+                })
             }
 
-            Command::Unknown(cmd) => {
-                resp::error(&format!("ERR unknown command '{}'", cmd))
+            "XREAD" => {
+                let mut count = None;
+                let mut i = 0;
+                loop {
+                    let option = Self::get_string(&args[i])?.to_uppercase();
+                    match option.as_str() {
+                        "COUNT" if i + 1 < args.len() => {
+                            // 0和负数表示不限制
+                            let n = args[i + 1].as_integer().ok_or_else(not_an_integer)?;
+                            count = usize::try_from(n).ok().filter(|&n| n > 0);
+                            i += 2;
+                        }
+                        "BLOCK" => {
+                            return Err(RedisError::InvalidArgument(
+                                "XREAD BLOCK is not supported".to_string(),
+                            ))
+                        }
+                        "STREAMS" => {
+                            i += 1;
+                            break;
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                    if i >= args.len() {
+                        return Err(syntax_error());
+                    }
+                }
+
+                let rest = &args[i..];
+                if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                    return Err(RedisError::InvalidArgument(
+                        "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified."
+                            .to_string(),
+                    ));
+                }
+                let (keys, ids) = rest.split_at(rest.len() / 2);
+                let mut streams = Vec::new();
+                for (key, id) in keys.iter().zip(ids) {
+                    let id = match Self::get_bytes(id)?.as_slice() {
+                        b"$" => None,
+                        id => Some(StreamId::parse(id, 0)?),
+                    };
+                    streams.push((Self::get_string(key)?, id));
+                }
+                Ok(Command::XRead { count, streams })
             }
-        };
 
-        (response, should_quit)
-    }
-}
+            "XGROUP" => {
+                let name = match Self::subcommand("XGROUP", &args)? {
+                    None => return Ok(Command::Help { container: "XGROUP" }),
+                    Some(name) => name,
+                };
+                let key = Self::get_string(&args[1])?;
+                let group = Self::get_string(&args[2])?;
+                match name {
+                    "CREATE" => {
+                        let mkstream = match &args[4..] {
+                            [] => false,
+                            [option] => match Self::get_string(option)?.to_uppercase().as_str() {
+                                "MKSTREAM" => true,
+                                _ => return Err(syntax_error()),
+                            },
+                            _ => return Err(syntax_error()),
+                        };
+                        Ok(Command::XGroupCreate {
+                            key,
+                            group,
+                            id: Self::parse_group_id(&args[3])?,
+                            mkstream,
+                        })
+                    }
+                    "SETID" => Ok(Command::XGroupSetId {
+                        key,
+                        group,
+                        id: Self::parse_group_id(&args[3])?,
+                    }),
+                    "DESTROY" => Ok(Command::XGroupDestroy { key, group }),
+                    "CREATECONSUMER" => Ok(Command::XGroupCreateConsumer {
+                        key,
+                        group,
+                        consumer: Self::get_string(&args[3])?,
+                    }),
+                    "DELCONSUMER" => Ok(Command::XGroupDelConsumer {
+                        key,
+                        group,
+                        consumer: Self::get_string(&args[3])?,
+                    }),
+                    name => Err(unknown_subcommand("XGROUP", name)),
+                }
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+            "XREADGROUP" => {
+                if !Self::get_string(&args[0])?.eq_ignore_ascii_case("GROUP") {
+                    return Err(syntax_error());
+                }
+                let group = Self::get_string(&args[1])?;
+                let consumer = Self::get_string(&args[2])?;
+                let (mut count, mut noack) = (None, false);
+                let mut i = 3;
+                loop {
+                    let option = Self::get_string(args.get(i).ok_or_else(syntax_error)?)?;
+                    match option.to_uppercase().as_str() {
+                        "COUNT" if i + 1 < args.len() => {
+                            let n = args[i + 1].as_integer().ok_or_else(not_an_integer)?;
+                            count = usize::try_from(n).ok().filter(|&n| n > 0);
+                            i += 2;
+                        }
+                        "NOACK" => {
+                            noack = true;
+                            i += 1;
+                        }
+                        "BLOCK" => {
+                            return Err(RedisError::InvalidArgument(
+                                "XREADGROUP BLOCK is not supported".to_string(),
+                            ))
+                        }
+                        "STREAMS" => {
+                            i += 1;
+                            break;
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
 
-    #[test]
-    fn test_parse_ping() {
-        let value = RespValue::Array(vec![RespValue::BulkString(b"PING".to_vec())]);
-        let cmd = Command::from_resp(value).unwrap();
-        assert!(matches!(cmd, Command::Ping(None)));
-    }
+                let rest = &args[i..];
+                if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                    return Err(RedisError::InvalidArgument(
+                        "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified."
+                            .to_string(),
+                    ));
+                }
+                let (keys, ids) = rest.split_at(rest.len() / 2);
+                let mut streams = Vec::new();
+                for (key, id) in keys.iter().zip(ids) {
+                    let id = match Self::get_bytes(id)?.as_slice() {
+                        b">" => None,
+                        b"$" => {
+                            return Err(RedisError::InvalidArgument(
+                                "The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set."
+                                    .to_string(),
+                            ))
+                        }
+                        id => Some(StreamId::parse(id, 0)?),
+                    };
+                    streams.push((Self::get_string(key)?, id));
+                }
+                Ok(Command::XReadGroup {
+                    group,
+                    consumer,
+                    count,
+                    noack,
+                    streams,
+                })
+            }
 
-    #[test]
-    fn test_parse_set() {
-        let value = RespValue::Array(vec![
-            RespValue::BulkString(b"SET".to_vec()),
-            RespValue::BulkString(b"key".to_vec()),
-            RespValue::BulkString(b"value".to_vec()),
-        ]);
-        let cmd = Command::from_resp(value).unwrap();
-        assert!(matches!(cmd, Command::Set { .. }));
+            "XACK" => Ok(Command::XAck {
+                key: Self::get_string(&args[0])?,
+                group: Self::get_string(&args[1])?,
+                ids: args[2..]
+                    .iter()
+                    .map(|id| StreamId::parse(&Self::get_bytes(id)?, 0))
+                    .collect::<Result<_, _>>()?,
+            }),
+
+            "XPENDING" => {
+                let query = match &args[2..] {
+                    [] => None,
+                    rest => Some(Self::parse_pending_query(rest)?),
+                };
+                Ok(Command::XPending {
+                    key: Self::get_string(&args[0])?,
+                    group: Self::get_string(&args[1])?,
+                    query,
+                })
+            }
+
+            "XCLAIM" => {
+                let min_idle = args[3].as_integer().ok_or_else(|| {
+                    RedisError::InvalidArgument(
+                        "Invalid min-idle-time argument for XCLAIM".to_string(),
+                    )
+                })?;
+                // 与Redis一致: 解析ID直到遇到第一个不是ID的参数，之后都是选项
+                let mut ids = Vec::new();
+                let mut i = 4;
+                while let Some(id) = args.get(i).and_then(|id| {
+                    StreamId::parse(&Self::get_bytes(id).ok()?, 0).ok()
+                }) {
+                    ids.push(id);
+                    i += 1;
+                }
+                let mut options = ClaimOptions {
+                    min_idle: min_idle.max(0) as u64,
+                    ..ClaimOptions::default()
+                };
+                while i < args.len() {
+                    let option = Self::get_string(&args[i])?;
+                    let value = args.get(i + 1);
+                    match option.to_uppercase().as_str() {
+                        "FORCE" => options.force = true,
+                        "JUSTID" => options.just_id = true,
+                        "IDLE" | "TIME" | "RETRYCOUNT" if value.is_some() => {
+                            let n = value.and_then(RespValue::as_integer);
+                            let n = n.ok_or_else(not_an_integer)?.max(0) as u64;
+                            match option.to_uppercase().as_str() {
+                                "IDLE" => options.idle = Some(n),
+                                "TIME" => options.time = Some(n),
+                                _ => options.retry_count = Some(n),
+                            }
+                            i += 1;
+                        }
+                        "LASTID" if value.is_some() => {
+                            let id = Self::get_bytes(&args[i + 1])?;
+                            options.last_id = Some(StreamId::parse(&id, 0)?);
+                            i += 1;
+                        }
+                        _ => {
+                            return Err(RedisError::InvalidArgument(format!(
+                                "Unrecognized XCLAIM option '{}'",
+                                option
+                            )))
+                        }
+                    }
+                    i += 1;
+                }
+                Ok(Command::XClaim {
+                    key: Self::get_string(&args[0])?,
+                    group: Self::get_string(&args[1])?,
+                    consumer: Self::get_string(&args[2])?,
+                    ids,
+                    options,
+                })
+            }
+
+            // ===== JSON命令 =====
+            "JSON.SET" => {
+                let value = Self::parse_json(&args[2])?;
+                let (mut nx, mut xx) = (false, false);
+                match args.get(3).map(Self::get_string).transpose()?.map(|s| s.to_uppercase()).as_deref() {
+                    None => {}
+                    Some("NX") => nx = true,
+                    Some("XX") => xx = true,
+                    Some(_) => return Err(syntax_error()),
+                }
+                if args.len() > 4 {
+                    return Err(syntax_error());
+                }
+                Ok(Command::JsonSet {
+                    key: Self::get_string(&args[0])?,
+                    path: JsonPath::parse(&Self::get_string(&args[1])?)?,
+                    value,
+                    nx,
+                    xx,
+                })
+            }
+
+            "JSON.GET" => {
+                let paths = args[1..]
+                    .iter()
+                    .map(|arg| JsonPath::parse(&Self::get_string(arg)?))
+                    .collect::<RedisResult<_>>()?;
+                Ok(Command::JsonGet {
+                    key: Self::get_string(&args[0])?,
+                    paths,
+                })
+            }
+
+            "JSON.DEL" | "JSON.TYPE" => {
+                if args.len() > 2 {
+                    return Err(syntax_error());
+                }
+                let key = Self::get_string(&args[0])?;
+                let path = match args.get(1) {
+                    Some(path) => JsonPath::parse(&Self::get_string(path)?)?,
+                    // 省略路径时: JSON.DEL删除整个键，JSON.TYPE按旧式路径返回单个类型
+                    None if cmd == "JSON.DEL" => JsonPath::root(),
+                    None => JsonPath::parse(".")?,
+                };
+                match cmd {
+                    "JSON.DEL" => Ok(Command::JsonDel { key, path }),
+                    _ => Ok(Command::JsonType { key, path }),
+                }
+            }
+
+            "JSON.NUMINCRBY" => {
+                let by = match Self::parse_json(&args[2])? {
+                    serde_json::Value::Number(by) => by,
+                    other => {
+                        return Err(RedisError::InvalidArgument(format!(
+                            "expected a number but found {}",
+                            json::type_name(&other)
+                        )))
+                    }
+                };
+                Ok(Command::JsonNumIncrBy {
+                    key: Self::get_string(&args[0])?,
+                    path: JsonPath::parse(&Self::get_string(&args[1])?)?,
+                    by,
+                })
+            }
+
+            "JSON.ARRAPPEND" => Ok(Command::JsonArrAppend {
+                key: Self::get_string(&args[0])?,
+                path: JsonPath::parse(&Self::get_string(&args[1])?)?,
+                values: args[2..].iter().map(Self::parse_json).collect::<RedisResult<_>>()?,
+            }),
+
+            // ===== 时间序列命令 =====
+            "TS.ADD" => {
+                let timestamp = match Self::get_string(&args[1])?.as_str() {
+                    "*" => None,
+                    ts => Some(ts.parse().map_err(|_| tsdb_error("invalid timestamp"))?),
+                };
+                let value = Self::get_string(&args[2])?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .ok_or_else(|| tsdb_error("invalid value"))?;
+
+                let mut retention = None;
+                let mut labels = Vec::new();
+                let mut options = args[3..].iter();
+                while let Some(option) = options.next() {
+                    match Self::get_string(option)?.to_uppercase().as_str() {
+                        "RETENTION" => {
+                            let value = options.next().map(Self::get_string).transpose()?;
+                            retention = Some(
+                                value
+                                    .and_then(|v| v.parse().ok())
+                                    .ok_or_else(|| tsdb_error("invalid retention"))?,
+                            );
+                        }
+                        // LABELS必须是最后一个选项，后面都是标签名和值
+                        "LABELS" => {
+                            let rest: Vec<String> =
+                                options.by_ref().map(Self::get_string).collect::<RedisResult<_>>()?;
+                            if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                                return Err(tsdb_error("failed parsing labels"));
+                            }
+                            labels = rest.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+
+                Ok(Command::TsAdd {
+                    key: Self::get_string(&args[0])?,
+                    timestamp,
+                    value,
+                    retention,
+                    labels,
+                })
+            }
+
+            "TS.RANGE" => {
+                let range = Self::parse_ts_range(&args[1..])?;
+                if range.with_labels || !range.filters.is_empty() {
+                    return Err(syntax_error());
+                }
+                Ok(Command::TsRange {
+                    key: Self::get_string(&args[0])?,
+                    from: range.from,
+                    to: range.to,
+                    aggregation: range.aggregation,
+                })
+            }
+
+            "TS.GET" => Ok(Command::TsGet {
+                key: Self::get_string(&args[0])?,
+            }),
+
+            "TS.MRANGE" => {
+                let range = Self::parse_ts_range(&args)?;
+                if range.filters.is_empty() {
+                    return Err(tsdb_error("missing FILTER argument"));
+                }
+                Ok(Command::TsMRange {
+                    from: range.from,
+                    to: range.to,
+                    aggregation: range.aggregation,
+                    with_labels: range.with_labels,
+                    filters: range.filters,
+                })
+            }
+
+            // ===== 布隆过滤器命令 =====
+            "BF.RESERVE" => {
+                let error_rate = Self::get_string(&args[1])?
+                    .parse()
+                    .map_err(|_| RedisError::InvalidArgument("bad error rate".to_string()))?;
+                let capacity = Self::get_string(&args[2])?
+                    .parse()
+                    .map_err(|_| RedisError::InvalidArgument("bad capacity".to_string()))?;
+
+                let mut expansion = DEFAULT_EXPANSION;
+                let mut options = args[3..].iter();
+                while let Some(option) = options.next() {
+                    match Self::get_string(option)?.to_uppercase().as_str() {
+                        "NONSCALING" => expansion = 0,
+                        "EXPANSION" => {
+                            let value = options.next().map(Self::get_string).transpose()?;
+                            expansion = value
+                                .and_then(|v| v.parse().ok())
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| RedisError::InvalidArgument("bad expansion".to_string()))?;
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+
+                Ok(Command::BfReserve {
+                    key: Self::get_string(&args[0])?,
+                    error_rate,
+                    capacity,
+                    expansion,
+                })
+            }
+
+            // ===== 概率数据结构的`key item`命令 =====
+            "BF.ADD" | "BF.EXISTS" | "CF.ADD" | "CF.EXISTS" | "CF.DEL" => {
+                let key = Self::get_string(&args[0])?;
+                let item = Self::get_bytes(&args[1])?;
+                match cmd {
+                    "BF.ADD" => Ok(Command::BfAdd { key, item }),
+                    "BF.EXISTS" => Ok(Command::BfExists { key, item }),
+                    "CF.ADD" => Ok(Command::CfAdd { key, item }),
+                    "CF.EXISTS" => Ok(Command::CfExists { key, item }),
+                    _ => Ok(Command::CfDel { key, item }),
+                }
+            }
+
+            "BF.MADD" => {
+                let items: Result<Vec<_>, _> = args[1..].iter().map(Self::get_bytes).collect();
+                Ok(Command::BfMAdd {
+                    key: Self::get_string(&args[0])?,
+                    items: items?,
+                })
+            }
+
+            // ===== 限流命令 =====
+            "CL.THROTTLE" => {
+                if args.len() > 5 {
+                    return Err(syntax_error());
+                }
+                let number = |value: &RespValue| -> RedisResult<u64> {
+                    u64::try_from(Self::get_integer(value)?).map_err(|_| not_an_integer())
+                };
+                let quantity = args.get(4).map(number).transpose()?.unwrap_or(1);
+                let period = Duration::from_secs(number(&args[3])?);
+                Ok(Command::ClThrottle {
+                    key: Self::get_string(&args[0])?,
+                    throttle: Throttle::new(number(&args[1])?, number(&args[2])?, period)?,
+                    quantity,
+                })
+            }
+
+            // ===== 分布式锁命令 =====
+            "LOCK" => Ok(Command::Lock {
+                key: Self::get_string(&args[0])?,
+                token: Self::get_bytes(&args[1])?,
+                ttl: Self::parse_expire_time(&args[2], "PX", "lock")?,
+            }),
+
+            "UNLOCK" => Ok(Command::Unlock {
+                key: Self::get_string(&args[0])?,
+                token: Self::get_bytes(&args[1])?,
+            }),
+
+            // ===== 事务命令 =====
+            "MULTI" => Ok(Command::Multi),
+
+            "EXEC" => Ok(Command::Exec),
+
+            "DISCARD" => Ok(Command::Discard),
+
+            "WATCH" => {
+                let keys: Result<Vec<_>, _> = args.iter().map(Self::get_string).collect();
+                Ok(Command::Watch { keys: keys? })
+            }
+
+            "UNWATCH" => Ok(Command::Unwatch),
+
+            // ===== 服务器命令 =====
+            "DBSIZE" => Ok(Command::DbSize),
+
+            // 只有一个数据库，FLUSHALL与FLUSHDB等价
+            "FLUSHDB" | "FLUSHALL" => {
+                let lazy = match args.as_slice() {
+                    [] => false,
+                    [mode] => match Self::get_string(mode)?.to_uppercase().as_str() {
+                        "ASYNC" => true,
+                        "SYNC" => false,
+                        _ => return Err(syntax_error()),
+                    },
+                    _ => return Err(syntax_error()),
+                };
+                Ok(Command::FlushDb { lazy })
+            }
+
+            "INFO" => Ok(Command::Info),
+
+            // ===== 容器命令: 子命令名和参数个数由subcommand统一检查 =====
+            "CONFIG" => match Self::subcommand("CONFIG", &args)? {
+                None => Ok(Command::Help { container: "CONFIG" }),
+                Some("RELOAD") => Ok(Command::ConfigReload),
+                Some("SET") => Ok(Command::ConfigSet {
+                    name: Self::get_string(&args[1])?,
+                    value: Self::get_string(&args[2])?,
+                }),
+                Some(name) => Err(unknown_subcommand("CONFIG", name)),
+            },
+
+            "CLIENT" => match Self::subcommand("CLIENT", &args)? {
+                None => Ok(Command::Help { container: "CLIENT" }),
+                Some("REPLY") => {
+                    let mode = match Self::get_string(&args[1])?.to_uppercase().as_str() {
+                        "ON" => ReplyMode::On,
+                        "OFF" => ReplyMode::Off,
+                        "SKIP" => ReplyMode::Skip,
+                        _ => return Err(syntax_error()),
+                    };
+                    Ok(Command::ClientReply { mode })
+                }
+                Some(name) => Err(unknown_subcommand("CLIENT", name)),
+            },
+
+            "MEMORY" => match Self::subcommand("MEMORY", &args)? {
+                None => Ok(Command::Help { container: "MEMORY" }),
+                Some("USAGE") => Ok(Command::MemoryUsage {
+                    key: Self::get_string(&args[1])?,
+                }),
+                Some(name) => Err(unknown_subcommand("MEMORY", name)),
+            },
+
+            "OBJECT" => match Self::subcommand("OBJECT", &args)? {
+                None => Ok(Command::Help { container: "OBJECT" }),
+                Some("ENCODING") => Ok(Command::ObjectEncoding {
+                    key: Self::get_string(&args[1])?,
+                }),
+                Some(name) => Err(unknown_subcommand("OBJECT", name)),
+            },
+
+            "COMMAND" if args.is_empty() => Ok(Command::CommandTable),
+
+            "COMMAND" => match Self::subcommand("COMMAND", &args)? {
+                None => Ok(Command::Help { container: "COMMAND" }),
+                Some("COUNT") => Ok(Command::CommandCount),
+                Some("LIST") => Ok(Command::CommandList),
+                Some("INFO") => Ok(Command::CommandInfo {
+                    names: args[1..].iter().map(Self::get_string).collect::<Result<_, _>>()?,
+                }),
+                Some("GETKEYS") => Ok(Command::CommandGetKeys {
+                    argv: args[1..].iter().map(Self::get_string).collect::<Result<_, _>>()?,
+                }),
+                Some(name) => Err(unknown_subcommand("COMMAND", name)),
+            },
+
+            "MODULE" => match Self::subcommand("MODULE", &args)? {
+                None => Ok(Command::Help { container: "MODULE" }),
+                Some("LOAD") => Ok(Command::ModuleLoad {
+                    path: Self::get_string(&args[1])?,
+                    args: args[2..].iter().map(Self::get_string).collect::<Result<_, _>>()?,
+                }),
+                Some("UNLOAD") => Ok(Command::ModuleUnload {
+                    name: Self::get_string(&args[1])?,
+                }),
+                Some("LIST") => Ok(Command::ModuleList),
+                Some(name) => Err(unknown_subcommand("MODULE", name)),
+            },
+
+            // 未知命令，可能由模块实现
+            _ => Ok(Command::Unknown {
+                name: cmd.to_string(),
+                args: args.iter().map(Self::get_bytes).collect::<Result<_, _>>()?,
+            }),
+        }
+    }
+
+    /// 解析容器命令的子命令名
+    ///
+    /// 子命令必须在`metadata::SUBCOMMANDS`中登记，参数个数按表中的arity检查；
+    /// 解析阻塞命令的超时时间(秒，可以有小数)，0表示一直等待，返回None
+    fn parse_timeout(value: &RespValue) -> RedisResult<Option<Duration>> {
+        let timeout = Self::get_string(value)?
+            .parse::<f64>()
+            .ok()
+            .filter(|timeout| timeout.is_finite())
+            .ok_or_else(|| {
+                RedisError::InvalidArgument("timeout is not a float or out of range".to_string())
+            })?;
+        if timeout < 0.0 {
+            return Err(RedisError::InvalidArgument("timeout is negative".to_string()));
+        }
+        Ok((timeout > 0.0).then(|| Duration::from_secs_f64(timeout)))
+    }
+
+    /// 解析`numkeys key [key ...] <端> [COUNT count]`，`ends`是两端的名字，第一个返回true
+    fn parse_mpop(args: &[RespValue], ends: [&str; 2]) -> RedisResult<(Vec<String>, bool, usize)> {
+        let numkeys = Self::parse_numkeys(&args[0])?;
+        if args.len() < numkeys + 2 {
+            return Err(syntax_error());
+        }
+        let keys = args[1..=numkeys].iter().map(Self::get_string).collect::<Result<_, _>>()?;
+        let end = Self::get_string(&args[numkeys + 1])?.to_uppercase();
+        let first = match ends.iter().position(|name| *name == end) {
+            Some(i) => i == 0,
+            None => return Err(syntax_error()),
+        };
+        let count = match &args[numkeys + 2..] {
+            [] => 1,
+            [option, count] if Self::get_string(option)?.eq_ignore_ascii_case("COUNT") => {
+                Self::get_string(count)?
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&count| count > 0)
+                    .ok_or_else(|| {
+                        RedisError::InvalidArgument("count should be greater than 0".to_string())
+                    })?
+            }
+            _ => return Err(syntax_error()),
+        };
+        Ok((keys, first, count))
+    }
+
+    /// 解析HEXPIRE等命令的`FIELDS numfields field [field ...]`
+    fn parse_hash_fields(args: &[RespValue]) -> RedisResult<Vec<Vec<u8>>> {
+        let position_error = || {
+            RedisError::InvalidArgument(
+                "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+            )
+        };
+        let [keyword, numfields, fields @ ..] = args else {
+            return Err(position_error());
+        };
+        if !Self::get_string(keyword)?.eq_ignore_ascii_case("FIELDS") {
+            return Err(position_error());
+        }
+        let numfields = Self::get_string(numfields)?
+            .parse::<usize>()
+            .ok()
+            .filter(|&numfields| numfields > 0)
+            .ok_or_else(|| {
+                RedisError::InvalidArgument("Parameter `numFields` should be greater than 0".to_string())
+            })?;
+        if numfields != fields.len() {
+            return Err(RedisError::InvalidArgument(
+                "The `numfields` parameter must match the number of arguments".to_string(),
+            ));
+        }
+        fields.iter().map(Self::get_bytes).collect()
+    }
+
+    /// 解析LPOP/RPOP/SPOP可选的count参数，不能是负数
+    fn parse_pop_count(value: Option<&RespValue>) -> RedisResult<Option<usize>> {
+        value
+            .map(|value| {
+                let count = value.as_integer().ok_or_else(not_an_integer)?;
+                usize::try_from(count).map_err(|_| {
+                    RedisError::InvalidArgument("value is out of range, must be positive".to_string())
+                })
+            })
+            .transpose()
+    }
+
+    /// 解析numkeys参数，必须是正整数
+    /// 解析JSON文本参数
+    fn parse_json(value: &RespValue) -> RedisResult<serde_json::Value> {
+        serde_json::from_slice(&Self::get_bytes(value)?)
+            .map_err(|e| RedisError::InvalidArgument(format!("invalid JSON: {}", e)))
+    }
+
+    fn parse_numkeys(value: &RespValue) -> RedisResult<usize> {
+        Self::get_string(value)?
+            .parse::<usize>()
+            .ok()
+            .filter(|&numkeys| numkeys > 0)
+            .ok_or_else(|| RedisError::InvalidArgument("numkeys should be greater than 0".to_string()))
+    }
+
+    /// 解析LEFT|RIGHT，LEFT返回true
+    fn parse_list_end(value: &RespValue) -> RedisResult<bool> {
+        match Self::get_string(value)?.to_uppercase().as_str() {
+            "LEFT" => Ok(true),
+            "RIGHT" => Ok(false),
+            _ => Err(syntax_error()),
+        }
+    }
+
+    /// 解析XGROUP CREATE/SETID的ID，`$`表示流的最后一个ID，返回None
+    fn parse_group_id(value: &RespValue) -> RedisResult<Option<StreamId>> {
+        match Self::get_bytes(value)?.as_slice() {
+            b"$" => Ok(None),
+            id => StreamId::parse(id, 0).map(Some),
+        }
+    }
+
+    /// 解析XPENDING的扩展形式`[IDLE min-idle-time] start end count [consumer]`
+    fn parse_pending_query(args: &[RespValue]) -> RedisResult<PendingQuery> {
+        let (min_idle, args) = match args {
+            [option, idle, rest @ ..] if Self::get_string(option)?.eq_ignore_ascii_case("IDLE") => {
+                let idle = idle.as_integer().ok_or_else(not_an_integer)?;
+                (Some(idle.max(0) as u64), rest)
+            }
+            _ => (None, args),
+        };
+        let (start, end, count, consumer) = match args {
+            [start, end, count] => (start, end, count, None),
+            [start, end, count, consumer] => (start, end, count, Some(Self::get_string(consumer)?)),
+            _ => return Err(syntax_error()),
+        };
+        // 与Redis一致: 负数按0处理
+        let count = count.as_integer().ok_or_else(not_an_integer)?;
+        Ok(PendingQuery {
+            min_idle,
+            start: stream::parse_range_start(&Self::get_bytes(start)?)?,
+            end: stream::parse_range_end(&Self::get_bytes(end)?)?,
+            count: usize::try_from(count).unwrap_or(0),
+            consumer,
+        })
+    }
+
+    /// 解析XADD/XTRIM的`MAXLEN|MINID [=|~] threshold [LIMIT count]`，返回裁剪参数和用掉的参数个数
+    fn parse_trim(args: &[RespValue]) -> RedisResult<(Trim, usize)> {
+        let by_len = Self::get_string(&args[0])?.eq_ignore_ascii_case("MAXLEN");
+        let mut i = 1;
+        let approximate = match args.get(i).map(Self::get_bytes).transpose()?.as_deref() {
+            Some(b"~") => {
+                i += 1;
+                true
+            }
+            Some(b"=") => {
+                i += 1;
+                false
+            }
+            _ => false,
+        };
+        let threshold = args.get(i).ok_or_else(syntax_error)?;
+        let strategy = if by_len {
+            match threshold.as_integer().ok_or_else(not_an_integer)? {
+                max if max < 0 => {
+                    return Err(RedisError::InvalidArgument(
+                        "The MAXLEN argument must be >= 0.".to_string(),
+                    ))
+                }
+                max => TrimStrategy::MaxLen(max as u64),
+            }
+        } else {
+            TrimStrategy::MinId(StreamId::parse(&Self::get_bytes(threshold)?, 0)?)
+        };
+        i += 1;
+
+        let mut limit = None;
+        if let Some(option) = args.get(i) {
+            if Self::get_string(option)?.eq_ignore_ascii_case("LIMIT") {
+                if !approximate {
+                    return Err(RedisError::InvalidArgument(
+                        "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+                    ));
+                }
+                let count = args.get(i + 1).ok_or_else(syntax_error)?;
+                match count.as_integer().ok_or_else(not_an_integer)? {
+                    count if count < 0 => {
+                        return Err(RedisError::InvalidArgument(
+                            "The LIMIT argument must be >= 0.".to_string(),
+                        ))
+                    }
+                    count => limit = Some(count as u64),
+                }
+                i += 2;
+            }
+        }
+        Ok((Trim { strategy, limit }, i))
+    }
+
+    /// 解析ZRANGE系列命令中键之后的部分: 两个端点和选项，返回查询和是否WITHSCORES
+    ///
+    /// ZRANGE和ZRANGESTORE用BYSCORE/BYLEX和REV选择方式和方向，其余命令名本身就确定了两者。
+    /// 倒序按分数或字典序查询时，先给出的是上限。
+    fn parse_range_query(cmd: &str, args: &[RespValue]) -> RedisResult<(RangeQuery, bool)> {
+        let (mut by_score, mut by_lex, mut rev) = match cmd {
+            "ZREVRANGE" => (false, false, true),
+            "ZRANGEBYSCORE" => (true, false, false),
+            "ZREVRANGEBYSCORE" => (true, false, true),
+            "ZRANGEBYLEX" => (false, true, false),
+            "ZREVRANGEBYLEX" => (false, true, true),
+            _ => (false, false, false),
+        };
+        let flexible = matches!(cmd, "ZRANGE" | "ZRANGESTORE");
+        let mut with_scores = false;
+        let mut limit = None;
+
+        let mut i = 2;
+        while i < args.len() {
+            let option = Self::get_string(&args[i])?.to_uppercase();
+            match option.as_str() {
+                // ZRANGEBYLEX和ZRANGESTORE没有WITHSCORES
+                "WITHSCORES" if cmd != "ZRANGESTORE" && (flexible || !by_lex) => with_scores = true,
+                "LIMIT" if i + 2 < args.len() => {
+                    let offset = args[i + 1].as_integer().ok_or_else(not_an_integer)?;
+                    let count = args[i + 2].as_integer().ok_or_else(not_an_integer)?;
+                    limit = Some((offset, count));
+                    i += 2;
+                }
+                "BYSCORE" if flexible => by_score = true,
+                "BYLEX" if flexible => by_lex = true,
+                "REV" if flexible => rev = true,
+                _ => return Err(syntax_error()),
+            }
+            i += 1;
+        }
+
+        if by_score && by_lex {
+            return Err(syntax_error());
+        }
+        if limit.is_some() && !by_score && !by_lex {
+            return Err(RedisError::InvalidArgument(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            ));
+        }
+        if with_scores && by_lex {
+            return Err(RedisError::InvalidArgument(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+            ));
+        }
+
+        let (first, second) = if rev && (by_score || by_lex) {
+            (&args[1], &args[0])
+        } else {
+            (&args[0], &args[1])
+        };
+        let by = if by_score {
+            RangeBy::Score {
+                min: ScoreBound::parse(&Self::get_bytes(first)?)?,
+                max: ScoreBound::parse(&Self::get_bytes(second)?)?,
+            }
+        } else if by_lex {
+            RangeBy::Lex {
+                min: LexBound::parse(&Self::get_bytes(first)?)?,
+                max: LexBound::parse(&Self::get_bytes(second)?)?,
+            }
+        } else {
+            RangeBy::Rank {
+                start: first.as_integer().ok_or_else(not_an_integer)?,
+                stop: second.as_integer().ok_or_else(not_an_integer)?,
+            }
+        };
+        Ok((RangeQuery { by, rev, limit }, with_scores))
+    }
+
+    /// HELP由这里统一识别，返回None。返回的名字是表中的大写形式，便于直接match。
+    fn subcommand(container: &str, args: &[RespValue]) -> RedisResult<Option<&'static str>> {
+        let name = Self::get_string(&args[0])?;
+        if name.eq_ignore_ascii_case("HELP") {
+            return Ok(None);
+        }
+
+        let info = metadata::lookup_subcommand(container, &name)
+            .ok_or_else(|| unknown_subcommand(container, &name))?;
+        // arity包括容器命令和子命令本身
+        if !info.accepts(args.len() + 1) {
+            return Err(RedisError::WrongNumberOfArguments {
+                command: format!("{}|{}", container, info.name).to_lowercase(),
+                expected: info.arity.unsigned_abs() as usize - 2,
+                got: args.len() - 1,
+            });
+        }
+        Ok(Some(info.name))
+    }
+
+    /// 从RESP值获取字符串
+    fn get_string(value: &RespValue) -> RedisResult<String> {
+        value
+            .as_string()
+            .ok_or_else(|| RedisError::TypeError("期望字符串".to_string()))
+    }
+
+    /// 解析TS.RANGE/TS.MRANGE共用的参数:
+    /// `from to [WITHLABELS] [AGGREGATION aggregator bucket] [FILTER filter ...]`
+    ///
+    /// `-`和`+`分别表示最早和最晚的时间戳；FILTER必须是最后一个选项
+    fn parse_ts_range(args: &[RespValue]) -> RedisResult<TsRangeArgs> {
+        let timestamp = |value: &RespValue| -> RedisResult<u64> {
+            match Self::get_string(value)?.as_str() {
+                "-" => Ok(0),
+                "+" => Ok(u64::MAX),
+                ts => ts.parse().map_err(|_| tsdb_error("invalid timestamp")),
+            }
+        };
+        let (from, to) = (timestamp(&args[0])?, timestamp(&args[1])?);
+
+        let mut aggregation = None;
+        let mut with_labels = false;
+        let mut filters = Vec::new();
+        let mut options = args[2..].iter();
+        while let Some(option) = options.next() {
+            match Self::get_string(option)?.to_uppercase().as_str() {
+                "WITHLABELS" => with_labels = true,
+                "AGGREGATION" => {
+                    let (Some(aggregator), Some(bucket)) = (options.next(), options.next()) else {
+                        return Err(syntax_error());
+                    };
+                    let bucket = Self::get_string(bucket)?
+                        .parse()
+                        .ok()
+                        .filter(|&b| b > 0)
+                        .ok_or_else(|| tsdb_error("invalid bucket duration"))?;
+                    aggregation = Some(Aggregation {
+                        aggregator: Self::get_string(aggregator)?.parse()?,
+                        bucket,
+                    });
+                }
+                "FILTER" => {
+                    filters = options
+                        .by_ref()
+                        .map(|f| Self::get_string(f)?.parse())
+                        .collect::<RedisResult<_>>()?;
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(TsRangeArgs {
+            from,
+            to,
+            aggregation,
+            with_labels,
+            filters,
+        })
+    }
+
+    /// 从RESP值获取字节
+    fn get_bytes(value: &RespValue) -> RedisResult<Vec<u8>> {
+        match value {
+            RespValue::BulkString(data) => Ok(data.clone()),
+            RespValue::SimpleString(s) => Ok(s.as_bytes().to_vec()),
+            _ => Err(RedisError::TypeError("期望字符串".to_string())),
+        }
+    }
+
+    /// 解析EX/PX等选项的过期时间，必须是正整数
+    fn parse_expire_time(value: &RespValue, unit: &str, command: &str) -> RedisResult<Duration> {
+        let amount = value.as_integer().ok_or_else(not_an_integer)?;
+        let invalid = || RedisError::InvalidArgument(format!("invalid expire time in '{}' command", command));
+        if amount <= 0 {
+            return Err(invalid());
+        }
+        match unit {
+            "EX" => amount.checked_mul(1000).map(|ms| Duration::from_millis(ms as u64)).ok_or_else(invalid),
+            _ => Ok(Duration::from_millis(amount as u64)),
+        }
+    }
+
+    /// 从RESP值获取整数
+    fn get_integer(value: &RespValue) -> RedisResult<i64> {
+        value
+            .as_integer()
+            .ok_or_else(|| RedisError::TypeError("期望整数".to_string()))
+    }
+
+    /// EXPIRE系列命令的时间参数换算为毫秒: P开头的命令以毫秒为单位，其余以秒为单位
+    fn get_expire_ms(cmd: &str, value: &RespValue) -> RedisResult<i64> {
+        let time = Self::get_integer(value)?;
+        if cmd.starts_with('P') {
+            return Ok(time);
+        }
+        time.checked_mul(1000).ok_or_else(|| invalid_expire_time(cmd))
+    }
+}
+
+/// TS.RANGE/TS.MRANGE解析出的参数
+struct TsRangeArgs {
+    from: u64,
+    to: u64,
+    aggregation: Option<Aggregation>,
+    with_labels: bool,
+    filters: Vec<LabelFilter>,
+}
+
+/// JSON.GET的结果文本
+///
+/// 没有路径时返回整个文档；一个路径时返回它的结果；多个路径时返回以路径为键的对象。
+/// JSONPath的结果是所有匹配组成的数组，旧式路径只取第一个匹配、没有匹配时报错
+fn json_get(doc: &serde_json::Value, paths: &[JsonPath]) -> RedisResult<String> {
+    let legacy = paths.iter().all(JsonPath::is_legacy);
+    let result = |path: &JsonPath| -> RedisResult<serde_json::Value> {
+        let matches = path.select(doc);
+        if legacy {
+            matches.first().map(|v| (*v).clone()).ok_or_else(|| {
+                RedisError::InvalidArgument(format!("Path '{}' does not exist", path.as_str()))
+            })
+        } else {
+            Ok(serde_json::Value::Array(matches.into_iter().cloned().collect()))
+        }
+    };
+
+    let value = match paths {
+        [] => doc.clone(),
+        [path] => result(path)?,
+        paths => {
+            let mut object = serde_json::Map::new();
+            for path in paths {
+                object.insert(path.as_str().to_string(), result(path)?);
+            }
+            serde_json::Value::Object(object)
+        }
+    };
+    Ok(value.to_string())
+}
+
+/// JSON.NUMINCRBY/JSON.ARRAPPEND对路径匹配的节点逐个修改，`f`对类型不符的节点返回None
+///
+/// 键不存在时报错。旧式路径只返回第一个匹配的结果，没有匹配或类型不符时报错
+fn json_update<T>(
+    doc: &mut Option<serde_json::Value>,
+    path: &JsonPath,
+    expected: &str,
+    f: impl FnMut(&mut serde_json::Value) -> Option<T>,
+) -> RedisResult<(Vec<Option<T>>, bool)> {
+    let doc = doc.as_mut().ok_or_else(|| {
+        RedisError::InvalidArgument(
+            "could not perform this operation on a key that doesn't exist".to_string(),
+        )
+    })?;
+    let mut results = path.update(doc, f);
+    let modified = results.iter().any(Option::is_some);
+    if path.is_legacy() {
+        match results.first() {
+            None => {
+                return Err(RedisError::InvalidArgument(format!(
+                    "Path '{}' does not exist",
+                    path.as_str()
+                )))
+            }
+            Some(None) => {
+                let found = path.select(doc).first().map_or("null", |v| json::type_name(v));
+                return Err(RedisError::InvalidArgument(format!(
+                    "wrong type of path value - expected {} but found {}",
+                    expected, found
+                )));
+            }
+            Some(Some(_)) => results.truncate(1),
+        }
+    }
+    Ok((results, modified))
+}
+
+/// 时间序列样本的回复: 每个样本是[时间戳, 值]
+fn samples_reply(samples: Vec<(u64, f64)>) -> RespValue {
+    RespValue::Array(
+        samples
+            .into_iter()
+            .map(|(ts, v)| {
+                RespValue::Array(vec![RespValue::Integer(ts as i64), resp::bulk_string(&v.to_string())])
+            })
+            .collect(),
+    )
+}
+
+/// 布尔结果的整数回复(1/0)，出错时回复错误
+fn bool_reply(result: RedisResult<bool>) -> RespValue {
+    match result {
+        Ok(b) => RespValue::Integer(b as i64),
+        Err(e) => error_reply(e),
+    }
+}
+
+/// LPOS: 按`rank`的方向查找等于`element`的元素，跳过前|rank|-1个匹配，最多返回`count`个下标
+///
+/// 只检查`maxlen`个元素；`count`和`maxlen`为0表示不限制
+fn lpos(list: &ListValue, element: &[u8], rank: i64, count: usize, maxlen: usize) -> Vec<usize> {
+    let count = if count == 0 { usize::MAX } else { count };
+    let maxlen = if maxlen == 0 { usize::MAX } else { maxlen };
+    let skip = (rank.unsigned_abs() - 1) as usize;
+    let last = list.len().saturating_sub(1);
+    // Rust特点: 两个方向的迭代器类型不同，装箱后统一处理
+    let items: Box<dyn Iterator<Item = (usize, &Vec<u8>)>> = if rank > 0 {
+        Box::new(list.iter().enumerate())
+    } else {
+        Box::new(list.iter().rev().enumerate().map(|(i, item)| (last - i, item)))
+    };
+    items
+        .take(maxlen)
+        .filter(|(_, item)| item.as_slice() == element)
+        .skip(skip)
+        .take(count)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// 多个集合的并集
+fn set_union(sets: &[&SetValue]) -> SetValue {
+    sets.iter().flat_map(|set| set.iter().cloned()).collect()
+}
+
+/// 多个集合的交集: 只遍历第一个集合，检查成员是否在其余每个集合中
+fn set_intersection(sets: &[&SetValue]) -> SetValue {
+    let Some((first, rest)) = sets.split_first() else {
+        return SetValue::new();
+    };
+    first.iter().filter(|member| rest.iter().all(|set| set.contains(member))).cloned().collect()
+}
+
+/// 第一个集合与其余集合的差集
+fn set_difference(sets: &[&SetValue]) -> SetValue {
+    let Some((first, rest)) = sets.split_first() else {
+        return SetValue::new();
+    };
+    first.iter().filter(|member| !rest.iter().any(|set| set.contains(member))).cloned().collect()
+}
+
+/// 整数数组回复(HEXPIRE/HTTL/HPERSIST)
+fn integers_reply(result: RedisResult<Vec<i64>>) -> RespValue {
+    match result {
+        Ok(values) => RespValue::Array(values.into_iter().map(RespValue::Integer).collect()),
+        Err(e) => error_reply(e),
+    }
+}
+
+/// 集合成员组成的批量字符串数组，顺序不确定
+fn members_reply(members: SetValue) -> RespValue {
+    RespValue::Array(members.into_iter().map(RespValue::BulkString).collect())
+}
+
+/// 分数以批量字符串回复，格式与Redis相同
+fn score_reply(score: f64) -> RespValue {
+    RespValue::BulkString(zset::format_score(score).into_bytes())
+}
+
+/// XRANGE/XREAD的条目回复: 每个条目是`[ID, [字段, 值, ...]]`
+fn stream_entries(entries: Vec<(StreamId, &Fields)>) -> RespValue {
+    RespValue::Array(
+        entries
+            .into_iter()
+            .map(|(id, fields)| stream_entry(id, Some(fields)))
+            .collect(),
+    )
+}
+
+/// 流中的一个条目，字段为None(XREADGROUP重读时条目已被删除)时回复`[ID, nil]`
+fn stream_entry(id: StreamId, fields: Option<&Fields>) -> RespValue {
+    let fields = match fields {
+        Some(fields) => RespValue::Array(
+            fields
+                .iter()
+                .flat_map(|(field, value)| [field.clone(), value.clone()])
+                .map(RespValue::BulkString)
+                .collect(),
+        ),
+        None => RespValue::Null,
+    };
+    RespValue::Array(vec![RespValue::BulkString(id.to_string().into_bytes()), fields])
+}
+
+/// XPENDING的摘要: `[条目数, 最小ID, 最大ID, [[消费者, 条目数], ...]]`，没有待确认条目时后三项为nil
+fn pending_summary(group: &ConsumerGroup) -> RespValue {
+    let mut ids = group.pending(StreamId::MIN, StreamId::MAX, None).map(|(id, _)| id);
+    let (Some(first), last) = (ids.next(), ids.last()) else {
+        return RespValue::Array(vec![
+            RespValue::Integer(0),
+            RespValue::Null,
+            RespValue::Null,
+            RespValue::Null,
+        ]);
+    };
+    let consumers = group
+        .pending_per_consumer()
+        .into_iter()
+        .map(|(name, count)| {
+            RespValue::Array(vec![resp::bulk_string(name), resp::bulk_string(&count.to_string())])
+        })
+        .collect();
+    RespValue::Array(vec![
+        RespValue::Integer(group.pending_len() as i64),
+        RespValue::BulkString(first.to_string().into_bytes()),
+        RespValue::BulkString(last.unwrap_or(first).to_string().into_bytes()),
+        RespValue::Array(consumers),
+    ])
+}
+
+/// XPENDING的扩展形式: 每个条目是`[ID, 消费者, 闲置毫秒数, 投递次数]`
+fn pending_entries(group: &ConsumerGroup, query: &PendingQuery, now: u64) -> RespValue {
+    RespValue::Array(
+        group
+            .pending(query.start, query.end, query.consumer.as_deref())
+            .map(|(id, entry)| (id, entry, now.saturating_sub(entry.delivered_at)))
+            .filter(|(_, _, idle)| query.min_idle.is_none_or(|min_idle| *idle >= min_idle))
+            .take(query.count)
+            .map(|(id, entry, idle)| {
+                RespValue::Array(vec![
+                    RespValue::BulkString(id.to_string().into_bytes()),
+                    resp::bulk_string(&entry.consumer),
+                    RespValue::Integer(idle as i64),
+                    RespValue::Integer(entry.delivery_count as i64),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// XGROUP要求键存在时的错误
+fn xgroup_missing_key() -> RedisError {
+    RedisError::InvalidArgument(
+        "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+            .to_string(),
+    )
+}
+
+/// XGROUP SETID/CREATECONSUMER/DELCONSUMER的消费者组不存在
+fn no_such_group(key: &str, group: &str) -> RedisError {
+    RedisError::Coded {
+        code: "NOGROUP",
+        message: format!("No such consumer group '{}' for key name '{}'", group, key),
+    }
+}
+
+/// XREADGROUP/XPENDING/XCLAIM的键或消费者组不存在，`context`附加在错误信息末尾
+fn no_such_key_or_group(key: &str, group: &str, context: &str) -> RedisError {
+    RedisError::Coded {
+        code: "NOGROUP",
+        message: format!("No such key '{}' or consumer group '{}'{}", key, group, context),
+    }
+}
+
+/// ZRANGE系列命令的回复: 成员数组，WITHSCORES时每个成员后面跟着它的分数
+fn zrange_reply(items: Vec<(Vec<u8>, f64)>, with_scores: bool) -> RespValue {
+    let mut reply = Vec::with_capacity(items.len() * if with_scores { 2 } else { 1 });
+    for (member, score) in items {
+        reply.push(RespValue::BulkString(member));
+        if with_scores {
+            reply.push(score_reply(score));
+        }
+    }
+    RespValue::Array(reply)
+}
+
+/// 执行出错时的回复: WRONGTYPE和带错误码的错误自带前缀，其他错误加ERR前缀
+fn error_reply(e: RedisError) -> RespValue {
+    match e {
+        RedisError::WrongType | RedisError::Coded { .. } => resp::error(&e.to_string()),
+        e => resp::error(&format!("ERR {}", e)),
+    }
+}
+
+/// COMMAND输出中的一项: 名称、arity、标志、第一个键、最后一个键、键间隔
+fn command_entry(info: &metadata::CommandInfo) -> RespValue {
+    RespValue::Array(vec![
+        resp::bulk_string(&info.name.to_lowercase()),
+        RespValue::Integer(info.arity as i64),
+        RespValue::Array(
+            info.flags
+                .names()
+                .map(|flag| RespValue::SimpleString(flag.to_string()))
+                .collect(),
+        ),
+        RespValue::Integer(info.keys.first as i64),
+        RespValue::Integer(info.keys.last as i64),
+        RespValue::Integer(info.keys.step as i64),
+    ])
+}
+
+/// 未知子命令错误，提示使用HELP
+fn unknown_subcommand(container: &str, name: &str) -> RedisError {
+    RedisError::InvalidArgument(format!("unknown subcommand '{}'. Try {} HELP.", name, container))
+}
+
+/// Redis的"syntax error"错误
+fn syntax_error() -> RedisError {
+    RedisError::InvalidArgument("syntax error".to_string())
+}
+
+/// EXPIRE系列命令的过期时间溢出时的错误
+fn invalid_expire_time(cmd: &str) -> RedisError {
+    RedisError::InvalidArgument(format!("invalid expire time in '{}' command", cmd.to_lowercase()))
+}
+
+/// Redis的"value is not an integer or out of range"错误
+fn not_an_integer() -> RedisError {
+    RedisError::InvalidArgument("value is not an integer or out of range".to_string())
+}
+
+/// 命令执行器 - 实现命令执行逻辑
+///
+/// Rust特点: 结构体方法实现业务逻辑
+pub struct CommandExecutor<'a> {
+    store: &'a Store,
+    /// 执行时间预算的截止时刻；None表示不限制
+    deadline: Option<Instant>,
+    /// 写入大小限制
+    limits: Limits,
+}
+
+impl<'a> CommandExecutor<'a> {
+    /// 创建新的执行器
+    ///
+    /// Rust特点: 生命周期'a确保执行器不会比store活得更久
+    pub fn new(store: &'a Store) -> Self {
+        Self {
+            store,
+            deadline: None,
+            limits: Limits::default(),
+        }
+    }
+
+    /// 设置执行时间预算
+    ///
+    /// 只有可以安全中止的只读遍历(如KEYS)会在超出预算时放弃并返回BUSY错误，
+    /// 写命令执行到一半无法回滚，总是执行完毕
+    pub fn with_budget(mut self, budget: Option<Duration>) -> Self {
+        self.deadline = budget.map(|budget| Instant::now() + budget);
+        self
+    }
+
+    /// 设置写入大小限制(max-key-size等)
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 检查写命令的键和值是否超出限制
+    ///
+    /// 写入前读取的字符串长度不计入命中率统计；集合的元素数不在这里检查，
+    /// 而是在执行时的写锁内检查，避免检查和写入之间被其他连接插入
+    fn check_limits(&self, cmd: &Command) -> RedisResult<()> {
+        let limits = &self.limits;
+        let writes = metadata::lookup(cmd.name()).is_some_and(|info| info.flags.contains(CommandFlags::WRITE));
+        if limits.is_unlimited() || !writes {
+            return Ok(());
+        }
+
+        for key in cmd.keys() {
+            limits.check_key(key)?;
+        }
+        match cmd {
+            Command::Set { value, .. } | Command::SetNx { value, .. } | Command::GetSet { value, .. } => {
+                limits.check_value(value.len())
+            }
+            Command::Lock { token, .. } => limits.check_value(token.len()),
+            Command::BitField { key, fields } => {
+                let written = fields.iter().filter(|field| field.writes());
+                let required = written.map(BitField::required_len).max().unwrap_or(0);
+                limits.check_value(required.max(self.store.peek_strlen(key)))
+            }
+            Command::LInsert { element, .. } => limits.check_value(element.len()),
+            Command::LSet { element, .. } => limits.check_value(element.len()),
+            Command::Append { key, value } => {
+                limits.check_value(self.store.peek_strlen(key) + value.len())
+            }
+            Command::SetRange { key, offset, value } if !value.is_empty() => {
+                limits.check_value(self.store.peek_strlen(key).max(offset + value.len()))
+            }
+            Command::MSet { pairs } | Command::MSetNx { pairs } => {
+                pairs.iter().try_for_each(|(_, value)| limits.check_value(value.len()))
+            }
+            // 集合的元素数在执行时于同一把写锁内检查，见各命令的update_as闭包
+            Command::HSet { pairs, .. } => {
+                pairs.iter().try_for_each(|(_, value)| limits.check_value(value.len()))
+            }
+            Command::LPush { values, .. } | Command::RPush { values, .. } => {
+                values.iter().try_for_each(|value| limits.check_value(value.len()))
+            }
+            Command::SAdd { members, .. } => {
+                members.iter().try_for_each(|member| limits.check_value(member.len()))
+            }
+            Command::ZAdd { pairs, .. } => {
+                pairs.iter().try_for_each(|(_, member)| limits.check_value(member.len()))
+            }
+            Command::XAdd { fields, .. } => {
+                fields.iter().try_for_each(|(_, value)| limits.check_value(value.len()))
+            }
+            // 写入后文档中容器的大小在执行时检查
+            Command::JsonSet { value, .. } => {
+                limits.check_value(value.to_string().len())?;
+                limits.check_elements(json::max_elements(value))
+            }
+            Command::JsonArrAppend { values, .. } => {
+                limits.check_value(values.iter().map(|v| v.to_string().len()).sum())?;
+                values.iter().try_for_each(|v| limits.check_elements(json::max_elements(v)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 执行命令并返回响应
+    ///
+    /// Rust特点: 穷尽的模式匹配确保所有命令都被处理
+    pub fn execute(&self, cmd: Command) -> (RespValue, bool) {
+        let should_quit = matches!(cmd, Command::Quit);
+        if let Err(e) = self.check_limits(&cmd) {
+            return (error_reply(e), should_quit);
+        }
+
+        let response = match cmd {
+            // 连接命令
+            Command::Ping(msg) => match msg {
+                Some(m) => resp::bulk_string(&m),
+                None => resp::pong(),
+            },
+
+            // 只有一个数据库，与databases为1的Redis行为一致
+            Command::Select { index: 0 } => resp::ok(),
+            Command::Select { .. } => resp::error("ERR DB index is out of range"),
+
+            Command::Echo(msg) => resp::bulk_string(&msg),
+
+            Command::Quit => resp::ok(),
+
+            // 字符串命令
+            Command::Get { key } => match self.store.get_checked(&key) {
+                Ok(Some(data)) => RespValue::BulkString(data),
+                Ok(None) => RespValue::Null,
+                Err(e) => error_reply(e),
+            },
+
+            Command::Set {
+                key,
+                value,
+                expiry,
+                nx,
+                xx,
+                get,
+            } => {
+                // NX/XX的检查和写入在Store的同一把写锁内完成，不计入keyspace_hits/keyspace_misses
+                let options = SetOptions { expiry, nx, xx, get };
+                match self.store.set_with_options(key, value, options) {
+                    // GET: 无论是否写入都回复旧值
+                    Ok((_, old)) if get => old.map_or(RespValue::Null, RespValue::BulkString),
+                    Ok((true, _)) => resp::ok(),
+                    Ok((false, _)) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 与MSETNX共用同一把写锁内的检查和写入
+            Command::SetNx { key, value } => {
+                RespValue::Integer(self.store.msetnx(vec![(key, value)]) as i64)
+            }
+
+            Command::GetSet { key, value } => match self.store.get_checked(&key) {
+                Ok(old) => {
+                    self.store.set(key, value);
+                    old.map_or(RespValue::Null, RespValue::BulkString)
+                }
+                Err(e) => error_reply(e),
+            },
+
+            Command::Append { key, value } => match self.store.append(&key, &value) {
+                Ok(len) => RespValue::Integer(len as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::SetRange { key, offset, value } => {
+                match self.store.set_range(&key, offset, &value) {
+                    Ok(len) => RespValue::Integer(len as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::GetRange { key, start, end } => {
+                match self.store.get_range_checked(&key, start, end) {
+                    Ok(data) => RespValue::BulkString(data),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::Strlen { key } => match self.store.strlen(&key) {
+                Ok(len) => RespValue::Integer(len as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::Incr { key } => self.incr(&key, 1),
+
+            Command::IncrBy { key, delta } => self.incr(&key, delta),
+
+            Command::Decr { key } => self.incr(&key, -1),
+
+            // i64::MIN取反会溢出，与Redis一样直接报错
+            Command::DecrBy { key, delta } => match delta.checked_neg() {
+                Some(delta) => self.incr(&key, delta),
+                None => resp::error("ERR decrement would overflow"),
+            },
+
+            Command::MGet { keys } => {
+                let values: Vec<RespValue> = keys
+                    .iter()
+                    .map(|k| match self.store.get(k) {
+                        Some(data) => RespValue::BulkString(data),
+                        None => RespValue::Null,
+                    })
+                    .collect();
+                RespValue::Array(values)
+            }
+
+            Command::MSet { pairs } => {
+                self.store.mset(pairs);
+                resp::ok()
+            }
+
+            Command::MSetNx { pairs } => RespValue::Integer(self.store.msetnx(pairs) as i64),
+
+            Command::BitOp { op, destination, keys } => {
+                let result = self.store.store_from_all(&keys, &destination, |values| {
+                    let sources: Vec<&[u8]> = values
+                        .iter()
+                        .map(|value: &Option<&Vec<u8>>| value.map_or(&[][..], Vec::as_slice))
+                        .collect();
+                    let result = op.apply(&sources);
+                    let len = result.len();
+                    // 结果为空(所有源键都不存在或为空串)时删除目标键
+                    ((!result.is_empty()).then_some(result), len)
+                });
+                match result {
+                    Ok(len) => RespValue::Integer(len as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::BitField { key, fields } => {
+                let writes = fields.iter().any(BitField::writes);
+                let result = self.store.update_as(&key, |data: &mut Option<Vec<u8>>| {
+                    // 只读取时不创建键
+                    let results = match data {
+                        None if !writes => bitops::bitfield(&mut Vec::new(), &fields),
+                        data => bitops::bitfield(data.get_or_insert_with(Vec::new), &fields),
+                    };
+                    Ok((results, writes))
+                });
+                match result {
+                    Ok(results) => RespValue::Array(
+                        results
+                            .into_iter()
+                            .map(|value| value.map_or(RespValue::Null, RespValue::Integer))
+                            .collect(),
+                    ),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 键命令
+            Command::Del { keys } => {
+                let count = self.store.del_multi(&keys);
+                RespValue::Integer(count as i64)
+            }
+
+            Command::Exists { keys } => {
+                let count = self.store.exists_multi(&keys);
+                RespValue::Integer(count as i64)
+            }
+
+            Command::Expire { key, milliseconds } => {
+                self.expire("EXPIRE", &key, self.store.clock().now_ms(), milliseconds)
+            }
+
+            Command::PExpire { key, milliseconds } => {
+                self.expire("PEXPIRE", &key, self.store.clock().now_ms(), milliseconds)
+            }
+
+            Command::ExpireAt { key, at_ms } => self.expire("EXPIREAT", &key, 0, at_ms),
+
+            Command::PExpireAt { key, at_ms } => self.expire("PEXPIREAT", &key, 0, at_ms),
+
+            Command::ExpireTime { key } => {
+                // 与Redis一样四舍五入到秒
+                let at_ms = self.store.pexpiretime(&key);
+                RespValue::Integer(if at_ms > 0 { (at_ms + 500) / 1000 } else { at_ms })
+            }
+
+            Command::PExpireTime { key } => RespValue::Integer(self.store.pexpiretime(&key)),
+
+            Command::Ttl { key } => {
+                let ttl_ms = self.store.pttl(&key);
+                let ttl_s = if ttl_ms > 0 {
+                    ttl_ms / 1000
+                } else {
+                    ttl_ms
+                };
+                RespValue::Integer(ttl_s)
+            }
+
+            Command::PTtl { key } => {
+                let ttl = self.store.pttl(&key);
+                RespValue::Integer(ttl)
+            }
+
+            Command::Persist { key } => {
+                let success = self.store.persist(&key);
+                RespValue::Integer(if success { 1 } else { 0 })
+            }
+
+            Command::Keys { pattern } => match self.store.keys_until(&pattern, self.deadline) {
+                Some(keys) => RespValue::Array(
+                    keys.into_iter()
+                        .map(|k| RespValue::BulkString(k.into_bytes()))
+                        .collect(),
+                ),
+                None => resp::error("BUSY KEYS exceeded the execution budget (busy-reply-threshold) and was aborted"),
+            },
+
+            Command::Type { key } => match self.store.key_type(&key) {
+                Some(t) => RespValue::SimpleString(t.to_string()),
+                None => RespValue::SimpleString("none".to_string()),
+            },
+
+            Command::Rename { old_key, new_key } => {
+                if self.store.rename(&old_key, &new_key) {
+                    resp::ok()
+                } else {
+                    resp::error("ERR no such key")
+                }
+            }
+
+            Command::RenameNx { old_key, new_key } => match self.store.rename_nx(&old_key, &new_key) {
+                Some(renamed) => RespValue::Integer(renamed as i64),
+                None => resp::error("ERR no such key"),
+            },
+
+            Command::Scan {
+                cursor,
+                pattern,
+                count,
+                key_type,
+            } => {
+                let (next, keys) = self.store.scan(cursor, pattern.as_deref(), count);
+                let keys = keys
+                    .into_iter()
+                    .filter(|k| match &key_type {
+                        Some(t) => self.store.key_type(k).is_some_and(|kt| kt.eq_ignore_ascii_case(t)),
+                        None => true,
+                    })
+                    .map(|k| RespValue::BulkString(k.into_bytes()))
+                    .collect();
+                RespValue::Array(vec![resp::bulk_string(&next.to_string()), RespValue::Array(keys)])
+            }
+
+            // 哈希命令
+            Command::HSet { key, pairs } => {
+                let result = self.store.update_as(&key, |hash: &mut Option<HashValue>| {
+                    // 已有的字段被覆盖时实际增加的更少，这里按上限估算
+                    let len = hash.as_ref().map_or(0, HashValue::len);
+                    self.limits.check_elements(len + pairs.len())?;
+                    let hash = hash.get_or_insert_with(HashValue::new);
+                    let mut added = 0;
+                    for (field, value) in pairs {
+                        if hash.insert(field, value).is_none() {
+                            added += 1;
+                        }
+                    }
+                    Ok((added, true))
+                });
+                match result {
+                    Ok(added) => RespValue::Integer(added),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::HGet { key, field } => {
+                match self.store.read_as(&key, |hash: &HashValue| hash.get(&field).cloned()) {
+                    Ok(Some(Some(value))) => RespValue::BulkString(value),
+                    Ok(_) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::HMGet { key, fields } => {
+                let values = self.store.read_as(&key, |hash: &HashValue| {
+                    fields.iter().map(|field| hash.get(field).cloned()).collect::<Vec<_>>()
+                });
+                match values {
+                    Ok(values) => RespValue::Array(
+                        values
+                            .unwrap_or_else(|| vec![None; fields.len()])
+                            .into_iter()
+                            .map(|value| value.map_or(RespValue::Null, RespValue::BulkString))
+                            .collect(),
+                    ),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::HGetAll { key } => self.hash_reply(&key, |hash| {
+                hash.iter()
+                    .flat_map(|(field, value)| [field.clone(), value.clone()])
+                    .collect()
+            }),
+
+            Command::HKeys { key } => self.hash_reply(&key, |hash| hash.keys().cloned().collect()),
+
+            Command::HVals { key } => self.hash_reply(&key, |hash| hash.values().cloned().collect()),
+
+            Command::HDel { key, fields } => {
+                let result = self.store.update_as(&key, |hash: &mut Option<HashValue>| {
+                    let Some(current) = hash else {
+                        return Ok((0, false));
+                    };
+                    let removed = fields.iter().filter(|field| current.remove(field).is_some()).count();
+                    // 删除最后一个字段时键也被删除
+                    if current.is_empty() {
+                        *hash = None;
+                    }
+                    Ok((removed as i64, removed > 0))
+                });
+                match result {
+                    Ok(removed) => RespValue::Integer(removed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::HExists { key, field } => {
+                match self.store.read_as(&key, |hash: &HashValue| hash.contains_key(&field)) {
+                    Ok(found) => RespValue::Integer(found.unwrap_or(false) as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::HLen { key } => match self.store.read_as(&key, |hash: &HashValue| hash.len()) {
+                Ok(len) => RespValue::Integer(len.unwrap_or(0) as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::HRandField {
+                key,
+                count,
+                with_values,
+            } => {
+                let sample = self.store.read_as(&key, |hash: &HashValue| {
+                    let entries: Vec<(&Vec<u8>, &Vec<u8>)> = hash.iter().collect();
+                    store::sample_indices(entries.len(), count.unwrap_or(1))
+                        .into_iter()
+                        .map(|i| (entries[i].0.clone(), entries[i].1.clone()))
+                        .collect::<Vec<_>>()
+                });
+                match (sample, count) {
+                    (Ok(items), Some(_)) => RespValue::Array(
+                        items
+                            .unwrap_or_default()
+                            .into_iter()
+                            .flat_map(|(field, value)| {
+                                std::iter::once(field).chain(with_values.then_some(value))
+                            })
+                            .map(RespValue::BulkString)
+                            .collect(),
+                    ),
+                    (Ok(items), None) => items
+                        .and_then(|items| items.into_iter().next())
+                        .map_or(RespValue::Null, |(field, _)| RespValue::BulkString(field)),
+                    (Err(e), _) => error_reply(e),
+                }
+            }
+
+            Command::HIncrBy { key, field, delta } => {
+                let result = self.store.update_as(&key, |hash: &mut Option<HashValue>| {
+                    let hash = hash.get_or_insert_with(HashValue::new);
+                    let current = match hash.get(&field) {
+                        Some(value) => store::parse_integer(value).ok_or_else(|| {
+                            RedisError::InvalidArgument("hash value is not an integer".to_string())
+                        })?,
+                        None => 0,
+                    };
+                    let value = current.checked_add(delta).ok_or_else(|| {
+                        RedisError::InvalidArgument("increment or decrement would overflow".to_string())
+                    })?;
+                    // 已有字段的过期时间保留
+                    let new = value.to_string().into_bytes();
+                    match hash.get_mut(&field) {
+                        Some(current) => *current = new,
+                        None => {
+                            hash.insert(field, new);
+                        }
+                    }
+                    Ok((value, true))
+                });
+                match result {
+                    Ok(value) => RespValue::Integer(value),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 对每个字段回复: -2字段不存在，0条件不满足，1已设置，2时间已过去、字段已删除
+            Command::HExpire {
+                key,
+                time,
+                at,
+                condition,
+                fields,
+                ..
+            } => {
+                let now = self.store.clock().now_ms();
+                let expires_at = if at { time } else { now.saturating_add(time) };
+                let result = self.store.update_as(&key, |hash: &mut Option<HashValue>| {
+                    let Some(current) = hash else {
+                        return Ok((vec![-2; fields.len()], false));
+                    };
+                    let replies: Vec<i64> = fields
+                        .iter()
+                        .map(|field| {
+                            if !current.contains_key(field) {
+                                -2
+                            } else if !condition.is_none_or(|condition| {
+                                condition.allows(current.expires_at(field), expires_at)
+                            }) {
+                                0
+                            } else if expires_at <= now {
+                                current.remove(field);
+                                2
+                            } else {
+                                current.set_expires_at(field, Some(expires_at));
+                                1
+                            }
+                        })
+                        .collect();
+                    if current.is_empty() {
+                        *hash = None;
+                    }
+                    let modified = replies.iter().any(|&reply| reply > 0);
+                    Ok((replies, modified))
+                });
+                integers_reply(result)
+            }
+
+            // 对每个字段回复: -2字段不存在，-1没有过期时间，否则为剩余时间
+            Command::HTtl { key, millis, fields } => {
+                let result = self.store.read_as(&key, |hash: &HashValue| {
+                    let now = self.store.clock().now_ms();
+                    fields
+                        .iter()
+                        .map(|field| match (hash.contains_key(field), hash.expires_at(field)) {
+                            (false, _) => -2,
+                            (true, None) => -1,
+                            (true, Some(expires_at)) => {
+                                let ttl = expires_at.saturating_sub(now);
+                                // 与Redis一样，以秒为单位时向上取整
+                                (if millis { ttl } else { ttl.div_ceil(1000) }) as i64
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                });
+                integers_reply(result.map(|replies| replies.unwrap_or_else(|| vec![-2; fields.len()])))
+            }
+
+            // 对每个字段回复: -2字段不存在，-1没有过期时间，1已清除
+            Command::HPersist { key, fields } => {
+                let result = self.store.update_as(&key, |hash: &mut Option<HashValue>| {
+                    let Some(hash) = hash else {
+                        return Ok((vec![-2; fields.len()], false));
+                    };
+                    let replies: Vec<i64> = fields
+                        .iter()
+                        .map(|field| match (hash.contains_key(field), hash.expires_at(field)) {
+                            (false, _) => -2,
+                            (true, None) => -1,
+                            (true, Some(_)) => {
+                                hash.set_expires_at(field, None);
+                                1
+                            }
+                        })
+                        .collect();
+                    let modified = replies.contains(&1);
+                    Ok((replies, modified))
+                });
+                integers_reply(result)
+            }
+
+            // 列表命令
+            Command::LPush { key, values } => self.push(&key, values, true),
+
+            Command::RPush { key, values } => self.push(&key, values, false),
+
+            Command::LPop { key, count } => self.pop(&key, count, true),
+
+            Command::RPop { key, count } => self.pop(&key, count, false),
+
+            Command::LRange { key, start, stop } => {
+                let items = self.store.read_as(&key, |list: &ListValue| {
+                    match store::index_range(list.len(), start, stop) {
+                        Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+                        None => Vec::new(),
+                    }
+                });
+                match items {
+                    Ok(items) => RespValue::Array(
+                        items.unwrap_or_default().into_iter().map(RespValue::BulkString).collect(),
+                    ),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LLen { key } => match self.store.read_as(&key, |list: &ListValue| list.len()) {
+                Ok(len) => RespValue::Integer(len.unwrap_or(0) as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::LIndex { key, index } => {
+                let item = self.store.read_as(&key, |list: &ListValue| {
+                    // 负数下标从末尾开始计数
+                    let index = if index < 0 { index + list.len() as i64 } else { index };
+                    usize::try_from(index).ok().and_then(|index| list.get(index).cloned())
+                });
+                match item {
+                    Ok(Some(Some(item))) => RespValue::BulkString(item),
+                    Ok(_) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LPos {
+                key,
+                element,
+                rank,
+                count,
+                maxlen,
+            } => {
+                let positions = self.store.read_as(&key, |list: &ListValue| {
+                    lpos(list, &element, rank, count.unwrap_or(1), maxlen)
+                });
+                match (positions, count) {
+                    (Ok(positions), Some(_)) => RespValue::Array(
+                        positions
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|i| RespValue::Integer(i as i64))
+                            .collect(),
+                    ),
+                    (Ok(positions), None) => positions
+                        .and_then(|positions| positions.first().copied())
+                        .map_or(RespValue::Null, |i| RespValue::Integer(i as i64)),
+                    (Err(e), _) => error_reply(e),
+                }
+            }
+
+            // 找不到pivot时回复-1，键不存在时回复0
+            Command::LInsert {
+                key,
+                before,
+                pivot,
+                element,
+            } => {
+                let result = self.store.update_as(&key, |list: &mut Option<ListValue>| {
+                    let Some(list) = list else {
+                        return Ok((0, false));
+                    };
+                    let Some(i) = list.iter().position(|item| *item == pivot) else {
+                        return Ok((-1, false));
+                    };
+                    self.limits.check_elements(list.len() + 1)?;
+                    list.insert(if before { i } else { i + 1 }, element);
+                    Ok((list.len() as i64, true))
+                });
+                match result {
+                    Ok(len) => RespValue::Integer(len),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LSet {
+                key,
+                index,
+                element,
+            } => {
+                let result = self.store.update_as(&key, |list: &mut Option<ListValue>| {
+                    let Some(list) = list else {
+                        return Err(RedisError::InvalidArgument("no such key".to_string()));
+                    };
+                    let index = if index < 0 { index + list.len() as i64 } else { index };
+                    let item = usize::try_from(index).ok().and_then(|index| list.get_mut(index));
+                    let Some(item) = item else {
+                        return Err(RedisError::InvalidArgument("index out of range".to_string()));
+                    };
+                    *item = element;
+                    Ok(((), true))
+                });
+                match result {
+                    Ok(()) => resp::ok(),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LRem {
+                key,
+                count,
+                element,
+            } => {
+                let result = self.store.update_as(&key, |list: &mut Option<ListValue>| {
+                    let Some(current) = list else {
+                        return Ok((0, false));
+                    };
+                    let limit = if count == 0 { usize::MAX } else { count.unsigned_abs() as usize };
+                    // retain从头部开始，从尾部删除时跳过前面多出的匹配
+                    let skip = if count < 0 {
+                        let matches = current.iter().filter(|item| **item == element).count();
+                        matches.saturating_sub(limit)
+                    } else {
+                        0
+                    };
+                    let (mut seen, mut removed) = (0, 0);
+                    current.retain(|item| {
+                        if *item != element {
+                            return true;
+                        }
+                        seen += 1;
+                        let remove = seen > skip && removed < limit;
+                        removed += remove as usize;
+                        !remove
+                    });
+                    if current.is_empty() {
+                        *list = None;
+                    }
+                    Ok((removed as i64, removed > 0))
+                });
+                match result {
+                    Ok(removed) => RespValue::Integer(removed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LTrim { key, start, stop } => {
+                let result = self.store.update_as(&key, |list: &mut Option<ListValue>| {
+                    let Some(current) = list else {
+                        return Ok(((), false));
+                    };
+                    let len = current.len();
+                    match store::index_range(len, start, stop) {
+                        Some((start, stop)) => {
+                            current.truncate(stop + 1);
+                            current.truncate_front(stop + 1 - start);
+                        }
+                        None => current.clear(),
+                    }
+                    let modified = current.len() != len;
+                    if current.is_empty() {
+                        *list = None;
+                    }
+                    Ok(((), modified))
+                });
+                match result {
+                    Ok(()) => resp::ok(),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::LMove {
+                source,
+                destination,
+                from_front,
+                to_front,
+            } => self.lmove(&source, &destination, from_front, to_front),
+
+            Command::RPopLPush { source, destination } => {
+                self.lmove(&source, &destination, false, true)
+            }
+
+            // 没有数据时立即回复nil，由连接负责排队等待后重新执行
+            Command::BPop { keys, front, .. } => self.bpop(&keys, front),
+
+            Command::LMPop { keys, front, count } | Command::BLMPop { keys, front, count, .. } => {
+                self.lmpop(&keys, front, count)
+            }
+
+            Command::BLMove {
+                source,
+                destination,
+                from_front,
+                to_front,
+                ..
+            } => self.lmove(&source, &destination, from_front, to_front),
+
+            // 集合命令
+            Command::SAdd { key, members } => {
+                let result = self.store.update_as(&key, |set: &mut Option<SetValue>| {
+                    let len = set.as_ref().map_or(0, SetValue::len);
+                    self.limits.check_elements(len + members.len())?;
+                    let set = set.get_or_insert_with(SetValue::new);
+                    let mut added = 0;
+                    for member in members {
+                        if set.insert(member) {
+                            added += 1;
+                        }
+                    }
+                    Ok((added, added > 0))
+                });
+                match result {
+                    Ok(added) => RespValue::Integer(added),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::SRem { key, members } => {
+                let result = self.store.update_as(&key, |set: &mut Option<SetValue>| {
+                    let Some(current) = set else {
+                        return Ok((0, false));
+                    };
+                    let removed = members.iter().filter(|member| current.remove(member)).count();
+                    // 删除最后一个成员时键也被删除
+                    if current.is_empty() {
+                        *set = None;
+                    }
+                    Ok((removed as i64, removed > 0))
+                });
+                match result {
+                    Ok(removed) => RespValue::Integer(removed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::SMembers { key } => match self.store.read_as(&key, SetValue::clone) {
+                Ok(set) => members_reply(set.unwrap_or_default()),
+                Err(e) => error_reply(e),
+            },
+
+            Command::SIsMember { key, member } => {
+                match self.store.read_as(&key, |set: &SetValue| set.contains(&member)) {
+                    Ok(found) => RespValue::Integer(found.unwrap_or(false) as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::SMIsMember { key, members } => {
+                let found = self.store.read_as(&key, |set: &SetValue| {
+                    members.iter().map(|member| set.contains(member)).collect::<Vec<_>>()
+                });
+                match found {
+                    Ok(found) => RespValue::Array(
+                        found
+                            .unwrap_or_else(|| vec![false; members.len()])
+                            .into_iter()
+                            .map(|found| RespValue::Integer(found as i64))
+                            .collect(),
+                    ),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::SPop { key, count } => {
+                let popped = self.store.update_as(&key, |set: &mut Option<SetValue>| {
+                    let Some(current) = set else {
+                        return Ok((Vec::new(), false));
+                    };
+                    let count = count.unwrap_or(1);
+                    // 全部弹出时直接取走整个集合
+                    if count >= current.len() {
+                        let members = set.take().unwrap_or_default();
+                        return Ok((members.into_iter().collect(), true));
+                    }
+                    let members: Vec<&Vec<u8>> = current.iter().collect();
+                    let picked: Vec<Vec<u8>> = store::sample_indices(members.len(), count as i64)
+                        .into_iter()
+                        .map(|i| members[i].clone())
+                        .collect();
+                    for member in &picked {
+                        current.remove(member);
+                    }
+                    let modified = !picked.is_empty();
+                    Ok((picked, modified))
+                });
+                match (popped, count) {
+                    (Ok(members), Some(_)) => {
+                        RespValue::Array(members.into_iter().map(RespValue::BulkString).collect())
+                    }
+                    (Ok(members), None) => {
+                        members.into_iter().next().map_or(RespValue::Null, RespValue::BulkString)
+                    }
+                    (Err(e), _) => error_reply(e),
+                }
+            }
+
+            Command::SRandMember { key, count } => {
+                let sample = self.store.read_as(&key, |set: &SetValue| {
+                    let members: Vec<&Vec<u8>> = set.iter().collect();
+                    store::sample_indices(members.len(), count.unwrap_or(1))
+                        .into_iter()
+                        .map(|i| members[i].clone())
+                        .collect::<Vec<_>>()
+                });
+                match (sample, count) {
+                    (Ok(members), Some(_)) => RespValue::Array(
+                        members.unwrap_or_default().into_iter().map(RespValue::BulkString).collect(),
+                    ),
+                    (Ok(members), None) => members
+                        .and_then(|members| members.into_iter().next())
+                        .map_or(RespValue::Null, RespValue::BulkString),
+                    (Err(e), _) => error_reply(e),
+                }
+            }
+
+            Command::SCard { key } => match self.store.read_as(&key, |set: &SetValue| set.len()) {
+                Ok(len) => RespValue::Integer(len.unwrap_or(0) as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::SUnion { keys } => match self.read_sets(&keys) {
+                Ok(sets) => members_reply(set_union(&sets.iter().collect::<Vec<_>>())),
+                Err(e) => error_reply(e),
+            },
+
+            Command::SInter { keys } => match self.read_sets(&keys) {
+                Ok(sets) => members_reply(set_intersection(&sets.iter().collect::<Vec<_>>())),
+                Err(e) => error_reply(e),
+            },
+
+            // 从最小的集合开始检查，数到limit个时提前结束
+            Command::SInterCard { keys, limit } => match self.read_sets(&keys) {
+                Ok(mut sets) => {
+                    sets.sort_by_key(SetValue::len);
+                    let limit = if limit == 0 { usize::MAX } else { limit };
+                    let count = sets.split_first().map_or(0, |(smallest, rest)| {
+                        smallest
+                            .iter()
+                            .filter(|member| rest.iter().all(|set| set.contains(member)))
+                            .take(limit)
+                            .count()
+                    });
+                    RespValue::Integer(count as i64)
+                }
+                Err(e) => error_reply(e),
+            },
+
+            Command::SDiff { keys } => match self.read_sets(&keys) {
+                Ok(sets) => members_reply(set_difference(&sets.iter().collect::<Vec<_>>())),
+                Err(e) => error_reply(e),
+            },
+
+            Command::SUnionStore { destination, keys } => {
+                self.set_store(&destination, &keys, set_union)
+            }
+
+            Command::SInterStore { destination, keys } => {
+                self.set_store(&destination, &keys, set_intersection)
+            }
+
+            Command::SDiffStore { destination, keys } => {
+                self.set_store(&destination, &keys, set_difference)
+            }
+
+            // 有序集合命令
+            Command::ZAdd { key, options, pairs } => {
+                let result = self.store.update_as(&key, |zset: &mut Option<SortedSet>| {
+                    // XX不创建新键
+                    if zset.is_none() && options.xx {
+                        return Ok((Vec::new(), false));
+                    }
+                    let len = zset.as_ref().map_or(0, SortedSet::len);
+                    self.limits.check_elements(len + pairs.len())?;
+                    let zset = zset.get_or_insert_with(SortedSet::new);
+                    // 只有INCR会出错(结果为NaN)，而INCR只有一个成员
+                    let outcomes = pairs
+                        .into_iter()
+                        .map(|(score, member)| zset.add(member, score, &options))
+                        .collect::<RedisResult<Vec<_>>>()?;
+                    let modified = outcomes
+                        .iter()
+                        .any(|outcome| matches!(outcome, ZAddOutcome::Added(_) | ZAddOutcome::Updated(_)));
+                    Ok((outcomes, modified))
+                });
+                match result {
+                    // INCR回复成员的新分数，没有执行时回复nil
+                    Ok(outcomes) if options.incr => outcomes
+                        .first()
+                        .and_then(ZAddOutcome::score)
+                        .map_or(RespValue::Null, score_reply),
+                    Ok(outcomes) => {
+                        let changed = outcomes
+                            .iter()
+                            .filter(|outcome| match outcome {
+                                ZAddOutcome::Added(_) => true,
+                                ZAddOutcome::Updated(_) => options.ch,
+                                _ => false,
+                            })
+                            .count();
+                        RespValue::Integer(changed as i64)
+                    }
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZIncrBy { key, delta, member } => {
+                let options = ZAddOptions {
+                    incr: true,
+                    ..Default::default()
+                };
+                let result = self.store.update_as(&key, |zset: &mut Option<SortedSet>| {
+                    let outcome = zset.get_or_insert_with(SortedSet::new).add(member, delta, &options)?;
+                    Ok((outcome.score(), true))
+                });
+                match result {
+                    Ok(score) => score.map_or(RespValue::Null, score_reply),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZScore { key, member } => {
+                match self.store.read_as(&key, |zset: &SortedSet| zset.score(&member)) {
+                    Ok(score) => score.flatten().map_or(RespValue::Null, score_reply),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZRank { key, member } => self.zrank(&key, &member, false),
+
+            Command::ZRevRank { key, member } => self.zrank(&key, &member, true),
+
+            Command::ZRange {
+                key,
+                query,
+                with_scores,
+                ..
+            } => {
+                let items = self.store.read_as(&key, |zset: &SortedSet| {
+                    zset.range(&query)
+                        .into_iter()
+                        .map(|(member, score)| (member.to_vec(), score))
+                        .collect()
+                });
+                match items {
+                    Ok(items) => zrange_reply(items.unwrap_or_default(), with_scores),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZRangeStore {
+                destination,
+                source,
+                query,
+            } => {
+                let stored = self.store.store_from(&source, &destination, |zset: Option<&SortedSet>| {
+                    let mut result = SortedSet::new();
+                    for (member, score) in zset.map(|zset| zset.range(&query)).unwrap_or_default() {
+                        result.insert(member.to_vec(), score);
+                    }
+                    let len = result.len();
+                    ((!result.is_empty()).then_some(result), len)
+                });
+                match stored {
+                    Ok(len) => RespValue::Integer(len as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZRandMember {
+                key,
+                count,
+                with_scores,
+            } => {
+                let sample = self.store.read_as(&key, |zset: &SortedSet| {
+                    let entries: Vec<(&[u8], f64)> = zset.iter().collect();
+                    store::sample_indices(entries.len(), count.unwrap_or(1))
+                        .into_iter()
+                        .map(|i| (entries[i].0.to_vec(), entries[i].1))
+                        .collect::<Vec<_>>()
+                });
+                match (sample, count) {
+                    (Ok(items), Some(_)) => zrange_reply(items.unwrap_or_default(), with_scores),
+                    (Ok(items), None) => items
+                        .and_then(|items| items.into_iter().next())
+                        .map_or(RespValue::Null, |(member, _)| RespValue::BulkString(member)),
+                    (Err(e), _) => error_reply(e),
+                }
+            }
+
+            Command::BZPop { keys, min, .. } => {
+                let popped =
+                    self.pop_first(&keys, |zset: &mut SortedSet| (zset.pop(min), zset.is_empty()));
+                match popped {
+                    Ok(Some((key, Some((member, score))))) => RespValue::Array(vec![
+                        RespValue::BulkString(key.into_bytes()),
+                        RespValue::BulkString(member),
+                        score_reply(score),
+                    ]),
+                    Ok(_) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::ZMPop { keys, min, count } | Command::BZMPop { keys, min, count, .. } => {
+                self.zmpop(&keys, min, count)
+            }
+
+            Command::ZCard { key } => match self.store.read_as(&key, |zset: &SortedSet| zset.len()) {
+                Ok(len) => RespValue::Integer(len.unwrap_or(0) as i64),
+                Err(e) => error_reply(e),
+            },
+
+            Command::ZRem { key, members } => {
+                let result = self.store.update_as(&key, |zset: &mut Option<SortedSet>| {
+                    let Some(current) = zset else {
+                        return Ok((0, false));
+                    };
+                    let removed = members.iter().filter(|member| current.remove(member)).count();
+                    // 删除最后一个成员时键也被删除
+                    if current.is_empty() {
+                        *zset = None;
+                    }
+                    Ok((removed as i64, removed > 0))
+                });
+                match result {
+                    Ok(removed) => RespValue::Integer(removed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 流命令
+            Command::XAdd {
+                key,
+                no_mkstream,
+                trim,
+                id,
+                fields,
+            } => {
+                let now = self.store.clock().now_ms();
+                let result = self.store.update_as(&key, |stream: &mut Option<Stream>| {
+                    if stream.is_none() && no_mkstream {
+                        return Ok((None, false));
+                    }
+                    // 添加失败时不能留下新建的空流
+                    let mut created = None;
+                    let target = match stream.as_mut() {
+                        Some(target) => target,
+                        None => created.insert(Stream::new()),
+                    };
+                    // 带裁剪参数时添加后的长度在裁剪之后才确定
+                    if trim.is_none() {
+                        self.limits.check_elements(target.len() + 1)?;
+                    }
+                    let id = target.add(id, fields, now)?;
+                    if let Some(trim) = trim {
+                        target.trim(&trim);
+                    }
+                    if created.is_some() {
+                        *stream = created;
+                    }
+                    Ok((Some(id), true))
+                });
+                match result {
+                    Ok(Some(id)) => RespValue::BulkString(id.to_string().into_bytes()),
+                    Ok(None) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XLen { key } => match self.store.read_as(&key, |stream: &Stream| stream.len()) {
+                Ok(len) => RespValue::Integer(len.unwrap_or(0) as i64),
+                Err(e) => error_reply(e),
+            },
+
+            // COUNT 0回复nil，与Redis一致
+            Command::XRange { count: Some(0), .. } => RespValue::Null,
+
+            Command::XRange {
+                key,
+                start,
+                end,
+                count,
+                rev,
+            } => {
+                let entries = self.store.read_as(&key, |stream: &Stream| {
+                    stream_entries(stream.range(start, end, count, rev))
+                });
+                match entries {
+                    Ok(entries) => entries.unwrap_or_else(|| RespValue::Array(Vec::new())),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XTrim { key, trim } => {
+                let result = self.store.update_as(&key, |stream: &mut Option<Stream>| {
+                    let removed = stream.as_mut().map_or(0, |stream| stream.trim(&trim));
+                    Ok((removed as i64, removed > 0))
+                });
+                match result {
+                    Ok(removed) => RespValue::Integer(removed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XRead { count, streams } => self.xread(&streams, count),
+
+            Command::XGroupCreate {
+                key,
+                group,
+                id,
+                mkstream,
+            } => {
+                let result = self.store.update_as(&key, |stream: &mut Option<Stream>| {
+                    if stream.is_none() && !mkstream {
+                        return Err(xgroup_missing_key());
+                    }
+                    let stream = stream.get_or_insert_with(Stream::new);
+                    let id = id.unwrap_or(stream.last_id());
+                    if !stream.create_group(&group, id) {
+                        return Err(RedisError::Coded {
+                            code: "BUSYGROUP",
+                            message: "Consumer Group name already exists".to_string(),
+                        });
+                    }
+                    Ok(((), true))
+                });
+                match result {
+                    Ok(()) => resp::ok(),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XGroupSetId { key, group, id } => {
+                let result = self.update_stream(&key, |stream| {
+                    let id = id.unwrap_or(stream.last_id());
+                    let group = stream
+                        .group_mut(&group)
+                        .ok_or_else(|| no_such_group(&key, &group))?;
+                    group.set_last_delivered(id);
+                    Ok(((), true))
+                });
+                match result {
+                    Ok(()) => resp::ok(),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XGroupDestroy { key, group } => {
+                let result = self.update_stream(&key, |stream| {
+                    let destroyed = stream.destroy_group(&group);
+                    Ok((destroyed as i64, destroyed))
+                });
+                match result {
+                    Ok(destroyed) => RespValue::Integer(destroyed),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XGroupCreateConsumer { key, group, consumer } => {
+                let result = self.update_stream(&key, |stream| {
+                    let group = stream
+                        .group_mut(&group)
+                        .ok_or_else(|| no_such_group(&key, &group))?;
+                    let created = group.create_consumer(&consumer);
+                    Ok((created as i64, created))
+                });
+                match result {
+                    Ok(created) => RespValue::Integer(created),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XGroupDelConsumer { key, group, consumer } => {
+                let result = self.update_stream(&key, |stream| {
+                    let group = stream
+                        .group_mut(&group)
+                        .ok_or_else(|| no_such_group(&key, &group))?;
+                    Ok((group.delete_consumer(&consumer) as i64, true))
+                });
+                match result {
+                    Ok(pending) => RespValue::Integer(pending),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XReadGroup {
+                group,
+                consumer,
+                count,
+                noack,
+                streams,
+            } => self.xreadgroup(&group, &consumer, &streams, count, noack),
+
+            Command::XAck { key, group, ids } => {
+                let result = self.store.update_as(&key, |stream: &mut Option<Stream>| {
+                    let acked = stream
+                        .as_mut()
+                        .and_then(|stream| stream.group_mut(&group))
+                        .map_or(0, |group| group.ack(&ids));
+                    Ok((acked as i64, acked > 0))
+                });
+                match result {
+                    Ok(acked) => RespValue::Integer(acked),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XPending { key, group, query } => {
+                let now = self.store.clock().now_ms();
+                let reply = self.store.read_as(&key, |stream: &Stream| {
+                    let group = stream.group(&group)?;
+                    Some(match &query {
+                        None => pending_summary(group),
+                        Some(query) => pending_entries(group, query, now),
+                    })
+                });
+                match reply {
+                    Ok(Some(Some(reply))) => reply,
+                    Ok(_) => error_reply(no_such_key_or_group(&key, &group, "")),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::XClaim {
+                key,
+                group,
+                consumer,
+                ids,
+                options,
+            } => {
+                let now = self.store.clock().now_ms();
+                let result = self.store.update_as(&key, |stream: &mut Option<Stream>| {
+                    let claimed = stream
+                        .as_mut()
+                        .and_then(|stream| stream.claim(&group, &consumer, &ids, &options, now));
+                    let claimed_any = claimed.as_ref().is_some_and(|claimed| !claimed.is_empty());
+                    let modified = claimed_any || options.last_id.is_some();
+                    Ok((claimed, modified))
+                });
+                match result {
+                    Ok(Some(claimed)) if options.just_id => RespValue::Array(
+                        claimed
+                            .into_iter()
+                            .map(|(id, _)| RespValue::BulkString(id.to_string().into_bytes()))
+                            .collect(),
+                    ),
+                    Ok(Some(claimed)) => {
+                        stream_entries(claimed.iter().map(|(id, fields)| (*id, fields)).collect())
+                    }
+                    Ok(None) => error_reply(no_such_key_or_group(&key, &group, "")),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // JSON命令
+            Command::JsonSet {
+                key,
+                path,
+                value,
+                nx,
+                xx,
+            } => {
+                let result = self.store.update_as(&key, |doc: &mut Option<serde_json::Value>| match doc {
+                    // 有元素数限制时在副本上修改，超出限制不影响原文档
+                    Some(doc) if self.limits.max_elements > 0 => {
+                        let mut updated = doc.clone();
+                        let changed = path.set(&mut updated, &value, nx, xx) > 0;
+                        self.limits.check_elements(json::max_elements(&updated))?;
+                        *doc = updated;
+                        Ok((changed, changed))
+                    }
+                    Some(doc) => {
+                        let changed = path.set(doc, &value, nx, xx) > 0;
+                        Ok((changed, changed))
+                    }
+                    None if !path.is_root() => Err(RedisError::InvalidArgument(
+                        "new objects must be created at the root".to_string(),
+                    )),
+                    None if xx => Ok((false, false)),
+                    None => {
+                        *doc = Some(value);
+                        Ok((true, true))
+                    }
+                });
+                match result {
+                    Ok(true) => resp::ok(),
+                    Ok(false) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::JsonGet { key, paths } => {
+                match self.store.read_as(&key, |doc: &serde_json::Value| json_get(doc, &paths)) {
+                    Ok(Some(Ok(text))) => resp::bulk_string(&text),
+                    Ok(Some(Err(e))) | Err(e) => error_reply(e),
+                    Ok(None) => RespValue::Null,
+                }
+            }
+
+            Command::JsonDel { key, path } => {
+                let result = self.store.update_as(&key, |doc: &mut Option<serde_json::Value>| {
+                    let deleted = match doc {
+                        Some(_) if path.is_root() => {
+                            *doc = None;
+                            1
+                        }
+                        Some(doc) => path.delete(doc),
+                        None => 0,
+                    };
+                    Ok((deleted, deleted > 0))
+                });
+                match result {
+                    Ok(deleted) => RespValue::Integer(deleted as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::JsonType { key, path } => {
+                let result = self.store.read_as(&key, |doc: &serde_json::Value| {
+                    let mut types = path
+                        .select(doc)
+                        .into_iter()
+                        .map(|v| RespValue::SimpleString(json::type_name(v).to_string()));
+                    if path.is_legacy() {
+                        types.next().unwrap_or(RespValue::Null)
+                    } else {
+                        RespValue::Array(types.collect())
+                    }
+                });
+                match result {
+                    Ok(Some(reply)) => reply,
+                    Ok(None) => RespValue::Null,
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::JsonNumIncrBy { key, path, by } => {
+                let result = self.store.update_as(&key, |doc: &mut Option<serde_json::Value>| {
+                    // 先检查所有结果再修改，出错时文档保持不变
+                    let matches = doc.as_ref().map(|doc| path.select(doc)).unwrap_or_default();
+                    if matches
+                        .iter()
+                        .filter_map(|value| value.as_number())
+                        .any(|n| json::add_numbers(n, &by).is_none())
+                    {
+                        return Err(RedisError::InvalidArgument(
+                            "result is not a finite number".to_string(),
+                        ));
+                    }
+                    json_update(doc, &path, "a number", |value| match value {
+                        serde_json::Value::Number(n) => {
+                            *n = json::add_numbers(n, &by)?;
+                            Some(value.clone())
+                        }
+                        _ => None,
+                    })
+                });
+                match result {
+                    Ok(mut results) if path.is_legacy() => match results.pop().flatten() {
+                        Some(value) => resp::bulk_string(&value.to_string()),
+                        None => RespValue::Null,
+                    },
+                    Ok(results) => {
+                        let values = results.into_iter().map(Option::unwrap_or_default).collect();
+                        resp::bulk_string(&serde_json::Value::Array(values).to_string())
+                    }
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::JsonArrAppend { key, path, values } => {
+                let result = self.store.update_as(&key, |doc: &mut Option<serde_json::Value>| {
+                    // 追加前检查元素数限制，超出限制时文档保持不变
+                    let matches = doc.as_ref().map(|doc| path.select(doc)).unwrap_or_default();
+                    let longest = matches.iter().filter_map(|value| value.as_array()).map(Vec::len).max();
+                    self.limits.check_elements(longest.map_or(0, |len| len + values.len()))?;
+                    json_update(doc, &path, "array", |value| match value {
+                        serde_json::Value::Array(items) => {
+                            items.extend(values.iter().cloned());
+                            Some(items.len())
+                        }
+                        _ => None,
+                    })
+                });
+                let length = |len: Option<usize>| {
+                    len.map_or(RespValue::Null, |len| RespValue::Integer(len as i64))
+                };
+                match result {
+                    Ok(mut lengths) if path.is_legacy() => length(lengths.pop().flatten()),
+                    Ok(lengths) => RespValue::Array(lengths.into_iter().map(length).collect()),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 时间序列命令
+            Command::TsAdd {
+                key,
+                timestamp,
+                value,
+                retention,
+                labels,
+            } => {
+                let timestamp = timestamp.unwrap_or_else(|| self.store.clock().now_ms());
+                let result = self.store.update_as(&key, |series: &mut Option<TimeSeries>| {
+                    self.limits.check_elements(series.as_ref().map_or(0, TimeSeries::len) + 1)?;
+                    let series =
+                        series.get_or_insert_with(|| TimeSeries::new(retention.unwrap_or(0), labels));
+                    series.add(timestamp, value)?;
+                    Ok(((), true))
+                });
+                match result {
+                    Ok(()) => RespValue::Integer(timestamp as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::TsRange {
+                key,
+                from,
+                to,
+                aggregation,
+            } => match self.store.read_as(&key, |series: &TimeSeries| series.range(from, to, aggregation)) {
+                Ok(Some(samples)) => samples_reply(samples),
+                Ok(None) => error_reply(tsdb_error("the key does not exist")),
+                Err(e) => error_reply(e),
+            },
+
+            // 没有样本时回复空数组
+            Command::TsGet { key } => match self.store.read_as(&key, |series: &TimeSeries| series.last()) {
+                Ok(Some(Some((ts, v)))) => {
+                    RespValue::Array(vec![RespValue::Integer(ts as i64), resp::bulk_string(&v.to_string())])
+                }
+                Ok(Some(None)) => RespValue::Array(Vec::new()),
+                Ok(None) => error_reply(tsdb_error("the key does not exist")),
+                Err(e) => error_reply(e),
+            },
+
+            Command::TsMRange {
+                from,
+                to,
+                aggregation,
+                with_labels,
+                filters,
+            } => {
+                // 每个序列回复[键, 标签, 样本]，不带WITHLABELS时标签为空数组
+                let series = self.store.collect_series(|key, series| {
+                    if !series.matches(&filters) {
+                        return None;
+                    }
+                    let labels = if with_labels {
+                        series
+                            .labels()
+                            .iter()
+                            .map(|(l, v)| RespValue::Array(vec![resp::bulk_string(l), resp::bulk_string(v)]))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    Some(RespValue::Array(vec![
+                        resp::bulk_string(key),
+                        RespValue::Array(labels),
+                        samples_reply(series.range(from, to, aggregation)),
+                    ]))
+                });
+                RespValue::Array(series)
+            }
+
+            // 布隆过滤器命令
+            Command::BfReserve {
+                key,
+                error_rate,
+                capacity,
+                expansion,
+            } => {
+                let result = self.store.update_as(&key, |filter: &mut Option<BloomFilter>| {
+                    if filter.is_some() {
+                        return Err(RedisError::InvalidArgument("item exists".to_string()));
+                    }
+                    *filter = Some(BloomFilter::new(error_rate, capacity, expansion)?);
+                    Ok(((), true))
+                });
+                match result {
+                    Ok(()) => resp::ok(),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::BfAdd { key, item } => match self.bf_add(&key, vec![item]) {
+                Ok(mut results) => bool_reply(results.remove(0)),
+                Err(e) => error_reply(e),
+            },
+
+            Command::BfMAdd { key, items } => match self.bf_add(&key, items) {
+                Ok(results) => RespValue::Array(results.into_iter().map(bool_reply).collect()),
+                Err(e) => error_reply(e),
+            },
+
+            Command::BfExists { key, item } => {
+                match self.store.read_as(&key, |filter: &BloomFilter| filter.contains(&item)) {
+                    Ok(found) => RespValue::Integer(found.unwrap_or(false) as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            // 布谷鸟过滤器命令
+            Command::CfAdd { key, item } => {
+                let result = self.store.update_as(&key, |filter: &mut Option<CuckooFilter>| {
+                    let filter = match filter {
+                        Some(filter) => filter,
+                        None => filter.insert(CuckooFilter::new(DEFAULT_CUCKOO_CAPACITY)?),
+                    };
+                    filter.add(&item)?;
+                    Ok((true, true))
+                });
+                bool_reply(result)
+            }
+
+            Command::CfExists { key, item } => {
+                match self.store.read_as(&key, |filter: &CuckooFilter| filter.contains(&item)) {
+                    Ok(found) => RespValue::Integer(found.unwrap_or(false) as i64),
+                    Err(e) => error_reply(e),
+                }
+            }
+
+            Command::CfDel { key, item } => {
+                let result = self.store.update_as(&key, |filter: &mut Option<CuckooFilter>| {
+                    let Some(filter) = filter else {
+                        return Err(RedisError::InvalidArgument("Not found".to_string()));
+                    };
+                    let deleted = filter.delete(&item);
+                    Ok((deleted, deleted))
+                });
+                bool_reply(result)
+            }
+
+            // 限流命令: 回复[是否被限流, 上限, 剩余, 多少秒后重试(-1表示不需要), 多少秒后完全恢复]
+            Command::ClThrottle {
+                key,
+                throttle,
+                quantity,
+            } => match self.store.throttle(&key, &throttle, quantity) {
+                Ok(result) => {
+                    // 秒数向上取整，按retry_after重试一定能成功
+                    let secs = |d: Duration| d.as_millis().div_ceil(1000) as i64;
+                    RespValue::Array(vec![
+                        RespValue::Integer(result.limited as i64),
+                        RespValue::Integer(result.limit as i64),
+                        RespValue::Integer(result.remaining as i64),
+                        RespValue::Integer(result.retry_after.map_or(-1, secs)),
+                        RespValue::Integer(secs(result.reset_after)),
+                    ])
+                }
+                Err(e) => error_reply(e),
+            },
+
+            // 分布式锁命令
+            // 键的值不是字符串时返回WRONGTYPE，不会覆盖或删除它
+            Command::Lock { key, token, ttl } => match self.store.lock(&key, &token, ttl) {
+                Ok(true) => resp::ok(),
+                Ok(false) => RespValue::Null,
+                Err(e) => error_reply(e),
+            },
+
+            Command::Unlock { key, token } => match self.store.unlock(&key, &token) {
+                Ok(released) => RespValue::Integer(released as i64),
+                Err(e) => error_reply(e),
+            },
+
+            // 认证、配置和事务命令依赖连接状态，由Connection处理
+            Command::Auth { .. } => {
+                resp::error("ERR AUTH is only available on a connection")
+            }
+
+            Command::ConfigReload | Command::ConfigSet { .. } => {
+                resp::error("ERR CONFIG is only available on a connection")
+            }
+
+            Command::ClientReply { .. } => {
+                resp::error("ERR CLIENT is only available on a connection")
+            }
+
+            Command::Multi
+            | Command::Exec
+            | Command::Discard
+            | Command::Watch { .. }
+            | Command::Unwatch => {
+                resp::error("ERR transaction commands are only available on a connection")
+            }
+
+            // 服务器命令
+            Command::DbSize => RespValue::Integer(self.store.dbsize() as i64),
+
+            Command::FlushDb { lazy: true } => {
+                self.store.flushdb_async();
+                resp::ok()
+            }
+
+            Command::FlushDb { lazy: false } => {
+                self.store.flushdb();
+                resp::ok()
+            }
+
+            Command::MemoryUsage { key } => match self.store.memory_usage(&key) {
+                Some(bytes) => RespValue::Integer(bytes as i64),
+                None => RespValue::Null,
+            },
+
+            Command::ObjectEncoding { key } => match self.store.encoding(&key) {
+                Some(encoding) => resp::bulk_string(encoding),
+                None => RespValue::Null,
+            },
+
+            Command::CommandTable => {
+                RespValue::Array(metadata::COMMANDS.iter().map(command_entry).collect())
+            }
+
+            Command::CommandCount => RespValue::Integer(metadata::COMMANDS.len() as i64),
+
+            Command::CommandList => RespValue::Array(
+                metadata::COMMANDS
+                    .iter()
+                    .map(|info| resp::bulk_string(&info.name.to_lowercase()))
+                    .collect(),
+            ),
+
+            // 不指定命令名时返回全部命令，未知命令对应nil
+            Command::CommandInfo { names } if names.is_empty() => {
+                RespValue::Array(metadata::COMMANDS.iter().map(command_entry).collect())
+            }
+            Command::CommandInfo { names } => RespValue::Array(
+                names
+                    .iter()
+                    .map(|name| metadata::lookup(name).map_or(RespValue::Null, command_entry))
+                    .collect(),
+            ),
+
+            Command::CommandGetKeys { argv } => match metadata::get_keys(&argv) {
+                Ok(keys) if keys.is_empty() => resp::error("ERR The command has no key arguments"),
+                Ok(keys) => RespValue::Array(keys.into_iter().map(resp::bulk_string).collect()),
+                Err(metadata::GetKeysError::UnknownCommand) => resp::error("ERR Invalid command specified"),
+                Err(metadata::GetKeysError::WrongArity) => {
+                    resp::error("ERR Invalid number of arguments specified for command")
+                }
+            },
+
+            Command::Help { container } => {
+                let mut lines = vec![format!(
+                    "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+                    container
+                )];
+                for info in metadata::subcommands(container) {
+                    lines.push(format!("{} {}", info.name, info.arguments).trim_end().to_string());
+                    lines.push(format!("    {}", info.summary));
+                }
+                lines.push("HELP".to_string());
+                lines.push("    显示本帮助".to_string());
+                RespValue::Array(lines.into_iter().map(RespValue::SimpleString).collect())
+            }
+
+            Command::Info => {
+                let info = format!(
+                    "# Server\r\n\
+                     redis_version:0.1.0\r\n\
+                     rust_version:{}\r\n\
+                     {}\
+                     blocked_clients:{}\r\n\
+                     {}\
+                     {}\
+                     # Keyspace\r\n\
+                     db0:keys={}\r\n",
+                    env!("CARGO_PKG_VERSION"),
+                    self.store.clients().info(),
+                    self.store.blocked_clients().len(),
+                    memory::info(),
+                    self.store.stats().info(),
+                    self.store.dbsize()
+                );
+                RespValue::BulkString(info.into_bytes())
+            }
+
+            Command::ModuleLoad { path, args } => match self.store.modules().load(&path, &args) {
+                Ok(_) => resp::ok(),
+                Err(e) => resp::error(&format!("ERR Error loading the extension: {}", e)),
+            },
+
+            Command::ModuleUnload { name } => match self.store.modules().unload(&name) {
+                Ok(()) => resp::ok(),
+                Err(e) => resp::error(&format!("ERR Error unloading module: {}", e)),
+            },
+
+            Command::ModuleList => RespValue::Array(
+                self.store
+                    .modules()
+                    .list()
+                    .into_iter()
+                    .map(|module| {
+                        RespValue::Array(vec![
+                            resp::bulk_string("name"),
+                            resp::bulk_string(&module.name),
+                            resp::bulk_string("ver"),
+                            RespValue::Integer(module.version),
+                            resp::bulk_string("path"),
+                            resp::bulk_string(&module.path),
+                            resp::bulk_string("args"),
+                            RespValue::Array(module.args.iter().map(|arg| resp::bulk_string(arg)).collect()),
+                        ])
+                    })
+                    .collect(),
+            ),
+
+            Command::Unknown { name, args } => self
+                .store
+                .modules()
+                .call(self.store, &name, &args)
+                .unwrap_or_else(|| resp::error(&format!("ERR unknown command '{}'", name))),
+        };
+
+        (response, should_quit)
+    }
+
+    /// BF.ADD/BF.MADD的公共实现，键不存在时按默认参数创建过滤器
+    ///
+    /// 每个元素单独返回结果: 不可扩展的过滤器满了时只有后面的元素失败
+    fn bf_add(&self, key: &str, items: Vec<Vec<u8>>) -> RedisResult<Vec<RedisResult<bool>>> {
+        self.store.update_as(key, |filter: &mut Option<BloomFilter>| {
+            let filter = match filter {
+                Some(filter) => filter,
+                None => {
+                    let created = BloomFilter::new(DEFAULT_ERROR_RATE, DEFAULT_BLOOM_CAPACITY, DEFAULT_EXPANSION)?;
+                    filter.insert(created)
+                }
+            };
+            let results: Vec<_> = items.iter().map(|item| filter.add(item)).collect();
+            let modified = results.iter().any(|r| matches!(r, Ok(true)));
+            Ok((results, modified))
+        })
+    }
+
+    /// HGETALL/HKEYS/HVALS的公共实现: 把哈希转换为批量字符串数组，键不存在时为空数组
+    fn hash_reply(&self, key: &str, f: impl FnOnce(&HashValue) -> Vec<Vec<u8>>) -> RespValue {
+        match self.store.read_as(key, f) {
+            Ok(items) => RespValue::Array(
+                items.unwrap_or_default().into_iter().map(RespValue::BulkString).collect(),
+            ),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// 读取SUNION/SINTER/SDIFF的各个集合，不存在的键视为空集合
+    ///
+    /// 任何一个键不是集合时返回WRONGTYPE错误
+    fn read_sets(&self, keys: &[String]) -> RedisResult<Vec<SetValue>> {
+        keys.iter()
+            .map(|key| Ok(self.store.read_as(key, SetValue::clone)?.unwrap_or_default()))
+            .collect()
+    }
+
+    /// SUNIONSTORE/SINTERSTORE/SDIFFSTORE: 在同一把写锁内计算并覆盖`destination`，回复结果的成员数
+    ///
+    /// 不存在的源键视为空集合；结果为空时删除目标键
+    fn set_store(
+        &self,
+        destination: &str,
+        keys: &[String],
+        op: fn(&[&SetValue]) -> SetValue,
+    ) -> RespValue {
+        let stored = self.store.store_from_all(keys, destination, |sets: Vec<Option<&SetValue>>| {
+            let empty = SetValue::new();
+            let sets: Vec<&SetValue> = sets.into_iter().map(|set| set.unwrap_or(&empty)).collect();
+            let result = op(&sets);
+            let len = result.len();
+            ((!result.is_empty()).then_some(result), len)
+        });
+        match stored {
+            Ok(len) => RespValue::Integer(len as i64),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// XREAD: 依次读取每个流中ID之后的条目，只回复有新条目的流，都没有时回复nil
+    fn xread(&self, streams: &[(String, Option<StreamId>)], count: Option<usize>) -> RespValue {
+        let mut replies = Vec::new();
+        for (key, id) in streams {
+            let entries = self.store.read_as(key, |stream: &Stream| {
+                let entries = stream.after(id.unwrap_or(stream.last_id()), count);
+                (!entries.is_empty()).then(|| stream_entries(entries))
+            });
+            match entries {
+                Ok(Some(Some(entries))) => replies.push(RespValue::Array(vec![
+                    RespValue::BulkString(key.as_bytes().to_vec()),
+                    entries,
+                ])),
+                Ok(_) => {}
+                Err(e) => return error_reply(e),
+            }
+        }
+        if replies.is_empty() {
+            RespValue::Null
+        } else {
+            RespValue::Array(replies)
+        }
+    }
+
+    /// 修改已存在的流(XGROUP SETID/DESTROY/CREATECONSUMER/DELCONSUMER)，键不存在时返回错误
+    fn update_stream<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Stream) -> RedisResult<(T, bool)>,
+    ) -> RedisResult<T> {
+        self.store.update_as(key, |stream: &mut Option<Stream>| match stream {
+            Some(stream) => f(stream),
+            None => Err(xgroup_missing_key()),
+        })
+    }
+
+    /// XREADGROUP: 以组内消费者的身份依次读取每个流
+    ///
+    /// 先检查所有键和消费者组都存在，避免读了一部分流之后才报错。
+    /// `>`只回复有新条目的流，重读待确认条目时总是回复该流；都没有时回复nil
+    fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> RespValue {
+        for (key, _) in streams {
+            match self.store.read_as(key, |stream: &Stream| stream.group(group).is_some()) {
+                Ok(Some(true)) => {}
+                Ok(_) => {
+                    let context = " in XREADGROUP with GROUP option";
+                    return error_reply(no_such_key_or_group(key, group, context));
+                }
+                Err(e) => return error_reply(e),
+            }
+        }
+
+        let now = self.store.clock().now_ms();
+        let mut replies = Vec::new();
+        for (key, id) in streams {
+            let result = self.store.update_as(key, |stream: &mut Option<Stream>| {
+                let entries = stream
+                    .as_mut()
+                    .and_then(|stream| stream.read_group(group, consumer, *id, count, noack, now))
+                    .unwrap_or_default();
+                let modified = id.is_none() && !entries.is_empty();
+                Ok((entries, modified))
+            });
+            let entries = match result {
+                Ok(entries) => entries,
+                Err(e) => return error_reply(e),
+            };
+            if id.is_none() && entries.is_empty() {
+                continue;
+            }
+            let entries = entries
+                .into_iter()
+                .map(|(id, fields)| stream_entry(id, fields.as_ref()))
+                .collect();
+            replies.push(RespValue::Array(vec![
+                RespValue::BulkString(key.as_bytes().to_vec()),
+                RespValue::Array(entries),
+            ]));
+        }
+        if replies.is_empty() {
+            RespValue::Null
+        } else {
+            RespValue::Array(replies)
+        }
+    }
+
+    /// ZRANK/ZREVRANK的公共实现，成员或键不存在时回复nil
+    fn zrank(&self, key: &str, member: &[u8], rev: bool) -> RespValue {
+        let rank = self.store.read_as(key, |zset: &SortedSet| {
+            zset.rank(member).map(|rank| if rev { zset.len() - 1 - rank } else { rank })
+        });
+        match rank {
+            Ok(Some(Some(rank))) => RespValue::Integer(rank as i64),
+            Ok(_) => RespValue::Null,
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// LPUSH/RPUSH的公共实现: 依次插入到头部或尾部，返回插入后的长度
+    fn push(&self, key: &str, values: Vec<Vec<u8>>, front: bool) -> RespValue {
+        let result = self.store.update_as(key, |list: &mut Option<ListValue>| {
+            self.limits.check_elements(list.as_ref().map_or(0, ListValue::len) + values.len())?;
+            let list = list.get_or_insert_with(ListValue::new);
+            for value in values {
+                if front {
+                    list.push_front(value);
+                } else {
+                    list.push_back(value);
+                }
+            }
+            Ok((list.len() as i64, true))
+        });
+        match result {
+            Ok(len) => RespValue::Integer(len),
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// 依次检查各个键，从第一个存在的键中弹出，返回键名和`pop`的结果
+    ///
+    /// `pop`同时返回弹出后值是否为空，为空时删除键；所有键都不存在时返回None，
+    /// 排在它之前的键类型不对时返回WRONGTYPE
+    fn pop_first<V: ValueKind, T>(
+        &self,
+        keys: &[String],
+        mut pop: impl FnMut(&mut V) -> (T, bool),
+    ) -> RedisResult<Option<(String, T)>> {
+        for key in keys {
+            let popped = self.store.update_as(key, |value: &mut Option<V>| {
+                let Some(current) = value else {
+                    return Ok((None, false));
+                };
+                let (popped, empty) = pop(current);
+                if empty {
+                    *value = None;
+                }
+                Ok((Some(popped), true))
+            })?;
+            if let Some(popped) = popped {
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// BLPOP/BRPOP不阻塞的部分: 从第一个非空列表弹出一个元素，回复`[键, 元素]`，都为空时回复nil
+    fn bpop(&self, keys: &[String], front: bool) -> RespValue {
+        let popped = self.pop_first(keys, |list: &mut ListValue| {
+            let item = if front { list.pop_front() } else { list.pop_back() };
+            (item, list.is_empty())
+        });
+        match popped {
+            Ok(Some((key, Some(item)))) => RespValue::Array(vec![
+                RespValue::BulkString(key.into_bytes()),
+                RespValue::BulkString(item),
+            ]),
+            Ok(_) => RespValue::Null,
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// LMPOP以及BLMPOP不阻塞的部分: 从第一个非空列表弹出最多`count`个元素，回复`[键, [元素, ...]]`
+    fn lmpop(&self, keys: &[String], front: bool, count: usize) -> RespValue {
+        let popped = self.pop_first(keys, |list: &mut ListValue| {
+            let items: Vec<Vec<u8>> = if front {
+                std::iter::from_fn(|| list.pop_front()).take(count).collect()
+            } else {
+                std::iter::from_fn(|| list.pop_back()).take(count).collect()
+            };
+            (items, list.is_empty())
+        });
+        match popped {
+            Ok(Some((key, items))) => RespValue::Array(vec![
+                RespValue::BulkString(key.into_bytes()),
+                RespValue::Array(items.into_iter().map(RespValue::BulkString).collect()),
+            ]),
+            Ok(None) => RespValue::Null,
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// ZMPOP以及BZMPOP不阻塞的部分: 从第一个非空有序集合弹出最多`count`个成员，
+    /// 回复`[键, [[成员, 分数], ...]]`
+    fn zmpop(&self, keys: &[String], min: bool, count: usize) -> RespValue {
+        let popped = self.pop_first(keys, |zset: &mut SortedSet| {
+            let members: Vec<(Vec<u8>, f64)> =
+                std::iter::from_fn(|| zset.pop(min)).take(count).collect();
+            (members, zset.is_empty())
+        });
+        match popped {
+            Ok(Some((key, members))) => RespValue::Array(vec![
+                RespValue::BulkString(key.into_bytes()),
+                RespValue::Array(
+                    members
+                        .into_iter()
+                        .map(|(member, score)| {
+                            RespValue::Array(vec![RespValue::BulkString(member), score_reply(score)])
+                        })
+                        .collect(),
+                ),
+            ]),
+            Ok(None) => RespValue::Null,
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// LMOVE/RPOPLPUSH以及BLMOVE不阻塞的部分: 从`source`的一端弹出元素，插入`destination`的一端，回复该元素
+    ///
+    /// 两个键在同一把写锁内修改；`source`为空时回复nil。两个键相同时把元素转到另一端
+    fn lmove(&self, source: &str, destination: &str, from_front: bool, to_front: bool) -> RespValue {
+        let keys = if source == destination { vec![source] } else { vec![source, destination] };
+        let result = self.store.update_all_as(&keys, |lists: &mut [Option<ListValue>]| {
+            let Some(list) = lists[0].as_mut() else {
+                return Ok((None, false));
+            };
+            let item = if from_front { list.pop_front() } else { list.pop_back() };
+            let Some(item) = item else {
+                return Ok((None, false));
+            };
+            if list.is_empty() {
+                lists[0] = None;
+            }
+            let Some(target) = lists.last_mut() else {
+                return Ok((None, false));
+            };
+            let target = target.get_or_insert_with(ListValue::new);
+            if to_front {
+                target.push_front(item.clone());
+            } else {
+                target.push_back(item.clone());
+            }
+            Ok((Some(item), true))
+        });
+        match result {
+            Ok(Some(item)) => RespValue::BulkString(item),
+            Ok(None) => RespValue::Null,
+            Err(e) => error_reply(e),
+        }
+    }
+
+    /// LPOP/RPOP的公共实现
+    ///
+    /// 没有count时回复一个元素，有count时回复数组；键不存在时都回复nil，弹出最后一个元素时删除键
+    fn pop(&self, key: &str, count: Option<usize>, front: bool) -> RespValue {
+        let result = self.store.update_as(key, |list: &mut Option<ListValue>| {
+            let Some(current) = list else {
+                return Ok((None, false));
+            };
+            let n = count.unwrap_or(1);
+            let popped: Vec<Vec<u8>> = if front {
+                std::iter::from_fn(|| current.pop_front()).take(n).collect()
+            } else {
+                std::iter::from_fn(|| current.pop_back()).take(n).collect()
+            };
+            if current.is_empty() {
+                *list = None;
+            }
+            Ok((Some(popped), n > 0))
+        });
+        match (result, count) {
+            (Ok(None), _) => RespValue::Null,
+            (Ok(Some(popped)), None) => {
+                popped.into_iter().next().map_or(RespValue::Null, RespValue::BulkString)
+            }
+            (Ok(Some(popped)), Some(_)) => {
+                RespValue::Array(popped.into_iter().map(RespValue::BulkString).collect())
+            }
+            (Err(e), _) => error_reply(e),
+        }
+    }
+
+    /// EXPIRE系列命令的公共实现: 在`base_ms`上加`offset_ms`毫秒后过期
+    ///
+    /// 相对时间(EXPIRE/PEXPIRE)的`base_ms`是当前时间，绝对时间戳(EXPIREAT/PEXPIREAT)为0。
+    /// 结果不晚于当前时间(包括负数)时删除键；相加溢出时返回错误，键不受影响
+    fn expire(&self, cmd: &str, key: &str, base_ms: u64, offset_ms: i64) -> RespValue {
+        let at_ms = i64::try_from(base_ms).ok().and_then(|base| base.checked_add(offset_ms));
+        match at_ms {
+            Some(at_ms) => RespValue::Integer(self.store.expire_at(key, at_ms.max(0) as u64) as i64),
+            None => error_reply(invalid_expire_time(cmd)),
+        }
+    }
+
+    /// INCR系列命令的公共实现
+    fn incr(&self, key: &str, delta: i64) -> RespValue {
+        match self.store.incr(key, delta) {
+            Ok(n) => RespValue::Integer(n),
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::EncodingThresholds;
+
+    #[test]
+    fn test_parse_ping() {
+        let value = RespValue::Array(vec![RespValue::BulkString(b"PING".to_vec())]);
+        let cmd = Command::from_resp(value).unwrap();
+        assert!(matches!(cmd, Command::Ping(None)));
+    }
+
+    #[test]
+    fn test_parse_set() {
+        let value = RespValue::Array(vec![
+            RespValue::BulkString(b"SET".to_vec()),
+            RespValue::BulkString(b"key".to_vec()),
+            RespValue::BulkString(b"value".to_vec()),
+        ]);
+        let cmd = Command::from_resp(value).unwrap();
+        assert!(matches!(cmd, Command::Set { .. }));
+    }
+
+    #[test]
+    fn test_execute_relative_expiry() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_700_000_000_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        assert!(matches!(
+            parse(&["EXPIRE", "k", "-1"]),
+            Ok(Command::Expire { milliseconds: -1000, .. })
+        ));
+        assert!(matches!(
+            parse(&["PEXPIRE", "k", "9223372036854775807"]),
+            Ok(Command::PExpire { milliseconds: i64::MAX, .. })
+        ));
+        assert_eq!(
+            parse_error(&["EXPIRE", "k", "9223372036854775807"]),
+            "invalid expire time in 'expire' command"
+        );
+        assert!(parse(&["EXPIRE", "k", "99999999999999999999"]).is_err());
+
+        // 加上当前时间后溢出的时长返回错误，键和原有的过期时间不变
+        run(&["SET", "k", "v"]);
+        run(&["EXPIRE", "k", "100"]);
+        let reply = run(&["PEXPIRE", "k", "9223372036854775807"]);
+        assert_eq!(reply, resp::error("ERR invalid expire time in 'pexpire' command"));
+        let reply = run(&["EXPIRE", "k", "9223372036854775"]);
+        assert_eq!(reply, resp::error("ERR invalid expire time in 'expire' command"));
+        assert_eq!(run(&["TTL", "k"]), RespValue::Integer(100));
+
+        // 负数和0删除键
+        assert_eq!(run(&["EXPIRE", "k", "-1"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXISTS", "k"]), RespValue::Integer(0));
+        run(&["SET", "k", "v"]);
+        assert_eq!(run(&["PEXPIRE", "k", "0"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXISTS", "k"]), RespValue::Integer(0));
+        assert_eq!(run(&["EXPIRE", "missing", "-1"]), RespValue::Integer(0));
+
+        // 很远的过期时间不会让TTL溢出成负数
+        run(&["SET", "far", "v"]);
+        let far = i64::MAX - 1_700_000_000_000;
+        assert_eq!(run(&["PEXPIRE", "far", &far.to_string()]), RespValue::Integer(1));
+        assert_eq!(run(&["PTTL", "far"]), RespValue::Integer(far));
+    }
+
+    #[test]
+    fn test_execute_absolute_expiry() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_700_000_000_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        run(&["SET", "k", "v"]);
+        assert_eq!(run(&["EXPIRETIME", "k"]), RespValue::Integer(-1));
+        assert_eq!(run(&["PEXPIRETIME", "missing"]), RespValue::Integer(-2));
+        assert_eq!(run(&["EXPIREAT", "k", "1700000100"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXPIRETIME", "k"]), RespValue::Integer(1_700_000_100));
+        assert_eq!(run(&["PEXPIRETIME", "k"]), RespValue::Integer(1_700_000_100_000));
+        assert_eq!(run(&["TTL", "k"]), RespValue::Integer(100));
+        assert_eq!(run(&["PEXPIREAT", "k", "1700000000250"]), RespValue::Integer(1));
+        assert_eq!(run(&["PTTL", "k"]), RespValue::Integer(250));
+        assert_eq!(run(&["EXPIRETIME", "k"]), RespValue::Integer(1_700_000_000));
+        assert_eq!(run(&["EXPIREAT", "missing", "1700000100"]), RespValue::Integer(0));
+
+        // SET EX写入的过期时间同样可以读成时间戳
+        run(&["SET", "s", "v", "PX", "1500"]);
+        assert_eq!(run(&["PEXPIRETIME", "s"]), RespValue::Integer(1_700_000_001_500));
+        // 毫秒部分不少于500时EXPIRETIME进位
+        assert_eq!(run(&["EXPIRETIME", "s"]), RespValue::Integer(1_700_000_002));
+        run(&["PEXPIREAT", "s", "1700000001499"]);
+        assert_eq!(run(&["EXPIRETIME", "s"]), RespValue::Integer(1_700_000_001));
+
+        // 已经过去的时间戳删除键
+        assert_eq!(run(&["PEXPIREAT", "s", "1600000000000"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXISTS", "s"]), RespValue::Integer(0));
+        assert_eq!(run(&["EXPIREAT", "k", "-10"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXISTS", "k"]), RespValue::Integer(0));
+
+        assert_eq!(
+            parse_error(&["EXPIREAT", "k", "9223372036854775807"]),
+            "invalid expire time in 'expireat' command"
+        );
+        assert!(parse(&["PEXPIREAT", "k", "soon"]).is_err());
+        // 与EXPIRE相同的换算: 负数保留到执行时，秒数换算溢出时报错
+        assert!(matches!(
+            parse(&["EXPIREAT", "k", "-1"]),
+            Ok(Command::ExpireAt { at_ms: -1000, .. })
+        ));
+        assert!(matches!(
+            parse(&["PEXPIREAT", "k", "-1"]),
+            Ok(Command::PExpireAt { at_ms: -1, .. })
+        ));
+        run(&["SET", "k", "v"]);
+        assert_eq!(run(&["PEXPIREAT", "k", "-9223372036854775808"]), RespValue::Integer(1));
+        assert_eq!(run(&["EXISTS", "k"]), RespValue::Integer(0));
+        assert!(parse(&["EXPIRETIME", "k", "extra"]).is_err());
+    }
+
+    #[test]
+    fn test_execute_set_options() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_700_000_000_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+
+        // GET回复旧值，键不存在时为nil
+        assert_eq!(run(&["SET", "k", "1", "GET"]), RespValue::Null);
+        assert_eq!(run(&["SET", "k", "2", "GET", "EX", "100"]), bulk("1"));
+        assert_eq!(store.pttl("k"), 100_000);
+        // KEEPTTL保留过期时间，普通SET清除过期时间
+        assert_eq!(run(&["SET", "k", "3", "KEEPTTL"]), resp::ok());
+        assert_eq!(store.pttl("k"), 100_000);
+        assert_eq!(run(&["SET", "k", "4"]), resp::ok());
+        assert_eq!(store.pttl("k"), -1);
+
+        // NX/XX不满足时不写入，带GET时仍然回复旧值
+        assert_eq!(run(&["SET", "k", "5", "NX"]), RespValue::Null);
+        assert_eq!(run(&["SET", "k", "5", "NX", "GET"]), bulk("4"));
+        assert_eq!(run(&["SET", "missing", "v", "XX", "GET"]), RespValue::Null);
+        assert_eq!(run(&["GET", "k"]), bulk("4"));
+        assert!(!store.exists("missing"));
+
+        assert_eq!(run(&["SET", "at", "v", "EXAT", "1700000010"]), resp::ok());
+        assert_eq!(store.pttl("at"), 10_000);
+        assert_eq!(run(&["SET", "at", "v", "PXAT", "1700000000500"]), resp::ok());
+        assert_eq!(store.pttl("at"), 500);
+        // 已经过去的时间戳: 写入成功但键立即过期
+        assert_eq!(run(&["SET", "at", "v", "PXAT", "1600000000000"]), resp::ok());
+        assert_eq!(run(&["EXISTS", "at"]), RespValue::Integer(0));
+
+        run(&["RPUSH", "l", "a"]);
+        assert_eq!(
+            run(&["SET", "l", "v", "GET"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+        assert_eq!(run(&["TYPE", "l"]), RespValue::SimpleString("list".to_string()));
+        assert_eq!(run(&["SET", "l", "v"]), resp::ok());
+    }
+
+    #[test]
+    fn test_execute_legacy_set_commands() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+
+        assert_eq!(run(&["SETNX", "k", "1"]), RespValue::Integer(1));
+        assert_eq!(run(&["SETNX", "k", "2"]), RespValue::Integer(0));
+        assert_eq!(run(&["GET", "k"]), bulk("1"));
+
+        assert_eq!(run(&["SETEX", "k", "10", "v"]), resp::ok());
+        assert_eq!(store.pttl("k"), 10_000);
+        assert_eq!(run(&["PSETEX", "p", "1500", "v"]), resp::ok());
+        assert_eq!(store.pttl("p"), 1_500);
+        // 过期后SETNX可以再次写入
+        clock.advance(Duration::from_millis(1_501));
+        assert_eq!(run(&["SETNX", "p", "again"]), RespValue::Integer(1));
+        assert_eq!(store.pttl("p"), -1);
+
+        assert_eq!(run(&["MSETNX", "a", "1", "k", "2"]), RespValue::Integer(0));
+        assert!(!store.exists("a"));
+        assert_eq!(run(&["MSETNX", "a", "1", "b", "2"]), RespValue::Integer(1));
+        assert_eq!(run(&["MGET", "a", "b"]), RespValue::Array(vec![bulk("1"), bulk("2")]));
+
+        assert_eq!(parse_error(&["SETEX", "k", "0", "v"]), "invalid expire time in 'setex' command");
+        assert_eq!(parse_error(&["PSETEX", "k", "-5", "v"]), "invalid expire time in 'psetex' command");
+        assert_eq!(parse_error(&["SETEX", "k", "x", "v"]), "value is not an integer or out of range");
+        assert!(parse(&["SETNX", "k"]).is_err());
+        assert!(parse(&["SETEX", "k", "10"]).is_err());
+    }
+
+    /// 把字符串参数解析为命令
+    fn parse(args: &[&str]) -> RedisResult<Command> {
+        Command::from_resp(RespValue::Array(
+            args.iter()
+                .map(|s| RespValue::BulkString(s.as_bytes().to_vec()))
+                .collect(),
+        ))
+    }
+
+    /// 解析失败时返回给客户端的错误信息
+    fn parse_error(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse_set_options() {
+        let after = |ms| Some(SetExpiry::After(Duration::from_millis(ms)));
+        let cases: &[(&[&str], Option<SetExpiry>, bool, bool)] = &[
+            (&["SET", "k", "v", "EX", "10"], after(10_000), false, false),
+            (&["SET", "k", "v", "px", "1500"], after(1500), false, false),
+            (&["SET", "k", "v", "NX", "EX", "1"], after(1000), true, false),
+            (&["SET", "k", "v", "PX", "5", "XX"], after(5), false, true),
+            // 重复同一个选项是允许的，以最后一个为准
+            (&["SET", "k", "v", "EX", "1", "EX", "2"], after(2000), false, false),
+            (&["SET", "k", "v", "NX", "NX"], None, true, false),
+            (&["SET", "k", "v", "EXAT", "1700000000"], Some(SetExpiry::At(1_700_000_000_000)), false, false),
+            (&["SET", "k", "v", "pxat", "1700000000123"], Some(SetExpiry::At(1_700_000_000_123)), false, false),
+            (&["SET", "k", "v", "XX", "KEEPTTL", "KEEPTTL"], Some(SetExpiry::Keep), false, true),
+        ];
+        for (args, expected_expiry, expected_nx, expected_xx) in cases {
+            match parse(args).unwrap() {
+                Command::Set { expiry, nx, xx, get: false, .. } => {
+                    assert_eq!(
+                        (expiry, nx, xx),
+                        (*expected_expiry, *expected_nx, *expected_xx),
+                        "{:?}",
+                        args
+                    );
+                }
+                cmd => panic!("{:?}", cmd),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_set_invalid_options() {
+        let syntax_errors: &[&[&str]] = &[
+            &["SET", "k", "v", "NX", "XX"],
+            &["SET", "k", "v", "XX", "NX"],
+            &["SET", "k", "v", "EX", "10", "PX", "100"],
+            &["SET", "k", "v", "PX", "100", "EX", "10"],
+            &["SET", "k", "v", "EX"],
+            &["SET", "k", "v", "NX", "PX"],
+            &["SET", "k", "v", "EX", "10", "garbage"],
+            &["SET", "k", "v", "garbage"],
+            &["SET", "k", "v", "KEEPTTL", "EX", "10"],
+            &["SET", "k", "v", "PX", "10", "KEEPTTL"],
+            &["SET", "k", "v", "EXAT", "10", "PXAT", "10000"],
+            &["SET", "k", "v", "EX", "10", "EXAT", "10"],
+            &["SET", "k", "v", "GET", "PXAT"],
+            &["SET", "k", "v", "EXAT"],
+        ];
+        for args in syntax_errors {
+            assert_eq!(parse_error(args), "syntax error", "{:?}", args);
+        }
+
+        for expire in ["0", "-1", "9223372036854775807"] {
+            assert_eq!(
+                parse_error(&["SET", "k", "v", "EX", expire]),
+                "invalid expire time in 'set' command"
+            );
+        }
+        assert_eq!(
+            parse_error(&["SET", "k", "v", "PX", "0"]),
+            "invalid expire time in 'set' command"
+        );
+        for (option, at) in [("EXAT", "0"), ("PXAT", "-1"), ("EXAT", "9223372036854775807")] {
+            assert_eq!(
+                parse_error(&["SET", "k", "v", option, at]),
+                "invalid expire time in 'set' command"
+            );
+        }
+        assert_eq!(
+            parse_error(&["SET", "k", "v", "EX", "ten"]),
+            "value is not an integer or out of range"
+        );
+    }
+
+    #[test]
+    fn test_subcommand_dispatch() {
+        assert!(matches!(parse(&["config", "reload"]), Ok(Command::ConfigReload)));
+        assert!(matches!(parse(&["FLUSHALL"]), Ok(Command::FlushDb { lazy: false })));
+        assert!(matches!(parse(&["flushdb", "async"]), Ok(Command::FlushDb { lazy: true })));
+        assert!(matches!(parse(&["FLUSHALL", "SYNC"]), Ok(Command::FlushDb { lazy: false })));
+        assert_eq!(parse_error(&["FLUSHDB", "LATER"]), "syntax error");
+        assert_eq!(parse_error(&["FLUSHDB", "ASYNC", "SYNC"]), "syntax error");
+        assert!(matches!(
+            parse(&["client", "reply", "skip"]),
+            Ok(Command::ClientReply { mode: ReplyMode::Skip })
+        ));
+        assert_eq!(parse_error(&["CLIENT", "REPLY", "maybe"]), "syntax error");
+        assert!(matches!(
+            parse(&["MEMORY", "usage", "k"]),
+            Ok(Command::MemoryUsage { key }) if key == "k"
+        ));
+        assert_eq!(
+            parse_error(&["CONFIG", "NOPE"]),
+            "unknown subcommand 'NOPE'. Try CONFIG HELP."
+        );
+        assert!(matches!(
+            parse(&["MEMORY", "USAGE"]),
+            Err(RedisError::WrongNumberOfArguments { command, expected: 1, got: 0 }) if command == "memory|usage"
+        ));
+        assert!(matches!(parse(&["CONFIG", "RELOAD", "extra"]), Err(RedisError::WrongNumberOfArguments { .. })));
+        assert!(matches!(
+            parse(&["config", "set", "deny-ips", "10.0.0.0/8"]),
+            Ok(Command::ConfigSet { name, value }) if name == "deny-ips" && value == "10.0.0.0/8"
+        ));
+        assert!(matches!(parse(&["CONFIG", "SET", "maxmemory"]), Err(RedisError::WrongNumberOfArguments { .. })));
+
+        // HELP由框架生成，列出表中登记的子命令
+        let store = Store::new();
+        let (response, _) = CommandExecutor::new(&store).execute(parse(&["memory", "help"]).unwrap());
+        let RespValue::Array(lines) = response else {
+            panic!("{:?}", response);
+        };
+        assert_eq!(lines[1], RespValue::SimpleString("USAGE key".to_string()));
+        assert_eq!(lines[lines.len() - 2], RespValue::SimpleString("HELP".to_string()));
+    }
+
+    #[test]
+    fn test_arity_from_metadata() {
+        assert!(matches!(
+            parse(&["GET"]),
+            Err(RedisError::WrongNumberOfArguments { expected: 1, got: 0, .. })
+        ));
+        assert!(matches!(
+            parse(&["GET", "a", "b"]),
+            Err(RedisError::WrongNumberOfArguments { .. })
+        ));
+        assert!(matches!(
+            parse(&["MGET"]),
+            Err(RedisError::WrongNumberOfArguments { .. })
+        ));
+        assert!(parse(&["MGET", "a", "b", "c"]).is_ok());
+        assert!(matches!(parse(&["NOPE", "x"]), Ok(Command::Unknown { .. })));
+    }
+
+    #[test]
+    fn test_execute_command_metadata() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        assert_eq!(
+            run(&["COMMAND", "COUNT"]),
+            RespValue::Integer(metadata::COMMANDS.len() as i64)
+        );
+        assert_eq!(
+            run(&["COMMAND", "INFO", "get", "nope"]),
+            RespValue::Array(vec![
+                RespValue::Array(vec![
+                    resp::bulk_string("get"),
+                    RespValue::Integer(2),
+                    RespValue::Array(vec![RespValue::SimpleString("readonly".to_string())]),
+                    RespValue::Integer(1),
+                    RespValue::Integer(1),
+                    RespValue::Integer(1),
+                ]),
+                RespValue::Null,
+            ])
+        );
+
+        let keys = |args: &[&str]| match run(args) {
+            RespValue::Array(keys) => keys.into_iter().filter_map(|k| k.as_string()).collect::<Vec<_>>(),
+            other => vec![format!("{:?}", other)],
+        };
+        assert_eq!(keys(&["COMMAND", "GETKEYS", "MSET", "a", "1", "b", "2"]), vec!["a", "b"]);
+        assert_eq!(keys(&["COMMAND", "GETKEYS", "RENAME", "a", "b"]), vec!["a", "b"]);
+        assert_eq!(keys(&["COMMAND", "GETKEYS", "MEMORY", "USAGE", "k"]), vec!["k"]);
+        assert_eq!(
+            run(&["COMMAND", "GETKEYS", "PING", "x"]),
+            resp::error("ERR The command has no key arguments")
+        );
+        assert_eq!(
+            run(&["COMMAND", "GETKEYS", "GET", "a", "b"]),
+            resp::error("ERR Invalid number of arguments specified for command")
+        );
+        assert_eq!(
+            run(&["COMMAND", "GETKEYS", "NOPE", "a"]),
+            resp::error("ERR Invalid command specified")
+        );
+    }
+
+    #[test]
+    fn test_keys_execution_budget() {
+        let store = Store::new();
+        for i in 0..3000 {
+            store.set(format!("key:{}", i), b"v".to_vec());
+        }
+
+        let (response, _) = CommandExecutor::new(&store)
+            .with_budget(Some(Duration::ZERO))
+            .execute(parse(&["KEYS", "*"]).unwrap());
+        assert!(matches!(response, RespValue::Error(e) if e.starts_with("BUSY")));
+
+        let (response, _) = CommandExecutor::new(&store)
+            .with_budget(None)
+            .execute(parse(&["KEYS", "*"]).unwrap());
+        assert!(matches!(response, RespValue::Array(keys) if keys.len() == 3000));
+    }
+
+    #[test]
+    fn test_command_keys() {
+        let cmd = Command::MSet {
+            pairs: vec![("a".to_string(), b"1".to_vec()), ("b".to_string(), b"2".to_vec())],
+        };
+        assert_eq!(cmd.keys(), vec!["a", "b"]);
+        assert_eq!(cmd.name(), "MSET");
+        assert!(Command::Ping(None).keys().is_empty());
+    }
+
+    #[test]
+    fn test_execute_scan() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        store.set("a".to_string(), b"1".to_vec());
+        store.set("b".to_string(), b"2".to_vec());
+
+        let value = RespValue::Array(
+            ["SCAN", "0", "MATCH", "a", "COUNT", "100"]
+                .iter()
+                .map(|s| RespValue::BulkString(s.as_bytes().to_vec()))
+                .collect(),
+        );
+        let (response, _) = executor.execute(Command::from_resp(value).unwrap());
+        assert_eq!(
+            response,
+            RespValue::Array(vec![
+                resp::bulk_string("0"),
+                RespValue::Array(vec![RespValue::BulkString(b"a".to_vec())]),
+            ])
+        );
+
+        let (response, _) = executor.execute(Command::MemoryUsage { key: "missing".to_string() });
+        assert_eq!(response, RespValue::Null);
+    }
+
+    #[test]
+    fn test_execute_incr_errors() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        store.set("n".to_string(), b" 1".to_vec());
+
+        let (response, _) = executor.execute(parse(&["INCR", "n"]).unwrap());
+        assert_eq!(response, resp::error("ERR value is not an integer or out of range"));
+
+        store.set("n".to_string(), b"-1".to_vec());
+        let (response, _) = executor.execute(parse(&["DECRBY", "n", &i64::MAX.to_string()]).unwrap());
+        assert_eq!(response, RespValue::Integer(i64::MIN));
+        let (response, _) = executor.execute(parse(&["DECR", "n"]).unwrap());
+        assert_eq!(response, resp::error("ERR increment or decrement would overflow"));
+        let (response, _) = executor.execute(parse(&["DECRBY", "n", &i64::MIN.to_string()]).unwrap());
+        assert_eq!(response, resp::error("ERR decrement would overflow"));
+    }
+
+    #[test]
+    fn test_execute_setrange_getrange() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |data: &[u8]| RespValue::BulkString(data.to_vec());
+
+        run(&["SET", "k", "Hello World"]);
+        assert_eq!(run(&["SETRANGE", "k", "6", "Redis"]), RespValue::Integer(11));
+        assert_eq!(run(&["GET", "k"]), bulk(b"Hello Redis"));
+        assert_eq!(run(&["GETRANGE", "k", "0", "4"]), bulk(b"Hello"));
+        assert_eq!(run(&["GETRANGE", "k", "-5", "-1"]), bulk(b"Redis"));
+        assert_eq!(run(&["GETRANGE", "k", "0", "-100"]), bulk(b"H"));
+        assert_eq!(run(&["GETRANGE", "k", "-1", "-5"]), bulk(b""));
+        assert_eq!(run(&["GETRANGE", "k", "5", "1000"]), bulk(b" Redis"));
+        assert_eq!(run(&["GETRANGE", "missing", "0", "-1"]), bulk(b""));
+
+        // 超出长度时用0填充，保留过期时间
+        run(&["EXPIRE", "k", "100"]);
+        assert_eq!(run(&["SETRANGE", "k", "13", "!"]), RespValue::Integer(14));
+        assert_eq!(run(&["GET", "k"]), bulk(b"Hello Redis\0\0!"));
+        assert!(store.pttl("k") > 0);
+        assert_eq!(run(&["SETRANGE", "new", "2", "x"]), RespValue::Integer(3));
+        assert_eq!(run(&["GET", "new"]), bulk(b"\0\0x"));
+        // 空值不创建键
+        assert_eq!(run(&["SETRANGE", "empty", "10", ""]), RespValue::Integer(0));
+        assert!(!store.exists("empty"));
+
+        run(&["RPUSH", "l", "a"]);
+        let wrong_type = resp::error("WRONGTYPE Operation against a key holding the wrong kind of value");
+        assert_eq!(run(&["SETRANGE", "l", "0", "x"]), wrong_type);
+        assert_eq!(run(&["GETRANGE", "l", "0", "1"]), wrong_type);
+        assert!(matches!(
+            parse(&["SETRANGE", "k", "-1", "x"]),
+            Err(RedisError::InvalidArgument(msg)) if msg == "offset is out of range"
+        ));
+        assert!(parse(&["SETRANGE", "k", "536870912", "x"]).is_err());
+        assert!(parse(&["SETRANGE", "k", "536870911", "x"]).is_ok());
+        assert!(parse(&["GETRANGE", "k", "a", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_execute_bitop_bitfield() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let ints = |values: &[i64]| {
+            RespValue::Array(values.iter().map(|&v| RespValue::Integer(v)).collect())
+        };
+        let wrong_type = resp::error("WRONGTYPE Operation against a key holding the wrong kind of value");
+        store.set("a".to_string(), b"\xff\x0f".to_vec());
+        store.set("b".to_string(), b"\x0f".to_vec());
+
+        assert_eq!(run(&["BITOP", "AND", "dest", "a", "b", "missing"]), RespValue::Integer(2));
+        assert_eq!(store.get("dest"), Some(b"\x00\x00".to_vec()));
+        assert_eq!(run(&["BITOP", "XOR", "dest", "a", "b"]), RespValue::Integer(2));
+        assert_eq!(store.get("dest"), Some(b"\xf0\x0f".to_vec()));
+        assert_eq!(run(&["BITOP", "NOT", "dest", "b"]), RespValue::Integer(1));
+        assert_eq!(store.get("dest"), Some(b"\xf0".to_vec()));
+        // 结果为空时删除目标键
+        assert_eq!(run(&["BITOP", "OR", "dest", "missing"]), RespValue::Integer(0));
+        assert!(!store.exists("dest"));
+        run(&["LPUSH", "list", "x"]);
+        assert_eq!(run(&["BITOP", "OR", "dest", "a", "list"]), wrong_type);
+        assert_eq!(
+            parse_error(&["BITOP", "NOT", "dest", "a", "b"]),
+            "BITOP NOT must be called with a single source key."
+        );
+        assert_eq!(parse_error(&["BITOP", "NAND", "dest", "a"]), "syntax error");
+
+        // 只读取时不创建键
+        assert_eq!(run(&["BITFIELD", "bits", "GET", "u8", "0"]), ints(&[0]));
+        assert!(!store.exists("bits"));
+        assert_eq!(
+            run(&[
+                "BITFIELD", "bits", "SET", "u8", "#1", "200", "GET", "u4", "8", "INCRBY", "u8", "8", "100",
+            ]),
+            ints(&[0, 12, 44])
+        );
+        assert_eq!(store.get("bits"), Some(b"\x00\x2c".to_vec()));
+        assert_eq!(
+            run(&[
+                "BITFIELD", "bits", "OVERFLOW", "FAIL", "INCRBY", "i8", "8", "100", "OVERFLOW", "SAT",
+                "INCRBY", "i8", "8", "100",
+            ]),
+            RespValue::Array(vec![RespValue::Null, RespValue::Integer(127)])
+        );
+        assert_eq!(run(&["BITFIELD", "list", "GET", "u8", "0"]), wrong_type);
+        assert_eq!(
+            parse_error(&["BITFIELD", "bits", "GET", "u64", "0"]),
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+        );
+        assert_eq!(
+            parse_error(&["BITFIELD", "bits", "GET", "u8", "-1"]),
+            "bit offset is not an integer or out of range"
+        );
+        assert_eq!(
+            parse_error(&["BITFIELD", "bits", "OVERFLOW", "NOPE"]),
+            "Invalid OVERFLOW type specified"
+        );
+        assert_eq!(parse_error(&["BITFIELD", "bits", "SET", "u8", "0"]), "syntax error");
+    }
+
+    #[test]
+    fn test_execute_json() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        assert_eq!(
+            run(&["JSON.SET", "doc", "$.a", "1"]),
+            resp::error("ERR new objects must be created at the root")
+        );
+        assert_eq!(run(&["JSON.SET", "doc", "$", r#"{"a":{"b":[1,2]},"c":"x"}"#]), resp::ok());
+        assert_eq!(run(&["JSON.SET", "doc", "$.c", "2", "NX"]), RespValue::Null);
+        assert_eq!(run(&["JSON.SET", "doc", "$.d", "true"]), resp::ok());
+
+        assert_eq!(run(&["JSON.GET", "doc", "$.a.b[*]"]), resp::bulk_string("[1,2]"));
+        assert_eq!(run(&["JSON.GET", "doc", ".c"]), resp::bulk_string(r#""x""#));
+        assert_eq!(run(&["JSON.GET", "doc", ".x"]), resp::error("ERR Path '.x' does not exist"));
+        assert_eq!(
+            run(&["JSON.GET", "doc", "$.c", "$.d"]),
+            resp::bulk_string(r#"{"$.c":["x"],"$.d":[true]}"#)
+        );
+        assert_eq!(run(&["JSON.GET", "missing"]), RespValue::Null);
+
+        assert_eq!(run(&["JSON.TYPE", "doc"]), RespValue::SimpleString("object".to_string()));
+        assert_eq!(
+            run(&["JSON.TYPE", "doc", "$.a.b[0]"]),
+            RespValue::Array(vec![RespValue::SimpleString("integer".to_string())])
+        );
+        assert_eq!(store.key_type("doc"), Some("ReJSON-RL"));
+
+        assert_eq!(run(&["JSON.DEL", "doc", "$.a.b[0]"]), RespValue::Integer(1));
+        assert_eq!(run(&["JSON.GET", "doc", ".a"]), resp::bulk_string(r#"{"b":[2]}"#));
+
+        // 类型不匹配时返回WRONGTYPE
+        let wrong = resp::error("WRONGTYPE Operation against a key holding the wrong kind of value");
+        store.set("s".to_string(), b"v".to_vec());
+        assert_eq!(run(&["JSON.GET", "s"]), wrong);
+        assert_eq!(run(&["GET", "doc"]), wrong);
+        assert_eq!(run(&["INCR", "doc"]), wrong);
+        assert!(parse(&["JSON.SET", "doc", "$", "{bad"]).is_err());
+        assert!(parse(&["JSON.GET", "doc", "$..a"]).is_err());
+
+        assert_eq!(run(&["JSON.DEL", "doc"]), RespValue::Integer(1));
+        assert!(!store.exists("doc"));
+    }
+
+    #[test]
+    fn test_execute_json_numincrby_arrappend() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let doc = r#"{"n":1,"f":1.5,"s":"x","list":[1],"o":{"n":2}}"#;
+
+        run(&["JSON.SET", "doc", "$", doc]);
+        assert_eq!(run(&["JSON.NUMINCRBY", "doc", ".n", "2"]), resp::bulk_string("3"));
+        assert_eq!(run(&["JSON.NUMINCRBY", "doc", "$.f", "1"]), resp::bulk_string("[2.5]"));
+        assert_eq!(
+            run(&["JSON.NUMINCRBY", "doc", "$.*", "10"]),
+            resp::bulk_string("[12.5,null,13,null,null]")
+        );
+        assert_eq!(
+            run(&["JSON.NUMINCRBY", "doc", ".s", "1"]),
+            resp::error("ERR wrong type of path value - expected a number but found string")
+        );
+        assert_eq!(run(&["JSON.NUMINCRBY", "doc", ".x", "1"]), resp::error("ERR Path '.x' does not exist"));
+        assert_eq!(
+            run(&["JSON.NUMINCRBY", "missing", "$", "1"]),
+            resp::error("ERR could not perform this operation on a key that doesn't exist")
+        );
+        assert!(parse(&["JSON.NUMINCRBY", "doc", "$.n", r#""1""#]).is_err());
+
+        assert_eq!(run(&["JSON.ARRAPPEND", "doc", ".list", "2", r#""a""#]), RespValue::Integer(3));
+        assert_eq!(
+            run(&["JSON.ARRAPPEND", "doc", "$.*", "null"]),
+            RespValue::Array(vec![
+                RespValue::Null,
+                RespValue::Integer(4),
+                RespValue::Null,
+                RespValue::Null,
+                RespValue::Null,
+            ])
+        );
+        assert_eq!(
+            run(&["JSON.GET", "doc", "$"]),
+            resp::bulk_string(r#"[{"f":12.5,"list":[1,2,"a",null],"n":13,"o":{"n":2},"s":"x"}]"#)
+        );
+        assert_eq!(
+            run(&["JSON.ARRAPPEND", "doc", ".o", "1"]),
+            resp::error("ERR wrong type of path value - expected array but found object")
+        );
+
+        // 任何一个结果溢出时所有位置都不修改
+        run(&["JSON.SET", "doc", "$.f", "1e308"]);
+        assert_eq!(
+            run(&["JSON.NUMINCRBY", "doc", "$.*", "1e308"]),
+            resp::error("ERR result is not a finite number")
+        );
+        assert_eq!(run(&["JSON.GET", "doc", ".n"]), resp::bulk_string("13"));
+        assert!(parse(&["JSON.ARRAPPEND", "doc", "$", "{bad"]).is_err());
+    }
+
+    #[test]
+    fn test_execute_timeseries() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000));
+        let store = Store::with_clock(clock);
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let samples = |samples: &[(i64, &str)]| {
+            RespValue::Array(
+                samples
+                    .iter()
+                    .map(|(ts, v)| RespValue::Array(vec![RespValue::Integer(*ts), resp::bulk_string(v)]))
+                    .collect(),
+            )
+        };
+
+        assert_eq!(
+            run(&["TS.ADD", "temp:eu", "10", "20.5", "RETENTION", "0", "LABELS", "area", "eu"]),
+            RespValue::Integer(10)
+        );
+        run(&["TS.ADD", "temp:eu", "15", "21.5"]);
+        assert_eq!(run(&["TS.ADD", "temp:eu", "*", "30"]), RespValue::Integer(1_000));
+        run(&["TS.ADD", "temp:us", "12", "5", "LABELS", "area", "us"]);
+        assert_eq!(store.key_type("temp:eu"), Some("TSDB-TYPE"));
+
+        assert_eq!(
+            run(&["TS.RANGE", "temp:eu", "-", "100"]),
+            samples(&[(10, "20.5"), (15, "21.5")])
+        );
+        assert_eq!(
+            run(&["TS.RANGE", "temp:eu", "-", "+", "AGGREGATION", "avg", "100"]),
+            samples(&[(0, "21"), (1_000, "30")])
+        );
+        assert_eq!(
+            run(&["TS.RANGE", "missing", "-", "+"]),
+            resp::error("ERR TSDB: the key does not exist")
+        );
+        assert_eq!(
+            run(&["TS.GET", "temp:eu"]),
+            RespValue::Array(vec![RespValue::Integer(1_000), resp::bulk_string("30")])
+        );
+        assert_eq!(run(&["TS.GET", "missing"]), resp::error("ERR TSDB: the key does not exist"));
+
+        assert_eq!(
+            run(&["TS.MRANGE", "-", "+", "WITHLABELS", "AGGREGATION", "max", "100", "FILTER", "area=us"]),
+            RespValue::Array(vec![RespValue::Array(vec![
+                resp::bulk_string("temp:us"),
+                RespValue::Array(vec![RespValue::Array(vec![
+                    resp::bulk_string("area"),
+                    resp::bulk_string("us"),
+                ])]),
+                samples(&[(0, "5")]),
+            ])])
+        );
+        let (response, _) = executor.execute(parse(&["TS.MRANGE", "0", "20", "FILTER", "area!=x"]).unwrap());
+        let RespValue::Array(series) = response else {
+            panic!("unexpected reply");
+        };
+        assert_eq!(series.len(), 2);
+
+        for bad in [
+            &["TS.ADD", "k", "abc", "1"][..],
+            &["TS.ADD", "k", "1", "nan"],
+            &["TS.ADD", "k", "1", "1", "LABELS", "a"],
+            &["TS.RANGE", "k", "-", "+", "AGGREGATION", "median", "10"],
+            &["TS.RANGE", "k", "-", "+", "AGGREGATION", "avg", "0"],
+            &["TS.MRANGE", "-", "+"],
+        ] {
+            assert!(parse(bad).is_err(), "{:?}", bad);
+        }
+        store.set("s".to_string(), b"v".to_vec());
+        assert_eq!(
+            run(&["TS.ADD", "s", "1", "1"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+    }
+
+    #[test]
+    fn test_execute_bloom_filter() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        assert_eq!(run(&["BF.RESERVE", "seen", "0.001", "10", "NONSCALING"]), resp::ok());
+        assert_eq!(run(&["BF.RESERVE", "seen", "0.01", "10"]), resp::error("ERR item exists"));
+        assert_eq!(run(&["BF.ADD", "seen", "a"]), RespValue::Integer(1));
+        assert_eq!(run(&["BF.ADD", "seen", "a"]), RespValue::Integer(0));
+        assert_eq!(run(&["BF.EXISTS", "seen", "a"]), RespValue::Integer(1));
+        assert_eq!(run(&["BF.EXISTS", "seen", "b"]), RespValue::Integer(0));
+        assert_eq!(run(&["BF.EXISTS", "missing", "a"]), RespValue::Integer(0));
+        assert_eq!(store.key_type("seen"), Some("MBbloom--"));
+
+        // 不可扩展的过滤器满了之后，后面的元素单独报错
+        let items: Vec<String> = (0..12).map(|i| format!("item:{}", i)).collect();
+        let mut args = vec!["BF.MADD", "seen"];
+        args.extend(items.iter().map(String::as_str));
+        let RespValue::Array(results) = run(&args) else {
+            panic!("unexpected reply");
+        };
+        assert_eq!(results.len(), 12);
+        assert_eq!(results[0], RespValue::Integer(1));
+        assert_eq!(results[11], resp::error("ERR non scaling filter is full"));
+
+        // 未预先创建时按默认参数创建
+        assert_eq!(
+            run(&["BF.MADD", "auto", "x", "y", "x"]),
+            RespValue::Array(vec![RespValue::Integer(1), RespValue::Integer(1), RespValue::Integer(0)])
+        );
+        assert_eq!(
+            run(&["BF.RESERVE", "bad", "1.5", "10"]),
+            resp::error("ERR (0 < error rate range < 1)")
+        );
+        assert!(parse(&["BF.RESERVE", "bad", "0.1", "10", "EXPANSION", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_execute_cuckoo_filter() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        assert_eq!(run(&["CF.DEL", "cf", "a"]), resp::error("ERR Not found"));
+        assert_eq!(run(&["CF.EXISTS", "cf", "a"]), RespValue::Integer(0));
+        assert_eq!(run(&["CF.ADD", "cf", "a"]), RespValue::Integer(1));
+        assert_eq!(run(&["CF.EXISTS", "cf", "a"]), RespValue::Integer(1));
+        assert_eq!(store.key_type("cf"), Some("MBbloomCF"));
+
+        assert_eq!(run(&["CF.DEL", "cf", "a"]), RespValue::Integer(1));
+        assert_eq!(run(&["CF.DEL", "cf", "a"]), RespValue::Integer(0));
+        assert_eq!(run(&["CF.EXISTS", "cf", "a"]), RespValue::Integer(0));
+
+        // 布隆过滤器和布谷鸟过滤器是不同的类型
+        run(&["BF.ADD", "bf", "a"]);
+        assert_eq!(
+            run(&["CF.ADD", "bf", "a"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+    }
+
+    #[test]
+    fn test_execute_hash() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let sorted = |reply: RespValue| match reply {
+            RespValue::Array(mut items) => {
+                items.sort_by_key(|item| item.as_string());
+                items
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        };
+
+        assert_eq!(run(&["HSET", "user", "name", "alice", "age", "30"]), RespValue::Integer(2));
+        // 覆盖已有字段不计入新增数
+        assert_eq!(run(&["HSET", "user", "name", "bob", "city", "x"]), RespValue::Integer(1));
+        assert_eq!(run(&["HGET", "user", "name"]), bulk("bob"));
+        assert_eq!(run(&["HGET", "user", "missing"]), RespValue::Null);
+        assert_eq!(
+            run(&["HMGET", "user", "age", "missing"]),
+            RespValue::Array(vec![bulk("30"), RespValue::Null])
+        );
+        assert_eq!(
+            run(&["HMGET", "nokey", "a", "b"]),
+            RespValue::Array(vec![RespValue::Null, RespValue::Null])
+        );
+        assert_eq!(run(&["HLEN", "user"]), RespValue::Integer(3));
+        assert_eq!(run(&["HEXISTS", "user", "city"]), RespValue::Integer(1));
+        assert_eq!(sorted(run(&["HKEYS", "user"])), vec![bulk("age"), bulk("city"), bulk("name")]);
+        assert_eq!(sorted(run(&["HVALS", "user"])), vec![bulk("30"), bulk("bob"), bulk("x")]);
+        assert_eq!(sorted(run(&["HGETALL", "user"])).len(), 6);
+        assert_eq!(run(&["HGETALL", "nokey"]), RespValue::Array(vec![]));
+        assert_eq!(store.key_type("user"), Some("hash"));
+
+        assert_eq!(run(&["HINCRBY", "user", "age", "5"]), RespValue::Integer(35));
+        assert_eq!(run(&["HINCRBY", "user", "visits", "-1"]), RespValue::Integer(-1));
+        assert_eq!(run(&["HINCRBY", "user", "name", "1"]), resp::error("ERR hash value is not an integer"));
+        // 与INCR一样只接受规范形式的整数
+        run(&["HSET", "user", "padded", " 1"]);
+        assert_eq!(run(&["HINCRBY", "user", "padded", "1"]), resp::error("ERR hash value is not an integer"));
+        run(&["HSET", "user", "big", &i64::MAX.to_string()]);
+        assert_eq!(
+            run(&["HINCRBY", "user", "big", "1"]),
+            resp::error("ERR increment or decrement would overflow")
+        );
+        assert!(parse(&["HINCRBY", "user", "age", "x"]).is_err());
+        assert!(parse(&["HSET", "user", "a", "1", "b"]).is_err());
+
+        // 删除最后一个字段时删除键
+        assert_eq!(run(&["HDEL", "user", "name", "missing"]), RespValue::Integer(1));
+        assert_eq!(run(&["HDEL", "user", "age", "city", "visits", "big", "padded"]), RespValue::Integer(5));
+        assert!(!store.exists("user"));
+        assert_eq!(run(&["HDEL", "user", "name"]), RespValue::Integer(0));
+
+        run(&["SET", "s", "v"]);
+        assert_eq!(
+            run(&["HGET", "s", "f"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+        run(&["HSET", "h", "f", "v"]);
+        assert_eq!(
+            run(&["GET", "h"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+    }
+
+    #[test]
+    fn test_execute_hash_field_expiry() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let replies = |values: &[i64]| {
+            RespValue::Array(values.iter().map(|&v| RespValue::Integer(v)).collect())
+        };
+
+        run(&["HSET", "h", "a", "1", "b", "2", "c", "3"]);
+        assert_eq!(run(&["HEXPIRE", "h", "10", "FIELDS", "2", "a", "missing"]), replies(&[1, -2]));
+        assert_eq!(run(&["HPEXPIRE", "h", "2500", "NX", "FIELDS", "2", "a", "b"]), replies(&[0, 1]));
+        assert_eq!(run(&["HEXPIRE", "h", "5", "GT", "FIELDS", "2", "a", "c"]), replies(&[0, 0]));
+        assert_eq!(run(&["HEXPIRE", "h", "5", "LT", "FIELDS", "1", "a"]), replies(&[1]));
+        assert_eq!(run(&["HTTL", "h", "FIELDS", "4", "a", "b", "c", "x"]), replies(&[5, 3, -1, -2]));
+        assert_eq!(run(&["HPTTL", "h", "FIELDS", "1", "b"]), replies(&[2500]));
+        assert_eq!(run(&["HTTL", "missing", "FIELDS", "1", "a"]), replies(&[-2]));
+
+        // 过期的字段在读取时被删除，HINCRBY保留过期时间，HSET覆盖时清除
+        run(&["HINCRBY", "b", "x", "1"]);
+        run(&["HINCRBY", "h", "b", "1"]);
+        run(&["HSET", "h", "a", "new"]);
+        assert_eq!(run(&["HTTL", "h", "FIELDS", "2", "a", "b"]), replies(&[-1, 3]));
+        clock.advance(Duration::from_millis(2501));
+        assert_eq!(run(&["HLEN", "h"]), RespValue::Integer(2));
+        assert_eq!(run(&["HGET", "h", "b"]), RespValue::Null);
+
+        assert_eq!(run(&["HEXPIREAT", "h", "2000", "FIELDS", "1", "a"]), replies(&[1]));
+        assert_eq!(run(&["HPERSIST", "h", "FIELDS", "3", "a", "c", "x"]), replies(&[1, -1, -2]));
+        assert_eq!(run(&["HTTL", "h", "FIELDS", "1", "a"]), replies(&[-1]));
+        // 时间已经过去时立即删除字段，删除最后一个字段时删除键
+        assert_eq!(run(&["HPEXPIREAT", "h", "0", "FIELDS", "1", "a"]), replies(&[2]));
+        assert_eq!(run(&["HPEXPIRE", "h", "100", "FIELDS", "1", "c"]), replies(&[1]));
+        clock.advance(Duration::from_millis(101));
+        assert_eq!(store.cleanup_expired(), 1);
+        assert!(!store.exists("h"));
+
+        assert_eq!(
+            parse(&["HEXPIRE", "h", "-1", "FIELDS", "1", "a"]).unwrap_err().to_string(),
+            "invalid expire time, must be >= 0"
+        );
+        assert_eq!(
+            parse(&["HEXPIRE", "h", "281474976710656", "FIELDS", "1", "a"]).unwrap_err().to_string(),
+            "invalid expire time in 'hexpire' command"
+        );
+        assert_eq!(
+            parse(&["HTTL", "h", "FIELDS", "2", "a"]).unwrap_err().to_string(),
+            "The `numfields` parameter must match the number of arguments"
+        );
+        assert_eq!(
+            parse(&["HTTL", "h", "FIELDS", "0", "a"]).unwrap_err().to_string(),
+            "Parameter `numFields` should be greater than 0"
+        );
+        assert_eq!(
+            parse(&["HPERSIST", "h", "FIELD", "1", "a"]).unwrap_err().to_string(),
+            "Mandatory argument FIELDS is missing or not at the right position"
+        );
+    }
+
+    #[test]
+    fn test_execute_list() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let items = |values: &[&str]| {
+            RespValue::Array(values.iter().map(|v| RespValue::BulkString(v.as_bytes().to_vec())).collect())
+        };
+
+        assert_eq!(run(&["RPUSH", "q", "b", "c"]), RespValue::Integer(2));
+        // 多个元素依次插入头部，结果与参数顺序相反
+        assert_eq!(run(&["LPUSH", "q", "a", "z"]), RespValue::Integer(4));
+        assert_eq!(run(&["LRANGE", "q", "0", "-1"]), items(&["z", "a", "b", "c"]));
+        assert_eq!(run(&["LRANGE", "q", "-3", "1"]), items(&["a"]));
+        assert_eq!(run(&["LRANGE", "q", "2", "100"]), items(&["b", "c"]));
+        assert_eq!(run(&["LRANGE", "q", "5", "10"]), items(&[]));
+        assert_eq!(run(&["LRANGE", "missing", "0", "-1"]), items(&[]));
+        assert_eq!(run(&["LLEN", "q"]), RespValue::Integer(4));
+        assert_eq!(run(&["LINDEX", "q", "-1"]), RespValue::BulkString(b"c".to_vec()));
+        assert_eq!(run(&["LINDEX", "q", "4"]), RespValue::Null);
+
+        assert_eq!(run(&["LPOP", "q"]), RespValue::BulkString(b"z".to_vec()));
+        assert_eq!(run(&["RPOP", "q", "2"]), items(&["c", "b"]));
+        assert_eq!(run(&["LPOP", "q", "0"]), items(&[]));
+        // 弹出最后一个元素时删除键
+        assert_eq!(run(&["RPOP", "q", "5"]), items(&["a"]));
+        assert!(!store.exists("q"));
+        assert_eq!(run(&["LPOP", "q"]), RespValue::Null);
+        assert_eq!(run(&["LPOP", "q", "2"]), RespValue::Null);
+        assert_eq!(store.key_type("q"), None);
+
+        assert!(parse(&["LPOP", "q", "-1"]).is_err());
+        assert!(parse(&["LRANGE", "q", "a", "1"]).is_err());
+        run(&["SET", "s", "v"]);
+        assert_eq!(
+            run(&["LPUSH", "s", "x"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+    }
+
+    #[test]
+    fn test_execute_large_list() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[String]| {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            executor.execute(parse(&args).unwrap()).0
+        };
+        let args = |line: &str| line.split(' ').map(str::to_string).collect::<Vec<_>>();
+        let item = |i: usize| RespValue::BulkString(i.to_string().into_bytes());
+
+        // 跨越多个块的列表
+        let mut push = args("RPUSH big");
+        push.extend((0..1000).map(|i| i.to_string()));
+        assert_eq!(run(&push), RespValue::Integer(1000));
+        assert_eq!(run(&args("LRANGE big 498 501")), RespValue::Array((498..502).map(item).collect()));
+        assert_eq!(run(&args("LINDEX big -300")), item(700));
+        assert_eq!(run(&args("LINSERT big BEFORE 600 x")), RespValue::Integer(1001));
+        assert_eq!(run(&args("LPOS big x")), RespValue::Integer(600));
+        assert_eq!(run(&args("LTRIM big 100 -101")), resp::ok());
+        assert_eq!(run(&args("LLEN big")), RespValue::Integer(801));
+        assert_eq!(run(&args("LPOP big")), item(100));
+        assert_eq!(run(&args("RPOP big 2")), RespValue::Array(vec![item(899), item(898)]));
+    }
+
+    #[test]
+    fn test_execute_object_encoding() {
+        let store = Store::new();
+        store.set_encoding_thresholds(EncodingThresholds {
+            hash_max_listpack_entries: 2,
+            set_max_intset_entries: 2,
+            set_max_listpack_entries: 3,
+            zset_max_listpack_value: 4,
+            ..Default::default()
+        });
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let encoding = |key: &str| run(&["OBJECT", "ENCODING", key]);
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+
+        run(&["SET", "n", "-42"]);
+        run(&["SET", "s", "hello"]);
+        run(&["SET", "r", &"x".repeat(45)]);
+        assert_eq!(encoding("n"), bulk("int"));
+        assert_eq!(encoding("s"), bulk("embstr"));
+        assert_eq!(encoding("r"), bulk("raw"));
+        assert_eq!(encoding("missing"), RespValue::Null);
+
+        run(&["HSET", "h", "a", "1", "b", "2"]);
+        assert_eq!(encoding("h"), bulk("listpack"));
+        run(&["HEXPIRE", "h", "100", "FIELDS", "1", "a"]);
+        assert_eq!(encoding("h"), bulk("listpackex"));
+        run(&["HSET", "h", "c", "3"]);
+        assert_eq!(encoding("h"), bulk("hashtable"));
+        // 转换是单向的
+        run(&["HDEL", "h", "b", "c"]);
+        assert_eq!(encoding("h"), bulk("hashtable"));
+        assert_eq!(run(&["HGET", "h", "a"]), bulk("1"));
+
+        run(&["SADD", "st", "1", "2"]);
+        assert_eq!(encoding("st"), bulk("intset"));
+        run(&["SADD", "st", "3"]);
+        assert_eq!(encoding("st"), bulk("listpack"));
+        run(&["SADD", "st", "x"]);
+        assert_eq!(encoding("st"), bulk("hashtable"));
+        assert_eq!(run(&["SCARD", "st"]), RespValue::Integer(4));
+
+        run(&["ZADD", "z", "1", "a", "2", "b"]);
+        assert_eq!(encoding("z"), bulk("listpack"));
+        assert_eq!(run(&["ZSCORE", "z", "b"]), bulk("2"));
+        run(&["ZADD", "z", "3", "long-member"]);
+        assert_eq!(encoding("z"), bulk("skiplist"));
+        assert_eq!(
+            run(&["ZRANGE", "z", "0", "-1"]),
+            RespValue::Array(vec![bulk("a"), bulk("b"), bulk("long-member")])
+        );
+
+        run(&["RPUSH", "l", "a"]);
+        assert_eq!(encoding("l"), bulk("listpack"));
+        let mut push = vec!["RPUSH", "l"];
+        push.extend(["x"; 200]);
+        run(&push);
+        assert_eq!(encoding("l"), bulk("quicklist"));
+
+        assert!(parse(&["OBJECT", "ENCODING"]).is_err());
+        assert!(parse(&["OBJECT", "NOPE", "k"]).is_err());
+    }
+
+    #[test]
+    fn test_execute_list_editing() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let items = |values: &[&str]| {
+            RespValue::Array(values.iter().map(|v| RespValue::BulkString(v.as_bytes().to_vec())).collect())
+        };
+        let positions = |values: &[i64]| {
+            RespValue::Array(values.iter().map(|&i| RespValue::Integer(i)).collect())
+        };
+
+        run(&["RPUSH", "q", "a", "b", "c", "1", "2", "3", "c", "c"]);
+        assert_eq!(run(&["LPOS", "q", "c"]), RespValue::Integer(2));
+        assert_eq!(run(&["LPOS", "q", "c", "RANK", "2"]), RespValue::Integer(6));
+        assert_eq!(run(&["LPOS", "q", "c", "RANK", "-1"]), RespValue::Integer(7));
+        assert_eq!(run(&["LPOS", "q", "c", "COUNT", "0"]), positions(&[2, 6, 7]));
+        assert_eq!(run(&["LPOS", "q", "c", "RANK", "-2", "COUNT", "2"]), positions(&[6, 2]));
+        assert_eq!(run(&["LPOS", "q", "c", "COUNT", "0", "MAXLEN", "3"]), positions(&[2]));
+        assert_eq!(run(&["LPOS", "q", "x"]), RespValue::Null);
+        assert_eq!(run(&["LPOS", "missing", "x", "COUNT", "1"]), positions(&[]));
+        assert!(parse(&["LPOS", "q", "c", "RANK", "0"]).is_err());
+        assert_eq!(
+            parse(&["LPOS", "q", "c", "COUNT", "-1"]).unwrap_err().to_string(),
+            "COUNT can't be negative"
+        );
+        assert!(parse(&["LPOS", "q", "c", "RANK"]).is_err());
+
+        assert_eq!(run(&["LREM", "q", "-2", "c"]), RespValue::Integer(2));
+        assert_eq!(run(&["LRANGE", "q", "0", "-1"]), items(&["a", "b", "c", "1", "2", "3"]));
+        assert_eq!(run(&["LINSERT", "q", "BEFORE", "c", "x"]), RespValue::Integer(7));
+        assert_eq!(run(&["LINSERT", "q", "after", "3", "y"]), RespValue::Integer(8));
+        assert_eq!(run(&["LINSERT", "q", "AFTER", "none", "y"]), RespValue::Integer(-1));
+        assert_eq!(run(&["LINSERT", "missing", "AFTER", "a", "y"]), RespValue::Integer(0));
+        assert!(!store.exists("missing"));
+        assert_eq!(run(&["LSET", "q", "-1", "z"]), resp::ok());
+        assert_eq!(run(&["LSET", "q", "8", "z"]), resp::error("ERR index out of range"));
+        assert_eq!(run(&["LSET", "missing", "0", "z"]), resp::error("ERR no such key"));
+        assert_eq!(run(&["LTRIM", "q", "1", "-2"]), resp::ok());
+        assert_eq!(run(&["LRANGE", "q", "0", "-1"]), items(&["b", "x", "c", "1", "2", "3"]));
+        assert_eq!(run(&["LREM", "q", "0", "x"]), RespValue::Integer(1));
+        assert_eq!(run(&["LREM", "q", "1", "none"]), RespValue::Integer(0));
+
+        // LMOVE/RPOPLPUSH与BLMOVE有数据时相同
+        assert_eq!(run(&["RPOPLPUSH", "q", "d"]), RespValue::BulkString(b"3".to_vec()));
+        assert_eq!(run(&["LMOVE", "q", "d", "LEFT", "RIGHT"]), RespValue::BulkString(b"b".to_vec()));
+        assert_eq!(run(&["LRANGE", "d", "0", "-1"]), items(&["3", "b"]));
+        assert_eq!(run(&["LMOVE", "missing", "d", "LEFT", "RIGHT"]), RespValue::Null);
+
+        // 裁剪为空时删除键
+        assert_eq!(run(&["LTRIM", "q", "5", "10"]), resp::ok());
+        assert!(!store.exists("q"));
+        run(&["SET", "s", "v"]);
+        assert_eq!(
+            run(&["LREM", "s", "0", "x"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+    }
+
+    #[test]
+    fn test_execute_blocking_list() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |value: &str| RespValue::BulkString(value.as_bytes().to_vec());
+
+        // 有数据时与LPOP/RPOP一样立即返回，回复中带上键名
+        run(&["RPUSH", "b", "1", "2"]);
+        assert_eq!(
+            run(&["BLPOP", "a", "b", "0"]),
+            RespValue::Array(vec![bulk("b"), bulk("1")])
+        );
+        assert_eq!(run(&["BRPOP", "a", "b", "1.5"]), RespValue::Array(vec![bulk("b"), bulk("2")]));
+        assert!(!store.exists("b"));
+        assert_eq!(run(&["BLPOP", "a", "b", "0"]), RespValue::Null);
+
+        run(&["RPUSH", "src", "x", "y"]);
+        assert_eq!(run(&["BLMOVE", "src", "dst", "RIGHT", "LEFT", "0"]), bulk("y"));
+        assert_eq!(run(&["BLMOVE", "src", "dst", "LEFT", "LEFT", "0"]), bulk("x"));
+        assert!(!store.exists("src"));
+        assert_eq!(run(&["LRANGE", "dst", "0", "-1"]), RespValue::Array(vec![bulk("x"), bulk("y")]));
+        // 源和目标相同时把元素转到另一端
+        assert_eq!(run(&["BLMOVE", "dst", "dst", "LEFT", "RIGHT", "0"]), bulk("x"));
+        assert_eq!(run(&["LRANGE", "dst", "0", "-1"]), RespValue::Array(vec![bulk("y"), bulk("x")]));
+        assert_eq!(run(&["BLMOVE", "src", "dst", "LEFT", "LEFT", "0"]), RespValue::Null);
+
+        // LMPOP与BLMPOP有数据时的回复相同；从RIGHT端弹出时按弹出的顺序回复
+        run(&["RPUSH", "m", "1", "2", "3"]);
+        let popped = |key: &str, items: &[&str]| {
+            RespValue::Array(vec![
+                bulk(key),
+                RespValue::Array(items.iter().map(|item| bulk(item)).collect()),
+            ])
+        };
+        assert_eq!(
+            run(&["BLMPOP", "0", "2", "a", "m", "RIGHT", "COUNT", "2"]),
+            popped("m", &["3", "2"])
+        );
+        assert_eq!(run(&["BLMPOP", "0", "1", "m", "LEFT", "COUNT", "5"]), popped("m", &["1"]));
+        assert_eq!(run(&["BLMPOP", "0.1", "1", "m", "LEFT"]), RespValue::Null);
+        run(&["RPUSH", "m", "4"]);
+        assert_eq!(run(&["LMPOP", "2", "m", "a", "LEFT"]), popped("m", &["4"]));
+        assert_eq!(run(&["LMPOP", "1", "m", "LEFT", "COUNT", "2"]), RespValue::Null);
+
+        run(&["SET", "s", "v"]);
+        let wrongtype =
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value");
+        assert_eq!(run(&["BLPOP", "s", "0"]), wrongtype);
+        assert_eq!(run(&["BLMOVE", "dst", "s", "LEFT", "LEFT", "0"]), wrongtype);
+        assert_eq!(run(&["LLEN", "dst"]), RespValue::Integer(2));
+
+        let blocking = parse(&["BLPOP", "a", "b", "0.5"]).unwrap();
+        assert_eq!(
+            blocking.blocking(),
+            Some((vec!["a", "b"], Some(Duration::from_millis(500))))
+        );
+        let blocking = parse(&["BLMOVE", "a", "b", "LEFT", "LEFT", "0"]).unwrap();
+        assert_eq!(blocking.blocking(), Some((vec!["a"], None)));
+        assert_eq!(
+            parse(&["BLPOP", "a", "-1"]).unwrap_err().to_string(),
+            "timeout is negative"
+        );
+        assert_eq!(
+            parse(&["BLPOP", "a", "x"]).unwrap_err().to_string(),
+            "timeout is not a float or out of range"
+        );
+        assert!(parse(&["BLMOVE", "a", "b", "UP", "LEFT", "0"]).is_err());
+        assert_eq!(
+            parse(&["BLMPOP", "0", "0", "a", "LEFT"]).unwrap_err().to_string(),
+            "numkeys should be greater than 0"
+        );
+        assert_eq!(
+            parse(&["BLMPOP", "0", "1", "a", "LEFT", "COUNT", "0"]).unwrap_err().to_string(),
+            "count should be greater than 0"
+        );
+        assert!(parse(&["BLMPOP", "0", "3", "a", "LEFT"]).is_err());
+        assert!(parse(&["BLMPOP", "0", "1", "a", "MIN"]).is_err());
+        let blocking = parse(&["BLMPOP", "0", "2", "a", "b", "LEFT", "COUNT", "3"]).unwrap();
+        assert_eq!(blocking.blocking(), Some((vec!["a", "b"], None)));
+    }
+
+    #[test]
+    fn test_execute_blocking_zset() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |value: &str| RespValue::BulkString(value.as_bytes().to_vec());
+
+        run(&["ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d"]);
+        assert_eq!(
+            run(&["BZPOPMIN", "missing", "z", "0"]),
+            RespValue::Array(vec![bulk("z"), bulk("a"), bulk("1")])
+        );
+        assert_eq!(
+            run(&["BZPOPMAX", "z", "0"]),
+            RespValue::Array(vec![bulk("z"), bulk("d"), bulk("4")])
+        );
+        assert_eq!(
+            run(&["BZMPOP", "0", "1", "z", "MAX", "COUNT", "5"]),
+            RespValue::Array(vec![
+                bulk("z"),
+                RespValue::Array(vec![
+                    RespValue::Array(vec![bulk("c"), bulk("3")]),
+                    RespValue::Array(vec![bulk("b"), bulk("2")]),
+                ]),
+            ])
+        );
+        assert!(!store.exists("z"));
+        assert_eq!(run(&["BZPOPMIN", "z", "0.01"]), RespValue::Null);
+        assert_eq!(run(&["BZMPOP", "0", "1", "z", "MIN"]), RespValue::Null);
+        run(&["ZADD", "z", "5", "e"]);
+        assert_eq!(
+            run(&["ZMPOP", "2", "missing", "z", "MIN", "COUNT", "2"]),
+            RespValue::Array(vec![
+                bulk("z"),
+                RespValue::Array(vec![RespValue::Array(vec![bulk("e"), bulk("5")])]),
+            ])
+        );
+        assert_eq!(run(&["ZMPOP", "1", "z", "MAX"]), RespValue::Null);
+
+        run(&["RPUSH", "l", "x"]);
+        assert_eq!(
+            run(&["BZPOPMIN", "l", "z", "0"]),
+            resp::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        );
+        assert!(parse(&["BZMPOP", "0", "1", "z", "LEFT"]).is_err());
+        assert!(parse(&["ZMPOP", "1", "z", "MAX", "COUNT", "-1"]).is_err());
+        assert_eq!(parse(&["ZMPOP", "1", "z", "MIN"]).unwrap().blocking(), None);
+        let blocking = parse(&["BZPOPMAX", "a", "b", "2"]).unwrap();
+        assert_eq!(blocking.blocking(), Some((vec!["a", "b"], Some(Duration::from_secs(2)))));
+    }
+
+    #[test]
+    fn test_execute_set() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let members = |args: &[&str]| match run(args) {
+            RespValue::Array(items) => {
+                let mut members: Vec<String> = items.iter().filter_map(RespValue::as_string).collect();
+                members.sort();
+                members
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        };
+
+        assert_eq!(run(&["SADD", "a", "x", "y", "z", "x"]), RespValue::Integer(3));
+        assert_eq!(run(&["SADD", "a", "x"]), RespValue::Integer(0));
+        assert_eq!(run(&["SADD", "b", "y", "w"]), RespValue::Integer(2));
+        assert_eq!(run(&["SCARD", "a"]), RespValue::Integer(3));
+        assert_eq!(run(&["SISMEMBER", "a", "y"]), RespValue::Integer(1));
+        assert_eq!(run(&["SISMEMBER", "a", "w"]), RespValue::Integer(0));
+        assert_eq!(members(&["SMEMBERS", "a"]), ["x", "y", "z"]);
+        assert_eq!(members(&["SMEMBERS", "missing"]), Vec::<String>::new());
+
+        assert_eq!(members(&["SUNION", "a", "b", "missing"]), ["w", "x", "y", "z"]);
+        assert_eq!(members(&["SINTER", "a", "b"]), ["y"]);
+        assert_eq!(members(&["SINTER", "a", "missing"]), Vec::<String>::new());
+        assert_eq!(members(&["SDIFF", "a", "b"]), ["x", "z"]);
+        assert_eq!(members(&["SDIFF", "missing", "a"]), Vec::<String>::new());
+
+        // 删除最后一个成员时删除键
+        assert_eq!(run(&["SREM", "b", "y", "v"]), RespValue::Integer(1));
+        assert_eq!(run(&["SREM", "b", "w"]), RespValue::Integer(1));
+        assert!(!store.exists("b"));
+        assert_eq!(store.key_type("a"), Some("set"));
+
+        assert_eq!(run(&["SADD", "c", "x", "y", "z", "w"]), RespValue::Integer(4));
+        assert_eq!(run(&["SINTERCARD", "2", "a", "c"]), RespValue::Integer(3));
+        assert_eq!(run(&["SINTERCARD", "2", "a", "c", "LIMIT", "2"]), RespValue::Integer(2));
+        assert_eq!(run(&["SINTERCARD", "2", "a", "c", "LIMIT", "0"]), RespValue::Integer(3));
+        assert_eq!(run(&["SINTERCARD", "2", "a", "missing"]), RespValue::Integer(0));
+        assert_eq!(
+            parse(&["SINTERCARD", "3", "a", "c"]).unwrap_err().to_string(),
+            "Number of keys can't be greater than number of args"
+        );
+        assert_eq!(
+            parse(&["SINTERCARD", "1", "a", "LIMIT", "-1"]).unwrap_err().to_string(),
+            "LIMIT can't be negative"
+        );
+        assert!(parse(&["SINTERCARD", "0", "a"]).is_err());
+        assert!(parse(&["SINTERCARD", "1", "a", "b"]).is_err());
+
+        run(&["SET", "s", "v"]);
+        let wrong_type = resp::error("WRONGTYPE Operation against a key holding the wrong kind of value");
+        assert_eq!(run(&["SADD", "s", "x"]), wrong_type);
+        assert_eq!(run(&["SUNION", "a", "s"]), wrong_type);
+        assert_eq!(run(&["SINTERCARD", "2", "a", "s"]), wrong_type);
+
+        // 结果覆盖目标键(无论原来的类型)，为空时删除目标键
+        assert_eq!(run(&["SINTERSTORE", "s", "a", "c"]), RespValue::Integer(3));
+        assert_eq!(members(&["SMEMBERS", "s"]), ["x", "y", "z"]);
+        assert_eq!(run(&["SUNIONSTORE", "u", "a", "c", "missing"]), RespValue::Integer(4));
+        assert_eq!(run(&["SDIFFSTORE", "d", "c", "a"]), RespValue::Integer(1));
+        assert_eq!(members(&["SMEMBERS", "d"]), ["w"]);
+        // 目标键也可以是源键
+        assert_eq!(run(&["SDIFFSTORE", "c", "c", "d"]), RespValue::Integer(3));
+        assert_eq!(run(&["SINTERSTORE", "d", "d", "missing"]), RespValue::Integer(0));
+        assert!(!store.exists("d"));
+        run(&["SET", "str", "v"]);
+        assert_eq!(run(&["SUNIONSTORE", "u", "a", "str"]), wrong_type);
+        assert_eq!(run(&["SCARD", "u"]), RespValue::Integer(4));
+    }
+
+    #[test]
+    fn test_execute_sorted_set() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let items = |values: &[&str]| RespValue::Array(values.iter().map(|v| bulk(v)).collect());
+
+        assert_eq!(
+            run(&["ZADD", "board", "10", "alice", "5", "bob", "7.5", "carol"]),
+            RespValue::Integer(3)
+        );
+        assert_eq!(run(&["ZADD", "board", "20", "alice", "1", "dave"]), RespValue::Integer(1));
+        assert_eq!(run(&["ZADD", "board", "CH", "21", "alice", "1", "dave"]), RespValue::Integer(1));
+        assert_eq!(run(&["ZADD", "board", "XX", "GT", "3", "bob", "1", "erin"]), RespValue::Integer(0));
+        assert_eq!(run(&["ZADD", "board", "INCR", "2.5", "bob"]), bulk("7.5"));
+        assert_eq!(run(&["ZADD", "board", "NX", "INCR", "1", "bob"]), RespValue::Null);
+        assert_eq!(run(&["ZADD", "missing", "XX", "1", "a"]), RespValue::Integer(0));
+        assert_eq!(run(&["ZADD", "missing", "XX", "INCR", "1", "a"]), RespValue::Null);
+        assert!(!store.exists("missing"));
+
+        assert_eq!(run(&["ZSCORE", "board", "alice"]), bulk("21"));
+        assert_eq!(run(&["ZSCORE", "board", "nobody"]), RespValue::Null);
+        assert_eq!(run(&["ZINCRBY", "board", "-0.5", "dave"]), bulk("0.5"));
+        assert_eq!(run(&["ZCARD", "board"]), RespValue::Integer(4));
+
+        // 分数相同的bob和carol按成员排序
+        assert_eq!(run(&["ZRANGE", "board", "0", "-1"]), items(&["dave", "bob", "carol", "alice"]));
+        assert_eq!(
+            run(&["ZRANGE", "board", "-2", "10", "WITHSCORES"]),
+            items(&["carol", "7.5", "alice", "21"])
+        );
+        assert_eq!(run(&["ZRANK", "board", "carol"]), RespValue::Integer(2));
+        assert_eq!(run(&["ZREVRANK", "board", "carol"]), RespValue::Integer(1));
+        assert_eq!(run(&["ZRANK", "board", "nobody"]), RespValue::Null);
+
+        assert_eq!(run(&["ZADD", "inf", "+inf", "a"]), RespValue::Integer(1));
+        assert_eq!(
+            run(&["ZINCRBY", "inf", "-inf", "a"]),
+            resp::error("ERR resulting score is not a number (NaN)")
+        );
+        assert_eq!(run(&["ZSCORE", "inf", "a"]), bulk("inf"));
+
+        // GT/LT只限制更新，新成员总是添加；INCR被拒绝时回复nil，CH不影响INCR的回复
+        assert_eq!(run(&["ZADD", "opts", "GT", "INCR", "5", "m"]), bulk("5"));
+        assert_eq!(run(&["ZADD", "opts", "GT", "INCR", "-1", "m"]), RespValue::Null);
+        assert_eq!(run(&["ZADD", "opts", "LT", "CH", "INCR", "-1", "m"]), bulk("4"));
+        assert_eq!(run(&["ZADD", "opts", "LT", "CH", "9", "m", "1", "n"]), RespValue::Integer(1));
+        assert_eq!(run(&["ZADD", "opts", "GT", "CH", "9", "m", "2", "n"]), RespValue::Integer(2));
+        assert_eq!(run(&["ZADD", "opts", "CH", "9", "m"]), RespValue::Integer(0));
+
+        assert_eq!(run(&["ZREM", "board", "alice", "bob", "nobody"]), RespValue::Integer(2));
+        assert_eq!(run(&["ZREM", "board", "carol", "dave"]), RespValue::Integer(2));
+        assert!(!store.exists("board"));
+        assert_eq!(store.key_type("inf"), Some("zset"));
+
+        assert_eq!(
+            parse_error(&["ZADD", "z", "NX", "XX", "1", "a"]),
+            "XX and NX options at the same time are not compatible"
+        );
+        assert_eq!(
+            parse_error(&["ZADD", "z", "GT", "LT", "1", "a"]),
+            "GT, LT, and/or NX options at the same time are not compatible"
+        );
+        assert_eq!(
+            parse_error(&["ZADD", "z", "INCR", "1", "a", "2", "b"]),
+            "INCR option supports a single increment-element pair"
+        );
+        assert_eq!(parse_error(&["ZADD", "z", "1", "a", "2"]), "syntax error");
+        assert_eq!(parse_error(&["ZADD", "z", "nan", "a"]), "value is not a valid float");
+        assert_eq!(parse_error(&["ZRANGE", "z", "0", "1", "LIMIT"]), "syntax error");
+    }
+
+    #[test]
+    fn test_execute_zrange_variants() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let items = |values: &[&str]| RespValue::Array(values.iter().map(|v| bulk(v)).collect());
+
+        run(&["ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d"]);
+        assert_eq!(run(&["ZREVRANGE", "z", "0", "1"]), items(&["d", "c"]));
+        assert_eq!(
+            run(&["ZRANGE", "z", "0", "1", "REV", "WITHSCORES"]),
+            items(&["d", "4", "c", "3"])
+        );
+
+        assert_eq!(run(&["ZRANGEBYSCORE", "z", "(1", "3"]), items(&["b", "c"]));
+        assert_eq!(
+            run(&["ZRANGEBYSCORE", "z", "-inf", "+inf", "LIMIT", "1", "2"]),
+            items(&["b", "c"])
+        );
+        assert_eq!(
+            run(&["ZREVRANGEBYSCORE", "z", "3", "(1", "WITHSCORES"]),
+            items(&["c", "3", "b", "2"])
+        );
+        assert_eq!(run(&["ZRANGE", "z", "(4", "2", "BYSCORE", "REV"]), items(&["c", "b"]));
+        assert_eq!(run(&["ZRANGEBYSCORE", "z", "5", "1"]), items(&[]));
+
+        run(&["ZADD", "lex", "0", "a", "0", "b", "0", "c", "0", "d"]);
+        assert_eq!(run(&["ZRANGEBYLEX", "lex", "[b", "(d"]), items(&["b", "c"]));
+        assert_eq!(run(&["ZREVRANGEBYLEX", "lex", "+", "[c", "LIMIT", "0", "1"]), items(&["d"]));
+        assert_eq!(
+            run(&["ZRANGE", "lex", "-", "+", "BYLEX", "LIMIT", "2", "-1"]),
+            items(&["c", "d"])
+        );
+        assert_eq!(run(&["ZRANGEBYLEX", "missing", "-", "+"]), items(&[]));
+
+        // ZRANGESTORE覆盖任意类型的目标键，结果为空时删除目标键
+        run(&["SET", "dst", "v"]);
+        assert_eq!(
+            run(&["ZRANGESTORE", "dst", "z", "4", "(1", "BYSCORE", "REV", "LIMIT", "0", "2"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(run(&["ZRANGE", "dst", "0", "-1", "WITHSCORES"]), items(&["c", "3", "d", "4"]));
+        assert_eq!(run(&["ZRANGESTORE", "dst", "missing", "0", "-1"]), RespValue::Integer(0));
+        assert!(!store.exists("dst"));
+        assert_eq!(run(&["ZRANGESTORE", "z", "z", "1", "2"]), RespValue::Integer(2));
+        assert_eq!(run(&["ZRANGE", "z", "0", "-1"]), items(&["b", "c"]));
+        run(&["SET", "str", "v"]);
+        assert!(matches!(
+            run(&["ZRANGESTORE", "dst", "str", "0", "-1"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+        assert_eq!(parse_error(&["ZRANGESTORE", "dst", "z", "0", "1", "WITHSCORES"]), "syntax error");
+
+        assert_eq!(
+            parse_error(&["ZRANGE", "z", "0", "1", "LIMIT", "0", "1"]),
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+        );
+        assert_eq!(
+            parse_error(&["ZRANGE", "z", "-", "+", "BYLEX", "WITHSCORES"]),
+            "syntax error, WITHSCORES not supported in combination with BYLEX"
+        );
+        assert_eq!(parse_error(&["ZRANGEBYLEX", "z", "-", "+", "WITHSCORES"]), "syntax error");
+        assert_eq!(parse_error(&["ZREVRANGE", "z", "0", "1", "BYSCORE"]), "syntax error");
+        assert_eq!(parse_error(&["ZRANGEBYSCORE", "z", "x", "1"]), "min or max is not a float");
+        assert_eq!(
+            parse_error(&["ZRANGEBYLEX", "z", "a", "+"]),
+            "min or max not valid string range item"
+        );
+    }
+
+    #[test]
+    fn test_execute_random_sampling() {
+        let store = Store::new();
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let len = |reply: RespValue| match reply {
+            RespValue::Array(items) => items.len(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+
+        run(&["ZADD", "z", "1", "a", "2", "b", "3", "c"]);
+        run(&["HSET", "h", "f1", "v1", "f2", "v2"]);
+
+        assert!([bulk("a"), bulk("b"), bulk("c")].contains(&run(&["ZRANDMEMBER", "z"])));
+        assert_eq!(run(&["ZRANDMEMBER", "missing"]), RespValue::Null);
+        assert_eq!(len(run(&["ZRANDMEMBER", "z", "2"])), 2);
+        assert_eq!(len(run(&["ZRANDMEMBER", "z", "10", "WITHSCORES"])), 6);
+        assert_eq!(len(run(&["ZRANDMEMBER", "z", "-5"])), 5);
+        assert_eq!(len(run(&["ZRANDMEMBER", "z", "0"])), 0);
+        assert_eq!(run(&["ZRANDMEMBER", "missing", "3"]), RespValue::Array(vec![]));
+        // 成员后面跟着它自己的分数
+        let RespValue::Array(pair) = run(&["ZRANDMEMBER", "z", "-1", "WITHSCORES"]) else {
+            panic!("expected an array");
+        };
+        assert_eq!(run(&["ZSCORE", "z", &pair[0].as_string().unwrap()]), pair[1]);
+
+        assert!([bulk("f1"), bulk("f2")].contains(&run(&["HRANDFIELD", "h"])));
+        assert_eq!(run(&["HRANDFIELD", "missing"]), RespValue::Null);
+        assert_eq!(len(run(&["HRANDFIELD", "h", "5", "WITHVALUES"])), 4);
+        assert_eq!(len(run(&["HRANDFIELD", "h", "-3"])), 3);
+        let RespValue::Array(pair) = run(&["HRANDFIELD", "h", "-1", "WITHVALUES"]) else {
+            panic!("expected an array");
+        };
+        assert_eq!(run(&["HGET", "h", &pair[0].as_string().unwrap()]), pair[1]);
+
+        run(&["SADD", "s", "a", "b", "c", "d"]);
+        assert!([bulk("a"), bulk("b"), bulk("c"), bulk("d")].contains(&run(&["SRANDMEMBER", "s"])));
+        assert_eq!(run(&["SRANDMEMBER", "missing"]), RespValue::Null);
+        assert_eq!(len(run(&["SRANDMEMBER", "s", "10"])), 4);
+        assert_eq!(len(run(&["SRANDMEMBER", "s", "-6"])), 6);
+        assert_eq!(run(&["SRANDMEMBER", "missing", "2"]), RespValue::Array(vec![]));
+        assert_eq!(run(&["SCARD", "s"]), RespValue::Integer(4));
+
+        // SPOP弹出的成员不重复，弹出最后一个成员时删除键
+        let RespValue::BulkString(popped) = run(&["SPOP", "s"]) else {
+            panic!("expected a member");
+        };
+        let popped = String::from_utf8(popped).unwrap();
+        assert_eq!(run(&["SISMEMBER", "s", &popped]), RespValue::Integer(0));
+        assert_eq!(len(run(&["SPOP", "s", "2"])), 2);
+        assert_eq!(run(&["SCARD", "s"]), RespValue::Integer(1));
+        assert_eq!(len(run(&["SPOP", "s", "0"])), 0);
+        assert_eq!(len(run(&["SPOP", "s", "5"])), 1);
+        assert!(!store.exists("s"));
+        assert_eq!(run(&["SPOP", "s"]), RespValue::Null);
+        assert_eq!(run(&["SPOP", "s", "1"]), RespValue::Array(vec![]));
+        assert_eq!(parse_error(&["SPOP", "s", "-1"]), "value is out of range, must be positive");
+
+        run(&["SADD", "m", "a", "b"]);
+        assert_eq!(
+            run(&["SMISMEMBER", "m", "a", "x", "b"]),
+            RespValue::Array(vec![RespValue::Integer(1), RespValue::Integer(0), RespValue::Integer(1)])
+        );
+        assert_eq!(
+            run(&["SMISMEMBER", "missing", "a", "b"]),
+            RespValue::Array(vec![RespValue::Integer(0), RespValue::Integer(0)])
+        );
+
+        assert_eq!(parse_error(&["ZRANDMEMBER", "z", "1", "WITHVALUES"]), "syntax error");
+        assert_eq!(parse_error(&["HRANDFIELD", "h", "1", "WITHVALUES", "x"]), "syntax error");
+        assert_eq!(parse_error(&["HRANDFIELD", "h", "x"]), "value is not an integer or out of range");
+        assert_eq!(
+            parse_error(&["ZRANDMEMBER", "z", &i64::MIN.to_string(), "WITHSCORES"]),
+            "value is out of range"
+        );
+    }
+
+    #[test]
+    fn test_execute_stream() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let entry = |id: &str, fields: &[&str]| {
+            let fields = fields.iter().map(|f| bulk(f)).collect();
+            RespValue::Array(vec![bulk(id), RespValue::Array(fields)])
+        };
+
+        assert_eq!(run(&["XADD", "s", "*", "n", "1"]), bulk("1000-0"));
+        assert_eq!(run(&["XADD", "s", "*", "n", "2"]), bulk("1000-1"));
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(run(&["XADD", "s", "*", "n", "3", "m", "x"]), bulk("1005-0"));
+        assert_eq!(run(&["XADD", "s", "2000-*", "n", "4"]), bulk("2000-0"));
+        assert_eq!(
+            run(&["XADD", "s", "1500", "n", "5"]),
+            resp::error("ERR The ID specified in XADD is equal or smaller than the target stream top item")
+        );
+        assert_eq!(run(&["XLEN", "s"]), RespValue::Integer(4));
+        assert_eq!(store.key_type("s"), Some("stream"));
+
+        assert_eq!(
+            run(&["XRANGE", "s", "1000-1", "1005"]),
+            RespValue::Array(vec![
+                entry("1000-1", &["n", "2"]),
+                entry("1005-0", &["n", "3", "m", "x"]),
+            ])
+        );
+        assert_eq!(
+            run(&["XREVRANGE", "s", "+", "(1000-1", "COUNT", "1"]),
+            RespValue::Array(vec![entry("2000-0", &["n", "4"])])
+        );
+        assert_eq!(run(&["XRANGE", "s", "-", "+", "COUNT", "0"]), RespValue::Null);
+        assert_eq!(run(&["XRANGE", "missing", "-", "+"]), RespValue::Array(vec![]));
+
+        // XREAD只回复有新条目的流
+        assert_eq!(
+            run(&["XREAD", "COUNT", "1", "STREAMS", "s", "missing", "1005", "0"]),
+            RespValue::Array(vec![RespValue::Array(vec![
+                bulk("s"),
+                RespValue::Array(vec![entry("2000-0", &["n", "4"])]),
+            ])])
+        );
+        assert_eq!(run(&["XREAD", "STREAMS", "s", "$"]), RespValue::Null);
+
+        assert_eq!(run(&["XTRIM", "s", "MAXLEN", "2"]), RespValue::Integer(2));
+        assert_eq!(run(&["XADD", "s", "MINID", "=", "2000", "3000", "n", "6"]), bulk("3000-0"));
+        assert_eq!(run(&["XLEN", "s"]), RespValue::Integer(2));
+        assert_eq!(run(&["XTRIM", "s", "MINID", "~", "9000", "LIMIT", "1"]), RespValue::Integer(1));
+        // 删空后流仍然存在
+        assert_eq!(run(&["XTRIM", "s", "MAXLEN", "0"]), RespValue::Integer(1));
+        assert!(store.exists("s"));
+
+        assert_eq!(run(&["XADD", "new", "NOMKSTREAM", "*", "f", "v"]), RespValue::Null);
+        assert!(!store.exists("new"));
+        // ID无效时不创建键
+        assert!(matches!(run(&["XADD", "new", "0-0", "f", "v"]), RespValue::Error(_)));
+        assert!(!store.exists("new"));
+
+        assert_eq!(
+            parse_error(&["XTRIM", "s", "MAXLEN", "1", "LIMIT", "1"]),
+            "syntax error, LIMIT cannot be used without the special ~ option"
+        );
+        assert_eq!(
+            parse_error(&["XTRIM", "s", "MAXLEN", "-1"]),
+            "The MAXLEN argument must be >= 0."
+        );
+        assert_eq!(
+            parse_error(&["XRANGE", "s", "x", "+"]),
+            "Invalid stream ID specified as stream command argument"
+        );
+        assert_eq!(parse_error(&["XRANGE", "s", "-", "+", "LIMIT", "1"]), "syntax error");
+        assert!(parse_error(&["XREAD", "STREAMS", "a", "b", "0"]).starts_with("Unbalanced 'xread'"));
+        assert_eq!(
+            parse_error(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]),
+            "XREAD BLOCK is not supported"
+        );
+        assert!(matches!(
+            parse(&["XADD", "s", "*", "f"]),
+            Err(RedisError::WrongNumberOfArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn test_execute_stream_groups() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let bulk = |s: &str| RespValue::BulkString(s.as_bytes().to_vec());
+        let entry = |id: &str, fields: &[&str]| {
+            let fields = fields.iter().map(|f| bulk(f)).collect();
+            RespValue::Array(vec![bulk(id), RespValue::Array(fields)])
+        };
+        let reply = |key: &str, entries: Vec<RespValue>| {
+            RespValue::Array(vec![RespValue::Array(vec![bulk(key), RespValue::Array(entries)])])
+        };
+
+        assert_eq!(
+            run(&["XGROUP", "CREATE", "s", "g", "$"]),
+            resp::error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")
+        );
+        assert_eq!(run(&["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"]), resp::ok());
+        assert_eq!(
+            run(&["XGROUP", "CREATE", "s", "g", "0"]),
+            resp::error("BUSYGROUP Consumer Group name already exists")
+        );
+        run(&["XADD", "s", "1-0", "n", "1"]);
+        run(&["XADD", "s", "2-0", "n", "2"]);
+
+        assert_eq!(
+            run(&["XREADGROUP", "GROUP", "g", "alice", "COUNT", "1", "STREAMS", "s", ">"]),
+            reply("s", vec![entry("1-0", &["n", "1"])])
+        );
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(
+            run(&["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"]),
+            reply("s", vec![entry("2-0", &["n", "2"])])
+        );
+        assert_eq!(run(&["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"]), RespValue::Null);
+        // 重读历史时即使没有条目也回复该流
+        assert_eq!(
+            run(&["XREADGROUP", "GROUP", "g", "carol", "STREAMS", "s", "0"]),
+            reply("s", vec![])
+        );
+        assert_eq!(
+            run(&["XREADGROUP", "GROUP", "nope", "c", "STREAMS", "s", ">"]),
+            resp::error("NOGROUP No such key 's' or consumer group 'nope' in XREADGROUP with GROUP option")
+        );
+
+        assert_eq!(
+            run(&["XPENDING", "s", "g"]),
+            RespValue::Array(vec![
+                RespValue::Integer(2),
+                bulk("1-0"),
+                bulk("2-0"),
+                RespValue::Array(vec![
+                    RespValue::Array(vec![bulk("alice"), bulk("1")]),
+                    RespValue::Array(vec![bulk("bob"), bulk("1")]),
+                ]),
+            ])
+        );
+        assert_eq!(
+            run(&["XPENDING", "s", "g", "IDLE", "50", "-", "+", "10"]),
+            RespValue::Array(vec![RespValue::Array(vec![
+                bulk("1-0"),
+                bulk("alice"),
+                RespValue::Integer(100),
+                RespValue::Integer(1),
+            ])])
+        );
+
+        // alice的条目闲置了100毫秒，交给bob
+        assert_eq!(
+            run(&["XCLAIM", "s", "g", "bob", "50", "1-0", "2-0", "JUSTID"]),
+            RespValue::Array(vec![bulk("1-0")])
+        );
+        let (zero, one) = (RespValue::Integer(0), RespValue::Integer(1));
+        assert_eq!(
+            run(&["XPENDING", "s", "g", "-", "+", "10", "bob"]),
+            RespValue::Array(vec![
+                RespValue::Array(vec![bulk("1-0"), bulk("bob"), zero.clone(), one.clone()]),
+                RespValue::Array(vec![bulk("2-0"), bulk("bob"), zero, one]),
+            ])
+        );
+        assert_eq!(
+            run(&["XCLAIM", "s", "g", "alice", "0", "2-0", "RETRYCOUNT", "5"]),
+            RespValue::Array(vec![entry("2-0", &["n", "2"])])
+        );
+        assert_eq!(
+            parse_error(&["XCLAIM", "s", "g", "alice", "0", "2-0", "BOGUS"]),
+            "Unrecognized XCLAIM option 'BOGUS'"
+        );
+
+        assert_eq!(run(&["XACK", "s", "g", "1-0", "1-0", "9-0"]), RespValue::Integer(1));
+        assert_eq!(run(&["XACK", "missing", "g", "1-0"]), RespValue::Integer(0));
+        assert_eq!(run(&["XGROUP", "CREATECONSUMER", "s", "g", "dave"]), RespValue::Integer(1));
+        assert_eq!(run(&["XGROUP", "CREATECONSUMER", "s", "g", "dave"]), RespValue::Integer(0));
+        assert_eq!(run(&["XGROUP", "DELCONSUMER", "s", "g", "alice"]), RespValue::Integer(1));
+        assert_eq!(
+            run(&["XPENDING", "s", "g"]),
+            RespValue::Array(vec![
+                RespValue::Integer(0),
+                RespValue::Null,
+                RespValue::Null,
+                RespValue::Null,
+            ])
+        );
+        assert_eq!(
+            run(&["XGROUP", "SETID", "s", "nope", "$"]),
+            resp::error("NOGROUP No such consumer group 'nope' for key name 's'")
+        );
+        assert_eq!(run(&["XGROUP", "SETID", "s", "g", "0"]), resp::ok());
+        assert_eq!(
+            run(&["XREADGROUP", "GROUP", "g", "dave", "NOACK", "STREAMS", "s", ">"]),
+            reply("s", vec![entry("1-0", &["n", "1"]), entry("2-0", &["n", "2"])])
+        );
+        assert_eq!(run(&["XPENDING", "s", "g", "-", "+", "10", "dave"]), RespValue::Array(vec![]));
+
+        assert_eq!(run(&["XGROUP", "DESTROY", "s", "g"]), RespValue::Integer(1));
+        assert_eq!(run(&["XGROUP", "DESTROY", "s", "g"]), RespValue::Integer(0));
+        assert_eq!(
+            run(&["XPENDING", "s", "g"]),
+            resp::error("NOGROUP No such key 's' or consumer group 'g'")
+        );
+        assert!(parse_error(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", "$"])
+            .starts_with("The $ ID is meaningless"));
+    }
+
+    #[test]
+    fn test_execute_throttle() {
+        let clock = std::sync::Arc::new(crate::clock::ManualClock::at(1_000_000));
+        let store = Store::with_clock(clock.clone());
+        let executor = CommandExecutor::new(&store);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let reply = |values: [i64; 5]| RespValue::Array(values.map(RespValue::Integer).to_vec());
+
+        // 每60秒30个请求(间隔2秒)，允许突发1个
+        assert_eq!(run(&["CL.THROTTLE", "user:1", "1", "30", "60"]), reply([0, 2, 1, -1, 2]));
+        assert_eq!(run(&["CL.THROTTLE", "user:1", "1", "30", "60"]), reply([0, 2, 0, -1, 4]));
+        assert_eq!(run(&["CL.THROTTLE", "user:1", "1", "30", "60"]), reply([1, 2, 0, 2, 4]));
+        assert!(store.pttl("user:1") > 0);
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(run(&["CL.THROTTLE", "user:1", "1", "30", "60"]), reply([0, 2, 0, -1, 4]));
+        // 空闲后键自动过期
+        clock.advance(Duration::from_secs(5));
+        assert!(!store.exists("user:1"));
+
+        assert_eq!(run(&["CL.THROTTLE", "user:2", "1", "30", "60", "3"]), reply([1, 2, 2, -1, 0]));
+        assert!(parse(&["CL.THROTTLE", "user:1", "1", "0", "60"]).is_err());
+        assert!(parse(&["CL.THROTTLE", "user:1", "-1", "30", "60"]).is_err());
+    }
+
+    #[test]
+    fn test_write_limits() {
+        let store = Store::new();
+        let limits = Limits {
+            max_key_size: 8,
+            max_value_size: 32,
+            max_elements: 3,
+        };
+        let executor = CommandExecutor::new(&store).with_limits(limits);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+        let is_error = |reply: RespValue| matches!(reply, RespValue::Error(_));
+
+        assert!(is_error(run(&["SET", "very-long-key", "v"])));
+        assert!(is_error(run(&["SET", "k", &"v".repeat(33)])));
+        assert!(is_error(run(&["MSET", "a", "1", "b", &"v".repeat(33)])));
+        assert!(!store.exists("a"));
+
+        // APPEND按追加后的总长度检查
+        assert_eq!(run(&["SET", "k", &"v".repeat(26)]), RespValue::SimpleString("OK".to_string()));
+        assert!(is_error(run(&["APPEND", "k", &"v".repeat(7)])));
+        assert_eq!(run(&["APPEND", "k", "vv"]), RespValue::Integer(28));
+        // SETRANGE按写入后的长度检查
+        assert!(is_error(run(&["SETRANGE", "k", "30", "vvv"])));
+        assert_eq!(run(&["SETRANGE", "k", "29", "vvv"]), RespValue::Integer(32));
+
+        // 读命令不受限制
+        assert_eq!(run(&["GET", "very-long-key"]), RespValue::Null);
+
+        assert!(is_error(run(&["JSON.SET", "doc", "$", "[1,2,3,4]"])));
+        let doc = r#"{"a":1,"b":2,"c":3}"#;
+        assert_eq!(run(&["JSON.SET", "doc", "$", doc]), RespValue::SimpleString("OK".to_string()));
+        // 修改后超出限制，原文档不变
+        assert!(is_error(run(&["JSON.SET", "doc", "$.d", "4"])));
+        assert_eq!(run(&["JSON.GET", "doc"]), RespValue::BulkString(doc.as_bytes().to_vec()));
+        run(&["JSON.SET", "list", "$", "[1,2]"]);
+        assert!(is_error(run(&["JSON.ARRAPPEND", "list", "$", "3", "4"])));
+        assert!(is_error(run(&["JSON.ARRAPPEND", "list", "$", "[1,2,3,4]"])));
+        assert_eq!(run(&["JSON.ARRAPPEND", "list", ".", "3"]), RespValue::Integer(3));
+
+        for ts in ["1", "2", "3"] {
+            assert_eq!(run(&["TS.ADD", "series", ts, "1"]), RespValue::Integer(ts.parse().unwrap()));
+        }
+        assert!(is_error(run(&["TS.ADD", "series", "4", "1"])));
+
+        // 集合元素数在写锁内检查，超出时不创建也不修改键
+        assert!(is_error(run(&["RPUSH", "l", "1", "2", "3", "4"])));
+        assert!(!store.exists("l"));
+        assert_eq!(run(&["RPUSH", "l", "1", "2", "3"]), RespValue::Integer(3));
+        assert!(is_error(run(&["LINSERT", "l", "BEFORE", "1", "0"])));
+        assert!(is_error(run(&["SADD", "s", "a", "b", "c", "d"])));
+        assert!(is_error(run(&["HSET", "h", "a", "1", "b", "2", "c", "3", "d", "4"])));
+        assert!(is_error(run(&["ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d"])));
+        assert!(!store.exists("s") && !store.exists("h") && !store.exists("z"));
+        run(&["XADD", "x", "*", "f", "v"]);
+        run(&["XADD", "x", "*", "f", "v"]);
+        run(&["XADD", "x", "*", "f", "v"]);
+        assert!(is_error(run(&["XADD", "x", "*", "f", "v"])));
+        assert_eq!(run(&["XLEN", "x"]), RespValue::Integer(3));
+    }
+
+    #[test]
+    fn test_write_limits_do_not_count_lookups() {
+        let store = Store::new();
+        let limits = Limits {
+            max_value_size: 512 * 1024 * 1024,
+            ..Limits::default()
+        };
+        let executor = CommandExecutor::new(&store).with_limits(limits);
+        let run = |args: &[&str]| executor.execute(parse(args).unwrap()).0;
+
+        // 默认配置下也有max-value-size，写命令的检查不能算作一次读取
+        for args in [
+            &["RPUSH", "l", "a"][..],
+            &["SADD", "s", "a"],
+            &["HSET", "h", "f", "v"],
+            &["ZADD", "z", "1", "a"],
+            &["APPEND", "k", "v"],
+            &["SETRANGE", "r", "1", "v"],
+        ] {
+            run(args);
+        }
+        assert_eq!(store.stats().keyspace_misses(), 0);
+        assert_eq!(store.stats().keyspace_hits(), 0);
     }
 
     #[test]
@@ -623,6 +7029,7 @@ mod tests {
             expiry: None,
             nx: false,
             xx: false,
+            get: false,
         });
         assert_eq!(response, RespValue::SimpleString("OK".to_string()));
 
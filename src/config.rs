@@ -0,0 +1,1011 @@
+//! 配置模块 - 服务器配置项
+//!
+//! 配置项名称与redis.conf一致，既可以来自配置文件，也可以来自命令行(见`cli`模块)
+//!
+//! Rust特点展示:
+//! - FromStr trait 让枚举可以用`parse()`解析
+//! - Default trait 提供默认配置
+//! - Arc<RwLock<T>> 在连接之间共享配置
+
+use crate::encoding::{self, EncodingThresholds};
+use crate::error::{RedisError, RedisResult};
+use crate::memory::{self, EvictionPolicy};
+use crate::DEFAULT_PORT;
+use ipnet::IpNet;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 日志级别，沿用Redis的四个级别名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogLevel {
+    /// 大量信息，适合开发和测试
+    Debug,
+    /// 许多很少用到的信息
+    Verbose,
+    /// 适度详细，生产环境使用
+    #[default]
+    Notice,
+    /// 只记录非常重要的信息
+    Warning,
+}
+
+impl LogLevel {
+    /// 对应的tracing过滤指令
+    pub fn as_filter(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "trace",
+            LogLevel::Verbose => "debug",
+            LogLevel::Notice => "info",
+            LogLevel::Warning => "warn",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        match s.to_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "verbose" => Ok(LogLevel::Verbose),
+            "notice" => Ok(LogLevel::Notice),
+            "warning" => Ok(LogLevel::Warning),
+            _ => Err(RedisError::Config(format!("无效的loglevel: {}", s))),
+        }
+    }
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// 便于人阅读的单行文本
+    #[default]
+    Human,
+    /// 每行一个JSON对象，便于日志系统采集
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(RedisError::Config(format!("无效的log-format: {}", s))),
+        }
+    }
+}
+
+/// 客户端命令速率超过client-command-rate时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitAction {
+    /// 推迟执行，直到速率回到限制以内
+    #[default]
+    Throttle,
+    /// 回复错误并断开连接
+    Disconnect,
+}
+
+impl FromStr for RateLimitAction {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        match s.to_lowercase().as_str() {
+            "throttle" => Ok(RateLimitAction::Throttle),
+            "disconnect" => Ok(RateLimitAction::Disconnect),
+            _ => Err(RedisError::Config(format!("无效的client-rate-action: {}", s))),
+        }
+    }
+}
+
+/// 缓存模式预设修改的配置项: 预设之前的值和预设设置的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CachePreset {
+    maxmemory: (u64, u64),
+    maxmemory_policy: (EvictionPolicy, EvictionPolicy),
+    lazyfree_lazy_eviction: (bool, bool),
+}
+
+/// 服务器配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// 监听端口
+    pub port: u16,
+    /// 监听地址
+    pub bind: String,
+    /// Unix socket路径；为None时不监听
+    pub unixsocket: Option<PathBuf>,
+    /// 内存上限(字节)，0表示不限制
+    pub maxmemory: u64,
+    /// 所有客户端连接缓冲区的内存上限(字节)，超过时断开占用最多的连接；0表示不限制
+    pub maxmemory_clients: u64,
+    /// 超过maxmemory时的淘汰策略
+    pub maxmemory_policy: EvictionPolicy,
+    /// 是否在后台线程中释放被淘汰的值
+    pub lazyfree_lazy_eviction: bool,
+    /// 缓存模式: 按缓存场景设置默认值，并定期在日志中报告命中率
+    pub cache_mode: bool,
+    /// 缓存模式预设覆盖之前的值，`cache-mode no`时恢复；预设没有生效时为None
+    pub(crate) cache_preset: Option<CachePreset>,
+    /// 连接需要通过AUTH提供的密码
+    pub requirepass: Option<String>,
+    /// 日志级别
+    pub loglevel: LogLevel,
+    /// 日志文件；为None时输出到标准输出
+    pub logfile: Option<PathBuf>,
+    /// 日志格式
+    pub log_format: LogFormat,
+    /// OTLP导出地址，如`http://localhost:4318/v1/traces`；需要启用otel特性
+    pub otlp_endpoint: Option<String>,
+    /// 健康检查HTTP端口(`GET /healthz`)；为None时不启动
+    pub health_port: Option<u16>,
+    /// 是否以守护进程方式在后台运行
+    pub daemonize: bool,
+    /// pid文件路径
+    pub pidfile: Option<PathBuf>,
+    /// 上游Redis地址(host:port)；设置后本服务器未实现的命令转发到上游
+    pub upstream: Option<String>,
+    /// 键匹配该模式的命令也转发到上游，如`legacy:*`
+    pub upstream_keys: Option<String>,
+    /// 单条命令的执行时间预算(毫秒)，0表示不限制
+    pub busy_reply_threshold: u64,
+    /// 键的最大长度(字节)，0表示不限制
+    pub max_key_size: u64,
+    /// 值和单个请求参数的最大长度(字节)，0表示不限制
+    pub max_value_size: u64,
+    /// 哈希、列表、集合、有序集合、流、JSON数组/对象、时间序列的最大元素数，0表示不限制
+    pub max_collection_elements: u64,
+    /// 哈希使用listpack编码的最大字段数
+    pub hash_max_listpack_entries: usize,
+    /// 哈希使用listpack编码时字段和值的最大长度
+    pub hash_max_listpack_value: usize,
+    /// 集合使用intset编码的最大成员数
+    pub set_max_intset_entries: usize,
+    /// 集合使用listpack编码的最大成员数
+    pub set_max_listpack_entries: usize,
+    /// 集合使用listpack编码时成员的最大长度
+    pub set_max_listpack_value: usize,
+    /// 有序集合使用listpack编码的最大成员数
+    pub zset_max_listpack_entries: usize,
+    /// 有序集合使用listpack编码时成员的最大长度
+    pub zset_max_listpack_value: usize,
+    /// 同一IP的最大连接数，0表示不限制
+    pub maxclients_per_ip: u64,
+    /// 每个客户端每秒最多执行的命令数，0表示不限制
+    pub client_command_rate: u64,
+    /// 超过client-command-rate时的处理方式
+    pub client_rate_action: RateLimitAction,
+    /// 允许连接的网段；为空时允许所有地址
+    pub allow_ips: Vec<IpNet>,
+    /// 拒绝连接的网段，优先于allow-ips
+    pub deny_ips: Vec<IpNet>,
+    /// 审计日志: 文件路径或`syslog`；为None时不记录
+    pub audit_log: Option<String>,
+    /// 审计日志中需要隐去值的键(glob模式)
+    pub audit_redact_keys: Vec<String>,
+    /// 平滑升级或停止时等待已有连接结束的最长时间(秒)，0表示一直等待
+    pub upgrade_drain_timeout: u64,
+    /// 被重命名或禁用的命令: 原名(大写) -> 新名，空字符串表示禁用
+    pub renamed_commands: BTreeMap<String, String>,
+    /// 是否允许客户端执行MODULE命令；默认关闭，与Redis的enable-module-command一致
+    pub enable_module_command: bool,
+    /// 启动时加载的模块: (共享库路径, 参数)
+    pub loadmodule: Vec<(String, Vec<String>)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let thresholds = EncodingThresholds::default();
+        Self {
+            port: DEFAULT_PORT,
+            bind: "0.0.0.0".to_string(),
+            unixsocket: None,
+            maxmemory: 0,
+            maxmemory_clients: 0,
+            maxmemory_policy: EvictionPolicy::default(),
+            lazyfree_lazy_eviction: false,
+            cache_mode: false,
+            cache_preset: None,
+            requirepass: None,
+            loglevel: LogLevel::default(),
+            logfile: None,
+            log_format: LogFormat::default(),
+            otlp_endpoint: None,
+            health_port: None,
+            daemonize: false,
+            pidfile: None,
+            upstream: None,
+            upstream_keys: None,
+            busy_reply_threshold: 0,
+            max_key_size: 0,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            max_collection_elements: 0,
+            hash_max_listpack_entries: thresholds.hash_max_listpack_entries,
+            hash_max_listpack_value: thresholds.hash_max_listpack_value,
+            set_max_intset_entries: thresholds.set_max_intset_entries,
+            set_max_listpack_entries: thresholds.set_max_listpack_entries,
+            set_max_listpack_value: thresholds.set_max_listpack_value,
+            zset_max_listpack_entries: thresholds.zset_max_listpack_entries,
+            zset_max_listpack_value: thresholds.zset_max_listpack_value,
+            maxclients_per_ip: 0,
+            client_command_rate: 0,
+            client_rate_action: RateLimitAction::default(),
+            allow_ips: Vec::new(),
+            deny_ips: Vec::new(),
+            audit_log: None,
+            audit_redact_keys: Vec::new(),
+            upgrade_drain_timeout: DEFAULT_UPGRADE_DRAIN_TIMEOUT,
+            renamed_commands: BTreeMap::new(),
+            enable_module_command: false,
+            loadmodule: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// 读取redis.conf格式的配置文件
+    pub fn from_file(path: impl AsRef<Path>) -> RedisResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            RedisError::Config(format!("无法读取配置文件 {}: {}", path.display(), e))
+        })?;
+        Self::parse_conf(&content)
+    }
+
+    /// 解析配置文件内容
+    ///
+    /// 每行一个`name value`，`#`开头的行是注释；值可以用双引号包围
+    pub fn parse_conf(content: &str) -> RedisResult<Self> {
+        let mut config = Config::default();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+
+            config.apply(name, value).map_err(|e| {
+                RedisError::Config(format!("配置文件第 {} 行: {}", index + 1, e))
+            })?;
+        }
+        Ok(config)
+    }
+
+    /// 从新配置中复制可以在运行时修改的配置项，返回发生变化的名称
+    ///
+    /// 监听地址、守护进程等只在启动时生效的配置项保持不变
+    pub fn apply_mutable(&mut self, new: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+
+        // Rust特点: 宏消除逐个字段比较和赋值的重复代码
+        macro_rules! update {
+            ($($field:ident => $name:literal),* $(,)?) => {
+                $(
+                    if self.$field != new.$field {
+                        self.$field = new.$field.clone();
+                        changed.push($name);
+                    }
+                )*
+            };
+        }
+        update! {
+            maxmemory => "maxmemory",
+            maxmemory_clients => "maxmemory-clients",
+            maxmemory_policy => "maxmemory-policy",
+            lazyfree_lazy_eviction => "lazyfree-lazy-eviction",
+            requirepass => "requirepass",
+            loglevel => "loglevel",
+            busy_reply_threshold => "busy-reply-threshold",
+            max_key_size => "max-key-size",
+            max_value_size => "max-value-size",
+            max_collection_elements => "max-collection-elements",
+            hash_max_listpack_entries => "hash-max-listpack-entries",
+            hash_max_listpack_value => "hash-max-listpack-value",
+            set_max_intset_entries => "set-max-intset-entries",
+            set_max_listpack_entries => "set-max-listpack-entries",
+            set_max_listpack_value => "set-max-listpack-value",
+            zset_max_listpack_entries => "zset-max-listpack-entries",
+            zset_max_listpack_value => "zset-max-listpack-value",
+            maxclients_per_ip => "maxclients-per-ip",
+            client_command_rate => "client-command-rate",
+            client_rate_action => "client-rate-action",
+            allow_ips => "allow-ips",
+            deny_ips => "deny-ips",
+            audit_redact_keys => "audit-redact-keys",
+            upgrade_drain_timeout => "upgrade-drain-timeout",
+            cache_mode => "cache-mode",
+        }
+        self.cache_preset = new.cache_preset;
+
+        macro_rules! restart_only {
+            ($($field:ident => $name:literal),* $(,)?) => {
+                $(
+                    if self.$field != new.$field {
+                        warn!(option = $name, "该配置项需要重启才能生效");
+                    }
+                )*
+            };
+        }
+        restart_only! {
+            port => "port",
+            bind => "bind",
+            unixsocket => "unixsocket",
+            logfile => "logfile",
+            log_format => "log-format",
+            otlp_endpoint => "otlp-endpoint",
+            health_port => "health-port",
+            daemonize => "daemonize",
+            pidfile => "pidfile",
+            renamed_commands => "rename-command",
+            upstream => "upstream",
+            upstream_keys => "upstream-keys",
+            audit_log => "audit-log",
+            enable_module_command => "enable-module-command",
+            loadmodule => "loadmodule",
+        }
+
+        changed
+    }
+
+    /// 缓存模式的预设: allkeys-lru淘汰、后台释放，maxmemory未设置时取可用内存的3/4
+    ///
+    /// 服务器本身没有持久化，不需要额外关闭。预设在`cache-mode yes`出现时生效，
+    /// 之后出现的配置项仍然可以覆盖它；已经生效时再次出现不重复应用
+    fn apply_cache_preset(&mut self) {
+        if self.cache_preset.is_some() {
+            return;
+        }
+        let before = (self.maxmemory, self.maxmemory_policy, self.lazyfree_lazy_eviction);
+        self.maxmemory_policy = EvictionPolicy::AllKeysLru;
+        self.lazyfree_lazy_eviction = true;
+        if self.maxmemory == 0 {
+            match memory::available_memory() {
+                Some(available) => self.maxmemory = available / 4 * 3,
+                None => warn!("无法确定可用内存，缓存模式下请设置maxmemory"),
+            }
+        }
+        self.cache_preset = Some(CachePreset {
+            maxmemory: (before.0, self.maxmemory),
+            maxmemory_policy: (before.1, self.maxmemory_policy),
+            lazyfree_lazy_eviction: (before.2, self.lazyfree_lazy_eviction),
+        });
+    }
+
+    /// `cache-mode no`: 恢复预设修改过的配置项
+    ///
+    /// 预设之后又被其他配置项修改过的值保持不变
+    fn revert_cache_preset(&mut self) {
+        let Some(preset) = self.cache_preset.take() else { return };
+
+        // Rust特点: 宏对每个字段执行相同的"仍是预设值才恢复"逻辑
+        macro_rules! revert {
+            ($($field:ident),*) => {
+                $(
+                    let (before, applied) = preset.$field;
+                    if self.$field == applied {
+                        self.$field = before;
+                    }
+                )*
+            };
+        }
+        revert!(maxmemory, maxmemory_policy, lazyfree_lazy_eviction);
+    }
+
+    /// 单条命令的执行时间预算
+    pub fn command_budget(&self) -> Option<Duration> {
+        (self.busy_reply_threshold > 0).then(|| Duration::from_millis(self.busy_reply_threshold))
+    }
+
+    /// 是否接受来自`ip`的连接: 先检查deny-ips，再检查allow-ips
+    pub fn ip_allowed(&self, ip: IpAddr) -> bool {
+        // IPv4映射的IPv6地址(::ffff:a.b.c.d)按IPv4匹配
+        let ip = ip.to_canonical();
+        if self.deny_ips.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow_ips.is_empty() || self.allow_ips.iter().any(|net| net.contains(&ip))
+    }
+
+    /// 写入大小限制
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_key_size: self.max_key_size as usize,
+            max_value_size: self.max_value_size as usize,
+            max_elements: self.max_collection_elements as usize,
+        }
+    }
+
+    /// 集合类型紧凑编码的转换阈值
+    pub fn encoding_thresholds(&self) -> EncodingThresholds {
+        EncodingThresholds {
+            hash_max_listpack_entries: self.hash_max_listpack_entries,
+            hash_max_listpack_value: self.hash_max_listpack_value,
+            set_max_intset_entries: self.set_max_intset_entries,
+            set_max_listpack_entries: self.set_max_listpack_entries,
+            set_max_listpack_value: self.set_max_listpack_value,
+            zset_max_listpack_entries: self.zset_max_listpack_entries,
+            zset_max_listpack_value: self.zset_max_listpack_value,
+        }
+    }
+
+    /// 客户端发来的命令名在rename-command/disable-command之后对应的真实命令
+    ///
+    /// 返回None表示该名称不可用: 命令已被禁用，或已被重命名、只能使用新名称
+    pub fn resolve_command(&self, name: &str) -> Option<String> {
+        let upper = name.to_uppercase();
+        if let Some(new_name) = self.renamed_commands.get(&upper) {
+            return new_name.eq_ignore_ascii_case(name).then_some(upper);
+        }
+        // 新名称如果恰好与另一个命令的原名相同，以重命名为准
+        match self
+            .renamed_commands
+            .iter()
+            .find(|(_, new_name)| !new_name.is_empty() && new_name.eq_ignore_ascii_case(name))
+        {
+            Some((command, _)) => Some(command.clone()),
+            None => Some(upper),
+        }
+    }
+
+    /// 设置单个配置项
+    pub fn apply(&mut self, name: &str, value: &str) -> RedisResult<()> {
+        match name.to_lowercase().as_str() {
+            "port" => self.port = parse_port(value)?,
+            "bind" => self.bind = value.to_string(),
+            "unixsocket" => self.unixsocket = non_empty(value).map(PathBuf::from),
+            "maxmemory" => self.maxmemory = parse_memory(value)?,
+            "maxmemory-clients" => self.maxmemory_clients = parse_memory(value)?,
+            "maxmemory-policy" => self.maxmemory_policy = value.parse()?,
+            "lazyfree-lazy-eviction" => self.lazyfree_lazy_eviction = parse_yes_no(value)?,
+            "cache-mode" => {
+                self.cache_mode = parse_yes_no(value)?;
+                if self.cache_mode {
+                    self.apply_cache_preset();
+                } else {
+                    self.revert_cache_preset();
+                }
+            }
+            "requirepass" => self.requirepass = non_empty(value).map(str::to_string),
+            "loglevel" => self.loglevel = value.parse()?,
+            "logfile" => self.logfile = non_empty(value).map(PathBuf::from),
+            "log-format" => self.log_format = value.parse()?,
+            "otlp-endpoint" => self.otlp_endpoint = Some(value.to_string()),
+            "health-port" => self.health_port = Some(parse_port(value)?),
+            "daemonize" => self.daemonize = parse_yes_no(value)?,
+            "pidfile" => self.pidfile = non_empty(value).map(PathBuf::from),
+            "upstream" => self.upstream = non_empty(value).map(str::to_string),
+            "upstream-keys" => self.upstream_keys = non_empty(value).map(str::to_string),
+            "busy-reply-threshold" => {
+                self.busy_reply_threshold = value
+                    .parse()
+                    .map_err(|_| RedisError::Config(format!("无效的busy-reply-threshold: {}", value)))?
+            }
+            "max-key-size" => self.max_key_size = parse_memory(value)?,
+            "max-value-size" => self.max_value_size = parse_memory(value)?,
+            "max-collection-elements" => {
+                self.max_collection_elements = value
+                    .parse()
+                    .map_err(|_| RedisError::Config(format!("无效的max-collection-elements: {}", value)))?
+            }
+            // 兼容Redis 7之前的ziplist名称
+            "hash-max-listpack-entries" | "hash-max-ziplist-entries" => {
+                self.hash_max_listpack_entries = encoding::parse_entries(name, value)?
+            }
+            "hash-max-listpack-value" | "hash-max-ziplist-value" => {
+                self.hash_max_listpack_value = parse_length(name, value)?
+            }
+            "set-max-intset-entries" => self.set_max_intset_entries = encoding::parse_entries(name, value)?,
+            "set-max-listpack-entries" => {
+                self.set_max_listpack_entries = encoding::parse_entries(name, value)?
+            }
+            "set-max-listpack-value" => self.set_max_listpack_value = parse_length(name, value)?,
+            "zset-max-listpack-entries" | "zset-max-ziplist-entries" => {
+                self.zset_max_listpack_entries = encoding::parse_entries(name, value)?
+            }
+            "zset-max-listpack-value" | "zset-max-ziplist-value" => {
+                self.zset_max_listpack_value = parse_length(name, value)?
+            }
+            "maxclients-per-ip" => {
+                self.maxclients_per_ip = value
+                    .parse()
+                    .map_err(|_| RedisError::Config(format!("无效的maxclients-per-ip: {}", value)))?
+            }
+            "client-command-rate" => {
+                self.client_command_rate = value
+                    .parse()
+                    .map_err(|_| RedisError::Config(format!("无效的client-command-rate: {}", value)))?
+            }
+            "client-rate-action" => self.client_rate_action = value.parse()?,
+            "allow-ips" => self.allow_ips = parse_ip_list(value)?,
+            "deny-ips" => self.deny_ips = parse_ip_list(value)?,
+            "audit-log" => self.audit_log = non_empty(value).map(str::to_string),
+            "audit-redact-keys" => {
+                self.audit_redact_keys = value.split_whitespace().map(str::to_string).collect()
+            }
+            "upgrade-drain-timeout" => {
+                self.upgrade_drain_timeout = value
+                    .parse()
+                    .map_err(|_| RedisError::Config(format!("无效的upgrade-drain-timeout: {}", value)))?
+            }
+            "rename-command" => {
+                let (command, new_name) = parse_rename(value)?;
+                self.renamed_commands.insert(command, new_name);
+            }
+            "disable-command" => {
+                self.renamed_commands.insert(value.to_uppercase(), String::new());
+            }
+            "enable-module-command" => self.enable_module_command = parse_yes_no(value)?,
+            "loadmodule" => {
+                let mut words = value.split_whitespace().map(str::to_string);
+                let path = words
+                    .next()
+                    .ok_or_else(|| RedisError::Config("loadmodule需要共享库路径".to_string()))?;
+                self.loadmodule.push((path, words.collect()));
+            }
+            _ => return Err(RedisError::Config(format!("未知的配置项: {}", name))),
+        }
+        Ok(())
+    }
+}
+
+/// upgrade-drain-timeout的默认值(秒)
+pub const DEFAULT_UPGRADE_DRAIN_TIMEOUT: u64 = 30;
+
+/// max-value-size的默认值，与Redis的proto-max-bulk-len一致
+pub const DEFAULT_MAX_VALUE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// 写入大小限制，每项为0表示不限制
+///
+/// 超出限制的写入返回错误，避免单个客户端写入巨大的值拖垮服务器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub max_key_size: usize,
+    pub max_value_size: usize,
+    pub max_elements: usize,
+}
+
+impl Limits {
+    /// 是否没有任何限制
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 检查键的长度
+    pub fn check_key(&self, key: &str) -> RedisResult<()> {
+        check(self.max_key_size, key.len(), "key exceeds maximum allowed size (max-key-size)")
+    }
+
+    /// 检查值的长度
+    pub fn check_value(&self, len: usize) -> RedisResult<()> {
+        check(self.max_value_size, len, "value exceeds maximum allowed size (max-value-size)")
+    }
+
+    /// 检查集合的元素数
+    pub fn check_elements(&self, count: usize) -> RedisResult<()> {
+        check(
+            self.max_elements,
+            count,
+            "collection exceeds maximum number of elements (max-collection-elements)",
+        )
+    }
+}
+
+/// `limit`为0表示不限制
+fn check(limit: usize, actual: usize, message: &str) -> RedisResult<()> {
+    if limit > 0 && actual > limit {
+        return Err(RedisError::InvalidArgument(message.to_string()));
+    }
+    Ok(())
+}
+
+/// 解析`rename-command NAME NEW-NAME`的值；新名称为`""`时表示禁用
+fn parse_rename(value: &str) -> RedisResult<(String, String)> {
+    let mut parts = value.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(command), Some(new_name), None) => {
+            let new_name = new_name
+                .strip_prefix('"')
+                .and_then(|n| n.strip_suffix('"'))
+                .unwrap_or(new_name);
+            Ok((command.to_uppercase(), new_name.to_string()))
+        }
+        (Some(command), None, None) => Ok((command.to_uppercase(), String::new())),
+        _ => Err(RedisError::Config(format!("rename-command应为: 命令名 新名称: {}", value))),
+    }
+}
+
+/// 解析空格分隔的网段列表，如`10.0.0.0/8 192.168.1.5 ::1`；单个地址视为只包含它自己的网段
+fn parse_ip_list(value: &str) -> RedisResult<Vec<IpNet>> {
+    value
+        .split_whitespace()
+        .map(|item| {
+            item.parse::<IpNet>()
+                .or_else(|_| item.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| RedisError::Config(format!("无效的网段: {}", item)))
+        })
+        .collect()
+}
+
+/// 解析端口号
+fn parse_port(value: &str) -> RedisResult<u16> {
+    value
+        .parse()
+        .map_err(|_| RedisError::Config(format!("无效的端口: {}", value)))
+}
+
+/// 配置来源: 重新读取配置文件并合并命令行选项
+type ConfigSource = Box<dyn Fn() -> RedisResult<Config> + Send + Sync>;
+
+/// 配置重新加载后的回调
+type ReloadHook = Box<dyn Fn(&Config) + Send + Sync>;
+
+/// 在服务器各部分之间共享的配置，支持运行时重新加载
+///
+/// Rust特点: Clone只复制Arc，所有连接看到同一份配置
+#[derive(Clone, Default)]
+pub struct SharedConfig {
+    inner: Arc<SharedConfigInner>,
+}
+
+#[derive(Default)]
+struct SharedConfigInner {
+    config: RwLock<Config>,
+    source: RwLock<Option<ConfigSource>>,
+    hooks: RwLock<Vec<ReloadHook>>,
+}
+
+impl SharedConfig {
+    /// 包装一份配置
+    pub fn new(config: Config) -> Self {
+        Self {
+            inner: Arc::new(SharedConfigInner {
+                config: RwLock::new(config),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 读取当前配置
+    pub fn read(&self) -> RwLockReadGuard<'_, Config> {
+        self.inner.config.read().unwrap()
+    }
+
+    /// 设置重新加载时使用的配置来源
+    pub fn set_source(&self, source: impl Fn() -> RedisResult<Config> + Send + Sync + 'static) {
+        *self.inner.source.write().unwrap() = Some(Box::new(source));
+    }
+
+    /// 注册重新加载后的回调，如调整日志级别
+    pub fn on_reload(&self, hook: impl Fn(&Config) + Send + Sync + 'static) {
+        self.inner.hooks.write().unwrap().push(Box::new(hook));
+    }
+
+    /// 在运行时修改单个配置项(CONFIG SET)，只支持无需重启的配置项
+    pub fn set(&self, name: &str, value: &str) -> RedisResult<()> {
+        let config = {
+            let mut config = self.inner.config.write().unwrap();
+            let mut new = config.clone();
+            new.apply(name, value)?;
+            // 值有变化但没有被应用，说明该配置项需要重启
+            if new != *config && config.apply_mutable(&new).is_empty() {
+                return Err(RedisError::Config(format!("{}需要重启才能修改", name)));
+            }
+            config.clone()
+        };
+
+        info!(option = name, "配置已修改");
+        for hook in self.inner.hooks.read().unwrap().iter() {
+            hook(&config);
+        }
+        Ok(())
+    }
+
+    /// 从配置来源重新加载，只应用可以在运行时修改的配置项
+    ///
+    /// 返回实际发生变化的配置项名称
+    pub fn reload(&self) -> RedisResult<Vec<&'static str>> {
+        let new = match self.inner.source.read().unwrap().as_ref() {
+            Some(source) => source()?,
+            None => return Err(RedisError::Config("没有可以重新加载的配置来源".to_string())),
+        };
+
+        let (changed, config) = {
+            let mut config = self.inner.config.write().unwrap();
+            let changed = config.apply_mutable(&new);
+            (changed, config.clone())
+        };
+
+        // 先记录日志，回调可能会调高日志级别
+        info!(changed = ?changed, "配置已重新加载");
+        for hook in self.inner.hooks.read().unwrap().iter() {
+            hook(&config);
+        }
+        Ok(changed)
+    }
+}
+
+impl std::fmt::Debug for SharedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedConfig").field(&*self.read()).finish()
+    }
+}
+
+/// 空字符串表示未设置
+fn non_empty(value: &str) -> Option<&str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// 解析内存大小，支持Redis的单位: k/m/g(1000进制)和kb/mb/gb(1024进制)
+pub fn parse_memory(value: &str) -> RedisResult<u64> {
+    let lower = value.to_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(RedisError::Config(format!("无效的内存大小: {}", value))),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| RedisError::Config(format!("无效的内存大小: {}", value)))
+}
+
+/// 解析紧凑编码的元素长度阈值
+fn parse_length(name: &str, value: &str) -> RedisResult<usize> {
+    value
+        .parse()
+        .map_err(|_| RedisError::Config(format!("无效的{}: {}", name, value)))
+}
+
+/// 解析yes/no形式的布尔配置
+fn parse_yes_no(value: &str) -> RedisResult<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(RedisError::Config(format!("应为yes或no: {}", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conf() {
+        let config = Config::parse_conf(
+            "# 示例配置\n\
+             port 6380\n\
+             \n\
+             loglevel warning\n\
+             logfile \"/tmp/redis.log\"\n\
+             requirepass secret\n\
+             maxmemory 100mb\n\
+             busy-reply-threshold 250\n\
+             daemonize yes\n",
+        )
+        .unwrap();
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.loglevel, LogLevel::Warning);
+        assert_eq!(config.logfile, Some(PathBuf::from("/tmp/redis.log")));
+        assert_eq!(config.requirepass, Some("secret".to_string()));
+        assert_eq!(config.maxmemory, 100 * 1024 * 1024);
+        assert!(config.daemonize);
+        assert_eq!(config.command_budget(), Some(Duration::from_millis(250)));
+        assert_eq!(Config::default().command_budget(), None);
+
+        assert_eq!(Config::parse_conf("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_shared_config_reload() {
+        let shared = SharedConfig::new(Config::default());
+        assert!(shared.reload().is_err());
+
+        let levels = Arc::new(RwLock::new(Vec::new()));
+        let seen = levels.clone();
+        shared.on_reload(move |config| seen.write().unwrap().push(config.loglevel));
+        shared.set_source(|| {
+            Config::parse_conf("port 7000\nloglevel warning\nrequirepass secret\n")
+        });
+
+        let changed = shared.reload().unwrap();
+        assert_eq!(changed, vec!["requirepass", "loglevel"]);
+        assert_eq!(shared.read().requirepass, Some("secret".to_string()));
+        // 端口只在启动时生效
+        assert_eq!(shared.read().port, DEFAULT_PORT);
+        assert_eq!(*levels.read().unwrap(), vec![LogLevel::Warning]);
+
+        assert!(shared.reload().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("0").unwrap(), 0);
+        assert_eq!(parse_memory("1k").unwrap(), 1000);
+        assert_eq!(parse_memory("1KB").unwrap(), 1024);
+        assert_eq!(parse_memory("2gb").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(parse_memory("mb").is_err());
+        assert!(parse_memory("10tb").is_err());
+    }
+
+    #[test]
+    fn test_rename_and_disable_commands() {
+        let config = Config::parse_conf(
+            "rename-command FLUSHALL \"\"\n\
+             rename-command config hidden-config\n\
+             disable-command keys\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.resolve_command("flushall"), None);
+        assert_eq!(config.resolve_command("KEYS"), None);
+        assert_eq!(config.resolve_command("config"), None);
+        assert_eq!(config.resolve_command("HIDDEN-CONFIG"), Some("CONFIG".to_string()));
+        assert_eq!(config.resolve_command("get"), Some("GET".to_string()));
+        assert!(Config::default().apply("rename-command", "a b c").is_err());
+    }
+
+    #[test]
+    fn test_modules() {
+        let config = Config::parse_conf(
+            "enable-module-command yes\n\
+             loadmodule /opt/modules/a.so\n\
+             loadmodule /opt/modules/b.so x 10\n",
+        )
+        .unwrap();
+
+        assert!(config.enable_module_command);
+        assert_eq!(
+            config.loadmodule,
+            [
+                ("/opt/modules/a.so".to_string(), vec![]),
+                ("/opt/modules/b.so".to_string(), vec!["x".to_string(), "10".to_string()]),
+            ]
+        );
+        assert!(!Config::default().enable_module_command);
+        assert!(Config::default().apply("loadmodule", "").is_err());
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let mut config = Config::default();
+        assert_eq!(config.limits().max_value_size, 512 * 1024 * 1024);
+        config.apply("max-key-size", "1kb").unwrap();
+        config.apply("max-value-size", "1mb").unwrap();
+        config.apply("max-collection-elements", "100").unwrap();
+
+        let limits = config.limits();
+        assert!(limits.check_key(&"k".repeat(1024)).is_ok());
+        assert!(limits.check_key(&"k".repeat(1025)).is_err());
+        assert!(limits.check_value(1024 * 1024 + 1).is_err());
+        assert!(limits.check_elements(101).is_err());
+        // 0表示不限制
+        assert!(Limits::default().check_key(&"k".repeat(1 << 20)).is_ok());
+    }
+
+    #[test]
+    fn test_encoding_thresholds() {
+        let mut config =
+            Config::parse_conf("hash-max-ziplist-entries 64\nset-max-intset-entries 1000\n").unwrap();
+        config.apply("zset-max-listpack-value", "32").unwrap();
+        let thresholds = config.encoding_thresholds();
+        assert_eq!(thresholds.hash_max_listpack_entries, 64);
+        assert_eq!(thresholds.set_max_intset_entries, 1000);
+        assert_eq!(thresholds.zset_max_listpack_value, 32);
+        assert_eq!(thresholds.set_max_listpack_entries, 128);
+        assert!(config.apply("set-max-listpack-entries", "100000").is_err());
+        assert!(config.apply("hash-max-listpack-value", "-1").is_err());
+
+        let mut running = Config::default();
+        assert_eq!(running.apply_mutable(&config), vec![
+            "hash-max-listpack-entries",
+            "set-max-intset-entries",
+            "zset-max-listpack-value",
+        ]);
+    }
+
+    #[test]
+    fn test_client_limits() {
+        let config = Config::parse_conf(
+            "maxclients-per-ip 10\nclient-command-rate 100\nclient-rate-action Disconnect\n",
+        )
+        .unwrap();
+        assert_eq!(config.maxclients_per_ip, 10);
+        assert_eq!(config.client_command_rate, 100);
+        assert_eq!(config.client_rate_action, RateLimitAction::Disconnect);
+        assert!(Config::default().apply("client-rate-action", "drop").is_err());
+    }
+
+    #[test]
+    fn test_cache_mode() {
+        let config = Config::parse_conf("maxmemory 1gb\ncache-mode yes\n").unwrap();
+        assert!(config.cache_mode);
+        assert_eq!(config.maxmemory, 1024 * 1024 * 1024);
+        assert_eq!(config.maxmemory_policy, EvictionPolicy::AllKeysLru);
+        assert!(config.lazyfree_lazy_eviction);
+
+        // 之后的配置项覆盖预设
+        let config =
+            Config::parse_conf("cache-mode yes\nmaxmemory-policy volatile-lru\n").unwrap();
+        assert_eq!(config.maxmemory_policy, EvictionPolicy::VolatileLru);
+        if let Some(available) = memory::available_memory() {
+            assert_eq!(config.maxmemory, available / 4 * 3);
+        }
+
+        // cache-mode no恢复预设之前的值，之后被修改过的配置项保持不变
+        let config = Config::parse_conf("maxmemory 1gb\ncache-mode yes\ncache-mode no\n").unwrap();
+        assert_eq!(config, Config::parse_conf("maxmemory 1gb\n").unwrap());
+        let config = Config::parse_conf(
+            "cache-mode yes\ncache-mode yes\nmaxmemory 2gb\ncache-mode no\n",
+        )
+        .unwrap();
+        assert!(!config.cache_mode);
+        assert_eq!(config.maxmemory, 2 * 1024 * 1024 * 1024);
+        assert_eq!(config.maxmemory_policy, EvictionPolicy::NoEviction);
+        assert!(!config.lazyfree_lazy_eviction);
+
+        // 运行时可以切换缓存模式
+        let shared = SharedConfig::new(Config::parse_conf("maxmemory 1gb\n").unwrap());
+        shared.set("cache-mode", "yes").unwrap();
+        assert_eq!(shared.read().maxmemory_policy, EvictionPolicy::AllKeysLru);
+        shared.set("cache-mode", "no").unwrap();
+        assert_eq!(*shared.read(), Config::parse_conf("maxmemory 1gb\n").unwrap());
+    }
+
+    #[test]
+    fn test_ip_lists() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let mut config = Config::default();
+        assert!(config.ip_allowed(ip("203.0.113.7")));
+
+        config.apply("allow-ips", "10.0.0.0/8 192.168.1.5").unwrap();
+        config.apply("deny-ips", "10.0.2.0/24").unwrap();
+        assert!(config.ip_allowed(ip("10.1.2.3")));
+        assert!(config.ip_allowed(ip("::ffff:192.168.1.5")));
+        assert!(!config.ip_allowed(ip("192.168.1.6")));
+        // deny-ips优先
+        assert!(!config.ip_allowed(ip("10.0.2.9")));
+
+        assert!(config.apply("allow-ips", "10.0.0.0/33").is_err());
+        config.apply("allow-ips", "").unwrap();
+        assert!(config.ip_allowed(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let mut config = Config::default();
+        assert!(matches!(config.apply("loglevel", "loud"), Err(RedisError::Config(_))));
+        assert!(matches!(config.apply("port", "abc"), Err(RedisError::Config(_))));
+        assert!(matches!(config.apply("bogus", "1"), Err(RedisError::Config(_))));
+        assert!(matches!(config.apply("daemonize", "maybe"), Err(RedisError::Config(_))));
+
+        assert!(config.apply("max-collection-elements", "-1").is_err());
+        assert!(config.apply("upgrade-drain-timeout", "soon").is_err());
+
+        let err = Config::parse_conf("port 1\nbogus 2\n").unwrap_err();
+        assert!(err.to_string().contains("第 2 行"));
+    }
+}
@@ -6,27 +6,104 @@
 //! - 所有权在异步上下文中的转移
 //! - 生命周期和借用检查
 
-use crate::command::{Command, CommandExecutor};
+use crate::audit::{self, AuditLog};
+use crate::client::Client;
+use crate::clients::ClientHandle;
+use crate::command::{Command, CommandExecutor, ReplyMode};
+use crate::config::{RateLimitAction, SharedConfig};
 use crate::error::{RedisError, RedisResult};
-use crate::resp::{RespParser, RespValue};
+use crate::event::StoreEvent;
+use crate::memory;
+use crate::metadata::{self, CommandFlags};
+use crate::ratelimit::Throttle;
+use crate::resp::{self, RespParser, RespValue};
 use crate::store::Store;
 use bytes::BytesMut;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::any::Any;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::{debug, error, field, info, info_span, instrument, warn};
+
+/// WATCH状态 - 记录被监视的键，并通过变更事件判断它们是否被修改
+///
+/// Rust特点: 复用Store的广播通道，而不是为每个键维护版本号
+struct WatchState {
+    /// 被监视的键
+    keys: HashSet<String>,
+    /// 从WATCH时刻开始的变更事件
+    events: broadcast::Receiver<StoreEvent>,
+}
+
+impl WatchState {
+    /// 检查被监视的键自WATCH以来是否被修改过
+    ///
+    /// 事件积压导致丢失时无法确定，保守地视为已修改
+    fn is_dirty(&mut self) -> bool {
+        loop {
+            match self.events.try_recv() {
+                Ok(StoreEvent::Flush) => return true,
+                Ok(StoreEvent::Rename { from, to }) => {
+                    if self.keys.contains(&from) || self.keys.contains(&to) {
+                        return true;
+                    }
+                }
+                Ok(event) => {
+                    if event.key().is_some_and(|key| self.keys.contains(key)) {
+                        return true;
+                    }
+                }
+                Err(TryRecvError::Empty) => return false,
+                Err(_) => return true,
+            }
+        }
+    }
+}
 
 /// 连接处理器
 ///
-/// Rust特点: 结构体持有连接状态，方法操作状态
-pub struct Connection {
-    /// TCP流
-    stream: TcpStream,
+/// Rust特点:
+/// - 结构体持有连接状态，方法操作状态
+/// - 泛型参数S让同一套处理逻辑既能服务TCP连接，也能服务内存中的双工流
+pub struct Connection<S = TcpStream> {
+    /// 底层字节流
+    stream: S,
     /// 读取缓冲区
     buffer: BytesMut,
     /// 客户端地址(用于日志)
     addr: String,
+    /// MULTI之后排队的命令 - None表示不在事务中
+    queued: Option<Vec<Command>>,
+    /// 排队期间是否出现过错误(EXEC时将放弃事务)
+    queue_error: bool,
+    /// WATCH状态
+    watch: Option<WatchState>,
+    /// 服务器配置(requirepass等)
+    config: SharedConfig,
+    /// 是否已通过AUTH认证
+    authenticated: bool,
+    /// 管理连接不受执行时间预算限制
+    admin: bool,
+    /// CLIENT REPLY设置的回复模式，写回复前检查
+    reply_mode: ReplyMode,
+    /// 到上游Redis的连接，第一次转发时建立
+    upstream: Option<Client>,
+    /// 连接建立的时刻，命令限流以它为时间原点
+    ///
+    /// 使用tokio的时钟，限流等待的sleep与计算的时间一致，在模拟运行时中同样成立
+    started: tokio::time::Instant,
+    /// 命令限流的理论到达时间(毫秒，相对started)
+    rate_tat: Option<u64>,
+    /// 审计日志；为None时不记录
+    audit: Option<AuditLog>,
+    /// 在Store的客户端注册表中的登记，处理连接期间存在
+    client: Option<ClientHandle>,
 }
 
-impl Connection {
+impl Connection<TcpStream> {
     /// 创建新连接
     ///
     /// Rust特点: 所有权转移 - TcpStream的所有权从调用者转移到Connection
@@ -36,13 +113,55 @@ impl Connection {
             .map(|a| a.to_string())
             .unwrap_or_else(|_| "unknown".to_string());
 
+        Self::with_addr(stream, addr)
+    }
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// 基于任意字节流创建连接，addr仅用于日志
+    pub fn with_addr(stream: S, addr: String) -> Self {
         Self {
             stream,
             buffer: BytesMut::with_capacity(4096),
             addr,
+            queued: None,
+            queue_error: false,
+            watch: None,
+            config: SharedConfig::default(),
+            authenticated: false,
+            admin: false,
+            reply_mode: ReplyMode::On,
+            upstream: None,
+            started: tokio::time::Instant::now(),
+            rate_tat: None,
+            audit: None,
+            client: None,
         }
     }
 
+    /// 使用服务器的共享配置
+    pub fn with_config(mut self, config: SharedConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 标记为管理连接，命令不受busy-reply-threshold限制
+    ///
+    /// 服务器繁忙时运维人员仍然可以通过管理连接完整地执行KEYS等命令
+    pub fn with_admin(mut self, admin: bool) -> Self {
+        self.admin = admin;
+        self
+    }
+
+    /// 把本连接的命令写入审计日志
+    pub fn with_audit_log(mut self, audit: Option<AuditLog>) -> Self {
+        self.audit = audit;
+        self
+    }
+
     /// 获取客户端地址
     pub fn addr(&self) -> &str {
         &self.addr
@@ -50,51 +169,122 @@ impl Connection {
 
     /// 处理客户端连接
     ///
+    /// 整个连接的生命周期处于一个`connection` span中，日志自动带上客户端地址
+    ///
     /// Rust特点:
     /// - async fn 定义异步函数
     /// - &Store 是共享引用，允许多个连接同时访问存储
+    /// - 属性宏 #[instrument] 为异步函数附加tracing span
+    #[instrument(name = "connection", skip_all, fields(client = %self.addr))]
     pub async fn handle(&mut self, store: &Store) -> RedisResult<()> {
-        println!("[{}] 客户端已连接", self.addr);
+        info!("客户端已连接");
 
+        // 管理连接不会因为maxmemory-clients被断开
+        let client = store.clients().register(self.admin);
+        let evicted = client.evicted();
+        self.client = Some(client);
+        // Rust特点: biased让select!先检查断开请求，丢弃serve的Future即关闭连接
+        let result = tokio::select! {
+            biased;
+            _ = evicted => {
+                warn!("客户端缓冲区占用的内存超过maxmemory-clients，断开连接");
+                Ok(())
+            }
+            result = self.serve(store) => result,
+        };
+        self.client = None;
+        result
+    }
+
+    /// 读取并执行命令，直到客户端断开
+    async fn serve(&mut self, store: &Store) -> RedisResult<()> {
         loop {
             // 尝试解析缓冲区中的命令
             match self.read_command().await {
                 Ok(Some(value)) => {
-                    // 解析并执行命令
-                    match Command::from_resp(value) {
-                        Ok(cmd) => {
-                            let executor = CommandExecutor::new(store);
-                            let (response, should_quit) = executor.execute(cmd);
+                    if !self.throttle().await? {
+                        warn!("命令速率超过client-command-rate，断开连接");
+                        break;
+                    }
+
+                    // 解析并执行命令；rename-command/disable-command在解析之前生效
+                    let parsed = self.resolve_command(value).and_then(|value| {
+                        // 代理模式下保留原始请求，需要时原样转发给上游
+                        let raw = self.config.read().upstream.is_some().then(|| value.clone());
+                        let args = self.audit.is_some().then(|| audit::command_args(&value));
+                        Command::from_resp(value).map(|cmd| (cmd, raw, args))
+                    });
+                    match parsed {
+                        Ok((cmd, raw, args)) => {
+                            if let Some(args) = args {
+                                self.audit(args, &cmd);
+                            }
+
+                            if let Some(raw) = raw.filter(|_| self.should_forward(&cmd, store)) {
+                                let response = self.forward(&raw).await;
+                                self.reply(&response).await?;
+                                continue;
+                            }
+
+                            // CLIENT REPLY OFF/SKIP本身也不回复
+                            let silent = matches!(
+                                cmd,
+                                Command::ClientReply { mode: ReplyMode::Off | ReplyMode::Skip }
+                            );
+                            // 读穿透: 设置了缓存加载器时先把未命中的键加载进来
+                            if let Some(key) = self.read_through_key(&cmd, store) {
+                                if let Err(e) = store.get_or_load(key).await {
+                                    self.reply(&RespValue::Error(format!("ERR {}", e))).await?;
+                                    continue;
+                                }
+                            }
+                            // 事务中的阻塞命令与Redis一样不阻塞，直接回复nil
+                            let blocking = (self.queued.is_none() && cmd.blocking().is_some())
+                                .then(|| cmd.clone());
+                            let (mut response, should_quit) = self.dispatch(cmd, store);
+                            if let Some(cmd) = blocking.filter(|_| response == RespValue::Null) {
+                                match self.block(&cmd, store).await? {
+                                    Some(unblocked) => response = unblocked,
+                                    None => {
+                                        info!("客户端在阻塞期间断开连接");
+                                        break;
+                                    }
+                                }
+                            }
 
                             // 发送响应
-                            self.write_response(&response).await?;
+                            if !silent {
+                                self.reply(&response).await?;
+                            }
 
                             // 如果是QUIT命令，断开连接
                             if should_quit {
-                                println!("[{}] 客户端请求断开", self.addr);
+                                info!("客户端请求断开");
                                 break;
                             }
                         }
                         Err(e) => {
-                            // 命令解析错误，发送错误响应
+                            // 命令解析错误，发送错误响应；事务中的错误会导致EXEC失败
+                            if self.queued.is_some() {
+                                self.queue_error = true;
+                            }
                             let error_response =
                                 RespValue::Error(format!("ERR {}", e));
-                            self.write_response(&error_response).await?;
+                            self.reply(&error_response).await?;
                         }
                     }
                 }
                 Ok(None) => {
                     // 连接关闭
-                    println!("[{}] 客户端断开连接", self.addr);
+                    info!("客户端断开连接");
                     break;
                 }
                 Err(e) => {
-                    // 协议错误
-                    eprintln!("[{}] 错误: {}", self.addr, e);
+                    // 协议错误后缓冲区中的数据无法再同步，与Redis一样回复错误后关闭连接
+                    warn!(error = %e, "协议错误");
                     let error_response = RespValue::Error(format!("ERR {}", e));
-                    if self.write_response(&error_response).await.is_err() {
-                        break;
-                    }
+                    let _ = self.write_response(&error_response).await;
+                    break;
                 }
             }
         }
@@ -102,6 +292,405 @@ impl Connection {
         Ok(())
     }
 
+    /// 隐去敏感参数后写入审计日志，在命令执行之前记录
+    fn audit(&self, mut args: Vec<String>, cmd: &Command) {
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        let config = self.config.read();
+        audit::redact(&mut args, cmd, &config.audit_redact_keys);
+        // 没有ACL用户，认证后(或无需认证时)都是default用户
+        let user = (self.authenticated || config.requirepass.is_none()).then_some("default");
+        audit.record(&self.addr, user, 0, &args);
+    }
+
+    /// 按client-command-rate限制命令速率，返回false表示应断开连接
+    ///
+    /// 使用与CL.THROTTLE相同的GCRA算法，允许一秒内的命令集中到达；管理连接不受限制
+    async fn throttle(&mut self) -> RedisResult<bool> {
+        let (rate, action) = {
+            let config = self.config.read();
+            (config.client_command_rate, config.client_rate_action)
+        };
+        if self.admin || rate == 0 {
+            return Ok(true);
+        }
+
+        let throttle = Throttle::new(rate - 1, rate, Duration::from_secs(1))?;
+        loop {
+            let now = self.started.elapsed().as_millis() as u64;
+            let (result, tat) = throttle.check(self.rate_tat, now, 1);
+            if tat.is_some() {
+                self.rate_tat = tat;
+                return Ok(true);
+            }
+            if action == RateLimitAction::Disconnect {
+                let error = "ERR max command rate per client reached (client-command-rate)";
+                self.write_response(&resp::error(error)).await?;
+                return Ok(false);
+            }
+            tokio::time::sleep(result.retry_after.unwrap_or(Duration::from_millis(1))).await;
+        }
+    }
+
+    /// 超过maxmemory时先按maxmemory-policy淘汰键，仍然超过时拒绝写命令
+    ///
+    /// 与Redis一样在执行每条命令之前检查，读命令也会触发淘汰
+    fn out_of_memory(&self, cmd: &Command, store: &Store) -> bool {
+        let (maxmemory, policy, lazy) = {
+            let config = self.config.read();
+            (config.maxmemory as usize, config.maxmemory_policy, config.lazyfree_lazy_eviction)
+        };
+        if maxmemory == 0 {
+            return false;
+        }
+        // 后台释放的值在释放之前仍计入used_memory
+        let within_limit = store.evict(policy, lazy, |pending| {
+            memory::used_memory().saturating_sub(pending) > maxmemory
+        });
+        !within_limit
+            && metadata::lookup(cmd.name()).is_some_and(|info| info.flags.contains(CommandFlags::WRITE))
+    }
+
+    /// 按rename-command/disable-command改写命令名
+    ///
+    /// 被禁用或已被重命名的原名与不存在的命令一样返回unknown command
+    fn resolve_command(&self, value: RespValue) -> RedisResult<RespValue> {
+        let mut parts = match value {
+            RespValue::Array(parts) => parts,
+            other => return Ok(other),
+        };
+        let config = self.config.read();
+        if config.renamed_commands.is_empty() {
+            return Ok(RespValue::Array(parts));
+        }
+
+        if let Some(name) = parts.first().and_then(RespValue::as_string) {
+            match config.resolve_command(&name) {
+                Some(command) => parts[0] = resp::bulk_string(&command),
+                None => {
+                    return Err(RedisError::InvalidArgument(format!(
+                        "unknown command '{}'",
+                        name
+                    )))
+                }
+            }
+        }
+        Ok(RespValue::Array(parts))
+    }
+
+    /// 是否把命令转发给上游: 本服务器和模块都未实现的命令，或涉及匹配upstream-keys的键
+    ///
+    /// 事务中的命令不转发，否则无法保证原子性；未认证的连接也不转发
+    fn should_forward(&self, cmd: &Command, store: &Store) -> bool {
+        let config = self.config.read();
+        if self.queued.is_some() || (config.requirepass.is_some() && !self.authenticated) {
+            return false;
+        }
+        matches!(cmd, Command::Unknown { name, .. } if !store.modules().contains(name))
+            || config.upstream_keys.as_deref().is_some_and(|pattern| {
+                cmd.keys().iter().any(|key| Store::match_pattern(key, pattern))
+            })
+    }
+
+    /// 把请求转发给上游并返回它的回复
+    ///
+    /// 上游连接出错时丢弃该连接，下一次转发重新建立
+    async fn forward(&mut self, request: &RespValue) -> RespValue {
+        let Some(addr) = self.config.read().upstream.clone() else {
+            return resp::error("ERR no upstream configured");
+        };
+
+        if self.upstream.is_none() {
+            match Client::connect(&addr).await {
+                Ok(client) => self.upstream = Some(client),
+                Err(e) => {
+                    warn!(upstream = %addr, error = %e, "无法连接上游");
+                    return resp::error(&format!("ERR upstream {} unavailable: {}", addr, e));
+                }
+            }
+        }
+
+        let Some(upstream) = self.upstream.as_mut() else {
+            return resp::error("ERR no upstream configured");
+        };
+        match upstream.execute(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.upstream = None;
+                warn!(upstream = %addr, error = %e, "转发到上游失败");
+                resp::error(&format!("ERR upstream {} unavailable: {}", addr, e))
+            }
+        }
+    }
+
+    /// 需要通过Store的缓存加载器读穿透的键(目前只有GET)
+    ///
+    /// 事务中的命令只排队，未认证的连接不能触发后端访问
+    fn read_through_key<'c>(&self, cmd: &'c Command, store: &Store) -> Option<&'c str> {
+        let Command::Get { key } = cmd else {
+            return None;
+        };
+        if !store.has_loader() || self.queued.is_some() {
+            return None;
+        }
+        if self.config.read().requirepass.is_some() && !self.authenticated {
+            return None;
+        }
+        Some(key)
+    }
+
+    /// 分发命令 - 事务相关命令在这里处理，其余交给CommandExecutor
+    ///
+    /// Rust特点: 带守卫条件的match分支 (`cmd if ...`)
+    fn dispatch(&mut self, cmd: Command, store: &Store) -> (RespValue, bool) {
+        // 启用otel特性时，这个span会被导出到追踪后端
+        let span = info_span!(
+            "command",
+            name = %cmd.name(),
+            keys = cmd.keys().len(),
+            outcome = field::Empty,
+        );
+        let _enter = span.enter();
+        let start = Instant::now();
+
+        let budget = self.config.read().command_budget();
+        let name = cmd.name().to_string();
+        let queued = self.queued.as_ref().map(Vec::len);
+        let (response, should_quit) = isolate(&name, || self.dispatch_inner(cmd, store));
+        // 排队的命令在EXEC时才计入
+        let was_queued = matches!(
+            (queued, self.queued.as_ref().map(Vec::len)),
+            (Some(before), Some(after)) if after > before
+        );
+        if !was_queued {
+            store.stats().record_commands(1);
+        }
+
+        // 不能中途中止的命令超出预算时只记录警告
+        let elapsed = start.elapsed();
+        if budget.is_some_and(|budget| elapsed > budget) {
+            warn!(duration_ms = elapsed.as_millis() as u64, "命令执行超出busy-reply-threshold");
+        }
+
+        let outcome = match &response {
+            RespValue::Error(_) => "error",
+            _ => "ok",
+        };
+        span.record("outcome", outcome);
+        debug!(
+            duration_us = elapsed.as_micros() as u64,
+            outcome, "命令执行完成"
+        );
+
+        (response, should_quit)
+    }
+
+    /// 分发命令的实际逻辑
+    fn dispatch_inner(&mut self, cmd: Command, store: &Store) -> (RespValue, bool) {
+        let requirepass = self.config.read().requirepass.clone();
+
+        // 配置了密码时，认证之前只允许AUTH和QUIT
+        if requirepass.is_some()
+            && !self.authenticated
+            && !matches!(cmd, Command::Auth { .. } | Command::Quit)
+        {
+            return (resp::error("NOAUTH Authentication required."), false);
+        }
+
+        if self.out_of_memory(&cmd, store) {
+            if self.queued.is_some() {
+                self.queue_error = true;
+            }
+            return (resp::error("OOM command not allowed when used memory > 'maxmemory'."), false);
+        }
+
+        let response = match cmd {
+            Command::Auth { username, password } => match requirepass {
+                None => resp::error(
+                    "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
+                ),
+                Some(expected)
+                    if password == expected
+                        && username.as_deref().is_none_or(|u| u == "default") =>
+                {
+                    self.authenticated = true;
+                    resp::ok()
+                }
+                Some(_) => resp::error(
+                    "WRONGPASS invalid username-password pair or user is disabled.",
+                ),
+            },
+
+            Command::ClientReply { mode } => {
+                self.reply_mode = mode;
+                resp::ok()
+            }
+
+            // 重新加载配置不影响已建立的连接
+            Command::ConfigReload if self.queued.is_none() => match self.config.reload() {
+                Ok(_) => resp::ok(),
+                Err(e) => resp::error(&format!("ERR {}", e)),
+            },
+
+            Command::ConfigSet { name, value } if self.queued.is_none() => {
+                match self.config.set(&name, &value) {
+                    Ok(()) => resp::ok(),
+                    Err(e) => resp::error(&format!("ERR {}", e)),
+                }
+            }
+
+            // 加载模块等于在服务器进程中执行任意代码，只能在配置文件中打开
+            Command::ModuleLoad { .. } | Command::ModuleUnload { .. } | Command::ModuleList
+                if !self.config.read().enable_module_command =>
+            {
+                resp::error(
+                    "ERR MODULE command not allowed. Set 'enable-module-command yes' in the configuration file and restart the server.",
+                )
+            }
+
+            Command::Multi => {
+                if self.queued.is_some() {
+                    resp::error("ERR MULTI calls can not be nested")
+                } else {
+                    self.queued = Some(Vec::new());
+                    self.queue_error = false;
+                    resp::ok()
+                }
+            }
+
+            Command::Exec => match self.queued.take() {
+                Some(commands) => self.exec_transaction(commands, store),
+                None => resp::error("ERR EXEC without MULTI"),
+            },
+
+            Command::Discard => match self.queued.take() {
+                Some(_) => {
+                    self.watch = None;
+                    resp::ok()
+                }
+                None => resp::error("ERR DISCARD without MULTI"),
+            },
+
+            Command::Watch { keys } => {
+                if self.queued.is_some() {
+                    resp::error("ERR WATCH inside MULTI is not allowed")
+                } else {
+                    let state = self.watch.get_or_insert_with(|| WatchState {
+                        keys: HashSet::new(),
+                        events: store.subscribe(),
+                    });
+                    state.keys.extend(keys);
+                    resp::ok()
+                }
+            }
+
+            Command::Unwatch => {
+                self.watch = None;
+                resp::ok()
+            }
+
+            // 事务中的普通命令只排队，不执行
+            cmd if self.queued.is_some() && !matches!(cmd, Command::Quit) => {
+                if let Some(queue) = self.queued.as_mut() {
+                    queue.push(cmd);
+                }
+                RespValue::SimpleString("QUEUED".to_string())
+            }
+
+            cmd => return self.execute(cmd, store),
+        };
+
+        (response, false)
+    }
+
+    /// 交给CommandExecutor执行普通命令
+    fn execute(&self, cmd: Command, store: &Store) -> (RespValue, bool) {
+        let (budget, limits) = {
+            let config = self.config.read();
+            let budget = if self.admin { None } else { config.command_budget() };
+            (budget, config.limits())
+        };
+        let _permit = store.shared_access();
+        CommandExecutor::new(store)
+            .with_budget(budget)
+            .with_limits(limits)
+            .execute(cmd)
+    }
+
+    /// 阻塞命令没有取到数据时排队等待，被唤醒后重新执行，直到取到数据或超时
+    ///
+    /// 超时回复nil；等待期间客户端断开时返回None。客户端在阻塞期间发来的命令留在缓冲区，
+    /// 解除阻塞后再依次执行
+    ///
+    /// Rust特点: `select!`同时等待唤醒、超时和连接关闭，未完成的分支被直接丢弃
+    async fn block(&mut self, cmd: &Command, store: &Store) -> RedisResult<Option<RespValue>> {
+        let Some((keys, timeout)) = cmd.blocking() else {
+            return Ok(Some(RespValue::Null));
+        };
+        let keys: Vec<String> = keys.into_iter().map(str::to_string).collect();
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let waiter = store.blocked_clients().block(&keys);
+        debug!(keys = keys.len(), "命令阻塞等待数据");
+
+        loop {
+            // 第一次执行和排队之间写入的数据没有信号，排队后先重试一次
+            let (response, _) = self.execute(cmd.clone(), store);
+            match response {
+                // 类型错误等与Redis一样继续等待，直到键被写入列表
+                RespValue::Null | RespValue::Error(_) => {}
+                response => return Ok(Some(response)),
+            }
+
+            loop {
+                tokio::select! {
+                    _ = waiter.ready() => break,
+                    _ = async {
+                        match deadline {
+                            Some(deadline) => tokio::time::sleep_until(deadline).await,
+                            None => std::future::pending().await,
+                        }
+                    } => return Ok(Some(RespValue::Null)),
+                    read = self.stream.read_buf(&mut self.buffer) => {
+                        self.track_memory(0);
+                        if read? == 0 {
+                            return Ok(None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 执行排队的事务命令
+    ///
+    /// 持有独占许可执行全部命令，保证其他连接的命令不会穿插其中
+    fn exec_transaction(&mut self, commands: Vec<Command>, store: &Store) -> RespValue {
+        let watch = self.watch.take();
+
+        if std::mem::take(&mut self.queue_error) {
+            return resp::error("EXECABORT Transaction discarded because of previous errors.");
+        }
+
+        let _permit = store.exclusive_access();
+
+        // WATCH的键被修改过，放弃事务
+        if let Some(mut watch) = watch {
+            if watch.is_dirty() {
+                return RespValue::Null;
+            }
+        }
+
+        let executor = CommandExecutor::new(store).with_limits(self.config.read().limits());
+        store.stats().record_commands(commands.len() as u64);
+        RespValue::Array(
+            commands
+                .into_iter()
+                .map(|cmd| executor.execute(cmd).0)
+                .collect(),
+        )
+    }
+
     /// 从连接读取命令
     ///
     /// Rust特点:
@@ -110,12 +699,18 @@ impl Connection {
     async fn read_command(&mut self) -> RedisResult<Option<RespValue>> {
         loop {
             // 先尝试从缓冲区解析命令
-            if let Some(value) = RespParser::parse(&mut self.buffer)? {
+            // 单个参数不能超过max-value-size，超大的请求在缓冲之前就被拒绝
+            let max_bulk_len = match self.config.read().max_value_size {
+                0 => usize::MAX,
+                limit => limit as usize,
+            };
+            if let Some(value) = RespParser::parse_limited(&mut self.buffer, max_bulk_len)? {
                 return Ok(Some(value));
             }
 
             // 缓冲区中没有完整命令，从网络读取更多数据
             let bytes_read = self.stream.read_buf(&mut self.buffer).await?;
+            self.track_memory(0);
 
             // 如果读取到0字节，说明连接已关闭
             if bytes_read == 0 {
@@ -129,19 +724,69 @@ impl Connection {
         }
     }
 
+    /// 按CLIENT REPLY模式发送命令的回复
+    ///
+    /// SKIP只作用于下一条命令，之后恢复为ON
+    async fn reply(&mut self, response: &RespValue) -> RedisResult<()> {
+        match self.reply_mode {
+            ReplyMode::On => self.write_response(response).await,
+            ReplyMode::Off => Ok(()),
+            ReplyMode::Skip => {
+                self.reply_mode = ReplyMode::On;
+                Ok(())
+            }
+        }
+    }
+
     /// 写入响应
     ///
     /// Rust特点: 引用避免不必要的数据复制
     async fn write_response(&mut self, response: &RespValue) -> RedisResult<()> {
         let data = response.serialize();
+        // 慢客户端接收期间，回复一直占用内存
+        self.track_memory(data.len());
         self.stream.write_all(&data).await?;
         self.stream.flush().await?;
+        self.track_memory(0);
         Ok(())
     }
+
+    /// 登记输入缓冲区和正在发送的回复(`output`字节)占用的内存，检查maxmemory-clients
+    fn track_memory(&self, output: usize) {
+        if let Some(client) = &self.client {
+            let limit = self.config.read().maxmemory_clients as usize;
+            client.set_memory(self.buffer.capacity() + output, limit);
+        }
+    }
+}
+
+/// 执行命令，把命令处理中的panic转换为错误回复
+///
+/// panic只影响当前这条命令: 连接继续服务，其他连接不受影响；Store会继续使用中毒的锁
+///
+/// Rust特点: `catch_unwind`要求闭包是UnwindSafe，`AssertUnwindSafe`表明panic后状态仍可使用
+fn isolate(name: &str, f: impl FnOnce() -> (RespValue, bool)) -> (RespValue, bool) {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        error!(command = name, panic = panic_message(payload.as_ref()), "命令执行时panic");
+        let message = format!("ERR internal error while executing '{}'", name.to_lowercase());
+        (RespValue::Error(message), false)
+    })
+}
+
+/// panic的消息，`panic!`的参数是字符串字面量或格式化字符串
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
 }
 
 /// 后台任务：定期清理过期的键
 ///
+/// 定时器和键的过期判断分别来自tokio运行时和Store的时钟，
+/// 使用`TokioClock`时两者一致，在暂停或模拟的运行时中可以精确控制清理时机
+///
 /// Rust特点: 独立的异步任务，通过Arc共享Store
 pub async fn cleanup_task(store: Store, interval_secs: u64) {
     use tokio::time::{interval, Duration};
@@ -152,7 +797,7 @@ pub async fn cleanup_task(store: Store, interval_secs: u64) {
         ticker.tick().await;
         let cleaned = store.cleanup_expired();
         if cleaned > 0 {
-            println!("[清理任务] 清理了 {} 个过期的键", cleaned);
+            info!(target: "cleanup", cleaned, "清理过期的键");
         }
     }
 }
@@ -160,6 +805,7 @@ pub async fn cleanup_task(store: Store, interval_secs: u64) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Config;
 
     // 异步测试需要tokio的测试宏
     #[tokio::test]
@@ -168,5 +814,191 @@ mod tests {
         let store = Store::new();
         assert_eq!(store.dbsize(), 0);
     }
+
+    #[tokio::test]
+    async fn test_client_reply_modes() {
+        let (stream, peer) = tokio::io::duplex(4096);
+        let store = Store::new();
+        let handle = tokio::spawn(async move {
+            let mut conn = Connection::with_addr(stream, "test".to_string());
+            conn.handle(&store).await
+        });
+
+        let mut client = crate::client::Client::from_stream(peer);
+        let encode = |args: &[&str]| {
+            let value = RespValue::Array(args.iter().map(|a| resp::bulk_string(a)).collect());
+            value.serialize()
+        };
+        let mut pipeline = Vec::new();
+        for args in [
+            &["CLIENT", "REPLY", "OFF"][..],
+            &["SET", "a", "1"],
+            &["CLIENT", "REPLY", "ON"],
+            &["CLIENT", "REPLY", "SKIP"],
+            &["GET", "a"],
+            &["ECHO", "done"],
+        ] {
+            pipeline.extend(encode(args));
+        }
+        client.send_raw(&pipeline).await.unwrap();
+
+        // 只有CLIENT REPLY ON和最后的ECHO有回复
+        assert_eq!(client.read_reply().await.unwrap(), resp::ok());
+        assert_eq!(client.read_reply().await.unwrap(), resp::bulk_string("done"));
+
+        drop(client);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_command_rate() {
+        let config = Config {
+            client_command_rate: 20,
+            ..Default::default()
+        };
+        let config = SharedConfig::new(config);
+        let (stream, peer) = tokio::io::duplex(4096);
+        let store = Store::new();
+        let shared = config.clone();
+        let handle = tokio::spawn(async move {
+            let mut conn = Connection::with_addr(stream, "test".to_string()).with_config(shared);
+            conn.handle(&store).await
+        });
+        let mut client = crate::client::Client::from_stream(peer);
+
+        // 一秒内允许20条命令集中到达，之后每条间隔50ms
+        let start = Instant::now();
+        for _ in 0..25 {
+            assert_eq!(client.ping().await.unwrap(), "PONG");
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // 改为断开后，超出速率的命令收到错误并断开
+        config.set_source(|| {
+            Ok(Config {
+                client_command_rate: 20,
+                client_rate_action: RateLimitAction::Disconnect,
+                ..Default::default()
+            })
+        });
+        config.reload().unwrap();
+        let err = client.ping().await.unwrap_err();
+        assert!(err.to_string().contains("client-command-rate"));
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop() {
+        let store = Store::new();
+        let connect = |store: Store| {
+            let (stream, peer) = tokio::io::duplex(4096);
+            tokio::spawn(async move {
+                let mut conn = Connection::with_addr(stream, "test".to_string());
+                conn.handle(&store).await
+            });
+            crate::client::Client::from_stream(peer)
+        };
+        let wait_blocked = |count: usize| {
+            let store = store.clone();
+            async move {
+                while store.blocked_clients().len() < count {
+                    tokio::task::yield_now().await;
+                }
+            }
+        };
+
+        // 先阻塞的连接先得到数据
+        let mut first = connect(store.clone());
+        let mut second = connect(store.clone());
+        let mut writer = connect(store.clone());
+        let first_pop = tokio::spawn(async move { first.command(["BLPOP", "q", "0"]).await });
+        wait_blocked(1).await;
+        let second_pop =
+            tokio::spawn(async move { second.command(["BRPOP", "other", "q", "0"]).await });
+        wait_blocked(2).await;
+
+        let pushed = writer.command(["RPUSH", "q", "a", "b"]).await.unwrap();
+        assert_eq!(pushed, RespValue::Integer(2));
+        let reply = |key: &str, item: &str| {
+            RespValue::Array(vec![resp::bulk_string(key), resp::bulk_string(item)])
+        };
+        assert_eq!(first_pop.await.unwrap().unwrap(), reply("q", "a"));
+        assert_eq!(second_pop.await.unwrap().unwrap(), reply("q", "b"));
+        assert!(store.blocked_clients().is_empty());
+
+        // 超时回复nil，BLMOVE被写入源列表的命令唤醒
+        let timed_out = writer.command(["BLPOP", "q", "0.01"]).await.unwrap();
+        assert_eq!(timed_out, RespValue::Null);
+        let mut mover = connect(store.clone());
+        let moved = tokio::spawn(async move {
+            mover.command(["BLMOVE", "q", "d", "LEFT", "RIGHT", "0"]).await
+        });
+        wait_blocked(1).await;
+        writer.command(["LPUSH", "q", "c"]).await.unwrap();
+        assert_eq!(moved.await.unwrap().unwrap(), resp::bulk_string("c"));
+        assert_eq!(writer.command(["LLEN", "d"]).await.unwrap(), RespValue::Integer(1));
+
+        // 有序集合的阻塞命令被ZADD唤醒
+        let mut zpop = connect(store.clone());
+        let popped = tokio::spawn(async move { zpop.command(["BZPOPMIN", "z", "0"]).await });
+        wait_blocked(1).await;
+        writer.command(["ZADD", "z", "2", "m"]).await.unwrap();
+        let expected = RespValue::Array(vec![
+            resp::bulk_string("z"),
+            resp::bulk_string("m"),
+            resp::bulk_string("2"),
+        ]);
+        assert_eq!(popped.await.unwrap().unwrap(), expected);
+
+        // 阻塞期间断开连接会离开等待队列
+        let mut leaver = connect(store.clone());
+        let request = crate::client::build_command(["BLPOP", "q", "0"]);
+        leaver.send(&request).await.unwrap();
+        wait_blocked(1).await;
+        drop(leaver);
+        while !store.blocked_clients().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        writer.command(["LPUSH", "q", "e"]).await.unwrap();
+        assert_eq!(writer.command(["LLEN", "q"]).await.unwrap(), RespValue::Integer(1));
+    }
+
+    #[test]
+    fn test_isolate_panic() {
+        let store = Store::new();
+        let (response, should_quit) = isolate("SET", || {
+            let _permit = store.shared_access();
+            let _ = store.update_with("k", |_| -> (crate::store::Update, ()) { panic!("bug in handler") });
+            unreachable!()
+        });
+        assert_eq!(response, resp::error("ERR internal error while executing 'set'"));
+        assert!(!should_quit);
+
+        // 持有锁时panic，之后的命令照常执行
+        let _permit = store.exclusive_access();
+        store.set("k".to_string(), b"v".to_vec());
+        assert_eq!(store.get("k"), Some(b"v".to_vec()));
+        assert_eq!(isolate("PING", || (resp::pong(), false)).0, resp::pong());
+    }
+
+    #[tokio::test]
+    async fn test_renamed_commands() {
+        let config = Config::parse_conf("rename-command FLUSHALL \"\"\nrename-command DEL remove\n").unwrap();
+        let (stream, _peer) = tokio::io::duplex(64);
+        let conn = Connection::with_addr(stream, "test".to_string())
+            .with_config(SharedConfig::new(config));
+
+        let command = |args: &[&str]| {
+            let value = RespValue::Array(args.iter().map(|a| resp::bulk_string(a)).collect());
+            conn.resolve_command(value).and_then(Command::from_resp)
+        };
+        assert!(matches!(command(&["REMOVE", "k"]), Ok(Command::Del { .. })));
+        assert_eq!(
+            command(&["del", "k"]).unwrap_err().to_string(),
+            "unknown command 'del'"
+        );
+        assert!(command(&["FLUSHALL"]).is_err());
+        assert!(matches!(command(&["GET", "k"]), Ok(Command::Get { .. })));
+    }
 }
 
@@ -0,0 +1,112 @@
+//! 守护进程模块 - daemonize和pid文件(Unix)
+//!
+//! 必须在启动tokio运行时之前调用`daemonize`: fork只会复制调用线程，
+//! 多线程运行时在子进程中将处于不可用状态。
+//!
+//! Rust特点展示:
+//! - 通过libc调用系统接口(fork、setsid、dup2)
+//! - unsafe 块隔离FFI调用
+//! - Drop trait 在退出时删除pid文件
+
+use crate::error::RedisResult;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// daemonize且未配置pidfile时使用的默认路径，与Redis一致
+pub const DEFAULT_PID_FILE: &str = "/var/run/redis.pid";
+
+/// 转入后台运行
+///
+/// fork后父进程退出，子进程调用setsid脱离终端；
+/// 标准输入重定向到/dev/null，标准输出和错误重定向到日志文件(未配置时为/dev/null)
+pub fn daemonize(logfile: Option<&Path>) -> RedisResult<()> {
+    // Rust特点: FFI调用总是unsafe，返回值需要手动检查
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error().into()),
+        0 => {}
+        // 父进程直接退出，不运行任何析构函数
+        _ => std::process::exit(0),
+    }
+
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let null = File::open("/dev/null")?;
+    redirect(&null, libc::STDIN_FILENO)?;
+
+    let output = match logfile {
+        Some(path) => OpenOptions::new().create(true).append(true).open(path)?,
+        None => OpenOptions::new().write(true).open("/dev/null")?,
+    };
+    redirect(&output, libc::STDOUT_FILENO)?;
+    redirect(&output, libc::STDERR_FILENO)?;
+    Ok(())
+}
+
+/// 让标准流`target`指向`file`
+fn redirect(file: &File, target: libc::c_int) -> RedisResult<()> {
+    if unsafe { libc::dup2(file.as_raw_fd(), target) } == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// pid文件 - 创建时写入当前进程号，被drop时删除
+///
+/// 平滑升级后新进程会改写同一个文件，所以只在文件中仍是本进程的pid时删除
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// 写入pid文件
+    pub fn create(path: impl AsRef<Path>) -> RedisResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::write(&path, format!("{}\n", std::process::id()))?;
+        Ok(Self { path })
+    }
+
+    /// pid文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let owned = std::fs::read_to_string(&self.path)
+            .is_ok_and(|content| content.trim() == std::process::id().to_string());
+        if owned {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file() {
+        let path = std::env::temp_dir().join(format!("rust-redis-{}.pid", std::process::id()));
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(pid_file.path()).unwrap().trim(),
+            std::process::id().to_string()
+        );
+
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // 已被其他进程改写的pid文件不删除
+        let pid_file = PidFile::create(&path).unwrap();
+        std::fs::write(&path, "1\n").unwrap();
+        drop(pid_file);
+        assert!(path.exists());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,98 @@
+//! 编码模块 - 小集合的紧凑编码和转换阈值(OBJECT ENCODING)
+//!
+//! 与Redis一样，元素少且都较短的集合类型使用紧凑编码，省去哈希表的桶和索引:
+//! - 哈希、集合: 按插入顺序排列的数组(`listpack`)，查找是线性扫描
+//! - 只含整数的集合: 按数值排序的数组(`intset`)，二分查找
+//! - 有序集合: 只保留按分数排序的部分，不建成员到分数的索引(`listpack`)
+//!
+//! 元素数或元素长度超过阈值后转换为`hashtable`/`skiplist`，转换是单向的。
+//! 阈值来自hash-max-listpack-entries等配置项，Store在每次写入后检查。
+//!
+//! Rust特点展示:
+//! - `Copy`的小结构体按值传递，不需要引用计数
+//! - 常量约束配置的取值范围
+
+use crate::error::{RedisError, RedisResult};
+
+/// 紧凑编码的元素数上限，阈值配置不能超过它
+///
+/// 紧凑编码的插入是O(n)，单条命令插入大量元素时超过该上限就直接转换，
+/// 避免退化为O(n²)
+pub const MAX_COMPACT_ENTRIES: usize = 4096;
+
+/// 紧凑编码的转换阈值，默认值与Redis相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodingThresholds {
+    pub hash_max_listpack_entries: usize,
+    pub hash_max_listpack_value: usize,
+    pub set_max_intset_entries: usize,
+    pub set_max_listpack_entries: usize,
+    pub set_max_listpack_value: usize,
+    pub zset_max_listpack_entries: usize,
+    pub zset_max_listpack_value: usize,
+}
+
+impl Default for EncodingThresholds {
+    fn default() -> Self {
+        Self {
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            set_max_intset_entries: 512,
+            set_max_listpack_entries: 128,
+            set_max_listpack_value: 64,
+            zset_max_listpack_entries: 128,
+            zset_max_listpack_value: 64,
+        }
+    }
+}
+
+/// 解析元素数阈值，不能超过`MAX_COMPACT_ENTRIES`
+pub fn parse_entries(name: &str, value: &str) -> RedisResult<usize> {
+    value
+        .parse()
+        .ok()
+        .filter(|&entries| entries <= MAX_COMPACT_ENTRIES)
+        .ok_or_else(|| {
+            RedisError::Config(format!("无效的{}: {} (最大为{})", name, value, MAX_COMPACT_ENTRIES))
+        })
+}
+
+/// 字符串的编码: 可以表示为64位整数的是`int`，不超过44字节的是`embstr`，其余是`raw`
+pub fn string_encoding(data: &[u8]) -> &'static str {
+    if is_integer(data) {
+        "int"
+    } else if data.len() <= 44 {
+        "embstr"
+    } else {
+        "raw"
+    }
+}
+
+/// 是否是64位整数的规范形式(没有前导0和`+`号)，这样的值转换为整数后不会丢失信息
+pub fn is_integer(data: &[u8]) -> bool {
+    data.len() <= 20
+        && std::str::from_utf8(data)
+            .ok()
+            .and_then(|text| text.parse::<i64>().ok())
+            .is_some_and(|n| n.to_string().as_bytes() == data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_encoding() {
+        assert_eq!(string_encoding(b"12345"), "int");
+        assert_eq!(string_encoding(b"-9223372036854775808"), "int");
+        assert_eq!(string_encoding(b"9223372036854775808"), "embstr");
+        assert_eq!(string_encoding(b"007"), "embstr");
+        assert_eq!(string_encoding(b"+1"), "embstr");
+        assert_eq!(string_encoding(&[b'x'; 44]), "embstr");
+        assert_eq!(string_encoding(&[b'x'; 45]), "raw");
+
+        assert_eq!(parse_entries("set-max-intset-entries", "4096").unwrap(), 4096);
+        assert!(parse_entries("set-max-intset-entries", "4097").is_err());
+        assert!(parse_entries("set-max-intset-entries", "-1").is_err());
+    }
+}
@@ -35,10 +35,22 @@ pub enum RedisError {
         got: usize,
     },
 
+    /// 命令参数无效，内容与Redis返回的错误信息一致(不含ERR前缀)
+    #[error("{0}")]
+    InvalidArgument(String),
+
     /// 类型错误
     #[error("类型错误: {0}")]
     TypeError(String),
 
+    /// 对错误类型的键执行命令，如对JSON文档执行GET
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    /// 带自己错误码的命令错误，如流消费者组的`BUSYGROUP`、`NOGROUP`，回复时不加ERR前缀
+    #[error("{code} {message}")]
+    Coded { code: &'static str, message: String },
+
     /// UTF-8解析错误
     #[error("UTF-8解析错误: {0}")]
     Utf8Error(#[from] FromUtf8Error),
@@ -47,6 +59,34 @@ pub enum RedisError {
     #[error("整数解析错误: {0}")]
     ParseIntError(#[from] ParseIntError),
 
+    /// JSON序列化/反序列化错误
+    #[error("JSON错误: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// 服务器返回的错误回复(客户端使用)
+    #[error("服务器错误: {0}")]
+    ServerError(String),
+
+    /// WATCH的键被反复修改，乐观事务重试次数耗尽
+    #[error("事务在重试 {0} 次后仍被中止")]
+    TransactionAborted(usize),
+
+    /// TLS配置或握手错误
+    #[error("TLS错误: {0}")]
+    Tls(String),
+
+    /// 编解码错误(bincode、MessagePack等)
+    #[error("编解码错误: {0}")]
+    Codec(String),
+
+    /// 配置错误(命令行参数或配置项无效)
+    #[error("配置错误: {0}")]
+    Config(String),
+
+    /// 模块加载、卸载失败
+    #[error("模块错误: {0}")]
+    Module(String),
+
     /// 连接已关闭
     #[error("连接已关闭")]
     ConnectionClosed,
@@ -61,6 +101,16 @@ pub enum RedisError {
 /// Rust特点: 类型别名提高代码可读性
 pub type RedisResult<T> = Result<T, RedisError>;
 
+/// 变体相同且错误信息相同时相等，测试中可以直接比较`RedisResult`
+///
+/// Rust特点: io::Error等内部错误没有实现PartialEq，不能derive，手动按显示内容比较
+impl PartialEq for RedisError {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.to_string() == other.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,5 +127,16 @@ mod tests {
         };
         assert!(err.to_string().contains("SET"));
     }
+
+    #[test]
+    fn test_error_eq() {
+        let result: RedisResult<i64> = Ok(1);
+        assert_eq!(result, Ok(1));
+        assert_eq!(RedisError::WrongType, RedisError::WrongType);
+        assert_ne!(
+            RedisError::Protocol("x".to_string()),
+            RedisError::ServerError("x".to_string())
+        );
+    }
 }
 
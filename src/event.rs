@@ -0,0 +1,101 @@
+//! 存储变更事件模块 - 展示Rust的广播通道
+//!
+//! 嵌入Store的应用程序可以订阅数据变更，在进程内对修改做出反应，
+//! 无需经过RESP协议。
+//!
+//! Rust特点展示:
+//! - tokio::sync::broadcast 多生产者多消费者通道
+//! - 枚举携带数据描述不同事件
+//! - async fn 把"等待某个键变化"包装成可以await的操作
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// 事件通道的容量 - 慢速订阅者落后超过此数量时会收到Lagged错误
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 存储变更事件
+///
+/// Rust特点: 枚举变体携带受影响的键，订阅者用match区分处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreEvent {
+    /// 键被写入(SET、INCR、APPEND等)
+    Set { key: String },
+    /// 键被删除
+    Del { key: String },
+    /// 键被设置了过期时间
+    Expire { key: String },
+    /// 键的过期时间被移除
+    Persist { key: String },
+    /// 键因过期被清理
+    Expired { key: String },
+    /// 键因内存超过maxmemory被淘汰
+    Evicted { key: String },
+    /// 键被重命名
+    Rename { from: String, to: String },
+    /// 数据库被清空
+    Flush,
+}
+
+impl StoreEvent {
+    /// 获取事件涉及的键(FLUSHDB不涉及具体键)
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            StoreEvent::Set { key }
+            | StoreEvent::Del { key }
+            | StoreEvent::Expire { key }
+            | StoreEvent::Persist { key }
+            | StoreEvent::Expired { key }
+            | StoreEvent::Evicted { key } => Some(key),
+            StoreEvent::Rename { to, .. } => Some(to),
+            StoreEvent::Flush => None,
+        }
+    }
+
+    /// 事件是否可能改变了`key`的值: 直接涉及该键、重命名的任一端或清空数据库
+    pub fn affects(&self, key: &str) -> bool {
+        match self {
+            StoreEvent::Rename { from, to } => from == key || to == key,
+            StoreEvent::Flush => true,
+            event => event.key() == Some(key),
+        }
+    }
+}
+
+/// 单个键的变化监听器，由`Store::key_watcher`创建
+///
+/// 从创建时刻开始记录事件，反复调用`changed`即可像流一样依次得到该键的每次变化
+#[derive(Debug)]
+pub struct KeyWatcher {
+    key: String,
+    events: broadcast::Receiver<StoreEvent>,
+}
+
+impl KeyWatcher {
+    pub(crate) fn new(key: String, events: broadcast::Receiver<StoreEvent>) -> Self {
+        Self { key, events }
+    }
+
+    /// 被监听的键
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 等待键的下一次变化(写入、删除、过期、重命名或清空)
+    ///
+    /// 返回None表示无法确定: 事件积压被丢弃，或者Store已全部释放。
+    /// 这时应重新读取键的当前值，而不是假设它没有变化
+    pub async fn changed(&mut self) -> Option<StoreEvent> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) if event.affects(&self.key) => return Some(event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => {
+                    // 丢弃积压，下次调用从最新的事件继续
+                    self.events = self.events.resubscribe();
+                    return None;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
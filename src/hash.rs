@@ -0,0 +1,306 @@
+//! 哈希模块 - H*命令使用的字段表，字段可以单独设置过期时间(HEXPIRE等)
+//!
+//! 小哈希使用按插入顺序排列的数组(listpack)，超过阈值后转换为HashMap(hashtable)。
+//!
+//! 字段的过期时间与键的过期时间一样是Unix毫秒。除了字段到过期时间的HashMap，
+//! 还用BTreeSet按时间排序，最早过期的字段在最前面: 检查有没有过期字段只需要看第一个，
+//! 读取时(惰性)和定期清理时(主动)都据此删除过期字段。
+//!
+//! Rust特点展示:
+//! - 两个表的同步由方法封装保证，字段不对外公开
+//! - 私有枚举隐藏两种编码，方法对调用者保持不变
+//! - `impl Iterator`返回迭代器而不暴露内部的HashMap
+
+use crate::encoding::{EncodingThresholds, MAX_COMPACT_ENTRIES};
+use std::collections::{BTreeSet, HashMap};
+
+/// 过期时间的上限(Unix毫秒)，与Redis的字段过期时间一样是2^48-1
+pub const MAX_EXPIRES_AT: u64 = (1 << 48) - 1;
+
+/// HEXPIRE的NX|XX|GT|LT条件
+///
+/// 没有过期时间视为无穷大: GT总是不满足，LT总是满足
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// 只在没有过期时间时设置
+    Nx,
+    /// 只在已有过期时间时设置
+    Xx,
+    /// 只在新的过期时间更晚时设置
+    Gt,
+    /// 只在新的过期时间更早时设置
+    Lt,
+}
+
+impl ExpireCondition {
+    /// 解析条件名(不区分大小写)，不是条件时返回None
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "NX" => Some(Self::Nx),
+            "XX" => Some(Self::Xx),
+            "GT" => Some(Self::Gt),
+            "LT" => Some(Self::Lt),
+            _ => None,
+        }
+    }
+
+    /// 当前过期时间为`current`时，能否设置为`new`
+    pub fn allows(self, current: Option<u64>, new: u64) -> bool {
+        match self {
+            Self::Nx => current.is_none(),
+            Self::Xx => current.is_some(),
+            Self::Gt => current.is_some_and(|current| new > current),
+            Self::Lt => current.is_none_or(|current| new < current),
+        }
+    }
+}
+
+/// 字段表的内部表示
+#[derive(Debug, Clone)]
+enum Fields {
+    /// 按插入顺序排列的(字段, 值)，线性查找
+    ListPack(Vec<(Vec<u8>, Vec<u8>)>),
+    HashTable(HashMap<Vec<u8>, Vec<u8>>),
+}
+
+/// 哈希: 字段 -> 值，字段和值都是二进制安全的
+///
+/// 小哈希使用listpack编码，超过阈值后转换为hashtable(见`encoding`模块)
+#[derive(Debug, Clone)]
+pub struct Hash {
+    /// 字段 -> 值
+    fields: Fields,
+    /// 设置了过期时间的字段 -> 过期时间
+    expires: HashMap<Vec<u8>, u64>,
+    /// 按(过期时间, 字段)排序
+    order: BTreeSet<(u64, Vec<u8>)>,
+}
+
+impl Hash {
+    /// 创建空哈希，初始为listpack编码
+    pub fn new() -> Self {
+        Self {
+            fields: Fields::ListPack(Vec::new()),
+            expires: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    /// 字段数
+    pub fn len(&self) -> usize {
+        match &self.fields {
+            Fields::ListPack(fields) => fields.len(),
+            Fields::HashTable(fields) => fields.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 编码名称(OBJECT ENCODING)，有字段设置了过期时间的listpack是`listpackex`
+    pub fn encoding(&self) -> &'static str {
+        match self.fields {
+            Fields::ListPack(_) if !self.expires.is_empty() => "listpackex",
+            Fields::ListPack(_) => "listpack",
+            Fields::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Vec<u8>> {
+        match &self.fields {
+            Fields::ListPack(fields) => fields.iter().find(|(f, _)| f == field).map(|(_, v)| v),
+            Fields::HashTable(fields) => fields.get(field),
+        }
+    }
+
+    /// 修改字段的值，过期时间保留(HINCRBY)
+    pub fn get_mut(&mut self, field: &[u8]) -> Option<&mut Vec<u8>> {
+        match &mut self.fields {
+            Fields::ListPack(fields) => {
+                fields.iter_mut().find(|(f, _)| f == field).map(|(_, v)| v)
+            }
+            Fields::HashTable(fields) => fields.get_mut(field),
+        }
+    }
+
+    pub fn contains_key(&self, field: &[u8]) -> bool {
+        self.get(field).is_some()
+    }
+
+    /// 设置字段的值，返回旧值；与Redis的HSET一样，覆盖字段时清除它的过期时间
+    ///
+    /// listpack超过`MAX_COMPACT_ENTRIES`时直接转换为hashtable，其余的转换由`fit`进行
+    pub fn insert(&mut self, field: Vec<u8>, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.set_expires_at(&field, None);
+        if self.len() >= MAX_COMPACT_ENTRIES {
+            self.convert();
+        }
+        match &mut self.fields {
+            Fields::ListPack(fields) => match fields.iter_mut().find(|(f, _)| *f == field) {
+                Some((_, old)) => Some(std::mem::replace(old, value)),
+                None => {
+                    fields.push((field, value));
+                    None
+                }
+            },
+            Fields::HashTable(fields) => fields.insert(field, value),
+        }
+    }
+
+    /// 删除字段，返回旧值
+    pub fn remove(&mut self, field: &[u8]) -> Option<Vec<u8>> {
+        self.set_expires_at(field, None);
+        self.remove_field(field)
+    }
+
+    /// 遍历(字段, 值): listpack按插入顺序(删除会打乱)，hashtable顺序不确定
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &Vec<u8>)> {
+        let (list, table) = match &self.fields {
+            Fields::ListPack(fields) => (Some(fields), None),
+            Fields::HashTable(fields) => (None, Some(fields)),
+        };
+        list.into_iter()
+            .flatten()
+            .map(|(f, v)| (f, v))
+            .chain(table.into_iter().flatten())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.iter().map(|(field, _)| field)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// 按阈值转换编码: 字段数或任何字段、值的长度超过阈值时listpack转换为hashtable
+    pub fn fit(&mut self, thresholds: &EncodingThresholds) {
+        if let Fields::ListPack(fields) = &self.fields {
+            let max = thresholds.hash_max_listpack_value;
+            if fields.len() > thresholds.hash_max_listpack_entries
+                || fields.iter().any(|(f, v)| f.len() > max || v.len() > max)
+            {
+                self.convert();
+            }
+        }
+    }
+
+    /// listpack转换为hashtable
+    fn convert(&mut self) {
+        if let Fields::ListPack(fields) = &mut self.fields {
+            self.fields = Fields::HashTable(std::mem::take(fields).into_iter().collect());
+        }
+    }
+
+    fn remove_field(&mut self, field: &[u8]) -> Option<Vec<u8>> {
+        match &mut self.fields {
+            Fields::ListPack(fields) => {
+                let i = fields.iter().position(|(f, _)| f == field)?;
+                Some(fields.swap_remove(i).1)
+            }
+            Fields::HashTable(fields) => fields.remove(field),
+        }
+    }
+
+    /// 字段的过期时间(Unix毫秒)，没有设置时为None
+    pub fn expires_at(&self, field: &[u8]) -> Option<u64> {
+        self.expires.get(field).copied()
+    }
+
+    /// 设置或清除(None)字段的过期时间，字段不存在时不做任何事
+    pub fn set_expires_at(&mut self, field: &[u8], expires_at: Option<u64>) {
+        if let Some(old) = self.expires.remove(field) {
+            self.order.remove(&(old, field.to_vec()));
+        }
+        if let Some(expires_at) = expires_at.filter(|_| self.contains_key(field)) {
+            self.expires.insert(field.to_vec(), expires_at);
+            self.order.insert((expires_at, field.to_vec()));
+        }
+    }
+
+    /// 在给定时间(Unix毫秒)是否有已过期的字段
+    pub fn has_expired(&self, now: u64) -> bool {
+        self.order.first().is_some_and(|(expires_at, _)| now > *expires_at)
+    }
+
+    /// 删除已过期的字段，返回删除的数量
+    pub fn purge_expired(&mut self, now: u64) -> usize {
+        let mut removed = 0;
+        while self.has_expired(now) {
+            if let Some((_, field)) = self.order.pop_first() {
+                self.expires.remove(&field);
+                self.remove_field(&field);
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl Default for Hash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 字段、值和过期时间相同即相等，与编码无关
+impl PartialEq for Hash {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.expires == other.expires
+            && self.iter().all(|(field, value)| other.get(field) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_expiry() {
+        let mut hash = Hash::new();
+        hash.insert(b"a".to_vec(), b"1".to_vec());
+        hash.insert(b"b".to_vec(), b"2".to_vec());
+        hash.insert(b"c".to_vec(), b"3".to_vec());
+        hash.set_expires_at(b"a", Some(100));
+        hash.set_expires_at(b"b", Some(200));
+        // 不存在的字段不能设置过期时间
+        hash.set_expires_at(b"missing", Some(50));
+        assert_eq!(hash.expires_at(b"a"), Some(100));
+        assert_eq!(hash.expires_at(b"missing"), None);
+
+        // 修改值保留过期时间，覆盖字段时清除
+        *hash.get_mut(b"a").unwrap() = b"10".to_vec();
+        assert_eq!(hash.expires_at(b"a"), Some(100));
+        hash.insert(b"b".to_vec(), b"20".to_vec());
+        assert_eq!(hash.expires_at(b"b"), None);
+
+        assert!(!hash.has_expired(100));
+        assert!(hash.has_expired(101));
+        assert_eq!(hash.purge_expired(101), 1);
+        assert!(!hash.contains_key(b"a"));
+        assert_eq!(hash.len(), 2);
+
+        hash.set_expires_at(b"c", Some(300));
+        hash.set_expires_at(b"c", None);
+        assert_eq!(hash.purge_expired(u64::MAX), 0);
+        hash.set_expires_at(b"c", Some(300));
+        hash.remove(b"c");
+        assert_eq!(hash.purge_expired(u64::MAX), 0);
+        assert_eq!(hash.len(), 1);
+    }
+
+    #[test]
+    fn test_expire_condition() {
+        assert_eq!(ExpireCondition::parse("gt"), Some(ExpireCondition::Gt));
+        assert_eq!(ExpireCondition::parse("FIELDS"), None);
+        assert!(ExpireCondition::Nx.allows(None, 10));
+        assert!(!ExpireCondition::Nx.allows(Some(5), 10));
+        assert!(ExpireCondition::Xx.allows(Some(5), 1));
+        assert!(!ExpireCondition::Gt.allows(None, 10));
+        assert!(ExpireCondition::Gt.allows(Some(5), 10));
+        assert!(ExpireCondition::Lt.allows(None, 10));
+        assert!(!ExpireCondition::Lt.allows(Some(5), 10));
+    }
+}
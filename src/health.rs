@@ -0,0 +1,184 @@
+//! 健康检查模块 - 为Kubernetes等编排系统提供探针
+//!
+//! - `check`: 向运行中的服务器发送PING，用于`redis-server --healthcheck`
+//! - `serve`: 一个极简的HTTP服务，`GET /healthz`返回就绪状态
+//!
+//! Rust特点展示:
+//! - 原子类型 AtomicBool 实现无锁的状态共享
+//! - tokio::time::timeout 为异步操作设置超时
+//! - 手写最小HTTP响应，无需引入Web框架
+
+use crate::client::Client;
+use crate::error::{RedisError, RedisResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tracing::{debug, info};
+
+/// 健康检查的默认超时时间
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 服务器健康状态
+///
+/// Rust特点: Clone只复制Arc，服务器各部分共享同一份状态
+#[derive(Debug, Clone, Default)]
+pub struct Health {
+    inner: Arc<HealthState>,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    /// 是否正在加载数据
+    loading: AtomicBool,
+}
+
+impl Health {
+    /// 创建健康状态(未在加载)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置加载状态，加载期间服务器不就绪
+    pub fn set_loading(&self, loading: bool) {
+        self.inner.loading.store(loading, Ordering::Relaxed);
+    }
+
+    /// 是否正在加载数据
+    pub fn is_loading(&self) -> bool {
+        self.inner.loading.load(Ordering::Relaxed)
+    }
+
+    /// 是否就绪: 不在加载中
+    ///
+    /// 服务器没有复制功能，总是以主节点运行，因此不检查主从连接
+    pub fn is_ready(&self) -> bool {
+        !self.is_loading()
+    }
+
+    /// 就绪状态的JSON描述
+    pub fn report(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": if self.is_ready() { "ok" } else { "unavailable" },
+            "loading": self.is_loading(),
+        })
+    }
+}
+
+/// 检查服务器是否存活: 在超时时间内连接并收到PONG
+pub async fn check(addr: impl ToSocketAddrs, timeout: Duration) -> RedisResult<()> {
+    let ping = async {
+        let mut client = Client::connect(addr).await?;
+        match client.ping().await?.as_str() {
+            "PONG" => Ok(()),
+            other => Err(RedisError::Protocol(format!("意外的PING回复: {}", other))),
+        }
+    };
+
+    tokio::time::timeout(timeout, ping)
+        .await
+        .map_err(|_| RedisError::Internal("健康检查超时".to_string()))?
+}
+
+/// 在`listener`上提供`GET /healthz`
+///
+/// 就绪时返回200，否则返回503；响应体是`Health::report`的JSON
+pub async fn serve(listener: TcpListener, health: Health) -> RedisResult<()> {
+    info!(addr = %listener.local_addr()?, "健康检查HTTP服务已启动");
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(socket, &health).await {
+                debug!(error = %e, "健康检查请求处理失败");
+            }
+        });
+    }
+}
+
+/// 处理一个HTTP请求
+async fn respond(socket: TcpStream, health: &Health) -> RedisResult<()> {
+    let mut reader = BufReader::new(socket);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // 读完请求头，空行表示结束
+    let mut line = String::new();
+    while reader.read_line(&mut line).await? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/healthz")) => {
+            let status = if health.is_ready() {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (status, health.report().to_string())
+        }
+        _ => ("404 Not Found", r#"{"status":"not found"}"#.to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let mut socket = reader.into_inner();
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use tokio::io::AsyncReadExt;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let server = TestServer::start().await;
+        assert!(check(server.addr(), CHECK_TIMEOUT).await.is_ok());
+
+        let addr = server.addr();
+        drop(server);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(check(addr, CHECK_TIMEOUT).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_healthz() {
+        let health = Health::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, health.clone()));
+
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains(r#""status":"ok""#));
+
+        health.set_loading(true);
+        let response = get(addr, "/healthz").await;
+        assert!(response.starts_with("HTTP/1.1 503"));
+        assert!(response.contains(r#""loading":true"#));
+
+        health.set_loading(false);
+        assert!(get(addr, "/healthz").await.starts_with("HTTP/1.1 200"));
+
+        assert!(get(addr, "/other").await.starts_with("HTTP/1.1 404"));
+    }
+}
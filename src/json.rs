@@ -0,0 +1,401 @@
+//! JSON文档模块 - JSON.*命令使用的JSONPath子集
+//!
+//! 支持的路径语法:
+//! - `$` 根节点
+//! - `.name` 或 `['name']` 对象成员
+//! - `[n]` 数组下标，负数从末尾开始计数
+//! - `.*` 或 `[*]` 所有子节点
+//!
+//! 以`$`开头的是JSONPath，返回所有匹配；不以`$`开头的是RedisJSON的旧式路径
+//! (`.`、`.a.b`、`a.b`)，只取第一个匹配。
+//!
+//! Rust特点展示:
+//! - 枚举表示路径的每一段
+//! - 递归函数遍历可变引用，原地修改文档
+//! - Peekable迭代器逐字符解析
+
+use crate::error::{RedisError, RedisResult};
+use serde_json::{Number, Value as JsonValue};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// 路径中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// 对象成员
+    Key(String),
+    /// 数组下标
+    Index(i64),
+    /// 所有子节点
+    Wildcard,
+}
+
+/// 解析后的路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    /// 原始路径文本，用于错误信息和多路径JSON.GET的结果
+    raw: String,
+    segments: Vec<Segment>,
+    legacy: bool,
+}
+
+impl JsonPath {
+    /// 根路径`$`
+    pub fn root() -> Self {
+        Self {
+            raw: "$".to_string(),
+            segments: Vec::new(),
+            legacy: false,
+        }
+    }
+
+    /// 解析路径文本
+    pub fn parse(path: &str) -> RedisResult<Self> {
+        let (rest, legacy) = match path.strip_prefix('$') {
+            Some(rest) => (rest.to_string(), false),
+            // 旧式路径的开头可以省略点号
+            None if path.starts_with('.') || path.starts_with('[') => (path.to_string(), true),
+            None => (format!(".{}", path), true),
+        };
+        let invalid = || RedisError::InvalidArgument(format!("invalid JSON path '{}'", path));
+
+        let mut segments = Vec::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // 旧式路径"."表示根
+                '.' if legacy && segments.is_empty() && chars.peek().is_none() => {}
+                '.' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    segments.push(Segment::Wildcard);
+                }
+                '.' => {
+                    let name = take_name(&mut chars);
+                    if name.is_empty() {
+                        return Err(invalid());
+                    }
+                    segments.push(Segment::Key(name));
+                }
+                '[' => segments.push(parse_bracket(&mut chars).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Self {
+            raw: path.to_string(),
+            segments,
+            legacy,
+        })
+    }
+
+    /// 原始路径文本
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// 是否指向根节点
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// 是否是旧式路径(只取第一个匹配)
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    /// 所有匹配的节点
+    pub fn select<'a>(&self, root: &'a JsonValue) -> Vec<&'a JsonValue> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| children(value, segment))
+                .collect();
+        }
+        current
+    }
+
+    /// 把匹配的位置设为`value`，返回修改的位置数
+    ///
+    /// 最后一段是对象成员且父节点存在时可以新建成员；数组下标越界时不做修改。
+    /// `nx`只新建不覆盖，`xx`只覆盖不新建
+    pub fn set(&self, root: &mut JsonValue, value: &JsonValue, nx: bool, xx: bool) -> usize {
+        let Some((last, parents)) = self.segments.split_last() else {
+            if nx {
+                return 0;
+            }
+            *root = value.clone();
+            return 1;
+        };
+
+        let mut changed = 0;
+        visit_mut(root, parents, &mut |parent| match (last, parent) {
+            (Segment::Key(key), JsonValue::Object(map)) => match map.get_mut(key) {
+                Some(existing) if !nx => {
+                    *existing = value.clone();
+                    changed += 1;
+                }
+                None if !xx => {
+                    map.insert(key.clone(), value.clone());
+                    changed += 1;
+                }
+                _ => {}
+            },
+            (Segment::Index(index), JsonValue::Array(items)) if !nx => {
+                if let Some(existing) = normalize(*index, items.len()).and_then(|i| items.get_mut(i)) {
+                    *existing = value.clone();
+                    changed += 1;
+                }
+            }
+            (Segment::Wildcard, parent) if !nx => {
+                for child in children_mut(parent) {
+                    *child = value.clone();
+                    changed += 1;
+                }
+            }
+            _ => {}
+        });
+        changed
+    }
+
+    /// 对每个匹配的节点调用`f`(原地修改)，按匹配顺序返回结果
+    pub fn update<T>(&self, root: &mut JsonValue, mut f: impl FnMut(&mut JsonValue) -> T) -> Vec<T> {
+        let mut results = Vec::new();
+        visit_mut(root, &self.segments, &mut |value| results.push(f(value)));
+        results
+    }
+
+    /// 删除匹配的位置，返回删除的数量；根路径由调用者删除整个键
+    pub fn delete(&self, root: &mut JsonValue) -> usize {
+        let Some((last, parents)) = self.segments.split_last() else {
+            return 0;
+        };
+
+        let mut deleted = 0;
+        visit_mut(root, parents, &mut |parent| match (last, parent) {
+            (Segment::Key(key), JsonValue::Object(map)) => {
+                deleted += map.remove(key).is_some() as usize;
+            }
+            (Segment::Index(index), JsonValue::Array(items)) => {
+                if let Some(i) = normalize(*index, items.len()) {
+                    items.remove(i);
+                    deleted += 1;
+                }
+            }
+            (Segment::Wildcard, JsonValue::Object(map)) => {
+                deleted += map.len();
+                map.clear();
+            }
+            (Segment::Wildcard, JsonValue::Array(items)) => {
+                deleted += items.len();
+                items.clear();
+            }
+            _ => {}
+        });
+        deleted
+    }
+}
+
+/// JSON.TYPE返回的类型名
+pub fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// JSON.NUMINCRBY: 两个整数相加的结果仍是整数(溢出时转为浮点数)，否则是浮点数
+///
+/// 结果不是有限的数时返回None
+pub fn add_numbers(current: &Number, by: &Number) -> Option<Number> {
+    if let Some(sum) = current.as_i64().zip(by.as_i64()).and_then(|(a, b)| a.checked_add(b)) {
+        return Some(sum.into());
+    }
+    Number::from_f64(current.as_f64()? + by.as_f64()?)
+}
+
+/// 文档中最大的数组/对象的元素数，用于max-collection-elements检查
+pub fn max_elements(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Array(items) => items.iter().map(max_elements).fold(items.len(), usize::max),
+        JsonValue::Object(map) => map.values().map(max_elements).fold(map.len(), usize::max),
+        _ => 0,
+    }
+}
+
+/// 读取成员名，直到下一个`.`或`[`
+fn take_name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '.' || c == '[' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    name
+}
+
+/// 解析`[...]`，开头的`[`已被读取
+fn parse_bracket(chars: &mut Peekable<Chars>) -> Option<Segment> {
+    let mut inner = String::new();
+    let quote = chars.peek().copied().filter(|c| *c == '\'' || *c == '"');
+    if let Some(quote) = quote {
+        chars.next();
+        loop {
+            match chars.next()? {
+                c if c == quote => break,
+                c => inner.push(c),
+            }
+        }
+        return (chars.next()? == ']').then_some(Segment::Key(inner));
+    }
+
+    loop {
+        match chars.next()? {
+            ']' => break,
+            c => inner.push(c),
+        }
+    }
+    match inner.trim() {
+        "*" => Some(Segment::Wildcard),
+        index => index.parse().ok().map(Segment::Index),
+    }
+}
+
+/// 负数下标从末尾计数，越界时返回None
+fn normalize(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// 一段路径匹配的子节点
+fn children<'a>(value: &'a JsonValue, segment: &Segment) -> Vec<&'a JsonValue> {
+    match (segment, value) {
+        (Segment::Key(key), JsonValue::Object(map)) => map.get(key).into_iter().collect(),
+        (Segment::Index(index), JsonValue::Array(items)) => normalize(*index, items.len())
+            .map(|i| &items[i])
+            .into_iter()
+            .collect(),
+        (Segment::Wildcard, JsonValue::Object(map)) => map.values().collect(),
+        (Segment::Wildcard, JsonValue::Array(items)) => items.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 所有子节点的可变引用
+fn children_mut(value: &mut JsonValue) -> Vec<&mut JsonValue> {
+    match value {
+        JsonValue::Object(map) => map.values_mut().collect(),
+        JsonValue::Array(items) => items.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 对路径匹配的每个节点调用`f`
+///
+/// Rust特点: 可变引用不能同时存在多个，所以用递归逐个访问而不是先收集
+fn visit_mut(value: &mut JsonValue, segments: &[Segment], f: &mut impl FnMut(&mut JsonValue)) {
+    let Some((segment, rest)) = segments.split_first() else {
+        f(value);
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(key), JsonValue::Object(map)) => {
+            if let Some(child) = map.get_mut(key) {
+                visit_mut(child, rest, f);
+            }
+        }
+        (Segment::Index(index), JsonValue::Array(items)) => {
+            if let Some(i) = normalize(*index, items.len()) {
+                visit_mut(&mut items[i], rest, f);
+            }
+        }
+        (Segment::Wildcard, value) => {
+            for child in children_mut(value) {
+                visit_mut(child, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(p: &str) -> JsonPath {
+        JsonPath::parse(p).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_select() {
+        let doc = json!({"a": {"b": [1, 2, 3]}, "c": "x", "d e": true});
+
+        assert!(path("$").is_root());
+        assert!(path(".").is_root() && path(".").is_legacy());
+        assert_eq!(path("$.a.b[0]").select(&doc), vec![&json!(1)]);
+        assert_eq!(path("$.a.b[-1]").select(&doc), vec![&json!(3)]);
+        assert_eq!(path("$.a.b[*]").select(&doc).len(), 3);
+        assert_eq!(path("$['d e']").select(&doc), vec![&json!(true)]);
+        assert_eq!(path("a.b[1]").select(&doc), vec![&json!(2)]);
+        assert!(path("$.missing.x").select(&doc).is_empty());
+
+        for bad in ["$..a", "$.a[", "$[x]", "$a"] {
+            assert!(JsonPath::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_set_and_delete() {
+        let mut doc = json!({"a": {"b": 1}, "list": [1, 2]});
+
+        assert_eq!(path("$.a.c").set(&mut doc, &json!("new"), false, false), 1);
+        assert_eq!(path("$.a.b").set(&mut doc, &json!(2), true, false), 0);
+        assert_eq!(path("$.a.z").set(&mut doc, &json!(2), false, true), 0);
+        assert_eq!(path("$.list[5]").set(&mut doc, &json!(0), false, false), 0);
+        assert_eq!(path("$.list[*]").set(&mut doc, &json!(0), false, false), 2);
+        // 父节点不存在时不会自动创建
+        assert_eq!(path("$.x.y").set(&mut doc, &json!(1), false, false), 0);
+        assert_eq!(doc, json!({"a": {"b": 1, "c": "new"}, "list": [0, 0]}));
+
+        assert_eq!(path("$.list[0]").delete(&mut doc), 1);
+        assert_eq!(path("$.a.*").delete(&mut doc), 2);
+        assert_eq!(path("$.missing").delete(&mut doc), 0);
+        assert_eq!(doc, json!({"a": {}, "list": [0]}));
+
+        assert_eq!(max_elements(&json!({"a": [1, 2, 3], "b": 1})), 3);
+        assert_eq!(type_name(&json!(1)), "integer");
+        assert_eq!(type_name(&json!(1.5)), "number");
+    }
+
+    #[test]
+    fn test_update_and_add_numbers() {
+        let mut doc = json!({"a": 1, "b": "x", "list": [1, [2]]});
+
+        let kinds = path("$.*").update(&mut doc, |v| type_name(v));
+        assert_eq!(kinds, vec!["integer", "string", "array"]);
+        let lengths = path("$.list[*]").update(&mut doc, |v| match v {
+            JsonValue::Array(items) => {
+                items.push(json!(3));
+                Some(items.len())
+            }
+            _ => None,
+        });
+        assert_eq!(lengths, vec![None, Some(2)]);
+        assert_eq!(doc["list"], json!([1, [2, 3]]));
+        assert!(path("$.missing").update(&mut doc, |_| ()).is_empty());
+
+        let n = |v: JsonValue| v.as_number().unwrap().clone();
+        assert_eq!(add_numbers(&n(json!(1)), &n(json!(2))), Some(n(json!(3))));
+        assert_eq!(add_numbers(&n(json!(1)), &n(json!(0.5))), Some(n(json!(1.5))));
+        assert_eq!(add_numbers(&n(json!(i64::MAX)), &n(json!(1))).unwrap().as_i64(), None);
+        assert_eq!(add_numbers(&n(json!(f64::MAX)), &n(json!(f64::MAX))), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! 键空间模块 - Store使用的键到值的映射
+//!
+//! 在HashMap之外按键的哈希值维护一个有序索引(`BTreeMap<哈希, 键>`):
+//! - SCAN的游标就是哈希值，从游标处继续遍历只访问本批返回的键，不需要每次扫描全部键
+//! - 从随机的哈希值开始取连续的几个键，就得到一组随机的淘汰候选键
+//! - 遍历期间有键被插入或删除时，其余键的哈希不变，一直存在的键保证至少返回一次
+//!
+//! Rust特点展示:
+//! - 泛型结构体`Keyspace<V>`，不依赖存储值的具体类型
+//! - `BTreeMap::range`从任意位置开始有序遍历
+//! - 哈希冲突的键放在同一个桶里，一起返回，游标不会停在它们中间
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::ops::Index;
+
+/// 按哈希有序索引的键空间
+///
+/// 哈希的种子在创建时随机选择，同一个键空间内保持不变，游标在键空间的生命周期内有效
+#[derive(Debug, Clone)]
+pub struct Keyspace<V> {
+    entries: HashMap<String, V>,
+    /// 哈希值 -> 哈希为该值的键(几乎总是只有一个)
+    index: BTreeMap<u64, Vec<String>>,
+    hasher: RandomState,
+}
+
+impl<V> Keyspace<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            index: BTreeMap::new(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 与HashMap一样接受`&str`、`&String`等可以借用为键的类型
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        String: Borrow<Q>,
+    {
+        self.entries.get(key)
+    }
+
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        String: Borrow<Q>,
+    {
+        self.entries.get_mut(key)
+    }
+
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        String: Borrow<Q>,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// 插入或替换，返回旧值
+    pub fn insert(&mut self, key: String, value: V) -> Option<V> {
+        if let Some(slot) = self.entries.get_mut(&key) {
+            return Some(std::mem::replace(slot, value));
+        }
+        self.index.entry(self.hash(key.as_str())).or_default().push(key.clone());
+        self.entries.insert(key, value);
+        None
+    }
+
+    /// 获取值的可变引用，不存在时先插入`f`的结果
+    pub fn get_or_insert_with(&mut self, key: &str, f: impl FnOnce() -> V) -> &mut V {
+        if !self.entries.contains_key(key) {
+            self.insert(key.to_string(), f());
+        }
+        self.entries.get_mut(key).expect("key was just inserted")
+    }
+
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        String: Borrow<Q>,
+    {
+        let value = self.entries.remove(key)?;
+        self.unindex(key);
+        Some(value)
+    }
+
+    /// 只保留`f`返回true的键
+    pub fn retain(&mut self, mut f: impl FnMut(&String, &mut V) -> bool) {
+        let mut removed = Vec::new();
+        self.entries.retain(|key, value| {
+            let keep = f(key, value);
+            if !keep {
+                removed.push(key.clone());
+            }
+            keep
+        });
+        for key in removed {
+            self.unindex(&key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// 遍历所有键值对，顺序不确定
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &V)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    /// 从游标开始按哈希顺序取至少`count`个键(同一哈希的键一起返回)
+    ///
+    /// 返回下一次调用的游标和本批的键值对，遍历结束时游标为0。
+    /// 只访问本批的键，开销与`count`成正比，与键空间的大小无关(BTreeMap查找为O(log n))
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&String, &V)>) {
+        let mut batch = Vec::new();
+        let mut buckets = self.index.range(cursor..);
+        for (_, keys) in buckets.by_ref() {
+            batch.extend(keys.iter().filter_map(|key| self.entries.get_key_value(key)));
+            if batch.len() >= count {
+                break;
+            }
+        }
+        let next = buckets.next().map_or(0, |(&hash, _)| hash);
+        (next, batch)
+    }
+
+    /// 从哈希值`start`开始按哈希顺序取`count`个值满足`keep`的键，到末尾后从头继续
+    ///
+    /// `start`随机时结果就是一组随机的键，淘汰时用来选出候选键。
+    /// 找够`count`个就停止；满足条件的键很少时才会遍历较多的键
+    pub fn sample(
+        &self,
+        start: u64,
+        count: usize,
+        mut keep: impl FnMut(&V) -> bool,
+    ) -> Vec<(&String, &V)> {
+        self.index
+            .range(start..)
+            .chain(self.index.range(..start))
+            .flat_map(|(_, keys)| keys)
+            .filter_map(|key| self.entries.get_key_value(key))
+            .filter(|(_, value)| keep(value))
+            .take(count)
+            .collect()
+    }
+
+    /// `Borrow`保证借用形式与String的哈希相同，所以`&str`和`&String`得到同一个值
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    /// 从哈希索引中删除键
+    fn unindex<Q: Hash + Eq + ?Sized>(&mut self, key: &Q)
+    where
+        String: Borrow<Q>,
+    {
+        let hash = self.hash(key);
+        if let Some(keys) = self.index.get_mut(&hash) {
+            keys.retain(|k| k.borrow() != key);
+            if keys.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+    }
+}
+
+impl<V> Default for Keyspace<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Index<&str> for Keyspace<V> {
+    type Output = V;
+
+    fn index(&self, key: &str) -> &V {
+        &self.entries[key]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// 从0开始反复SCAN直到游标回到0，返回每批的大小和所有键
+    fn scan_all(keyspace: &Keyspace<usize>, count: usize) -> (Vec<usize>, Vec<String>) {
+        let (mut cursor, mut sizes, mut keys) = (0, Vec::new(), Vec::new());
+        loop {
+            let (next, batch) = keyspace.scan(cursor, count);
+            sizes.push(batch.len());
+            keys.extend(batch.into_iter().map(|(key, _)| key.clone()));
+            if next == 0 {
+                return (sizes, keys);
+            }
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn test_scan_batches() {
+        let mut keyspace = Keyspace::new();
+        for i in 0..1000 {
+            keyspace.insert(format!("key:{}", i), i);
+        }
+        let (sizes, keys) = scan_all(&keyspace, 10);
+        // 每批恰好count个键，不重复不遗漏
+        assert_eq!(sizes.len(), 100);
+        assert!(sizes.iter().all(|&size| size == 10));
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 1000);
+
+        assert_eq!(Keyspace::<usize>::new().scan(0, 10), (0, Vec::new()));
+    }
+
+    #[test]
+    fn test_scan_with_concurrent_changes() {
+        let mut keyspace = Keyspace::new();
+        for i in 0..500 {
+            keyspace.insert(format!("stable:{}", i), i);
+        }
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut round: usize = 0;
+        loop {
+            let (next, batch) = keyspace.scan(cursor, 7);
+            seen.extend(batch.into_iter().map(|(key, _)| key.clone()));
+            // 遍历期间插入和删除其他键
+            keyspace.insert(format!("new:{}", round), round);
+            keyspace.remove(&format!("new:{}", round.saturating_sub(3)));
+            round += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert!((0..500).all(|i| seen.contains(&format!("stable:{}", i))));
+    }
+
+    #[test]
+    fn test_map_operations() {
+        let mut keyspace = Keyspace::new();
+        assert_eq!(keyspace.insert("a".to_string(), 1), None);
+        assert_eq!(keyspace.insert("a".to_string(), 2), Some(1));
+        *keyspace.get_or_insert_with("b", || 10) += 1;
+        *keyspace.get_or_insert_with("b", || 100) += 1;
+        assert_eq!(keyspace["b"], 12);
+        assert_eq!(keyspace.len(), 2);
+
+        for i in 0..100 {
+            keyspace.insert(i.to_string(), i);
+        }
+        keyspace.retain(|_, value| *value % 2 == 0);
+        assert_eq!(keyspace.remove("a"), Some(2));
+        assert_eq!(keyspace.remove("a"), None);
+        // 索引与映射保持一致
+        let (_, keys) = scan_all(&keyspace, 1000);
+        assert_eq!(keys.len(), keyspace.len());
+        assert_eq!(keyspace.index.values().map(Vec::len).sum::<usize>(), keyspace.len());
+
+        let sample = keyspace.sample(u64::MAX, 5, |_| true);
+        assert_eq!(sample.len(), 5);
+        assert_eq!(keyspace.sample(0, 1000, |_| true).len(), keyspace.len());
+        let sample = keyspace.sample(0, 1000, |value| value % 4 == 0);
+        assert_eq!(sample.len(), 26);
+        assert!(sample.iter().all(|(_, value)| *value % 4 == 0));
+
+        keyspace.clear();
+        assert!(keyspace.is_empty());
+        assert!(keyspace.index.is_empty());
+    }
+}
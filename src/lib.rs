@@ -46,19 +46,106 @@
 //! ## 模块结构
 //!
 //! - `error` - 错误处理
+//! - `clock` - 时钟抽象
+//! - `config` - 服务器配置
+//! - `cli` - 服务器命令行参数
+//! - `logging` - 日志输出(级别、格式、日志文件)
+//! - `daemon` - 守护进程与pid文件(Unix)
 //! - `resp` - RESP协议解析
 //! - `store` - 数据存储
+//! - `keyspace` - 按哈希有序索引的键空间(SCAN游标、淘汰抽样)
+//! - `event` - 存储变更事件
+//! - `blocking` - 阻塞命令的等待队列(BLPOP等)
+//! - `stats` - 运行时统计(INFO stats)
+//! - `memory` - used_memory统计与maxmemory淘汰策略
+//! - `json` - JSON文档的JSONPath子集
+//! - `bitops` - 位操作(BITOP、BITFIELD)
+//! - `encoding` - 小集合的紧凑编码(OBJECT ENCODING)
+//! - `hash` - 哈希类型(字段过期时间)
+//! - `quicklist` - 列表类型(分段存储)
+//! - `set` - 集合类型
+//! - `zset` - 有序集合类型
+//! - `stream` - 流类型
+//! - `timeseries` - 时间序列类型
+//! - `probabilistic` - 概率数据结构(布隆过滤器、布谷鸟过滤器)
+//! - `cache` - 读穿透/写穿透的缓存加载器
+//! - `ratelimit` - GCRA限流(CL.THROTTLE)
 //! - `command` - 命令处理
+//! - `metadata` - 命令元数据表
+//! - `module` - 运行时加载的模块(命令和值类型)
 //! - `connection` - 连接处理
+//! - `clients` - 客户端内存统计(maxmemory-clients)
+//! - `audit` - 命令审计日志
+//! - `monitor` - MONITOR输出解析
+//! - `rdb` - RDB快照解析(离线检查与分析)
+//! - `aof` - AOF文件检查
+//! - `server` - 服务器(接受连接)
+//! - `health` - 健康检查(PING探测与/healthz)
+//! - `systemd` - sd_notify与socket激活(Unix)
+//! - `upgrade` - 平滑升级时交接监听socket(Unix)
+//! - `testing` - 集成测试用的TestServer
+//! - `client` - 异步客户端库
+//! - `lock` - 分布式锁(自动续期的Mutex)
+//! - `cluster` - 集群客户端(槽位路由)
+//! - `tls` - 客户端TLS
+//! - `uri` - redis://连接URI解析
+//! - `codec` - 类型化值的编解码器
+//! - `telemetry` - OTLP span导出(otel特性)
 
+pub mod aof;
+pub mod audit;
+pub mod bitops;
+pub mod blocking;
+pub mod cache;
+pub mod cli;
+pub mod client;
+pub mod clients;
+pub mod clock;
+pub mod cluster;
+pub mod codec;
 pub mod command;
+pub mod config;
 pub mod connection;
+#[cfg(unix)]
+pub mod daemon;
+pub mod encoding;
 pub mod error;
+pub mod event;
+pub mod hash;
+pub mod health;
+pub mod json;
+pub mod keyspace;
+pub mod lock;
+pub mod logging;
+pub mod memory;
+pub mod metadata;
+pub mod module;
+pub mod monitor;
+pub mod probabilistic;
+pub mod quicklist;
+pub mod ratelimit;
+pub mod rdb;
 pub mod resp;
+pub mod server;
+pub mod set;
+pub mod stats;
 pub mod store;
+#[cfg(unix)]
+pub mod systemd;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod testing;
+pub mod timeseries;
+pub mod tls;
+#[cfg(unix)]
+pub mod upgrade;
+pub mod uri;
+pub mod zset;
+pub mod stream;
 
 // 重新导出常用类型
 pub use error::{RedisError, RedisResult};
+pub use event::{KeyWatcher, StoreEvent};
 pub use resp::RespValue;
 pub use store::Store;
 
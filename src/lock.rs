@@ -0,0 +1,231 @@
+//! 分布式锁模块 - 基于LOCK/UNLOCK命令的客户端互斥锁
+//!
+//! 每次获取锁都生成唯一的token作为锁的值:
+//! - 获取: `LOCK key token ttl`，相当于`SET key token NX PX ttl`，持有者再次执行时续期
+//! - 释放: `UNLOCK key token`，只有值仍是自己的token时才删除，不会误删别人的锁
+//! - 续期: 持有锁期间后台任务每隔ttl/3续期一次，临界区超过ttl也不会丢锁
+//!
+//! 这是单实例版本的Redlock模式，持有者崩溃时锁在ttl后自动释放。
+//!
+//! Rust特点展示:
+//! - RAII: `MutexGuard`被丢弃时停止续期任务
+//! - `Arc<tokio::sync::Mutex<Client>>`让续期任务和持有者共享同一个连接
+//! - `AtomicBool`在任务之间传递"锁已丢失"
+
+use crate::client::Client;
+use crate::error::RedisResult;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::task::JoinHandle;
+
+/// 获取锁失败后的默认重试间隔
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// 生成进程内唯一、跨进程几乎不会重复的token
+fn new_token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!(
+        "{:x}-{:x}-{:x}",
+        nanos,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// 分布式互斥锁
+///
+/// Rust特点: 泛型参数S与`Client`一致，TCP、TLS和Unix套接字连接都可以使用
+pub struct Mutex<S = TcpStream> {
+    client: Arc<AsyncMutex<Client<S>>>,
+    key: String,
+    ttl: Duration,
+    retry_delay: Duration,
+}
+
+impl<S> Mutex<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// 创建锁；锁使用`client`的连接，自动续期时也通过它发送命令
+    pub fn new(client: Client<S>, key: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            client: Arc::new(AsyncMutex::new(client)),
+            key: key.into(),
+            ttl,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+
+    /// 设置`lock`获取失败后的重试间隔
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// 尝试获取一次，被占用时返回None
+    pub async fn try_lock(&self) -> RedisResult<Option<MutexGuard<S>>> {
+        let token = new_token();
+        if !self.client.lock().await.lock(&self.key, &token, self.ttl).await? {
+            return Ok(None);
+        }
+        Ok(Some(MutexGuard::new(self.client.clone(), self.key.clone(), token, self.ttl)))
+    }
+
+    /// 获取锁，被占用时等待重试，直到获取成功或超过`timeout`(返回None)
+    pub async fn lock(&self, timeout: Option<Duration>) -> RedisResult<Option<MutexGuard<S>>> {
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        loop {
+            if let Some(guard) = self.try_lock().await? {
+                return Ok(Some(guard));
+            }
+            let now = tokio::time::Instant::now();
+            if deadline.is_some_and(|deadline| now + self.retry_delay > deadline) {
+                return Ok(None);
+            }
+            tokio::time::sleep(self.retry_delay).await;
+        }
+    }
+}
+
+/// 持有中的锁
+///
+/// 丢弃时只停止续期，锁在ttl后过期；需要立即释放时调用`unlock`
+pub struct MutexGuard<S = TcpStream> {
+    client: Arc<AsyncMutex<Client<S>>>,
+    key: String,
+    token: String,
+    /// 续期失败(锁已过期并被他人获取，或连接断开)时置为true
+    lost: Arc<AtomicBool>,
+    renewal: JoinHandle<()>,
+}
+
+impl<S> MutexGuard<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    fn new(client: Arc<AsyncMutex<Client<S>>>, key: String, token: String, ttl: Duration) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+        let renewal = tokio::spawn(renew(
+            client.clone(),
+            key.clone(),
+            token.clone(),
+            ttl,
+            lost.clone(),
+        ));
+        Self {
+            client,
+            key,
+            token,
+            lost,
+            renewal,
+        }
+    }
+
+    /// 锁的token，可以作为写入受保护资源时的凭证
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// 是否仍持有锁；续期失败后返回false，临界区应停止写入
+    pub fn is_held(&self) -> bool {
+        !self.lost.load(Ordering::Relaxed)
+    }
+
+    /// 释放锁，返回锁是否仍由自己持有(false表示已过期或被他人获取)
+    pub async fn unlock(self) -> RedisResult<bool> {
+        self.renewal.abort();
+        let released = self.client.lock().await.unlock(&self.key, &self.token).await?;
+        Ok(released)
+    }
+}
+
+impl<S> Drop for MutexGuard<S> {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+/// 续期任务: 每隔ttl/3续期一次，续期失败后标记锁已丢失并退出
+async fn renew<S>(
+    client: Arc<AsyncMutex<Client<S>>>,
+    key: String,
+    token: String,
+    ttl: Duration,
+    lost: Arc<AtomicBool>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let interval = (ttl / 3).max(Duration::from_millis(1));
+    loop {
+        tokio::time::sleep(interval).await;
+        let renewed = client.lock().await.lock(&key, &token, ttl).await;
+        if !matches!(renewed, Ok(true)) {
+            lost.store(true, Ordering::Relaxed);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use crate::resp::RespValue;
+
+    #[tokio::test]
+    async fn test_lock_commands() {
+        let server = TestServer::start().await;
+        let mut client = server.client().await;
+        let ttl = Duration::from_secs(10);
+
+        assert!(client.lock("res", "a", ttl).await.unwrap());
+        assert!(!client.lock("res", "b", ttl).await.unwrap());
+        // 持有者再次LOCK是续期
+        assert!(client.lock("res", "a", ttl).await.unwrap());
+        assert!(!client.unlock("res", "b").await.unwrap());
+        assert!(client.unlock("res", "a").await.unwrap());
+        assert!(!client.exists("res").await.unwrap());
+
+        // 其他类型的键不会被当成空闲的锁覆盖
+        client.command(["HSET", "victim", "f", "v"]).await.unwrap();
+        assert!(client.lock("victim", "a", ttl).await.is_err());
+        assert!(client.unlock("victim", "a").await.is_err());
+        let kind = client.command(["TYPE", "victim"]).await.unwrap();
+        assert_eq!(kind, RespValue::SimpleString("hash".to_string()));
+        let value = client.command(["HGET", "victim", "f"]).await.unwrap();
+        assert_eq!(value, RespValue::BulkString(b"v".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_mutex_renewal() {
+        let server = TestServer::start().await;
+        let ttl = Duration::from_millis(150);
+        let mutex = Mutex::new(server.client().await, "job", ttl);
+        let other = Mutex::new(server.client().await, "job", ttl)
+            .with_retry_delay(Duration::from_millis(10));
+
+        let guard = mutex.try_lock().await.unwrap().unwrap();
+        // 临界区远超ttl，自动续期使锁一直有效
+        tokio::time::sleep(ttl * 3).await;
+        assert!(guard.is_held());
+        assert!(other.try_lock().await.unwrap().is_none());
+        assert!(other.lock(Some(Duration::from_millis(50))).await.unwrap().is_none());
+
+        assert!(guard.unlock().await.unwrap());
+        let guard = other.lock(Some(Duration::from_secs(1))).await.unwrap().unwrap();
+        assert_ne!(guard.token(), "");
+
+        // 锁被其他客户端覆盖后续期失败
+        server.store().set("job".to_string(), b"intruder".to_vec());
+        tokio::time::sleep(ttl).await;
+        assert!(!guard.is_held());
+        assert!(!guard.unlock().await.unwrap());
+    }
+}
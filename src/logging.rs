@@ -0,0 +1,225 @@
+//! 日志模块 - 按配置初始化tracing输出
+//!
+//! 日志文件以追加方式打开。配合logrotate等工具时，先移动旧文件，
+//! 再向服务器发送SIGUSR1，服务器会在原路径重新打开日志文件。
+//!
+//! Rust特点展示:
+//! - 为自定义类型实现第三方trait(MakeWriter、Write)
+//! - Arc<Mutex<T>> 让多个线程共享同一个文件句柄
+//! - 环境变量优先于配置，方便临时调试
+
+use crate::config::{Config, LogFormat, LogLevel};
+use crate::error::{RedisError, RedisResult};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+/// 可重新打开的日志文件
+///
+/// Rust特点: Clone只复制Arc，所有克隆写入同一个文件句柄
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    /// 日志文件路径
+    path: PathBuf,
+    /// 当前打开的文件
+    file: Arc<Mutex<File>>,
+}
+
+impl LogFile {
+    /// 以追加方式打开日志文件，不存在时创建
+    pub fn open(path: impl AsRef<Path>) -> RedisResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// 日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 在原路径重新打开日志文件
+    ///
+    /// 旧文件被移走后调用，之后的日志写入新文件
+    pub fn reopen(&self) -> RedisResult<()> {
+        let file = open_append(&self.path)?;
+        *self.file.lock().unwrap() = file;
+        Ok(())
+    }
+}
+
+/// 每条日志事件获取一个写入器
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogFileWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogFileWriter { log_file: self }
+    }
+}
+
+/// 写入当前日志文件的写入器
+pub struct LogFileWriter<'a> {
+    log_file: &'a LogFile,
+}
+
+impl Write for LogFileWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.log_file.file.lock().unwrap().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        // 整条日志在一次加锁内写完，避免不同线程的日志交错
+        self.log_file.file.lock().unwrap().write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.log_file.file.lock().unwrap().flush()
+    }
+}
+
+/// 以追加方式打开文件
+fn open_append(path: &Path) -> RedisResult<File> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+/// 运行时调整日志级别的句柄
+///
+/// Rust特点: reload::Handle可以替换已安装到全局订阅者中的过滤器
+#[derive(Debug, Clone)]
+pub struct LogLevelHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// 设置了RUST_LOG时以环境变量为准，忽略loglevel的变化
+    env_override: bool,
+}
+
+impl LogLevelHandle {
+    /// 切换到新的日志级别
+    pub fn set(&self, level: LogLevel) -> RedisResult<()> {
+        if self.env_override {
+            return Ok(());
+        }
+        self.handle
+            .reload(EnvFilter::new(level.as_filter()))
+            .map_err(|e| RedisError::Config(format!("调整日志级别失败: {}", e)))
+    }
+}
+
+/// 已安装的日志输出，需要在程序运行期间保持存活
+#[derive(Debug)]
+pub struct Logging {
+    /// 日志文件，输出到标准输出时为None
+    log_file: Option<LogFile>,
+    /// 日志级别句柄
+    level: LogLevelHandle,
+    /// OTLP导出管线
+    #[cfg(feature = "otel")]
+    _telemetry: Option<crate::telemetry::Telemetry>,
+}
+
+impl Logging {
+    /// 日志文件句柄，用于之后重新打开
+    pub fn log_file(&self) -> Option<&LogFile> {
+        self.log_file.as_ref()
+    }
+
+    /// 日志级别句柄，用于配置重新加载
+    pub fn level_handle(&self) -> LogLevelHandle {
+        self.level.clone()
+    }
+}
+
+/// 按配置安装全局日志订阅者
+///
+/// 设置了RUST_LOG环境变量时以它为准，否则使用配置中的loglevel。
+///
+/// Rust特点: json()会改变层的类型，boxed()把两种格式擦除成同一个类型
+pub fn init(config: &Config) -> RedisResult<Logging> {
+    let env_filter = EnvFilter::try_from_default_env().ok();
+    let env_override = env_filter.is_some();
+    let filter = env_filter.unwrap_or_else(|| EnvFilter::new(config.loglevel.as_filter()));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let log_file = config.logfile.as_ref().map(LogFile::open).transpose()?;
+    let writer = match &log_file {
+        Some(file) => BoxMakeWriter::new(file.clone()),
+        None => BoxMakeWriter::new(io::stdout),
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(log_file.is_none());
+    let fmt_layer = match config.log_format {
+        LogFormat::Human => fmt_layer.boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(fmt_layer);
+
+    #[cfg(feature = "otel")]
+    let (registry, telemetry) = {
+        let (telemetry, otel_layer) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (telemetry, layer) = crate::telemetry::Telemetry::init(endpoint)?;
+                (Some(telemetry), Some(layer))
+            }
+            None => (None, None),
+        };
+        (registry.with(otel_layer), telemetry)
+    };
+
+    #[cfg(not(feature = "otel"))]
+    if config.otlp_endpoint.is_some() {
+        return Err(RedisError::Config(
+            "--otlp-endpoint 需要启用otel特性编译".to_string(),
+        ));
+    }
+
+    registry
+        .try_init()
+        .map_err(|e| RedisError::Config(format!("初始化日志失败: {}", e)))?;
+
+    Ok(Logging {
+        log_file,
+        level: LogLevelHandle {
+            handle,
+            env_override,
+        },
+        #[cfg(feature = "otel")]
+        _telemetry: telemetry,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reopen_after_rotation() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("rust-redis-log-{}.log", std::process::id()));
+        let rotated = dir.join(format!("rust-redis-log-{}.log.1", std::process::id()));
+
+        let log_file = LogFile::open(&path).unwrap();
+        log_file.make_writer().write_all(b"before\n").unwrap();
+
+        // 模拟logrotate: 移走旧文件后重新打开
+        std::fs::rename(&path, &rotated).unwrap();
+        log_file.make_writer().write_all(b"moved\n").unwrap();
+        log_file.reopen().unwrap();
+        log_file.make_writer().write_all(b"after\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&rotated).unwrap(), "before\nmoved\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "after\n");
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(rotated);
+    }
+}
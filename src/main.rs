@@ -5,70 +5,273 @@
 //! - 并发任务处理
 //! - 错误处理和传播
 
-use redis_lib::connection::{cleanup_task, Connection};
+use clap::Parser;
+use redis_lib::memory::CountingAllocator;
+use redis_lib::audit::AuditLog;
+use redis_lib::cli::ServerArgs;
+use redis_lib::config::{Config, SharedConfig};
+use redis_lib::connection::cleanup_task;
+#[cfg(unix)]
+use redis_lib::daemon::{self, PidFile};
+#[cfg(unix)]
+use redis_lib::upgrade::{wait_for_upgrade, Handover, Inherited};
+use redis_lib::health::{self, Health};
+use redis_lib::logging::{self, LogFile};
+use redis_lib::server::Server;
+use redis_lib::stats;
 use redis_lib::store::Store;
-use redis_lib::{DEFAULT_PORT, VERSION};
-use std::env;
+use redis_lib::{RedisResult, VERSION};
+use std::process::ExitCode;
 use tokio::net::TcpListener;
 
+/// 统计分配的字节数，作为used_memory和maxmemory的依据
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
 /// 程序入口点
 ///
+/// daemonize需要在创建tokio运行时之前fork，所以这里手动构建运行时
+///
 /// Rust特点:
-/// - #[tokio::main] 宏设置异步运行时
 /// - Result返回类型允许使用?操作符
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// - 变量绑定控制生命周期: pid文件在main返回时删除
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // 解析命令行参数和配置文件
+    let args = ServerArgs::parse();
+    let config = args.to_config()?;
+
+    // 健康检查模式: PING本机服务器，失败时以非零状态退出
+    if args.healthcheck {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let addr = format!("127.0.0.1:{}", config.port);
+        return Ok(match runtime.block_on(health::check(&addr, health::CHECK_TIMEOUT)) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("健康检查失败: {}", e);
+                ExitCode::FAILURE
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    let _pid_file = daemonize(&config)?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(run(args, config))
+}
+
+/// 转入后台并写入pid文件
+///
+/// 与Redis一致: 只要配置了pidfile就写入；daemonize时默认写入/var/run/redis.pid
+#[cfg(unix)]
+fn daemonize(config: &Config) -> RedisResult<Option<PidFile>> {
+    if config.daemonize {
+        daemon::daemonize(config.logfile.as_deref())?;
+    }
+
+    let path = match (&config.pidfile, config.daemonize) {
+        (Some(path), _) => path.clone(),
+        (None, true) => daemon::DEFAULT_PID_FILE.into(),
+        (None, false) => return Ok(None),
+    };
+    Ok(Some(PidFile::create(path)?))
+}
+
+/// 服务器主流程
+///
+/// Rust特点: async fn 在运行时的block_on中执行
+async fn run(args: ServerArgs, config: Config) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // 初始化日志，RUST_LOG环境变量优先于--loglevel
+    // Rust特点: 绑定到变量使日志管线一直存活到main结束
+    let logging = logging::init(&config)?;
+    if let Some(log_file) = logging.log_file() {
+        spawn_log_reopen(log_file.clone());
+    }
+
     // 打印欢迎信息
     print_banner();
 
-    // 解析命令行参数获取端口
-    let port = parse_port();
-
     // 创建共享存储
     // Rust特点: Store实现了Clone，内部使用Arc实现共享
     let store = Store::new();
 
+    // 加载配置文件中的模块，任何一个失败都不启动
+    for (path, module_args) in &config.loadmodule {
+        let name = store.modules().load(path, module_args)?;
+        tracing::info!(module = %name, path = %path, "已加载模块");
+    }
+
     // 启动后台清理任务
     // Rust特点: tokio::spawn创建独立的异步任务
     let cleanup_store = store.clone();
     tokio::spawn(async move {
         cleanup_task(cleanup_store, 10).await;
     });
+    // 定期采样命令数，计算instantaneous_ops_per_sec
+    tokio::spawn(stats::sample_task(store.clone()));
+    // 缓存模式下定期报告命中率；重新加载配置后按新的cache-mode启动或停止
+    if config.cache_mode {
+        tracing::info!(
+            maxmemory = config.maxmemory,
+            policy = %config.maxmemory_policy,
+            "以缓存模式运行"
+        );
+    }
+    let cache_reporter = stats::CacheReporter::new(store.clone(), stats::REPORT_INTERVAL);
+    cache_reporter.update(config.cache_mode);
 
-    // 绑定TCP监听器
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).await?;
+    // 由平滑升级启动时，监听socket从旧进程继承，不重新bind
+    let mut inherited = Inherited::take();
+    let mut handover = Handover::default();
+
+    // 启动健康检查HTTP服务
+    if let Some(health_port) = config.health_port {
+        let listener = match inherited.tcp("health")? {
+            Some(listener) => TcpListener::from_std(listener)?,
+            None => TcpListener::bind(("0.0.0.0", health_port)).await?,
+        };
+        handover.add("health", &listener)?;
+        tokio::spawn(health::serve(listener, Health::new()));
+    }
+
+    // 绑定TCP监听器；由systemd socket激活或平滑升级启动时使用传入的socket
+    let activated = match inherited.tcp("tcp")? {
+        Some(listener) => Some(listener),
+        None => take_activated_listener()?,
+    };
+    let server = match activated {
+        Some(listener) => Server::from_listener(TcpListener::from_std(listener)?, store),
+        None => Server::bind((config.bind.as_str(), config.port), store).await?,
+    };
+    #[cfg(unix)]
+    let server = match (inherited.unix("unix")?, &config.unixsocket) {
+        (Some(listener), _) => server.with_unix_listener(listener)?,
+        (None, Some(path)) => server.with_unix_socket(path)?,
+        (None, None) => server,
+    };
+    #[cfg(unix)]
+    server.add_to_handover(&mut handover)?;
+    let server = match &config.audit_log {
+        Some(target) => {
+            let audit = AuditLog::open(target)?;
+            if let Some(log_file) = audit.log_file() {
+                spawn_log_reopen(log_file.clone());
+            }
+            server.with_audit_log(audit)
+        }
+        None => server,
+    };
+    let addr = server.local_addr()?;
+
+    // 共享配置: 重新加载时重新读取配置文件并合并命令行选项
+    let shared_config = SharedConfig::new(config);
+    shared_config.set_source(move || args.to_config());
+    let level = logging.level_handle();
+    shared_config.on_reload(move |config| {
+        if let Err(e) = level.set(config.loglevel) {
+            tracing::warn!(error = %e, "调整日志级别失败");
+        }
+    });
+    shared_config.on_reload(move |config| cache_reporter.update(config.cache_mode));
+    spawn_config_reload(shared_config.clone());
+    let server = server.with_config(shared_config);
 
     println!("🚀 服务器启动成功，监听 {}", addr);
     println!("📝 支持的命令: PING, GET, SET, DEL, EXISTS, KEYS, INCR, DECR, TTL, EXPIRE 等");
     println!("💡 使用 redis-cli 或 telnet 连接测试");
     println!();
 
-    // 接受连接循环
-    // Rust特点: loop是无限循环，比while true更惯用
-    loop {
-        // 等待新连接
-        // Rust特点: 模式匹配解构元组
-        let (socket, _addr) = listener.accept().await?;
-
-        // 为每个连接克隆store
-        let conn_store = store.clone();
-
-        // 为每个连接创建新任务
-        // Rust特点:
-        // - move 闭包获取变量所有权
-        // - async move 创建异步闭包
-        tokio::spawn(async move {
-            let mut connection = Connection::new(socket);
-
-            // 处理连接，忽略错误（已在handle中记录日志）
-            if let Err(e) = connection.handle(&conn_store).await {
-                eprintln!("[{}] 连接错误: {}", connection.addr(), e);
+    // 数据已就绪，通知systemd(Type=notify)
+    notify_ready(&format!("监听 {}", addr));
+
+    // 接受连接循环；收到SIGUSR2且新进程就绪后停止接受新连接，等待已有连接结束后退出
+    server.run_until(wait_for_upgrade(handover)).await?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 收到SIGHUP时重新加载配置文件
+#[cfg(unix)]
+fn spawn_config_reload(config: SharedConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut signals = match signal(SignalKind::hangup()) {
+            Ok(signals) => signals,
+            Err(e) => {
+                tracing::warn!(error = %e, "无法监听SIGHUP，只能通过CONFIG RELOAD重新加载配置");
+                return;
             }
-        });
+        };
+        while signals.recv().await.is_some() {
+            if let Err(e) = config.reload() {
+                tracing::warn!(error = %e, "重新加载配置失败，继续使用当前配置");
+            }
+        }
+    });
+}
+
+/// 非Unix平台没有SIGHUP，只能通过CONFIG RELOAD重新加载
+#[cfg(not(unix))]
+fn spawn_config_reload(_config: SharedConfig) {}
+
+/// 非Unix平台不支持平滑升级，没有继承的监听socket
+#[cfg(not(unix))]
+#[derive(Default)]
+struct Inherited;
+
+#[cfg(not(unix))]
+impl Inherited {
+    fn take() -> Self {
+        Self
+    }
+
+    fn tcp(&mut self, _name: &str) -> RedisResult<Option<std::net::TcpListener>> {
+        Ok(None)
+    }
+}
+
+#[cfg(not(unix))]
+#[derive(Default)]
+struct Handover;
+
+#[cfg(not(unix))]
+impl Handover {
+    fn add(&mut self, _name: &str, _listener: &TcpListener) -> RedisResult<()> {
+        Ok(())
     }
 }
 
+#[cfg(not(unix))]
+async fn wait_for_upgrade(_handover: Handover) {
+    std::future::pending().await
+}
+
+/// systemd socket激活传入的第一个监听socket
+#[cfg(unix)]
+fn take_activated_listener() -> RedisResult<Option<std::net::TcpListener>> {
+    Ok(redis_lib::systemd::listen_fds()?.into_iter().next())
+}
+
+#[cfg(not(unix))]
+fn take_activated_listener() -> RedisResult<Option<std::net::TcpListener>> {
+    Ok(None)
+}
+
+/// 通知systemd服务已就绪；不是由systemd启动时什么也不做
+#[cfg(unix)]
+fn notify_ready(status: &str) {
+    if let Err(e) = redis_lib::systemd::notify_ready(status) {
+        tracing::warn!(error = %e, "发送sd_notify就绪通知失败");
+    }
+}
+
+#[cfg(not(unix))]
+fn notify_ready(_status: &str) {}
+
 /// 打印欢迎横幅
 fn print_banner() {
     println!(
@@ -98,12 +301,29 @@ fn print_banner() {
     );
 }
 
-/// 从命令行参数解析端口号xxx
-fn parse_port() -> u16 {
-    // Rust特点: 迭代器和Option的链式调用
-    env::args()
-        .nth(1)
-        .and_then(|arg| arg.parse().ok())
-        .unwrap_or(DEFAULT_PORT)
+/// 收到SIGUSR1时重新打开日志文件，配合logrotate等工具轮转日志
+#[cfg(unix)]
+fn spawn_log_reopen(log_file: LogFile) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut signals = match signal(SignalKind::user_defined1()) {
+            Ok(signals) => signals,
+            Err(e) => {
+                tracing::warn!(error = %e, "无法监听SIGUSR1，日志文件不会被重新打开");
+                return;
+            }
+        };
+        while signals.recv().await.is_some() {
+            match log_file.reopen() {
+                Ok(()) => tracing::info!(path = %log_file.path().display(), "日志文件已重新打开"),
+                Err(e) => eprintln!("重新打开日志文件失败: {}", e),
+            }
+        }
+    });
 }
 
+/// 非Unix平台没有SIGUSR1，日志文件只在启动时打开
+#[cfg(not(unix))]
+fn spawn_log_reopen(_log_file: LogFile) {}
+
@@ -0,0 +1,242 @@
+//! 内存模块 - used_memory统计和maxmemory淘汰策略
+//!
+//! `CountingAllocator`包装系统分配器，记录当前分配的字节数，与Redis的zmalloc一样
+//! 作为used_memory和maxmemory的依据。服务器在main.rs中把它安装为全局分配器；
+//! 没有安装时(如嵌入到其他程序或在测试中)used_memory为0，maxmemory不会触发淘汰。
+//!
+//! `lazy_free`把FLUSHDB ASYNC的旧数据和被淘汰的值交给唯一的后台线程释放，
+//! 与Redis的lazyfree线程一样，不会每次释放都创建一个线程。
+//!
+//! Rust特点展示:
+//! - 实现`GlobalAlloc` trait替换全局分配器，unsafe只出现在转发给System的调用中
+//! - `Box<dyn Send>`擦除类型，任何可以跨线程移动的值都能通过同一个通道交给后台线程析构
+//! - 原子计数器在所有线程的分配路径上无锁更新
+//! - FromStr + Display让策略名在配置文件和INFO中往返
+
+use crate::error::{RedisError, RedisResult};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, OnceLock};
+
+/// 当前分配的字节数
+static USED: AtomicUsize = AtomicUsize::new(0);
+
+/// 已交给后台线程、还没有释放的对象数
+static LAZYFREE_PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// 统计分配字节数的全局分配器
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: redis_lib::memory::CountingAllocator = redis_lib::memory::CountingAllocator;
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountingAllocator;
+
+// Rust特点: unsafe impl 由我们保证分配和释放都原样转发给System，只额外更新计数
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            USED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            USED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        USED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            USED.fetch_add(new_size, Ordering::Relaxed);
+            USED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+/// 当前分配的字节数，对应INFO中的used_memory
+pub fn used_memory() -> usize {
+    USED.load(Ordering::Relaxed)
+}
+
+/// 在后台线程中释放`value`
+///
+/// 第一次调用时启动名为lazyfree的线程，之后所有调用共用它；线程无法启动或已经退出时
+/// 在当前线程直接释放
+pub fn lazy_free<T: Send + 'static>(value: T) {
+    static WORKER: OnceLock<Option<mpsc::Sender<Box<dyn Send>>>> = OnceLock::new();
+    let worker = WORKER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Box<dyn Send>>();
+        std::thread::Builder::new()
+            .name("lazyfree".to_string())
+            .spawn(move || {
+                for garbage in receiver {
+                    drop(garbage);
+                    LAZYFREE_PENDING.fetch_sub(1, Ordering::Relaxed);
+                }
+            })
+            .ok()
+            .map(|_| sender)
+    });
+
+    // 先计数再发送，后台线程的减法不会早于这里的加法
+    LAZYFREE_PENDING.fetch_add(1, Ordering::Relaxed);
+    let unsent = match worker {
+        Some(sender) => sender.send(Box::new(value)).err().map(|mpsc::SendError(garbage)| garbage),
+        None => Some(Box::new(value) as Box<dyn Send>),
+    };
+    if let Some(garbage) = unsent {
+        drop(garbage);
+        LAZYFREE_PENDING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 等待后台释放的对象数，对应INFO中的lazyfree_pending_objects
+pub fn lazyfree_pending_objects() -> usize {
+    LAZYFREE_PENDING.load(Ordering::Relaxed)
+}
+
+/// 本机可用的内存(字节): 物理内存和cgroup限制中较小的一个；无法确定时返回None
+pub fn available_memory() -> Option<u64> {
+    let physical = physical_memory();
+    // cgroup v2的memory.max，不限制时为"max"
+    let cgroup = std::fs::read_to_string("/sys/fs/cgroup/memory.max")
+        .ok()
+        .and_then(|limit| limit.trim().parse::<u64>().ok());
+    match (physical, cgroup) {
+        (Some(physical), Some(cgroup)) => Some(physical.min(cgroup)),
+        (physical, cgroup) => physical.or(cgroup),
+    }
+}
+
+#[cfg(unix)]
+fn physical_memory() -> Option<u64> {
+    // Rust特点: unsafe 由我们保证sysconf只读取系统参数，没有内存安全问题
+    let (pages, page_size) =
+        unsafe { (libc::sysconf(libc::_SC_PHYS_PAGES), libc::sysconf(libc::_SC_PAGESIZE)) };
+    (pages > 0 && page_size > 0).then(|| pages as u64 * page_size as u64)
+}
+
+#[cfg(not(unix))]
+fn physical_memory() -> Option<u64> {
+    None
+}
+
+/// 以K/M/G为单位显示字节数，与Redis的used_memory_human一致
+pub fn human(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+    UNITS
+        .iter()
+        .find(|(size, _)| bytes >= *size)
+        .map_or_else(
+            || format!("{}B", bytes),
+            |(size, unit)| format!("{:.2}{}", bytes as f64 / *size as f64, unit),
+        )
+}
+
+/// 超过maxmemory时的淘汰策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// 不淘汰，写命令返回OOM错误
+    #[default]
+    NoEviction,
+    /// 在所有键中淘汰最久未访问的
+    AllKeysLru,
+    /// 只在设置了过期时间的键中淘汰最久未访问的
+    VolatileLru,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        match s.to_lowercase().as_str() {
+            "noeviction" => Ok(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Ok(EvictionPolicy::AllKeysLru),
+            "volatile-lru" => Ok(EvictionPolicy::VolatileLru),
+            _ => Err(RedisError::Config(format!("无效的maxmemory-policy: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+        })
+    }
+}
+
+/// INFO中的`# Memory`部分
+pub fn info() -> String {
+    let used = used_memory() as u64;
+    format!(
+        "# Memory\r\n\
+         used_memory:{}\r\n\
+         used_memory_human:{}\r\n\
+         lazyfree_pending_objects:{}\r\n",
+        used,
+        human(used),
+        lazyfree_pending_objects()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_and_human() {
+        assert_eq!("ALLKEYS-LRU".parse::<EvictionPolicy>().unwrap(), EvictionPolicy::AllKeysLru);
+        assert_eq!(EvictionPolicy::VolatileLru.to_string(), "volatile-lru");
+        assert!("allkeys-lfu".parse::<EvictionPolicy>().is_err());
+
+        assert_eq!(human(512), "512B");
+        assert_eq!(human(1536), "1.50K");
+        assert_eq!(human(3 << 30), "3.00G");
+    }
+
+    #[test]
+    fn test_lazy_free() {
+        use std::sync::Arc;
+
+        // 值在lazyfree线程中析构，多次调用共用同一个线程
+        let (sender, receiver) = mpsc::channel();
+        struct Probe(mpsc::Sender<Option<String>>);
+        impl Drop for Probe {
+            fn drop(&mut self) {
+                let _ = self.0.send(std::thread::current().name().map(str::to_string));
+            }
+        }
+        let shared = Arc::new(());
+        for _ in 0..100 {
+            lazy_free((Probe(sender.clone()), Arc::clone(&shared)));
+        }
+        for _ in 0..100 {
+            let thread = receiver.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+            assert_eq!(thread.as_deref(), Some("lazyfree"));
+        }
+        // 所有值都释放之后只剩这里的引用
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while Arc::strong_count(&shared) > 1 {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::yield_now();
+        }
+    }
+}
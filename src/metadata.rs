@@ -0,0 +1,494 @@
+//! 命令元数据模块 - 所有支持命令的名称、参数个数、标志、键位置和说明
+//!
+//! 这是命令的唯一登记处: 解析器按这里的arity检查参数个数，COMMAND命令
+//! 输出这里的内容，COMMAND GETKEYS按键位置提取键，客户端的补全和参数提示
+//! 也从这里读取。新增命令时在表中添加一行。
+//!
+//! Rust特点展示:
+//! - &'static 切片构成的常量表，编译期确定、零运行时开销
+//! - const fn 在编译期组合标志位
+//! - 迭代器查找，大小写不敏感的比较
+
+/// 命令标志，对应Redis COMMAND输出中的flags
+///
+/// Rust特点: 新类型包装u8，用关联常量代替外部的bitflags依赖
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommandFlags(u8);
+
+impl CommandFlags {
+    /// 无标志
+    pub const NONE: Self = Self(0);
+    /// 会修改数据
+    pub const WRITE: Self = Self(1);
+    /// 只读取数据
+    pub const READONLY: Self = Self(1 << 1);
+    /// 管理命令，可能影响整个服务器
+    pub const ADMIN: Self = Self(1 << 2);
+    /// 可能阻塞连接
+    pub const BLOCKING: Self = Self(1 << 3);
+
+    /// 标志名，与Redis一致
+    const NAMES: [(Self, &'static str); 4] = [
+        (Self::WRITE, "write"),
+        (Self::READONLY, "readonly"),
+        (Self::ADMIN, "admin"),
+        (Self::BLOCKING, "blocking"),
+    ];
+
+    /// 合并两组标志
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// 是否包含`other`中的全部标志
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// 包含的标志名
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+    }
+}
+
+/// 键在参数中的位置，含义与Redis COMMAND输出的first/last/step相同
+///
+/// 位置从命令名算起(命令名为0)；last为负数表示从末尾倒数，first为0表示没有键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    /// 第一个键的位置
+    pub first: i32,
+    /// 最后一个键的位置
+    pub last: i32,
+    /// 相邻两个键的间隔
+    pub step: i32,
+}
+
+impl KeySpec {
+    /// 没有键
+    pub const NONE: Self = Self {
+        first: 0,
+        last: 0,
+        step: 0,
+    };
+
+    /// 参数个数为`argc`(含命令名)时，键所在的位置
+    pub fn positions(&self, argc: usize) -> impl Iterator<Item = usize> {
+        let last = if self.last < 0 {
+            argc as i32 + self.last
+        } else {
+            self.last.min(argc as i32 - 1)
+        };
+        let (first, step) = (self.first, self.step.max(1));
+        (first..=last)
+            .step_by(step as usize)
+            .filter(move |_| first > 0)
+            .map(|pos| pos as usize)
+    }
+}
+
+/// 单个命令的元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandInfo {
+    /// 命令名(大写)
+    pub name: &'static str,
+    /// 参数个数，包括命令名本身；负数表示至少这么多
+    pub arity: i32,
+    /// 命令标志
+    pub flags: CommandFlags,
+    /// 键的位置
+    pub keys: KeySpec,
+    /// 参数格式，与Redis文档一致
+    pub arguments: &'static str,
+    /// 简短说明
+    pub summary: &'static str,
+    /// 命令分组
+    pub group: &'static str,
+}
+
+impl CommandInfo {
+    /// 检查参数个数(含命令名)是否符合arity
+    pub fn accepts(&self, argc: usize) -> bool {
+        arity_accepts(self.arity, argc)
+    }
+}
+
+/// 容器命令(CONFIG、MEMORY等)的子命令元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubcommandInfo {
+    /// 所属的容器命令(大写)
+    pub container: &'static str,
+    /// 子命令名(大写)
+    pub name: &'static str,
+    /// 参数个数，含义与Redis的arity相同: 包括容器命令和子命令本身，负数表示至少
+    pub arity: i32,
+    /// 命令标志
+    pub flags: CommandFlags,
+    /// 键的位置(容器命令为0)
+    pub keys: KeySpec,
+    /// 子命令之后的参数格式
+    pub arguments: &'static str,
+    /// 简短说明，用于HELP回复
+    pub summary: &'static str,
+}
+
+impl SubcommandInfo {
+    /// 检查参数个数(含容器命令和子命令)是否符合arity
+    pub fn accepts(&self, argc: usize) -> bool {
+        arity_accepts(self.arity, argc)
+    }
+}
+
+/// arity为正数时参数个数必须相等，为负数时至少为其绝对值
+fn arity_accepts(arity: i32, argc: usize) -> bool {
+    let expected = arity.unsigned_abs() as usize;
+    if arity < 0 {
+        argc >= expected
+    } else {
+        argc == expected
+    }
+}
+
+/// 把标志名列表展开为CommandFlags常量
+macro_rules! flags {
+    ($($flag:ident)*) => {
+        CommandFlags::NONE$(.union(CommandFlags::$flag))*
+    };
+}
+
+/// 命令表
+///
+/// 每行依次是: 名称、arity、[标志]、第一个键、最后一个键、键间隔、参数格式、分组、说明
+///
+/// Rust特点: 宏把每行展开成一个CommandInfo，让表更紧凑
+macro_rules! commands {
+    ($($name:literal, $arity:literal, [$($flag:ident)*], $first:literal, $last:literal, $step:literal,
+       $args:literal, $group:literal, $summary:literal;)*) => {
+        &[$(CommandInfo {
+            name: $name,
+            arity: $arity,
+            flags: flags!($($flag)*),
+            keys: KeySpec { first: $first, last: $last, step: $step },
+            arguments: $args,
+            group: $group,
+            summary: $summary,
+        }),*]
+    };
+}
+
+/// 服务器支持的全部命令
+pub const COMMANDS: &[CommandInfo] = commands! {
+    "PING", -1, [], 0, 0, 0, "[message]", "connection", "测试连接";
+    "ECHO", 2, [], 0, 0, 0, "message", "connection", "回显消息";
+    "QUIT", -1, [], 0, 0, 0, "", "connection", "关闭连接";
+    "AUTH", -2, [], 0, 0, 0, "[username] password", "connection", "认证";
+    "SELECT", 2, [], 0, 0, 0, "index", "connection", "选择数据库";
+    "GET", 2, [READONLY], 1, 1, 1, "key", "string", "获取值";
+    "SET", -3, [WRITE], 1, 1, 1, "key value [NX|XX] [GET] [EX seconds|PX milliseconds|EXAT unix-time-seconds|PXAT unix-time-milliseconds|KEEPTTL]", "string", "设置值";
+    "SETNX", 3, [WRITE], 1, 1, 1, "key value", "string", "仅当键不存在时设置，返回是否设置";
+    "SETEX", 4, [WRITE], 1, 1, 1, "key seconds value", "string", "设置值和过期时间(秒)";
+    "PSETEX", 4, [WRITE], 1, 1, 1, "key milliseconds value", "string", "设置值和过期时间(毫秒)";
+    "GETSET", 3, [WRITE], 1, 1, 1, "key value", "string", "设置新值并返回旧值";
+    "APPEND", 3, [WRITE], 1, 1, 1, "key value", "string", "追加字符串";
+    "SETRANGE", 4, [WRITE], 1, 1, 1, "key offset value", "string", "从指定偏移处覆盖写入，超出长度时用0填充";
+    "GETRANGE", 4, [READONLY], 1, 1, 1, "key start end", "string", "获取字符串的子区间，负数下标从末尾计数";
+    "STRLEN", 2, [READONLY], 1, 1, 1, "key", "string", "获取字符串长度";
+    "INCR", 2, [WRITE], 1, 1, 1, "key", "string", "递增1";
+    "INCRBY", 3, [WRITE], 1, 1, 1, "key increment", "string", "递增指定值";
+    "DECR", 2, [WRITE], 1, 1, 1, "key", "string", "递减1";
+    "DECRBY", 3, [WRITE], 1, 1, 1, "key decrement", "string", "递减指定值";
+    "MGET", -2, [READONLY], 1, -1, 1, "key [key ...]", "string", "批量获取";
+    "MSET", -3, [WRITE], 1, -1, 2, "key value [key value ...]", "string", "批量设置";
+    "MSETNX", -3, [WRITE], 1, -1, 2, "key value [key value ...]", "string", "仅当所有键都不存在时批量设置";
+    "BITOP", -4, [WRITE], 2, -1, 1, "AND|OR|XOR|NOT destkey key [key ...]", "bitmap", "对多个字符串按位运算，结果写入目标键";
+    "BITFIELD", -2, [WRITE], 1, 1, 1, "key [GET type offset] [SET type offset value] [INCRBY type offset increment] [OVERFLOW WRAP|SAT|FAIL] ...", "bitmap", "把字符串中任意位置和宽度的位当作整数读写";
+    "DEL", -2, [WRITE], 1, -1, 1, "key [key ...]", "generic", "删除键";
+    "EXISTS", -2, [READONLY], 1, -1, 1, "key [key ...]", "generic", "检查键是否存在";
+    "EXPIRE", 3, [WRITE], 1, 1, 1, "key seconds", "generic", "设置过期时间(秒)";
+    "PEXPIRE", 3, [WRITE], 1, 1, 1, "key milliseconds", "generic", "设置过期时间(毫秒)";
+    "EXPIREAT", 3, [WRITE], 1, 1, 1, "key unix-time-seconds", "generic", "设置过期的Unix时间(秒)，已经过去时删除键";
+    "PEXPIREAT", 3, [WRITE], 1, 1, 1, "key unix-time-milliseconds", "generic", "设置过期的Unix时间(毫秒)，已经过去时删除键";
+    "EXPIRETIME", 2, [READONLY], 1, 1, 1, "key", "generic", "获取过期的Unix时间(秒)";
+    "PEXPIRETIME", 2, [READONLY], 1, 1, 1, "key", "generic", "获取过期的Unix时间(毫秒)";
+    "TTL", 2, [READONLY], 1, 1, 1, "key", "generic", "获取剩余生存时间(秒)";
+    "PTTL", 2, [READONLY], 1, 1, 1, "key", "generic", "获取剩余生存时间(毫秒)";
+    "PERSIST", 2, [WRITE], 1, 1, 1, "key", "generic", "移除过期时间";
+    "KEYS", 2, [READONLY], 0, 0, 0, "pattern", "generic", "查找键";
+    "TYPE", 2, [READONLY], 1, 1, 1, "key", "generic", "获取键类型";
+    "RENAME", 3, [WRITE], 1, 2, 1, "key newkey", "generic", "重命名键";
+    "RENAMENX", 3, [WRITE], 1, 2, 1, "key newkey", "generic", "仅当新键不存在时重命名";
+    "SCAN", -2, [READONLY], 0, 0, 0, "cursor [MATCH pattern] [COUNT count] [TYPE type]", "generic", "增量遍历键";
+    "HSET", -4, [WRITE], 1, 1, 1, "key field value [field value ...]", "hash", "设置哈希字段的值";
+    "HGET", 3, [READONLY], 1, 1, 1, "key field", "hash", "获取哈希字段的值";
+    "HMGET", -3, [READONLY], 1, 1, 1, "key field [field ...]", "hash", "获取多个哈希字段的值";
+    "HGETALL", 2, [READONLY], 1, 1, 1, "key", "hash", "获取哈希的所有字段和值";
+    "HDEL", -3, [WRITE], 1, 1, 1, "key field [field ...]", "hash", "删除哈希字段";
+    "HEXISTS", 3, [READONLY], 1, 1, 1, "key field", "hash", "检查哈希字段是否存在";
+    "HLEN", 2, [READONLY], 1, 1, 1, "key", "hash", "获取哈希的字段数";
+    "HKEYS", 2, [READONLY], 1, 1, 1, "key", "hash", "获取哈希的所有字段";
+    "HVALS", 2, [READONLY], 1, 1, 1, "key", "hash", "获取哈希的所有值";
+    "HRANDFIELD", -2, [READONLY], 1, 1, 1, "key [count [WITHVALUES]]", "hash", "随机获取字段";
+    "HINCRBY", 4, [WRITE], 1, 1, 1, "key field increment", "hash", "哈希字段的值递增指定值";
+    "HEXPIRE", -6, [WRITE], 1, 1, 1, "key seconds [NX|XX|GT|LT] FIELDS numfields field [field ...]", "hash", "设置哈希字段的过期时间(秒)";
+    "HPEXPIRE", -6, [WRITE], 1, 1, 1, "key milliseconds [NX|XX|GT|LT] FIELDS numfields field [field ...]", "hash", "设置哈希字段的过期时间(毫秒)";
+    "HEXPIREAT", -6, [WRITE], 1, 1, 1, "key unix-time-seconds [NX|XX|GT|LT] FIELDS numfields field [field ...]", "hash", "设置哈希字段的过期时刻(Unix秒)";
+    "HPEXPIREAT", -6, [WRITE], 1, 1, 1, "key unix-time-milliseconds [NX|XX|GT|LT] FIELDS numfields field [field ...]", "hash", "设置哈希字段的过期时刻(Unix毫秒)";
+    "HTTL", -5, [READONLY], 1, 1, 1, "key FIELDS numfields field [field ...]", "hash", "获取哈希字段的剩余生存时间(秒)";
+    "HPTTL", -5, [READONLY], 1, 1, 1, "key FIELDS numfields field [field ...]", "hash", "获取哈希字段的剩余生存时间(毫秒)";
+    "HPERSIST", -5, [WRITE], 1, 1, 1, "key FIELDS numfields field [field ...]", "hash", "清除哈希字段的过期时间";
+    "LPUSH", -3, [WRITE], 1, 1, 1, "key element [element ...]", "list", "在列表头部插入元素";
+    "RPUSH", -3, [WRITE], 1, 1, 1, "key element [element ...]", "list", "在列表尾部插入元素";
+    "LPOP", -2, [WRITE], 1, 1, 1, "key [count]", "list", "弹出列表头部的元素";
+    "RPOP", -2, [WRITE], 1, 1, 1, "key [count]", "list", "弹出列表尾部的元素";
+    "LRANGE", 4, [READONLY], 1, 1, 1, "key start stop", "list", "获取列表指定范围的元素";
+    "LLEN", 2, [READONLY], 1, 1, 1, "key", "list", "获取列表长度";
+    "LINDEX", 3, [READONLY], 1, 1, 1, "key index", "list", "获取列表指定下标的元素";
+    "LPOS", -3, [READONLY], 1, 1, 1, "key element [RANK rank] [COUNT num-matches] [MAXLEN len]", "list", "查找等于指定值的元素的下标";
+    "LINSERT", 5, [WRITE], 1, 1, 1, "key BEFORE|AFTER pivot element", "list", "在指定元素之前或之后插入元素";
+    "LSET", 4, [WRITE], 1, 1, 1, "key index element", "list", "设置列表指定下标的元素";
+    "LREM", 4, [WRITE], 1, 1, 1, "key count element", "list", "删除等于指定值的元素";
+    "LTRIM", 4, [WRITE], 1, 1, 1, "key start stop", "list", "只保留列表指定范围内的元素";
+    "LMOVE", 5, [WRITE], 1, 2, 1, "source destination LEFT|RIGHT LEFT|RIGHT", "list", "把元素从一个列表的一端移到另一个列表的一端";
+    "RPOPLPUSH", 3, [WRITE], 1, 2, 1, "source destination", "list", "弹出源列表的尾部元素并插入目标列表的头部";
+    "BLPOP", -3, [WRITE BLOCKING], 1, -2, 1, "key [key ...] timeout", "list", "弹出第一个非空列表的头部元素，都为空时阻塞等待";
+    "BRPOP", -3, [WRITE BLOCKING], 1, -2, 1, "key [key ...] timeout", "list", "弹出第一个非空列表的尾部元素，都为空时阻塞等待";
+    "LMPOP", -4, [WRITE], 0, 0, 0, "numkeys key [key ...] LEFT|RIGHT [COUNT count]", "list", "从第一个非空列表弹出多个元素";
+    "BLMPOP", -5, [WRITE BLOCKING], 0, 0, 0, "timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]", "list", "从第一个非空列表弹出多个元素，都为空时阻塞等待";
+    "BLMOVE", 6, [WRITE BLOCKING], 1, 2, 1, "source destination LEFT|RIGHT LEFT|RIGHT timeout", "list", "把元素从一个列表移到另一个列表，源列表为空时阻塞等待";
+    "SADD", -3, [WRITE], 1, 1, 1, "key member [member ...]", "set", "向集合添加成员";
+    "SREM", -3, [WRITE], 1, 1, 1, "key member [member ...]", "set", "从集合删除成员";
+    "SMEMBERS", 2, [READONLY], 1, 1, 1, "key", "set", "获取集合的所有成员";
+    "SISMEMBER", 3, [READONLY], 1, 1, 1, "key member", "set", "检查成员是否在集合中";
+    "SMISMEMBER", -3, [READONLY], 1, 1, 1, "key member [member ...]", "set", "检查多个成员是否在集合中";
+    "SCARD", 2, [READONLY], 1, 1, 1, "key", "set", "获取集合的成员数";
+    "SPOP", -2, [WRITE], 1, 1, 1, "key [count]", "set", "随机弹出成员，弹出最后一个成员时键也被删除";
+    "SRANDMEMBER", -2, [READONLY], 1, 1, 1, "key [count]", "set", "随机获取成员";
+    "SUNION", -2, [READONLY], 1, -1, 1, "key [key ...]", "set", "求多个集合的并集";
+    "SINTER", -2, [READONLY], 1, -1, 1, "key [key ...]", "set", "求多个集合的交集";
+    "SINTERCARD", -3, [READONLY], 0, 0, 0, "numkeys key [key ...] [LIMIT limit]", "set", "求多个集合的交集的成员数，数到limit个时提前结束";
+    "SDIFF", -2, [READONLY], 1, -1, 1, "key [key ...]", "set", "求第一个集合与其他集合的差集";
+    "SUNIONSTORE", -3, [WRITE], 1, -1, 1, "destination key [key ...]", "set", "求多个集合的并集并写入destination";
+    "SINTERSTORE", -3, [WRITE], 1, -1, 1, "destination key [key ...]", "set", "求多个集合的交集并写入destination";
+    "SDIFFSTORE", -3, [WRITE], 1, -1, 1, "destination key [key ...]", "set", "求第一个集合与其余集合的差集并写入destination";
+    "ZADD", -4, [WRITE], 1, 1, 1, "key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]", "sorted-set", "向有序集合添加成员或更新分数";
+    "ZSCORE", 3, [READONLY], 1, 1, 1, "key member", "sorted-set", "获取成员的分数";
+    "ZINCRBY", 4, [WRITE], 1, 1, 1, "key increment member", "sorted-set", "成员的分数增加指定值";
+    "ZRANK", 3, [READONLY], 1, 1, 1, "key member", "sorted-set", "获取成员按分数从小到大的名次";
+    "ZREVRANK", 3, [READONLY], 1, 1, 1, "key member", "sorted-set", "获取成员按分数从大到小的名次";
+    "ZRANGE", -4, [READONLY], 1, 1, 1, "key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]", "sorted-set", "按名次、分数或字典序范围获取成员";
+    "ZREVRANGE", -4, [READONLY], 1, 1, 1, "key start stop [WITHSCORES]", "sorted-set", "按名次范围从大到小获取成员";
+    "ZRANGEBYSCORE", -4, [READONLY], 1, 1, 1, "key min max [WITHSCORES] [LIMIT offset count]", "sorted-set", "按分数范围获取成员";
+    "ZREVRANGEBYSCORE", -4, [READONLY], 1, 1, 1, "key max min [WITHSCORES] [LIMIT offset count]", "sorted-set", "按分数范围从大到小获取成员";
+    "ZRANGEBYLEX", -4, [READONLY], 1, 1, 1, "key min max [LIMIT offset count]", "sorted-set", "按字典序范围获取成员";
+    "ZREVRANGEBYLEX", -4, [READONLY], 1, 1, 1, "key max min [LIMIT offset count]", "sorted-set", "按字典序范围从大到小获取成员";
+    "ZRANGESTORE", -5, [WRITE], 1, 2, 1, "dst src min max [BYSCORE|BYLEX] [REV] [LIMIT offset count]", "sorted-set", "把范围查询的结果存入目标有序集合";
+    "ZRANDMEMBER", -2, [READONLY], 1, 1, 1, "key [count [WITHSCORES]]", "sorted-set", "随机获取成员";
+    "ZCARD", 2, [READONLY], 1, 1, 1, "key", "sorted-set", "获取有序集合的成员数";
+    "ZREM", -3, [WRITE], 1, 1, 1, "key member [member ...]", "sorted-set", "从有序集合删除成员";
+    "ZMPOP", -4, [WRITE], 0, 0, 0, "numkeys key [key ...] MIN|MAX [COUNT count]", "sorted-set", "从第一个非空有序集合弹出多个成员";
+    "BZPOPMIN", -3, [WRITE BLOCKING], 1, -2, 1, "key [key ...] timeout", "sorted-set", "弹出第一个非空有序集合中分数最小的成员，都为空时阻塞等待";
+    "BZPOPMAX", -3, [WRITE BLOCKING], 1, -2, 1, "key [key ...] timeout", "sorted-set", "弹出第一个非空有序集合中分数最大的成员，都为空时阻塞等待";
+    "BZMPOP", -5, [WRITE BLOCKING], 0, 0, 0, "timeout numkeys key [key ...] MIN|MAX [COUNT count]", "sorted-set", "从第一个非空有序集合弹出多个成员，都为空时阻塞等待";
+    "XADD", -5, [WRITE], 1, 1, 1, "key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold [LIMIT count]] *|id field value [field value ...]", "stream", "向流添加条目";
+    "XLEN", 2, [READONLY], 1, 1, 1, "key", "stream", "获取流的条目数";
+    "XRANGE", -4, [READONLY], 1, 1, 1, "key start end [COUNT count]", "stream", "按ID范围获取条目";
+    "XREVRANGE", -4, [READONLY], 1, 1, 1, "key end start [COUNT count]", "stream", "按ID范围从大到小获取条目";
+    "XTRIM", -4, [WRITE], 1, 1, 1, "key MAXLEN|MINID [=|~] threshold [LIMIT count]", "stream", "按长度或最小ID裁剪流";
+    "XREAD", -4, [READONLY], 0, 0, 0, "[COUNT count] STREAMS key [key ...] id [id ...]", "stream", "读取一个或多个流中指定ID之后的条目";
+    "XGROUP", -2, [], 0, 0, 0, "CREATE key group id|$ [MKSTREAM]|SETID key group id|$|DESTROY key group|CREATECONSUMER key group consumer|DELCONSUMER key group consumer|HELP", "stream", "管理流的消费者组";
+    "XREADGROUP", -7, [WRITE], 0, 0, 0, "GROUP group consumer [COUNT count] [NOACK] STREAMS key [key ...] id|> [id|> ...]", "stream", "以消费者组中一个消费者的身份读取流";
+    "XACK", -4, [WRITE], 1, 1, 1, "key group id [id ...]", "stream", "确认条目已处理，从待确认列表中删除";
+    "XPENDING", -3, [READONLY], 1, 1, 1, "key group [[IDLE min-idle-time] start end count [consumer]]", "stream", "查看消费者组的待确认列表";
+    "XCLAIM", -6, [WRITE], 1, 1, 1, "key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds] [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]", "stream", "把闲置的待确认条目转给另一个消费者";
+    "JSON.SET", -4, [WRITE], 1, 1, 1, "key path value [NX|XX]", "json", "设置JSON文档中路径处的值";
+    "JSON.GET", -2, [READONLY], 1, 1, 1, "key [path ...]", "json", "读取JSON文档中路径处的值";
+    "JSON.DEL", -2, [WRITE], 1, 1, 1, "key [path]", "json", "删除JSON文档中路径处的值";
+    "JSON.TYPE", -2, [READONLY], 1, 1, 1, "key [path]", "json", "获取JSON文档中路径处值的类型";
+    "JSON.NUMINCRBY", 4, [WRITE], 1, 1, 1, "key path value", "json", "把JSON文档中路径处的数字增加指定值";
+    "JSON.ARRAPPEND", -4, [WRITE], 1, 1, 1, "key path value [value ...]", "json", "向JSON文档中路径处的数组追加元素";
+    "TS.ADD", -4, [WRITE], 1, 1, 1, "key timestamp value [RETENTION retention] [LABELS label value ...]", "timeseries", "向时间序列添加样本";
+    "TS.RANGE", -4, [READONLY], 1, 1, 1, "key fromTimestamp toTimestamp [AGGREGATION aggregator bucketDuration]", "timeseries", "查询时间序列的样本";
+    "TS.GET", 2, [READONLY], 1, 1, 1, "key", "timeseries", "获取时间序列的最新样本";
+    "TS.MRANGE", -5, [READONLY], 0, 0, 0, "fromTimestamp toTimestamp [WITHLABELS] [AGGREGATION aggregator bucketDuration] FILTER filter ...", "timeseries", "按标签查询多个时间序列";
+    "BF.RESERVE", -4, [WRITE], 1, 1, 1, "key error_rate capacity [EXPANSION expansion] [NONSCALING]", "bf", "创建布隆过滤器";
+    "BF.ADD", 3, [WRITE], 1, 1, 1, "key item", "bf", "向布隆过滤器添加元素";
+    "BF.MADD", -3, [WRITE], 1, 1, 1, "key item [item ...]", "bf", "向布隆过滤器添加多个元素";
+    "BF.EXISTS", 3, [READONLY], 1, 1, 1, "key item", "bf", "检查元素是否可能在布隆过滤器中";
+    "CF.ADD", 3, [WRITE], 1, 1, 1, "key item", "cf", "向布谷鸟过滤器添加元素";
+    "CF.EXISTS", 3, [READONLY], 1, 1, 1, "key item", "cf", "检查元素是否可能在布谷鸟过滤器中";
+    "CF.DEL", 3, [WRITE], 1, 1, 1, "key item", "cf", "从布谷鸟过滤器删除元素的一份";
+    "CL.THROTTLE", -5, [WRITE], 1, 1, 1, "key max_burst count period [quantity]", "cl", "GCRA限流检查";
+    "LOCK", 4, [WRITE], 1, 1, 1, "key token milliseconds", "lock", "获取或续期分布式锁";
+    "UNLOCK", 3, [WRITE], 1, 1, 1, "key token", "lock", "释放持有的分布式锁";
+    "MULTI", 1, [], 0, 0, 0, "", "transactions", "开始事务";
+    "EXEC", 1, [], 0, 0, 0, "", "transactions", "执行事务";
+    "DISCARD", 1, [], 0, 0, 0, "", "transactions", "放弃事务";
+    "WATCH", -2, [], 1, -1, 1, "key [key ...]", "transactions", "监视键";
+    "UNWATCH", 1, [], 0, 0, 0, "", "transactions", "取消监视";
+    "DBSIZE", 1, [READONLY], 0, 0, 0, "", "server", "获取键数量";
+    "FLUSHDB", -1, [WRITE], 0, 0, 0, "[ASYNC|SYNC]", "server", "清空数据库";
+    "FLUSHALL", -1, [WRITE], 0, 0, 0, "[ASYNC|SYNC]", "server", "清空所有数据库";
+    "INFO", -1, [], 0, 0, 0, "[section]", "server", "获取服务器信息";
+    "CLIENT", -2, [], 0, 0, 0, "REPLY ON|OFF|SKIP|HELP", "connection", "连接管理";
+    "CONFIG", -2, [], 0, 0, 0, "RELOAD|SET parameter value|HELP", "server", "配置管理";
+    "MEMORY", -2, [], 0, 0, 0, "USAGE key|HELP", "server", "内存诊断";
+    "OBJECT", -2, [], 0, 0, 0, "ENCODING key|HELP", "generic", "查看键的内部信息";
+    "MODULE", -2, [], 0, 0, 0, "LOAD path [arg ...]|UNLOAD name|LIST|HELP", "server", "模块管理";
+    "COMMAND", -1, [], 0, 0, 0, "[COUNT|LIST|INFO command ...|GETKEYS command arg ...|HELP]", "server", "查询命令元数据";
+};
+
+/// 子命令表
+///
+/// 每行依次是: 容器命令、子命令、arity、[标志]、第一个键、最后一个键、键间隔、参数格式、说明。
+/// HELP子命令由解析器统一提供，不需要登记
+macro_rules! subcommands {
+    ($($container:literal, $name:literal, $arity:literal, [$($flag:ident)*], $first:literal, $last:literal,
+       $step:literal, $args:literal, $summary:literal;)*) => {
+        &[$(SubcommandInfo {
+            container: $container,
+            name: $name,
+            arity: $arity,
+            flags: flags!($($flag)*),
+            keys: KeySpec { first: $first, last: $last, step: $step },
+            arguments: $args,
+            summary: $summary,
+        }),*]
+    };
+}
+
+/// 服务器支持的全部子命令
+pub const SUBCOMMANDS: &[SubcommandInfo] = subcommands! {
+    "CLIENT", "REPLY", 3, [], 0, 0, 0, "ON|OFF|SKIP", "控制服务器是否回复本连接的命令";
+    "CONFIG", "RELOAD", 2, [ADMIN], 0, 0, 0, "", "重新加载配置文件，可在运行时修改的配置项立即生效";
+    "CONFIG", "SET", 4, [ADMIN], 0, 0, 0, "parameter value", "修改一个可在运行时修改的配置项";
+    "MEMORY", "USAGE", 3, [READONLY], 2, 2, 1, "key", "估算键及其值占用的内存(字节)";
+    "OBJECT", "ENCODING", 3, [READONLY], 2, 2, 1, "key", "值的内部编码，如listpack、intset、hashtable、skiplist";
+    "XGROUP", "CREATE", -5, [WRITE], 2, 2, 1, "key group id|$ [MKSTREAM]", "创建消费者组，$表示只投递之后添加的条目";
+    "XGROUP", "SETID", 5, [WRITE], 2, 2, 1, "key group id|$", "修改消费者组最后投递的ID";
+    "XGROUP", "DESTROY", 4, [WRITE], 2, 2, 1, "key group", "删除消费者组";
+    "XGROUP", "CREATECONSUMER", 5, [WRITE], 2, 2, 1, "key group consumer", "在消费者组中创建消费者";
+    "XGROUP", "DELCONSUMER", 5, [WRITE], 2, 2, 1, "key group consumer", "删除消费者和它的待确认条目，返回删除的条目数";
+    "MODULE", "LOAD", -3, [ADMIN], 0, 0, 0, "path [arg ...]", "加载共享库中的模块，参数传给模块的初始化函数";
+    "MODULE", "UNLOAD", 3, [ADMIN], 0, 0, 0, "name", "卸载模块；注册了值类型的模块不能卸载";
+    "MODULE", "LIST", 2, [ADMIN], 0, 0, 0, "", "列出已加载的模块";
+    "COMMAND", "COUNT", 2, [], 0, 0, 0, "", "返回命令总数";
+    "COMMAND", "LIST", 2, [], 0, 0, 0, "", "返回所有命令名";
+    "COMMAND", "INFO", -2, [], 0, 0, 0, "[command ...]", "返回指定命令(不指定时为全部命令)的元数据";
+    "COMMAND", "GETKEYS", -3, [], 0, 0, 0, "command [arg ...]", "从一条完整的命令中提取键";
+};
+
+/// 容器命令的全部子命令
+pub fn subcommands(container: &str) -> impl Iterator<Item = &'static SubcommandInfo> + '_ {
+    SUBCOMMANDS
+        .iter()
+        .filter(move |info| info.container.eq_ignore_ascii_case(container))
+}
+
+/// 按名称查找子命令(大小写不敏感)
+pub fn lookup_subcommand(container: &str, name: &str) -> Option<&'static SubcommandInfo> {
+    subcommands(container).find(|info| info.name.eq_ignore_ascii_case(name))
+}
+
+/// 按名称查找命令(大小写不敏感)
+pub fn lookup(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|info| info.name.eq_ignore_ascii_case(name))
+}
+
+/// 以`prefix`开头的命令(大小写不敏感)
+pub fn completions(prefix: &str) -> impl Iterator<Item = &'static CommandInfo> + '_ {
+    COMMANDS.iter().filter(move |info| {
+        info.name.len() >= prefix.len() && info.name[..prefix.len()].eq_ignore_ascii_case(prefix)
+    })
+}
+
+/// 提取一条完整命令(含命令名)中的键，供COMMAND GETKEYS使用
+///
+/// 容器命令按子命令的键位置提取。命令未登记时返回`GetKeysError::UnknownCommand`
+pub fn get_keys(argv: &[String]) -> Result<Vec<&str>, GetKeysError> {
+    let info = argv
+        .first()
+        .and_then(|name| lookup(name))
+        .ok_or(GetKeysError::UnknownCommand)?;
+    if !info.accepts(argv.len()) {
+        return Err(GetKeysError::WrongArity);
+    }
+
+    let keys = match argv.get(1).and_then(|sub| lookup_subcommand(info.name, sub)) {
+        Some(sub) if sub.accepts(argv.len()) => sub.keys,
+        Some(_) => return Err(GetKeysError::WrongArity),
+        None => info.keys,
+    };
+    Ok(keys.positions(argv.len()).map(|pos| argv[pos].as_str()).collect())
+}
+
+/// COMMAND GETKEYS的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GetKeysError {
+    /// 命令不存在
+    UnknownCommand,
+    /// 参数个数不符合arity
+    WrongArity,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_completions() {
+        assert_eq!(lookup("get").unwrap().arguments, "key");
+        assert!(lookup("NOPE").is_none());
+
+        let names: Vec<_> = completions("pe").map(|info| info.name).collect();
+        assert_eq!(names, vec!["PEXPIRE", "PEXPIREAT", "PEXPIRETIME", "PERSIST"]);
+    }
+
+    #[test]
+    fn test_table_matches_parser() {
+        use crate::command::Command;
+        use crate::resp::RespValue;
+
+        // 表中每个命令都能被解析器识别，而不是落入Unknown
+        for info in COMMANDS {
+            let cmd = Command::from_resp(RespValue::Array(vec![RespValue::BulkString(
+                info.name.as_bytes().to_vec(),
+            )]));
+            assert!(!matches!(cmd, Ok(Command::Unknown { .. })), "{}", info.name);
+        }
+    }
+
+    #[test]
+    fn test_subcommands_match_parser() {
+        use crate::command::Command;
+        use crate::resp::RespValue;
+
+        // 每个登记的子命令按arity补足参数后都能被解析器识别
+        for info in SUBCOMMANDS {
+            assert!(lookup(info.container).is_some(), "{}", info.container);
+            let mut parts = vec![info.container, info.name];
+            parts.resize(info.arity.unsigned_abs() as usize, "arg");
+            let cmd = Command::from_resp(RespValue::Array(
+                parts
+                    .iter()
+                    .map(|s| RespValue::BulkString(s.as_bytes().to_vec()))
+                    .collect(),
+            ));
+            // 占位参数取值不合法时的错误同样说明子命令已被识别
+            let placeholder_errors =
+                ["syntax error", "Invalid stream ID specified as stream command argument"];
+            assert!(
+                cmd.is_ok()
+                    || matches!(&cmd, Err(e) if placeholder_errors.contains(&e.to_string().as_str())),
+                "{} {}: {:?}",
+                info.container,
+                info.name,
+                cmd
+            );
+        }
+        assert_eq!(lookup_subcommand("config", "reload").unwrap().arity, 2);
+        assert!(lookup_subcommand("CONFIG", "USAGE").is_none());
+    }
+}
@@ -0,0 +1,875 @@
+//! 模块系统 - 在运行时加载共享库，注册新的命令和值类型
+//!
+//! 模块是导出`rust_redis_module_init`的共享库，接口全部使用C ABI，可以用任何能导出C函数的语言编写。
+//! `MODULE LOAD`(或配置文件中的`loadmodule`)用dlopen打开共享库并调用初始化函数，
+//! 初始化函数通过`ModuleApi`中的函数设置模块名、注册命令和值类型:
+//!
+//! ```c
+//! int rust_redis_module_init(LoadContext *ctx, const ModuleApi *api,
+//!                            const ModuleStr *argv, size_t argc);
+//! void command(CallContext *ctx, const ModuleApi *api, const ModuleStr *argv, size_t argc);
+//! ```
+//!
+//! - 命令名不能与内置命令或其他模块的命令相同；本服务器未实现的命令优先交给模块处理，
+//!   没有模块注册时才按未知命令处理(或转发给上游)
+//! - 命令通过`get_string`/`set_string`等函数读写键，必须调用且只调用一次`reply_*`生成回复，
+//!   `reply_array`之后的若干次回复组成数组的元素
+//! - 值类型的数据由模块自己编码成字节串保存，服务器不持有模块的指针，
+//!   与Redis一样，注册了值类型的模块不能卸载，否则已有的值无法再被解释
+//! - `ModuleApi::version`是接口版本，只会在末尾追加函数，模块应检查它不小于自己需要的版本
+//!
+//! Rust特点展示:
+//! - `#[repr(C)]`和`extern "C"`保证结构体布局和调用约定与C一致
+//! - 传给模块的上下文是不透明指针，模块只能通过接口函数访问
+//! - `Arc<Library>`: 执行中的命令持有共享库的引用，卸载后最后一个引用释放时才dlclose
+
+use crate::error::{RedisError, RedisResult};
+use crate::metadata;
+use crate::resp::RespValue;
+use crate::store::Store;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// 接口版本
+pub const API_VERSION: u32 = 1;
+
+/// 模块初始化函数的符号名
+pub const INIT_SYMBOL: &str = "rust_redis_module_init";
+
+/// 接口函数的返回值: 成功
+pub const STATUS_OK: i32 = 0;
+/// 接口函数的返回值: 键不存在
+pub const STATUS_MISSING: i32 = 1;
+/// 接口函数的返回值: 键的类型不对
+pub const STATUS_WRONG_TYPE: i32 = 2;
+/// 接口函数的返回值: 参数无效
+pub const STATUS_ERR: i32 = -1;
+
+/// 模块与服务器之间传递的字节串，只在调用期间有效
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ModuleStr {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl ModuleStr {
+    pub fn new(bytes: &[u8]) -> Self {
+        Self {
+            ptr: bytes.as_ptr(),
+            len: bytes.len(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `ptr`必须指向`len`个有效字节(`len`为0时可以为空指针)，并且在返回的切片使用期间保持有效
+    pub unsafe fn as_bytes<'a>(self) -> &'a [u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(self.ptr, self.len)
+        }
+    }
+
+    unsafe fn to_string_lossy(self) -> String {
+        String::from_utf8_lossy(self.as_bytes()).into_owned()
+    }
+}
+
+/// 模块的初始化函数，返回`STATUS_OK`表示成功
+pub type InitFn =
+    unsafe extern "C" fn(*mut LoadContext, *const ModuleApi, *const ModuleStr, usize) -> i32;
+
+/// 模块命令的实现，`argv[0]`是命令名(大写)
+pub type CommandFn =
+    unsafe extern "C" fn(*mut CallContext<'_>, *const ModuleApi, *const ModuleStr, usize);
+
+/// 提供给模块的接口函数表
+///
+/// 前三个函数只能在初始化函数中调用，其余只能在命令执行期间调用
+#[repr(C)]
+pub struct ModuleApi {
+    pub version: u32,
+    /// 设置模块名和版本，初始化函数必须调用
+    pub set_name: unsafe extern "C" fn(*mut LoadContext, ModuleStr, i64),
+    /// 注册命令，命令名大小写不敏感
+    pub register_command: unsafe extern "C" fn(*mut LoadContext, ModuleStr, CommandFn) -> i32,
+    /// 注册值类型，返回类型ID(失败时为`STATUS_ERR`)，名称即TYPE命令的回复
+    pub register_type: unsafe extern "C" fn(*mut LoadContext, ModuleStr) -> i32,
+    pub reply_simple: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr),
+    pub reply_error: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr),
+    pub reply_integer: unsafe extern "C" fn(*mut CallContext<'_>, i64),
+    pub reply_bulk: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr),
+    pub reply_null: unsafe extern "C" fn(*mut CallContext<'_>),
+    /// 开始一个长度为`len`的数组，之后的`len`次回复是它的元素
+    pub reply_array: unsafe extern "C" fn(*mut CallContext<'_>, usize),
+    /// 读取字符串键，结果在命令返回前有效
+    pub get_string: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr, *mut ModuleStr) -> i32,
+    /// 写入字符串键，与SET一样覆盖任何类型的旧值并清除过期时间
+    pub set_string: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr, ModuleStr) -> i32,
+    /// 读取模块类型的键，结果在命令返回前有效
+    pub get_value:
+        unsafe extern "C" fn(*mut CallContext<'_>, i32, ModuleStr, *mut ModuleStr) -> i32,
+    /// 写入模块类型的键，键已存在且是其他类型时返回`STATUS_WRONG_TYPE`，过期时间保留
+    pub set_value: unsafe extern "C" fn(*mut CallContext<'_>, i32, ModuleStr, ModuleStr) -> i32,
+    /// 删除键，键不存在时返回`STATUS_MISSING`
+    pub delete_key: unsafe extern "C" fn(*mut CallContext<'_>, ModuleStr) -> i32,
+}
+
+static API: ModuleApi = ModuleApi {
+    version: API_VERSION,
+    set_name,
+    register_command,
+    register_type,
+    reply_simple,
+    reply_error,
+    reply_integer,
+    reply_bulk,
+    reply_null,
+    reply_array,
+    get_string,
+    set_string,
+    get_value,
+    set_value,
+    delete_key,
+};
+
+/// 模块类型的值: 类型名和模块编码后的数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleValue {
+    type_name: &'static str,
+    data: Vec<u8>,
+}
+
+impl ModuleValue {
+    /// TYPE命令返回的类型名
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + self.data.capacity()
+    }
+}
+
+/// 初始化函数的上下文，对模块不透明
+pub struct LoadContext<'a> {
+    registry: &'a Registry,
+    name: Option<(String, i64)>,
+    commands: Vec<(String, CommandFn)>,
+    types: Vec<String>,
+    /// 第一个失败的调用，初始化函数忽略了返回值时也能让加载失败
+    error: Option<String>,
+}
+
+impl LoadContext<'_> {
+    fn fail(&mut self, message: String) -> i32 {
+        self.error.get_or_insert(message);
+        STATUS_ERR
+    }
+}
+
+/// 命令执行的上下文，对模块不透明
+pub struct CallContext<'a> {
+    store: &'a Store,
+    types: &'a [&'static str],
+    /// `get_*`返回给模块的数据，保存到命令返回
+    buffers: Vec<Vec<u8>>,
+    reply: Reply,
+}
+
+impl CallContext<'_> {
+    /// 把数据交给模块: 保存在上下文中，指针写入`out`
+    unsafe fn lend(&mut self, data: Vec<u8>, out: *mut ModuleStr) {
+        if !out.is_null() {
+            *out = ModuleStr::new(&data);
+        }
+        // 移动Vec不会移动它的堆内存，指针保持有效
+        self.buffers.push(data);
+    }
+}
+
+/// 按调用顺序拼装回复，`reply_array`压入一层未填满的数组
+#[derive(Debug, Default)]
+struct Reply {
+    open: Vec<(usize, Vec<RespValue>)>,
+    done: Option<RespValue>,
+}
+
+impl Reply {
+    fn push(&mut self, mut value: RespValue) {
+        loop {
+            let Some((len, items)) = self.open.last_mut() else {
+                // 多余的回复忽略
+                self.done.get_or_insert(value);
+                return;
+            };
+            items.push(value);
+            if items.len() < *len {
+                return;
+            }
+            let (_, items) = self.open.pop().unwrap_or_default();
+            value = RespValue::Array(items);
+        }
+    }
+
+    fn array(&mut self, len: usize) {
+        if len == 0 {
+            self.push(RespValue::Array(Vec::new()));
+        } else {
+            // 长度由模块给出，不按它预分配
+            self.open.push((len, Vec::new()));
+        }
+    }
+
+    fn finish(self) -> RespValue {
+        match self.done {
+            Some(value) if self.open.is_empty() => value,
+            _ => {
+                RespValue::Error("ERR module command did not produce a complete reply".to_string())
+            }
+        }
+    }
+}
+
+// ===== 接口函数 =====
+// 模块保证传入的上下文指针来自服务器，字节串在调用期间有效
+
+unsafe extern "C" fn set_name(ctx: *mut LoadContext, name: ModuleStr, version: i64) {
+    let ctx = &mut *ctx;
+    let name = name.to_string_lossy();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        ctx.fail(format!("无效的模块名 '{}'", name));
+        return;
+    }
+    ctx.name = Some((name, version));
+}
+
+unsafe extern "C" fn register_command(
+    ctx: *mut LoadContext,
+    name: ModuleStr,
+    func: CommandFn,
+) -> i32 {
+    let ctx = &mut *ctx;
+    let name = name.to_string_lossy().to_uppercase();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return ctx.fail(format!("无效的命令名 '{}'", name));
+    }
+    let taken = metadata::lookup(&name).is_some()
+        || ctx.registry.commands.contains_key(&name)
+        || ctx.commands.iter().any(|(existing, _)| *existing == name);
+    if taken {
+        return ctx.fail(format!("命令 {} 已经存在", name));
+    }
+    ctx.commands.push((name, func));
+    STATUS_OK
+}
+
+unsafe extern "C" fn register_type(ctx: *mut LoadContext, name: ModuleStr) -> i32 {
+    let ctx = &mut *ctx;
+    let name = name.to_string_lossy();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return ctx.fail(format!("无效的类型名 '{}'", name));
+    }
+    if ctx.registry.types.contains(&name.as_str()) || ctx.types.contains(&name) {
+        return ctx.fail(format!("类型 {} 已经存在", name));
+    }
+    ctx.types.push(name);
+    (ctx.registry.types.len() + ctx.types.len() - 1) as i32
+}
+
+unsafe extern "C" fn reply_simple(ctx: *mut CallContext, text: ModuleStr) {
+    (*ctx)
+        .reply
+        .push(RespValue::SimpleString(text.to_string_lossy()));
+}
+
+unsafe extern "C" fn reply_error(ctx: *mut CallContext, text: ModuleStr) {
+    (*ctx).reply.push(RespValue::Error(text.to_string_lossy()));
+}
+
+unsafe extern "C" fn reply_integer(ctx: *mut CallContext, value: i64) {
+    (*ctx).reply.push(RespValue::Integer(value));
+}
+
+unsafe extern "C" fn reply_bulk(ctx: *mut CallContext, data: ModuleStr) {
+    (*ctx)
+        .reply
+        .push(RespValue::BulkString(data.as_bytes().to_vec()));
+}
+
+unsafe extern "C" fn reply_null(ctx: *mut CallContext) {
+    (*ctx).reply.push(RespValue::Null);
+}
+
+unsafe extern "C" fn reply_array(ctx: *mut CallContext, len: usize) {
+    (*ctx).reply.array(len);
+}
+
+unsafe extern "C" fn get_string(ctx: *mut CallContext, key: ModuleStr, out: *mut ModuleStr) -> i32 {
+    let ctx = &mut *ctx;
+    match ctx.store.get_checked(&key.to_string_lossy()) {
+        Ok(Some(data)) => {
+            ctx.lend(data, out);
+            STATUS_OK
+        }
+        Ok(None) => STATUS_MISSING,
+        Err(_) => STATUS_WRONG_TYPE,
+    }
+}
+
+unsafe extern "C" fn set_string(ctx: *mut CallContext, key: ModuleStr, value: ModuleStr) -> i32 {
+    (*ctx)
+        .store
+        .set(key.to_string_lossy(), value.as_bytes().to_vec());
+    STATUS_OK
+}
+
+unsafe extern "C" fn get_value(
+    ctx: *mut CallContext,
+    type_id: i32,
+    key: ModuleStr,
+    out: *mut ModuleStr,
+) -> i32 {
+    let ctx = &mut *ctx;
+    let Some(&type_name) = usize::try_from(type_id)
+        .ok()
+        .and_then(|id| ctx.types.get(id))
+    else {
+        return STATUS_ERR;
+    };
+    let found = ctx
+        .store
+        .read_as(&key.to_string_lossy(), |value: &ModuleValue| {
+            (value.type_name == type_name).then(|| value.data.clone())
+        });
+    match found {
+        Ok(Some(Some(data))) => {
+            ctx.lend(data, out);
+            STATUS_OK
+        }
+        Ok(None) => STATUS_MISSING,
+        Ok(Some(None)) | Err(_) => STATUS_WRONG_TYPE,
+    }
+}
+
+unsafe extern "C" fn set_value(
+    ctx: *mut CallContext,
+    type_id: i32,
+    key: ModuleStr,
+    data: ModuleStr,
+) -> i32 {
+    let ctx = &*ctx;
+    let Some(&type_name) = usize::try_from(type_id)
+        .ok()
+        .and_then(|id| ctx.types.get(id))
+    else {
+        return STATUS_ERR;
+    };
+    let data = data.as_bytes().to_vec();
+    let result = ctx
+        .store
+        .update_as(&key.to_string_lossy(), |slot: &mut Option<ModuleValue>| {
+            if slot
+                .as_ref()
+                .is_some_and(|value| value.type_name != type_name)
+            {
+                return Err(RedisError::WrongType);
+            }
+            *slot = Some(ModuleValue { type_name, data });
+            Ok(((), true))
+        });
+    match result {
+        Ok(()) => STATUS_OK,
+        Err(_) => STATUS_WRONG_TYPE,
+    }
+}
+
+unsafe extern "C" fn delete_key(ctx: *mut CallContext, key: ModuleStr) -> i32 {
+    if (*ctx).store.del(&key.to_string_lossy()) {
+        STATUS_OK
+    } else {
+        STATUS_MISSING
+    }
+}
+
+// ===== 共享库 =====
+
+/// dlopen打开的共享库，drop时dlclose
+#[derive(Debug)]
+struct Library {
+    #[cfg(unix)]
+    handle: *mut libc::c_void,
+}
+
+// Rust特点: unsafe impl 由我们保证: 句柄只在打开和关闭时使用，dlopen/dlclose是线程安全的
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+#[cfg(unix)]
+impl Library {
+    /// 打开共享库并找到初始化函数
+    fn open(path: &str) -> RedisResult<(Self, InitFn)> {
+        use std::ffi::CString;
+
+        let c_path = CString::new(path)
+            .map_err(|_| RedisError::Module("路径中不能含有NUL字符".to_string()))?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(RedisError::Module(dl_error()));
+        }
+        let library = Library { handle };
+
+        let symbol = CString::new(INIT_SYMBOL).unwrap_or_default();
+        let init = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+        if init.is_null() {
+            return Err(RedisError::Module(format!(
+                "{} 没有导出 {}",
+                path, INIT_SYMBOL
+            )));
+        }
+        // Rust特点: transmute 由模块接口约定保证该符号是InitFn签名的函数
+        let init = unsafe { std::mem::transmute::<*mut libc::c_void, InitFn>(init) };
+        Ok((library, init))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+/// 最近一次dl*调用的错误信息
+#[cfg(unix)]
+fn dl_error() -> String {
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        return "无法打开共享库".to_string();
+    }
+    unsafe { std::ffi::CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+// ===== 注册表 =====
+
+/// 已加载的模块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    pub name: String,
+    pub version: i64,
+    pub path: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Module {
+    info: ModuleInfo,
+    /// 是否注册了值类型
+    has_types: bool,
+    _library: Option<Arc<Library>>,
+}
+
+#[derive(Debug, Clone)]
+struct ModuleCommand {
+    module: String,
+    func: CommandFn,
+    library: Option<Arc<Library>>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    modules: Vec<Module>,
+    /// 命令名(大写) -> 实现
+    commands: HashMap<String, ModuleCommand>,
+    /// 类型ID -> 类型名；类型不会被注销，ID保持稳定
+    types: Vec<&'static str>,
+}
+
+/// 模块注册表，由Store持有，所有连接共享
+#[derive(Debug, Default)]
+pub struct ModuleRegistry {
+    inner: RwLock<Registry>,
+}
+
+impl ModuleRegistry {
+    fn read(&self) -> RwLockReadGuard<'_, Registry> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Registry> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 加载共享库中的模块，`args`传给初始化函数；返回模块名
+    pub fn load(&self, path: &str, args: &[String]) -> RedisResult<String> {
+        #[cfg(unix)]
+        {
+            let (library, init) = Library::open(path)?;
+            self.register(path, args, init, Some(Arc::new(library)))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = args;
+            Err(RedisError::Module(format!(
+                "当前平台不支持加载模块: {}",
+                path
+            )))
+        }
+    }
+
+    /// 调用初始化函数，成功后登记它注册的命令和类型；失败时什么都不登记
+    ///
+    /// 初始化期间持有写锁，并发的加载不会分配到相同的类型ID
+    fn register(
+        &self,
+        path: &str,
+        args: &[String],
+        init: InitFn,
+        library: Option<Arc<Library>>,
+    ) -> RedisResult<String> {
+        let mut registry = self.write();
+        let argv: Vec<ModuleStr> = args
+            .iter()
+            .map(|arg| ModuleStr::new(arg.as_bytes()))
+            .collect();
+        let mut ctx = LoadContext {
+            registry: &registry,
+            name: None,
+            commands: Vec::new(),
+            types: Vec::new(),
+            error: None,
+        };
+        let status = unsafe { init(&mut ctx, &API, argv.as_ptr(), argv.len()) };
+        let LoadContext {
+            name,
+            commands,
+            types,
+            error,
+            ..
+        } = ctx;
+
+        if let Some(error) = error {
+            return Err(RedisError::Module(error));
+        }
+        if status != STATUS_OK {
+            return Err(RedisError::Module(format!(
+                "{} 初始化失败({})",
+                path, status
+            )));
+        }
+        let Some((name, version)) = name else {
+            return Err(RedisError::Module(format!("{} 没有设置模块名", path)));
+        };
+        if registry
+            .modules
+            .iter()
+            .any(|module| module.info.name == name)
+        {
+            return Err(RedisError::Module(format!("模块 {} 已经加载", name)));
+        }
+
+        for (command, func) in commands {
+            let library = library.clone();
+            registry.commands.insert(
+                command,
+                ModuleCommand {
+                    module: name.clone(),
+                    func,
+                    library,
+                },
+            );
+        }
+        let has_types = !types.is_empty();
+        // 类型名在值中以&'static str保存；类型不会注销，泄漏的只有注册时的少量字符串
+        registry.types.extend(
+            types
+                .into_iter()
+                .map(|name| &*Box::leak(name.into_boxed_str())),
+        );
+        registry.modules.push(Module {
+            info: ModuleInfo {
+                name: name.clone(),
+                version,
+                path: path.to_string(),
+                args: args.to_vec(),
+            },
+            has_types,
+            _library: library,
+        });
+        Ok(name)
+    }
+
+    /// 卸载模块并注销它的命令；注册了值类型的模块不能卸载
+    pub fn unload(&self, name: &str) -> RedisResult<()> {
+        let mut registry = self.write();
+        let index = registry
+            .modules
+            .iter()
+            .position(|module| module.info.name == name)
+            .ok_or_else(|| RedisError::Module(format!("没有名为 {} 的模块", name)))?;
+        if registry.modules[index].has_types {
+            return Err(RedisError::Module(format!(
+                "模块 {} 注册了值类型，不能卸载",
+                name
+            )));
+        }
+        registry.modules.remove(index);
+        registry
+            .commands
+            .retain(|_, command| command.module != name);
+        Ok(())
+    }
+
+    /// 已加载的模块，按加载顺序
+    pub fn list(&self) -> Vec<ModuleInfo> {
+        self.read()
+            .modules
+            .iter()
+            .map(|module| module.info.clone())
+            .collect()
+    }
+
+    /// 是否有模块注册了该命令(大写)
+    pub fn contains(&self, command: &str) -> bool {
+        self.read().commands.contains_key(command)
+    }
+
+    /// 执行模块命令；没有模块注册该命令时返回None
+    ///
+    /// 执行期间不持有注册表的锁，命令中可以再访问Store，也不会阻塞MODULE LOAD
+    pub fn call(&self, store: &Store, command: &str, args: &[Vec<u8>]) -> Option<RespValue> {
+        let (handler, types) = {
+            let registry = self.read();
+            (
+                registry.commands.get(command)?.clone(),
+                registry.types.clone(),
+            )
+        };
+        let argv: Vec<ModuleStr> = std::iter::once(command.as_bytes())
+            .chain(args.iter().map(Vec::as_slice))
+            .map(ModuleStr::new)
+            .collect();
+        let mut ctx = CallContext {
+            store,
+            types: &types,
+            buffers: Vec::new(),
+            reply: Reply::default(),
+        };
+        unsafe { (handler.func)(&mut ctx, &API, argv.as_ptr(), argv.len()) };
+        drop(handler.library);
+        Some(ctx.reply.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(text: &str) -> ModuleStr {
+        ModuleStr::new(text.as_bytes())
+    }
+
+    /// COUNTER.INCR key: 计数保存在模块类型counter的值中(8字节小端)
+    unsafe extern "C" fn counter_incr(
+        ctx: *mut CallContext,
+        api: *const ModuleApi,
+        argv: *const ModuleStr,
+        argc: usize,
+    ) {
+        let api = &*api;
+        let argv = std::slice::from_raw_parts(argv, argc);
+        if argc != 2 {
+            return (api.reply_error)(ctx, s("ERR wrong number of arguments"));
+        }
+        let mut current = ModuleStr::new(&[]);
+        let count = match (api.get_value)(ctx, 0, argv[1], &mut current) {
+            STATUS_OK => i64::from_le_bytes(current.as_bytes().try_into().unwrap_or_default()),
+            STATUS_MISSING => 0,
+            _ => return (api.reply_error)(ctx, s("WRONGTYPE not a counter")),
+        } + 1;
+        (api.set_value)(ctx, 0, argv[1], ModuleStr::new(&count.to_le_bytes()));
+        (api.reply_integer)(ctx, count);
+    }
+
+    /// COUNTER.ECHO arg...: 返回参数数组，测试嵌套回复
+    unsafe extern "C" fn counter_echo(
+        ctx: *mut CallContext,
+        api: *const ModuleApi,
+        argv: *const ModuleStr,
+        argc: usize,
+    ) {
+        let api = &*api;
+        (api.reply_array)(ctx, 2);
+        (api.reply_simple)(ctx, s("args"));
+        (api.reply_array)(ctx, argc - 1);
+        for arg in &std::slice::from_raw_parts(argv, argc)[1..] {
+            (api.reply_bulk)(ctx, *arg);
+        }
+    }
+
+    unsafe extern "C" fn counter_init(
+        ctx: *mut LoadContext,
+        api: *const ModuleApi,
+        _: *const ModuleStr,
+        _: usize,
+    ) -> i32 {
+        let api = &*api;
+        (api.set_name)(ctx, s("counter"), 1);
+        (api.register_command)(ctx, s("counter.incr"), counter_incr);
+        (api.register_command)(ctx, s("counter.echo"), counter_echo);
+        match (api.register_type)(ctx, s("counter")) {
+            0 => STATUS_OK,
+            _ => STATUS_ERR,
+        }
+    }
+
+    /// 没有值类型的模块，可以卸载；第一个参数作为回复
+    unsafe extern "C" fn greet(
+        ctx: *mut CallContext,
+        api: *const ModuleApi,
+        _: *const ModuleStr,
+        _: usize,
+    ) {
+        let api = &*api;
+        let mut value = ModuleStr::new(&[]);
+        match (api.get_string)(ctx, s("greeting"), &mut value) {
+            STATUS_OK => (api.reply_bulk)(ctx, value),
+            _ => (api.reply_null)(ctx),
+        }
+    }
+
+    unsafe extern "C" fn greet_init(
+        ctx: *mut LoadContext,
+        api: *const ModuleApi,
+        _: *const ModuleStr,
+        _: usize,
+    ) -> i32 {
+        ((*api).set_name)(ctx, s("greet"), 3);
+        ((*api).register_command)(ctx, s("GREET"), greet)
+    }
+
+    /// 试图覆盖内置命令的模块
+    unsafe extern "C" fn bad_init(
+        ctx: *mut LoadContext,
+        api: *const ModuleApi,
+        _: *const ModuleStr,
+        _: usize,
+    ) -> i32 {
+        ((*api).set_name)(ctx, s("bad"), 1);
+        ((*api).register_command)(ctx, s("GREET.OK"), greet);
+        ((*api).register_command)(ctx, s("get"), greet);
+        STATUS_OK
+    }
+
+    #[test]
+    fn test_commands_and_types() {
+        let store = Store::new();
+        let modules = ModuleRegistry::default();
+        let args = vec!["a".to_string()];
+        assert_eq!(
+            modules
+                .register("counter.so", &args, counter_init, None)
+                .unwrap(),
+            "counter"
+        );
+
+        let call = |args: &[&str]| {
+            let argv: Vec<Vec<u8>> = args[1..]
+                .iter()
+                .map(|arg| arg.as_bytes().to_vec())
+                .collect();
+            modules.call(&store, args[0], &argv)
+        };
+        assert_eq!(call(&["COUNTER.INCR", "c"]), Some(RespValue::Integer(1)));
+        assert_eq!(call(&["COUNTER.INCR", "c"]), Some(RespValue::Integer(2)));
+        assert_eq!(store.key_type("c"), Some("counter"));
+        // 字符串键上执行模块命令，以及对模块类型执行字符串命令，都是类型错误
+        store.set("s".to_string(), b"1".to_vec());
+        assert!(
+            matches!(call(&["COUNTER.INCR", "s"]), Some(RespValue::Error(e)) if e.starts_with("WRONGTYPE"))
+        );
+        assert!(matches!(store.get_checked("c"), Err(RedisError::WrongType)));
+        assert!(store.memory_usage("c").is_some());
+
+        assert_eq!(
+            call(&["COUNTER.ECHO", "x", "y"]),
+            Some(RespValue::Array(vec![
+                RespValue::SimpleString("args".to_string()),
+                RespValue::Array(vec![
+                    RespValue::BulkString(b"x".to_vec()),
+                    RespValue::BulkString(b"y".to_vec())
+                ]),
+            ]))
+        );
+        assert_eq!(call(&["NOPE"]), None);
+
+        assert_eq!(
+            modules.list(),
+            [ModuleInfo {
+                name: "counter".to_string(),
+                version: 1,
+                path: "counter.so".to_string(),
+                args
+            }]
+        );
+        // 再次加载同名模块或注册已有的命令都会失败
+        assert!(modules
+            .register("other.so", &[], counter_init, None)
+            .is_err());
+        // 有值类型的模块不能卸载
+        assert!(modules.unload("counter").is_err());
+        assert!(modules.contains("COUNTER.INCR"));
+    }
+
+    #[test]
+    fn test_unload() {
+        let store = Store::new();
+        let modules = ModuleRegistry::default();
+        modules.register("greet.so", &[], greet_init, None).unwrap();
+        assert_eq!(modules.call(&store, "GREET", &[]), Some(RespValue::Null));
+        store.set("greeting".to_string(), b"hi".to_vec());
+        assert_eq!(
+            modules.call(&store, "GREET", &[]),
+            Some(RespValue::BulkString(b"hi".to_vec()))
+        );
+
+        modules.unload("greet").unwrap();
+        assert!(!modules.contains("GREET"));
+        assert!(modules.list().is_empty());
+        assert!(modules.unload("greet").is_err());
+    }
+
+    #[test]
+    fn test_failed_load_registers_nothing() {
+        let modules = ModuleRegistry::default();
+        let err = modules.register("bad.so", &[], bad_init, None).unwrap_err();
+        assert!(err.to_string().contains("GET"));
+        assert!(!modules.contains("GREET.OK"));
+        assert!(modules.list().is_empty());
+
+        #[cfg(unix)]
+        assert!(modules.load("/nonexistent/module.so", &[]).is_err());
+    }
+
+    #[test]
+    fn test_incomplete_reply() {
+        let mut reply = Reply::default();
+        reply.array(2);
+        reply.push(RespValue::Integer(1));
+        assert!(matches!(reply.finish(), RespValue::Error(_)));
+
+        let mut reply = Reply::default();
+        reply.array(0);
+        reply.push(RespValue::Null);
+        assert_eq!(reply.finish(), RespValue::Array(Vec::new()));
+    }
+}
@@ -0,0 +1,119 @@
+//! MONITOR输出解析 - redis-client的监视模式和redis-replay共用
+//!
+//! 每行形如`1339518083.107412 [0 127.0.0.1:60866] "SET" "k" "v"`，
+//! 参数按redis的sdscatrepr规则转义(`\"`、`\\`、`\n`、`\xHH`等)；
+//! Lua脚本执行的命令客户端地址为`lua`
+//!
+//! Rust特点展示:
+//! - 返回借用自输入行的&str，解析时不复制客户端地址
+//! - Option配合?在任何一步无法识别时直接返回None
+
+/// 一行MONITOR输出
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorLine<'a> {
+    /// 执行时间(unix秒，精确到微秒)
+    pub timestamp: f64,
+    /// 执行时所在的数据库
+    pub db: Option<u32>,
+    /// 客户端地址；Lua脚本执行的命令为`lua`，Unix socket连接为`unix:<路径>`
+    pub client: &'a str,
+    /// 命令和参数，已还原转义
+    pub args: Vec<Vec<u8>>,
+}
+
+impl MonitorLine<'_> {
+    /// 是否是Lua脚本中执行的命令
+    pub fn is_lua(&self) -> bool {
+        self.client == "lua"
+    }
+}
+
+/// 解析一行MONITOR输出，无法识别时返回None
+pub fn parse_line(line: &str) -> Option<MonitorLine<'_>> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    let timestamp: f64 = timestamp.parse().ok()?;
+    let (client, args) = rest.strip_prefix('[')?.split_once("] ")?;
+    let (db, client) = client.split_once(' ')?;
+
+    let args = unquote_args(args)?;
+    if args.is_empty() {
+        return None;
+    }
+    Some(MonitorLine {
+        timestamp,
+        db: db.parse().ok(),
+        client,
+        args,
+    })
+}
+
+/// 拆分`"SET" "k" "v"`形式的参数并还原转义
+pub fn unquote_args(input: &str) -> Option<Vec<Vec<u8>>> {
+    let bytes = input.as_bytes();
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while bytes.get(i) == Some(&b' ') {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return Some(args),
+            Some(b'"') => i += 1,
+            Some(_) => return None,
+        }
+
+        let mut arg = Vec::new();
+        loop {
+            match *bytes.get(i)? {
+                b'"' => {
+                    i += 1;
+                    break;
+                }
+                b'\\' => {
+                    let escaped = *bytes.get(i + 1)?;
+                    i += 2;
+                    let byte = match escaped {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'a' => 0x07,
+                        b'b' => 0x08,
+                        b'x' => {
+                            let hex = input.get(i..i + 2)?;
+                            i += 2;
+                            u8::from_str_radix(hex, 16).ok()?
+                        }
+                        other => other,
+                    };
+                    arg.push(byte);
+                }
+                byte => {
+                    arg.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let line = parse_line(r#"1339518083.107412 [0 127.0.0.1:60866] "SET" "a \"b\"" "\x00\n""#)
+            .unwrap();
+        assert_eq!(line.timestamp, 1339518083.107412);
+        assert_eq!(line.db, Some(0));
+        assert_eq!(line.client, "127.0.0.1:60866");
+        assert_eq!(line.args, [&b"SET"[..], b"a \"b\"", b"\x00\n"]);
+        assert!(!line.is_lua());
+
+        assert!(parse_line(r#"1339518083.107412 [0 lua] "GET" "k""#).unwrap().is_lua());
+        assert!(parse_line("OK").is_none());
+        assert!(parse_line(r#"1339518083.1 [0 127.0.0.1:1] "unterminated"#).is_none());
+    }
+}
@@ -0,0 +1,412 @@
+//! 概率数据结构模块 - BF.*命令使用的布隆过滤器和CF.*命令使用的布谷鸟过滤器
+//!
+//! 概率数据结构用很少的内存回答"某个元素是否出现过":
+//! 可能误判存在，但不会把已添加的元素判为不存在。
+//! 适合去重检查这类不需要保存完整集合的场景。
+//!
+//! - 布隆过滤器是可扩展的: 当前层装满后追加一层容量更大、误判率更低的新层，
+//!   总误判率仍然不超过设定值。
+//! - 布谷鸟过滤器在桶中保存元素的指纹，因此支持删除；
+//!   桶满时把已有指纹踢到它的另一个候选桶，仍然放不下时追加一张新表。
+//!
+//! Rust特点展示:
+//! - 位运算操作`Vec<u64>`位图
+//! - 自己实现的确定性哈希，保证同一元素在任何版本、任何进程中位置相同
+//! - 迭代器`any`/`all`短路求值
+//! - 固定长度数组`[u16; N]`作为桶
+
+use crate::error::{RedisError, RedisResult};
+use std::f64::consts::LN_2;
+
+/// 未预先BF.RESERVE时自动创建的过滤器的误判率
+pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+/// 未预先BF.RESERVE时自动创建的过滤器的初始容量
+pub const DEFAULT_BLOOM_CAPACITY: u64 = 100;
+/// 自动创建的布谷鸟过滤器每张表的容量
+pub const DEFAULT_CUCKOO_CAPACITY: u64 = 1024;
+/// 默认每次扩展时新层容量的倍数
+pub const DEFAULT_EXPANSION: u32 = 2;
+
+/// 新层误判率相对上一层的比例，保证各层误判率之和收敛到设定值
+const TIGHTENING_RATIO: f64 = 0.5;
+
+/// FNV-1a哈希，`seed`区分两个独立的哈希函数
+///
+/// 不使用`DefaultHasher`: 它的算法不保证跨Rust版本稳定，而过滤器的位图需要长期有效
+fn hash(item: &[u8], seed: u64) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    item.iter()
+        .fold(OFFSET ^ seed, |h, &b| (h ^ b as u64).wrapping_mul(PRIME))
+}
+
+/// 元素的两个基础哈希，其他哈希由它们组合得到(双重哈希)
+fn hash_pair(item: &[u8]) -> (u64, u64) {
+    // 第二个哈希必须是奇数，避免步长与位图大小有公因数时只覆盖部分位置
+    (hash(item, 0), hash(item, 0x9e37_79b9_7f4a_7c15) | 1)
+}
+
+/// 可扩展布隆过滤器的一层
+#[derive(Debug, Clone, PartialEq)]
+struct BloomLayer {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    capacity: u64,
+    count: u64,
+}
+
+impl BloomLayer {
+    /// 按容量和误判率计算位图大小和哈希函数个数
+    fn new(capacity: u64, error_rate: f64) -> Self {
+        let num_bits = ((-(capacity as f64) * error_rate.ln()) / (LN_2 * LN_2)).ceil().max(64.0) as u64;
+        let num_hashes = (-error_rate.log2()).ceil().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            capacity,
+            count: 0,
+        }
+    }
+
+    /// 元素在位图中的所有位置
+    fn positions(&self, (h1, h2): (u64, u64)) -> impl Iterator<Item = u64> + '_ {
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    fn contains(&self, hashes: (u64, u64)) -> bool {
+        self.positions(hashes)
+            .all(|pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+
+    fn insert(&mut self, hashes: (u64, u64)) {
+        let positions: Vec<u64> = self.positions(hashes).collect();
+        for pos in positions {
+            self.bits[(pos / 64) as usize] |= 1 << (pos % 64);
+        }
+        self.count += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.count >= self.capacity
+    }
+}
+
+/// 可扩展布隆过滤器
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    layers: Vec<BloomLayer>,
+    error_rate: f64,
+    /// 新层容量相对上一层的倍数，0表示不扩展(NONSCALING)
+    expansion: u32,
+}
+
+impl BloomFilter {
+    /// 创建过滤器；误判率必须在(0, 1)之间，容量必须大于0
+    pub fn new(error_rate: f64, capacity: u64, expansion: u32) -> RedisResult<Self> {
+        if !(error_rate > 0.0 && error_rate < 1.0) {
+            return Err(RedisError::InvalidArgument("(0 < error rate range < 1)".to_string()));
+        }
+        if capacity == 0 {
+            return Err(RedisError::InvalidArgument("(capacity should be larger than 0)".to_string()));
+        }
+        Ok(Self {
+            layers: vec![BloomLayer::new(capacity, error_rate * TIGHTENING_RATIO)],
+            error_rate,
+            expansion,
+        })
+    }
+
+    /// 添加元素，返回元素之前是否不存在
+    ///
+    /// 当前层已满时追加新层；不可扩展的过滤器满了返回错误
+    pub fn add(&mut self, item: &[u8]) -> RedisResult<bool> {
+        let hashes = hash_pair(item);
+        if self.layers.iter().any(|layer| layer.contains(hashes)) {
+            return Ok(false);
+        }
+
+        let last = self.layers.last().expect("过滤器至少有一层");
+        if last.is_full() {
+            if self.expansion == 0 {
+                return Err(RedisError::InvalidArgument("non scaling filter is full".to_string()));
+            }
+            let capacity = last.capacity.saturating_mul(self.expansion as u64);
+            let error_rate = self.error_rate * TIGHTENING_RATIO.powi(self.layers.len() as i32 + 1);
+            self.layers.push(BloomLayer::new(capacity, error_rate));
+        }
+        self.layers.last_mut().expect("过滤器至少有一层").insert(hashes);
+        Ok(true)
+    }
+
+    /// 元素是否可能存在
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let hashes = hash_pair(item);
+        self.layers.iter().any(|layer| layer.contains(hashes))
+    }
+
+    /// 已添加的元素数
+    pub fn len(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.count).sum()
+    }
+
+    /// 是否没有添加过元素
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 层数
+    pub fn layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        self.layers.iter().map(|layer| layer.bits.capacity() * 8).sum()
+    }
+}
+
+/// 布谷鸟过滤器每个桶的指纹数
+const BUCKET_SIZE: usize = 4;
+/// 插入时最多踢出已有指纹的次数
+const MAX_KICKS: usize = 500;
+/// 最多的表数，超过后过滤器视为已满
+const MAX_TABLES: usize = 32;
+/// 由指纹计算候选桶时使用的哈希种子
+const FINGERPRINT_SEED: u64 = 0x5bd1_e995;
+
+/// 指纹的另一个候选桶，对两个候选桶互为逆运算
+fn alt_index(index: usize, fingerprint: u16, num_buckets: usize) -> usize {
+    (index ^ hash(&fingerprint.to_le_bytes(), FINGERPRINT_SEED) as usize) & (num_buckets - 1)
+}
+
+/// 布谷鸟过滤器的一张表
+#[derive(Debug, Clone, PartialEq)]
+struct CuckooTable {
+    /// 每个桶保存若干16位指纹，0表示空位
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    count: u64,
+}
+
+impl CuckooTable {
+    fn new(num_buckets: usize) -> Self {
+        Self {
+            buckets: vec![[0; BUCKET_SIZE]; num_buckets],
+            count: 0,
+        }
+    }
+
+    /// 放入桶中的空位，桶满时返回false
+    fn put(&mut self, index: usize, fingerprint: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == 0) {
+            Some(slot) => {
+                *slot = fingerprint;
+                self.count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn contains(&self, index: usize, fingerprint: u16) -> bool {
+        self.buckets[index].contains(&fingerprint)
+    }
+
+    /// 从桶中删除一个指纹
+    fn remove(&mut self, index: usize, fingerprint: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == fingerprint) {
+            Some(slot) => {
+                *slot = 0;
+                self.count -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 布谷鸟过滤器
+///
+/// 每个元素对应一个指纹和两个候选桶，任一个桶可以由另一个桶和指纹算出，
+/// 所以踢出指纹时不需要原始元素。所有表的桶数相同，被踢出的指纹可以原样放进新表
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuckooFilter {
+    tables: Vec<CuckooTable>,
+    /// 每张表的桶数，是2的幂
+    num_buckets: usize,
+}
+
+impl CuckooFilter {
+    /// 创建每张表容量为`capacity`的过滤器
+    pub fn new(capacity: u64) -> RedisResult<Self> {
+        if capacity == 0 {
+            return Err(RedisError::InvalidArgument("(capacity should be larger than 0)".to_string()));
+        }
+        let num_buckets = (capacity as usize).div_ceil(BUCKET_SIZE).next_power_of_two();
+        Ok(Self {
+            tables: vec![CuckooTable::new(num_buckets)],
+            num_buckets,
+        })
+    }
+
+    /// 元素的指纹和第一个候选桶
+    fn locate(&self, item: &[u8]) -> (u16, usize) {
+        let h = hash(item, 0);
+        // 指纹取高16位，0表示空位所以不能使用
+        let fingerprint = ((h >> 48) as u16).max(1);
+        (fingerprint, h as usize & (self.num_buckets - 1))
+    }
+
+    /// 添加元素；与布隆过滤器不同，重复添加同一元素会保存多份，需要删除同样次数
+    pub fn add(&mut self, item: &[u8]) -> RedisResult<()> {
+        let (mut fingerprint, i1) = self.locate(item);
+        let i2 = alt_index(i1, fingerprint, self.num_buckets);
+        for table in &mut self.tables {
+            if table.put(i1, fingerprint) || table.put(i2, fingerprint) {
+                return Ok(());
+            }
+        }
+
+        // 踢出链失败时需要追加新表，表数已达上限时不修改过滤器直接报错
+        if self.tables.len() >= MAX_TABLES {
+            return Err(RedisError::InvalidArgument("Filter is full".to_string()));
+        }
+
+        // 所有表的两个候选桶都满了: 在最新的表中踢出已有指纹，为它寻找另一个候选桶
+        let mut index = i1;
+        let table = self.tables.last_mut().expect("过滤器至少有一张表");
+        for kick in 0..MAX_KICKS {
+            std::mem::swap(&mut table.buckets[index][kick % BUCKET_SIZE], &mut fingerprint);
+            index = alt_index(index, fingerprint, self.num_buckets);
+            if table.put(index, fingerprint) {
+                return Ok(());
+            }
+        }
+
+        // 仍然放不下: 被踢出的最后一个指纹放进新表的同一个桶
+        let mut table = CuckooTable::new(self.num_buckets);
+        table.put(index, fingerprint);
+        self.tables.push(table);
+        Ok(())
+    }
+
+    /// 元素是否可能存在
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (fingerprint, i1) = self.locate(item);
+        let i2 = alt_index(i1, fingerprint, self.num_buckets);
+        self.tables
+            .iter()
+            .any(|table| table.contains(i1, fingerprint) || table.contains(i2, fingerprint))
+    }
+
+    /// 删除元素的一份，返回是否找到
+    ///
+    /// 只能删除确实添加过的元素，否则可能误删指纹相同的其他元素
+    pub fn delete(&mut self, item: &[u8]) -> bool {
+        let (fingerprint, i1) = self.locate(item);
+        let i2 = alt_index(i1, fingerprint, self.num_buckets);
+        self.tables
+            .iter_mut()
+            .rev()
+            .any(|table| table.remove(i1, fingerprint) || table.remove(i2, fingerprint))
+    }
+
+    /// 保存的元素数
+    pub fn len(&self) -> u64 {
+        self.tables.iter().map(|table| table.count).sum()
+    }
+
+    /// 是否没有元素
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 表数
+    pub fn tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|table| table.buckets.capacity() * std::mem::size_of::<[u16; BUCKET_SIZE]>())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(0.01, 100, DEFAULT_EXPANSION).unwrap();
+        assert!(filter.add(b"a").unwrap());
+        assert!(!filter.add(b"a").unwrap());
+        assert!(filter.contains(b"a"));
+        assert!(!filter.contains(b"b"));
+
+        assert!(BloomFilter::new(1.0, 100, 2).is_err());
+        assert!(BloomFilter::new(0.01, 0, 2).is_err());
+    }
+
+    #[test]
+    fn test_bloom_filter_scaling() {
+        let mut filter = BloomFilter::new(0.01, 100, DEFAULT_EXPANSION).unwrap();
+        for i in 0..1000 {
+            filter.add(format!("item:{}", i).as_bytes()).unwrap();
+        }
+        assert!(filter.layers() > 1);
+        // 已添加的元素不会被判为不存在
+        assert!((0..1000).all(|i| filter.contains(format!("item:{}", i).as_bytes())));
+
+        // 扩展后总误判率仍在设定值附近
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(format!("other:{}", i).as_bytes()))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+
+        let mut fixed = BloomFilter::new(0.01, 10, 0).unwrap();
+        for i in 0..10 {
+            fixed.add(format!("item:{}", i).as_bytes()).unwrap();
+        }
+        assert!(fixed.add(b"one more").is_err());
+    }
+
+    #[test]
+    fn test_cuckoo_filter() {
+        let mut filter = CuckooFilter::new(1024).unwrap();
+        filter.add(b"a").unwrap();
+        filter.add(b"a").unwrap();
+        assert!(filter.contains(b"a"));
+        assert!(!filter.contains(b"b"));
+
+        // 重复添加的元素需要删除同样次数
+        assert!(filter.delete(b"a"));
+        assert!(filter.contains(b"a"));
+        assert!(filter.delete(b"a"));
+        assert!(!filter.contains(b"a"));
+        assert!(!filter.delete(b"a"));
+        assert!(filter.is_empty());
+        assert!(CuckooFilter::new(0).is_err());
+    }
+
+    #[test]
+    fn test_cuckoo_filter_expansion() {
+        let mut filter = CuckooFilter::new(64).unwrap();
+        for i in 0..500 {
+            filter.add(format!("item:{}", i).as_bytes()).unwrap();
+        }
+        assert!(filter.tables() > 1);
+        assert_eq!(filter.len(), 500);
+        // 踢出和扩展都不会丢失已添加的元素
+        assert!((0..500).all(|i| filter.contains(format!("item:{}", i).as_bytes())));
+
+        for i in 0..500 {
+            assert!(filter.delete(format!("item:{}", i).as_bytes()));
+        }
+        assert!(filter.is_empty());
+    }
+}
@@ -0,0 +1,397 @@
+//! 快速列表模块 - L*命令使用的分段列表
+//!
+//! 与Redis的quicklist一样，列表由多个小块(chunk)组成，每块最多`CHUNK_SIZE`个元素:
+//! - 两端插入和弹出只涉及首尾的块，均摊O(1)
+//! - 按下标访问先跳过整块再在块内定位，从离下标较近的一端开始查找
+//! - 中间插入只移动一个块内的元素，块满时先尝试放入前一块，否则一分为二
+//! - 删除元素后少于`CHUNK_SIZE / 4`个元素的块与相邻的块合并，块数不会只增不减
+//! - LRANGE等范围读取只遍历需要的块，不复制整个列表
+//!
+//! Rust特点展示:
+//! - `VecDeque<VecDeque<_>>`嵌套，外层和内层都支持两端操作
+//! - `flatten`把块的迭代器展开成元素的双端迭代器
+//! - `RangeBounds`让`range`同时接受`a..b`和`a..=b`
+
+use std::collections::VecDeque;
+use std::ops::{Bound, RangeBounds};
+
+/// 每块的最大元素数
+pub const CHUNK_SIZE: usize = 128;
+
+/// 元素数少于它的块与相邻的块合并
+const MIN_CHUNK_SIZE: usize = CHUNK_SIZE / 4;
+
+/// 分段存储的列表，块不为空
+#[derive(Debug, Clone, Default)]
+pub struct QuickList {
+    chunks: VecDeque<VecDeque<Vec<u8>>>,
+    len: usize,
+}
+
+impl QuickList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 元素数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 块数
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn push_front(&mut self, item: Vec<u8>) {
+        match self.chunks.front_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => chunk.push_front(item),
+            _ => self.chunks.push_front(VecDeque::from([item])),
+        }
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, item: Vec<u8>) {
+        match self.chunks.back_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => chunk.push_back(item),
+            _ => self.chunks.push_back(VecDeque::from([item])),
+        }
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<Vec<u8>> {
+        let chunk = self.chunks.front_mut()?;
+        let item = chunk.pop_front();
+        if chunk.is_empty() {
+            self.chunks.pop_front();
+        }
+        self.len -= 1;
+        item
+    }
+
+    pub fn pop_back(&mut self) -> Option<Vec<u8>> {
+        let chunk = self.chunks.back_mut()?;
+        let item = chunk.pop_back();
+        if chunk.is_empty() {
+            self.chunks.pop_back();
+        }
+        self.len -= 1;
+        item
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Vec<u8>> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get(offset)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Vec<u8>> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].get_mut(offset)
+    }
+
+    /// 在`index`处插入元素，`index == len`时插入到末尾
+    ///
+    /// 所在的块满时，插入位置在块的开头(即两块之间)且前一块未满就放到前一块的末尾，
+    /// 否则把块一分为二
+    ///
+    /// # Panics
+    /// `index > len`时panic，与`VecDeque::insert`一致
+    pub fn insert(&mut self, index: usize, item: Vec<u8>) {
+        assert!(index <= self.len, "index out of bounds");
+        if index == self.len {
+            self.push_back(item);
+            return;
+        }
+        let Some((mut chunk, mut offset)) = self.locate(index) else {
+            return;
+        };
+        if self.chunks[chunk].len() >= CHUNK_SIZE {
+            // 插入位置在两块之间，前一块还有空间
+            if offset == 0 && chunk > 0 && self.chunks[chunk - 1].len() < CHUNK_SIZE {
+                self.chunks[chunk - 1].push_back(item);
+                self.len += 1;
+                return;
+            }
+            let tail = self.chunks[chunk].split_off(CHUNK_SIZE / 2);
+            self.chunks.insert(chunk + 1, tail);
+            if offset >= CHUNK_SIZE / 2 {
+                chunk += 1;
+                offset -= CHUNK_SIZE / 2;
+            }
+        }
+        self.chunks[chunk].insert(offset, item);
+        self.len += 1;
+    }
+
+    /// 从头到尾遍历元素
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Vec<u8>> {
+        self.chunks.iter().flatten()
+    }
+
+    /// 遍历下标范围内的元素，只访问范围所在的块；超出长度的部分被忽略
+    pub fn range(&self, range: impl RangeBounds<usize>) -> impl Iterator<Item = &Vec<u8>> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len,
+        }
+        .min(self.len);
+        let (chunk, offset) = self.locate(start).unwrap_or((self.chunks.len(), 0));
+        self.chunks
+            .range(chunk..)
+            .flatten()
+            .skip(offset)
+            .take(end.saturating_sub(start))
+    }
+
+    /// 只保留`f`返回true的元素，按从头到尾的顺序调用
+    pub fn retain(&mut self, mut f: impl FnMut(&Vec<u8>) -> bool) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.retain(&mut f);
+        }
+        self.len = self.chunks.iter().map(VecDeque::len).sum();
+        self.merge_small_chunks();
+    }
+
+    /// 只保留前`len`个元素
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            let Some(chunk) = self.chunks.back_mut() else {
+                break;
+            };
+            let excess = self.len - len;
+            if chunk.len() <= excess {
+                self.len -= chunk.len();
+                self.chunks.pop_back();
+            } else {
+                chunk.truncate(chunk.len() - excess);
+                self.len = len;
+            }
+        }
+    }
+
+    /// 只保留最后`len`个元素
+    pub fn truncate_front(&mut self, len: usize) {
+        while self.len > len {
+            let Some(chunk) = self.chunks.front_mut() else {
+                break;
+            };
+            let excess = self.len - len;
+            if chunk.len() <= excess {
+                self.len -= chunk.len();
+                self.chunks.pop_front();
+            } else {
+                chunk.drain(..excess);
+                self.len = len;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    /// 去掉空块，并把少于`MIN_CHUNK_SIZE`个元素的块并入前一块，合并后不超过`CHUNK_SIZE`
+    ///
+    /// 只遍历一次块列表，每个元素最多移动一次
+    fn merge_small_chunks(&mut self) {
+        let mut merged: VecDeque<VecDeque<Vec<u8>>> = VecDeque::with_capacity(self.chunks.len());
+        for mut chunk in self.chunks.drain(..) {
+            if chunk.is_empty() {
+                continue;
+            }
+            if let Some(last) = merged.back_mut() {
+                let small = last.len() < MIN_CHUNK_SIZE || chunk.len() < MIN_CHUNK_SIZE;
+                if small && last.len() + chunk.len() <= CHUNK_SIZE {
+                    last.append(&mut chunk);
+                    continue;
+                }
+            }
+            merged.push_back(chunk);
+        }
+        self.chunks = merged;
+    }
+
+    /// 下标所在的(块, 块内偏移)，从离下标较近的一端开始查找
+    fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        if index < self.len / 2 {
+            let mut offset = index;
+            for (i, chunk) in self.chunks.iter().enumerate() {
+                if offset < chunk.len() {
+                    return Some((i, offset));
+                }
+                offset -= chunk.len();
+            }
+        } else {
+            // 从尾部数起的偏移
+            let mut offset = self.len - 1 - index;
+            for (i, chunk) in self.chunks.iter().enumerate().rev() {
+                if offset < chunk.len() {
+                    return Some((i, chunk.len() - 1 - offset));
+                }
+                offset -= chunk.len();
+            }
+        }
+        None
+    }
+}
+
+/// 元素相同即相等，与分块方式无关
+impl PartialEq for QuickList {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl FromIterator<Vec<u8>> for QuickList {
+    fn from_iter<I: IntoIterator<Item = Vec<u8>>>(iter: I) -> Self {
+        let mut list = Self::new();
+        for item in iter {
+            list.push_back(item);
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(i: usize) -> Vec<u8> {
+        i.to_string().into_bytes()
+    }
+
+    /// 与VecDeque的结果逐个比较
+    fn assert_same(list: &QuickList, expected: &VecDeque<Vec<u8>>) {
+        assert_eq!(list.len(), expected.len());
+        assert!(list.iter().eq(expected.iter()));
+        assert!(list.iter().rev().eq(expected.iter().rev()));
+        for i in [0, expected.len() / 3, expected.len().saturating_sub(1), expected.len()] {
+            assert_eq!(list.get(i), expected.get(i), "index {}", i);
+        }
+    }
+
+    /// 相邻的两块中有一块过小时，合起来一定超过CHUNK_SIZE
+    fn assert_no_mergeable_chunks(list: &QuickList) {
+        assert!(list.chunks.iter().all(|chunk| !chunk.is_empty() && chunk.len() <= CHUNK_SIZE));
+        for pair in list.chunks.iter().collect::<Vec<_>>().windows(2) {
+            let (a, b) = (pair[0].len(), pair[1].len());
+            assert!(a.min(b) >= MIN_CHUNK_SIZE || a + b > CHUNK_SIZE, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_push_pop_across_chunks() {
+        let mut list = QuickList::new();
+        let mut expected = VecDeque::new();
+        for i in 0..1000 {
+            if i % 3 == 0 {
+                list.push_front(item(i));
+                expected.push_front(item(i));
+            } else {
+                list.push_back(item(i));
+                expected.push_back(item(i));
+            }
+        }
+        assert_same(&list, &expected);
+        assert!(list.chunk_count() >= 1000 / CHUNK_SIZE);
+
+        for _ in 0..300 {
+            assert_eq!(list.pop_front(), expected.pop_front());
+            assert_eq!(list.pop_back(), expected.pop_back());
+        }
+        assert_same(&list, &expected);
+        while list.pop_back().is_some() {}
+        assert!(list.is_empty());
+        assert_eq!(list.chunk_count(), 0);
+        assert_eq!(list.pop_front(), None);
+    }
+
+    #[test]
+    fn test_insert_and_range() {
+        let mut list: QuickList = (0..500).map(item).collect();
+        let mut expected: VecDeque<Vec<u8>> = (0..500).map(item).collect();
+        // 反复插入同一个块，触发块的分裂
+        for i in 0..300 {
+            let index = 200 + i % 7;
+            list.insert(index, item(10_000 + i));
+            expected.insert(index, item(10_000 + i));
+        }
+        list.insert(list.len(), item(1));
+        expected.push_back(item(1));
+        assert_same(&list, &expected);
+        assert!(list.chunks.iter().all(|chunk| !chunk.is_empty() && chunk.len() <= CHUNK_SIZE));
+
+        assert!(list.range(150..=450).eq(expected.range(150..=450)));
+        assert!(list.range(790..).eq(expected.range(790..)));
+        assert_eq!(list.range(700..10_000).count(), expected.len() - 700);
+        assert_eq!(list.range(10_000..).count(), 0);
+
+        *list.get_mut(300).unwrap() = b"x".to_vec();
+        assert_eq!(list.get(300), Some(&b"x".to_vec()));
+    }
+
+    #[test]
+    fn test_retain_and_truncate() {
+        let mut list: QuickList = (0..1000).map(item).collect();
+        let mut expected: VecDeque<Vec<u8>> = (0..1000).map(item).collect();
+        list.retain(|item| item.len() != 2);
+        expected.retain(|item| item.len() != 2);
+        assert_same(&list, &expected);
+
+        assert_no_mergeable_chunks(&list);
+
+        list.truncate(500);
+        expected.truncate(500);
+        assert_same(&list, &expected);
+        list.truncate_front(333);
+        expected.drain(..expected.len() - 333);
+        assert_same(&list, &expected);
+        list.truncate_front(1000);
+        assert_eq!(list.len(), 333);
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.range(..).count(), 0);
+    }
+
+    #[test]
+    fn test_retain_merges_chunks() {
+        let mut list: QuickList = (0..10_000).map(item).collect();
+        let mut expected: VecDeque<Vec<u8>> = (0..10_000).map(item).collect();
+        assert_eq!(list.chunk_count(), 10_000_usize.div_ceil(CHUNK_SIZE));
+
+        // 每块只剩一两个元素，合并后块数与元素数相称
+        let keep = |item: &Vec<u8>| {
+            String::from_utf8_lossy(item).parse::<usize>().unwrap() % 50 == 0
+        };
+        list.retain(keep);
+        expected.retain(keep);
+        assert_same(&list, &expected);
+        assert_eq!(list.len(), 200);
+        assert!(list.chunk_count() <= 3, "{}", list.chunk_count());
+        assert_no_mergeable_chunks(&list);
+
+        // 在满块之间插入时先放入前一块的空位，不分裂块
+        let mut list: QuickList = (0..CHUNK_SIZE * 2).map(item).collect();
+        list.pop_front();
+        let chunks = list.chunk_count();
+        list.insert(CHUNK_SIZE - 1, item(0));
+        assert_eq!(list.chunk_count(), chunks);
+        assert_eq!(list.get(CHUNK_SIZE - 1), Some(&item(0)));
+        assert_eq!(list.get(CHUNK_SIZE), Some(&item(CHUNK_SIZE)));
+    }
+}
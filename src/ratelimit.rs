@@ -0,0 +1,125 @@
+//! 限流模块 - CL.THROTTLE使用的GCRA(通用信元速率算法)
+//!
+//! GCRA只为每个键保存一个"理论到达时间"(TAT): 按设定速率，下一个请求最早应该在何时到达。
+//! 请求到达时比较当前时间与TAT，允许突发`max_burst`个请求提前到达。
+//! 相比计数窗口，它不需要定时重置，也没有窗口边界处的突发问题。
+//!
+//! Rust特点展示:
+//! - 纯函数计算限流结果，由Store在写锁内原子地读取和写回TAT
+//! - 饱和运算(`saturating_*`)避免整数溢出
+
+use crate::error::{RedisError, RedisResult};
+use std::time::Duration;
+
+/// 限流参数: 每`period`允许`count`个请求，另外允许突发`max_burst`个
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    max_burst: u64,
+    /// 两个请求之间的理论间隔(毫秒)
+    emission_interval: u64,
+}
+
+/// 一次限流检查的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleResult {
+    /// 是否被限流
+    pub limited: bool,
+    /// 空闲时最多允许的请求数(`max_burst + 1`)
+    pub limit: u64,
+    /// 现在还允许的请求数
+    pub remaining: u64,
+    /// 被限流时，多久之后重试可以成功；请求超过突发上限时永远不会成功，为None
+    pub retry_after: Option<Duration>,
+    /// 多久之后恢复到完全空闲
+    pub reset_after: Duration,
+}
+
+impl Throttle {
+    /// 创建限流参数；`count`和`period`必须大于0
+    pub fn new(max_burst: u64, count: u64, period: Duration) -> RedisResult<Self> {
+        if count == 0 || period.is_zero() {
+            return Err(RedisError::InvalidArgument(
+                "count and period must be greater than 0".to_string(),
+            ));
+        }
+        // 以毫秒计算，间隔至少为1ms
+        let emission_interval = (period.as_millis() / count as u128).clamp(1, u64::MAX as u128) as u64;
+        Ok(Self {
+            max_burst,
+            emission_interval,
+        })
+    }
+
+    /// 允许的最大提前量: 突发`max_burst`个请求再加上当前这一个
+    fn tolerance(&self) -> u64 {
+        self.emission_interval.saturating_mul(self.max_burst.saturating_add(1))
+    }
+
+    /// 检查`quantity`个请求，返回结果和需要写回的新TAT(被限流时为None)
+    ///
+    /// `tat`是之前保存的理论到达时间，键不存在时为None
+    pub fn check(&self, tat: Option<u64>, now: u64, quantity: u64) -> (ThrottleResult, Option<u64>) {
+        let tolerance = self.tolerance();
+        let tat = tat.unwrap_or(now).max(now);
+        let increment = self.emission_interval.saturating_mul(quantity);
+        let new_tat = tat.saturating_add(increment);
+        // 新TAT减去容忍量就是允许这些请求的最早时刻
+        let allow_at = new_tat.saturating_sub(tolerance);
+
+        let (limited, retry_after, ttl, stored) = if now < allow_at {
+            // 请求数超过突发上限时，等多久都不会被允许
+            let retry_after = (increment <= tolerance).then(|| Duration::from_millis(allow_at - now));
+            (true, retry_after, tat - now, None)
+        } else {
+            (false, None, new_tat - now, Some(new_tat))
+        };
+
+        let result = ThrottleResult {
+            limited,
+            limit: self.max_burst.saturating_add(1),
+            remaining: tolerance.saturating_sub(ttl) / self.emission_interval,
+            retry_after,
+            reset_after: Duration::from_millis(ttl),
+        };
+        (result, stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcra() {
+        // 每秒10个请求(间隔100ms)，允许突发2个
+        let throttle = Throttle::new(2, 10, Duration::from_secs(1)).unwrap();
+        let mut tat = None;
+        let mut check = |now, quantity| {
+            let (result, stored) = throttle.check(tat, now, quantity);
+            tat = stored.or(tat);
+            result
+        };
+
+        // 空闲时一次最多允许3个
+        for remaining in [2, 1, 0] {
+            let result = check(0, 1);
+            assert!(!result.limited);
+            assert_eq!((result.limit, result.remaining), (3, remaining));
+        }
+        let result = check(0, 1);
+        assert!(result.limited);
+        assert_eq!(result.retry_after, Some(Duration::from_millis(100)));
+        assert_eq!(result.reset_after, Duration::from_millis(300));
+
+        // 过了一个间隔后又允许一个
+        assert!(!check(100, 1).limited);
+        assert!(check(100, 1).limited);
+
+        // 超过突发上限的请求永远不会被允许
+        let result = check(10_000, 4);
+        assert!(result.limited && result.retry_after.is_none());
+        assert!(!check(10_000, 3).limited);
+
+        assert!(Throttle::new(1, 0, Duration::from_secs(1)).is_err());
+    }
+}
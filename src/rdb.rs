@@ -0,0 +1,731 @@
+//! RDB文件解析 - 离线读取Redis生成的快照
+//!
+//! 本服务器不写RDB文件，这个模块用于检查和分析真实Redis生成的快照(版本1到12，即Redis 7.4及以前)。
+//! 解析只进行到能得到每个键的类型、编码、元素数和长度为止，不还原值的内容:
+//! - 紧凑编码(ziplist、listpack、intset、zipmap)作为整体读出，元素数取自它们的头部
+//! - LZF压缩的字符串会被解压，以便发现损坏的压缩数据
+//! - 文件末尾的CRC64校验和与Redis使用的Jones多项式一致
+//!
+//! Rust特点展示:
+//! - 切片加游标的零拷贝解析，`Cow`只在解压或整数编码时分配
+//! - 回调`FnMut`逐个交出键，不需要把整个数据集保存在内存中
+//! - `const fn`在编译期生成CRC查找表
+
+use std::borrow::Cow;
+use thiserror::Error;
+
+/// 支持的最高RDB版本
+pub const MAX_VERSION: u32 = 12;
+
+/// 解析错误，`offset`是出错位置在文件中的字节偏移
+#[derive(Debug, Error)]
+#[error("偏移 {offset}: {message}")]
+pub struct RdbError {
+    pub offset: usize,
+    pub message: String,
+}
+
+pub type RdbResult<T> = Result<T, RdbError>;
+
+/// 值的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RdbType {
+    String,
+    List,
+    Set,
+    ZSet,
+    Hash,
+    Stream,
+    Module,
+}
+
+impl RdbType {
+    /// 与TYPE命令的回复一致的类型名称
+    pub fn name(self) -> &'static str {
+        match self {
+            RdbType::String => "string",
+            RdbType::List => "list",
+            RdbType::Set => "set",
+            RdbType::ZSet => "zset",
+            RdbType::Hash => "hash",
+            RdbType::Stream => "stream",
+            RdbType::Module => "module",
+        }
+    }
+}
+
+/// 快照中的一个键
+#[derive(Debug, Clone)]
+pub struct RdbEntry {
+    pub db: u64,
+    pub key: Vec<u8>,
+    pub kind: RdbType,
+    /// 存储编码，与OBJECT ENCODING的名称一致，如`listpack`、`quicklist`、`hashtable`
+    pub encoding: &'static str,
+    /// 过期时刻(unix毫秒)
+    pub expires_at: Option<u64>,
+    /// 元素数: 字符串为1，哈希和有序集合为字段/成员数，流为消息数
+    pub elements: u64,
+    /// 值的数据在解压后的总字节数
+    pub value_len: u64,
+    /// 键和值在文件中占用的字节数
+    pub serialized_len: usize,
+}
+
+/// 文件末尾校验和的检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    /// 校验和正确
+    Valid,
+    /// 写入时关闭了rdbchecksum，校验和为0
+    Disabled,
+    /// 版本5以前的文件没有校验和
+    Absent,
+    /// 校验和不符
+    Mismatch { expected: u64, actual: u64 },
+}
+
+/// 整个文件的解析结果
+#[derive(Debug, Clone)]
+pub struct RdbSummary {
+    pub version: u32,
+    /// AUX字段，如redis-ver、ctime、used-mem
+    pub aux: Vec<(String, String)>,
+    pub keys: u64,
+    pub checksum: Checksum,
+    /// 快照占用的字节数(含校验和)，AOF的RDB前缀之后是普通命令
+    pub len: usize,
+}
+
+/// 解析快照，每读出一个键调用一次`visit`
+///
+/// 结构错误(截断、未知的类型、损坏的压缩数据)返回Err；
+/// 校验和不符不算解析错误，由调用者根据`RdbSummary::checksum`决定如何处理
+pub fn parse(data: &[u8], mut visit: impl FnMut(RdbEntry)) -> RdbResult<RdbSummary> {
+    let mut reader = Reader { data, pos: 0 };
+    let version = reader.header()?;
+    let mut summary = RdbSummary {
+        version,
+        aux: Vec::new(),
+        keys: 0,
+        checksum: Checksum::Absent,
+        len: 0,
+    };
+    let mut db = 0;
+    let mut expires_at = None;
+
+    loop {
+        let start = reader.pos;
+        match reader.u8()? {
+            // AUX: 两个字符串
+            0xFA => {
+                let name = reader.string()?;
+                let value = reader.string()?;
+                summary.aux.push((
+                    String::from_utf8_lossy(&name).into_owned(),
+                    String::from_utf8_lossy(&value).into_owned(),
+                ));
+            }
+            // RESIZEDB: 哈希表大小和带过期时间的键数
+            0xFB => {
+                reader.length()?;
+                reader.length()?;
+            }
+            // SELECTDB
+            0xFE => db = reader.length()?,
+            // EXPIRETIME(秒)
+            0xFD => expires_at = Some(u64::from(reader.u32_le()?) * 1000),
+            // EXPIRETIME_MS
+            0xFC => expires_at = Some(reader.u64_le()?),
+            // FREQ(LFU计数)
+            0xF9 => {
+                reader.u8()?;
+            }
+            // IDLE(LRU空闲时间)
+            0xF8 => {
+                reader.length()?;
+            }
+            // MODULE_AUX: 模块ID之后是模块自描述的数据
+            0xF7 => {
+                reader.length()?;
+                reader.module_value()?;
+            }
+            // FUNCTION2: 函数库的源码
+            0xF5 => {
+                reader.string()?;
+            }
+            0xF6 => return Err(reader.error_at(start, "不支持Redis 7.0预览版的函数格式")),
+            // SLOT_INFO: 槽位、键数和带过期时间的键数
+            0xF4 => {
+                reader.length()?;
+                reader.length()?;
+                reader.length()?;
+            }
+            // EOF
+            0xFF => {
+                let end = reader.pos;
+                summary.checksum = if version >= 5 {
+                    let stored = reader.u64_le()?;
+                    let actual = crc64(&data[..end]);
+                    match stored {
+                        0 => Checksum::Disabled,
+                        _ if stored == actual => Checksum::Valid,
+                        _ => Checksum::Mismatch {
+                            expected: stored,
+                            actual,
+                        },
+                    }
+                } else {
+                    Checksum::Absent
+                };
+                summary.len = reader.pos;
+                return Ok(summary);
+            }
+            value_type => {
+                let key = reader.string()?.into_owned();
+                let (kind, encoding, elements, value_len) = reader.value(value_type, start)?;
+                summary.keys += 1;
+                visit(RdbEntry {
+                    db,
+                    key,
+                    kind,
+                    encoding,
+                    // 过期时间、LRU和LFU信息只作用于紧随其后的一个键
+                    expires_at: expires_at.take(),
+                    elements,
+                    value_len,
+                    serialized_len: reader.pos - start,
+                });
+            }
+        }
+    }
+}
+
+/// 长度编码: 普通长度或特殊编码的字符串
+enum Length {
+    Len(u64),
+    /// 低6位: 0/1/2为8/16/32位整数，3为LZF压缩
+    Encoded(u8),
+}
+
+/// 带位置的只读游标
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error_at(&self, offset: usize, message: impl Into<String>) -> RdbError {
+        RdbError {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> RdbResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.error_at(self.pos, format!("文件被截断，还需要 {} 字节", n)))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> RdbResult<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> RdbResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32_le(&mut self) -> RdbResult<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64_le(&mut self) -> RdbResult<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// `REDIS`加4位十进制版本号
+    fn header(&mut self) -> RdbResult<u32> {
+        let header = self.bytes(9).map_err(|_| self.error_at(0, "文件太短，不是RDB文件"))?;
+        if &header[..5] != b"REDIS" {
+            return Err(self.error_at(0, "缺少REDIS文件头，不是RDB文件"));
+        }
+        let version = std::str::from_utf8(&header[5..])
+            .ok()
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| self.error_at(5, "RDB版本号无效"))?;
+        if !(1..=MAX_VERSION).contains(&version) {
+            return Err(self.error_at(5, format!("不支持的RDB版本 {}", version)));
+        }
+        Ok(version)
+    }
+
+    fn raw_length(&mut self) -> RdbResult<Length> {
+        let first = self.u8()?;
+        Ok(match first >> 6 {
+            0 => Length::Len(u64::from(first & 0x3F)),
+            1 => Length::Len(u64::from(first & 0x3F) << 8 | u64::from(self.u8()?)),
+            2 => match first {
+                0x80 => Length::Len(u64::from(u32::from_be_bytes(self.array()?))),
+                0x81 => Length::Len(u64::from_be_bytes(self.array()?)),
+                _ => {
+                    return Err(self.error_at(self.pos - 1, format!("无效的长度编码 0x{:02x}", first)))
+                }
+            },
+            _ => Length::Encoded(first & 0x3F),
+        })
+    }
+
+    fn length(&mut self) -> RdbResult<u64> {
+        let start = self.pos;
+        match self.raw_length()? {
+            Length::Len(len) => Ok(len),
+            Length::Encoded(_) => Err(self.error_at(start, "此处应为长度，实际为编码的字符串")),
+        }
+    }
+
+    /// 长度转成usize，防止损坏的长度导致巨大的分配
+    fn size(&mut self) -> RdbResult<usize> {
+        let start = self.pos;
+        let len = self.length()?;
+        usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= self.data.len())
+            .ok_or_else(|| self.error_at(start, format!("长度 {} 超出文件大小", len)))
+    }
+
+    fn string(&mut self) -> RdbResult<Cow<'a, [u8]>> {
+        let start = self.pos;
+        match self.raw_length()? {
+            Length::Len(len) => {
+                let len = usize::try_from(len)
+                    .map_err(|_| self.error_at(start, format!("字符串长度 {} 过大", len)))?;
+                self.bytes(len).map(Cow::Borrowed)
+            }
+            Length::Encoded(0) => Ok(Cow::Owned((self.u8()? as i8).to_string().into_bytes())),
+            Length::Encoded(1) => {
+                let value = i16::from_le_bytes(self.array()?);
+                Ok(Cow::Owned(value.to_string().into_bytes()))
+            }
+            Length::Encoded(2) => {
+                let value = i32::from_le_bytes(self.array()?);
+                Ok(Cow::Owned(value.to_string().into_bytes()))
+            }
+            Length::Encoded(3) => {
+                let compressed_len = self.size()?;
+                let len = self.size()?;
+                let compressed = self.bytes(compressed_len)?;
+                lzf_decompress(compressed, len)
+                    .map(Cow::Owned)
+                    .ok_or_else(|| self.error_at(start, "LZF压缩数据损坏"))
+            }
+            Length::Encoded(other) => {
+                Err(self.error_at(start, format!("未知的字符串编码 {}", other)))
+            }
+        }
+    }
+
+    /// 旧格式有序集合的分数: 1字节长度加文本，253/254/255表示NaN/+inf/-inf
+    fn text_double(&mut self) -> RdbResult<()> {
+        match self.u8()? {
+            253..=255 => Ok(()),
+            len => self.bytes(usize::from(len)).map(|_| ()),
+        }
+    }
+
+    /// 读取`count`个字符串，返回总长度
+    fn strings(&mut self, count: u64) -> RdbResult<u64> {
+        let mut total = 0;
+        for _ in 0..count {
+            total += self.string()?.len() as u64;
+        }
+        Ok(total)
+    }
+
+    /// 读取一个紧凑编码的整体，返回(内容, 元素数)
+    fn blob(&mut self, element_count: fn(&[u8]) -> Option<u64>) -> RdbResult<(Cow<'a, [u8]>, u64)> {
+        let start = self.pos;
+        let blob = self.string()?;
+        let count = element_count(&blob).ok_or_else(|| self.error_at(start, "紧凑编码的头部损坏"))?;
+        Ok((blob, count))
+    }
+
+    /// 模块数据: 自描述的操作码序列，以0结束
+    fn module_value(&mut self) -> RdbResult<u64> {
+        let start = self.pos;
+        loop {
+            match self.length()? {
+                0 => return Ok((self.pos - start) as u64),
+                // 有符号/无符号整数
+                1 | 2 => {
+                    self.length()?;
+                }
+                // float
+                3 => {
+                    self.bytes(4)?;
+                }
+                // double
+                4 => {
+                    self.bytes(8)?;
+                }
+                5 => {
+                    self.string()?;
+                }
+                other => {
+                    return Err(self.error_at(self.pos - 1, format!("未知的模块数据操作码 {}", other)))
+                }
+            }
+        }
+    }
+
+    /// 读取一个值，返回(类型, 编码, 元素数, 数据长度)
+    fn value(
+        &mut self,
+        value_type: u8,
+        start: usize,
+    ) -> RdbResult<(RdbType, &'static str, u64, u64)> {
+        let value = match value_type {
+            0 => (RdbType::String, "string", 1, self.string()?.len() as u64),
+            1 => {
+                let count = self.length()?;
+                (RdbType::List, "linkedlist", count, self.strings(count)?)
+            }
+            2 => {
+                let count = self.length()?;
+                (RdbType::Set, "hashtable", count, self.strings(count)?)
+            }
+            3 => {
+                let count = self.length()?;
+                let mut total = 0;
+                for _ in 0..count {
+                    total += self.string()?.len() as u64;
+                    self.text_double()?;
+                }
+                (RdbType::ZSet, "skiplist", count, total)
+            }
+            4 => {
+                let count = self.length()?;
+                let total = self.strings(count.saturating_mul(2))?;
+                (RdbType::Hash, "hashtable", count, total)
+            }
+            5 => {
+                let count = self.length()?;
+                let mut total = 0;
+                for _ in 0..count {
+                    total += self.string()?.len() as u64;
+                    self.bytes(8)?;
+                }
+                (RdbType::ZSet, "skiplist", count, total)
+            }
+            7 => {
+                self.length()?;
+                (RdbType::Module, "module", 1, self.module_value()?)
+            }
+            9 => {
+                let (blob, count) = self.blob(zipmap_len)?;
+                (RdbType::Hash, "zipmap", count, blob.len() as u64)
+            }
+            10 => {
+                let (blob, count) = self.blob(ziplist_len)?;
+                (RdbType::List, "ziplist", count, blob.len() as u64)
+            }
+            11 => {
+                let (blob, count) = self.blob(intset_len)?;
+                (RdbType::Set, "intset", count, blob.len() as u64)
+            }
+            12 => {
+                let (blob, count) = self.blob(ziplist_len)?;
+                (RdbType::ZSet, "ziplist", count / 2, blob.len() as u64)
+            }
+            13 => {
+                let (blob, count) = self.blob(ziplist_len)?;
+                (RdbType::Hash, "ziplist", count / 2, blob.len() as u64)
+            }
+            14 => {
+                let nodes = self.length()?;
+                let (mut count, mut total) = (0, 0);
+                for _ in 0..nodes {
+                    let (blob, n) = self.blob(ziplist_len)?;
+                    count += n;
+                    total += blob.len() as u64;
+                }
+                (RdbType::List, "quicklist", count, total)
+            }
+            15 | 19 | 21 => {
+                let (count, total) = self.stream(value_type)?;
+                (RdbType::Stream, "stream", count, total)
+            }
+            16 => {
+                let (blob, count) = self.blob(listpack_len)?;
+                (RdbType::Hash, "listpack", count / 2, blob.len() as u64)
+            }
+            17 => {
+                let (blob, count) = self.blob(listpack_len)?;
+                (RdbType::ZSet, "listpack", count / 2, blob.len() as u64)
+            }
+            18 => {
+                let nodes = self.length()?;
+                let (mut count, mut total) = (0, 0);
+                for _ in 0..nodes {
+                    // 容器类型: 1为单个大元素(PLAIN)，2为listpack(PACKED)
+                    let container = self.length()?;
+                    let (blob, n) = if container == 1 {
+                        (self.string()?, 1)
+                    } else {
+                        self.blob(listpack_len)?
+                    };
+                    count += n;
+                    total += blob.len() as u64;
+                }
+                (RdbType::List, "quicklist", count, total)
+            }
+            20 => {
+                let (blob, count) = self.blob(listpack_len)?;
+                (RdbType::Set, "listpack", count, blob.len() as u64)
+            }
+            other => {
+                return Err(self.error_at(start, format!("不支持的值类型 {}", other)));
+            }
+        };
+        Ok(value)
+    }
+
+    /// 流: listpack节点、元数据、消费者组及其待确认列表，返回(消息数, 数据长度)
+    fn stream(&mut self, value_type: u8) -> RdbResult<(u64, u64)> {
+        let mut total = 0;
+        for _ in 0..self.length()? {
+            // 节点的主ID(16字节)和存放消息的listpack
+            total += self.string()?.len() as u64;
+            total += self.blob(listpack_len)?.0.len() as u64;
+        }
+        let count = self.length()?;
+        // 最后一个ID
+        self.length()?;
+        self.length()?;
+        if value_type >= 19 {
+            // 第一个ID、最大的已删除ID和添加过的消息总数
+            for _ in 0..5 {
+                self.length()?;
+            }
+        }
+
+        for _ in 0..self.length()? {
+            self.string()?;
+            self.length()?;
+            self.length()?;
+            if value_type >= 19 {
+                // entries_read
+                self.length()?;
+            }
+            // 组的待确认列表: 消息ID、投递时间、投递次数
+            for _ in 0..self.length()? {
+                self.bytes(16 + 8)?;
+                self.length()?;
+            }
+            for _ in 0..self.length()? {
+                self.string()?;
+                // seen-time，版本21起还有active-time
+                self.bytes(if value_type >= 21 { 16 } else { 8 })?;
+                // 消费者的待确认列表只有消息ID
+                for _ in 0..self.length()? {
+                    self.bytes(16)?;
+                }
+            }
+        }
+        Ok((count, total))
+    }
+}
+
+/// ziplist头部: zlbytes(u32)、zltail(u32)、zllen(u16)，zlbytes必须等于整体长度
+fn ziplist_len(blob: &[u8]) -> Option<u64> {
+    let total = u32::from_le_bytes(blob.get(0..4)?.try_into().ok()?);
+    let count = u16::from_le_bytes(blob.get(8..10)?.try_into().ok()?);
+    (total as usize == blob.len()).then_some(u64::from(count))
+}
+
+/// listpack头部: 总字节数(u32)、元素数(u16)
+fn listpack_len(blob: &[u8]) -> Option<u64> {
+    let total = u32::from_le_bytes(blob.get(0..4)?.try_into().ok()?);
+    let count = u16::from_le_bytes(blob.get(4..6)?.try_into().ok()?);
+    (total as usize == blob.len() && blob.last() == Some(&0xFF)).then_some(u64::from(count))
+}
+
+/// intset头部: 元素宽度(u32)、元素数(u32)
+fn intset_len(blob: &[u8]) -> Option<u64> {
+    let width = u32::from_le_bytes(blob.get(0..4)?.try_into().ok()?);
+    let count = u32::from_le_bytes(blob.get(4..8)?.try_into().ok()?);
+    let expected = 8 + u64::from(width) * u64::from(count);
+    (matches!(width, 2 | 4 | 8) && expected == blob.len() as u64).then_some(u64::from(count))
+}
+
+/// zipmap头部: 1字节的元素数，254表示需要遍历才能得知
+fn zipmap_len(blob: &[u8]) -> Option<u64> {
+    blob.first().map(|&count| u64::from(count))
+}
+
+/// LZF解压，结果长度必须等于`len`
+fn lzf_decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    while i < input.len() {
+        let ctrl = usize::from(input[i]);
+        i += 1;
+        if ctrl < 32 {
+            // 字面量: 之后的ctrl+1字节原样复制
+            out.extend_from_slice(input.get(i..i + ctrl + 1)?);
+            i += ctrl + 1;
+        } else {
+            // 回溯引用: 长度在高3位(7表示再读一个字节)，距离在低5位和下一个字节
+            let mut copy = ctrl >> 5;
+            if copy == 7 {
+                copy += usize::from(*input.get(i)?);
+                i += 1;
+            }
+            let back = ((ctrl & 0x1F) << 8) + usize::from(*input.get(i)?) + 1;
+            i += 1;
+            let from = out.len().checked_sub(back)?;
+            for k in 0..copy + 2 {
+                let byte = out[from + k];
+                out.push(byte);
+            }
+        }
+        if out.len() > len {
+            return None;
+        }
+    }
+    (out.len() == len).then_some(out)
+}
+
+/// CRC-64/Jones的查找表(反射多项式)
+const CRC64_TABLE: [u64; 256] = {
+    // 0xad93d23594c935a9按位反转
+    const POLY: u64 = 0x95AC_9329_AC4B_C9B5;
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Redis的RDB校验和
+fn crc64(data: &[u8]) -> u64 {
+    data.iter().fold(0, |crc, &b| {
+        CRC64_TABLE[((crc ^ u64::from(b)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 长度小于64的字符串
+    fn string(s: &[u8]) -> Vec<u8> {
+        let mut out = vec![s.len() as u8];
+        out.extend_from_slice(s);
+        out
+    }
+
+    /// 加上文件头、EOF和校验和
+    fn rdb(body: &[u8]) -> Vec<u8> {
+        let mut data = b"REDIS0011".to_vec();
+        data.extend_from_slice(body);
+        data.push(0xFF);
+        let checksum = crc64(&data);
+        data.extend_from_slice(&checksum.to_le_bytes());
+        data
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut body = vec![0xFA];
+        body.extend(string(b"redis-ver"));
+        body.extend(string(b"7.2.4"));
+        body.extend([0xFE, 0, 0xFB, 2, 1]);
+        // 带过期时间的字符串，值是16位整数编码
+        body.push(0xFC);
+        body.extend(1_700_000_000_000u64.to_le_bytes());
+        body.push(0);
+        body.extend(string(b"counter"));
+        body.extend([0xC1, 0x39, 0x30]);
+        // 数据库1中的intset: 3个16位整数
+        body.extend([0xFE, 1, 11]);
+        body.extend(string(b"ids"));
+        let mut intset = 2u32.to_le_bytes().to_vec();
+        intset.extend(3u32.to_le_bytes());
+        intset.extend([1, 0, 2, 0, 3, 0]);
+        body.extend(string(&intset));
+        // LZF压缩的字符串"aaaaaa": 字面量"a"加回溯5字节
+        body.push(0);
+        body.extend(string(b"lzf"));
+        body.extend([0xC3, 4, 6, 0x00, b'a', 0x60, 0x00]);
+        rdb(&body)
+    }
+
+    #[test]
+    fn test_crc64() {
+        // Redis crc64.c中的测试向量
+        assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = sample();
+        let mut entries = Vec::new();
+        let summary = parse(&data, |entry| entries.push(entry)).unwrap();
+
+        assert_eq!(summary.version, 11);
+        assert_eq!(summary.aux, [("redis-ver".to_string(), "7.2.4".to_string())]);
+        assert_eq!(summary.keys, 3);
+        assert_eq!(summary.checksum, Checksum::Valid);
+        assert_eq!(summary.len, data.len());
+
+        assert_eq!(entries[0].key, b"counter");
+        assert_eq!(entries[0].kind, RdbType::String);
+        assert_eq!(entries[0].value_len, "12345".len() as u64);
+        assert_eq!(entries[0].expires_at, Some(1_700_000_000_000));
+
+        assert_eq!((entries[1].db, entries[1].kind), (1, RdbType::Set));
+        assert_eq!((entries[1].encoding, entries[1].elements), ("intset", 3));
+        assert_eq!(entries[1].expires_at, None);
+
+        assert_eq!(entries[2].value_len, 6);
+    }
+
+    #[test]
+    fn test_corruption() {
+        let data = sample();
+
+        let mut flipped = data.clone();
+        let pos = flipped.windows(7).position(|w| w == b"counter").unwrap();
+        flipped[pos] = b'C';
+        let summary = parse(&flipped, |_| {}).unwrap();
+        assert!(matches!(summary.checksum, Checksum::Mismatch { .. }));
+
+        let truncated = &data[..data.len() - 12];
+        let err = parse(truncated, |_| {}).unwrap_err();
+        assert!(err.message.contains("截断"), "{}", err);
+
+        let mut bad_type = data.clone();
+        bad_type[pos - 2] = 99;
+        let err = parse(&bad_type, |_| {}).unwrap_err();
+        assert_eq!(err.offset, pos - 2);
+
+        assert!(parse(b"REDIS0099", |_| {}).is_err());
+        assert!(parse(b"*1\r\n$4\r\nPING\r\n", |_| {}).is_err());
+    }
+}
@@ -109,10 +109,22 @@ impl RespParser {
     /// - &mut BytesMut 是可变借用，允许修改缓冲区
     /// - Result类型处理可能的错误
     pub fn parse(buf: &mut BytesMut) -> RedisResult<Option<RespValue>> {
+        Self::parse_limited(buf, usize::MAX)
+    }
+
+    /// 解析RESP值，批量字符串长度超过`max_bulk_len`时返回协议错误
+    ///
+    /// 在读取数据之前就根据长度行拒绝，服务器不会为超大的参数缓冲数据
+    pub fn parse_limited(buf: &mut BytesMut, max_bulk_len: usize) -> RedisResult<Option<RespValue>> {
         if buf.is_empty() {
             return Ok(None);
         }
 
+        // 数据不完整时不消费缓冲区，等待更多数据到达
+        if Self::frame_end(buf, 0, max_bulk_len)?.is_none() {
+            return Ok(None);
+        }
+
         // 检查是否有完整的行
         let first_byte = buf[0];
 
@@ -257,6 +269,49 @@ impl RespParser {
         }
     }
 
+    /// 检查从pos开始是否有一个完整的帧，不消费缓冲区
+    ///
+    /// 返回帧结束的位置；数据不足时返回None
+    ///
+    /// Rust特点: 递归处理嵌套数组，只借用切片不做拷贝
+    fn frame_end(buf: &[u8], pos: usize, max_bulk_len: usize) -> RedisResult<Option<usize>> {
+        if pos >= buf.len() {
+            return Ok(None);
+        }
+
+        let line_end = match buf[pos..].windows(2).position(|w| w == b"\r\n") {
+            Some(offset) => pos + offset,
+            None => return Ok(None),
+        };
+        let next = line_end + 2;
+
+        match buf[pos] {
+            b'$' => {
+                let len: i64 = String::from_utf8(buf[pos + 1..line_end].to_vec())?.parse()?;
+                if len < 0 {
+                    return Ok(Some(next));
+                }
+                if len as u64 > max_bulk_len as u64 {
+                    return Err(RedisError::Protocol("invalid bulk length".to_string()));
+                }
+                let end = next + len as usize + 2;
+                Ok(if buf.len() >= end { Some(end) } else { None })
+            }
+            b'*' => {
+                let count: i64 = String::from_utf8(buf[pos + 1..line_end].to_vec())?.parse()?;
+                let mut cursor = next;
+                for _ in 0..count.max(0) {
+                    match Self::frame_end(buf, cursor, max_bulk_len)? {
+                        Some(end) => cursor = end,
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(cursor))
+            }
+            _ => Ok(Some(next)),
+        }
+    }
+
     /// 读取一行并从缓冲区移除
     fn read_line(buf: &mut BytesMut) -> RedisResult<Option<Vec<u8>>> {
         if let Some((line, total_len)) = Self::peek_line(buf)? {
@@ -342,11 +397,40 @@ mod tests {
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
     }
 
+    #[test]
+    fn test_parse_partial_array() {
+        let full = b"*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        let mut buf = BytesMut::from(&full[..20]);
+        assert_eq!(RespParser::parse(&mut buf).unwrap(), None);
+        assert_eq!(buf.len(), 20);
+
+        buf.extend_from_slice(&full[20..]);
+        let result = RespParser::parse(&mut buf).unwrap().unwrap();
+        assert_eq!(
+            result,
+            RespValue::Array(vec![
+                RespValue::BulkString(b"foo".to_vec()),
+                RespValue::BulkString(b"bar".to_vec()),
+            ])
+        );
+        assert!(buf.is_empty());
+    }
+
     #[test]
     fn test_parse_integer() {
         let mut buf = BytesMut::from(&b":1000\r\n"[..]);
         let result = RespParser::parse(&mut buf).unwrap().unwrap();
         assert_eq!(result, RespValue::Integer(1000));
     }
+
+    #[test]
+    fn test_parse_limited() {
+        // 声明的长度超过限制时立即报错，不等待数据到达
+        let mut buf = BytesMut::from(&b"*1\r\n$100\r\n"[..]);
+        assert!(RespParser::parse_limited(&mut buf, 10).is_err());
+
+        let mut buf = BytesMut::from(&b"*1\r\n$3\r\nfoo\r\n"[..]);
+        assert!(RespParser::parse_limited(&mut buf, 10).unwrap().is_some());
+    }
 }
 
@@ -0,0 +1,711 @@
+//! 服务器模块 - 接受连接并为每个连接创建任务
+//!
+//! Rust特点展示:
+//! - tokio::task::JoinSet 管理一组并发任务
+//! - 任务随JoinSet一起被取消，服务器停止时不会遗留连接
+//! - tokio::select! 同时等待多个监听器
+//! - `Listener` trait抽象接受连接的方式，同一个服务器可以运行在模拟网络上做确定性测试
+//! - RAII: 连接任务持有`IpSlot`，任务结束时自动释放同一IP的连接计数
+//! - Result返回类型允许使用?操作符
+
+use crate::audit::AuditLog;
+use crate::config::SharedConfig;
+use crate::connection::Connection;
+use crate::error::RedisResult;
+use crate::store::Store;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+/// 接受客户端连接的监听器
+///
+/// 生产环境是`TcpListener`；测试中可以换成内存中的模拟网络(`testing::MemoryListener`)，
+/// 或为turmoil等网络模拟器的监听器实现本trait(需要包装成新类型)，
+/// 配合`clock::TokioClock`，断线、半包和时序问题都可以在确定的调度下复现
+pub trait Listener: Send + 'static {
+    /// 连接的字节流
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// 等待下一个连接，返回字节流和客户端地址
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Stream, SocketAddr)>> + Send;
+
+    /// 监听的地址
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept(&self) -> impl Future<Output = io::Result<(TcpStream, SocketAddr)>> + Send {
+        TcpListener::accept(self)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpListener::local_addr(self)
+    }
+}
+
+/// Redis服务器
+///
+/// Rust特点: 结构体拥有监听器和存储，run消费self；泛型参数L默认为TCP监听器
+pub struct Server<L = TcpListener> {
+    /// 主监听器
+    listener: L,
+    /// 可选的Unix socket监听器
+    #[cfg(unix)]
+    unix_listener: Option<UnixListener>,
+    /// 所有连接共享的存储
+    store: Store,
+    /// 所有连接共享的配置
+    config: SharedConfig,
+    /// 每个IP的连接数
+    per_ip: IpConnections,
+    /// 审计日志
+    audit: Option<AuditLog>,
+}
+
+/// 每个IP当前的连接数，用于maxclients-per-ip
+#[derive(Clone, Default)]
+struct IpConnections {
+    counts: Arc<Mutex<HashMap<IpAddr, u64>>>,
+}
+
+impl IpConnections {
+    /// 为新连接占用一个名额；该IP已达到`limit`时返回None，`limit`为0表示不限制
+    fn acquire(&self, ip: IpAddr, limit: u64) -> Option<IpSlot> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(ip).or_insert(0);
+        if limit > 0 && *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(IpSlot {
+            connections: self.clone(),
+            ip,
+        })
+    }
+}
+
+/// 一个连接占用的名额，被drop时归还
+struct IpSlot {
+    connections: IpConnections,
+    ip: IpAddr,
+}
+
+impl Drop for IpSlot {
+    fn drop(&mut self) {
+        let mut counts = self.connections.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
+
+impl Server {
+    /// 绑定地址并创建服务器
+    pub async fn bind(addr: impl ToSocketAddrs, store: Store) -> RedisResult<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Self::from_listener(listener, store))
+    }
+
+    /// 把监听socket加入平滑升级时要交给新进程的列表
+    #[cfg(unix)]
+    pub fn add_to_handover(&self, handover: &mut crate::upgrade::Handover) -> RedisResult<()> {
+        handover.add("tcp", &self.listener)?;
+        if let Some(listener) = &self.unix_listener {
+            handover.add("unix", listener)?;
+        }
+        Ok(())
+    }
+}
+
+impl<L: Listener> Server<L> {
+    /// 基于已绑定的监听器创建服务器
+    pub fn from_listener(listener: L, store: Store) -> Self {
+        Self {
+            listener,
+            #[cfg(unix)]
+            unix_listener: None,
+            store,
+            config: SharedConfig::default(),
+            per_ip: IpConnections::default(),
+            audit: None,
+        }
+    }
+
+    /// 使用指定的配置(如requirepass)
+    ///
+    /// Rust特点: 消费self并返回Self的builder风格方法
+    pub fn with_config(mut self, config: SharedConfig) -> Self {
+        // 紧凑编码的阈值由Store在写入时使用，配置修改后同步过去
+        self.store.set_encoding_thresholds(config.read().encoding_thresholds());
+        let store = self.store.clone();
+        config.on_reload(move |config| store.set_encoding_thresholds(config.encoding_thresholds()));
+        self.config = config;
+        self
+    }
+
+    /// 把所有连接的命令写入审计日志
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// 同时在Unix socket上接受连接，已存在的socket文件会被删除
+    #[cfg(unix)]
+    pub fn with_unix_socket(mut self, path: impl AsRef<std::path::Path>) -> RedisResult<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        self.unix_listener = Some(UnixListener::bind(path)?);
+        Ok(self)
+    }
+
+    /// 使用已绑定的Unix socket监听器，如平滑升级时从旧进程继承的监听器
+    #[cfg(unix)]
+    pub fn with_unix_listener(
+        mut self,
+        listener: std::os::unix::net::UnixListener,
+    ) -> RedisResult<Self> {
+        self.unix_listener = Some(UnixListener::from_std(listener)?);
+        Ok(self)
+    }
+
+    /// 实际监听的地址(绑定端口0时可以得到系统分配的端口)
+    pub fn local_addr(&self) -> RedisResult<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// 服务器使用的存储
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// 接受连接循环
+    ///
+    /// 返回的Future被丢弃(或所在任务被abort)时，所有连接任务也会随之取消
+    pub async fn run(self) -> RedisResult<()> {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// 接受连接，直到`stop`完成；之后关闭监听socket，等待已有连接结束
+    ///
+    /// 最多等待upgrade-drain-timeout，超时后剩余的连接随JoinSet一起被关闭
+    ///
+    /// Rust特点: loop是无限循环，比while true更惯用
+    pub async fn run_until(self, stop: impl Future<Output = ()>) -> RedisResult<()> {
+        let mut connections = JoinSet::new();
+        tokio::pin!(stop);
+
+        loop {
+            // 等待新连接
+            // Rust特点: select!的每个分支处理一种监听器，分支之间类型可以不同
+            tokio::select! {
+                _ = &mut stop => break,
+                accepted = self.listener.accept() => {
+                    let (mut socket, addr) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            recover_accept(e).await?;
+                            continue;
+                        }
+                    };
+                    let (allowed, limit) = {
+                        let config = self.config.read();
+                        (config.ip_allowed(addr.ip()), config.maxclients_per_ip)
+                    };
+                    // allow-ips/deny-ips不允许的地址在读取任何命令之前直接关闭
+                    if !allowed {
+                        warn!(client = %addr, "地址不在allow-ips中或在deny-ips中，拒绝连接");
+                        continue;
+                    }
+                    match self.per_ip.acquire(addr.ip(), limit) {
+                        Some(slot) => {
+                            self.store.stats().record_connection();
+                            let connection = Connection::with_addr(socket, addr.to_string())
+                                .with_config(self.config.clone())
+                                .with_audit_log(self.audit.clone());
+                            let store = self.store.clone();
+                            spawn_connection(&mut connections, connection, store, Some(slot));
+                        }
+                        None => {
+                            // 与Redis的maxclients一样先回复错误再关闭，不阻塞接受循环
+                            warn!(client = %addr, "同一IP的连接数超过maxclients-per-ip，拒绝连接");
+                            connections.spawn(async move {
+                                let error = b"-ERR max number of clients per IP reached\r\n";
+                                let _ = socket.write_all(error).await;
+                            });
+                        }
+                    }
+                }
+                accepted = accept_unix(&self) => {
+                    let socket = match accepted {
+                        Ok(socket) => socket,
+                        Err(e) => {
+                            recover_accept(e).await?;
+                            continue;
+                        }
+                    };
+                    self.store.stats().record_connection();
+                    // 本机Unix socket受文件权限保护，作为管理连接不受执行时间预算限制
+                    let connection = Connection::with_addr(socket, "unix".to_string())
+                        .with_config(self.config.clone())
+                        .with_admin(true)
+                        .with_audit_log(self.audit.clone());
+                    spawn_connection(&mut connections, connection, self.store.clone(), None);
+                }
+            }
+
+            // 回收已经结束的连接任务，避免JoinSet无限增长
+            while connections.try_join_next().is_some() {}
+        }
+
+        // 关闭监听socket；平滑升级时新进程持有它们的副本，继续接受连接
+        drop(self.listener);
+        #[cfg(unix)]
+        drop(self.unix_listener);
+
+        let timeout = self.config.read().upgrade_drain_timeout;
+        info!(connections = connections.len(), "停止接受新连接，等待已有连接结束");
+        let drain = async { while connections.join_next().await.is_some() {} };
+        if timeout == 0 {
+            drain.await;
+        } else if tokio::time::timeout(Duration::from_secs(timeout), drain).await.is_err() {
+            warn!(connections = connections.len(), "等待超时，关闭剩余的连接");
+        }
+        Ok(())
+    }
+}
+
+/// 等待Unix socket上的新连接；没有配置时永远挂起
+#[cfg(unix)]
+async fn accept_unix<L>(server: &Server<L>) -> io::Result<tokio::net::UnixStream> {
+    match &server.unix_listener {
+        Some(listener) => Ok(listener.accept().await?.0),
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn accept_unix<L>(_server: &Server<L>) -> io::Result<tokio::io::DuplexStream> {
+    std::future::pending().await
+}
+
+/// 文件描述符等资源耗尽时，重新accept之前等待的时间
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// accept失败后决定是否继续: 只有监听器本身已经失效时才返回错误
+///
+/// - 客户端在握手完成前断开(ECONNABORTED等)只影响那一个连接，立即继续
+/// - EMFILE/ENFILE等资源耗尽是暂时的，等待一会儿让已有连接释放文件描述符；
+///   不等待的话accept会立刻再次失败，空转占满CPU
+async fn recover_accept(error: io::Error) -> io::Result<()> {
+    if listener_failed(&error) {
+        warn!(error = %error, "监听socket已不可用，停止接受连接");
+        return Err(error);
+    }
+    warn!(error = %error, "接受连接失败");
+    if resources_exhausted(&error) {
+        tokio::time::sleep(ACCEPT_BACKOFF).await;
+    }
+    Ok(())
+}
+
+/// 监听socket本身失效(已关闭、不是socket、不在监听状态)
+fn listener_failed(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if matches!(error.raw_os_error(), Some(libc::EBADF | libc::ENOTSOCK | libc::EOPNOTSUPP)) {
+        return true;
+    }
+    error.kind() == io::ErrorKind::InvalidInput
+}
+
+/// 进程或系统的文件描述符、内存等资源暂时耗尽
+fn resources_exhausted(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if matches!(
+        error.raw_os_error(),
+        Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM)
+    ) {
+        return true;
+    }
+    error.kind() == io::ErrorKind::OutOfMemory
+}
+
+/// 为连接创建任务
+///
+/// Rust特点:
+/// - 泛型函数同时服务TCP和Unix socket连接
+/// - async move 获取connection、store和slot的所有权，任务结束时slot随之释放
+fn spawn_connection<S>(
+    connections: &mut JoinSet<()>,
+    mut connection: Connection<S>,
+    store: Store,
+    slot: Option<IpSlot>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    connections.spawn(async move {
+        let _slot = slot;
+        // 处理连接，忽略错误（已在handle中记录日志）
+        if let Err(e) = connection.handle(&store).await {
+            warn!(client = %connection.addr(), error = %e, "连接错误");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::config::Config;
+    use crate::resp::RespValue;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("rust-redis-server-{}.sock", std::process::id()));
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_unix_socket(&path)
+            .unwrap();
+        let handle = tokio::spawn(server.run());
+
+        let mut client = Client::connect_unix(&path).await.unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        handle.abort();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_forwarding() {
+        let upstream = crate::testing::TestServer::start().await;
+        upstream.store().set("legacy:1".to_string(), b"old".to_vec());
+
+        let config = Config {
+            upstream: Some(upstream.addr().to_string()),
+            upstream_keys: Some("legacy:*".to_string()),
+            ..Default::default()
+        };
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_config(SharedConfig::new(config));
+        let addr = server.local_addr().unwrap();
+        let store = server.store().clone();
+        let handle = tokio::spawn(server.run());
+
+        let mut client = Client::connect(addr).await.unwrap();
+        // 匹配upstream-keys的键读写上游，其余键留在本地
+        assert_eq!(client.get("legacy:1").await.unwrap(), Some(b"old".to_vec()));
+        client.set("legacy:2", "new").await.unwrap();
+        client.set("local", "here").await.unwrap();
+        assert_eq!(upstream.store().get("legacy:2"), Some(b"new".to_vec()));
+        assert!(!store.exists("legacy:2"));
+        assert_eq!(store.get("local"), Some(b"here".to_vec()));
+        assert!(!upstream.store().exists("local"));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_maxclients_per_ip() {
+        let config = Config {
+            maxclients_per_ip: 2,
+            ..Default::default()
+        };
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_config(SharedConfig::new(config));
+        let addr = server.local_addr().unwrap();
+        let handle = tokio::spawn(server.run());
+
+        let mut first = Client::connect(addr).await.unwrap();
+        let mut second = Client::connect(addr).await.unwrap();
+        assert_eq!(first.ping().await.unwrap(), "PONG");
+        assert_eq!(second.ping().await.unwrap(), "PONG");
+
+        let mut third = Client::connect(addr).await.unwrap();
+        let err = third.ping().await.unwrap_err();
+        assert!(err.to_string().contains("max number of clients per IP"));
+
+        // 断开一个连接后名额被归还
+        drop(first);
+        let mut retry = Client::connect(addr).await.unwrap();
+        for _ in 0..50 {
+            if retry.ping().await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            retry = Client::connect(addr).await.unwrap();
+        }
+        assert_eq!(retry.ping().await.unwrap(), "PONG");
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_ip_deny_list() {
+        let config = SharedConfig::new(Config::default());
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_config(config.clone());
+        let addr = server.local_addr().unwrap();
+        let handle = tokio::spawn(server.run());
+
+        let mut admin = Client::connect(addr).await.unwrap();
+        assert_eq!(admin.ping().await.unwrap(), "PONG");
+
+        // CONFIG SET立即生效，新连接被直接关闭；已建立的连接不受影响
+        let reply = admin.command(["CONFIG", "SET", "deny-ips", "127.0.0.0/8"]).await.unwrap();
+        assert_eq!(reply, crate::resp::ok());
+        let mut client = Client::connect(addr).await.unwrap();
+        assert!(client.ping().await.is_err());
+        assert_eq!(admin.ping().await.unwrap(), "PONG");
+
+        config.set("deny-ips", "").unwrap();
+        config.set("allow-ips", "127.0.0.1").unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        assert!(config.set("port", "7000").is_err());
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_run_until_drains_connections() {
+        let server = Server::bind("127.0.0.1:0", Store::new()).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let handle = tokio::spawn(server.run_until(async {
+            let _ = stopped.await;
+        }));
+
+        let mut client = Client::connect(addr).await.unwrap();
+        client.set("k", "v").await.unwrap();
+        stop.send(()).unwrap();
+
+        // 监听socket关闭后不再接受新连接
+        let mut refused = false;
+        for _ in 0..50 {
+            if Client::connect(addr).await.is_err() {
+                refused = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(refused);
+
+        // 已有连接继续工作，断开后run_until返回
+        assert_eq!(client.get("k").await.unwrap(), Some(b"v".to_vec()));
+        assert!(!handle.is_finished());
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let path = std::env::temp_dir()
+            .join(format!("rust-redis-server-audit-{}.log", std::process::id()));
+        let config = Config {
+            requirepass: Some("secret".to_string()),
+            audit_redact_keys: vec!["token:*".to_string()],
+            ..Default::default()
+        };
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_config(SharedConfig::new(config))
+            .with_audit_log(AuditLog::open(path.to_str().unwrap()).unwrap());
+        let addr = server.local_addr().unwrap();
+        let handle = tokio::spawn(server.run());
+
+        let mut client = Client::connect(addr).await.unwrap();
+        client.auth("secret").await.unwrap();
+        client.set("token:1", "abc").await.unwrap();
+        client.get("plain").await.unwrap();
+        handle.abort();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> =
+            content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let commands: Vec<_> = lines.iter().map(|line| line["command"].clone()).collect();
+        assert_eq!(
+            commands,
+            [
+                serde_json::json!(["AUTH", "(redacted)"]),
+                serde_json::json!(["SET", "token:1", "(redacted)"]),
+                serde_json::json!(["GET", "plain"]),
+            ]
+        );
+        // AUTH在认证之前记录
+        assert!(lines[0]["user"].is_null());
+        assert_eq!(lines[1]["user"], "default");
+        assert_eq!(lines[1]["db"], 0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// 先依次返回给定的错误，之后转发给内存监听器
+    struct FlakyListener {
+        errors: Mutex<Vec<io::Error>>,
+        inner: crate::testing::MemoryListener,
+    }
+
+    impl Listener for FlakyListener {
+        type Stream = tokio::io::DuplexStream;
+
+        async fn accept(&self) -> io::Result<(Self::Stream, SocketAddr)> {
+            let error = self.errors.lock().unwrap().pop();
+            match error {
+                Some(error) => Err(error),
+                None => self.inner.accept().await,
+            }
+        }
+
+        fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.inner.local_addr()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transient_accept_errors() {
+        let (inner, connector) = crate::testing::memory_network(4096);
+        let mut errors = vec![
+            io::Error::from(io::ErrorKind::ConnectionAborted),
+            io::Error::from(io::ErrorKind::OutOfMemory),
+        ];
+        #[cfg(unix)]
+        errors.push(io::Error::from_raw_os_error(libc::EMFILE));
+        let listener = FlakyListener {
+            errors: Mutex::new(errors),
+            inner,
+        };
+        let handle = tokio::spawn(Server::from_listener(listener, Store::new()).run());
+
+        // 暂时的错误之后继续接受连接
+        let mut client = connector.client().unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        assert!(!handle.is_finished());
+        handle.abort();
+
+        // 监听socket失效时停止
+        let (inner, _connector) = crate::testing::memory_network(4096);
+        let listener = FlakyListener {
+            errors: Mutex::new(vec![io::Error::from(io::ErrorKind::InvalidInput)]),
+            inner,
+        };
+        let result = Server::from_listener(listener, Store::new()).run().await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_info_stats() {
+        let (listener, connector) = crate::testing::memory_network(4096);
+        let server = Server::from_listener(listener, Store::new());
+        let handle = tokio::spawn(server.run());
+
+        let mut client = connector.client().unwrap();
+        client.set("k", "v").await.unwrap();
+        client.get("k").await.unwrap();
+        client.get("missing").await.unwrap();
+        // 事务中的命令在EXEC时计入，排队时不计入
+        for command in [&["MULTI"][..], &["SET", "a", "1"], &["EXEC"]] {
+            client.command(command.iter().copied()).await.unwrap();
+        }
+
+        let info = match client.command(["INFO"]).await.unwrap() {
+            RespValue::BulkString(info) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        for line in [
+            "total_connections_received:1",
+            "total_commands_processed:6",
+            "keyspace_hits:1",
+            "keyspace_misses:1",
+            "evicted_keys:0",
+        ] {
+            assert!(info.contains(line), "{} not in {}", line, info);
+        }
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_maxmemory_clients() {
+        let config = Config {
+            maxmemory_clients: 100 * 1024,
+            ..Default::default()
+        };
+        let (listener, connector) = crate::testing::memory_network(64 * 1024);
+        let server = Server::from_listener(listener, Store::new())
+            .with_config(SharedConfig::new(config));
+        let handle = tokio::spawn(server.run());
+
+        let mut client = connector.client().unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        // 超大请求堆积在输入缓冲区中，超过上限时该连接被断开
+        let mut hungry = connector.connect().unwrap();
+        let mut request = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1000000\r\n".to_vec();
+        request.resize(request.len() + 300 * 1024, b'x');
+        let _ = hungry.write_all(&request).await;
+        let mut buf = Vec::new();
+        let _ = tokio::io::AsyncReadExt::read_to_end(&mut hungry, &mut buf).await;
+        assert!(buf.is_empty());
+
+        // 其他连接不受影响
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        let info = match client.command(["INFO"]).await.unwrap() {
+            RespValue::BulkString(info) => String::from_utf8(info).unwrap(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert!(info.contains("evicted_clients:1\r\n"), "{}", info);
+        assert!(info.contains("connected_clients:1\r\n"), "{}", info);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_requirepass() {
+        let config = Config {
+            requirepass: Some("secret".to_string()),
+            ..Default::default()
+        };
+        let server = Server::bind("127.0.0.1:0", Store::new())
+            .await
+            .unwrap()
+            .with_config(SharedConfig::new(config));
+        let addr = server.local_addr().unwrap();
+        let handle = tokio::spawn(server.run());
+
+        let mut client = Client::connect(addr).await.unwrap();
+        let err = client.get("foo").await.unwrap_err();
+        assert!(err.to_string().contains("NOAUTH"));
+
+        assert!(client.auth("wrong").await.is_err());
+        client.auth("secret").await.unwrap();
+        assert_eq!(client.get("foo").await.unwrap(), None);
+
+        handle.abort();
+    }
+}
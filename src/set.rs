@@ -0,0 +1,297 @@
+//! 集合模块 - S*命令使用的集合类型
+//!
+//! 小集合使用紧凑编码(见`encoding`模块):
+//! - `intset`: 成员都是整数时按数值排序，二分查找
+//! - `listpack`: 按插入顺序排列的数组，线性查找
+//! - `hashtable`: 超过阈值后转换为HashSet，之后不再转换回来
+//!
+//! Rust特点展示:
+//! - 私有枚举隐藏内部表示，对外只暴露与HashSet相同的方法
+//! - `Option`的迭代器加`flatten`/`chain`，不装箱就统一不同表示的迭代器类型
+
+use crate::encoding::{self, EncodingThresholds, MAX_COMPACT_ENTRIES};
+use std::collections::HashSet;
+
+/// 集合的内部表示
+#[derive(Debug, Clone)]
+enum Members {
+    /// 规范形式的整数，按数值升序
+    IntSet(Vec<Vec<u8>>),
+    ListPack(Vec<Vec<u8>>),
+    HashTable(HashSet<Vec<u8>>),
+}
+
+/// 集合: 成员不重复，二进制安全
+#[derive(Debug, Clone)]
+pub struct Set {
+    members: Members,
+}
+
+/// `Set::into_iter`的迭代器类型
+pub type IntoIter = std::iter::Chain<
+    std::iter::Flatten<std::option::IntoIter<Vec<Vec<u8>>>>,
+    std::iter::Flatten<std::option::IntoIter<HashSet<Vec<u8>>>>,
+>;
+
+impl Set {
+    /// 创建空集合，初始为intset编码
+    pub fn new() -> Self {
+        Self {
+            members: Members::IntSet(Vec::new()),
+        }
+    }
+
+    /// 成员数
+    pub fn len(&self) -> usize {
+        match &self.members {
+            Members::IntSet(members) | Members::ListPack(members) => members.len(),
+            Members::HashTable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 编码名称(OBJECT ENCODING)
+    pub fn encoding(&self) -> &'static str {
+        match self.members {
+            Members::IntSet(_) => "intset",
+            Members::ListPack(_) => "listpack",
+            Members::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match &self.members {
+            Members::IntSet(members) => int_search(members, member).is_ok(),
+            Members::ListPack(members) => members.iter().any(|m| m == member),
+            Members::HashTable(members) => members.contains(member),
+        }
+    }
+
+    /// 添加成员，返回是否是新成员
+    ///
+    /// 非整数成员加入intset时先转换为listpack；紧凑编码超过`MAX_COMPACT_ENTRIES`时
+    /// 直接转换为hashtable，其余的转换由`fit`按配置的阈值进行
+    pub fn insert(&mut self, member: Vec<u8>) -> bool {
+        if matches!(self.members, Members::IntSet(_)) && !encoding::is_integer(&member) {
+            self.convert(false);
+        }
+        if self.len() >= MAX_COMPACT_ENTRIES {
+            self.convert(true);
+        }
+        match &mut self.members {
+            Members::IntSet(members) => match int_search(members, &member) {
+                Ok(_) => false,
+                Err(i) => {
+                    members.insert(i, member);
+                    true
+                }
+            },
+            Members::ListPack(members) => {
+                if members.contains(&member) {
+                    return false;
+                }
+                members.push(member);
+                true
+            }
+            Members::HashTable(members) => members.insert(member),
+        }
+    }
+
+    /// 删除成员，返回成员是否存在
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match &mut self.members {
+            Members::IntSet(members) => match int_search(members, member) {
+                Ok(i) => {
+                    members.remove(i);
+                    true
+                }
+                Err(_) => false,
+            },
+            Members::ListPack(members) => match members.iter().position(|m| m == member) {
+                Some(i) => {
+                    members.swap_remove(i);
+                    true
+                }
+                None => false,
+            },
+            Members::HashTable(members) => members.remove(member),
+        }
+    }
+
+    /// 遍历成员: intset按数值升序，listpack按插入顺序(删除会打乱)，hashtable顺序不确定
+    pub fn iter(&self) -> impl Iterator<Item = &Vec<u8>> + Clone {
+        let (list, table) = match &self.members {
+            Members::IntSet(members) | Members::ListPack(members) => (Some(members), None),
+            Members::HashTable(members) => (None, Some(members)),
+        };
+        list.into_iter().flatten().chain(table.into_iter().flatten())
+    }
+
+    /// 按阈值转换编码: 超过阈值时intset转换为listpack或hashtable，listpack转换为hashtable
+    pub fn fit(&mut self, thresholds: &EncodingThresholds) {
+        let fits_listpack = |members: &[Vec<u8>]| {
+            members.len() <= thresholds.set_max_listpack_entries
+                && members.iter().all(|m| m.len() <= thresholds.set_max_listpack_value)
+        };
+        match &self.members {
+            Members::IntSet(members) if members.len() > thresholds.set_max_intset_entries => {
+                self.convert(!fits_listpack(members));
+            }
+            Members::ListPack(members) if !fits_listpack(members) => self.convert(true),
+            _ => {}
+        }
+    }
+
+    /// 转换为listpack(`table`为false)或hashtable；不会从hashtable转换回来
+    fn convert(&mut self, table: bool) {
+        let members = match &mut self.members {
+            Members::IntSet(members) | Members::ListPack(members) => std::mem::take(members),
+            Members::HashTable(_) => return,
+        };
+        self.members = if table {
+            Members::HashTable(members.into_iter().collect())
+        } else {
+            Members::ListPack(members)
+        };
+    }
+}
+
+/// 在按数值排序的intset中二分查找，非整数返回Err
+fn int_search(members: &[Vec<u8>], member: &[u8]) -> Result<usize, usize> {
+    let Some(value) = parse_int(member).filter(|_| encoding::is_integer(member)) else {
+        return Err(members.len());
+    };
+    members.binary_search_by_key(&value, |m| parse_int(m).unwrap_or_default())
+}
+
+fn parse_int(member: &[u8]) -> Option<i64> {
+    std::str::from_utf8(member).ok()?.parse().ok()
+}
+
+impl Default for Set {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 成员相同即相等，与编码无关
+impl PartialEq for Set {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|member| other.contains(member))
+    }
+}
+
+/// 一次收集所有成员: 先去重再按结果的大小选择编码，避免逐个插入紧凑编码的O(n²)
+impl FromIterator<Vec<u8>> for Set {
+    fn from_iter<I: IntoIterator<Item = Vec<u8>>>(iter: I) -> Self {
+        let table: HashSet<Vec<u8>> = iter.into_iter().collect();
+        if table.len() > MAX_COMPACT_ENTRIES {
+            return Self {
+                members: Members::HashTable(table),
+            };
+        }
+        let mut members: Vec<Vec<u8>> = table.into_iter().collect();
+        if members.iter().all(|m| encoding::is_integer(m)) {
+            members.sort_by_key(|m| parse_int(m).unwrap_or_default());
+            Self {
+                members: Members::IntSet(members),
+            }
+        } else {
+            Self {
+                members: Members::ListPack(members),
+            }
+        }
+    }
+}
+
+impl IntoIterator for Set {
+    type Item = Vec<u8>;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        let (list, table) = match self.members {
+            Members::IntSet(members) | Members::ListPack(members) => (Some(members), None),
+            Members::HashTable(members) => (None, Some(members)),
+        };
+        list.into_iter().flatten().chain(table.into_iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(set: &Set) -> Vec<&[u8]> {
+        set.iter().map(Vec::as_slice).collect()
+    }
+
+    #[test]
+    fn test_intset_and_conversion() {
+        let mut set = Set::new();
+        for member in ["10", "-3", "7", "10"] {
+            set.insert(member.as_bytes().to_vec());
+        }
+        assert_eq!(set.encoding(), "intset");
+        assert_eq!(members(&set), vec![&b"-3"[..], b"7", b"10"]);
+        assert!(set.contains(b"7"));
+        // 非规范形式的整数不是intset的成员
+        assert!(!set.contains(b"07"));
+        assert!(set.remove(b"-3"));
+        assert!(!set.remove(b"-3"));
+
+        // 非整数成员转换为listpack，保留已有成员
+        assert!(set.insert(b"07".to_vec()));
+        assert_eq!(set.encoding(), "listpack");
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(b"07") && set.contains(b"10"));
+
+        let thresholds = EncodingThresholds {
+            set_max_listpack_entries: 3,
+            ..Default::default()
+        };
+        set.fit(&thresholds);
+        assert_eq!(set.encoding(), "listpack");
+        set.insert(b"x".to_vec());
+        set.fit(&thresholds);
+        assert_eq!(set.encoding(), "hashtable");
+        // 不会转换回紧凑编码
+        set.remove(b"x");
+        set.fit(&thresholds);
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains(b"07") && !set.contains(b"x"));
+    }
+
+    #[test]
+    fn test_intset_thresholds() {
+        let thresholds = EncodingThresholds {
+            set_max_intset_entries: 4,
+            ..Default::default()
+        };
+        let mut set: Set = (0..5).map(|i: i32| i.to_string().into_bytes()).collect();
+        assert_eq!(set.encoding(), "intset");
+        set.fit(&thresholds);
+        assert_eq!(set.encoding(), "listpack");
+
+        // 超过intset阈值、也放不进listpack时直接转换为hashtable
+        let mut set: Set = (0..200).map(|i: i32| i.to_string().into_bytes()).collect();
+        set.fit(&thresholds);
+        assert_eq!(set.encoding(), "hashtable");
+
+        let mut set = Set::new();
+        for i in 0..=MAX_COMPACT_ENTRIES {
+            set.insert(i.to_string().into_bytes());
+        }
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), MAX_COMPACT_ENTRIES + 1);
+        let set: Set = (0..=MAX_COMPACT_ENTRIES).map(|i| i.to_string().into_bytes()).collect();
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.clone().into_iter().count(), MAX_COMPACT_ENTRIES + 1);
+
+        let a: Set = ["a", "b"].map(|m| m.as_bytes().to_vec()).into_iter().collect();
+        let b: Set = ["b", "a"].map(|m| m.as_bytes().to_vec()).into_iter().collect();
+        assert_eq!(a, b);
+    }
+}
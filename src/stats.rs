@@ -0,0 +1,303 @@
+//! 运行时统计 - INFO stats中的计数器
+//!
+//! 计数器都是原子变量，执行路径上只做一次`fetch_add`；每秒操作数按Redis的方法估算:
+//! 每100毫秒采样一次命令总数，取最近16个采样区间速率的平均值。
+//!
+//! Rust特点展示:
+//! - 原子类型实现无锁计数，`Relaxed`顺序足够(计数之间没有先后依赖)
+//! - 固定长度数组作为环形缓冲区，不需要堆分配
+
+use crate::memory;
+use crate::store::Store;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// 采样间隔
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 缓存模式下报告命中率的间隔
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 参与平均的采样数
+const SAMPLES: usize = 16;
+
+/// 服务器的运行时计数器，由Store持有，所有连接共享
+#[derive(Debug, Default)]
+pub struct Stats {
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    expired_keys: AtomicU64,
+    evicted_keys: AtomicU64,
+    evicted_clients: AtomicU64,
+    total_connections: AtomicU64,
+    total_commands: AtomicU64,
+    ops: Mutex<OpsSampler>,
+}
+
+impl Stats {
+    /// 读取键时记录命中或未命中(已过期的键算未命中)
+    pub fn record_lookup(&self, hit: bool) {
+        let counter = if hit { &self.keyspace_hits } else { &self.keyspace_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录删除的过期键(惰性删除和定期清理)
+    pub fn record_expired(&self, count: u64) {
+        self.expired_keys.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录因内存不足被淘汰的键
+    pub fn record_evicted(&self, count: u64) {
+        self.evicted_keys.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录因maxmemory-clients被断开的连接
+    pub fn record_evicted_clients(&self, count: u64) {
+        self.evicted_clients.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录接受的连接
+    pub fn record_connection(&self) {
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录执行的命令
+    pub fn record_commands(&self, count: u64) {
+        self.total_commands.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn keyspace_hits(&self) -> u64 {
+        self.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn keyspace_misses(&self) -> u64 {
+        self.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys.load(Ordering::Relaxed)
+    }
+
+    pub fn evicted_clients(&self) -> u64 {
+        self.evicted_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn total_connections(&self) -> u64 {
+        self.total_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands(&self) -> u64 {
+        self.total_commands.load(Ordering::Relaxed)
+    }
+
+    /// 采样命令总数，`now_ms`是采样时刻(毫秒)
+    pub fn sample(&self, now_ms: u64) {
+        let total = self.total_commands();
+        self.ops.lock().unwrap_or_else(PoisonError::into_inner).sample(now_ms, total);
+    }
+
+    /// 最近的每秒操作数
+    pub fn instantaneous_ops_per_sec(&self) -> u64 {
+        self.ops.lock().unwrap_or_else(PoisonError::into_inner).average()
+    }
+
+    /// INFO中的`# Stats`部分
+    pub fn info(&self) -> String {
+        format!(
+            "# Stats\r\n\
+             total_connections_received:{}\r\n\
+             total_commands_processed:{}\r\n\
+             instantaneous_ops_per_sec:{}\r\n\
+             keyspace_hits:{}\r\n\
+             keyspace_misses:{}\r\n\
+             expired_keys:{}\r\n\
+             evicted_keys:{}\r\n\
+             evicted_clients:{}\r\n",
+            self.total_connections(),
+            self.total_commands(),
+            self.instantaneous_ops_per_sec(),
+            self.keyspace_hits(),
+            self.keyspace_misses(),
+            self.expired_keys(),
+            self.evicted_keys(),
+            self.evicted_clients()
+        )
+    }
+}
+
+/// 每秒操作数的滚动采样
+#[derive(Debug, Default)]
+struct OpsSampler {
+    /// 上一次采样的时刻和命令总数
+    last: Option<(u64, u64)>,
+    /// 最近各采样区间的速率(次/秒)，环形缓冲区
+    rates: [u64; SAMPLES],
+    /// 下一个写入位置
+    index: usize,
+    /// 已写入的采样数，最多SAMPLES
+    filled: usize,
+}
+
+impl OpsSampler {
+    fn sample(&mut self, now_ms: u64, total: u64) {
+        if let Some((last_ms, last_total)) = self.last {
+            let elapsed = now_ms.saturating_sub(last_ms);
+            if elapsed == 0 {
+                return;
+            }
+            self.rates[self.index] = total.saturating_sub(last_total) * 1000 / elapsed;
+            self.index = (self.index + 1) % SAMPLES;
+            self.filled = (self.filled + 1).min(SAMPLES);
+        }
+        self.last = Some((now_ms, total));
+    }
+
+    fn average(&self) -> u64 {
+        match self.filled {
+            0 => 0,
+            filled => self.rates[..filled].iter().sum::<u64>() / filled as u64,
+        }
+    }
+}
+
+/// 后台任务: 定期采样命令总数，时间取自Store的时钟
+pub async fn sample_task(store: Store) {
+    let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        store.stats().sample(store.clock().now_ms());
+    }
+}
+
+/// 后台任务: 缓存模式下定期在日志中报告这段时间的命中率、淘汰的键数和内存用量
+pub async fn report_task(store: Store, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    // 第一次tick立即完成，从启动时开始计算
+    ticker.tick().await;
+    let stats = store.stats();
+    let mut last = (stats.keyspace_hits(), stats.keyspace_misses(), stats.evicted_keys());
+    loop {
+        ticker.tick().await;
+        let now = (stats.keyspace_hits(), stats.keyspace_misses(), stats.evicted_keys());
+        let (hits, misses) = (now.0 - last.0, now.1 - last.1);
+        let hit_ratio = match hits + misses {
+            0 => 0.0,
+            lookups => hits as f64 * 100.0 / lookups as f64,
+        };
+        tracing::info!(
+            hits,
+            misses,
+            hit_ratio = format!("{:.2}%", hit_ratio),
+            evicted_keys = now.2 - last.2,
+            keys = store.dbsize(),
+            used_memory = memory::human(memory::used_memory() as u64),
+            "缓存统计"
+        );
+        last = now;
+    }
+}
+
+/// 按cache-mode启动或停止`report_task`
+///
+/// 启动时和每次重新加载配置后调用`update`，缓存模式在运行时打开或关闭都会生效
+///
+/// Rust特点: 持有任务的JoinHandle，关闭时abort取消任务
+pub struct CacheReporter {
+    store: Store,
+    interval: Duration,
+    runtime: tokio::runtime::Handle,
+    task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl CacheReporter {
+    /// 必须在tokio运行时中创建，之后的`update`可以在任意线程调用
+    pub fn new(store: Store, interval: Duration) -> Self {
+        Self {
+            store,
+            interval,
+            runtime: tokio::runtime::Handle::current(),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// 缓存模式打开且任务没有运行时启动，关闭时停止
+    pub fn update(&self, cache_mode: bool) {
+        let mut task = self.task.lock().unwrap_or_else(PoisonError::into_inner);
+        match (cache_mode, task.take()) {
+            (true, None) => {
+                let report = report_task(self.store.clone(), self.interval);
+                *task = Some(self.runtime.spawn(report));
+            }
+            (true, running) => *task = running,
+            (false, running) => running.into_iter().for_each(|task| task.abort()),
+        }
+    }
+
+    /// 报告任务是否在运行
+    pub fn is_running(&self) -> bool {
+        self.task.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instantaneous_ops() {
+        let stats = Stats::default();
+        assert_eq!(stats.instantaneous_ops_per_sec(), 0);
+
+        // 每100毫秒50条命令，即500次/秒
+        stats.sample(0);
+        for tick in 1..=20 {
+            stats.record_commands(50);
+            stats.sample(tick * 100);
+        }
+        assert_eq!(stats.instantaneous_ops_per_sec(), 500);
+
+        // 空闲之后速率逐渐回落，16个采样后归零
+        for tick in 21..=28 {
+            stats.sample(tick * 100);
+        }
+        assert_eq!(stats.instantaneous_ops_per_sec(), 250);
+        for tick in 29..=36 {
+            stats.sample(tick * 100);
+        }
+        assert_eq!(stats.instantaneous_ops_per_sec(), 0);
+    }
+
+    #[test]
+    fn test_info() {
+        let stats = Stats::default();
+        stats.record_lookup(true);
+        stats.record_lookup(false);
+        stats.record_lookup(false);
+        stats.record_connection();
+        let info = stats.info();
+        assert!(info.contains("keyspace_hits:1\r\n"));
+        assert!(info.contains("keyspace_misses:2\r\n"));
+        assert!(info.contains("total_connections_received:1\r\n"));
+        assert!(info.contains("evicted_keys:0\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_reporter() {
+        let reporter = CacheReporter::new(Store::new(), REPORT_INTERVAL);
+        reporter.update(false);
+        assert!(!reporter.is_running());
+        // 重新加载打开缓存模式时启动，重复打开不会启动第二个任务
+        reporter.update(true);
+        assert!(reporter.is_running());
+        reporter.update(true);
+        assert!(reporter.is_running());
+        reporter.update(false);
+        assert!(!reporter.is_running());
+    }
+
+}
@@ -6,66 +6,405 @@
 //! - 生命周期和所有权
 //! - Option类型处理可能为空的值
 
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use crate::blocking::BlockedClients;
+use crate::cache::CacheLoader;
+use crate::clients::ClientRegistry;
+use crate::clock::{Clock, SystemClock};
+use crate::encoding::{self, EncodingThresholds};
+use crate::error::{RedisError, RedisResult};
+use crate::event::{KeyWatcher, StoreEvent, EVENT_CHANNEL_CAPACITY};
+use crate::hash::Hash;
+use crate::keyspace::Keyspace;
+use crate::memory::{self, EvictionPolicy};
+use crate::module::{ModuleRegistry, ModuleValue};
+use crate::probabilistic::{BloomFilter, CuckooFilter};
+use crate::quicklist::QuickList;
+use crate::ratelimit::{Throttle, ThrottleResult};
+use crate::set::Set;
+use crate::stats::Stats;
+use crate::stream::Stream;
+use crate::timeseries::TimeSeries;
+use crate::zset::SortedSet;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// 哈希: 字段 -> 值，字段可以单独设置过期时间
+pub type HashValue = Hash;
+
+/// 列表: 分段存储，两端都可以高效地插入和弹出
+pub type ListValue = QuickList;
+
+/// 集合: 无序且不重复的成员
+pub type SetValue = Set;
+
+/// 值的类型
+///
+/// Rust特点: 枚举让每种类型携带各自的数据结构，match保证所有类型都被处理
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// 字符串(二进制安全)
+    String(Vec<u8>),
+    /// 哈希
+    Hash(HashValue),
+    /// 列表
+    List(ListValue),
+    /// 集合
+    Set(SetValue),
+    /// 有序集合
+    SortedSet(SortedSet),
+    /// 流
+    Stream(Stream),
+    /// JSON文档
+    Json(JsonValue),
+    /// 时间序列
+    TimeSeries(TimeSeries),
+    /// 布隆过滤器
+    Bloom(BloomFilter),
+    /// 布谷鸟过滤器
+    Cuckoo(CuckooFilter),
+    /// 模块注册的类型
+    Module(ModuleValue),
+}
+
+impl Value {
+    /// TYPE命令返回的类型名
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+            Value::Json(_) => "ReJSON-RL",
+            Value::TimeSeries(_) => "TSDB-TYPE",
+            Value::Bloom(_) => "MBbloom--",
+            Value::Cuckoo(_) => "MBbloomCF",
+            Value::Module(value) => value.type_name(),
+        }
+    }
+
+    /// OBJECT ENCODING返回的编码名称，模块类型(JSON等)与Redis一样是`raw`
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(data) => encoding::string_encoding(data),
+            Value::Hash(hash) => hash.encoding(),
+            Value::List(list) if list.chunk_count() <= 1 => "listpack",
+            Value::List(_) => "quicklist",
+            Value::Set(set) => set.encoding(),
+            Value::SortedSet(zset) => zset.encoding(),
+            Value::Stream(_) => "stream",
+            _ => "raw",
+        }
+    }
+
+    /// 写入后按阈值转换集合类型的编码
+    fn fit_encoding(&mut self, thresholds: &EncodingThresholds) {
+        match self {
+            Value::Hash(hash) => hash.fit(thresholds),
+            Value::Set(set) => set.fit(thresholds),
+            Value::SortedSet(zset) => zset.fit(thresholds),
+            _ => {}
+        }
+    }
+}
+
+/// 可以按类型读写的值，`read_as`/`update_as`通过它取出和放回具体类型
+///
+/// Rust特点: 为每种数据类型实现同一个trait，通用的读写逻辑只写一次
+pub trait ValueKind: Sized {
+    /// 值是该类型时返回引用
+    fn as_kind(value: &Value) -> Option<&Self>;
+    /// 值是该类型时取出，否则原样返回
+    fn into_kind(value: Value) -> Result<Self, Value>;
+    /// 放回Value
+    fn into_value(self) -> Value;
+}
+
+/// `update_as`/`update_all_as`从表中取出、正在交给闭包修改的值
+///
+/// 正常返回时调用者先取走`current`；闭包panic时析构把仍在这里的值放回表中
+///
+/// Rust特点: 栈展开时Drop同样会运行，起到finally的作用
+struct TakenValues<'a, V: ValueKind> {
+    store: &'a mut Keyspace<StoredValue>,
+    keys: &'a [&'a str],
+    current: Vec<Option<V>>,
+    expires_at: Vec<Option<u64>>,
+}
+
+impl<V: ValueKind> Drop for TakenValues<'_, V> {
+    fn drop(&mut self) {
+        let values = self.keys.iter().zip(self.current.drain(..)).zip(&self.expires_at);
+        for ((key, value), expires_at) in values {
+            if let Some(value) = value {
+                let value = StoredValue::with_parts(value.into_value(), *expires_at);
+                self.store.insert(key.to_string(), value);
+            }
+        }
+    }
+}
+
+/// 为Value的各个变体实现ValueKind
+macro_rules! value_kinds {
+    ($($ty:ty => $variant:ident),* $(,)?) => {$(
+        impl ValueKind for $ty {
+            fn as_kind(value: &Value) -> Option<&Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn into_kind(value: Value) -> Result<Self, Value> {
+                match value {
+                    Value::$variant(v) => Ok(v),
+                    other => Err(other),
+                }
+            }
+
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+        }
+    )*};
+}
+
+value_kinds! {
+    Vec<u8> => String,
+    HashValue => Hash,
+    ListValue => List,
+    SetValue => Set,
+    SortedSet => SortedSet,
+    Stream => Stream,
+    JsonValue => Json,
+    TimeSeries => TimeSeries,
+    BloomFilter => Bloom,
+    CuckooFilter => Cuckoo,
+    ModuleValue => Module,
+}
+
+/// 对错误类型的键执行命令时的错误
+fn wrong_type() -> RedisError {
+    RedisError::WrongType
+}
+
+/// LRU时钟: 进程启动以来的毫秒数
+///
+/// 只用于比较键的访问先后，与Store的时钟无关，模拟时间的测试中同样单调递增
+fn lru_clock() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
 
 /// 存储的值，包含数据和可选的过期时间
 ///
 /// Rust特点: 结构体组合多个字段，Option表示可选值
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct StoredValue {
     /// 实际数据
-    data: Vec<u8>,
-    /// 过期时间点 - None表示永不过期
-    expires_at: Option<Instant>,
+    value: Value,
+    /// 过期时间(Unix毫秒) - None表示永不过期
+    ///
+    /// 存绝对时间而不是Instant，才能写入持久化文件并在重启后继续生效
+    expires_at: Option<u64>,
+    /// 最近一次写入或读取的LRU时钟，淘汰时使用
+    ///
+    /// Rust特点: 原子类型让持有读锁的读取也能更新它
+    last_access: AtomicU64,
+}
+
+/// 原子字段不能派生Clone，手动实现
+impl Clone for StoredValue {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            expires_at: self.expires_at,
+            last_access: AtomicU64::new(self.last_access.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 impl StoredValue {
     /// 创建新的存储值
     pub fn new(data: Vec<u8>) -> Self {
+        Self::from_value(Value::String(data))
+    }
+
+    /// 创建任意类型的存储值
+    pub fn from_value(value: Value) -> Self {
+        Self::with_parts(value, None)
+    }
+
+    /// 由值和过期时间组成，访问时间为当前
+    fn with_parts(value: Value, expires_at: Option<u64>) -> Self {
         Self {
-            data,
-            expires_at: None,
+            value,
+            expires_at,
+            last_access: AtomicU64::new(lru_clock()),
         }
     }
 
-    /// 创建带过期时间的存储值
+    /// 记录一次访问
+    fn touch(&self) {
+        self.last_access.store(lru_clock(), Ordering::Relaxed);
+    }
+
+    /// 创建带过期时间的存储值，`expires_at`为Unix毫秒
     ///
     /// Rust特点: 方法链式调用，返回Self实现构建器模式
-    pub fn with_expiry(mut self, ttl: Duration) -> Self {
-        self.expires_at = Some(Instant::now() + ttl);
+    pub fn with_expiry(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
         self
     }
 
-    /// 检查是否已过期
-    pub fn is_expired(&self) -> bool {
+    /// 过期时间(Unix毫秒)
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    /// 检查在给定时间(Unix毫秒)是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
         match self.expires_at {
-            Some(expires_at) => Instant::now() > expires_at,
+            Some(expires_at) => now > expires_at,
             None => false,
         }
     }
 
-    /// 获取数据的引用
+    /// 哈希中是否有在给定时间已过期的字段
+    fn has_expired_fields(&self, now: u64) -> bool {
+        matches!(&self.value, Value::Hash(hash) if hash.has_expired(now))
+    }
+
+    /// 删除哈希中已过期的字段，返回是否因此变为空哈希(应删除整个键)
+    fn purge_expired_fields(&mut self, now: u64) -> bool {
+        match &mut self.value {
+            Value::Hash(hash) => hash.purge_expired(now) > 0 && hash.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// 获取字符串数据的引用，其他类型返回None
     ///
     /// Rust特点: 返回引用避免不必要的复制
-    pub fn data(&self) -> &[u8] {
-        &self.data
+    pub fn data(&self) -> Option<&[u8]> {
+        match &self.value {
+            Value::String(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// 获取值
+    pub fn value(&self) -> &Value {
+        &self.value
     }
 
     /// 获取剩余生存时间(毫秒)
-    pub fn ttl_ms(&self) -> Option<i64> {
+    pub fn ttl_ms(&self, now: u64) -> Option<i64> {
         self.expires_at.map(|expires_at| {
-            let now = Instant::now();
             if now > expires_at {
                 -1
             } else {
-                (expires_at - now).as_millis() as i64
+                i64::try_from(expires_at - now).unwrap_or(i64::MAX)
             }
         })
     }
 }
 
+/// `update_with`的闭包对键做出的修改
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Update {
+    /// 保持不变，不产生变更事件
+    Keep,
+    /// 写入新值(保留已有的过期时间)
+    Set(Vec<u8>),
+    /// 写入新值并设置过期时间
+    SetWithExpiry(Vec<u8>, Duration),
+    /// 删除该键
+    Delete,
+}
+
+/// SET命令的过期时间选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetExpiry {
+    /// EX/PX: 从现在起经过的时长
+    After(Duration),
+    /// EXAT/PXAT: Unix毫秒时间戳，已经过去时写入的键立即过期
+    At(u64),
+    /// KEEPTTL: 保留已有的过期时间
+    Keep,
+}
+
+/// SET命令的写入条件和选项，由`Store::set_with_options`使用
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    /// None表示清除已有的过期时间
+    pub expiry: Option<SetExpiry>,
+    /// 仅当键不存在时写入
+    pub nx: bool,
+    /// 仅当键存在时写入
+    pub xx: bool,
+    /// 返回旧值(SET ... GET)
+    pub get: bool,
+}
+
+/// 快照迭代器产生的一项
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotEntry {
+    /// 键
+    pub key: String,
+    /// 值
+    pub value: Value,
+    /// 过期时间(Unix毫秒)，持久化时可以直接写入
+    pub expires_at: Option<u64>,
+    /// 读取时的剩余生存时间
+    pub ttl: Option<Duration>,
+}
+
+/// 分批读取的数据集快照，由`Store::iter_snapshot`创建
+///
+/// Rust特点: 实现Iterator后可以直接用for循环和各种适配器
+#[derive(Debug)]
+pub struct Snapshot {
+    store: Store,
+    /// 下一批的游标；遍历结束后为None
+    cursor: Option<u64>,
+    batch: usize,
+    buffer: VecDeque<SnapshotEntry>,
+}
+
+impl Iterator for Snapshot {
+    type Item = SnapshotEntry;
+
+    fn next(&mut self) -> Option<SnapshotEntry> {
+        while self.buffer.is_empty() {
+            let cursor = self.cursor?;
+
+            // 每批只持有一次读锁
+            let store = self.store.read_data();
+            let now = self.store.clock.now_ms();
+            let (next, batch) = store.scan(cursor, self.batch);
+            let live = batch.into_iter().filter(|(_, v)| !v.is_expired(now));
+            self.buffer.extend(live.map(|(key, value)| SnapshotEntry {
+                key: key.clone(),
+                value: value.value.clone(),
+                expires_at: value.expires_at,
+                ttl: value.expires_at.map(|at| Duration::from_millis(at - now)),
+            }));
+            self.cursor = (next != 0).then_some(next);
+        }
+        self.buffer.pop_front()
+    }
+}
+
 /// 键值存储 - 线程安全的数据存储
 ///
 /// Rust特点:
@@ -77,14 +416,276 @@ pub struct Store {
     /// 内部存储
     ///
     /// Arc<RwLock<...>> 是Rust中实现线程安全共享状态的惯用方式
-    inner: Arc<RwLock<HashMap<String, StoredValue>>>,
+    inner: Arc<RwLock<Keyspace<StoredValue>>>,
+    /// 变更事件广播通道 - Sender可以克隆，所有克隆共享同一个通道
+    events: broadcast::Sender<StoreEvent>,
+    /// 时钟 - 所有过期判断都通过它获取当前时间
+    clock: Arc<dyn Clock>,
+    /// 命令执行闸门 - 普通命令共享持有，EXEC独占持有以保证事务原子性
+    exec_gate: Arc<RwLock<()>>,
+    /// 运行时统计(命中率、过期键数等)
+    stats: Arc<Stats>,
+    /// 可选的缓存加载器 - 未命中时从后端加载，写入时同步到后端
+    loader: Option<Arc<dyn CacheLoader>>,
+    /// 已加载的模块
+    modules: Arc<ModuleRegistry>,
+    /// 客户端连接的内存占用(maxmemory-clients)
+    clients: Arc<ClientRegistry>,
+    /// 阻塞命令的等待队列，键被写入时唤醒
+    blocked: Arc<BlockedClients>,
+    /// 集合类型紧凑编码的转换阈值
+    encodings: Arc<RwLock<EncodingThresholds>>,
 }
 
 impl Store {
     /// 创建新的空存储
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建存储
+    ///
+    /// Rust特点: 依赖注入 - 测试时传入ManualClock即可控制时间流逝
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let stats = Arc::new(Stats::default());
         Self {
-            inner: Arc::new(RwLock::new(HashMap::new())),
+            inner: Arc::new(RwLock::new(Keyspace::new())),
+            events,
+            clock,
+            exec_gate: Arc::new(RwLock::new(())),
+            clients: Arc::new(ClientRegistry::new(stats.clone())),
+            stats,
+            loader: None,
+            modules: Arc::default(),
+            blocked: Arc::default(),
+            encodings: Arc::default(),
+        }
+    }
+
+    /// 设置缓存加载器，Store成为后端数据库前面的缓存层
+    pub fn with_loader(mut self, loader: Arc<dyn CacheLoader>) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    /// 模块注册表
+    pub fn modules(&self) -> &ModuleRegistry {
+        &self.modules
+    }
+
+    /// 客户端连接的内存占用
+    pub fn clients(&self) -> &Arc<ClientRegistry> {
+        &self.clients
+    }
+
+    /// 阻塞命令(BLPOP等)的等待队列
+    pub fn blocked_clients(&self) -> &Arc<BlockedClients> {
+        &self.blocked
+    }
+
+    /// 是否设置了缓存加载器
+    pub fn has_loader(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    /// 读穿透: 缓存未命中时通过加载器从后端读取并写入缓存
+    ///
+    /// 加载期间其他连接写入了该键时，以已写入的值为准，加载结果被丢弃。
+    /// 没有设置加载器时与`get`相同
+    pub async fn get_or_load(&self, key: &str) -> RedisResult<Option<Vec<u8>>> {
+        if let Some(value) = self.get_checked(key)? {
+            return Ok(Some(value));
+        }
+        let Some(loader) = &self.loader else {
+            return Ok(None);
+        };
+        let Some(loaded) = loader.load(key).await? else {
+            return Ok(None);
+        };
+
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
+        if let Some(current) = store.get(key) {
+            return current.data().map(|data| Some(data.to_vec())).ok_or_else(wrong_type);
+        }
+
+        let mut value = StoredValue::new(loaded.value.clone());
+        if let Some(ttl) = loaded.ttl {
+            value = value.with_expiry(deadline(now, ttl));
+        }
+        store.insert(key.to_string(), value);
+        self.notify(|| StoreEvent::Set { key: key.to_string() });
+        Ok(Some(loaded.value))
+    }
+
+    /// 写穿透: 先写入后端，成功后再更新缓存
+    ///
+    /// 后端写入失败时缓存保持不变，避免缓存中出现后端没有的数据
+    pub async fn set_through(&self, key: &str, value: Vec<u8>) -> RedisResult<()> {
+        if let Some(loader) = &self.loader {
+            loader.write(key, Some(&value)).await?;
+        }
+        self.set(key.to_string(), value);
+        Ok(())
+    }
+
+    /// 写穿透的删除: 先从后端删除，成功后再删除缓存中的键
+    pub async fn del_through(&self, key: &str) -> RedisResult<bool> {
+        if let Some(loader) = &self.loader {
+            loader.write(key, None).await?;
+        }
+        Ok(self.del(key))
+    }
+
+    /// 获取数据的读锁
+    ///
+    /// 命令执行中的panic会使锁中毒。连接捕获panic后继续服务，所以这里忽略中毒标记、
+    /// 继续使用数据，否则之后每条命令都会panic
+    fn read_data(&self) -> RwLockReadGuard<'_, Keyspace<StoredValue>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 获取数据的写锁，同样忽略中毒标记
+    fn write_data(&self) -> RwLockWriteGuard<'_, Keyspace<StoredValue>> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 获取执行单条命令的共享许可
+    ///
+    /// 多个连接可以同时持有；与`exclusive_access`互斥
+    pub fn shared_access(&self) -> RwLockReadGuard<'_, ()> {
+        self.exec_gate.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 获取独占执行许可 - 持有期间其他连接的命令都会等待
+    ///
+    /// Rust特点: RAII守卫，离开作用域时自动释放
+    pub fn exclusive_access(&self) -> RwLockWriteGuard<'_, ()> {
+        self.exec_gate.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 获取存储使用的时钟
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// 订阅存储变更事件
+    ///
+    /// 每个订阅者从订阅时刻开始接收事件；处理过慢的订阅者会收到
+    /// `RecvError::Lagged`，丢失的事件不会重发。
+    ///
+    /// Rust特点: broadcast通道让多个消费者各自获得事件的副本
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.events.subscribe()
+    }
+
+    /// 创建单个键的变化监听器，可以反复等待该键的变化
+    pub fn key_watcher(&self, key: impl Into<String>) -> KeyWatcher {
+        KeyWatcher::new(key.into(), self.subscribe())
+    }
+
+    /// 等待键被修改或删除，进程内的消费者无需轮询
+    ///
+    /// 订阅在调用时立即完成，调用之后、await之前发生的变化同样会被捕获。
+    /// 返回None的含义见`KeyWatcher::changed`
+    ///
+    /// Rust特点: 返回impl Future，future拥有自己的接收端，可以移动到其他任务中await
+    pub fn watch_key(
+        &self,
+        key: impl Into<String>,
+    ) -> impl std::future::Future<Output = Option<StoreEvent>> + Send + 'static {
+        let mut watcher = self.key_watcher(key);
+        async move { watcher.changed().await }
+    }
+
+    /// 累计删除的过期键数，对应INFO中的expired_keys
+    pub fn expired_keys(&self) -> u64 {
+        self.stats.expired_keys()
+    }
+
+    /// 运行时统计
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// 读取一个未过期的键
+    ///
+    /// 遇到已过期的条目时把它真正删除(惰性删除)，而不是只当作不存在；哈希中过期的字段同样
+    /// 先删除再读取。读锁不能直接升级，所以先释放读锁再获取写锁，并在写锁下重新检查。
+    /// 结果计入keyspace_hits/keyspace_misses
+    fn read_live<T>(&self, key: &str, f: impl FnOnce(&StoredValue, u64) -> T) -> Option<T> {
+        loop {
+            let expired = {
+                let store = self.read_data();
+                let now = self.clock.now_ms();
+                match store.get(key) {
+                    Some(v) if !v.is_expired(now) && !v.has_expired_fields(now) => {
+                        self.stats.record_lookup(true);
+                        v.touch();
+                        return Some(f(v, now));
+                    }
+                    Some(v) => v.is_expired(now),
+                    None => {
+                        self.stats.record_lookup(false);
+                        return None;
+                    }
+                }
+            };
+            self.remove_expired(&[key]);
+            if expired {
+                self.stats.record_lookup(false);
+                return None;
+            }
+        }
+    }
+
+    /// 删除仍处于过期状态的键，返回删除的数量
+    ///
+    /// 释放读锁到获取写锁之间，键可能已被其他连接重新写入，这种键不能删除
+    fn remove_expired<K: AsRef<str>>(&self, keys: &[K]) -> usize {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        keys.iter()
+            .filter(|key| self.purge_expired(&mut store, key.as_ref(), now))
+            .count()
+    }
+
+    /// 已持有写锁时，如果键已过期就删除它，返回是否删除
+    ///
+    /// 写命令在修改前调用，保证过期的旧值不会被当作现有值，并且计入统计。
+    /// 哈希中过期的字段也在这里删除，最后一个字段过期时整个键随之删除
+    fn purge_expired(&self, store: &mut Keyspace<StoredValue>, key: &str, now: u64) -> bool {
+        let Some(v) = store.get_mut(key) else {
+            return false;
+        };
+        if !v.is_expired(now) && !v.purge_expired_fields(now) {
+            return false;
+        }
+        store.remove(key);
+        self.stats.record_expired(1);
+        self.notify(|| StoreEvent::Expired { key: key.to_string() });
+        true
+    }
+
+    /// 发布变更事件，并唤醒在被写入的键上阻塞的连接
+    ///
+    /// 没有订阅者也没有阻塞的连接时不构造事件，避免写入路径上多余的分配
+    fn notify(&self, event: impl FnOnce() -> StoreEvent) {
+        let subscribed = self.events.receiver_count() > 0;
+        if !subscribed && self.blocked.is_empty() {
+            return;
+        }
+        let event = event();
+        if let StoreEvent::Set { key } | StoreEvent::Rename { to: key, .. } = &event {
+            self.blocked.signal(key);
+        }
+        if subscribed {
+            let _ = self.events.send(event);
         }
     }
 
@@ -94,138 +695,374 @@ impl Store {
     /// - &self 表示不可变借用，但内部使用RwLock实现内部可变性
     /// - write() 获取写锁，保证独占访问
     pub fn set(&self, key: String, value: Vec<u8>) {
-        let mut store = self.inner.write().unwrap();
+        let mut store = self.write_data();
+        self.notify(|| StoreEvent::Set { key: key.clone() });
         store.insert(key, StoredValue::new(value));
     }
 
     /// 设置键值对，带过期时间
     pub fn set_with_expiry(&self, key: String, value: Vec<u8>, ttl: Duration) {
-        let mut store = self.inner.write().unwrap();
-        store.insert(key, StoredValue::new(value).with_expiry(ttl));
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.notify(|| StoreEvent::Set { key: key.clone() });
+        store.insert(key, StoredValue::new(value).with_expiry(deadline(now, ttl)));
     }
 
-    /// 获取值
+    /// 批量设置键值对，在同一次加锁中完成，其他连接不会看到只写入了一部分的状态
+    pub fn mset(&self, pairs: Vec<(String, Vec<u8>)>) {
+        let mut store = self.write_data();
+        for (key, value) in pairs {
+            self.notify(|| StoreEvent::Set { key: key.clone() });
+            store.insert(key, StoredValue::new(value));
+        }
+    }
+
+    /// 仅当所有键都不存在时批量设置，返回是否设置
+    ///
+    /// 检查和写入持有同一把写锁，要么全部写入，要么一个都不写
+    pub fn msetnx(&self, pairs: Vec<(String, Vec<u8>)>) -> bool {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        // 已过期的键视为不存在，顺便删除
+        let exists = pairs
+            .iter()
+            .any(|(key, _)| !self.purge_expired(&mut store, key, now) && store.contains_key(key));
+        if exists {
+            return false;
+        }
+        for (key, value) in pairs {
+            self.notify(|| StoreEvent::Set { key: key.clone() });
+            store.insert(key, StoredValue::new(value));
+        }
+        true
+    }
+
+    /// 按SET的选项写入，条件检查、读取旧值和写入持有同一把写锁
+    ///
+    /// 返回(是否写入, 旧值)；只有`options.get`为true时才读取旧值，
+    /// 此时旧值不是字符串返回WRONGTYPE错误且不写入
+    pub fn set_with_options(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        options: SetOptions,
+    ) -> RedisResult<(bool, Option<Vec<u8>>)> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, &key, now);
+
+        let current = store.get(&key);
+        let old = match current {
+            Some(entry) if options.get => Some(entry.data().ok_or_else(wrong_type)?.to_vec()),
+            _ => None,
+        };
+        let exists = current.is_some();
+        if (options.nx && exists) || (options.xx && !exists) {
+            return Ok((false, old));
+        }
+
+        let expires_at = match options.expiry {
+            None => None,
+            Some(SetExpiry::After(ttl)) => Some(deadline(now, ttl)),
+            Some(SetExpiry::At(at_ms)) => Some(at_ms),
+            Some(SetExpiry::Keep) => current.and_then(|entry| entry.expires_at),
+        };
+        // 时间戳已经过去: 与Redis一样视为写入后立即过期，直接删除
+        if expires_at.is_some_and(|at| at < now) {
+            if store.remove(&key).is_some() {
+                self.notify(|| StoreEvent::Del { key: key.clone() });
+            }
+            return Ok((true, old));
+        }
+        self.notify(|| StoreEvent::Set { key: key.clone() });
+        store.insert(key, StoredValue::with_parts(Value::String(value), expires_at));
+        Ok((true, old))
+    }
+
+    /// 获取值，键不存在或不是字符串时返回None
     ///
     /// Rust特点:
     /// - Option<Vec<u8>> 明确表示可能不存在
     /// - read() 获取读锁，允许并发读取
     /// - Clone用于返回数据的副本，避免生命周期问题
     pub fn get(&self, key: &str) -> Option<Vec<u8>> {
-        let store = self.inner.read().unwrap();
-        store.get(key).and_then(|v| {
-            if v.is_expired() {
-                None
-            } else {
-                Some(v.data().to_vec())
-            }
-        })
+        self.read_live(key, |v, _| v.data().map(<[u8]>::to_vec)).flatten()
+    }
+
+    /// 获取字符串值，键存在但不是字符串时返回WRONGTYPE错误
+    pub fn get_checked(&self, key: &str) -> RedisResult<Option<Vec<u8>>> {
+        self.read_live(key, |v, _| v.data().map(<[u8]>::to_vec).ok_or_else(wrong_type))
+            .transpose()
     }
 
     /// 删除键
     ///
     /// 返回是否成功删除
     pub fn del(&self, key: &str) -> bool {
-        let mut store = self.inner.write().unwrap();
-        store.remove(key).is_some()
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        let removed = !self.purge_expired(&mut store, key, now) && store.remove(key).is_some();
+        if removed {
+            self.notify(|| StoreEvent::Del { key: key.to_string() });
+        }
+        removed
     }
 
     /// 批量删除键
     ///
     /// Rust特点: 迭代器和闭包的组合使用
     pub fn del_multi(&self, keys: &[String]) -> usize {
-        let mut store = self.inner.write().unwrap();
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
         keys.iter()
-            .filter(|key| store.remove(*key).is_some())
+            .filter(|key| !self.purge_expired(&mut store, key, now) && store.remove(*key).is_some())
+            .inspect(|key| self.notify(|| StoreEvent::Del { key: key.to_string() }))
             .count()
     }
 
     /// 检查键是否存在
     pub fn exists(&self, key: &str) -> bool {
-        let store = self.inner.read().unwrap();
-        store.get(key).map_or(false, |v| !v.is_expired())
+        self.read_live(key, |_, _| ()).is_some()
     }
 
     /// 批量检查键是否存在
     pub fn exists_multi(&self, keys: &[String]) -> usize {
-        let store = self.inner.read().unwrap();
-        keys.iter()
-            .filter(|key| {
-                store
-                    .get(*key)
-                    .map_or(false, |v| !v.is_expired())
-            })
-            .count()
+        let mut expired = Vec::new();
+        let count = {
+            let store = self.read_data();
+            let now = self.clock.now_ms();
+            keys.iter()
+                .filter(|key| match store.get(*key) {
+                    Some(v) if v.is_expired(now) => {
+                        expired.push(key.as_str());
+                        false
+                    }
+                    Some(_) => true,
+                    None => false,
+                })
+                .count()
+        };
+        self.remove_expired(&expired);
+        count
     }
 
     /// 获取所有键
     ///
     /// Rust特点: 迭代器链式调用，惰性求值
     pub fn keys(&self, pattern: &str) -> Vec<String> {
-        let store = self.inner.read().unwrap();
-        store
-            .iter()
-            .filter(|(_, v)| !v.is_expired())
-            .filter(|(k, _)| Self::match_pattern(k, pattern))
+        self.keys_until(pattern, None).unwrap_or_default()
+    }
+
+    /// 获取所有键，超过`deadline`时放弃遍历并返回None
+    ///
+    /// KEYS只读取数据，中途放弃是安全的；每遍历一批键检查一次时间，避免频繁读取时钟
+    pub fn keys_until(&self, pattern: &str, deadline: Option<Instant>) -> Option<Vec<String>> {
+        const CHECK_EVERY: usize = 1024;
+
+        let mut keys = Vec::new();
+        let mut expired = Vec::new();
+        {
+            let store = self.read_data();
+            let now = self.clock.now_ms();
+            for (i, (k, v)) in store.iter().enumerate() {
+                if i % CHECK_EVERY == CHECK_EVERY - 1
+                    && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return None;
+                }
+                if !Self::match_pattern(k, pattern) {
+                    continue;
+                }
+                if v.is_expired(now) {
+                    expired.push(k.clone());
+                } else {
+                    keys.push(k.clone());
+                }
+            }
+        }
+        // 顺便删除遍历时遇到的过期键
+        self.remove_expired(&expired);
+        Some(keys)
+    }
+
+    /// 增量遍历键空间
+    ///
+    /// 游标是键的哈希值: 每次从游标处按哈希顺序返回大约`count`个键，
+    /// 以及下一个键的哈希作为新游标，遍历结束时返回0。
+    /// 遍历期间一直存在的键保证至少返回一次，与Redis的SCAN语义一致。
+    ///
+    /// 键空间维护按哈希排序的索引，每次调用只访问本批的键，开销与`count`成正比；
+    /// 与Redis一样先取键再按模式过滤，所以一批返回的键可能少于`count`甚至为空
+    pub fn scan(&self, cursor: u64, pattern: Option<&str>, count: usize) -> (u64, Vec<String>) {
+        let store = self.read_data();
+        let now = self.clock.now_ms();
+        let (next, batch) = store.scan(cursor, count.max(1));
+        let keys = batch
+            .into_iter()
+            .filter(|(k, v)| !v.is_expired(now) && pattern.is_none_or(|p| Self::match_pattern(k, p)))
             .map(|(k, _)| k.clone())
-            .collect()
+            .collect();
+        (next, keys)
     }
 
-    /// 简单的模式匹配 (* 匹配任意字符)
-    fn match_pattern(key: &str, pattern: &str) -> bool {
-        if pattern == "*" {
-            return true;
+    /// 遍历整个数据集的快照迭代器，每批读取`batch`个键
+    ///
+    /// 与SCAN一样按哈希游标遍历，每批只持有一次读锁、只复制本批的键值，
+    /// 批与批之间只保存一个u64游标，锁的持有时间与`batch`成正比，与数据集大小无关。
+    /// 遍历期间一直存在的键恰好返回一次，被删除的键会被跳过，新写入的键可能出现也可能不出现，
+    /// 值是读取该批时的最新值。
+    pub fn iter_snapshot(&self, batch: usize) -> Snapshot {
+        Snapshot {
+            store: self.clone(),
+            cursor: Some(0),
+            batch: batch.max(1),
+            buffer: VecDeque::new(),
         }
+    }
+
+    /// 估算键占用的内存(字节)，键不存在时返回None
+    pub fn memory_usage(&self, key: &str) -> Option<usize> {
+        self.read_live(key, |v, _| estimate_memory(key, v))
+    }
+
+    /// 按淘汰策略删除键，直到`over_limit`返回false或没有可以淘汰的键
+    ///
+    /// 返回内存是否已回到限制以内；淘汰的键数计入INFO的evicted_keys
+    ///
+    /// 与Redis的近似LRU一样不遍历整个键空间: 每轮从随机位置抽取`EVICTION_SAMPLES`个
+    /// 候选键放入按访问时间排序的淘汰池，再删除池中最久未访问的一个键，然后检查`over_limit`。
+    /// 抽到的已过期键直接删除(计入expired_keys)。
+    ///
+    /// `lazy`为true时删除的值交给lazyfree线程释放，释放之前已分配的内存不会减少，
+    /// 所以`over_limit`的参数是等待后台释放的估算字节数，调用者应从当前用量中减去
+    pub fn evict(
+        &self,
+        policy: EvictionPolicy,
+        lazy: bool,
+        mut over_limit: impl FnMut(usize) -> bool,
+    ) -> bool {
+        /// 每轮抽样的键数，与Redis的maxmemory-samples默认值相同
+        const EVICTION_SAMPLES: usize = 5;
+        /// 淘汰池最多保留的候选键数，与Redis的EVPOOL_SIZE相同
+        const EVICTION_POOL_SIZE: usize = 16;
 
-        // 简化的glob匹配
-        if pattern.starts_with('*') && pattern.ends_with('*') {
-            let middle = &pattern[1..pattern.len() - 1];
-            return key.contains(middle);
+        if !over_limit(0) {
+            return true;
         }
+        if policy == EvictionPolicy::NoEviction {
+            return false;
+        }
+
+        let now = self.clock.now_ms();
+        let (mut evicted, mut expired) = (0, 0);
+        let mut pending = Vec::new();
+        let mut pending_bytes = 0;
+        // (最后访问时间, 键)，从旧到新排列
+        let mut pool: Vec<(u64, String)> = Vec::new();
+        let mut store = self.write_data();
+        while over_limit(pending_bytes) {
+            let mut expired_keys = Vec::new();
+            let candidates = store.sample(random_u64(), EVICTION_SAMPLES, |v| {
+                policy != EvictionPolicy::VolatileLru || v.expires_at.is_some()
+            });
+            for (key, value) in candidates {
+                if value.is_expired(now) {
+                    expired_keys.push(key.clone());
+                } else if !pool.iter().any(|(_, k)| k == key) {
+                    pool.push((value.last_access.load(Ordering::Relaxed), key.clone()));
+                }
+            }
+            pool.sort_unstable();
+            pool.truncate(EVICTION_POOL_SIZE);
 
-        if pattern.starts_with('*') {
-            return key.ends_with(&pattern[1..]);
+            // 已过期的键最先删除
+            let victim = match expired_keys.pop() {
+                Some(key) => Some((key, true)),
+                None if pool.is_empty() => break,
+                None => Some((pool.remove(0).1, false)),
+            };
+            let victims = victim.into_iter().chain(expired_keys.into_iter().map(|key| (key, true)));
+            for (key, is_expired) in victims {
+                let Some(value) = store.remove(&key) else { continue };
+                if is_expired {
+                    expired += 1;
+                    self.notify(|| StoreEvent::Expired { key: key.clone() });
+                } else {
+                    evicted += 1;
+                    self.notify(|| StoreEvent::Evicted { key: key.clone() });
+                }
+                if lazy {
+                    pending_bytes += estimate_memory(&key, &value);
+                    pending.push(value);
+                }
+            }
         }
+        let within_limit = !over_limit(pending_bytes);
+        drop(store);
 
-        if pattern.ends_with('*') {
-            return key.starts_with(&pattern[..pattern.len() - 1]);
+        self.stats.record_evicted(evicted);
+        self.stats.record_expired(expired);
+        if !pending.is_empty() {
+            memory::lazy_free(pending);
         }
+        within_limit
+    }
 
-        key == pattern
+    /// 按Redis的glob规则匹配键名，规则见[`glob_match`]
+    pub fn match_pattern(key: &str, pattern: &str) -> bool {
+        glob_match(pattern.as_bytes(), key.as_bytes())
     }
 
     /// 获取键的剩余生存时间(毫秒)
     pub fn pttl(&self, key: &str) -> i64 {
-        let store = self.inner.read().unwrap();
-        match store.get(key) {
-            Some(v) => {
-                if v.is_expired() {
-                    -2 // 键不存在
-                } else {
-                    v.ttl_ms().unwrap_or(-1) // -1表示永不过期
-                }
-            }
-            None => -2, // 键不存在
-        }
+        // -1表示永不过期，-2表示键不存在
+        self.read_live(key, |v, now| v.ttl_ms(now).unwrap_or(-1))
+            .unwrap_or(-2)
     }
 
     /// 设置键的过期时间
     pub fn expire(&self, key: &str, ttl: Duration) -> bool {
-        let mut store = self.inner.write().unwrap();
-        if let Some(v) = store.get_mut(key) {
-            if !v.is_expired() {
-                v.expires_at = Some(Instant::now() + ttl);
-                return true;
+        self.expire_at(key, deadline(self.clock.now_ms(), ttl))
+    }
+
+    /// 获取键的过期时间(Unix毫秒)
+    pub fn pexpiretime(&self, key: &str) -> i64 {
+        // 与pttl相同: -1表示永不过期，-2表示键不存在
+        self.read_live(key, |v, _| v.expires_at().map_or(-1, |at| at.min(i64::MAX as u64) as i64))
+            .unwrap_or(-2)
+    }
+
+    /// 设置键在指定Unix时间(毫秒)过期
+    ///
+    /// 与Redis一样，时间不晚于当前时间时直接删除键
+    pub fn expire_at(&self, key: &str, at_ms: u64) -> bool {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
+        if at_ms <= now {
+            let removed = store.remove(key).is_some();
+            if removed {
+                self.notify(|| StoreEvent::Del { key: key.to_string() });
             }
+            return removed;
+        }
+        if let Some(v) = store.get_mut(key) {
+            v.expires_at = Some(at_ms);
+            self.notify(|| StoreEvent::Expire { key: key.to_string() });
+            return true;
         }
         false
     }
 
     /// 移除键的过期时间
     pub fn persist(&self, key: &str) -> bool {
-        let mut store = self.inner.write().unwrap();
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
         if let Some(v) = store.get_mut(key) {
             if v.expires_at.is_some() {
                 v.expires_at = None;
+                self.notify(|| StoreEvent::Persist { key: key.to_string() });
                 return true;
             }
         }
@@ -234,24 +1071,22 @@ impl Store {
 
     /// 原子递增
     ///
-    /// Rust特点: Result类型表示可能失败的操作
-    pub fn incr(&self, key: &str, delta: i64) -> Result<i64, String> {
-        let mut store = self.inner.write().unwrap();
-
-        let current = store.get(key).and_then(|v| {
-            if v.is_expired() {
-                None
-            } else {
-                Some(v.data().to_vec())
-            }
-        });
+    /// 与Redis一致，值必须是规范的整数字符串，结果溢出时返回错误而不是回绕。
+    ///
+    /// Rust特点: Result类型表示可能失败的操作，checked_add在溢出时返回None
+    pub fn incr(&self, key: &str, delta: i64) -> RedisResult<i64> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
 
-        let value = match current {
-            Some(data) => {
-                let s = String::from_utf8(data)
-                    .map_err(|_| "值不是有效的UTF-8字符串")?;
-                let num: i64 = s.parse().map_err(|_| "值不是整数")?;
-                num + delta
+        let invalid = |msg: &str| RedisError::InvalidArgument(msg.to_string());
+        let value = match store.get(key) {
+            Some(v) => {
+                let data = v.data().ok_or_else(wrong_type)?;
+                let num = parse_integer(data)
+                    .ok_or_else(|| invalid("value is not an integer or out of range"))?;
+                num.checked_add(delta)
+                    .ok_or_else(|| invalid("increment or decrement would overflow"))?
             }
             None => delta,
         };
@@ -260,83 +1095,562 @@ impl Store {
             key.to_string(),
             StoredValue::new(value.to_string().into_bytes()),
         );
+        self.notify(|| StoreEvent::Set { key: key.to_string() });
 
         Ok(value)
     }
 
     /// 追加字符串
-    pub fn append(&self, key: &str, value: &[u8]) -> usize {
-        let mut store = self.inner.write().unwrap();
-
-        let entry = store.entry(key.to_string()).or_insert_with(|| {
-            StoredValue::new(Vec::new())
-        });
+    pub fn append(&self, key: &str, value: &[u8]) -> RedisResult<usize> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
 
-        if entry.is_expired() {
-            *entry = StoredValue::new(value.to_vec());
-            value.len()
-        } else {
-            let mut data = entry.data.clone();
-            data.extend_from_slice(value);
-            let len = data.len();
-            entry.data = data;
-            len
+        let entry = store.get_or_insert_with(key, || StoredValue::new(Vec::new()));
+        let Value::String(data) = &mut entry.value else {
+            return Err(wrong_type());
+        };
+        data.extend_from_slice(value);
+        let len = data.len();
+        self.notify(|| StoreEvent::Set { key: key.to_string() });
+        Ok(len)
+    }
+
+    /// 从`offset`处开始覆盖写入字符串，返回写入后的长度
+    ///
+    /// `offset`超过当前长度时中间用0填充；键不存在时视为空字符串。
+    /// `value`为空时不修改也不创建键，只返回当前长度。已有的过期时间会被保留
+    pub fn set_range(&self, key: &str, offset: usize, value: &[u8]) -> RedisResult<usize> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
+
+        if value.is_empty() {
+            return match store.get(key) {
+                Some(entry) => entry.data().map(<[u8]>::len).ok_or_else(wrong_type),
+                None => Ok(0),
+            };
+        }
+        let entry = store.get_or_insert_with(key, || StoredValue::new(Vec::new()));
+        let Value::String(data) = &mut entry.value else {
+            return Err(wrong_type());
+        };
+        let end = offset + value.len();
+        if data.len() < end {
+            data.resize(end, 0);
         }
+        data[offset..end].copy_from_slice(value);
+        let len = data.len();
+        self.notify(|| StoreEvent::Set { key: key.to_string() });
+        Ok(len)
     }
 
     /// 获取字符串长度
-    pub fn strlen(&self, key: &str) -> usize {
-        let store = self.inner.read().unwrap();
+    pub fn strlen(&self, key: &str) -> RedisResult<usize> {
+        self.read_live(key, |v, _| v.data().map(<[u8]>::len).ok_or_else(wrong_type))
+            .unwrap_or(Ok(0))
+    }
+
+    /// 字符串值的长度，键不存在、已过期或不是字符串时为0
+    ///
+    /// 写命令执行前估算写入后的大小时使用: 不计入命中率统计，也不更新访问时间
+    pub fn peek_strlen(&self, key: &str) -> usize {
+        let store = self.read_data();
+        let now = self.clock.now_ms();
         store
             .get(key)
-            .filter(|v| !v.is_expired())
-            .map_or(0, |v| v.data().len())
+            .filter(|v| !v.is_expired(now))
+            .and_then(StoredValue::data)
+            .map_or(0, <[u8]>::len)
+    }
+
+    /// 获取字符串值
+    ///
+    /// Rust特点: Result<Option<T>> 同时区分"键不存在"和"值无法转换"
+    pub fn get_str(&self, key: &str) -> RedisResult<Option<String>> {
+        self.get(key)
+            .map(|data| String::from_utf8(data).map_err(RedisError::from))
+            .transpose()
+    }
+
+    /// 获取整数值
+    pub fn get_i64(&self, key: &str) -> RedisResult<Option<i64>> {
+        match self.get_str(key)? {
+            Some(s) => parse_integer(s.as_bytes())
+                .map(Some)
+                .ok_or_else(|| RedisError::TypeError("值不是整数".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// 将值序列化为JSON后存储
+    ///
+    /// Rust特点: 泛型 + trait约束，任何实现了Serialize的类型都可以存储
+    pub fn set_json<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> RedisResult<()> {
+        let data = serde_json::to_vec(value)?;
+        self.set(key.to_string(), data);
+        Ok(())
+    }
+
+    /// 读取JSON值并反序列化
+    pub fn get_json<T: DeserializeOwned>(&self, key: &str) -> RedisResult<Option<T>> {
+        self.get(key)
+            .map(|data| serde_json::from_slice(&data).map_err(RedisError::from))
+            .transpose()
+    }
+
+    /// 获取字符串的子区间 (与GETRANGE语义一致)
+    ///
+    /// start和end都是闭区间，负数表示从末尾开始计数；键不存在时返回空
+    pub fn get_range(&self, key: &str, start: i64, end: i64) -> Vec<u8> {
+        self.get_range_checked(key, start, end).unwrap_or_default()
+    }
+
+    /// 与`get_range`相同，键存在但不是字符串时返回WRONGTYPE错误
+    ///
+    /// 与Redis一致: 换算后仍为负数的下标按0处理，所以两端都超出开头时可能返回第一个字节；
+    /// 只有start和end都是负数且start > end时才直接返回空
+    pub fn get_range_checked(&self, key: &str, start: i64, end: i64) -> RedisResult<Vec<u8>> {
+        let slice = |data: &[u8]| {
+            let len = data.len() as i64;
+            if start < 0 && end < 0 && start > end {
+                return Vec::new();
+            }
+            let start = if start < 0 { len.saturating_add(start).max(0) } else { start };
+            let end = if end < 0 { len.saturating_add(end).max(0) } else { end.min(len - 1) };
+            if start > end || start >= len {
+                return Vec::new();
+            }
+            data[start as usize..=end as usize].to_vec()
+        };
+        self.read_live(key, |v, _| v.data().map(slice).ok_or_else(wrong_type))
+            .unwrap_or(Ok(Vec::new()))
+    }
+
+    /// 原子地更新一个键
+    ///
+    /// 闭包接收当前值(不存在或已过期时为None)，返回新值；返回None表示删除该键。
+    /// 已有的过期时间会被保留。整个过程持有写锁，不会与其他写入交错。
+    /// 键的值不是字符串时返回WRONGTYPE错误，不调用闭包。
+    ///
+    /// Rust特点: FnOnce约束允许闭包消费捕获的变量
+    pub fn update<F>(&self, key: &str, f: F) -> RedisResult<Option<Vec<u8>>>
+    where
+        F: FnOnce(Option<&[u8]>) -> Option<Vec<u8>>,
+    {
+        self.update_with(key, |current| match f(current) {
+            Some(data) => (Update::Set(data.clone()), Some(data)),
+            None => (Update::Delete, None),
+        })
+    }
+
+    /// 原子地读取并修改一个键，返回闭包计算的任意结果
+    ///
+    /// 闭包在写锁内执行，可以根据当前值决定保持、写入或删除，
+    /// 嵌入方不需要WATCH/MULTI就能实现乐观更新。闭包中不能再访问同一个Store，否则会死锁。
+    /// 闭包收到None只表示键确实不存在；键的值不是字符串时返回WRONGTYPE错误，
+    /// 不调用闭包，也不修改该键。
+    ///
+    /// Rust特点: 泛型返回值R让调用者从闭包中带出检查结果
+    pub fn update_with<F, R>(&self, key: &str, f: F) -> RedisResult<R>
+    where
+        F: FnOnce(Option<&[u8]>) -> (Update, R),
+    {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
+
+        let current = match store.get(key) {
+            Some(v) => Some((v.data().ok_or_else(wrong_type)?, v.expires_at)),
+            None => None,
+        };
+        let expires_at = current.and_then(|(_, expires_at)| expires_at);
+        let (update, result) = f(current.map(|(data, _)| data));
+
+        match update {
+            Update::Keep => {}
+            Update::Set(data) => {
+                let value = Value::String(data);
+                store.insert(key.to_string(), StoredValue::with_parts(value, expires_at));
+                self.notify(|| StoreEvent::Set { key: key.to_string() });
+            }
+            Update::SetWithExpiry(data, ttl) => {
+                let value = Value::String(data);
+                let expires_at = Some(now.saturating_add(ttl.as_millis() as u64));
+                store.insert(key.to_string(), StoredValue::with_parts(value, expires_at));
+                self.notify(|| StoreEvent::Set { key: key.to_string() });
+            }
+            Update::Delete => {
+                if store.remove(key).is_some() {
+                    self.notify(|| StoreEvent::Del { key: key.to_string() });
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// 比较并交换: 只有当前值等于`expected`时才改为`new`，返回是否交换成功
+    ///
+    /// `expected`为None表示要求键不存在，`new`为None表示删除该键；
+    /// 键的值不是字符串时返回WRONGTYPE错误
+    pub fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> RedisResult<bool> {
+        self.update_with(key, |current| {
+            if current != expected {
+                return (Update::Keep, false);
+            }
+            match new {
+                Some(data) => (Update::Set(data), true),
+                None => (Update::Delete, true),
+            }
+        })
+    }
+
+    /// 获取或续期分布式锁(LOCK)，返回是否持有锁
+    ///
+    /// 键不存在时写入`token`并设置过期时间；键的值已经是`token`时只刷新过期时间(续期)；
+    /// 被其他持有者占用时不做修改
+    pub fn lock(&self, key: &str, token: &[u8], ttl: Duration) -> RedisResult<bool> {
+        self.update_with(key, |current| match current {
+            None => (Update::SetWithExpiry(token.to_vec(), ttl), true),
+            Some(holder) if holder == token => (Update::SetWithExpiry(token.to_vec(), ttl), true),
+            Some(_) => (Update::Keep, false),
+        })
     }
 
-    /// 清理过期的键
+    /// 释放分布式锁(UNLOCK): 只有值仍是`token`时才删除，返回是否释放
+    ///
+    /// 锁过期后被其他客户端获取时，原持有者的释放不会误删新锁
+    pub fn unlock(&self, key: &str, token: &[u8]) -> RedisResult<bool> {
+        self.compare_and_set(key, Some(token), None)
+    }
+
+    /// GCRA限流检查(CL.THROTTLE)，在写锁内原子地读取和更新理论到达时间
+    ///
+    /// 键保存理论到达时间(Unix毫秒)，过期时间设为恢复到空闲所需的时长，空闲的键会自动删除
+    pub fn throttle(&self, key: &str, throttle: &Throttle, quantity: u64) -> RedisResult<ThrottleResult> {
+        let now = self.clock.now_ms();
+        self.update_with(key, |current| {
+            let tat = match current.map(|data| parse_integer(data).and_then(|n| u64::try_from(n).ok())) {
+                None => None,
+                Some(Some(tat)) => Some(tat),
+                Some(None) => {
+                    let e = RedisError::InvalidArgument("value is not an integer or out of range".to_string());
+                    return (Update::Keep, Err(e));
+                }
+            };
+
+            let (result, new_tat) = throttle.check(tat, now, quantity);
+            let update = match new_tat {
+                Some(new_tat) => Update::SetWithExpiry(new_tat.to_string().into_bytes(), result.reset_after),
+                None => Update::Keep,
+            };
+            (update, Ok(result))
+        })?
+    }
+
+    /// 清理过期的键，同时删除哈希中过期的字段
     ///
     /// Rust特点: retain方法实现原地过滤
     pub fn cleanup_expired(&self) -> usize {
-        let mut store = self.inner.write().unwrap();
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
         let before = store.len();
-        store.retain(|_, v| !v.is_expired());
-        before - store.len()
+        store.retain(|key, v| {
+            if v.is_expired(now) || v.purge_expired_fields(now) {
+                self.notify(|| StoreEvent::Expired { key: key.clone() });
+                false
+            } else {
+                true
+            }
+        });
+        let removed = before - store.len();
+        self.stats.record_expired(removed as u64);
+        removed
     }
 
     /// 获取数据库大小(键的数量)
     pub fn dbsize(&self) -> usize {
-        let store = self.inner.read().unwrap();
-        store.iter().filter(|(_, v)| !v.is_expired()).count()
+        let store = self.read_data();
+        let now = self.clock.now_ms();
+        store.iter().filter(|(_, v)| !v.is_expired(now)).count()
     }
 
     /// 清空所有数据
     pub fn flushdb(&self) {
-        let mut store = self.inner.write().unwrap();
+        let mut store = self.write_data();
         store.clear();
+        self.notify(|| StoreEvent::Flush);
+    }
+
+    /// 清空数据库，旧数据交给lazyfree线程释放
+    ///
+    /// 写锁只在交换空表期间持有；大量值逐个drop的开销转移到后台，不阻塞其他命令，
+    /// 连续执行也只有一个后台线程
+    ///
+    /// Rust特点: mem::take取出整张表的所有权，通过通道move进后台线程后在那里析构
+    pub fn flushdb_async(&self) {
+        let old = {
+            let mut store = self.write_data();
+            std::mem::take(&mut *store)
+        };
+        self.notify(|| StoreEvent::Flush);
+        if !old.is_empty() {
+            memory::lazy_free(old);
+        }
     }
 
     /// 获取键的类型
     pub fn key_type(&self, key: &str) -> Option<&'static str> {
-        let store = self.inner.read().unwrap();
-        store.get(key).and_then(|v| {
-            if v.is_expired() {
-                None
-            } else {
-                Some("string") // 目前只支持字符串类型
+        self.read_live(key, |v, _| v.value.type_name())
+    }
+
+    /// 获取键的编码(OBJECT ENCODING)
+    pub fn encoding(&self, key: &str) -> Option<&'static str> {
+        self.read_live(key, |v, _| v.value.encoding())
+    }
+
+    /// 紧凑编码的转换阈值
+    pub fn encoding_thresholds(&self) -> EncodingThresholds {
+        *self.encodings.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 修改紧凑编码的转换阈值，只影响之后的写入，已转换的值不会转换回来
+    pub fn set_encoding_thresholds(&self, thresholds: EncodingThresholds) {
+        *self.encodings.write().unwrap_or_else(PoisonError::into_inner) = thresholds;
+    }
+
+    /// 按类型读取值(JSON文档、时间序列等)，键存在但类型不同时返回WRONGTYPE错误
+    pub fn read_as<V: ValueKind, T>(&self, key: &str, f: impl FnOnce(&V) -> T) -> RedisResult<Option<T>> {
+        self.read_live(key, |v, _| V::as_kind(&v.value).map(f).ok_or_else(wrong_type))
+            .transpose()
+    }
+
+    /// 在写锁内按类型修改值，已有的过期时间保留
+    ///
+    /// 闭包收到当前值(键不存在时为None)，可以新建、修改或取走(删除键)，
+    /// 返回结果和是否做了修改；没有修改时不产生变更事件。
+    ///
+    /// 闭包panic时取出的值(包括闭包已做的修改)放回表中，键不会丢失
+    ///
+    /// Rust特点: 先把值移出表再交给闭包，修改时不需要克隆整个文档
+    pub fn update_as<V: ValueKind, T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Option<V>) -> RedisResult<(T, bool)>,
+    ) -> RedisResult<T> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, key, now);
+
+        let (current, expires_at) = match store.remove(key) {
+            Some(StoredValue { value, expires_at, .. }) => match V::into_kind(value) {
+                Ok(current) => (Some(current), expires_at),
+                Err(value) => {
+                    store.insert(key.to_string(), StoredValue::with_parts(value, expires_at));
+                    return Err(wrong_type());
+                }
+            },
+            None => (None, None),
+        };
+        let existed = current.is_some();
+
+        let keys = [key];
+        let mut taken = TakenValues {
+            store: &mut store,
+            keys: &keys,
+            current: vec![current],
+            expires_at: vec![expires_at],
+        };
+        let result = f(&mut taken.current[0]);
+        let modified = matches!(result, Ok((_, true)));
+        let current = taken.current.pop().flatten();
+        let store = &mut *taken.store;
+        if let Some(current) = current {
+            let mut value = current.into_value();
+            value.fit_encoding(&self.encoding_thresholds());
+            store.insert(key.to_string(), StoredValue::with_parts(value, expires_at));
+            if modified {
+                self.notify(|| StoreEvent::Set { key: key.to_string() });
             }
-        })
+        } else if existed {
+            self.notify(|| StoreEvent::Del { key: key.to_string() });
+        }
+        result.map(|(value, _)| value)
     }
 
-    /// 重命名键
-    pub fn rename(&self, old_key: &str, new_key: &str) -> bool {
-        let mut store = self.inner.write().unwrap();
-        if let Some(value) = store.remove(old_key) {
-            if !value.is_expired() {
-                store.insert(new_key.to_string(), value);
-                return true;
+    /// 在同一把写锁内按类型修改多个键(LMOVE)，各键已有的过期时间保留
+    ///
+    /// 闭包按`keys`的顺序收到各个键的当前值，其余与`update_as`相同；任何一个键类型不同时
+    /// 不调用闭包，直接返回WRONGTYPE错误。`keys`不能重复
+    pub fn update_all_as<V: ValueKind, T>(
+        &self,
+        keys: &[&str],
+        f: impl FnOnce(&mut [Option<V>]) -> RedisResult<(T, bool)>,
+    ) -> RedisResult<T> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        for key in keys {
+            self.purge_expired(&mut store, key, now);
+            if store.get(*key).is_some_and(|v| V::as_kind(&v.value).is_none()) {
+                return Err(wrong_type());
             }
         }
-        false
+
+        let (current, expires_at): (Vec<Option<V>>, Vec<Option<u64>>) = keys
+            .iter()
+            .map(|key| match store.remove(*key) {
+                Some(StoredValue { value, expires_at, .. }) => {
+                    (V::into_kind(value).ok(), expires_at)
+                }
+                None => (None, None),
+            })
+            .unzip();
+        let existed: Vec<bool> = current.iter().map(Option::is_some).collect();
+
+        let mut taken = TakenValues { store: &mut store, keys, current, expires_at };
+        let result = f(&mut taken.current);
+        let modified = matches!(result, Ok((_, true)));
+        let current = std::mem::take(&mut taken.current);
+        let store = &mut *taken.store;
+        for (i, value) in current.into_iter().enumerate() {
+            let key = keys[i];
+            if let Some(value) = value {
+                let mut value = value.into_value();
+                value.fit_encoding(&self.encoding_thresholds());
+                let value = StoredValue::with_parts(value, taken.expires_at[i]);
+                store.insert(key.to_string(), value);
+                if modified {
+                    self.notify(|| StoreEvent::Set { key: key.to_string() });
+                }
+            } else if existed[i] {
+                self.notify(|| StoreEvent::Del { key: key.to_string() });
+            }
+        }
+        result.map(|(value, _)| value)
+    }
+
+    /// 用`source`的值计算新值并写入`destination`，读取和写入在同一把写锁内完成(ZRANGESTORE)
+    ///
+    /// 源键不存在时闭包收到None，类型不同时返回WRONGTYPE错误。闭包返回的新值覆盖目标键，
+    /// 目标键原有的值(无论类型)和过期时间都被丢弃；新值为None时删除目标键
+    pub fn store_from<V: ValueKind, W: ValueKind, T>(
+        &self,
+        source: &str,
+        destination: &str,
+        f: impl FnOnce(Option<&V>) -> (Option<W>, T),
+    ) -> RedisResult<T> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, source, now);
+        self.purge_expired(&mut store, destination, now);
+
+        let current = match store.get(source) {
+            Some(v) => Some(V::as_kind(&v.value).ok_or_else(wrong_type)?),
+            None => None,
+        };
+        let (value, result) = f(current);
+        self.replace_value(&mut store, destination, value.map(W::into_value));
+        Ok(result)
+    }
+
+    /// 把`key`整个替换为新值(原有的过期时间一并丢弃)，None表示删除
+    fn replace_value(&self, store: &mut Keyspace<StoredValue>, key: &str, value: Option<Value>) {
+        match value {
+            Some(mut value) => {
+                value.fit_encoding(&self.encoding_thresholds());
+                store.insert(key.to_string(), StoredValue::from_value(value));
+                self.notify(|| StoreEvent::Set { key: key.to_string() });
+            }
+            None => {
+                if store.remove(key).is_some() {
+                    self.notify(|| StoreEvent::Del { key: key.to_string() });
+                }
+            }
+        }
+    }
+
+    /// 用多个源键的值计算新值并写入`destination`，读取和写入在同一把写锁内完成(BITOP)
+    ///
+    /// 闭包按`sources`的顺序收到各个源键的值，不存在的键为None；与`store_from`一样，
+    /// 任何一个源键类型不同时返回WRONGTYPE错误，新值覆盖目标键，为None时删除目标键
+    pub fn store_from_all<V: ValueKind, W: ValueKind, T>(
+        &self,
+        sources: &[String],
+        destination: &str,
+        f: impl FnOnce(Vec<Option<&V>>) -> (Option<W>, T),
+    ) -> RedisResult<T> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        for source in sources {
+            self.purge_expired(&mut store, source, now);
+        }
+        self.purge_expired(&mut store, destination, now);
+
+        let current = sources
+            .iter()
+            .map(|source| match store.get(source) {
+                Some(v) => V::as_kind(&v.value).map(Some).ok_or_else(wrong_type),
+                None => Ok(None),
+            })
+            .collect::<RedisResult<Vec<_>>>()?;
+        let (value, result) = f(current);
+        self.replace_value(&mut store, destination, value.map(W::into_value));
+        Ok(result)
+    }
+
+    /// 对所有未过期的时间序列调用`f`，按键名排序返回非None的结果(TS.MRANGE)
+    pub fn collect_series<T>(&self, mut f: impl FnMut(&str, &TimeSeries) -> Option<T>) -> Vec<T> {
+        let store = self.read_data();
+        let now = self.clock.now_ms();
+        let mut series: Vec<(&String, &TimeSeries)> = store
+            .iter()
+            .filter(|(_, v)| !v.is_expired(now))
+            .filter_map(|(k, v)| TimeSeries::as_kind(&v.value).map(|series| (k, series)))
+            .collect();
+        series.sort_by_key(|(k, _)| *k);
+        series.into_iter().filter_map(|(k, series)| f(k, series)).collect()
+    }
+
+    /// 重命名键，覆盖已存在的目标键
+    ///
+    /// 值和过期时间一起移动；源键不存在时返回false
+    pub fn rename(&self, old_key: &str, new_key: &str) -> bool {
+        self.rename_inner(old_key, new_key, false).is_some()
+    }
+
+    /// 仅当目标键不存在时重命名(RENAMENX)
+    ///
+    /// 源键不存在时返回None，目标键已存在时返回Some(false)
+    pub fn rename_nx(&self, old_key: &str, new_key: &str) -> Option<bool> {
+        self.rename_inner(old_key, new_key, true)
+    }
+
+    fn rename_inner(&self, old_key: &str, new_key: &str, nx: bool) -> Option<bool> {
+        let mut store = self.write_data();
+        let now = self.clock.now_ms();
+        self.purge_expired(&mut store, old_key, now);
+        self.purge_expired(&mut store, new_key, now);
+
+        if !store.contains_key(old_key) {
+            return None;
+        }
+        // 与Redis一致: 源和目标相同时RENAME直接成功，RENAMENX视为目标已存在
+        if old_key == new_key || (nx && store.contains_key(new_key)) {
+            return Some(!nx);
+        }
+
+        let value = store.remove(old_key)?;
+        store.insert(new_key.to_string(), value);
+        self.notify(|| StoreEvent::Rename {
+            from: old_key.to_string(),
+            to: new_key.to_string(),
+        });
+        Some(true)
     }
 }
 
@@ -349,9 +1663,191 @@ impl Default for Store {
     }
 }
 
+/// 估算一个键占用的内存(字节): 键名、值和表项本身
+fn estimate_memory(key: &str, v: &StoredValue) -> usize {
+    let data = match &v.value {
+        Value::String(data) => data.capacity(),
+        Value::Hash(hash) => hash
+            .iter()
+            .map(|(field, value)| field.capacity() + value.capacity() + 2 * std::mem::size_of::<Vec<u8>>())
+            .sum(),
+        Value::Set(set) => set
+            .iter()
+            .map(|member| member.capacity() + std::mem::size_of::<Vec<u8>>())
+            .sum(),
+        Value::SortedSet(zset) => zset.memory_usage(),
+        Value::Stream(stream) => stream.memory_usage(),
+        Value::List(list) => list
+            .iter()
+            .map(|item| item.capacity() + std::mem::size_of::<Vec<u8>>())
+            .sum(),
+        // JSON文档按序列化后的长度估算
+        Value::Json(doc) => serde_json::to_vec(doc).map_or(0, |bytes| bytes.len()),
+        Value::TimeSeries(series) => series.memory_usage(),
+        Value::Bloom(filter) => filter.memory_usage(),
+        Value::Cuckoo(filter) => filter.memory_usage(),
+        Value::Module(value) => value.memory_usage(),
+    };
+    key.len() + data + std::mem::size_of::<(String, StoredValue)>()
+}
+
+/// 从`now`(Unix毫秒)起经过`ttl`后的时间点，溢出时取最大值
+fn deadline(now: u64, ttl: Duration) -> u64 {
+    now.saturating_add(ttl.as_millis().min(u64::MAX as u128) as u64)
+}
+
+/// 把LRANGE/ZRANGE的start/stop换算为闭区间下标
+///
+/// 负数从末尾计数，超出范围的部分截掉；区间为空时返回None
+pub fn index_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (start + len).max(0) } else { start };
+    let stop = if stop < 0 { stop + len } else { stop.min(len - 1) };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+/// 随机抽样的下标(ZRANDMEMBER/HRANDFIELD)
+///
+/// `count`为正数时从`len`个元素中取不重复的min(count, len)个，顺序随机；
+/// 为负数时取|count|个，同一个下标可以出现多次
+pub fn sample_indices(len: usize, count: i64) -> Vec<usize> {
+    if len == 0 {
+        return Vec::new();
+    }
+    if count < 0 {
+        return (0..count.unsigned_abs()).map(|_| random_below(len)).collect();
+    }
+    // 部分Fisher-Yates洗牌: 只打乱前count个位置
+    let count = usize::try_from(count).unwrap_or(usize::MAX).min(len);
+    let mut indices: Vec<usize> = (0..len).collect();
+    for i in 0..count {
+        let j = i + random_below(len - i);
+        indices.swap(i, j);
+    }
+    indices.truncate(count);
+    indices
+}
+
+/// `[0, n)`中的随机数，n不能为0
+fn random_below(n: usize) -> usize {
+    (random_u64() % n as u64) as usize
+}
+
+/// 随机的u64，用作淘汰抽样的起始哈希值
+///
+/// Rust特点: thread_local!保存每个线程的xorshift状态，种子取自std的RandomState，
+/// 抽样不需要加锁，也不需要引入随机数依赖
+fn random_u64() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x
+    })
+}
+
+/// 按Redis的规则解析整数字符串
+///
+/// 只接受规范形式: 可选的负号加数字，不允许前后空白、`+`号和多余的前导零，
+/// 因此`" 1"`、`"+1"`、`"01"`、`"-0"`都不是整数
+pub fn parse_integer(data: &[u8]) -> Option<i64> {
+    let digits = data.strip_prefix(b"-").unwrap_or(data);
+    let canonical = match digits {
+        [] => false,
+        [b'0'] => digits.len() == data.len(),
+        [first, ..] => *first != b'0' && digits.iter().all(u8::is_ascii_digit),
+    };
+    if !canonical {
+        return None;
+    }
+    std::str::from_utf8(data).ok()?.parse().ok()
+}
+
+/// Redis的glob匹配(对应`stringmatchlen`)
+///
+/// 支持`*`(任意长度)、`?`(单个字符)、`[abc]`/`[a-z]`/`[^...]`字符类和`\`转义，
+/// 按字节比较。遇到不匹配时回到最近的`*`多吞一个字符重试
+///
+/// Rust特点: 用循环加回溯点代替C版本的递归，最坏O(模式长度×键长度)，不会栈溢出
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // 最近一个`*`之后的模式位置，以及它当前吞到的字符串位置
+    let mut star: Option<(usize, usize)> = None;
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+            if p == pattern.len() {
+                return true;
+            }
+            star = Some((p, s));
+            continue;
+        }
+        if p < pattern.len() {
+            if let Some(next) = glob_match_one(pattern, p, string[s]) {
+                p = next;
+                s += 1;
+                continue;
+            }
+        }
+        match star {
+            Some((star_p, star_s)) => {
+                star = Some((star_p, star_s + 1));
+                p = star_p;
+                s = star_s + 1;
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// 用`pattern[p..]`开头的一个元素(字符、`?`、转义或字符类)匹配字符`c`，
+/// 匹配时返回下一个元素的位置
+fn glob_match_one(pattern: &[u8], mut p: usize, c: u8) -> Option<usize> {
+    match pattern[p] {
+        b'?' => Some(p + 1),
+        // 末尾单独的`\`按普通字符处理
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then_some(p + 2),
+        b'[' => {
+            p += 1;
+            let negate = pattern.get(p) == Some(&b'^');
+            if negate {
+                p += 1;
+            }
+            let mut matched = false;
+            while p < pattern.len() && pattern[p] != b']' {
+                if pattern[p] == b'\\' && p + 1 < pattern.len() {
+                    matched |= pattern[p + 1] == c;
+                    p += 2;
+                } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' {
+                    let (lo, hi) = (pattern[p].min(pattern[p + 2]), pattern[p].max(pattern[p + 2]));
+                    matched |= (lo..=hi).contains(&c);
+                    p += 3;
+                } else {
+                    matched |= pattern[p] == c;
+                    p += 1;
+                }
+            }
+            // 缺少`]`时字符类延续到模式末尾
+            (matched != negate).then_some((p + 1).min(pattern.len()))
+        }
+        literal => (literal == c).then_some(p + 1),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::{LoadFuture, Loaded};
+    use crate::clock::ManualClock;
+    use std::collections::HashMap;
 
     #[test]
     fn test_set_and_get() {
@@ -399,6 +1895,503 @@ mod tests {
         assert!(!store.exists("key"));
     }
 
+    #[test]
+    fn test_typed_accessors() {
+        let store = Store::new();
+        store.set("name".to_string(), b"rust".to_vec());
+        store.set("count".to_string(), b"42".to_vec());
+
+        assert_eq!(store.get_str("name").unwrap(), Some("rust".to_string()));
+        assert_eq!(store.get_i64("count").unwrap(), Some(42));
+        assert_eq!(store.get_i64("missing").unwrap(), None);
+        assert!(store.get_i64("name").is_err());
+
+        store.set_json("list", &vec![1, 2, 3]).unwrap();
+        let list: Vec<i32> = store.get_json("list").unwrap().unwrap();
+        assert_eq!(list, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_get_range() {
+        let store = Store::new();
+        store.set("key".to_string(), b"This is a string".to_vec());
+        assert_eq!(store.get_range("key", 0, 3), b"This".to_vec());
+        assert_eq!(store.get_range("key", -3, -1), b"ing".to_vec());
+        assert_eq!(store.get_range("key", 0, -1), b"This is a string".to_vec());
+        assert_eq!(store.get_range("key", 10, 100), b"string".to_vec());
+        assert_eq!(store.get_range("key", 5, 2), Vec::<u8>::new());
+        assert_eq!(store.get_range("missing", 0, -1), Vec::<u8>::new());
+        // 两端都超出开头时按0处理
+        assert_eq!(store.get_range("key", -100, -50), b"T".to_vec());
+        assert_eq!(store.get_range("key", -1, -3), Vec::<u8>::new());
+
+        store.set_range("key", 10, b"STRING").unwrap();
+        assert_eq!(store.get("key"), Some(b"This is a STRING".to_vec()));
+        assert_eq!(store.set_range("padded", 3, b"x").unwrap(), 4);
+        assert_eq!(store.get("padded"), Some(b"\0\0\0x".to_vec()));
+        assert_eq!(store.set_range("missing", 5, b"").unwrap(), 0);
+        assert!(!store.exists("missing"));
+    }
+
+    #[test]
+    fn test_update() {
+        let store = Store::new();
+        let result = store.update("counter", |old| {
+            assert!(old.is_none());
+            Some(b"1".to_vec())
+        });
+        assert_eq!(result, Ok(Some(b"1".to_vec())));
+
+        store
+            .update("counter", |old| {
+                let mut data = old.unwrap().to_vec();
+                data.push(b'0');
+                Some(data)
+            })
+            .unwrap();
+        assert_eq!(store.get("counter"), Some(b"10".to_vec()));
+
+        assert_eq!(store.update("counter", |_| None), Ok(None));
+        assert!(!store.exists("counter"));
+    }
+
+    /// 以HashMap模拟后端数据库的加载器
+    #[derive(Debug, Default)]
+    struct MapLoader {
+        backend: std::sync::Mutex<HashMap<String, Vec<u8>>>,
+        loads: AtomicU64,
+    }
+
+    impl CacheLoader for MapLoader {
+        fn load<'a>(&'a self, key: &'a str) -> LoadFuture<'a, Option<Loaded>> {
+            Box::pin(async move {
+                self.loads.fetch_add(1, Ordering::Relaxed);
+                let value = self.backend.lock().unwrap().get(key).cloned();
+                Ok(value.map(|v| Loaded::new(v).with_ttl(Duration::from_secs(60))))
+            })
+        }
+
+        fn write<'a>(&'a self, key: &'a str, value: Option<&'a [u8]>) -> LoadFuture<'a, ()> {
+            Box::pin(async move {
+                let mut backend = self.backend.lock().unwrap();
+                match value {
+                    Some(v) => backend.insert(key.to_string(), v.to_vec()),
+                    None => backend.remove(key),
+                };
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_loader() {
+        let loader = Arc::new(MapLoader::default());
+        loader.backend.lock().unwrap().insert("user:1".to_string(), b"alice".to_vec());
+        let store = Store::new().with_loader(loader.clone());
+
+        // 第一次未命中时加载并带上TTL，之后直接命中缓存
+        assert_eq!(store.get_or_load("user:1").await.unwrap(), Some(b"alice".to_vec()));
+        assert_eq!(store.get_or_load("user:1").await.unwrap(), Some(b"alice".to_vec()));
+        assert_eq!(loader.loads.load(Ordering::Relaxed), 1);
+        assert!(store.pttl("user:1") > 0);
+        assert_eq!(store.get_or_load("missing").await.unwrap(), None);
+
+        store.set_through("user:2", b"bob".to_vec()).await.unwrap();
+        assert_eq!(loader.backend.lock().unwrap().get("user:2"), Some(&b"bob".to_vec()));
+        assert!(store.del_through("user:1").await.unwrap());
+        assert!(!loader.backend.lock().unwrap().contains_key("user:1"));
+    }
+
+    #[test]
+    fn test_iter_snapshot() {
+        let clock = Arc::new(ManualClock::at(1_000));
+        let store = Store::with_clock(clock.clone());
+        for i in 0..10 {
+            store.set(format!("key:{}", i), i.to_string().into_bytes());
+        }
+        store.set_with_expiry("temp".to_string(), b"t".to_vec(), Duration::from_secs(5));
+
+        let snapshot = store.iter_snapshot(3);
+        // 创建之后删除的键被跳过
+        store.del("key:9");
+        clock.advance(Duration::from_secs(1));
+
+        let entries: Vec<SnapshotEntry> = snapshot.collect();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys.len(), 10);
+        assert!(!keys.contains(&"key:9"));
+
+        // 遍历中途写入和删除其他键，一直存在的键恰好返回一次
+        let mut snapshot = store.iter_snapshot(2);
+        let mut seen = vec![snapshot.next().unwrap().key];
+        for i in 0..50 {
+            store.set(format!("late:{}", i), b"x".to_vec());
+        }
+        store.del("temp");
+        seen.extend(snapshot.map(|e| e.key).filter(|key| !key.starts_with("late:")));
+        seen.sort();
+        let mut expected: Vec<String> = (0..9).map(|i| format!("key:{}", i)).collect();
+        if seen.contains(&"temp".to_string()) {
+            expected.push("temp".to_string());
+        }
+        expected.sort();
+        assert_eq!(seen, expected);
+
+        let temp = entries.iter().find(|e| e.key == "temp").unwrap();
+        assert_eq!(temp.expires_at, Some(6_000));
+        assert_eq!(temp.ttl, Some(Duration::from_secs(4)));
+    }
+
+    #[tokio::test]
+    async fn test_watch_key() {
+        let store = Store::new();
+        let changed = store.watch_key("k");
+        // 调用之后发生的修改即使早于await也不会丢失
+        store.set("other".to_string(), b"1".to_vec());
+        store.set("k".to_string(), b"1".to_vec());
+        assert_eq!(changed.await, Some(StoreEvent::Set { key: "k".to_string() }));
+
+        let mut watcher = store.key_watcher("k");
+        let waiter = tokio::spawn(async move {
+            let first = watcher.changed().await;
+            let second = watcher.changed().await;
+            (first, second)
+        });
+        assert!(store.rename("k", "moved"));
+        store.flushdb();
+        assert_eq!(
+            waiter.await.unwrap(),
+            (
+                Some(StoreEvent::Rename { from: "k".to_string(), to: "moved".to_string() }),
+                Some(StoreEvent::Flush)
+            )
+        );
+    }
+
+    #[test]
+    fn test_compare_and_set() {
+        let store = Store::new();
+        let mut events = store.subscribe();
+
+        assert_eq!(store.compare_and_set("k", None, Some(b"1".to_vec())), Ok(true));
+        assert_eq!(store.compare_and_set("k", None, Some(b"2".to_vec())), Ok(false));
+        assert_eq!(store.compare_and_set("k", Some(b"0"), Some(b"2".to_vec())), Ok(false));
+        assert_eq!(store.compare_and_set("k", Some(b"1"), Some(b"2".to_vec())), Ok(true));
+        assert_eq!(store.get("k"), Some(b"2".to_vec()));
+        assert_eq!(store.compare_and_set("k", Some(b"2"), None), Ok(true));
+        assert!(!store.exists("k"));
+
+        // 比较失败不产生变更事件
+        let events: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(events.len(), 3);
+
+        store.set("n".to_string(), b"5".to_vec());
+        let seen = store.update_with("n", |current| (Update::Keep, current.map(<[u8]>::to_vec)));
+        assert_eq!(seen, Ok(Some(b"5".to_vec())));
+    }
+
+    #[test]
+    fn test_compare_and_set_non_string() {
+        let store = Store::new();
+        let hash = |store: &Store| {
+            store
+                .update_as("h", |hash: &mut Option<Hash>| {
+                    hash.get_or_insert_with(Hash::new).insert(b"f".to_vec(), b"v".to_vec());
+                    Ok(((), true))
+                })
+                .unwrap()
+        };
+        hash(&store);
+        store.expire("h", Duration::from_secs(100));
+
+        // 非字符串的键不是"不存在"，CAS失败并且不修改该键
+        let cas = store.compare_and_set("h", None, Some(b"1".to_vec()));
+        assert_eq!(cas, Err(RedisError::WrongType));
+        assert_eq!(store.compare_and_set("h", Some(b"v"), None), Err(RedisError::WrongType));
+        assert_eq!(store.update("h", |_| Some(b"1".to_vec())), Err(RedisError::WrongType));
+        let called = std::cell::Cell::new(false);
+        let result = store.update_with("h", |_| {
+            called.set(true);
+            (Update::Set(b"1".to_vec()), ())
+        });
+        assert!(result.is_err() && !called.get());
+        assert_eq!(store.key_type("h"), Some("hash"));
+        assert!(store.pttl("h") > 0);
+    }
+
+    #[test]
+    fn test_expiry_with_manual_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let store = Store::with_clock(clock.clone());
+        store.set_with_expiry("key".to_string(), b"value".to_vec(), Duration::from_secs(10));
+        store.set("forever".to_string(), b"value".to_vec());
+
+        clock.advance(Duration::from_secs(4));
+        assert!(store.exists("key"));
+        assert_eq!(store.pttl("key"), 6000);
+
+        clock.advance(Duration::from_secs(7));
+        assert_eq!(store.dbsize(), 1);
+        assert_eq!(store.cleanup_expired(), 1);
+        assert!(!store.exists("key"));
+        assert_eq!(store.pttl("key"), -2);
+    }
+
+    #[test]
+    fn test_evict_lru() {
+        let clock = Arc::new(ManualClock::new());
+        let store = Store::with_clock(clock.clone());
+        for key in ["a", "b", "c"] {
+            store.set(key.to_string(), b"value".to_vec());
+        }
+        store.set_with_expiry("v".to_string(), b"value".to_vec(), Duration::from_secs(10));
+        // c最久未访问，其次是v
+        for (key, at) in [("c", 1), ("v", 2), ("a", 3), ("b", 4)] {
+            store.write_data()[key].last_access.store(at, Ordering::Relaxed);
+        }
+
+        // 后台释放时每淘汰一个键pending就大于0，正好淘汰一个
+        let evict_one = |pending: usize| pending == 0;
+        assert!(!store.evict(EvictionPolicy::NoEviction, true, evict_one));
+        assert!(store.evict(EvictionPolicy::AllKeysLru, true, evict_one));
+        assert!(!store.exists("c"));
+        assert!(store.evict(EvictionPolicy::VolatileLru, true, evict_one));
+        assert!(!store.exists("v"));
+        // 只剩没有设置过期时间的键，volatile-lru无法淘汰
+        assert!(!store.evict(EvictionPolicy::VolatileLru, true, evict_one));
+        assert!(store.evict(EvictionPolicy::AllKeysLru, false, |_| false));
+
+        assert_eq!(store.dbsize(), 2);
+        assert_eq!(store.stats().evicted_keys(), 2);
+
+        // 抽到的过期键先于最久未访问的键删除，计入expired_keys
+        store.set_with_expiry("e".to_string(), b"value".to_vec(), Duration::from_secs(1));
+        clock.advance(Duration::from_secs(2));
+        assert!(store.evict(EvictionPolicy::AllKeysLru, true, evict_one));
+        assert_eq!(store.dbsize(), 2);
+        assert_eq!(store.stats().expired_keys(), 1);
+        assert_eq!(store.stats().evicted_keys(), 2);
+    }
+
+    #[test]
+    fn test_evict_samples_old_keys() {
+        let store = Store::new();
+        for i in 0..10_000u64 {
+            let key = format!("key:{}", i);
+            store.set(key.clone(), b"value".to_vec());
+            store.write_data()[key.as_str()].last_access.store(i, Ordering::Relaxed);
+        }
+
+        // 第一次检查加上1000轮，每轮淘汰一个键
+        let mut checks = 0;
+        assert!(store.evict(EvictionPolicy::AllKeysLru, false, |_| {
+            checks += 1;
+            checks <= 1_001
+        }));
+        assert_eq!(store.dbsize(), 9_000);
+        // 抽样只能近似LRU，但淘汰的键应当集中在最久未访问的一端(均匀随机时约为一半)
+        let evicted_old = (0..5_000).filter(|i| !store.exists(&format!("key:{}", i))).count();
+        assert!(evicted_old >= 900, "{}", evicted_old);
+    }
+
+    #[test]
+    fn test_lazy_expiry_on_access() {
+        let clock = Arc::new(ManualClock::new());
+        let store = Store::with_clock(clock.clone());
+        for key in ["a", "b", "c", "d"] {
+            store.set_with_expiry(key.to_string(), b"value".to_vec(), Duration::from_secs(1));
+        }
+        let mut events = store.subscribe();
+        clock.advance(Duration::from_secs(2));
+
+        // 读取遇到的过期键被真正删除，而不是留给定期清理
+        assert_eq!(store.get("a"), None);
+        assert!(!store.exists("b"));
+        assert_eq!(store.key_type("c"), None);
+        assert!(!store.del("d"));
+        assert_eq!(store.inner.read().unwrap().len(), 0);
+        assert_eq!(store.expired_keys(), 4);
+        assert_eq!(store.cleanup_expired(), 0);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            StoreEvent::Expired { key: "a".to_string() }
+        );
+
+        // 过期后写入的新值不受影响
+        store.set_with_expiry("a".to_string(), b"old".to_vec(), Duration::from_secs(1));
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(store.append("a", b"new"), Ok(3));
+        assert_eq!(store.get("a"), Some(b"new".to_vec()));
+        assert_eq!(store.expired_keys(), 5);
+    }
+
+    #[test]
+    fn test_absolute_expiry() {
+        let clock = Arc::new(ManualClock::at(1_700_000_000_000));
+        let store = Store::with_clock(clock.clone());
+        store.set_with_expiry("key".to_string(), b"value".to_vec(), Duration::from_secs(10));
+
+        // 过期时间是绝对的Unix毫秒，可以原样持久化
+        let expires_at = store.inner.read().unwrap()["key"].expires_at();
+        assert_eq!(expires_at, Some(1_700_000_010_000));
+
+        store.set("other".to_string(), b"value".to_vec());
+        assert!(store.expire_at("other", 1_700_000_001_000));
+        assert_eq!(store.pttl("other"), 1000);
+        assert!(!store.expire_at("missing", 1_700_000_001_000));
+        assert_eq!(store.pexpiretime("other"), 1_700_000_001_000);
+        assert_eq!(store.pexpiretime("missing"), -2);
+        store.set("persistent".to_string(), b"value".to_vec());
+        assert_eq!(store.pexpiretime("persistent"), -1);
+        // 不晚于当前时间的过期时间直接删除键
+        assert!(store.expire_at("persistent", 1_700_000_000_000));
+        assert!(!store.exists("persistent"));
+        assert!(!store.expire_at("persistent", 0));
+
+        clock.advance(Duration::from_millis(1001));
+        assert!(!store.exists("other"));
+        assert!(store.exists("key"));
+    }
+
+    #[test]
+    fn test_subscribe_events() {
+        let store = Store::new();
+        let mut events = store.subscribe();
+
+        store.set("key".to_string(), b"value".to_vec());
+        store.expire("key", Duration::from_secs(10));
+        store.del("key");
+        store.del("missing");
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            StoreEvent::Set { key: "key".to_string() }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            StoreEvent::Expire { key: "key".to_string() }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            StoreEvent::Del { key: "key".to_string() }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_set_with_options() {
+        let clock = Arc::new(crate::clock::ManualClock::at(10_000));
+        let store = Store::with_clock(clock.clone());
+        let set = |key: &str, value: &str, expiry, nx, xx, get| {
+            let options = SetOptions { expiry, nx, xx, get };
+            store.set_with_options(key.to_string(), value.as_bytes().to_vec(), options)
+        };
+
+        let ttl = Some(SetExpiry::After(Duration::from_secs(5)));
+        assert_eq!(set("k", "1", ttl, true, false, true).unwrap(), (true, None));
+        assert_eq!(store.pttl("k"), 5_000);
+        // NX不满足时不写入，仍然返回旧值
+        assert_eq!(set("k", "2", None, true, false, true).unwrap(), (false, Some(b"1".to_vec())));
+        assert_eq!(set("k", "3", Some(SetExpiry::Keep), false, true, false).unwrap(), (true, None));
+        assert_eq!(store.get("k"), Some(b"3".to_vec()));
+        assert_eq!(store.pttl("k"), 5_000);
+        set("k", "4", None, false, false, false).unwrap();
+        assert_eq!(store.pttl("k"), -1);
+        assert_eq!(set("missing", "v", None, false, true, false).unwrap(), (false, None));
+
+        set("at", "v", Some(SetExpiry::At(12_000)), false, false, false).unwrap();
+        assert_eq!(store.pttl("at"), 2_000);
+        // 已经过去的时间戳删除键
+        assert!(set("at", "v", Some(SetExpiry::At(9_000)), false, false, false).unwrap().0);
+        assert!(!store.exists("at"));
+
+        store
+            .update_as("list", |list: &mut Option<ListValue>| {
+                list.get_or_insert_with(ListValue::new).push_back(b"x".to_vec());
+                Ok(((), true))
+            })
+            .unwrap();
+        assert!(set("list", "v", None, false, false, true).is_err());
+        assert_eq!(store.key_type("list"), Some("list"));
+        assert!(set("list", "v", None, false, false, false).unwrap().0);
+        assert_eq!(store.get("list"), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_update_as_panic_keeps_key() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let store = Store::new();
+        let push = |list: &mut Option<ListValue>| {
+            list.get_or_insert_with(ListValue::new).push_back(b"x".to_vec());
+        };
+        store
+            .update_as("list", |list: &mut Option<ListValue>| {
+                push(list);
+                Ok(((), true))
+            })
+            .unwrap();
+        store.expire("list", Duration::from_secs(100));
+
+        // 闭包panic后键和过期时间还在，已做的修改保留
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            store.update_as("list", |list: &mut Option<ListValue>| -> RedisResult<((), bool)> {
+                push(list);
+                panic!("closure failed");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(store.read_as("list", |list: &ListValue| list.len()), Ok(Some(2)));
+        assert!(store.pttl("list") > 0);
+
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            let keys = ["list", "other"];
+            store.update_all_as(&keys, |lists: &mut [Option<ListValue>]| -> RedisResult<((), bool)> {
+                push(&mut lists[1]);
+                panic!("closure failed");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(store.read_as("list", |list: &ListValue| list.len()), Ok(Some(2)));
+        assert_eq!(store.read_as("other", |list: &ListValue| list.len()), Ok(Some(1)));
+
+        // 锁没有因为panic而不可用
+        store.set("k".to_string(), b"v".to_vec());
+        assert_eq!(store.get("k"), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_msetnx_all_or_nothing() {
+        let clock = Arc::new(ManualClock::new());
+        let store = Store::with_clock(clock.clone());
+        let pairs = |keys: &[&str]| keys.iter().map(|k| (k.to_string(), b"v".to_vec())).collect();
+
+        assert!(store.msetnx(pairs(&["a", "b"])));
+        assert!(!store.msetnx(pairs(&["c", "b"])));
+        assert!(!store.exists("c"));
+
+        // 过期的键不阻止写入
+        store.set_with_expiry("d".to_string(), b"old".to_vec(), Duration::from_secs(1));
+        clock.advance(Duration::from_secs(2));
+        assert!(store.msetnx(pairs(&["c", "d"])));
+        assert_eq!(store.get("d"), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_flushdb_async() {
+        let store = Store::new();
+        let mut events = store.subscribe();
+        for i in 0..10 {
+            store.set(format!("key:{}", i), vec![0; 1024]);
+        }
+
+        store.flushdb_async();
+        assert_eq!(store.dbsize(), 0);
+        assert!(std::iter::from_fn(|| events.try_recv().ok()).any(|e| matches!(e, StoreEvent::Flush)));
+        store.set("after".to_string(), b"v".to_vec());
+        assert_eq!(store.get("after"), Some(b"v".to_vec()));
+    }
+
     #[test]
     fn test_pattern_matching() {
         assert!(Store::match_pattern("hello", "*"));
@@ -406,6 +2399,143 @@ mod tests {
         assert!(Store::match_pattern("hello", "*llo"));
         assert!(Store::match_pattern("hello", "*ell*"));
         assert!(!Store::match_pattern("hello", "world"));
+
+        // 中间的`*`和多个`*`
+        assert!(Store::match_pattern("user:42:token", "user:*:token"));
+        assert!(!Store::match_pattern("user:42:tokens", "user:*:token"));
+        assert!(Store::match_pattern("a:b:c:d", "a*c*d"));
+        assert!(Store::match_pattern("", "**"));
+        assert!(!Store::match_pattern("", "?"));
+
+        // `?`
+        assert!(Store::match_pattern("hello", "h?llo"));
+        assert!(!Store::match_pattern("hllo", "h?llo"));
+
+        // 字符类、范围和取反
+        assert!(Store::match_pattern("hallo", "h[ae]llo"));
+        assert!(!Store::match_pattern("hillo", "h[ae]llo"));
+        assert!(Store::match_pattern("hbllo", "h[a-c]llo"));
+        assert!(Store::match_pattern("hbllo", "h[c-a]llo"));
+        assert!(!Store::match_pattern("hallo", "h[^a]llo"));
+        assert!(Store::match_pattern("hello", "h[^a]llo"));
+        assert!(Store::match_pattern("key9", "key[0-9]"));
+        assert!(!Store::match_pattern("keyx", "key[0-9]"));
+
+        // 转义
+        assert!(Store::match_pattern("a*b", "a\\*b"));
+        assert!(!Store::match_pattern("axb", "a\\*b"));
+        assert!(Store::match_pattern("a?", "a\\?"));
+        assert!(Store::match_pattern("]", "[\\]]"));
+        assert!(Store::match_pattern("a\\", "a\\"));
+    }
+
+    #[test]
+    fn test_incr_overflow_and_format() {
+        let store = Store::new();
+        store.set("max".to_string(), i64::MAX.to_string().into_bytes());
+        assert_eq!(
+            store.incr("max", 1).map_err(|e| e.to_string()),
+            Err("increment or decrement would overflow".to_string())
+        );
+        assert_eq!(store.incr("max", -1), Ok(i64::MAX - 1));
+
+        store.set("min".to_string(), i64::MIN.to_string().into_bytes());
+        assert_eq!(
+            store.incr("min", -1).map_err(|e| e.to_string()),
+            Err("increment or decrement would overflow".to_string())
+        );
+
+        for value in [" 1", "1 ", "+1", "01", "-0", "", "1.5", "abc"] {
+            store.set("bad".to_string(), value.as_bytes().to_vec());
+            assert_eq!(
+                store.incr("bad", 1).map_err(|e| e.to_string()),
+                Err("value is not an integer or out of range".to_string()),
+                "{:?}",
+                value
+            );
+        }
+
+        assert_eq!(parse_integer(b"0"), Some(0));
+        assert_eq!(parse_integer(b"-42"), Some(-42));
+        assert_eq!(parse_integer(b"9223372036854775808"), None);
+    }
+
+    #[test]
+    fn test_rename_nx() {
+        let clock = Arc::new(ManualClock::new());
+        let store = Store::with_clock(clock.clone());
+        store.set_with_expiry("a".to_string(), b"1".to_vec(), Duration::from_secs(10));
+        store.set("b".to_string(), b"2".to_vec());
+
+        assert_eq!(store.rename_nx("missing", "c"), None);
+        assert_eq!(store.rename_nx("a", "b"), Some(false));
+        assert_eq!(store.rename_nx("a", "a"), Some(false));
+        assert_eq!(store.rename_nx("a", "c"), Some(true));
+
+        // 过期时间随值一起移动
+        assert_eq!(store.pttl("c"), 10_000);
+        assert!(!store.exists("a"));
+
+        // 已过期的目标键视为不存在
+        store.set_with_expiry("d".to_string(), b"3".to_vec(), Duration::from_secs(1));
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(store.rename_nx("b", "d"), Some(true));
+        assert_eq!(store.get("d"), Some(b"2".to_vec()));
+
+        assert!(store.rename("d", "d"));
+        assert!(!store.rename("missing", "d"));
+    }
+
+    #[test]
+    fn test_scan() {
+        let store = Store::new();
+        for i in 0..25 {
+            store.set(format!("key:{}", i), b"value".to_vec());
+        }
+        store.set("other".to_string(), b"value".to_vec());
+
+        // 分批遍历，合起来恰好是全部键
+        let mut cursor = 0;
+        let mut seen = Vec::new();
+        loop {
+            let (next, keys) = store.scan(cursor, Some("key:*"), 10);
+            assert!(keys.len() <= 10);
+            seen.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 25);
+
+        assert!(store.memory_usage("other").unwrap() > "other".len() + 5);
+        assert_eq!(store.memory_usage("missing"), None);
+    }
+
+    #[test]
+    fn test_sample_indices() {
+        // 正数: 不重复，最多len个
+        let mut distinct = sample_indices(10, 4);
+        assert_eq!(distinct.len(), 4);
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 4);
+        assert!(distinct.iter().all(|&i| i < 10));
+
+        let mut all = sample_indices(5, 100);
+        all.sort();
+        assert_eq!(all, [0, 1, 2, 3, 4]);
+
+        // 负数: 可以重复
+        let repeated = sample_indices(2, -50);
+        assert_eq!(repeated.len(), 50);
+        assert!(repeated.iter().all(|&i| i < 2));
+
+        assert!(sample_indices(0, 3).is_empty());
+        assert!(sample_indices(0, -3).is_empty());
+        assert!(sample_indices(5, 0).is_empty());
     }
 }
 
@@ -0,0 +1,741 @@
+//! 流模块 - X*命令使用的流类型
+//!
+//! 流是只能追加的日志: 每个条目有一个递增的ID(`毫秒时间戳-序号`)和若干字段值对。
+//! - XADD的ID为`*`时由服务器生成: 时间戳取当前时间和最后一个ID中较大的一个，
+//!   同一毫秒内序号递增，因此时钟回拨时ID仍然递增
+//! - 按ID范围读取(XRANGE/XREVRANGE)，或读取某个ID之后的新条目(XREAD)
+//! - MAXLEN/MINID按条目数或最小ID裁剪旧条目
+//!
+//! 与其他集合类型不同，条目全部删除后流仍然存在，并记住最后一个ID，
+//! 之后添加的条目ID不会倒退。
+//!
+//! 消费者组让多个消费者分担同一个流: 组记录最后投递的ID，每个新条目只投递给组内一个消费者，
+//! 投递后进入待确认列表(PEL)，直到XACK确认。消费者崩溃后，
+//! 其他消费者可以用XCLAIM认领闲置太久的条目，因此每个条目至少被处理一次。
+//!
+//! Rust特点展示:
+//! - BTreeMap按ID排序，`range`直接回答区间查询
+//! - 派生`Ord`按字段声明顺序比较，StreamId先比时间戳再比序号
+//! - `checked_add`/`checked_sub`处理ID用尽的边界
+
+use crate::error::{RedisError, RedisResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Bound;
+
+/// 条目的字段值对，保持XADD时的顺序
+pub type Fields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// 流条目ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    /// 毫秒时间戳
+    pub ms: u64,
+    /// 同一毫秒内的序号
+    pub seq: u64,
+}
+
+impl StreamId {
+    /// 最小的ID，XRANGE中的`-`
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    /// 最大的ID，XRANGE中的`+`
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// 解析`ms-seq`；只给出时间戳时序号取`default_seq`
+    pub fn parse(text: &[u8], default_seq: u64) -> RedisResult<Self> {
+        let text = std::str::from_utf8(text).map_err(|_| invalid_id())?;
+        let (ms, seq) = match text.split_once('-') {
+            Some((ms, seq)) => (ms, Some(seq)),
+            None => (text, None),
+        };
+        let ms = ms.parse().map_err(|_| invalid_id())?;
+        let seq = match seq {
+            Some(seq) => seq.parse().map_err(|_| invalid_id())?,
+            None => default_seq,
+        };
+        Ok(Self { ms, seq })
+    }
+
+    /// 下一个ID，已经是最大的ID时返回None
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self { ms: self.ms, seq }),
+            None => self.ms.checked_add(1).map(|ms| Self { ms, seq: 0 }),
+        }
+    }
+
+    /// 上一个ID，已经是最小的ID时返回None
+    pub fn prev(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(Self { ms: self.ms, seq }),
+            None => self.ms.checked_sub(1).map(|ms| Self { ms, seq: u64::MAX }),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// 无法解析的ID参数
+pub fn invalid_id() -> RedisError {
+    RedisError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+/// 解析XRANGE的起点: `-`、`(id`(不包含)或ID，只给出时间戳时序号取0
+pub fn parse_range_start(text: &[u8]) -> RedisResult<StreamId> {
+    match text {
+        b"-" => Ok(StreamId::MIN),
+        [b'(', id @ ..] => StreamId::parse(id, 0)?.next().ok_or_else(|| {
+            RedisError::InvalidArgument("invalid start ID for the interval".to_string())
+        }),
+        _ => StreamId::parse(text, 0),
+    }
+}
+
+/// 解析XRANGE的终点: `+`、`(id`(不包含)或ID，只给出时间戳时序号取最大值
+pub fn parse_range_end(text: &[u8]) -> RedisResult<StreamId> {
+    match text {
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => StreamId::parse(id, u64::MAX)?.prev().ok_or_else(|| {
+            RedisError::InvalidArgument("invalid end ID for the interval".to_string())
+        }),
+        _ => StreamId::parse(text, u64::MAX),
+    }
+}
+
+/// XADD的ID参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdSpec {
+    /// `*`: 时间戳和序号都由服务器生成
+    Auto,
+    /// `ms-*`: 指定时间戳，序号由服务器生成
+    AutoSeq(u64),
+    /// 完整的ID
+    Explicit(StreamId),
+}
+
+impl IdSpec {
+    pub fn parse(text: &[u8]) -> RedisResult<Self> {
+        if text == b"*" {
+            return Ok(IdSpec::Auto);
+        }
+        if let Some(ms) = text.strip_suffix(b"-*") {
+            let ms = std::str::from_utf8(ms)
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .ok_or_else(invalid_id)?;
+            return Ok(IdSpec::AutoSeq(ms));
+        }
+        StreamId::parse(text, 0).map(IdSpec::Explicit)
+    }
+}
+
+/// 裁剪的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    /// MAXLEN: 最多保留的条目数
+    MaxLen(u64),
+    /// MINID: 删除ID小于它的条目
+    MinId(StreamId),
+}
+
+/// XADD/XTRIM的裁剪参数
+///
+/// `~`表示允许少删。这里总是精确裁剪，`~`只决定能否使用LIMIT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trim {
+    pub strategy: TrimStrategy,
+    /// 一次最多删除的条目数，None或0表示不限制
+    pub limit: Option<u64>,
+}
+
+/// 待确认列表中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    /// 当前负责处理的消费者
+    pub consumer: String,
+    /// 最后一次投递的时间(毫秒时间戳)
+    pub delivered_at: u64,
+    /// 投递次数
+    pub delivery_count: u64,
+}
+
+/// 消费者组
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConsumerGroup {
+    /// 最后投递给组内消费者的ID，`>`读取它之后的条目
+    last_delivered: StreamId,
+    /// 全组的待确认列表
+    pending: BTreeMap<StreamId, PendingEntry>,
+    /// 消费者 -> 它的待确认条目
+    consumers: BTreeMap<String, BTreeSet<StreamId>>,
+}
+
+impl ConsumerGroup {
+    /// 最后投递的ID
+    pub fn last_delivered(&self) -> StreamId {
+        self.last_delivered
+    }
+
+    /// 修改最后投递的ID(XGROUP SETID)
+    pub fn set_last_delivered(&mut self, id: StreamId) {
+        self.last_delivered = id;
+    }
+
+    /// 创建消费者，已存在时返回false
+    pub fn create_consumer(&mut self, name: &str) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        self.consumers.insert(name.to_string(), BTreeSet::new());
+        true
+    }
+
+    /// 删除消费者和它的待确认条目，返回删除的待确认条目数
+    pub fn delete_consumer(&mut self, name: &str) -> usize {
+        let Some(ids) = self.consumers.remove(name) else {
+            return 0;
+        };
+        for id in &ids {
+            self.pending.remove(id);
+        }
+        ids.len()
+    }
+
+    /// 确认条目，返回确实在待确认列表中的数量
+    pub fn ack(&mut self, ids: &[StreamId]) -> usize {
+        ids.iter()
+            .filter(|id| match self.pending.remove(id) {
+                Some(entry) => {
+                    if let Some(owned) = self.consumers.get_mut(&entry.consumer) {
+                        owned.remove(id);
+                    }
+                    true
+                }
+                None => false,
+            })
+            .count()
+    }
+
+    /// `[start, end]`范围内的待确认条目，可以只看一个消费者的
+    pub fn pending<'a>(
+        &'a self,
+        start: StreamId,
+        end: StreamId,
+        consumer: Option<&'a str>,
+    ) -> impl Iterator<Item = (StreamId, &'a PendingEntry)> + 'a {
+        let range = if start <= end { Some(self.pending.range(start..=end)) } else { None };
+        range
+            .into_iter()
+            .flatten()
+            .filter(move |(_, entry)| consumer.is_none_or(|name| entry.consumer == name))
+            .map(|(id, entry)| (*id, entry))
+    }
+
+    /// 待确认条目总数
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 有待确认条目的消费者及各自的条目数，按消费者名排序
+    pub fn pending_per_consumer(&self) -> Vec<(&str, usize)> {
+        self.consumers
+            .iter()
+            .filter(|(_, ids)| !ids.is_empty())
+            .map(|(name, ids)| (name.as_str(), ids.len()))
+            .collect()
+    }
+
+    /// 把条目交给`consumer`，记入它的待确认列表
+    ///
+    /// 已经在待确认列表中的条目换到新的消费者名下，`delivery_count`为None时投递次数加1
+    fn deliver(&mut self, id: StreamId, consumer: &str, at: u64, delivery_count: Option<u64>) {
+        let entry = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: consumer.to_string(),
+            delivered_at: at,
+            delivery_count: 0,
+        });
+        if entry.consumer != consumer {
+            if let Some(owned) = self.consumers.get_mut(&entry.consumer) {
+                owned.remove(&id);
+            }
+            entry.consumer = consumer.to_string();
+        }
+        entry.delivered_at = at;
+        entry.delivery_count = delivery_count.unwrap_or(entry.delivery_count + 1);
+        self.consumers.entry(consumer.to_string()).or_default().insert(id);
+    }
+
+    /// 从待确认列表中删除条目(条目已不在流中)
+    fn forget(&mut self, id: StreamId) {
+        if let Some(entry) = self.pending.remove(&id) {
+            if let Some(owned) = self.consumers.get_mut(&entry.consumer) {
+                owned.remove(&id);
+            }
+        }
+    }
+}
+
+/// XCLAIM的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClaimOptions {
+    /// 只认领闲置至少这么久(毫秒)的条目
+    pub min_idle: u64,
+    /// IDLE: 认领后的闲置时间(毫秒)
+    pub idle: Option<u64>,
+    /// TIME: 认领后的投递时间(毫秒时间戳)
+    pub time: Option<u64>,
+    /// RETRYCOUNT: 认领后的投递次数
+    pub retry_count: Option<u64>,
+    /// FORCE: 条目不在待确认列表中(但仍在流中)时也认领
+    pub force: bool,
+    /// JUSTID: 只回复ID，不增加投递次数
+    pub just_id: bool,
+    /// LASTID: 最后投递的ID小于它时更新为它
+    pub last_id: Option<StreamId>,
+}
+
+/// XPENDING扩展形式的查询条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingQuery {
+    /// IDLE: 只看闲置至少这么久(毫秒)的条目
+    pub min_idle: Option<u64>,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    /// 只看这个消费者的条目
+    pub consumer: Option<String>,
+}
+
+/// 流
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+    /// 最后添加的ID，条目被删除后仍然保留，保证ID递增
+    last_id: StreamId,
+    /// 消费者组
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+impl Stream {
+    /// 创建空的流
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否没有条目
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最后添加的ID
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// 添加条目，返回它的ID
+    ///
+    /// 新ID必须大于最后一个ID；`now_ms`是生成ID时使用的当前时间
+    pub fn add(&mut self, id: IdSpec, fields: Fields, now_ms: u64) -> RedisResult<StreamId> {
+        let last = self.last_id;
+        let id = match id {
+            IdSpec::Auto if now_ms > last.ms => StreamId::new(now_ms, 0),
+            IdSpec::Auto => last.next().ok_or_else(|| {
+                RedisError::InvalidArgument(
+                    "The stream has exhausted the last possible ID, unable to add more items"
+                        .to_string(),
+                )
+            })?,
+            IdSpec::AutoSeq(ms) if ms > last.ms => StreamId::new(ms, 0),
+            IdSpec::AutoSeq(ms) if ms == last.ms => match last.seq.checked_add(1) {
+                Some(seq) => StreamId::new(ms, seq),
+                None => return Err(id_too_small()),
+            },
+            IdSpec::AutoSeq(_) => return Err(id_too_small()),
+            IdSpec::Explicit(StreamId::MIN) => {
+                return Err(RedisError::InvalidArgument(
+                    "The ID specified in XADD must be greater than 0-0".to_string(),
+                ))
+            }
+            IdSpec::Explicit(id) if id <= last => return Err(id_too_small()),
+            IdSpec::Explicit(id) => id,
+        };
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        Ok(id)
+    }
+
+    /// 按策略删除最旧的条目，返回删除的数量
+    pub fn trim(&mut self, trim: &Trim) -> usize {
+        let excess = match trim.strategy {
+            TrimStrategy::MaxLen(max) => {
+                self.entries.len().saturating_sub(usize::try_from(max).unwrap_or(usize::MAX))
+            }
+            TrimStrategy::MinId(min) => self.entries.range(..min).count(),
+        };
+        let limit = trim
+            .limit
+            .filter(|&limit| limit > 0)
+            .map_or(usize::MAX, |limit| usize::try_from(limit).unwrap_or(usize::MAX));
+        let removed = excess.min(limit);
+        for _ in 0..removed {
+            self.entries.pop_first();
+        }
+        removed
+    }
+
+    /// `[start, end]`范围内的条目，`rev`时从end开始倒序，最多`count`个
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, &Fields)> {
+        if start > end {
+            return Vec::new();
+        }
+        let count = count.unwrap_or(usize::MAX);
+        let entries = self.entries.range(start..=end).map(|(id, fields)| (*id, fields));
+        if rev {
+            entries.rev().take(count).collect()
+        } else {
+            entries.take(count).collect()
+        }
+    }
+
+    /// ID大于`id`的条目(XREAD)，最多`count`个
+    pub fn after(&self, id: StreamId, count: Option<usize>) -> Vec<(StreamId, &Fields)> {
+        self.entries
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields))
+            .collect()
+    }
+
+    /// 创建消费者组，从`last_delivered`之后开始投递；组已存在时返回false
+    pub fn create_group(&mut self, name: &str, last_delivered: StreamId) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        let group = ConsumerGroup {
+            last_delivered,
+            ..ConsumerGroup::default()
+        };
+        self.groups.insert(name.to_string(), group);
+        true
+    }
+
+    /// 删除消费者组，返回组是否存在
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// 按名称获取消费者组
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    /// 按名称获取可修改的消费者组
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// 以组内消费者的身份读取(XREADGROUP)，组不存在时返回None
+    ///
+    /// `after`为None(`>`)时读取从未投递过的新条目，投递后记入待确认列表(`noack`时不记)；
+    /// 否则重读该消费者待确认列表中ID大于`after`的条目，已从流中删除的条目字段为None
+    pub fn read_group(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Option<Fields>)>> {
+        let group = self.groups.get_mut(group)?;
+        group.create_consumer(consumer);
+        let count = count.unwrap_or(usize::MAX);
+
+        let Some(after) = after else {
+            let start = (Bound::Excluded(group.last_delivered), Bound::Unbounded);
+            let entries: Vec<(StreamId, Option<Fields>)> = self
+                .entries
+                .range(start)
+                .take(count)
+                .map(|(id, fields)| (*id, Some(fields.clone())))
+                .collect();
+            for (id, _) in &entries {
+                group.last_delivered = *id;
+                if !noack {
+                    group.deliver(*id, consumer, now, None);
+                }
+            }
+            return Some(entries);
+        };
+
+        let owned = &group.consumers[consumer];
+        let entries = owned
+            .range((Bound::Excluded(after), Bound::Unbounded))
+            .take(count)
+            .map(|id| (*id, self.entries.get(id).cloned()))
+            .collect();
+        Some(entries)
+    }
+
+    /// 把待确认条目转给`consumer`(XCLAIM)，返回认领到的条目；组不存在时返回None
+    ///
+    /// 已经从流中删除的条目同时从待确认列表中删除，不会被认领
+    pub fn claim(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Fields)>> {
+        let group = self.groups.get_mut(group)?;
+        // 与Redis一致: TIME不能晚于当前时间
+        let delivered_at = options
+            .time
+            .map(|time| time.min(now))
+            .or(options.idle.map(|idle| now.saturating_sub(idle)))
+            .unwrap_or(now);
+
+        let mut claimed = Vec::new();
+        for &id in ids {
+            let idle = match group.pending.get(&id) {
+                Some(entry) => now.saturating_sub(entry.delivered_at),
+                // FORCE认领流中存在但不在待确认列表中的条目，视为闲置了无限久
+                None if options.force && self.entries.contains_key(&id) => u64::MAX,
+                None => continue,
+            };
+            if idle < options.min_idle {
+                continue;
+            }
+            let Some(fields) = self.entries.get(&id) else {
+                group.forget(id);
+                continue;
+            };
+            let delivery_count = match options.retry_count {
+                Some(count) => Some(count),
+                // JUSTID不增加投递次数
+                None if options.just_id => {
+                    Some(group.pending.get(&id).map_or(0, |entry| entry.delivery_count))
+                }
+                None => None,
+            };
+            group.deliver(id, consumer, delivered_at, delivery_count);
+            claimed.push((id, fields.clone()));
+        }
+        if let Some(last_id) = options.last_id {
+            group.last_delivered = group.last_delivered.max(last_id);
+        }
+        Some(claimed)
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        self.entries
+            .values()
+            .map(|fields| {
+                std::mem::size_of::<(StreamId, Fields)>()
+                    + fields
+                        .iter()
+                        .map(|(field, value)| {
+                            field.capacity() + value.capacity() + 2 * std::mem::size_of::<Vec<u8>>()
+                        })
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+/// XADD指定的ID不大于最后一个ID
+fn id_too_small() -> RedisError {
+    RedisError::InvalidArgument(
+        "The ID specified in XADD is equal or smaller than the target stream top item".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Fields {
+        pairs
+            .iter()
+            .map(|(field, value)| (field.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_id() {
+        assert_eq!(StreamId::parse(b"5-3", 0).unwrap(), StreamId::new(5, 3));
+        assert_eq!(StreamId::parse(b"5", u64::MAX).unwrap(), StreamId::new(5, u64::MAX));
+        assert!(StreamId::parse(b"5-x", 0).is_err());
+        assert!(StreamId::parse(b"-1", 0).is_err());
+        assert_eq!(StreamId::new(1, 2).to_string(), "1-2");
+
+        assert_eq!(IdSpec::parse(b"*").unwrap(), IdSpec::Auto);
+        assert_eq!(IdSpec::parse(b"7-*").unwrap(), IdSpec::AutoSeq(7));
+        assert_eq!(IdSpec::parse(b"7").unwrap(), IdSpec::Explicit(StreamId::new(7, 0)));
+
+        assert_eq!(parse_range_start(b"-").unwrap(), StreamId::MIN);
+        assert_eq!(parse_range_start(b"(5-1").unwrap(), StreamId::new(5, 2));
+        assert_eq!(parse_range_end(b"5").unwrap(), StreamId::new(5, u64::MAX));
+        assert_eq!(parse_range_end(b"(5").unwrap(), StreamId::new(5, u64::MAX - 1));
+        assert!(parse_range_start(b"(-").is_err());
+        assert!(parse_range_end(b"(0-0").is_err());
+
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+        assert_eq!(StreamId::MIN.prev(), None);
+    }
+
+    #[test]
+    fn test_add_ids() {
+        let mut stream = Stream::new();
+        let entry = || fields(&[("f", "v")]);
+
+        assert_eq!(stream.add(IdSpec::Auto, entry(), 1000).unwrap(), StreamId::new(1000, 0));
+        assert_eq!(stream.add(IdSpec::Auto, entry(), 1000).unwrap(), StreamId::new(1000, 1));
+        // 时钟回拨时沿用最后一个ID的时间戳
+        assert_eq!(stream.add(IdSpec::Auto, entry(), 900).unwrap(), StreamId::new(1000, 2));
+        assert_eq!(stream.add(IdSpec::AutoSeq(1000), entry(), 0).unwrap(), StreamId::new(1000, 3));
+        assert_eq!(stream.add(IdSpec::AutoSeq(2000), entry(), 0).unwrap(), StreamId::new(2000, 0));
+        assert!(stream.add(IdSpec::AutoSeq(1999), entry(), 0).is_err());
+        assert!(stream.add(IdSpec::Explicit(StreamId::new(2000, 0)), entry(), 0).is_err());
+        assert_eq!(
+            stream.add(IdSpec::Explicit(StreamId::new(2000, 5)), entry(), 0).unwrap(),
+            StreamId::new(2000, 5)
+        );
+        assert_eq!(stream.len(), 6);
+
+        let mut empty = Stream::new();
+        assert!(empty.add(IdSpec::Explicit(StreamId::MIN), entry(), 0).is_err());
+        assert_eq!(empty.add(IdSpec::AutoSeq(0), entry(), 0).unwrap(), StreamId::new(0, 1));
+    }
+
+    #[test]
+    fn test_range_and_trim() {
+        let mut stream = Stream::new();
+        for ms in 1..=5 {
+            stream.add(IdSpec::Explicit(StreamId::new(ms, 0)), fields(&[("n", "x")]), 0).unwrap();
+        }
+        let ids = |entries: Vec<(StreamId, &Fields)>| -> Vec<u64> {
+            entries.iter().map(|(id, _)| id.ms).collect()
+        };
+
+        let (two, four) = (StreamId::new(2, 0), StreamId::new(4, 0));
+        assert_eq!(ids(stream.range(two, four, None, false)), [2, 3, 4]);
+        assert_eq!(ids(stream.range(StreamId::MIN, StreamId::MAX, Some(2), true)), [5, 4]);
+        assert!(stream.range(four, two, None, false).is_empty());
+        assert_eq!(ids(stream.after(StreamId::new(3, 0), None)), [4, 5]);
+        assert_eq!(ids(stream.after(StreamId::MIN, Some(1))), [1]);
+
+        let limited = Trim {
+            strategy: TrimStrategy::MaxLen(1),
+            limit: Some(2),
+        };
+        assert_eq!(stream.trim(&limited), 2);
+        let min_id = Trim {
+            strategy: TrimStrategy::MinId(StreamId::new(5, 0)),
+            limit: None,
+        };
+        assert_eq!(stream.trim(&min_id), 2);
+        assert_eq!(ids(stream.range(StreamId::MIN, StreamId::MAX, None, false)), [5]);
+
+        // 删空后仍然记住最后一个ID
+        let all = Trim {
+            strategy: TrimStrategy::MaxLen(0),
+            limit: None,
+        };
+        assert_eq!(stream.trim(&all), 1);
+        assert!(stream.is_empty());
+        assert!(stream.add(IdSpec::Explicit(StreamId::new(5, 0)), fields(&[]), 0).is_err());
+    }
+
+    #[test]
+    fn test_consumer_group() {
+        let mut stream = Stream::new();
+        for ms in 1..=3 {
+            stream.add(IdSpec::Explicit(StreamId::new(ms, 0)), fields(&[("n", "x")]), 0).unwrap();
+        }
+        let ids = |entries: &[(StreamId, Option<Fields>)]| -> Vec<u64> {
+            entries.iter().map(|(id, _)| id.ms).collect()
+        };
+
+        assert!(stream.create_group("g", StreamId::MIN));
+        assert!(!stream.create_group("g", StreamId::MIN));
+        assert!(stream.read_group("missing", "alice", None, None, false, 0).is_none());
+
+        // 新条目只投递一次，分给先来的消费者
+        let first = stream.read_group("g", "alice", None, Some(2), false, 100).unwrap();
+        assert_eq!(ids(&first), [1, 2]);
+        let second = stream.read_group("g", "bob", None, None, false, 200).unwrap();
+        assert_eq!(ids(&second), [3]);
+        assert!(stream.read_group("g", "bob", None, None, false, 200).unwrap().is_empty());
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.last_delivered(), StreamId::new(3, 0));
+        assert_eq!(group.pending_per_consumer(), [("alice", 2), ("bob", 1)]);
+
+        // 重读自己的待确认条目，不改变投递信息
+        let history = stream.read_group("g", "alice", Some(StreamId::MIN), None, false, 300);
+        let history = history.unwrap();
+        assert_eq!(ids(&history), [1, 2]);
+        assert_eq!(stream.group("g").unwrap().pending[&StreamId::new(1, 0)].delivery_count, 1);
+
+        // 认领: 只认领闲置足够久的条目，投递次数加1
+        let options = ClaimOptions {
+            min_idle: 150,
+            ..ClaimOptions::default()
+        };
+        let targets = [StreamId::new(1, 0), StreamId::new(3, 0)];
+        let claimed = stream.claim("g", "bob", &targets, &options, 300).unwrap();
+        assert_eq!(claimed.iter().map(|(id, _)| id.ms).collect::<Vec<_>>(), [1]);
+        let pending = &stream.group("g").unwrap().pending[&StreamId::new(1, 0)];
+        assert_eq!((pending.consumer.as_str(), pending.delivery_count), ("bob", 2));
+
+        // 已从流中删除的条目重读时字段为None，认领时从待确认列表中删除
+        let trim = Trim {
+            strategy: TrimStrategy::MinId(StreamId::new(2, 0)),
+            limit: None,
+        };
+        stream.trim(&trim);
+        let history = stream.read_group("g", "bob", Some(StreamId::MIN), None, false, 400).unwrap();
+        assert_eq!(history[0], (StreamId::new(1, 0), None));
+        let force = ClaimOptions {
+            force: true,
+            ..ClaimOptions::default()
+        };
+        assert!(stream.claim("g", "bob", &[StreamId::new(1, 0)], &force, 400).unwrap().is_empty());
+
+        let group = stream.group_mut("g").unwrap();
+        assert_eq!(group.pending_len(), 2);
+        assert_eq!(group.ack(&[StreamId::new(2, 0), StreamId::new(9, 0)]), 1);
+        assert_eq!(group.delete_consumer("bob"), 1);
+        assert_eq!(group.pending_len(), 0);
+        assert!(stream.destroy_group("g"));
+        assert!(stream.group("g").is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! systemd集成模块 - sd_notify就绪通知和socket激活
+//!
+//! 两者都只是环境变量加Unix socket上的简单协议，这里直接实现，不依赖libsystemd:
+//! - `NOTIFY_SOCKET`: 向该数据报socket发送`READY=1`，配合`Type=notify`
+//! - `LISTEN_PID`/`LISTEN_FDS`: systemd预先打开的监听socket，从文件描述符3开始
+//!
+//! Rust特点展示:
+//! - #[cfg(...)] 按平台选择实现(Linux的抽象命名空间socket)
+//! - unsafe 块明确标出需要人工保证正确性的代码
+//! - FromRawFd 把原始文件描述符转换为拥有所有权的标准库类型
+
+use crate::error::{RedisError, RedisResult};
+use std::env;
+use std::net::TcpListener;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+
+/// socket激活传入的第一个文件描述符(SD_LISTEN_FDS_START)
+const LISTEN_FDS_START: RawFd = 3;
+
+/// 向systemd发送状态，如`READY=1`
+///
+/// 未设置NOTIFY_SOCKET(不是由systemd以Type=notify启动)时返回Ok(false)
+pub fn notify(state: &str) -> RedisResult<bool> {
+    match env::var_os("NOTIFY_SOCKET") {
+        Some(path) => {
+            send_notify(&path.to_string_lossy(), state)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// 通知systemd服务已就绪
+///
+/// 同时报告MAINPID: 平滑升级时旧进程据此得知daemonize之后的真实pid
+pub fn notify_ready(status: &str) -> RedisResult<bool> {
+    notify(&format!("READY=1\nMAINPID={}\nSTATUS={}", std::process::id(), status))
+}
+
+/// 向指定的通知socket发送状态
+///
+/// 以`@`开头的地址是Linux抽象命名空间中的socket
+fn send_notify(path: &str, state: &str) -> RedisResult<()> {
+    let socket = UnixDatagram::unbound()?;
+
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+/// 取出systemd通过socket激活传入的监听socket
+///
+/// 读取后清除相关环境变量，避免被子进程误用
+pub fn listen_fds() -> RedisResult<Vec<TcpListener>> {
+    let count = parse_listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    )?;
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    let listeners = (0..count as RawFd)
+        .map(|offset| {
+            // Rust特点: unsafe 由调用方保证fd有效且没有其他所有者
+            // 这里由systemd的协议保证: 这些fd专门传给了本进程
+            let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START + offset) };
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(listeners)
+}
+
+/// 根据LISTEN_PID和LISTEN_FDS计算传给本进程的fd数量
+fn parse_listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> RedisResult<usize> {
+    let (Some(listen_pid), Some(listen_fds)) = (listen_pid, listen_fds) else {
+        return Ok(0);
+    };
+
+    // LISTEN_PID不是本进程时，这些fd是传给别的进程的
+    if listen_pid.parse::<u32>().ok() != Some(pid) {
+        return Ok(0);
+    }
+
+    listen_fds
+        .parse()
+        .map_err(|_| RedisError::Config(format!("无效的LISTEN_FDS: {}", listen_fds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_fds() {
+        assert_eq!(parse_listen_fds(None, None, 42).unwrap(), 0);
+        assert_eq!(parse_listen_fds(Some("42"), Some("2"), 42).unwrap(), 2);
+        assert_eq!(parse_listen_fds(Some("7"), Some("2"), 42).unwrap(), 0);
+        assert!(parse_listen_fds(Some("42"), Some("x"), 42).is_err());
+    }
+
+    #[test]
+    fn test_send_notify() {
+        let path = env::temp_dir().join(format!("rust-redis-notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        send_notify(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,59 @@
+//! 遥测模块 - 通过OTLP导出tracing span(需要启用otel特性)
+//!
+//! 每条命令的`command` span(命令名、键数量、结果)及其所属的`connection` span
+//! (客户端地址)会以OpenTelemetry格式批量发送到采集端，
+//! 从而能在已有的追踪后端中把慢请求和应用侧的调用链对应起来。
+//!
+//! Rust特点展示:
+//! - 条件编译 #[cfg(feature = "...")] 让可选依赖不影响默认构建
+//! - Drop trait 在退出时刷新尚未导出的span
+
+use crate::error::{RedisError, RedisResult};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// 上报给追踪后端的服务名
+const SERVICE_NAME: &str = "rust-redis";
+
+/// 遥测句柄 - 持有span导出管线
+///
+/// 被drop时关闭导出器，把缓冲中的span发送出去
+#[derive(Debug)]
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// 创建导出到`endpoint`(OTLP/HTTP)的管线，返回对应的tracing层
+    pub fn init<S>(endpoint: &str) -> RedisResult<(Self, OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>)>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| RedisError::Config(format!("创建OTLP导出器失败: {}", e)))?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build();
+
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+        Ok((Self { provider }, layer))
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("关闭OTLP导出器失败: {}", e);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! 测试辅助模块 - 为使用本crate的应用提供隔离的测试服务器
+//!
+//! 在集成测试中调用`TestServer::start()`，通过`client()`获取已连接的客户端，
+//! 通过`store()`直接预置或检查数据；服务器离开作用域时自动停止。
+//!
+//! `memory_network`创建内存中的模拟网络，`Server::from_listener`可以直接运行在上面。
+//! 配合`#[tokio::test(start_paused = true)]`和`clock::TokioClock`，
+//! 时间只在所有任务都空闲时按需前进，断线、半包、过期等场景每次运行的结果都相同。
+//!
+//! Rust特点展示:
+//! - Drop trait 实现RAII式的资源清理
+//! - tokio::io::duplex 在内存中模拟网络连接
+
+use crate::client::Client;
+use crate::connection::Connection;
+use crate::server::{Listener, Server};
+use crate::store::Store;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// 内存双工流的缓冲区大小
+const DUPLEX_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 测试服务器 - 在随机端口上运行的独立服务器实例
+///
+/// 每个TestServer拥有自己的Store，测试之间互不干扰；
+/// 被drop时停止接受连接并关闭所有已建立的连接。
+pub struct TestServer {
+    /// 监听地址
+    addr: SocketAddr,
+    /// 服务器使用的存储，测试可以直接检查或预置数据
+    store: Store,
+    /// 接受连接的后台任务
+    accept_task: JoinHandle<()>,
+    /// 内存连接的任务
+    ///
+    /// Rust特点: Mutex提供内部可变性，让`&self`方法也能登记新任务
+    duplex_tasks: Mutex<JoinSet<()>>,
+}
+
+impl TestServer {
+    /// 使用空存储启动测试服务器
+    pub async fn start() -> Self {
+        Self::with_store(Store::new()).await
+    }
+
+    /// 使用预置的存储启动测试服务器
+    ///
+    /// 可以传入`Store::with_clock`创建的存储来控制时间
+    pub async fn with_store(store: Store) -> Self {
+        let server = Server::bind("127.0.0.1:0", store.clone())
+            .await
+            .expect("绑定测试端口失败");
+        let addr = server.local_addr().expect("获取测试端口失败");
+
+        let accept_task = tokio::spawn(async move {
+            let _ = server.run().await;
+        });
+
+        Self {
+            addr,
+            store,
+            accept_task,
+            duplex_tasks: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// 服务器监听的地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 服务器使用的存储
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// 创建一个已连接的TCP客户端
+    pub async fn client(&self) -> Client {
+        Client::connect(self.addr)
+            .await
+            .expect("连接测试服务器失败")
+    }
+
+    /// 创建一个通过内存双工流连接的客户端，不经过网络栈
+    pub fn duplex_client(&self) -> Client<DuplexStream> {
+        let (client_side, server_side) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+        let store = self.store.clone();
+
+        self.duplex_tasks.lock().unwrap().spawn(async move {
+            let mut connection = Connection::with_addr(server_side, "duplex".to_string());
+            let _ = connection.handle(&store).await;
+        });
+
+        Client::from_stream(client_side)
+    }
+}
+
+impl Drop for TestServer {
+    /// 停止服务器；JoinSet被drop时会取消其中所有连接任务
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+/// 模拟网络的监听地址
+const MEMORY_LISTENER_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6379);
+
+/// 创建内存中的模拟网络，返回监听端和连接端
+///
+/// 每个连接是一对容量为`buffer_size`的双工流；容量很小时，
+/// 一次写入会被拆成多次读写，可以测试命令被拆开到达的情况
+pub fn memory_network(buffer_size: usize) -> (MemoryListener, MemoryConnector) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let listener = MemoryListener {
+        incoming: tokio::sync::Mutex::new(receiver),
+    };
+    let connector = MemoryConnector {
+        sender,
+        next_port: Arc::new(AtomicU16::new(40000)),
+        buffer_size,
+    };
+    (listener, connector)
+}
+
+/// 模拟网络的监听端，实现`Listener`
+pub struct MemoryListener {
+    /// 等待接受的连接
+    ///
+    /// Rust特点: accept只拿到&self，用异步Mutex取得接收端的可变借用
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<(DuplexStream, SocketAddr)>>,
+}
+
+impl Listener for MemoryListener {
+    type Stream = DuplexStream;
+
+    async fn accept(&self) -> io::Result<(DuplexStream, SocketAddr)> {
+        match self.incoming.lock().await.recv().await {
+            Some(accepted) => Ok(accepted),
+            // 所有连接端都已丢弃，和没有客户端的监听socket一样一直等待
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(MEMORY_LISTENER_ADDR)
+    }
+}
+
+/// 模拟网络的连接端，可以克隆给多个任务
+#[derive(Clone)]
+pub struct MemoryConnector {
+    sender: mpsc::UnboundedSender<(DuplexStream, SocketAddr)>,
+    /// 下一个客户端的端口，让每个连接的地址不同
+    next_port: Arc<AtomicU16>,
+    buffer_size: usize,
+}
+
+impl MemoryConnector {
+    /// 从127.0.0.1建立连接；监听端已丢弃时返回ConnectionRefused
+    pub fn connect(&self) -> io::Result<DuplexStream> {
+        let port = self.next_port.fetch_add(1, Ordering::Relaxed);
+        self.connect_from(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port))
+    }
+
+    /// 以指定的客户端地址建立连接，用于测试allow-ips、maxclients-per-ip等
+    pub fn connect_from(&self, addr: SocketAddr) -> io::Result<DuplexStream> {
+        let (client_side, server_side) = tokio::io::duplex(self.buffer_size);
+        self.sender
+            .send((server_side, addr))
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+        Ok(client_side)
+    }
+
+    /// 建立连接并包装成客户端
+    pub fn client(&self) -> io::Result<Client<DuplexStream>> {
+        self.connect().map(Client::from_stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TokioClock;
+    use crate::connection::cleanup_task;
+    use crate::resp::RespValue;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_server_and_store_share_data() {
+        let server = TestServer::start().await;
+        server.store().set("preset".to_string(), b"1".to_vec());
+
+        let mut client = server.client().await;
+        assert_eq!(client.get("preset").await.unwrap(), Some(b"1".to_vec()));
+
+        client.set("foo", "bar").await.unwrap();
+        assert_eq!(server.store().get("foo"), Some(b"bar".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_duplex_client() {
+        let server = TestServer::start().await;
+        let mut client = server.duplex_client();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        assert_eq!(client.incr("n").await.unwrap(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_expiry() {
+        let store = Store::with_clock(Arc::new(TokioClock::new()));
+        let (listener, connector) = memory_network(DUPLEX_BUFFER_SIZE);
+        tokio::spawn(Server::from_listener(listener, store.clone()).run());
+        tokio::spawn(cleanup_task(store.clone(), 10));
+
+        let mut client = connector.client().unwrap();
+        let reply = client.command(["SET", "k", "v", "PX", "1500"]).await.unwrap();
+        assert_eq!(reply, RespValue::SimpleString("OK".to_string()));
+        client.command(["SET", "untouched", "v", "PX", "1000"]).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(client.get("k").await.unwrap(), Some(b"v".to_vec()));
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(client.get("k").await.unwrap(), None);
+
+        // 没有被访问的键由定期清理删除，第一次清理发生在第10秒
+        assert_eq!(store.expired_keys(), 1);
+        tokio::time::sleep(Duration::from_secs(9)).await;
+        assert_eq!(store.expired_keys(), 2);
+        assert_eq!(store.dbsize(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_partial_writes_and_disconnect() {
+        let store = Store::with_clock(Arc::new(TokioClock::new()));
+        // 3字节的缓冲区让每条命令和回复都被拆成多段
+        let (listener, connector) = memory_network(3);
+        tokio::spawn(Server::from_listener(listener, store.clone()).run());
+
+        let mut client = connector.client().unwrap();
+        let value = "x".repeat(1000);
+        client.set("big", &value).await.unwrap();
+        assert_eq!(client.get("big").await.unwrap(), Some(value.into_bytes()));
+
+        // 命令只发出一半就断开，服务器丢弃这个连接，不影响其他客户端
+        let mut stream = connector.connect().unwrap();
+        stream.write_all(b"*3\r\n$3\r\nSET\r\n$4\r\nhalf").await.unwrap();
+        drop(stream);
+        let mut other = connector.client().unwrap();
+        assert_eq!(other.ping().await.unwrap(), "PONG");
+        assert!(!store.exists("half"));
+    }
+
+    #[tokio::test]
+    async fn test_drop_stops_server() {
+        let server = TestServer::start().await;
+        let addr = server.addr();
+        let mut client = server.client().await;
+        drop(server);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // 连接任务被取消后，客户端读到连接关闭，新连接也会被拒绝
+        assert!(client.ping().await.is_err());
+        assert!(Client::connect(addr).await.is_err());
+    }
+}
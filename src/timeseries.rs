@@ -0,0 +1,271 @@
+//! 时间序列模块 - TS.*命令使用的时间序列类型
+//!
+//! 每个时间序列是按时间戳排序的`(时间戳, 值)`样本，加上保留时长和标签:
+//! - 保留时长: 比最新样本早超过该时长的样本会被丢弃，0表示永久保留
+//! - 标签: TS.MRANGE按标签过滤多个序列
+//! - 聚合: 按固定宽度的时间桶计算avg/min/max/sum/count，用于降采样
+//!
+//! Rust特点展示:
+//! - Vec保持有序，追加是最常见的写入，二分查找处理乱序写入
+//! - `partition_point`二分查找切分范围和时间桶
+//! - `FromStr`把命令参数解析成枚举
+
+use crate::error::{RedisError, RedisResult};
+use std::str::FromStr;
+
+/// 时间序列命令的错误，带上RedisTimeSeries的`TSDB:`前缀
+pub fn tsdb_error(message: &str) -> RedisError {
+    RedisError::InvalidArgument(format!("TSDB: {}", message))
+}
+
+/// 一个时间序列
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    /// 按时间戳升序排列的样本
+    samples: Vec<(u64, f64)>,
+    /// 保留时长(毫秒)，0表示永久保留
+    retention: u64,
+    /// 标签
+    labels: Vec<(String, String)>,
+}
+
+impl TimeSeries {
+    /// 创建空的时间序列
+    pub fn new(retention: u64, labels: Vec<(String, String)>) -> Self {
+        Self {
+            samples: Vec::new(),
+            retention,
+            labels,
+        }
+    }
+
+    /// 保留时长(毫秒)
+    pub fn retention(&self) -> u64 {
+        self.retention
+    }
+
+    /// 标签
+    pub fn labels(&self) -> &[(String, String)] {
+        &self.labels
+    }
+
+    /// 样本数
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 是否没有样本
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// 最新的样本(TS.GET)
+    pub fn last(&self) -> Option<(u64, f64)> {
+        self.samples.last().copied()
+    }
+
+    /// 添加样本，同一时间戳的样本会被覆盖
+    ///
+    /// 比保留窗口更早的样本会被拒绝；添加后丢弃移出保留窗口的旧样本
+    pub fn add(&mut self, timestamp: u64, value: f64) -> RedisResult<()> {
+        if let Some(&(last, _)) = self.samples.last() {
+            if self.retention > 0 && timestamp < last.saturating_sub(self.retention) {
+                return Err(tsdb_error("Timestamp is older than retention"));
+            }
+        }
+
+        match self.samples.binary_search_by_key(&timestamp, |&(ts, _)| ts) {
+            Ok(i) => self.samples[i].1 = value,
+            Err(i) if i == self.samples.len() => self.samples.push((timestamp, value)),
+            Err(i) => self.samples.insert(i, (timestamp, value)),
+        }
+        self.trim();
+        Ok(())
+    }
+
+    /// 丢弃移出保留窗口的样本
+    fn trim(&mut self) {
+        let Some(&(last, _)) = self.samples.last() else {
+            return;
+        };
+        if self.retention == 0 {
+            return;
+        }
+        let oldest = last.saturating_sub(self.retention);
+        let keep_from = self.samples.partition_point(|&(ts, _)| ts < oldest);
+        self.samples.drain(..keep_from);
+    }
+
+    /// `[from, to]`范围内的样本，可选按时间桶聚合
+    pub fn range(&self, from: u64, to: u64, aggregation: Option<Aggregation>) -> Vec<(u64, f64)> {
+        let start = self.samples.partition_point(|&(ts, _)| ts < from);
+        let end = self.samples.partition_point(|&(ts, _)| ts <= to);
+        let samples = self.samples.get(start..end).unwrap_or_default();
+
+        let Some(aggregation) = aggregation else {
+            return samples.to_vec();
+        };
+
+        let mut result = Vec::new();
+        let mut rest = samples;
+        while let Some(&(ts, _)) = rest.first() {
+            let bucket = ts - ts % aggregation.bucket;
+            let len = rest.partition_point(|&(ts, _)| ts < bucket.saturating_add(aggregation.bucket));
+            let values = rest[..len].iter().map(|&(_, v)| v);
+            result.push((bucket, aggregation.aggregator.apply(values)));
+            rest = &rest[len..];
+        }
+        result
+    }
+
+    /// 是否满足所有标签过滤条件
+    pub fn matches(&self, filters: &[LabelFilter]) -> bool {
+        filters.iter().all(|filter| {
+            // 没有该标签的序列按空值处理，所以`label=`匹配没有该标签的序列
+            let value = self
+                .labels
+                .iter()
+                .find(|(l, _)| *l == filter.label)
+                .map_or("", |(_, v)| v.as_str());
+            (value == filter.value) != filter.negate
+        })
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        self.samples.capacity() * std::mem::size_of::<(u64, f64)>()
+            + self.labels.iter().map(|(l, v)| l.len() + v.len()).sum::<usize>()
+    }
+}
+
+/// 聚合函数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregator {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+impl Aggregator {
+    /// 对一个时间桶内的值求聚合结果，桶内至少有一个值
+    fn apply(self, values: impl Iterator<Item = f64>) -> f64 {
+        match self {
+            Aggregator::Avg => {
+                let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+                sum / count as f64
+            }
+            Aggregator::Min => values.fold(f64::INFINITY, f64::min),
+            Aggregator::Max => values.fold(f64::NEG_INFINITY, f64::max),
+            Aggregator::Sum => values.sum(),
+            Aggregator::Count => values.count() as f64,
+        }
+    }
+}
+
+impl FromStr for Aggregator {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        match s.to_lowercase().as_str() {
+            "avg" => Ok(Aggregator::Avg),
+            "min" => Ok(Aggregator::Min),
+            "max" => Ok(Aggregator::Max),
+            "sum" => Ok(Aggregator::Sum),
+            "count" => Ok(Aggregator::Count),
+            _ => Err(tsdb_error("unknown aggregation type")),
+        }
+    }
+}
+
+/// 降采样方式: 聚合函数和时间桶宽度(毫秒)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aggregation {
+    pub aggregator: Aggregator,
+    pub bucket: u64,
+}
+
+/// 标签过滤条件: `label=value`或`label!=value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelFilter {
+    pub label: String,
+    pub value: String,
+    pub negate: bool,
+}
+
+impl FromStr for LabelFilter {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> RedisResult<Self> {
+        let (label, value, negate) = match s.split_once("!=") {
+            Some((label, value)) => (label, value, true),
+            None => match s.split_once('=') {
+                Some((label, value)) => (label, value, false),
+                None => return Err(tsdb_error("failed parsing labels")),
+            },
+        };
+        if label.is_empty() {
+            return Err(tsdb_error("failed parsing labels"));
+        }
+        Ok(Self {
+            label: label.to_string(),
+            value: value.to_string(),
+            negate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_retention() {
+        let mut series = TimeSeries::new(100, Vec::new());
+        series.add(10, 1.0).unwrap();
+        series.add(30, 3.0).unwrap();
+        // 乱序写入和覆盖
+        series.add(20, 2.0).unwrap();
+        series.add(30, 4.0).unwrap();
+        assert_eq!(series.range(0, u64::MAX, None), vec![(10, 1.0), (20, 2.0), (30, 4.0)]);
+        assert_eq!(series.last(), Some((30, 4.0)));
+
+        // 最新样本到150后，早于50的样本被丢弃，也不能再写入
+        series.add(150, 5.0).unwrap();
+        assert_eq!(series.range(0, u64::MAX, None), vec![(150, 5.0)]);
+        assert!(series.add(40, 0.0).is_err());
+        assert!(series.add(60, 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_range_aggregation() {
+        let mut series = TimeSeries::new(0, Vec::new());
+        for (ts, v) in [(0, 1.0), (5, 3.0), (10, 10.0), (25, 4.0), (29, 2.0)] {
+            series.add(ts, v).unwrap();
+        }
+
+        let agg = |aggregator| Some(Aggregation { aggregator, bucket: 10 });
+        assert_eq!(series.range(0, 30, agg(Aggregator::Avg)), vec![(0, 2.0), (10, 10.0), (20, 3.0)]);
+        assert_eq!(series.range(0, 30, agg(Aggregator::Min)), vec![(0, 1.0), (10, 10.0), (20, 2.0)]);
+        assert_eq!(series.range(0, 30, agg(Aggregator::Max)), vec![(0, 3.0), (10, 10.0), (20, 4.0)]);
+        assert_eq!(series.range(5, 25, agg(Aggregator::Count)), vec![(0, 1.0), (10, 1.0), (20, 1.0)]);
+        assert_eq!(series.range(11, 24, None), vec![]);
+
+        assert_eq!("AVG".parse::<Aggregator>().unwrap(), Aggregator::Avg);
+        assert!("median".parse::<Aggregator>().is_err());
+    }
+
+    #[test]
+    fn test_label_filters() {
+        let series = TimeSeries::new(0, vec![("area".to_string(), "eu".to_string())]);
+        let filters = |fs: &[&str]| fs.iter().map(|f| f.parse().unwrap()).collect::<Vec<LabelFilter>>();
+
+        assert!(series.matches(&filters(&["area=eu"])));
+        assert!(!series.matches(&filters(&["area!=eu"])));
+        assert!(series.matches(&filters(&["area=eu", "host!=a"])));
+        // `label=`匹配没有该标签的序列
+        assert!(series.matches(&filters(&["host="])));
+        assert!("area".parse::<LabelFilter>().is_err());
+    }
+}
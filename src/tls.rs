@@ -0,0 +1,146 @@
+//! TLS模块 - 基于rustls的客户端TLS配置
+//!
+//! Rust特点展示:
+//! - 纯Rust实现的TLS，无需依赖OpenSSL
+//! - Builder模式构造配置
+//! - 错误类型转换
+
+use crate::error::{RedisError, RedisResult};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// 客户端TLS选项
+///
+/// Rust特点: Default派生 + 结构体更新语法，方便只设置部分字段
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// CA证书文件(PEM)；不指定时使用内置的公共根证书
+    pub ca_cert: Option<PathBuf>,
+    /// 客户端证书文件(PEM)，用于双向认证
+    pub client_cert: Option<PathBuf>,
+    /// 客户端私钥文件(PEM)
+    pub client_key: Option<PathBuf>,
+    /// 用于证书校验的服务器名(SNI)；不指定时使用连接地址中的主机名
+    pub server_name: Option<String>,
+}
+
+impl TlsOptions {
+    /// 根据选项构造rustls客户端配置
+    pub fn client_config(&self) -> RedisResult<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        match &self.ca_cert {
+            Some(path) => {
+                for cert in CertificateDer::pem_file_iter(path).map_err(tls_error)? {
+                    roots.add(cert.map_err(tls_error)?).map_err(tls_error)?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+
+        let builder = ClientConfig::builder().with_root_certificates(roots);
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let certs = CertificateDer::pem_file_iter(cert_path)
+                    .map_err(tls_error)?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(tls_error)?;
+                let key = PrivateKeyDer::from_pem_file(key_path).map_err(tls_error)?;
+                builder.with_client_auth_cert(certs, key).map_err(tls_error)
+            }
+            (None, None) => Ok(builder.with_no_client_auth()),
+            _ => Err(RedisError::Tls(
+                "客户端证书和私钥必须同时指定".to_string(),
+            )),
+        }
+    }
+}
+
+/// 建立TLS连接
+///
+/// `addr`形如"host:port"，未指定server_name时取其中的host做证书校验
+pub async fn connect(addr: &str, options: &TlsOptions) -> RedisResult<TlsStream<TcpStream>> {
+    let host = match &options.server_name {
+        Some(name) => name.clone(),
+        None => addr
+            .rsplit_once(':')
+            .map_or(addr, |(host, _)| host)
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_string(),
+    };
+    let server_name = ServerName::try_from(host).map_err(tls_error)?;
+
+    let connector = TlsConnector::from(Arc::new(options.client_config()?));
+    let stream = TcpStream::connect(addr).await?;
+    Ok(connector.connect(server_name, stream).await?)
+}
+
+/// 将各种TLS相关错误转换为RedisError
+fn tls_error(e: impl std::fmt::Display) -> RedisError {
+    RedisError::Tls(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    #[test]
+    fn test_key_without_cert_is_rejected() {
+        let options = TlsOptions {
+            client_key: Some(PathBuf::from("client.key")),
+            ..Default::default()
+        };
+        assert!(matches!(options.client_config(), Err(RedisError::Tls(_))));
+    }
+
+    #[tokio::test]
+    async fn test_connect_tls() {
+        // 生成自签名证书，CA文件就是证书本身
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let ca_path = std::env::temp_dir().join(format!("rust-redis-ca-{}.pem", std::process::id()));
+        std::fs::write(&ca_path, certified.cert.pem()).unwrap();
+
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![certified.cert.der().clone()],
+                PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into()),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        // 一个只会回复PONG的TLS服务器
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(socket).await.unwrap();
+            let mut buf = [0u8; 64];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream.write_all(b"+PONG\r\n").await.unwrap();
+        });
+
+        let options = TlsOptions {
+            ca_cert: Some(ca_path.clone()),
+            server_name: Some("localhost".to_string()),
+            ..Default::default()
+        };
+        let mut client = Client::connect_tls(&format!("127.0.0.1:{}", port), &options)
+            .await
+            .unwrap();
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        let _ = std::fs::remove_file(ca_path);
+    }
+}
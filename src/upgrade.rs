@@ -0,0 +1,266 @@
+//! 平滑升级模块 - 把监听socket交给新启动的服务器进程(Unix)
+//!
+//! 收到SIGUSR2时:
+//! 1. 以相同的参数启动当前的可执行文件(通常已被替换为新版本)，监听socket从fd 3开始继承给它，
+//!    环境变量`REDIS_UPGRADE_FDS`按顺序列出每个fd的用途(`tcp`、`unix`、`health`)
+//! 2. 新进程直接使用继承的socket，不重新bind，两个进程在交接期间共用同一个accept队列，
+//!    不会有连接被拒绝；新进程就绪后通过`NOTIFY_SOCKET`发送`READY=1`，
+//!    这个通知socket由旧进程临时创建，协议与sd_notify相同
+//! 3. 旧进程停止accept，已有连接继续处理，全部断开或超过`upgrade-drain-timeout`后退出；
+//!    新进程启动失败或超时未就绪时旧进程继续运行
+//!
+//! 旧进程由systemd管理时，会通过`MAINPID=`把主进程改为新进程。
+//! 数据不会迁移: 本服务器没有持久化，新进程从空数据集开始，旧连接在退出前看到的仍是旧数据。
+//!
+//! Rust特点展示:
+//! - `OwnedFd`表示拥有所有权的文件描述符，drop时自动关闭
+//! - `pre_exec`在fork之后、exec之前运行，只能调用异步信号安全的函数
+//! - tokio::select! 同时等待就绪通知、子进程退出和超时
+
+use crate::error::{RedisError, RedisResult};
+use std::env;
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::net::UnixDatagram;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// 列出继承的fd用途的环境变量
+pub const UPGRADE_FDS: &str = "REDIS_UPGRADE_FDS";
+
+/// 继承的第一个fd，与systemd socket激活相同
+const FDS_START: RawFd = 3;
+
+/// 最多传递的socket数
+const MAX_FDS: usize = 8;
+
+/// 等待新进程就绪的最长时间
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 要交给新进程的监听socket
+#[derive(Debug, Default)]
+pub struct Handover {
+    fds: Vec<(&'static str, OwnedFd)>,
+}
+
+impl Handover {
+    /// 加入一个监听socket，保存的是复制出来的fd，原socket照常使用
+    pub fn add(&mut self, name: &'static str, socket: &impl AsFd) -> RedisResult<()> {
+        if self.fds.len() == MAX_FDS {
+            return Err(RedisError::Config(format!("最多传递 {} 个socket", MAX_FDS)));
+        }
+        self.fds.push((name, socket.as_fd().try_clone_to_owned()?));
+        Ok(())
+    }
+
+    /// 启动新进程并等待它就绪，返回新进程的pid
+    pub async fn spawn_successor(&self) -> RedisResult<u32> {
+        let exe = env::current_exe()?;
+        let notify_path =
+            env::temp_dir().join(format!("rust-redis-upgrade-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&notify_path);
+        let notify = UnixDatagram::bind(&notify_path)?;
+
+        let names: Vec<&str> = self.fds.iter().map(|(name, _)| *name).collect();
+        let fds: Vec<RawFd> = self.fds.iter().map(|(_, fd)| fd.as_raw_fd()).collect();
+        let mut command = Command::new(&exe);
+        command
+            .args(env::args_os().skip(1))
+            .env(UPGRADE_FDS, names.join(","))
+            .env("NOTIFY_SOCKET", &notify_path)
+            .stdin(Stdio::null());
+        // Rust特点: unsafe 由我们保证闭包中只有dup类的系统调用，它们是异步信号安全的
+        unsafe {
+            command.pre_exec(move || move_fds(&fds));
+        }
+
+        let result = async {
+            let mut child = command.spawn()?;
+            let pid = child.id().unwrap_or_default();
+            info!(pid, exe = %exe.display(), "已启动新进程，等待它就绪");
+
+            let mut buf = [0u8; 1024];
+            tokio::select! {
+                received = notify.recv(&mut buf) => {
+                    let message = String::from_utf8_lossy(&buf[..received?]).into_owned();
+                    // 新进程daemonize时实际的pid与启动的子进程不同，以它报告的为准
+                    Ok(main_pid(&message).unwrap_or(pid))
+                }
+                status = child.wait() => Err(RedisError::Protocol(format!(
+                    "新进程在就绪前退出: {}",
+                    status?
+                ))),
+                _ = tokio::time::sleep(READY_TIMEOUT) => {
+                    let _ = child.start_kill();
+                    Err(RedisError::Protocol(format!("新进程在 {:?} 内没有就绪", READY_TIMEOUT)))
+                }
+            }
+        }
+        .await;
+        let _ = std::fs::remove_file(&notify_path);
+        result
+    }
+}
+
+/// 在子进程中把要传递的fd移到从3开始的位置
+///
+/// 先把所有fd复制到较大的编号(带CLOEXEC，exec时自动关闭)，
+/// 避免目标编号恰好是另一个尚未移动的fd；dup2的结果不带CLOEXEC，会被新程序继承。
+/// fork之后不能分配内存，所以用栈上的数组保存副本
+fn move_fds(fds: &[RawFd]) -> io::Result<()> {
+    let mut copies = [0; MAX_FDS];
+    for (copy, &fd) in copies.iter_mut().zip(fds) {
+        *copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 100) };
+        if *copy == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    for (offset, &copy) in copies.iter().take(fds.len()).enumerate() {
+        if unsafe { libc::dup2(copy, FDS_START + offset as RawFd) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// 就绪通知中的`MAINPID=`
+fn main_pid(message: &str) -> Option<u32> {
+    message
+        .lines()
+        .find_map(|line| line.strip_prefix("MAINPID="))
+        .and_then(|pid| pid.parse().ok())
+}
+
+/// 收到SIGUSR2后启动新进程，新进程就绪时返回；启动失败时记录警告并等待下一次信号
+///
+/// 用作`Server::run_until`的停止条件
+pub async fn wait_for_upgrade(handover: Handover) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = match signal(SignalKind::user_defined2()) {
+        Ok(signals) => signals,
+        Err(e) => {
+            warn!(error = %e, "无法监听SIGUSR2，不支持平滑升级");
+            return std::future::pending().await;
+        }
+    };
+    while signals.recv().await.is_some() {
+        match handover.spawn_successor().await {
+            Ok(pid) => {
+                info!(pid, "新进程已就绪，停止接受新连接");
+                if let Err(e) = crate::systemd::notify(&format!("MAINPID={}", pid)) {
+                    warn!(error = %e, "向systemd报告新的主进程失败");
+                }
+                return;
+            }
+            Err(e) => warn!(error = %e, "平滑升级失败，继续运行"),
+        }
+    }
+    std::future::pending().await
+}
+
+/// 从旧进程继承的监听socket
+#[derive(Debug, Default)]
+pub struct Inherited {
+    fds: Vec<(String, OwnedFd)>,
+}
+
+impl Inherited {
+    /// 取出继承的socket；不是由平滑升级启动时为空
+    ///
+    /// 读取后清除环境变量，避免被之后再启动的进程误用
+    pub fn take() -> Self {
+        let Ok(names) = env::var(UPGRADE_FDS) else {
+            return Self::default();
+        };
+        env::remove_var(UPGRADE_FDS);
+
+        let fds = parse_names(&names)
+            .into_iter()
+            .enumerate()
+            .map(|(offset, name)| {
+                // Rust特点: unsafe 由升级协议保证: 这些fd是旧进程专门传给本进程的
+                let fd = unsafe { OwnedFd::from_raw_fd(FDS_START + offset as RawFd) };
+                (name, fd)
+            })
+            .collect();
+        Self { fds }
+    }
+
+    fn remove(&mut self, name: &str) -> Option<OwnedFd> {
+        let index = self.fds.iter().position(|(n, _)| n == name)?;
+        Some(self.fds.remove(index).1)
+    }
+
+    /// 取出继承的TCP监听socket
+    pub fn tcp(&mut self, name: &str) -> RedisResult<Option<std::net::TcpListener>> {
+        self.remove(name)
+            .map(|fd| {
+                let listener = std::net::TcpListener::from(fd);
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .transpose()
+    }
+
+    /// 取出继承的Unix socket监听socket
+    pub fn unix(&mut self, name: &str) -> RedisResult<Option<std::os::unix::net::UnixListener>> {
+        self.remove(name)
+            .map(|fd| {
+                let listener = std::os::unix::net::UnixListener::from(fd);
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .transpose()
+    }
+}
+
+/// 解析`tcp,unix,health`
+fn parse_names(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_names("tcp,unix"), ["tcp", "unix"]);
+        assert!(parse_names("").is_empty());
+        assert_eq!(main_pid("READY=1\nMAINPID=42\nSTATUS=ok"), Some(42));
+        assert_eq!(main_pid("READY=1"), None);
+    }
+
+    #[test]
+    fn test_move_fds() {
+        let a = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let b = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let fds = [a.as_raw_fd(), b.as_raw_fd()];
+        // 通过/proc比较socket的inode，没有/proc的平台跳过
+        let Ok(links) = fds
+            .iter()
+            .map(|fd| std::fs::read_link(format!("/proc/self/fd/{}", fd)))
+            .collect::<io::Result<Vec<_>>>()
+        else {
+            return;
+        };
+
+        // 子进程中的fd 3、4应该分别是a和b
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "readlink /proc/self/fd/3 /proc/self/fd/4"]);
+        unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, move || move_fds(&fds));
+        }
+        let output = String::from_utf8(command.output().unwrap().stdout).unwrap();
+        let expected: Vec<String> = links.iter().map(|l| l.display().to_string()).collect();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+    }
+}
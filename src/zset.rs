@@ -0,0 +1,556 @@
+//! 有序集合模块 - Z*命令使用的按分数排序的集合
+//!
+//! 每个成员带一个分数，成员按(分数, 成员)排序: 分数相同时按成员的字节序排列，与Redis一致。
+//! BTreeSet保存排序，用于按名次和按分数的范围查询；成员到分数的HashMap回答ZSCORE。
+//! 小集合(listpack编码)不建HashMap，查分数时线性扫描，超过阈值后才建立(skiplist编码)。
+//!
+//! 范围查询有三种: 按名次、按分数(`(`前缀表示不包含端点)和按字典序
+//! (`[`包含、`(`不包含，`-`/`+`表示最小/最大)，都可以倒序并用LIMIT分页。
+//! 按字典序查询只在所有成员分数相同时有意义，与Redis一样不做检查。
+//!
+//! Rust特点展示:
+//! - 新类型包装f64，用`total_cmp`实现Ord，才能作为BTreeSet的键
+//! - 两个集合的同步由方法封装保证，字段不对外公开
+
+use crate::encoding::{EncodingThresholds, MAX_COMPACT_ENTRIES};
+use crate::error::{RedisError, RedisResult};
+use crate::store;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+
+/// 可以排序的分数
+///
+/// 有序集合中不会出现NaN，-0.0在插入时已归一化为0.0，所以`total_cmp`与数值比较一致
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// ZADD的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ZAddOptions {
+    /// 只添加新成员，不更新已有成员
+    pub nx: bool,
+    /// 只更新已有成员，不添加新成员
+    pub xx: bool,
+    /// 只在新分数更大时更新
+    pub gt: bool,
+    /// 只在新分数更小时更新
+    pub lt: bool,
+    /// 回复中计入分数被修改的成员，而不只是新增的成员
+    pub ch: bool,
+    /// 把分数加到已有分数上，与ZINCRBY相同
+    pub incr: bool,
+}
+
+/// ZADD对一个成员的处理结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZAddOutcome {
+    /// 新增了成员
+    Added(f64),
+    /// 修改了已有成员的分数
+    Updated(f64),
+    /// 成员已存在，分数没有变化
+    Unchanged(f64),
+    /// 因NX/XX/GT/LT没有执行
+    Skipped,
+}
+
+impl ZAddOutcome {
+    /// 执行后成员的分数，被跳过时为None
+    pub fn score(&self) -> Option<f64> {
+        match self {
+            ZAddOutcome::Added(score) | ZAddOutcome::Updated(score) | ZAddOutcome::Unchanged(score) => {
+                Some(*score)
+            }
+            ZAddOutcome::Skipped => None,
+        }
+    }
+}
+
+/// 分数范围的一端
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    /// 是否不包含端点(`(`前缀)
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    /// 解析`1.5`、`(1.5`、`-inf`、`+inf`
+    pub fn parse(text: &[u8]) -> RedisResult<Self> {
+        let (exclusive, value) = match text.strip_prefix(b"(") {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let value = parse_score(value)
+            .map_err(|_| RedisError::InvalidArgument("min or max is not a float".to_string()))?;
+        Ok(Self { value, exclusive })
+    }
+
+    /// `score`是否不小于这个下限
+    fn admits_above(&self, score: f64) -> bool {
+        if self.exclusive { score > self.value } else { score >= self.value }
+    }
+
+    /// `score`是否不大于这个上限
+    fn admits_below(&self, score: f64) -> bool {
+        if self.exclusive { score < self.value } else { score <= self.value }
+    }
+}
+
+/// 字典序范围的一端
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    /// `-`，比任何成员都小
+    Min,
+    /// `+`，比任何成员都大
+    Max,
+    /// `[member`
+    Inclusive(Vec<u8>),
+    /// `(member`
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    /// 解析`-`、`+`、`[member`、`(member`
+    pub fn parse(text: &[u8]) -> RedisResult<Self> {
+        match text {
+            b"-" => Ok(LexBound::Min),
+            b"+" => Ok(LexBound::Max),
+            [b'[', member @ ..] => Ok(LexBound::Inclusive(member.to_vec())),
+            [b'(', member @ ..] => Ok(LexBound::Exclusive(member.to_vec())),
+            _ => Err(RedisError::InvalidArgument(
+                "min or max not valid string range item".to_string(),
+            )),
+        }
+    }
+
+    /// `member`是否不小于这个下限
+    fn admits_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(bound) => member >= bound.as_slice(),
+            LexBound::Exclusive(bound) => member > bound.as_slice(),
+        }
+    }
+
+    /// `member`是否不大于这个上限
+    fn admits_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(bound) => member <= bound.as_slice(),
+            LexBound::Exclusive(bound) => member < bound.as_slice(),
+        }
+    }
+}
+
+/// 范围查询的方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBy {
+    /// 按名次，负数从末尾计数
+    Rank { start: i64, stop: i64 },
+    /// 按分数
+    Score { min: ScoreBound, max: ScoreBound },
+    /// 按字典序
+    Lex { min: LexBound, max: LexBound },
+}
+
+/// 一次范围查询: ZRANGE及ZRANGEBYSCORE等命令都转换为它
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeQuery {
+    pub by: RangeBy,
+    /// 从大到小返回；按名次查询时名次也从最大的成员开始计数
+    pub rev: bool,
+    /// LIMIT offset count: 跳过offset个结果，最多返回count个，count为负数时不限制
+    pub limit: Option<(i64, i64)>,
+}
+
+/// 有序集合
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    /// 成员 -> 分数，listpack编码时为None
+    scores: Option<HashMap<Vec<u8>, f64>>,
+    /// 按(分数, 成员)排序
+    order: BTreeSet<(Score, Vec<u8>)>,
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 成员数
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// 编码名称(OBJECT ENCODING)
+    pub fn encoding(&self) -> &'static str {
+        match self.scores {
+            Some(_) => "skiplist",
+            None => "listpack",
+        }
+    }
+
+    /// 成员的分数
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        match &self.scores {
+            Some(scores) => scores.get(member).copied(),
+            None => self.order.iter().find(|(_, m)| m == member).map(|(score, _)| score.0),
+        }
+    }
+
+    /// 按阈值转换编码: 成员数或任何成员的长度超过阈值时建立成员到分数的索引
+    pub fn fit(&mut self, thresholds: &EncodingThresholds) {
+        if self.scores.is_none()
+            && (self.len() > thresholds.zset_max_listpack_entries
+                || self.order.iter().any(|(_, m)| m.len() > thresholds.zset_max_listpack_value))
+        {
+            self.convert();
+        }
+    }
+
+    /// listpack转换为skiplist
+    fn convert(&mut self) {
+        if self.scores.is_none() {
+            self.scores = Some(self.order.iter().map(|(score, m)| (m.clone(), score.0)).collect());
+        }
+    }
+
+    /// 按ZADD的选项添加成员或更新分数
+    ///
+    /// `incr`时`score`是增量；相加的结果是NaN(如inf加-inf)时返回错误，集合不变
+    pub fn add(&mut self, member: Vec<u8>, score: f64, options: &ZAddOptions) -> RedisResult<ZAddOutcome> {
+        let Some(old) = self.score(&member) else {
+            if options.xx {
+                return Ok(ZAddOutcome::Skipped);
+            }
+            self.insert(member, score);
+            return Ok(ZAddOutcome::Added(score));
+        };
+        if options.nx {
+            return Ok(ZAddOutcome::Skipped);
+        }
+
+        let new = if options.incr { old + score } else { score };
+        if new.is_nan() {
+            return Err(RedisError::InvalidArgument("resulting score is not a number (NaN)".to_string()));
+        }
+        if (options.gt && new <= old) || (options.lt && new >= old) {
+            return Ok(ZAddOutcome::Skipped);
+        }
+        if new == old {
+            return Ok(ZAddOutcome::Unchanged(new));
+        }
+        self.insert(member, new);
+        Ok(ZAddOutcome::Updated(new))
+    }
+
+    /// 设置成员的分数，返回是否是新成员
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> bool {
+        // 加0.0把-0.0归一化为0.0
+        let score = score + 0.0;
+        if self.len() >= MAX_COMPACT_ENTRIES {
+            self.convert();
+        }
+        let old = match &mut self.scores {
+            Some(scores) => scores.insert(member.clone(), score),
+            None => self.score(&member),
+        };
+        if let Some(old) = old {
+            self.order.remove(&(Score(old), member.clone()));
+        }
+        self.order.insert((Score(score), member));
+        old.is_none()
+    }
+
+    /// 删除成员，返回成员是否存在
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        let score = match &mut self.scores {
+            Some(scores) => scores.remove(member),
+            None => self.score(member),
+        };
+        match score {
+            Some(score) => {
+                self.order.remove(&(Score(score), member.to_vec()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 弹出分数最小(`min`)或最大的成员
+    pub fn pop(&mut self, min: bool) -> Option<(Vec<u8>, f64)> {
+        let (score, member) = if min { self.order.pop_first() } else { self.order.pop_last() }?;
+        if let Some(scores) = &mut self.scores {
+            scores.remove(&member);
+        }
+        Some((member, score.0))
+    }
+
+    /// 成员按分数从小到大的名次(从0开始)
+    ///
+    /// BTreeSet不记录子树大小，需要数出排在前面的成员，复杂度O(n)
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.order.range(..(Score(score), member.to_vec())).count())
+    }
+
+    /// 按分数从小到大遍历(成员, 分数)
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], f64)> {
+        self.order.iter().map(|(score, member)| (member.as_slice(), score.0))
+    }
+
+    /// 执行范围查询，按查询的方向返回(成员, 分数)
+    pub fn range(&self, query: &RangeQuery) -> Vec<(&[u8], f64)> {
+        let (offset, count) = match query.limit {
+            // 负数的offset返回空结果，与Redis一致
+            Some((offset, _)) if offset < 0 => return Vec::new(),
+            Some((offset, count)) => (offset as usize, usize::try_from(count).unwrap_or(usize::MAX)),
+            None => (0, usize::MAX),
+        };
+        // Rust特点: 各分支的迭代器类型不同，装箱成trait对象后统一分页
+        let iter: Box<dyn Iterator<Item = &(Score, Vec<u8>)>> = match &query.by {
+            RangeBy::Rank { start, stop } => {
+                let Some((start, stop)) = store::index_range(self.len(), *start, *stop) else {
+                    return Vec::new();
+                };
+                let n = stop - start + 1;
+                if query.rev {
+                    Box::new(self.order.iter().rev().skip(start).take(n))
+                } else {
+                    Box::new(self.order.iter().skip(start).take(n))
+                }
+            }
+            RangeBy::Score { min, max } => {
+                if query.rev {
+                    // 从上限之后的第一个分数开始倒序遍历
+                    let upper = match max.value.next_up() {
+                        next if next.is_finite() => Bound::Excluded((Score(next), Vec::new())),
+                        _ => Bound::Unbounded,
+                    };
+                    Box::new(
+                        self.order
+                            .range((Bound::Unbounded, upper))
+                            .rev()
+                            .skip_while(|(score, _)| !max.admits_below(score.0))
+                            .take_while(|(score, _)| min.admits_above(score.0)),
+                    )
+                } else {
+                    let lower = Bound::Included((Score(min.value), Vec::new()));
+                    Box::new(
+                        self.order
+                            .range((lower, Bound::Unbounded))
+                            .skip_while(|(score, _)| !min.admits_above(score.0))
+                            .take_while(|(score, _)| max.admits_below(score.0)),
+                    )
+                }
+            }
+            RangeBy::Lex { min, max } => {
+                if query.rev {
+                    Box::new(
+                        self.order
+                            .iter()
+                            .rev()
+                            .skip_while(|(_, member)| !max.admits_below(member))
+                            .take_while(|(_, member)| min.admits_above(member)),
+                    )
+                } else {
+                    Box::new(
+                        self.order
+                            .iter()
+                            .skip_while(|(_, member)| !min.admits_above(member))
+                            .take_while(|(_, member)| max.admits_below(member)),
+                    )
+                }
+            }
+        };
+        iter.skip(offset)
+            .take(count)
+            .map(|(score, member)| (member.as_slice(), score.0))
+            .collect()
+    }
+
+    /// 估算占用的内存(字节)
+    pub fn memory_usage(&self) -> usize {
+        // skiplist编码时成员在两个集合中各保存一份
+        let copies = if self.scores.is_some() { 2 } else { 1 };
+        self.order
+            .iter()
+            .map(|(_, member)| copies * (member.capacity() + std::mem::size_of::<(Vec<u8>, f64)>()))
+            .sum()
+    }
+}
+
+/// 成员和分数相同即相等，与编码无关
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.order == other.order
+    }
+}
+
+/// 解析分数: 接受整数、小数、指数形式和`inf`/`+inf`/`-inf`，拒绝NaN
+pub fn parse_score(text: &[u8]) -> RedisResult<f64> {
+    std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| RedisError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+/// 按Redis的格式输出分数: 整数不带小数点，很大或很小的数用指数形式(如`1e+20`)，无穷为`inf`/`-inf`
+pub fn format_score(score: f64) -> String {
+    if score.is_infinite() {
+        return if score > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let abs = score.abs();
+    if abs != 0.0 && !(1e-4..1e17).contains(&abs) {
+        let text = format!("{:e}", score);
+        if let Some((mantissa, exponent)) = text.split_once('e') {
+            let exponent: i32 = exponent.parse().unwrap_or(0);
+            let sign = if exponent < 0 { '-' } else { '+' };
+            return format!("{}e{}{:02}", mantissa, sign, exponent.abs());
+        }
+    }
+    score.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_and_rank() {
+        let mut zset = SortedSet::new();
+        assert!(zset.insert(b"b".to_vec(), 2.0));
+        assert!(zset.insert(b"a".to_vec(), 2.0));
+        assert!(zset.insert(b"c".to_vec(), -1.0));
+        assert!(!zset.insert(b"c".to_vec(), 5.0));
+
+        // 分数相同时按成员排序
+        let members: Vec<&[u8]> = zset.iter().map(|(member, _)| member).collect();
+        assert_eq!(members, [&b"a"[..], b"b", b"c"]);
+        assert_eq!(zset.rank(b"c"), Some(2));
+        assert_eq!(zset.rank(b"missing"), None);
+
+        assert!(zset.remove(b"a"));
+        assert!(!zset.remove(b"a"));
+        assert_eq!(zset.len(), 2);
+        assert_eq!(zset.rank(b"b"), Some(0));
+    }
+
+    #[test]
+    fn test_add_options() {
+        let mut zset = SortedSet::new();
+        let mut add = |score: f64, set: fn(&mut ZAddOptions)| {
+            let mut options = ZAddOptions::default();
+            set(&mut options);
+            zset.add(b"m".to_vec(), score, &options)
+        };
+
+        assert_eq!(add(1.0, |o| o.xx = true).unwrap(), ZAddOutcome::Skipped);
+        assert_eq!(add(1.0, |_| {}).unwrap(), ZAddOutcome::Added(1.0));
+        assert_eq!(add(5.0, |o| o.nx = true).unwrap(), ZAddOutcome::Skipped);
+        assert_eq!(add(0.5, |o| o.gt = true).unwrap(), ZAddOutcome::Skipped);
+        assert_eq!(add(3.0, |o| o.gt = true).unwrap(), ZAddOutcome::Updated(3.0));
+        assert_eq!(add(3.0, |_| {}).unwrap(), ZAddOutcome::Unchanged(3.0));
+        assert_eq!(add(2.5, |o| o.incr = true).unwrap(), ZAddOutcome::Updated(5.5));
+
+        // 相加结果为NaN时出错，分数不变
+        assert_eq!(add(f64::INFINITY, |_| {}).unwrap(), ZAddOutcome::Updated(f64::INFINITY));
+        assert!(add(f64::NEG_INFINITY, |o| o.incr = true).is_err());
+        assert_eq!(zset.score(b"m"), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut zset = SortedSet::new();
+        zset.insert(b"b".to_vec(), 1.0);
+        zset.insert(b"a".to_vec(), 1.0);
+        zset.insert(b"c".to_vec(), -2.0);
+        assert_eq!(zset.pop(true), Some((b"c".to_vec(), -2.0)));
+        // 分数相同时按成员的字节序
+        assert_eq!(zset.pop(false), Some((b"b".to_vec(), 1.0)));
+        assert_eq!(zset.pop(true), Some((b"a".to_vec(), 1.0)));
+        assert_eq!(zset.pop(true), None);
+        assert!(zset.is_empty() && zset.score(b"a").is_none());
+    }
+
+    #[test]
+    fn test_range() {
+        let mut zset = SortedSet::new();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0)] {
+            zset.insert(member.as_bytes().to_vec(), score);
+        }
+        let members = |by: RangeBy, rev: bool, limit: Option<(i64, i64)>| -> Vec<String> {
+            zset.range(&RangeQuery { by, rev, limit })
+                .into_iter()
+                .map(|(member, _)| String::from_utf8_lossy(member).into_owned())
+                .collect()
+        };
+        let score = |text: &str| ScoreBound::parse(text.as_bytes()).unwrap();
+        let lex = |text: &str| LexBound::parse(text.as_bytes()).unwrap();
+
+        assert_eq!(members(RangeBy::Rank { start: 1, stop: -2 }, false, None), ["b", "c"]);
+        assert_eq!(members(RangeBy::Rank { start: 0, stop: 0 }, true, None), ["d"]);
+
+        let by_score = |min, max| RangeBy::Score { min: score(min), max: score(max) };
+        assert_eq!(members(by_score("2", "3"), false, None), ["b", "c", "d"]);
+        assert_eq!(members(by_score("(2", "+inf"), false, None), ["d"]);
+        assert_eq!(members(by_score("-inf", "(2"), true, None), ["a"]);
+        assert_eq!(members(by_score("1", "2"), true, None), ["c", "b", "a"]);
+        assert_eq!(members(by_score("-inf", "+inf"), false, Some((1, 2))), ["b", "c"]);
+        assert_eq!(members(by_score("-inf", "+inf"), false, Some((3, -1))), ["d"]);
+        assert!(members(by_score("-inf", "+inf"), false, Some((-1, 2))).is_empty());
+
+        let by_lex = |min, max| RangeBy::Lex { min: lex(min), max: lex(max) };
+        assert_eq!(members(by_lex("[b", "(d"), false, None), ["b", "c"]);
+        assert_eq!(members(by_lex("-", "+"), true, Some((0, 2))), ["d", "c"]);
+        assert!(members(by_lex("+", "-"), false, None).is_empty());
+
+        assert!(ScoreBound::parse(b"(").is_err());
+        assert!(LexBound::parse(b"b").is_err());
+    }
+
+    #[test]
+    fn test_parse_and_format_score() {
+        assert_eq!(parse_score(b"1.5").unwrap(), 1.5);
+        assert_eq!(parse_score(b"-inf").unwrap(), f64::NEG_INFINITY);
+        assert_eq!(parse_score(b"+inf").unwrap(), f64::INFINITY);
+        assert!(parse_score(b"nan").is_err());
+        assert!(parse_score(b"abc").is_err());
+
+        assert_eq!(format_score(3.0), "3");
+        assert_eq!(format_score(-1.5), "-1.5");
+        assert_eq!(format_score(0.0001), "0.0001");
+        assert_eq!(format_score(1e20), "1e+20");
+        assert_eq!(format_score(1.5e-7), "1.5e-07");
+        assert_eq!(format_score(f64::NEG_INFINITY), "-inf");
+    }
+}